@@ -0,0 +1,325 @@
+// SPDX-FileCopyrightText: 2024 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+//! Staging changes.
+//!
+//! Through `ricer add <REPO> <PATHS>...`, changes to files are staged in a
+//! repository, no matter whether it uses its own working directory, or the
+//! home directory through the fake bare technique. Paths are given relative to
+//! the current directory, like Git does.
+//!
+//! Through `--patch`, changes are split into hunks instead, and only the hunks
+//! the user accepts through [`add_patch`] are staged, like `git add --patch`.
+
+use crate::{
+    config::{ConfigFile, ConfigFileError, RepoConfig, TomlError},
+    context::AddContext,
+    locate::Locator,
+    ui::{Prompter, UiError},
+    vcs::{GitRepo, GitRepoError},
+};
+
+use log::info;
+use std::{
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+/// Error types for [`add_files`] and [`add_patch`].
+#[derive(Debug, thiserror::Error)]
+pub enum AddError {
+    #[error("Repository '{name}' is not registered")]
+    MissingRepo { name: String },
+
+    #[error("Repository '{name}' is not cloned at '{path}'")]
+    NotCloned { name: String, path: PathBuf },
+
+    #[error("Repository '{name}' has no working directory")]
+    NoWorkdir { name: String },
+
+    #[error("Path '{path}' is outside working directory '{workdir}'")]
+    OutsideWorkdir { path: PathBuf, workdir: PathBuf },
+
+    #[error("Failed to operate on repository '{name}'")]
+    Repo { source: GitRepoError, name: String },
+
+    #[error("Failed to ask which hunks to stage")]
+    Ui { source: UiError },
+
+    #[error("Failed to read repository configuration")]
+    Config { source: ConfigFileError },
+}
+
+impl From<ConfigFileError> for AddError {
+    fn from(err: ConfigFileError) -> Self {
+        AddError::Config { source: err }
+    }
+}
+
+impl From<UiError> for AddError {
+    fn from(err: UiError) -> Self {
+        AddError::Ui { source: err }
+    }
+}
+
+/// What to do with one hunk of [`add_patch`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HunkChoice {
+    /// Stage hunk.
+    Stage,
+
+    /// Leave hunk unstaged.
+    Skip,
+
+    /// Leave this and every remaining hunk unstaged.
+    Quit,
+}
+
+impl FromStr for HunkChoice {
+    type Err = String;
+
+    fn from_str(choice: &str) -> Result<Self, Self::Err> {
+        match choice {
+            "y" => Ok(HunkChoice::Stage),
+            "n" => Ok(HunkChoice::Skip),
+            "q" => Ok(HunkChoice::Quit),
+            _ => Err(format!("invalid choice '{choice}', expected 'y', 'n', or 'q'")),
+        }
+    }
+}
+
+/// Stage every change to paths of [`AddContext`], relative to `cwd`.
+///
+/// Will return staged paths relative to working directory of repository.
+/// Untracked paths that the repository ignores are left out, like `git add`
+/// does.
+///
+/// # Errors
+///
+/// - Return [`AddError::MissingRepo`] if repository is not registered.
+/// - Return [`AddError::NotCloned`] if repository is not cloned.
+/// - Return [`AddError::NoWorkdir`] if repository is bare.
+/// - Return [`AddError::OutsideWorkdir`] if a path is not inside working
+///   directory of repository.
+/// - Return [`AddError::Repo`] if paths cannot be staged.
+pub fn add_files(
+    ctx: &AddContext,
+    locator: &impl Locator,
+    cwd: &Path,
+) -> Result<Vec<PathBuf>, AddError> {
+    let (repo, paths) = open_repo(ctx, locator, cwd)?;
+    info!("Stage {} paths in '{}'", paths.len(), ctx.repo);
+    repo.stage(&paths).map_err(|err| AddError::Repo { source: err, name: ctx.repo.clone() })
+}
+
+/// Stage hunks of changes to paths of [`AddContext`], relative to `cwd`, that
+/// the user accepts through `prompter`.
+///
+/// Without any paths, every changed file of the repository is split into
+/// hunks. Will return paths that had at least one hunk staged, relative to
+/// working directory of repository.
+///
+/// # Invariants
+///
+/// Nothing is staged until every hunk was either accepted or skipped, or the
+/// user quit. Errors of `prompter` leave the index untouched.
+///
+/// # Errors
+///
+/// - Return [`AddError::MissingRepo`] if repository is not registered.
+/// - Return [`AddError::NotCloned`] if repository is not cloned.
+/// - Return [`AddError::NoWorkdir`] if repository is bare.
+/// - Return [`AddError::OutsideWorkdir`] if a path is not inside working
+///   directory of repository.
+/// - Return [`AddError::Ui`] if `prompter` fails.
+/// - Return [`AddError::Repo`] if hunks cannot be determined or staged.
+pub fn add_patch(
+    ctx: &AddContext,
+    locator: &impl Locator,
+    cwd: &Path,
+    prompter: &impl Prompter,
+) -> Result<Vec<PathBuf>, AddError> {
+    let repo_err = |err| AddError::Repo { source: err, name: ctx.repo.clone() };
+    let (repo, paths) = open_repo(ctx, locator, cwd)?;
+    let hunks = repo.unstaged_hunks(&paths).map_err(repo_err)?;
+
+    let mut accepted = Vec::new();
+    for hunk in &hunks {
+        prompter.show_diff(&format!("+++ {}\n{hunk}", hunk.path.display()))?;
+        let choice = prompter.choose("Stage this hunk [y,n,q]?")?;
+        match choice.unwrap_or(HunkChoice::Quit) {
+            HunkChoice::Stage => accepted.push(hunk),
+            HunkChoice::Skip => (),
+            HunkChoice::Quit => break,
+        }
+    }
+
+    if accepted.is_empty() {
+        info!("No hunks staged in '{}'", ctx.repo);
+        return Ok(Vec::new());
+    }
+
+    info!("Stage {} hunks in '{}'", accepted.len(), ctx.repo);
+    repo.stage_hunks(&accepted).map_err(repo_err)?;
+    let mut staged = accepted.iter().map(|hunk| hunk.path.clone()).collect::<Vec<_>>();
+    staged.dedup();
+    Ok(staged)
+}
+
+/// Open repository of [`AddContext`], resolving its paths relative to its
+/// working directory.
+fn open_repo(
+    ctx: &AddContext,
+    locator: &impl Locator,
+    cwd: &Path,
+) -> Result<(GitRepo, Vec<PathBuf>), AddError> {
+    let settings = match ConfigFile::load(RepoConfig, locator)?.get(&ctx.repo) {
+        Ok(settings) => settings,
+        Err(ConfigFileError::Toml {
+            source: TomlError::EntryNotFound { .. } | TomlError::TableNotFound { .. },
+            ..
+        }) => return Err(AddError::MissingRepo { name: ctx.repo.clone() }),
+        Err(err) => return Err(err.into()),
+    };
+
+    let gitdir = settings.gitdir(locator.repos_dir());
+    if !gitdir.exists() {
+        return Err(AddError::NotCloned { name: ctx.repo.clone(), path: gitdir });
+    }
+    let repo = GitRepo::open(&gitdir)
+        .map_err(|err| AddError::Repo { source: err, name: ctx.repo.clone() })?;
+    let workdir = match repo.workdir() {
+        Some(workdir) => workdir.to_path_buf(),
+        None => return Err(AddError::NoWorkdir { name: ctx.repo.clone() }),
+    };
+
+    let mut paths = Vec::new();
+    for path in &ctx.paths {
+        let full = cwd.join(path);
+        match full.strip_prefix(&workdir) {
+            Ok(relative) => paths.push(relative.to_path_buf()),
+            Err(_) => return Err(AddError::OutsideWorkdir { path: full, workdir }),
+        }
+    }
+
+    Ok((repo, paths))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testenv::{mock_locator, parse_ctx, FileKind, FixtureHarness, ScriptedPrompter};
+
+    use anyhow::Result;
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+    use rstest::{fixture, rstest};
+    use std::fs;
+
+    #[fixture]
+    fn rice_dir() -> Result<FixtureHarness> {
+        let harness = FixtureHarness::open()?
+            .with_file("config/repos.toml", |fixture| {
+                fixture
+                    .with_data(indoc! {r#"
+                        [repos.dwm]
+                        branch = "main"
+                        remote = "origin"
+                        workdir_home = false
+                    "#})
+                    .with_kind(FileKind::Normal)
+            })
+            .with_repo("repos/dwm", |repo| {
+                let repo = repo
+                    .stage("config.h", "1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n")?
+                    .stage("README", "nothing to see here\n")?;
+                repo.commit("initial commit")?;
+                Ok(repo)
+            })?
+            .setup()?;
+        Ok(harness)
+    }
+
+    #[rstest]
+    fn add_files_stage_paths_relative_to_cwd(rice_dir: Result<FixtureHarness>) -> Result<()> {
+        let rice_dir = rice_dir?;
+        let workdir = rice_dir.as_path().join("repos/dwm.git");
+        fs::write(workdir.join("README"), "something to see here\n")?;
+        let staged = add_files(
+            &parse_ctx::<AddContext>("add", &["dwm", "README"])?,
+            &mock_locator(&rice_dir),
+            &workdir,
+        )?;
+
+        assert_eq!(staged, vec![PathBuf::from("README")]);
+        let repo = GitRepo::open(&workdir)?;
+        assert!(repo.unstaged_hunks(&["README"])?.is_empty());
+        Ok(())
+    }
+
+    #[rstest]
+    fn add_files_leave_out_ignored_paths(rice_dir: Result<FixtureHarness>) -> Result<()> {
+        let rice_dir = rice_dir?;
+        let workdir = rice_dir.as_path().join("repos/dwm.git");
+        fs::create_dir_all(workdir.join(".git/info"))?;
+        fs::write(workdir.join(".git/info/exclude"), "*.o\n")?;
+        fs::write(workdir.join("dwm.o"), "object code\n")?;
+        fs::write(workdir.join("dwm.c"), "source code\n")?;
+        let ctx = parse_ctx::<AddContext>("add", &["dwm", "dwm.o", "dwm.c"])?;
+        let staged = add_files(&ctx, &mock_locator(&rice_dir), &workdir)?;
+
+        assert_eq!(staged, vec![PathBuf::from("dwm.c")]);
+        assert_eq!(GitRepo::open(&workdir)?.staged_files()?, vec![PathBuf::from("dwm.c")]);
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::stage_first(vec!["y", "n"], vec!["config.h"], 1)]
+    #[case::skip_all(vec!["n", "n"], vec![], 2)]
+    #[case::quit(vec!["q"], vec![], 2)]
+    #[case::retry_invalid(vec!["maybe", "y", "y"], vec!["config.h"], 0)]
+    #[case::no_answer(vec![], vec![], 2)]
+    fn add_patch_stage_chosen_hunks(
+        rice_dir: Result<FixtureHarness>,
+        #[case] answers: Vec<&str>,
+        #[case] expect: Vec<&str>,
+        #[case] unstaged: usize,
+    ) -> Result<()> {
+        let rice_dir = rice_dir?;
+        let workdir = rice_dir.as_path().join("repos/dwm.git");
+        fs::write(workdir.join("config.h"), "one\n2\n3\n4\n5\n6\n7\n8\n9\nten\n")?;
+        let prompter = ScriptedPrompter::new(answers);
+        let ctx = parse_ctx::<AddContext>("add", &["dwm", "--patch"])?;
+        let staged = add_patch(&ctx, &mock_locator(&rice_dir), &workdir, &prompter)?;
+
+        assert_eq!(staged, expect.iter().map(PathBuf::from).collect::<Vec<_>>());
+        let repo = GitRepo::open(&workdir)?;
+        assert_eq!(repo.unstaged_hunks::<&str>(&[])?.len(), unstaged);
+        assert!(prompter.diffs().iter().all(|diff| diff.starts_with("+++ config.h\n")));
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::missing_repo(&["vim", "README"])]
+    #[case::outside_workdir(&["dwm", "/etc/passwd"])]
+    fn add_files_return_err(rice_dir: Result<FixtureHarness>, #[case] args: &[&str]) -> Result<()> {
+        let rice_dir = rice_dir?;
+        let workdir = rice_dir.as_path().join("repos/dwm.git");
+        let result =
+            add_files(&parse_ctx::<AddContext>("add", args)?, &mock_locator(&rice_dir), &workdir);
+        match args[0] {
+            "vim" => assert!(matches!(result.unwrap_err(), AddError::MissingRepo { .. })),
+            _ => assert!(matches!(result.unwrap_err(), AddError::OutsideWorkdir { .. })),
+        }
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::stage("y", Ok(HunkChoice::Stage))]
+    #[case::skip("n", Ok(HunkChoice::Skip))]
+    #[case::quit("q", Ok(HunkChoice::Quit))]
+    #[case::invalid("maybe", Err("invalid choice 'maybe', expected 'y', 'n', or 'q'".into()))]
+    fn hunk_choice_from_str(#[case] input: &str, #[case] expect: Result<HunkChoice, String>) {
+        assert_eq!(input.parse::<HunkChoice>(), expect);
+    }
+}
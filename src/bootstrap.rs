@@ -0,0 +1,874 @@
+// SPDX-FileCopyrightText: 2024 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+//! Bootstrap of new machines.
+//!
+//! Bootstrapping a new machine clones every repository whose `bootstrap`
+//! settings match it, i.e., its operating system, user, and host, checks out
+//! the configured branch, or pin, and runs its `on_clone` setup script. Through
+//! `ricer bootstrap --plan`, the user can see what that would do beforehand:
+//! which repositories match the machine and why, which of them would be
+//! cloned or skipped, which hooks and setup scripts would run, and how large
+//! each clone is, as far as its forge reports it. Nothing is cloned, and no
+//! hook runs, while planning.
+
+use crate::{
+    cancel::{self, CleanupGuard},
+    config::{BootstrapSettings, ConfigFile, ConfigFileError, OsType, RepoConfig, RepoSettings},
+    context::{BootstrapContext, EnvSnapshot},
+    forge::{self, Forge, HttpClient},
+    hook::{CmdHook, CmdHookError, ResolvedHook},
+    ignore,
+    init::DEFAULT_REMOTE,
+    locate::Locator,
+    plan::{self, DryRunEffect},
+    ui::Prompter,
+    util::human_size,
+    vcs::GitRepo,
+};
+
+use log::{debug, info, warn};
+use std::{fmt, path::Path};
+
+/// Error types for [`plan_bootstrap`] and [`bootstrap`].
+#[derive(Debug, thiserror::Error)]
+pub enum BootstrapError {
+    #[error("Failed to resolve bootstrap hooks")]
+    Hooks { source: Box<CmdHookError> },
+
+    #[error("Failed to read repository configuration")]
+    Config { source: ConfigFileError },
+
+    #[error("Bootstrap option '{option}' is not supported yet")]
+    Unsupported { option: &'static str },
+}
+
+impl From<ConfigFileError> for BootstrapError {
+    fn from(err: ConfigFileError) -> Self {
+        BootstrapError::Config { source: err }
+    }
+}
+
+impl From<CmdHookError> for BootstrapError {
+    fn from(err: CmdHookError) -> Self {
+        BootstrapError::Hooks { source: Box::new(err) }
+    }
+}
+
+/// Machine being bootstrapped.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Machine {
+    pub os: OsType,
+
+    /// Name of user account, if it could be determined.
+    pub user: Option<String>,
+
+    /// Name of host, if it could be determined.
+    pub host: Option<String>,
+}
+
+impl Machine {
+    pub fn new(os: OsType) -> Self {
+        Self { os, user: None, host: None }
+    }
+
+    pub fn user(mut self, user: impl Into<String>) -> Self {
+        self.user = Some(user.into());
+        self
+    }
+
+    pub fn host(mut self, host: impl Into<String>) -> Self {
+        self.host = Some(host.into());
+        self
+    }
+
+    /// Describe machine Ricer runs on, see [`EnvSnapshot::current`].
+    pub fn current() -> Self {
+        Self::from(EnvSnapshot::current().as_ref())
+    }
+}
+
+impl From<&EnvSnapshot> for Machine {
+    fn from(snapshot: &EnvSnapshot) -> Self {
+        Self { os: snapshot.os, user: snapshot.user.clone(), host: snapshot.host.clone() }
+    }
+}
+
+impl fmt::Display for Machine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} machine", self.os)?;
+        if let Some(user) = &self.user {
+            write!(f, ", user '{user}'")?;
+        }
+        if let Some(host) = &self.host {
+            write!(f, ", host '{host}'")?;
+        }
+        Ok(())
+    }
+}
+
+/// What bootstrapping would do with a repository.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlannedAction {
+    Clone,
+    Skip,
+}
+
+impl fmt::Display for PlannedAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlannedAction::Clone => write!(f, "clone"),
+            PlannedAction::Skip => write!(f, "skip"),
+        }
+    }
+}
+
+/// Repository of [`BootstrapPlan`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlannedRepo {
+    pub name: String,
+    pub action: PlannedAction,
+
+    /// Why repository matches machine or not, and why it would be skipped.
+    pub reasons: Vec<String>,
+
+    /// URL repository would be cloned from.
+    pub url: Option<String>,
+
+    /// Setup hook script that would run once repository is cloned.
+    pub on_clone: Option<String>,
+
+    /// Size of clone in bytes, if its forge reports it.
+    pub size: Option<u64>,
+}
+
+/// Everything bootstrapping would do, without doing any of it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BootstrapPlan {
+    pub machine: Machine,
+    pub repos: Vec<PlannedRepo>,
+
+    /// Command hooks of bootstrap that would run.
+    pub hooks: Vec<ResolvedHook>,
+}
+
+impl BootstrapPlan {
+    /// Repositories that would be cloned.
+    pub fn clones(&self) -> impl Iterator<Item = &PlannedRepo> {
+        self.repos.iter().filter(|repo| repo.action == PlannedAction::Clone)
+    }
+}
+
+impl fmt::Display for BootstrapPlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Bootstrap plan for {}", self.machine)?;
+        if self.repos.is_empty() {
+            return writeln!(f, "No repositories to bootstrap");
+        }
+
+        let sizes = self
+            .repos
+            .iter()
+            .map(|repo| repo.size.map_or_else(|| "-".into(), human_size))
+            .collect::<Vec<_>>();
+        let name_width = self
+            .repos
+            .iter()
+            .map(|repo| repo.name.len())
+            .max()
+            .unwrap_or(0)
+            .max("Repository".len());
+        let size_width = sizes.iter().map(String::len).max().unwrap_or(0).max("Size".len());
+        writeln!(f)?;
+        writeln!(
+            f,
+            "{:<name_width$}  {:<6}  {:>size_width$}  Reasons",
+            "Repository", "Action", "Size"
+        )?;
+        for (repo, size) in self.repos.iter().zip(&sizes) {
+            writeln!(
+                f,
+                "{:<name_width$}  {:<6}  {:>size_width$}  {}",
+                repo.name,
+                repo.action.to_string(),
+                size,
+                repo.reasons.join(", ")
+            )?;
+        }
+
+        let total = self.clones().filter_map(|repo| repo.size).sum::<u64>();
+        if total > 0 {
+            writeln!(f)?;
+            writeln!(f, "Estimated download: {}", human_size(total))?;
+        }
+
+        let setup = self
+            .clones()
+            .filter_map(|repo| repo.on_clone.as_ref().map(|script| (&repo.name, script)))
+            .collect::<Vec<_>>();
+        if !setup.is_empty() {
+            writeln!(f)?;
+            writeln!(f, "Setup scripts:")?;
+            for (name, script) in setup {
+                writeln!(f, "  {name}: {script}")?;
+            }
+        }
+
+        if !self.hooks.is_empty() {
+            writeln!(f)?;
+            writeln!(f, "Hooks:")?;
+            for hook in &self.hooks {
+                writeln!(f, "  {hook}")?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// What bootstrapping did with a repository.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BootstrapOutcome {
+    Cloned,
+    Skipped { reason: String },
+    Failed { reason: String },
+}
+
+impl fmt::Display for BootstrapOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BootstrapOutcome::Cloned => write!(f, "cloned"),
+            BootstrapOutcome::Skipped { reason } => write!(f, "skipped, {reason}"),
+            BootstrapOutcome::Failed { reason } => write!(f, "failed, {reason}"),
+        }
+    }
+}
+
+/// Check `bootstrap` settings against `machine`.
+///
+/// Returns whether every restriction matches, along with one reason per
+/// restriction.
+fn match_machine(bootstrap: Option<&BootstrapSettings>, machine: &Machine) -> (bool, Vec<String>) {
+    let mut matches = true;
+    let mut reasons = Vec::new();
+    let Some(bootstrap) = bootstrap else {
+        return (matches, vec!["no machine restrictions".into()]);
+    };
+
+    match bootstrap.os {
+        None | Some(OsType::Any) => (),
+        // INVARIANT: MacOS counts as Unix-like.
+        Some(os) if os == machine.os || (os == OsType::Unix && machine.os == OsType::MacOs) => {
+            reasons.push(format!("os '{os}' matches"));
+        }
+        Some(os) => {
+            matches = false;
+            reasons.push(format!("os '{os}' does not match '{}'", machine.os));
+        }
+    }
+
+    for (kind, allowed, actual) in
+        [("user", &bootstrap.users, &machine.user), ("host", &bootstrap.hosts, &machine.host)]
+    {
+        let Some(allowed) = allowed else {
+            continue;
+        };
+
+        match actual {
+            Some(actual) if allowed.contains(actual) => {
+                reasons.push(format!("{kind} '{actual}' listed"));
+            }
+            Some(actual) => {
+                matches = false;
+                reasons.push(format!("{kind} '{actual}' not in '{}'", allowed.join(", ")));
+            }
+            None => {
+                matches = false;
+                reasons.push(format!("{kind} unknown, needs one of '{}'", allowed.join(", ")));
+            }
+        }
+    }
+
+    if reasons.is_empty() {
+        reasons.push("no machine restrictions".into());
+    }
+    (matches, reasons)
+}
+
+/// Plan bootstrap of `machine` from [`BootstrapContext`].
+///
+/// Every registered repository is listed, in order of the repository
+/// configuration file, with the reasons it would be cloned or skipped.
+/// Repositories are skipped if they are left out through `--only`, do not
+/// match `machine`, have no clone URL, or are cloned already. Sizes of clones
+/// are looked up through `client` for repositories hosted on GitHub or GitLab.
+///
+/// # Invariants
+///
+/// 1. Nothing is cloned, and no hook is executed.
+/// 1. Failing to look up the size of a clone never fails the plan, it only
+///    leaves the size out.
+///
+/// # Errors
+///
+/// - Return [`BootstrapError::Config`] if repository configuration cannot be
+///   read.
+/// - Return [`BootstrapError::Hooks`] if bootstrap hooks cannot be resolved.
+pub fn plan_bootstrap<L, P, C>(
+    ctx: &BootstrapContext,
+    locator: &L,
+    hooks: &CmdHook<'_, L, P>,
+    machine: &Machine,
+    client: &C,
+) -> Result<BootstrapPlan, BootstrapError>
+where
+    L: Locator,
+    P: Prompter,
+    C: HttpClient + Clone,
+{
+    let mut repos = Vec::new();
+    for settings in ConfigFile::load(RepoConfig, locator)?.entries()? {
+        let bootstrap = settings.bootstrap.as_ref();
+        let url = bootstrap.and_then(BootstrapSettings::clone_url);
+        let on_clone = bootstrap.and_then(|bootstrap| bootstrap.on_clone.clone());
+        let (matches, mut reasons) = match_machine(bootstrap, machine);
+        let action = match skip_reason(ctx, locator, &settings, matches) {
+            Some(reason) => {
+                reasons.push(reason.into());
+                PlannedAction::Skip
+            }
+            None => PlannedAction::Clone,
+        };
+        let size = match (action, &url) {
+            (PlannedAction::Clone, Some(url)) => clone_size(url, client),
+            _ => None,
+        };
+        repos.push(PlannedRepo { name: settings.name, action, reasons, url, on_clone, size });
+    }
+
+    let hooks = hooks.resolved_hooks("bootstrap")?;
+    Ok(BootstrapPlan { machine: machine.clone(), repos, hooks })
+}
+
+/// Bootstrap `machine` from [`BootstrapContext`].
+///
+/// Every registered repository that [`plan_bootstrap`] would clone is cloned,
+/// in order of the repository configuration file. Once cloned, its configured
+/// branch is checked out, followed by its pin if any, and its `on_clone` setup
+/// script is executed through `hooks`. Repositories whose working directory is
+/// `home` are cloned through the fake bare technique.
+///
+/// # Invariants
+///
+/// 1. Failing to bootstrap one repository never stops the others, it is
+///    reported through [`BootstrapOutcome::Failed`] instead.
+/// 1. Fake bare clones never overwrite existing files of `home`, and leave no
+///    Git directory behind if they would. Such files can be adopted through
+///    `ricer clone --adopt-existing-files` instead.
+/// 1. Once cancelled, remaining repositories are left out.
+///
+/// # Errors
+///
+/// - Return [`BootstrapError::Unsupported`] if `--config` or `--from` is
+///   given.
+/// - Return [`BootstrapError::Config`] if repository configuration cannot be
+///   read.
+pub fn bootstrap<L, P>(
+    ctx: &BootstrapContext,
+    locator: &L,
+    hooks: &CmdHook<'_, L, P>,
+    machine: &Machine,
+    home: &Path,
+) -> Result<Vec<(String, BootstrapOutcome)>, BootstrapError>
+where
+    L: Locator,
+    P: Prompter,
+{
+    if ctx.config.is_some() {
+        return Err(BootstrapError::Unsupported { option: "--config" });
+    }
+    if ctx.from.is_some() {
+        return Err(BootstrapError::Unsupported { option: "--from" });
+    }
+
+    info!("Bootstrap {machine}");
+    let mut outcomes = Vec::new();
+    for settings in ConfigFile::load(RepoConfig, locator)?.entries()? {
+        if cancel::is_cancelled() {
+            warn!("Bootstrap cancelled, skip remaining repositories");
+            break;
+        }
+
+        let (matches, _) = match_machine(settings.bootstrap.as_ref(), machine);
+        let outcome = match skip_reason(ctx, locator, &settings, matches) {
+            Some(reason) => BootstrapOutcome::Skipped { reason: reason.into() },
+            None => match clone_repo(locator, &settings, hooks, home, machine) {
+                Ok(()) => BootstrapOutcome::Cloned,
+                Err(reason) => {
+                    warn!("Failed to bootstrap '{}': {reason}", settings.name);
+                    BootstrapOutcome::Failed { reason }
+                }
+            },
+        };
+        outcomes.push((settings.name, outcome));
+    }
+
+    Ok(outcomes)
+}
+
+/// Why bootstrapping leaves out repository of `settings`, if it does.
+fn skip_reason(
+    ctx: &BootstrapContext,
+    locator: &impl Locator,
+    settings: &RepoSettings,
+    matches: bool,
+) -> Option<&'static str> {
+    let url = settings.bootstrap.as_ref().and_then(|bootstrap| bootstrap.clone.as_ref());
+    match &ctx.only {
+        Some(only) if !only.contains(&settings.name) => Some("not selected through '--only'"),
+        _ if !matches => Some("does not match machine"),
+        _ if url.is_none() => Some("no clone URL"),
+        _ if settings.gitdir(locator.repos_dir()).exists() => Some("already cloned"),
+        _ => None,
+    }
+}
+
+/// Clone, check out, and set up repository of `settings` on `machine`.
+///
+/// Returns why it failed otherwise.
+fn clone_repo<L, P>(
+    locator: &L,
+    settings: &RepoSettings,
+    hooks: &CmdHook<'_, L, P>,
+    home: &Path,
+    machine: &Machine,
+) -> Result<(), String>
+where
+    L: Locator,
+    P: Prompter,
+{
+    let name = &settings.name;
+    let bootstrap = settings.bootstrap.as_ref();
+    // INVARIANT: repositories without clone URL are skipped.
+    let url = bootstrap.and_then(BootstrapSettings::clone_url).unwrap_or_default();
+    let gitdir = settings.gitdir(locator.repos_dir());
+    let into = gitdir.with_extension("");
+
+    info!("Clone '{name}' from '{url}'");
+    if !plan::perform(|| DryRunEffect::Clone { url: url.clone(), path: gitdir.clone() }) {
+        return Ok(());
+    }
+
+    let guard = CleanupGuard::new(&gitdir);
+    let repo = match settings.workdir_home {
+        false => GitRepo::clone(url, &into),
+        true => GitRepo::clone_fake_bare(url, &into, home),
+    }
+    .map_err(|err| err.to_string())?;
+
+    // INVARIANT: branches saved for host of machine win over default branch.
+    let branch = settings.branch_for(machine.host.as_deref());
+    if repo.head_branch().as_deref() != Some(branch) {
+        repo.switch_head(DEFAULT_REMOTE, branch)
+            .map_err(|err| format!("cannot switch to branch '{branch}': {err}"))?;
+    }
+    if settings.workdir_home {
+        let paths = repo.conflicting_files().map_err(|err| err.to_string())?;
+        if !paths.is_empty() {
+            return Err(format!(
+                "would overwrite {} existing file(s), adopt them through 'ricer clone \
+                 --adopt-existing-files'",
+                paths.len()
+            ));
+        }
+    }
+    repo.force_checkout_head().map_err(|err| err.to_string())?;
+    if let Some(pin) = &settings.pin {
+        info!("Check out '{name}' at pin '{pin}'");
+        repo.checkout_rev(pin).map_err(|err| format!("cannot check out pin '{pin}': {err}"))?;
+    }
+    guard.keep();
+    ignore::sync_excludes(locator, settings).map_err(|err| err.to_string())?;
+
+    if let Some(script) = bootstrap.and_then(|bootstrap| bootstrap.on_clone.as_deref()) {
+        hooks
+            .run_setup_hook(name, script, repo.workdir())
+            .map_err(|err| format!("setup script '{script}' failed: {err}"))?;
+    }
+
+    Ok(())
+}
+
+fn clone_size<C>(url: &str, client: &C) -> Option<u64>
+where
+    C: HttpClient + Clone,
+{
+    let (kind, path) = forge::hosted_repo(url)?;
+    match Forge::from_env(kind, client.clone()).repo_size(&path) {
+        Ok(size) => size,
+        Err(err) => {
+            debug!("Skip size of '{url}': {err}");
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        cli::Cli,
+        context::Context,
+        forge::{ForgeError, HttpResponse},
+        locate::MockLocator,
+        testenv::{mock_locator, FileKind, FixtureHarness},
+    };
+
+    use anyhow::Result;
+    use indoc::{formatdoc, indoc};
+    use pretty_assertions::assert_eq;
+    use rstest::{fixture, rstest};
+    use std::fs;
+
+    #[derive(Clone)]
+    struct FakeClient;
+
+    impl HttpClient for FakeClient {
+        fn get(
+            &self,
+            url: &str,
+            _accept: &str,
+            _token: Option<&str>,
+        ) -> Result<HttpResponse, ForgeError> {
+            match url {
+                "https://api.github.com/repos/awkless/vim" => {
+                    Ok(HttpResponse { status: 200, body: r#"{"size": 1536}"#.into() })
+                }
+                _ => Err(ForgeError::Http { url: url.into(), msg: "offline".into() }),
+            }
+        }
+    }
+
+    #[fixture]
+    fn rice_dir() -> Result<FixtureHarness> {
+        let harness = FixtureHarness::open()?
+            .with_file("config/repos.toml", |fixture| {
+                fixture
+                    .with_data(indoc! {r#"
+                        [repos.vim]
+                        branch = "main"
+                        remote = "origin"
+
+                        [repos.vim.bootstrap]
+                        clone = "https://github.com/awkless/vim.git"
+                        os = "unix"
+                        hosts = ["lovelace"]
+                        on_clone = "vim_setup.sh"
+
+                        [repos.st]
+                        branch = "main"
+                        remote = "origin"
+
+                        [repos.st.bootstrap]
+                        clone = "https://codeberg.org/awkless/st.git"
+                        users = ["sedgwick"]
+
+                        [repos.dwm]
+                        branch = "main"
+                        remote = "origin"
+
+                        [repos.dwm.bootstrap]
+                        clone = "https://gitlab.com/awkless/dwm.git"
+
+                        [repos.notes]
+                        branch = "main"
+                        remote = "origin"
+                    "#})
+                    .with_kind(FileKind::Normal)
+            })
+            .with_file("config/hooks.toml", |fixture| {
+                fixture
+                    .with_data(indoc! {r#"
+                        [hooks]
+                        bootstrap = [{ post = "welcome.sh" }]
+                    "#})
+                    .with_kind(FileKind::Normal)
+            })
+            .with_repo("repos/dwm", |repo| repo.stage("config.h", "configure DWM here"))?
+            .setup()?;
+        Ok(harness)
+    }
+
+    fn plan(args: &[&str], locator: &MockLocator) -> Result<BootstrapPlan> {
+        let ctx = Context::from(Cli::parse_args(args)?);
+        let hooks = CmdHook::load(&ctx, locator)?;
+        let machine = Machine::new(OsType::Unix).user("awkless").host("lovelace");
+        match &ctx {
+            Context::Bootstrap(bootstrap_ctx) => {
+                Ok(plan_bootstrap(bootstrap_ctx, locator, &hooks, &machine, &FakeClient)?)
+            }
+            _ => unreachable!("Expected bootstrap context"),
+        }
+    }
+
+    #[rstest]
+    fn plan_bootstrap_explain_every_repo(rice_dir: Result<FixtureHarness>) -> Result<()> {
+        let rice_dir = rice_dir?;
+        let result = plan(&["ricer", "bootstrap", "--plan"], &mock_locator(&rice_dir))?;
+        let summary = result
+            .repos
+            .iter()
+            .map(|repo| (repo.name.as_str(), repo.action, repo.reasons.join(", "), repo.size))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            vec![
+                (
+                    "vim",
+                    PlannedAction::Clone,
+                    "os 'unix' matches, host 'lovelace' listed".into(),
+                    Some(1536 * 1024)
+                ),
+                (
+                    "st",
+                    PlannedAction::Skip,
+                    "user 'awkless' not in 'sedgwick', does not match machine".into(),
+                    None
+                ),
+                (
+                    "dwm",
+                    PlannedAction::Skip,
+                    "no machine restrictions, already cloned".into(),
+                    None
+                ),
+                (
+                    "notes",
+                    PlannedAction::Skip,
+                    "no machine restrictions, no clone URL".into(),
+                    None
+                ),
+            ]
+        );
+        assert_eq!(result.hooks.len(), 1);
+        let hook = rice_dir.as_path().join("config/hooks/welcome.sh");
+        assert_eq!(
+            result.to_string(),
+            formatdoc! {"
+                Bootstrap plan for unix machine, user 'awkless', host 'lovelace'
+
+                Repository  Action     Size  Reasons
+                vim         clone   1.5 MiB  os 'unix' matches, host 'lovelace' listed
+                st          skip          -  user 'awkless' not in 'sedgwick', does not match machine
+                dwm         skip          -  no machine restrictions, already cloned
+                notes       skip          -  no machine restrictions, no clone URL
+
+                Estimated download: 1.5 MiB
+
+                Setup scripts:
+                  vim: vim_setup.sh
+
+                Hooks:
+                  bootstrap post #1: {} (prompt)
+            ", hook.display()}
+        );
+        Ok(())
+    }
+
+    #[rstest]
+    fn plan_bootstrap_skip_unselected_repos(rice_dir: Result<FixtureHarness>) -> Result<()> {
+        let rice_dir = rice_dir?;
+        let result =
+            plan(&["ricer", "bootstrap", "--plan", "--only", "st"], &mock_locator(&rice_dir))?;
+        assert_eq!(result.clones().count(), 0);
+        assert!(result.repos[0].reasons.contains(&"not selected through '--only'".to_string()));
+        Ok(())
+    }
+
+    #[fixture]
+    fn upstream_dir() -> Result<FixtureHarness> {
+        let harness = FixtureHarness::open()?
+            .with_repo("remote/vim", |repo| {
+                let repo = repo.stage("vimrc", "config for vim!")?;
+                repo.commit("initial commit")?;
+                Ok(repo)
+            })?
+            .with_repo("remote/bash", |repo| {
+                let repo = repo.stage(".bashrc", "set -o vi")?;
+                repo.commit("initial commit")?;
+                Ok(repo)
+            })?
+            .with_repo("remote/x11", |repo| {
+                let repo = repo.stage(".xinitrc", "exec dwm")?;
+                repo.commit("initial commit")?;
+                Ok(repo)
+            })?
+            .with_file("home/.bashrc", |fixture| {
+                fixture.with_data("set -o emacs").with_kind(FileKind::Normal)
+            })
+            .setup()?;
+
+        let root = harness.as_path();
+        let remote = |name: &str| -> Result<String> {
+            Ok(harness.get_repo(format!("remote/{name}"))?.as_path().display().to_string())
+        };
+        let vim = git2::Repository::open(remote("vim")?)?;
+        vim.branch("stable", &vim.head()?.peel_to_commit()?, false)?;
+        fs::create_dir_all(root.join("config"))?;
+        fs::write(
+            root.join("config/repos.toml"),
+            formatdoc! {r#"
+                [repos.vim]
+                branch = "stable"
+                remote = "origin"
+
+                [repos.vim.bootstrap]
+                clone = "{vim}"
+
+                [repos.bash]
+                branch = "main"
+                remote = "origin"
+                workdir_home = true
+
+                [repos.bash.bootstrap]
+                clone = "{bash}"
+
+                [repos.x11]
+                branch = "main"
+                remote = "origin"
+                workdir_home = true
+
+                [repos.x11.bootstrap]
+                clone = "{x11}"
+                os = "unix"
+
+                [repos.st]
+                branch = "main"
+                remote = "origin"
+
+                [repos.st.bootstrap]
+                clone = "{vim}"
+                users = ["sedgwick"]
+            "#, vim = remote("vim")?, bash = remote("bash")?, x11 = remote("x11")?},
+        )?;
+        Ok(harness)
+    }
+
+    fn run(
+        args: &[&str],
+        upstream_dir: &FixtureHarness,
+    ) -> Result<Vec<(String, BootstrapOutcome)>> {
+        let locator = mock_locator(upstream_dir);
+        let ctx = Context::from(Cli::parse_args(args)?);
+        let hooks = CmdHook::load(&ctx, &locator)?;
+        let machine = Machine::new(OsType::Unix).user("awkless").host("lovelace");
+        let home = upstream_dir.as_path().join("home");
+        match &ctx {
+            Context::Bootstrap(bootstrap_ctx) => {
+                Ok(bootstrap(bootstrap_ctx, &locator, &hooks, &machine, &home)?)
+            }
+            _ => unreachable!("Expected bootstrap context"),
+        }
+    }
+
+    #[rstest]
+    fn bootstrap_clone_matching_repos(upstream_dir: Result<FixtureHarness>) -> Result<()> {
+        let upstream_dir = upstream_dir?;
+        let outcomes = run(&["ricer", "bootstrap"], &upstream_dir)?;
+        let summary = outcomes
+            .iter()
+            .map(|(name, outcome)| (name.as_str(), outcome.to_string()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            vec![
+                ("vim", "cloned".into()),
+                (
+                    "bash",
+                    "failed, would overwrite 1 existing file(s), adopt them through 'ricer clone \
+                     --adopt-existing-files'"
+                        .into()
+                ),
+                ("x11", "cloned".into()),
+                ("st", "skipped, does not match machine".into()),
+            ]
+        );
+
+        let root = upstream_dir.as_path();
+        let vim = GitRepo::open(root.join("repos/vim.git"))?;
+        assert_eq!(vim.head_branch().as_deref(), Some("stable"));
+        assert!(vim.workdir().is_some_and(|workdir| workdir.join("vimrc").exists()));
+        assert!(!root.join("repos/bash.git").exists());
+        assert_eq!(fs::read_to_string(root.join("home/.bashrc"))?, "set -o emacs");
+        assert_eq!(fs::read_to_string(root.join("home/.xinitrc"))?, "exec dwm");
+
+        let outcomes = run(&["ricer", "bootstrap", "--only", "vim"], &upstream_dir)?;
+        assert_eq!(outcomes[0].1, BootstrapOutcome::Skipped { reason: "already cloned".into() });
+        Ok(())
+    }
+
+    #[rstest]
+    fn bootstrap_check_out_branch_of_host(upstream_dir: Result<FixtureHarness>) -> Result<()> {
+        let upstream_dir = upstream_dir?;
+        let config = upstream_dir.as_path().join("config/repos.toml");
+        let data = fs::read_to_string(&config)?.replacen(
+            "branch = \"stable\"",
+            "branch = \"main\"\nhost_branches = { lovelace = \"stable\" }",
+            1,
+        );
+        fs::write(&config, data)?;
+
+        let outcomes = run(&["ricer", "bootstrap", "--only", "vim"], &upstream_dir)?;
+        assert_eq!(outcomes[0].1, BootstrapOutcome::Cloned);
+        let vim = GitRepo::open(upstream_dir.as_path().join("repos/vim.git"))?;
+        assert_eq!(vim.head_branch().as_deref(), Some("stable"));
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::from(&["ricer", "bootstrap", "--from", "https://example.com/rice.git"], "--from")]
+    #[case::config(&["ricer", "bootstrap", "--config", "vim"], "--config")]
+    fn bootstrap_return_err_unsupported(
+        upstream_dir: Result<FixtureHarness>,
+        #[case] args: &[&str],
+        #[case] expect: &str,
+    ) -> Result<()> {
+        let upstream_dir = upstream_dir?;
+        let result = run(args, &upstream_dir);
+        let err = result.unwrap_err().downcast::<BootstrapError>()?;
+        assert!(matches!(err, BootstrapError::Unsupported { option } if option == expect));
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::unix_on_macos(Machine::new(OsType::MacOs).host("lovelace"), true, "os 'unix' matches")]
+    #[case::wrong_os(
+        Machine::new(OsType::Windows).host("lovelace"),
+        false,
+        "os 'unix' does not match 'windows'"
+    )]
+    #[case::unknown_host(
+        Machine::new(OsType::Unix),
+        false,
+        "host unknown, needs one of 'lovelace'"
+    )]
+    fn match_machine_explain_restrictions(
+        #[case] machine: Machine,
+        #[case] expect: bool,
+        #[case] reason: &str,
+    ) {
+        let bootstrap = BootstrapSettings::new().os(OsType::Unix).hosts(["lovelace"]);
+        let (matches, reasons) = match_machine(Some(&bootstrap), &machine);
+        assert_eq!(matches, expect);
+        assert!(reasons.contains(&reason.to_string()), "{reasons:?}");
+    }
+
+    #[rstest]
+    fn machine_from_env_snapshot() {
+        let snapshot =
+            EnvSnapshot::new(OsType::MacOs).user("awkless").host("lovelace").set("SHELL", "zsh");
+        assert_eq!(
+            Machine::from(&snapshot),
+            Machine::new(OsType::MacOs).user("awkless").host("lovelace")
+        );
+    }
+}
@@ -0,0 +1,177 @@
+// SPDX-FileCopyrightText: 2024 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+//! Graceful cancellation through Ctrl-C.
+//!
+//! Once [`install`] is called, the first SIGINT no longer kills Ricer outright.
+//! Instead, it marks the running command as cancelled. Operations that can
+//! take a while, like cloning or fetching, check [`is_cancelled`] and abort,
+//! so the command unwinds through its regular error path. Anything left
+//! half-written on the way is removed through [`CleanupGuard`], and Ricer
+//! exits with [`CANCELLED_EXIT_CODE`].
+//!
+//! A second SIGINT still exits right away, in case the running operation
+//! cannot be aborted.
+
+use log::{debug, warn};
+use signal_hook::{consts::SIGINT, flag};
+use std::{
+    fs,
+    io::Error as IoError,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, OnceLock,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Exit code of cancelled commands, like shells use for SIGINT.
+pub const CANCELLED_EXIT_CODE: i32 = 130;
+
+/// How often [`sleep`] checks for cancellation.
+const SLEEP_SLICE: Duration = Duration::from_millis(100);
+
+/// Error types for [`install`].
+#[derive(Debug, thiserror::Error)]
+pub enum CancelError {
+    #[error("Failed to install SIGINT handler")]
+    Install { source: IoError },
+}
+
+fn cancelled() -> &'static Arc<AtomicBool> {
+    static CANCELLED: OnceLock<Arc<AtomicBool>> = OnceLock::new();
+    CANCELLED.get_or_init(Default::default)
+}
+
+/// Handle SIGINT by marking running command as cancelled.
+///
+/// # Invariants
+///
+/// SIGINT only exits right away if command was already cancelled.
+///
+/// # Errors
+///
+/// - Return [`CancelError::Install`] if signal handler cannot be installed.
+pub fn install() -> Result<(), CancelError> {
+    let install_err = |err| CancelError::Install { source: err };
+
+    // INVARIANT: check for earlier SIGINT before this one marks cancellation.
+    flag::register_conditional_shutdown(SIGINT, CANCELLED_EXIT_CODE, Arc::clone(cancelled()))
+        .map_err(install_err)?;
+    flag::register(SIGINT, Arc::clone(cancelled())).map_err(install_err)?;
+    Ok(())
+}
+
+/// Determine if running command was cancelled.
+pub fn is_cancelled() -> bool {
+    cancelled().load(Ordering::SeqCst)
+}
+
+/// Sleep for `duration`, waking up early if command is cancelled.
+///
+/// Returns false if command was cancelled.
+pub fn sleep(duration: Duration) -> bool {
+    let start = Instant::now();
+    while !is_cancelled() {
+        let left = duration.saturating_sub(start.elapsed());
+        if left.is_zero() {
+            return true;
+        }
+        thread::sleep(left.min(SLEEP_SLICE));
+    }
+    false
+}
+
+/// Remove partially written file or directory, unless told to keep it.
+///
+/// Guards paths of operations that can fail or be cancelled midway, like
+/// cloning. Once the operation succeeds, [`CleanupGuard::keep`] keeps the
+/// path. Otherwise, the path is removed once the guard is dropped.
+///
+/// # Invariants
+///
+/// Paths that already existed before guarding them are never removed.
+#[derive(Debug)]
+pub struct CleanupGuard {
+    path: Option<PathBuf>,
+}
+
+impl CleanupGuard {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        match fs::symlink_metadata(&path) {
+            Ok(_) => Self { path: None },
+            Err(_) => Self { path: Some(path) },
+        }
+    }
+
+    /// Keep guarded path.
+    pub fn keep(mut self) {
+        self.path = None;
+    }
+}
+
+impl Drop for CleanupGuard {
+    fn drop(&mut self) {
+        let path = match self.path.take() {
+            Some(path) => path,
+            None => return,
+        };
+
+        let result = match fs::symlink_metadata(&path) {
+            Ok(meta) if meta.is_dir() => fs::remove_dir_all(&path),
+            Ok(_) => fs::remove_file(&path),
+            Err(_) => return,
+        };
+        match result {
+            Ok(_) => debug!("Remove partial '{}'", path.display()),
+            Err(err) => warn!("Failed to remove partial '{}': {err}", path.display()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testenv::FixtureHarness;
+
+    use anyhow::Result;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::dropped(false, false)]
+    #[case::kept(true, true)]
+    fn cleanup_guard_remove_unless_kept(#[case] keep: bool, #[case] expect: bool) -> Result<()> {
+        let harness = FixtureHarness::open()?;
+        let path = harness.as_path().join("vim.git");
+        let guard = CleanupGuard::new(&path);
+        fs::create_dir_all(path.join("objects"))?;
+        fs::write(path.join("HEAD"), "ref: refs/heads/main")?;
+        if keep {
+            guard.keep();
+        } else {
+            drop(guard);
+        }
+
+        assert_eq!(path.exists(), expect);
+        Ok(())
+    }
+
+    #[rstest]
+    fn cleanup_guard_never_remove_existing_path() -> Result<()> {
+        let harness = FixtureHarness::open()?;
+        let path = harness.as_path().join("existing.txt");
+        fs::write(&path, "do not touch")?;
+        drop(CleanupGuard::new(&path));
+
+        assert!(path.exists());
+        Ok(())
+    }
+
+    #[rstest]
+    fn sleep_return_true_if_not_cancelled() {
+        assert!(sleep(Duration::from_millis(10)));
+    }
+}
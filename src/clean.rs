@@ -0,0 +1,380 @@
+// SPDX-FileCopyrightText: 2024 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+//! Repository cleaning.
+//!
+//! Through `ricer clean <REPO> [PATHS]...`, untracked files are removed from
+//! the working directory of a repository, and files Git ignores too through
+//! `--ignored`. Files slated for removal are listed first, and only removed
+//! once the user confirms it, or `--yes` was given.
+//!
+//! Repositories that use the home directory as their working directory track
+//! only a handful of the files in it, so everything else would count as
+//! untracked. They are only ever cleaned inside paths given explicitly.
+
+use crate::{
+    config::{ConfigFile, ConfigFileError, RepoConfig, TomlError},
+    context::CleanContext,
+    locate::Locator,
+    plan::{self, DryRunEffect},
+    ui::{Prompter, UiError},
+    util::remove_file_pruning,
+    vcs::{GitRepo, GitRepoError},
+};
+
+use log::{debug, info};
+use std::{
+    fmt,
+    io::Error as IoError,
+    path::{Component, Path, PathBuf},
+};
+
+/// Error types for [`clean_repo`].
+#[derive(Debug, thiserror::Error)]
+pub enum CleanError {
+    #[error("Repository '{name}' is not registered")]
+    MissingRepo { name: String },
+
+    #[error("Repository '{name}' is not cloned at '{path}'")]
+    NotCloned { name: String, path: PathBuf },
+
+    #[error("Repository '{name}' has no working directory")]
+    NoWorkdir { name: String },
+
+    #[error(
+        "Repository '{name}' uses the home directory, name the paths to clean inside it \
+         explicitly"
+    )]
+    HomeWorkdir { name: String },
+
+    #[error("Path '{path}' is outside of working directory of '{name}'")]
+    OutsideWorkdir { name: String, path: PathBuf },
+
+    #[error("Failed to operate on repository '{name}'")]
+    Repo { source: GitRepoError, name: String },
+
+    #[error("Failed to remove '{path}'")]
+    FileRemove { source: IoError, path: PathBuf },
+
+    #[error("Failed to ask user")]
+    Ui { source: UiError },
+
+    #[error("Failed to access repository configuration")]
+    Config { source: ConfigFileError },
+}
+
+impl From<ConfigFileError> for CleanError {
+    fn from(err: ConfigFileError) -> Self {
+        CleanError::Config { source: err }
+    }
+}
+
+impl From<UiError> for CleanError {
+    fn from(err: UiError) -> Self {
+        CleanError::Ui { source: err }
+    }
+}
+
+/// Repository cleaned through [`clean_repo`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CleanedRepo {
+    pub name: String,
+
+    /// Removed files relative to working directory.
+    pub removed: Vec<PathBuf>,
+}
+
+impl fmt::Display for CleanedRepo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.removed.len() {
+            0 => write!(f, "Nothing removed from '{}'", self.name),
+            1 => write!(f, "Removed 1 file from '{}'", self.name),
+            count => write!(f, "Removed {count} files from '{}'", self.name),
+        }
+    }
+}
+
+/// Remove untracked files of repository from [`CleanContext`].
+///
+/// Paths of `ctx` are relative to the working directory of the repository, or
+/// absolute paths inside of it.
+///
+/// # Invariants
+///
+/// 1. Repositories that use the home directory as their working directory
+///    are only cleaned inside paths of `ctx`, none of which is the home
+///    directory itself.
+/// 1. Nothing is removed before the user agreed to it through `prompter`,
+///    unless `--yes` was given.
+/// 1. Directories emptied by cleaning are removed, up to the working
+///    directory.
+///
+/// # Errors
+///
+/// - Return [`CleanError::MissingRepo`] if repository is not registered.
+/// - Return [`CleanError::NotCloned`] if repository is not cloned.
+/// - Return [`CleanError::HomeWorkdir`] if repository uses the home
+///   directory, and no paths to clean inside it were given.
+/// - Return [`CleanError::NoWorkdir`] if repository is bare.
+/// - Return [`CleanError::OutsideWorkdir`] if a path points outside of the
+///   working directory.
+/// - Return [`CleanError::Repo`] if untracked files cannot be listed.
+/// - Return [`CleanError::Ui`] if user cannot be asked.
+/// - Return [`CleanError::FileRemove`] if a file cannot be removed.
+/// - Return [`CleanError::Config`] if repository configuration cannot be
+///   read.
+pub fn clean_repo(
+    ctx: &CleanContext,
+    locator: &impl Locator,
+    prompter: &impl Prompter,
+) -> Result<CleanedRepo, CleanError> {
+    let name = ctx.repo.clone();
+    let config = ConfigFile::load(RepoConfig, locator)?;
+    let settings = match config.get(&name) {
+        Ok(settings) => settings,
+        Err(ConfigFileError::Toml {
+            source: TomlError::EntryNotFound { .. } | TomlError::TableNotFound { .. },
+            ..
+        }) => return Err(CleanError::MissingRepo { name }),
+        Err(err) => return Err(err.into()),
+    };
+
+    let gitdir = settings.gitdir(locator.repos_dir());
+    if !gitdir.exists() {
+        return Err(CleanError::NotCloned { name, path: gitdir });
+    }
+    let repo_err = |err| CleanError::Repo { source: err, name: name.clone() };
+    let repo = GitRepo::open(&gitdir).map_err(repo_err)?;
+    let Some(workdir) = repo.workdir().map(Path::to_path_buf) else {
+        return Err(CleanError::NoWorkdir { name });
+    };
+
+    let scope = ctx
+        .paths
+        .iter()
+        .map(|path| {
+            scope_path(&workdir, path).ok_or_else(|| CleanError::OutsideWorkdir {
+                name: name.clone(),
+                path: path.clone(),
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let everything = scope.is_empty() || scope.iter().any(|path| path.as_os_str().is_empty());
+    // INVARIANT: never clean all of the home directory.
+    if settings.workdir_home && everything {
+        return Err(CleanError::HomeWorkdir { name });
+    }
+    let scope = match everything {
+        true => Vec::new(),
+        false => scope,
+    };
+
+    let files = repo
+        .untracked_files_in(&scope, ctx.ignored)
+        .map_err(repo_err)?
+        .into_iter()
+        .filter(|file| scope.is_empty() || scope.iter().any(|path| file.starts_with(path)))
+        .collect::<Vec<_>>();
+    if files.is_empty() {
+        info!("No untracked files in '{name}'");
+        return Ok(CleanedRepo { name, removed: files });
+    }
+
+    // INVARIANT: dry runs list files that would be removed without asking.
+    if plan::is_dry_run() {
+        for file in &files {
+            plan::perform(|| DryRunEffect::RemoveFile { path: workdir.join(file) });
+        }
+        return Ok(CleanedRepo { name, removed: Vec::new() });
+    }
+
+    if !ctx.yes {
+        let heading = format!("Untracked files of '{name}' in '{}':", workdir.display());
+        let items = files.iter().map(|file| file.display().to_string()).collect::<Vec<_>>();
+        prompter.show_list(&heading, &items)?;
+        if !prompter.confirm(&format!("Remove {} files of '{name}'?", files.len()))? {
+            return Ok(CleanedRepo { name, removed: Vec::new() });
+        }
+    }
+
+    for file in &files {
+        debug!("Remove '{}'", workdir.join(file).display());
+        remove_file_pruning(&workdir, file)
+            .map_err(|err| CleanError::FileRemove { source: err, path: workdir.join(file) })?;
+    }
+
+    Ok(CleanedRepo { name, removed: files })
+}
+
+/// Path to clean relative to `workdir`, given as `path` relative to, or
+/// inside of `workdir`.
+///
+/// Returns an empty path for `workdir` itself, or `None` if `path` points
+/// outside of it.
+fn scope_path(workdir: &Path, path: &Path) -> Option<PathBuf> {
+    let path = match path.is_absolute() {
+        true => path.strip_prefix(workdir).ok()?,
+        false => path,
+    };
+
+    let mut scope = PathBuf::new();
+    for part in path.components() {
+        match part {
+            Component::Normal(part) => scope.push(part),
+            Component::CurDir => (),
+            _ => return None,
+        }
+    }
+
+    Some(scope)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testenv::{mock_locator, parse_ctx, FileKind, FixtureHarness, ScriptedPrompter};
+
+    use anyhow::Result;
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+    use rstest::{fixture, rstest};
+    use std::fs;
+
+    #[fixture]
+    fn rice_dir() -> Result<FixtureHarness> {
+        let harness = FixtureHarness::open()?
+            .with_file("config/repos.toml", |fixture| {
+                fixture
+                    .with_data(indoc! {r#"
+                        [repos.vim]
+                        branch = "main"
+                        remote = "origin"
+                        workdir_home = false
+
+                        [repos.dots]
+                        branch = "main"
+                        remote = "origin"
+                        workdir_home = true
+                    "#})
+                    .with_kind(FileKind::Normal)
+            })
+            .with_repo("repos/vim", |repo| {
+                let repo = repo.stage("vimrc", "set number\n")?;
+                let repo = repo.stage(".gitignore", "*.swp\n")?;
+                repo.commit("Add vimrc")?;
+                Ok(repo)
+            })?
+            .with_fake_bare_repo("repos/dots", |repo| {
+                let repo = repo.stage(".bashrc", "set -o vi\n")?;
+                repo.commit("Add bashrc")?;
+                Ok(repo)
+            })?
+            .setup()?;
+        let vim = harness.as_path().join("repos/vim.git");
+        fs::create_dir_all(vim.join("plugged/fzf"))?;
+        fs::write(vim.join("plugged/fzf/plugin.vim"), "untracked")?;
+        fs::write(vim.join("notes.txt"), "untracked")?;
+        fs::write(vim.join("vimrc.swp"), "ignored")?;
+        Ok(harness)
+    }
+
+    #[rstest]
+    #[case::untracked(&["vim", "--yes"], vec!["notes.txt", "plugged/fzf/plugin.vim"])]
+    #[case::ignored(&["vim", "--yes", "--ignored"], vec![
+        "notes.txt", "plugged/fzf/plugin.vim", "vimrc.swp"
+    ])]
+    #[case::inside_paths(&["vim", "plugged", "--yes"], vec!["plugged/fzf/plugin.vim"])]
+    fn clean_repo_remove_untracked_files(
+        rice_dir: Result<FixtureHarness>,
+        #[case] args: &[&str],
+        #[case] expect: Vec<&str>,
+    ) -> Result<()> {
+        let rice_dir = rice_dir?;
+        let locator = mock_locator(&rice_dir);
+        let prompter = ScriptedPrompter::default();
+
+        let cleaned = clean_repo(&parse_ctx::<CleanContext>("clean", args)?, &locator, &prompter)?;
+        let mut removed = cleaned.removed.clone();
+        removed.sort();
+        assert_eq!(removed, expect.iter().map(PathBuf::from).collect::<Vec<_>>());
+        let vim = rice_dir.as_path().join("repos/vim.git");
+        for file in expect {
+            assert!(!vim.join(file).exists());
+        }
+        assert!(vim.join("vimrc").exists());
+        assert!(!vim.join("plugged").exists());
+        assert!(prompter.questions().is_empty());
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::confirm("y", true)]
+    #[case::decline("n", false)]
+    fn clean_repo_ask_before_removing(
+        rice_dir: Result<FixtureHarness>,
+        #[case] answer: &str,
+        #[case] removed: bool,
+    ) -> Result<()> {
+        let rice_dir = rice_dir?;
+        let locator = mock_locator(&rice_dir);
+        let prompter = ScriptedPrompter::new([answer]);
+
+        let cleaned = clean_repo(
+            &parse_ctx::<CleanContext>("clean", &["vim", "notes.txt"])?,
+            &locator,
+            &prompter,
+        )?;
+        assert_eq!(prompter.lists(), [vec!["notes.txt"]]);
+        assert_eq!(cleaned.removed.len(), usize::from(removed));
+        let notes = rice_dir.as_path().join("repos/vim.git/notes.txt");
+        assert_eq!(notes.exists(), !removed);
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::no_paths(&["dots", "--yes"])]
+    #[case::home_itself(&["dots", ".", "--yes"])]
+    fn clean_repo_return_err_home_workdir(
+        rice_dir: Result<FixtureHarness>,
+        #[case] args: &[&str],
+    ) -> Result<()> {
+        let rice_dir = rice_dir?;
+        let locator = mock_locator(&rice_dir);
+        let result = clean_repo(
+            &parse_ctx::<CleanContext>("clean", args)?,
+            &locator,
+            &ScriptedPrompter::default(),
+        );
+        assert!(matches!(result, Err(CleanError::HomeWorkdir { .. })));
+        Ok(())
+    }
+
+    #[rstest]
+    fn clean_repo_clean_home_workdir_inside_paths(rice_dir: Result<FixtureHarness>) -> Result<()> {
+        let rice_dir = rice_dir?;
+        let root = rice_dir.as_path();
+        fs::create_dir_all(root.join(".cache/dots"))?;
+        fs::write(root.join(".cache/dots/stale"), "untracked")?;
+        let locator = mock_locator(&rice_dir);
+
+        let ctx = parse_ctx::<CleanContext>("clean", &["dots", ".cache/dots", "--yes"])?;
+        let cleaned = clean_repo(&ctx, &locator, &ScriptedPrompter::default())?;
+        assert_eq!(cleaned.removed, [PathBuf::from(".cache/dots/stale")]);
+        assert!(!root.join(".cache").exists());
+        assert!(root.join(".bashrc").exists());
+        assert!(root.join("config/repos.toml").exists());
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::relative("vim", Some("vim"))]
+    #[case::current_dir("./vim", Some("vim"))]
+    #[case::absolute("/home/user/vim", Some("vim"))]
+    #[case::parent_dir("../vim", None)]
+    #[case::outside("/etc", None)]
+    #[case::workdir_itself("/home/user", Some(""))]
+    fn scope_path_stay_inside_workdir(#[case] path: &str, #[case] expect: Option<&str>) {
+        let expect = expect.map(PathBuf::from);
+        assert_eq!(scope_path(Path::new("/home/user"), Path::new(path)), expect);
+    }
+}
@@ -14,11 +14,16 @@
 //! Ricer's command set, `<COMMAND>` is the name of the Ricer command, and
 //! `[CMD_ARGS]` are the arguments to execute with.
 
-use crate::context::{FixupAction, HookAction};
-use clap::{Args, Parser, Subcommand};
+use crate::{
+    context::{ExampleTopic, FixupAction, HookAction, ListFormat, ProgressMode},
+    deprecate,
+    locate::{CONFIG_DIR_ENV, DATA_DIR_ENV, PROFILE_ENV, STATE_DIR_ENV},
+};
+use clap::{ArgGroup, Args, CommandFactory, Parser, Subcommand};
+use clap_complete::Shell;
 use clap_verbosity_flag::{InfoLevel, Verbosity};
 use indoc::indoc;
-use std::ffi::OsString;
+use std::{ffi::OsString, path::PathBuf};
 
 #[derive(Debug, thiserror::Error)]
 pub enum CliError {
@@ -31,6 +36,12 @@ macro_rules! explain_cmd_shortcuts {
         indoc! {r#"
         Command Shortcuts:
           <REPO> <GIT_CMD>  Shortcut to run user's Git binary on a target repository
+          <NAME> [ARGS]     Run `ricer-<NAME>` plugin from PATH, unless a repository
+                            is named <NAME>
+
+        Everything after <REPO> is forwarded to Git as-is, including flags like
+        --help. Use `ricer [OPTIONS] -- <REPO> <GIT_CMD>` for repositories that
+        share their name with a Ricer command.
         "#}
     };
 }
@@ -60,6 +71,9 @@ pub struct Cli {
     #[command(flatten, next_help_heading = "Logging Options")]
     pub log_opts: Verbosity<InfoLevel>,
 
+    #[command(flatten)]
+    pub locate_opts: LocateOptions,
+
     #[command(flatten)]
     pub shared_opts: SharedOptions,
 
@@ -70,6 +84,8 @@ pub struct Cli {
 impl Cli {
     /// Parse a set of command-line arguments.
     ///
+    /// Deprecated flags are translated into their replacement, with a warning.
+    ///
     /// # Errors
     ///
     /// Will return [`CliError::BadParse`] for invalid command-line arguments.
@@ -80,10 +96,93 @@ pub fn parse_args<I, T>(args: I) -> Result<Self, CliError>
         I: IntoIterator<Item = T>,
         T: Into<OsString> + Clone,
     {
+        let args = rewrite_deprecated(args.into_iter().map(Into::into));
         Self::try_parse_from(args).map_err(|err| CliError::BadParse { source: err })
     }
 }
 
+/// Translate deprecated flags of top-level options and Ricer commands.
+///
+/// # Invariants
+///
+/// 1. Arguments of the Git shortcut and plugins are never translated.
+/// 1. Arguments after `--` are never translated.
+fn rewrite_deprecated(args: impl IntoIterator<Item = OsString>) -> Vec<OsString> {
+    let cli = Cli::command();
+    let mut args = args.into_iter();
+    let mut rewritten = Vec::from_iter(args.next());
+    let mut command: Option<String> = None;
+    let mut expect_value = false;
+    for arg in args.by_ref() {
+        if arg == "--" {
+            rewritten.push(arg);
+            break;
+        }
+
+        if expect_value {
+            expect_value = false;
+        } else if !arg.to_str().is_some_and(|arg| arg.starts_with('-')) {
+            if command.is_none() {
+                match arg.to_str().and_then(|name| cli.find_subcommand(name)) {
+                    Some(subcommand) => command = Some(subcommand.get_name().into()),
+                    None => {
+                        rewritten.push(arg);
+                        break;
+                    }
+                }
+            }
+        } else {
+            let arg = deprecate::rewrite_flag(command.as_deref(), &arg).unwrap_or(arg);
+            expect_value = command.is_none() && takes_separate_value(&cli, &arg);
+            rewritten.push(arg);
+            continue;
+        }
+        rewritten.push(arg);
+    }
+    rewritten.extend(args);
+    rewritten
+}
+
+/// Check if top-level flag `arg` takes the next argument as its value.
+fn takes_separate_value(cli: &clap::Command, arg: &OsString) -> bool {
+    let Some(arg) = arg.to_str() else {
+        return false;
+    };
+    if let Some(long) = arg.strip_prefix("--") {
+        return !long.contains('=')
+            && cli
+                .get_arguments()
+                .any(|opt| opt.get_long() == Some(long) && opt.get_action().takes_values());
+    }
+
+    // INVARIANT: a short flag taking a value ends its cluster, e.g., `-vr ACTION`.
+    let shorts = arg.trim_start_matches('-');
+    for (index, short) in shorts.char_indices() {
+        let takes_value = cli
+            .get_arguments()
+            .any(|opt| opt.get_short() == Some(short) && opt.get_action().takes_values());
+        if takes_value {
+            return index + short.len_utf8() == shorts.len();
+        }
+    }
+    false
+}
+
+/// Parse `KEY=VALUE` argument of `--context`.
+///
+/// Keys may only consist of ASCII letters, digits, `_`, and `-`, so they
+/// always make valid environment variable names once upper-cased.
+fn parse_context_var(arg: &str) -> Result<(String, String), String> {
+    let (key, value) =
+        arg.split_once('=').ok_or_else(|| format!("expected KEY=VALUE, got '{arg}'"))?;
+    if key.is_empty() || !key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+        return Err(format!(
+            "invalid key '{key}', expected only ASCII letters, digits, '_', or '-'"
+        ));
+    }
+    Ok((key.into(), value.into()))
+}
+
 #[derive(Debug, Subcommand)]
 pub enum CommandSet {
     /// Bootstrap available repository configurations.
@@ -93,9 +192,16 @@ pub enum CommandSet {
     Clone(CloneOptions),
 
     /// Commit changes to all repositories.
+    ///
+    /// Patterns that look like paths, e.g., `~/.config/nvim/**`, select the
+    /// repositories that track changed files matching them, and only those
+    /// files are committed.
     Commit(CommitOptions),
 
     /// Delete target repository.
+    ///
+    /// Repositories that deploy into the home directory list their files, and
+    /// ask whether to remove them too.
     Delete(DeleteOptions),
 
     /// Enter a target repository.
@@ -119,16 +225,194 @@ pub enum CommandSet {
     /// Show status of repositories.
     Status(StatusOptions),
 
+    /// Show resolved environment of Ricer.
+    Env(EnvOptions),
+
+    /// Manage profiles.
+    Profile(ProfileOptions),
+
+    /// Inspect command hooks.
+    #[command(visible_alias = "hooks")]
+    Hook(HookOptions),
+
+    /// Tag all repositories and export their exact state.
+    Snapshot(SnapshotOptions),
+
+    /// Link files of a symlink-mode repository into the home directory.
+    Deploy(DeployOptions),
+
+    /// Remove links made by deploy from the home directory.
+    Undeploy(UndeployOptions),
+
+    /// Show which repositories track a file.
+    Owns(OwnsOptions),
+
+    /// Periodically fetch all repositories in the background.
+    Daemon(DaemonOptions),
+
+    /// Search and replace in tracked files of repositories.
+    Sed(SedOptions),
+
+    /// Show history of Ricer commands.
+    History(HistoryOptions),
+
+    /// Stage changes to files of a repository.
+    Add(AddOptions),
+
+    /// Split a directory out of a repository into a new repository.
+    Split(SplitOptions),
+
+    /// Remove untracked files from working directory of a repository.
+    ///
+    /// Files slated for removal are listed, and only removed once confirmed.
+    /// Repositories that use the home directory are only cleaned inside given
+    /// paths.
+    Clean(CleanOptions),
+
+    /// Switch repository to another branch, e.g., another theme.
+    ///
+    /// Branches that only exist on the default remote are checked out as new
+    /// tracking branches. Post hooks of 'switch' run once switched, to reload
+    /// programs.
+    Switch(SwitchOptions),
+
+    /// Manage remotes of repositories.
+    Remotes(RemotesOptions),
+
+    /// Run shell snippet in every repository.
+    Foreach(ForeachOptions),
+
+    /// Encrypt configuration files through age.
+    Lock(LockOptions),
+
+    /// Decrypt configuration files locked through lock.
+    Unlock(UnlockOptions),
+
+    /// Check configuration files for problems, like deprecated keys, or
+    /// references to deleted repositories and hook scripts.
+    Doctor(DoctorOptions),
+
+    /// Work with configuration files.
+    Config(ConfigOptions),
+
+    /// Show everything Ricer knows about one repository.
+    Info(InfoOptions),
+
+    /// Print or install shell completions.
+    Completions(CompletionsOptions),
+
+    /// Show aggregate statistics of recorded commands or hooks.
+    Stats(StatsOptions),
+
+    /// Show annotated examples of configuration and commands.
+    Examples(ExamplesOptions),
+
+    /// Keep files out of status of repositories through ignore files.
+    Ignore(IgnoreOptions),
+
+    /// Write redacted diagnostic bundle to attach to a bug report.
+    Bugreport(BugreportOptions),
+
+    /// Move configuration directory elsewhere, e.g., into a synced folder.
+    ///
+    /// Configuration directory moves into DIR/config, and, if asked to, data
+    /// and state directories into DIR/data and DIR/state. Absolute paths of
+    /// configuration files are updated, and the new location is recorded in
+    /// location.toml of the standard configuration directory.
+    MoveConfigDir(MoveConfigDirOptions),
+
     /// Run user's Git binary on target repository.
+    ///
+    /// # Invariants
+    ///
+    /// 1. First element is the repository name, and the rest are arguments to
+    ///    Git that are captured verbatim, i.e., Ricer never interprets flags
+    ///    that come after the repository name.
+    /// 1. Ricer options must come before the repository name.
+    /// 1. A `--` before the repository name forces the shortcut, even if the
+    ///    repository shares its name with a Ricer command.
     #[command(external_subcommand)]
     Git(Vec<OsString>),
 }
 
+#[derive(Debug, Args)]
+#[command(next_help_heading = "Location Options")]
+pub struct LocateOptions {
+    /// Use DIR as configuration directory.
+    #[arg(long, value_name = "DIR", env = CONFIG_DIR_ENV)]
+    pub config_dir: Option<PathBuf>,
+
+    /// Use DIR as data directory, which holds repositories.
+    #[arg(long, value_name = "DIR", env = DATA_DIR_ENV)]
+    pub data_dir: Option<PathBuf>,
+
+    /// Use DIR as state directory.
+    #[arg(long, value_name = "DIR", env = STATE_DIR_ENV)]
+    pub state_dir: Option<PathBuf>,
+
+    /// Use separate configuration and data of profile NAME.
+    #[arg(long, value_name = "NAME", env = PROFILE_ENV)]
+    pub profile: Option<String>,
+}
+
 #[derive(Debug, Args)]
 #[command(next_help_heading = "Command Options")]
 pub struct SharedOptions {
-    #[arg(default_value_t = HookAction::default(), long, short, value_enum, value_name = "ACTION")]
+    #[arg(
+        default_value_t = HookAction::default(),
+        long = "run-hooks",
+        short,
+        value_enum,
+        value_name = "ACTION"
+    )]
     pub run_hook: HookAction,
+
+    /// Never execute hooks, regardless of any other hook setting.
+    #[arg(long)]
+    pub no_hooks: bool,
+
+    /// Only execute hooks selected by name, script file name, or position.
+    #[arg(long, value_name = "HOOKS", value_delimiter = ',')]
+    pub only_hooks: Vec<String>,
+
+    /// Skip hooks selected by name, script file name, or position.
+    #[arg(long, value_name = "HOOKS", value_delimiter = ',')]
+    pub skip_hooks: Vec<String>,
+
+    /// Re-read hook configuration file between hooks whenever it changes.
+    #[arg(long)]
+    pub reload_hooks: bool,
+
+    /// Never page command output.
+    #[arg(long)]
+    pub no_pager: bool,
+
+    /// Print how long each phase of the command took.
+    #[arg(long)]
+    pub timings: bool,
+
+    /// Trust SSH host keys of unknown hosts without asking.
+    #[arg(long)]
+    pub no_verify_host: bool,
+
+    /// Do not warn about unknown keys, wrong value types, or missing keys of
+    /// configuration files.
+    #[arg(long)]
+    pub no_validate: bool,
+
+    /// Draw progress of clone, fetch, pull, and push.
+    #[arg(default_value_t = ProgressMode::default(), long, value_enum, value_name = "WHEN")]
+    pub progress: ProgressMode,
+
+    /// Pass variable KEY to hooks as RICER_VAR_KEY, and to templates as
+    /// {{var.KEY}}.
+    #[arg(long, value_name = "KEY=VALUE", value_parser = parse_context_var)]
+    pub context: Vec<(String, String)>,
+
+    /// Show which files would be written, repositories cloned, and hooks run,
+    /// without doing any of it.
+    #[arg(long)]
+    pub dry_run: bool,
 }
 
 #[derive(Args, Debug)]
@@ -144,6 +428,10 @@ pub struct BootstrapOptions {
     /// Bootstrap only a set of specific repositories.
     #[arg(long, short, value_name = "REPOS", num_args = 1.., value_delimiter = ',')]
     pub only: Option<Vec<String>>,
+
+    /// Show what bootstrapping this machine would do, without doing it.
+    #[arg(long, conflicts_with_all = ["config", "from"])]
+    pub plan: bool,
 }
 
 #[derive(Args, Debug)]
@@ -155,21 +443,74 @@ pub struct CommitOptions {
     /// Use MSG as the commit message.
     #[arg(long, short, value_name = "MSG")]
     pub message: Option<String>,
+
+    /// Skip lint rules of commit messages, and checks of staged files.
+    #[arg(long, short)]
+    pub no_verify: bool,
+
+    /// Push every repository committed to right after committing.
+    #[arg(long, short)]
+    pub push: bool,
+
+    /// Only stage and commit changed tracked files matching gitignore-style
+    /// pattern.
+    #[arg(long, value_name = "GLOB")]
+    pub include_glob: Vec<String>,
+
+    /// Leave changed tracked files matching gitignore-style pattern out of
+    /// commit.
+    #[arg(long, value_name = "GLOB")]
+    pub exclude_glob: Vec<String>,
+
+    /// Commit staged files even if they match the exclude list of their
+    /// repository.
+    #[arg(long)]
+    pub allow_excluded: bool,
+
+    #[command(flatten)]
+    pub select: SelectOptions,
 }
 
 #[derive(Args, Debug)]
+#[command(group(ArgGroup::new("forge").args(["from_github", "from_gitlab"])))]
 pub struct CloneOptions {
     /// Remove to clone from.
-    pub remote: String,
+    #[arg(required_unless_present_any = ["from_github", "from_gitlab"])]
+    pub remote: Option<String>,
 
     /// Set name of cloned repository.
     pub repo: Option<String>,
+
+    /// Clone all repositories of GitHub user or organization.
+    #[arg(long, value_name = "NAMESPACE", conflicts_with = "remote")]
+    pub from_github: Option<String>,
+
+    /// Clone all repositories of GitLab user or group.
+    #[arg(long, value_name = "NAMESPACE", conflicts_with = "remote")]
+    pub from_gitlab: Option<String>,
+
+    /// Only clone forge repositories tagged with topic.
+    #[arg(long, requires = "forge")]
+    pub topic: Option<String>,
+
+    /// Only clone forge repositories whose name matches glob pattern.
+    #[arg(long, value_name = "GLOB", requires = "forge")]
+    pub pattern: Option<String>,
+
+    /// Keep, replace, or back up files of home directory one by one, instead
+    /// of aborting when cloning would overwrite them.
+    #[arg(long, conflicts_with = "forge")]
+    pub adopt_existing_files: bool,
 }
 
 #[derive(Args, Debug)]
 pub struct DeleteOptions {
     /// Target repository to delete.
     pub repo: String,
+
+    /// Leave files of repository in home directory without asking.
+    #[arg(long)]
+    pub keep_files: bool,
 }
 
 #[derive(Args, Debug)]
@@ -194,6 +535,18 @@ pub struct InitOptions {
     /// Set default remote to use.
     #[arg(short, long, value_name = "ORIGIN")]
     pub remote: Option<String>,
+
+    /// Copy template NAME into new repository.
+    #[arg(short, long, value_name = "NAME")]
+    pub template: Option<String>,
+
+    /// Make initial commit, with template files if any.
+    #[arg(short = 'c', long)]
+    pub initial_commit: bool,
+
+    /// Replace existing registration of repository.
+    #[arg(short, long)]
+    pub force: bool,
 }
 
 #[derive(Args, Debug)]
@@ -205,6 +558,10 @@ pub struct ListOptions {
     /// Show all untracked files in repositories.
     #[arg(short, long)]
     pub untracked: bool,
+
+    /// Print listing in FORMAT, so scripts can read it.
+    #[arg(long, value_name = "FORMAT", value_enum, default_value_t = ListFormat::Plain)]
+    pub format: ListFormat,
 }
 
 #[derive(Args, Debug)]
@@ -218,11 +575,24 @@ pub struct PushOptions {
 
 #[derive(Args, Debug)]
 pub struct PullOptions {
-    /// Target remote to push to.
+    /// Target remote to pull from.
+    #[arg(long, value_name = "REMOTE")]
     pub remote: Option<String>,
 
-    /// Target branch to push to.
+    /// Target branch to pull from.
+    #[arg(long, value_name = "BRANCH")]
     pub branch: Option<String>,
+
+    /// Migrate to new default branch of remote if old branch is gone.
+    #[arg(long)]
+    pub fix: bool,
+
+    /// Pull up to N repositories at once.
+    #[arg(long, short, value_name = "N", value_parser = clap::value_parser!(u16).range(1..))]
+    pub jobs: Option<u16>,
+
+    #[command(flatten)]
+    pub select: SelectOptions,
 }
 
 #[derive(Args, Debug)]
@@ -232,6 +602,10 @@ pub struct RenameOptions {
 
     /// New name to give target repository.
     pub to: String,
+
+    /// Show what would be done without doing it.
+    #[arg(long)]
+    pub dry_run: bool,
 }
 
 #[derive(Args, Debug)]
@@ -239,13 +613,449 @@ pub struct StatusOptions {
     /// Give a short status report.
     #[arg(long, short)]
     pub terse: bool,
+
+    /// Skip scanning working directories, only reporting staged changes.
+    #[arg(long)]
+    pub assume_clean: bool,
+
+    #[command(flatten)]
+    pub select: SelectOptions,
+}
+
+#[derive(Args, Debug)]
+pub struct EnvOptions;
+
+#[derive(Args, Debug)]
+pub struct ProfileOptions {
+    #[command(subcommand)]
+    pub action: ProfileAction,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ProfileAction {
+    /// Fork existing profile into a new profile.
+    Fork(ProfileForkOptions),
+}
+
+#[derive(Args, Debug)]
+pub struct ProfileForkOptions {
+    /// Name of new profile.
+    pub name: String,
+
+    /// Profile to fork, instead of the default profile.
+    #[arg(long, value_name = "PROFILE")]
+    pub from: Option<String>,
+
+    /// Share Git directories of forked profile's repositories.
+    #[arg(long)]
+    pub share_repos: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct HookOptions {
+    #[command(subcommand)]
+    pub action: HookCmdAction,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum HookCmdAction {
+    /// List hooks defined for commands.
+    List(HookListOptions),
+
+    /// Show kept output of executed hook scripts.
+    Log(HookLogOptions),
+}
+
+#[derive(Args, Debug)]
+pub struct HookListOptions {
+    /// Only list hooks of this command.
+    #[arg(value_name = "COMMAND")]
+    pub cmd: Option<String>,
+
+    /// Only list hooks that would run with the given hook options.
+    #[arg(long)]
+    pub effective: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct HookLogOptions {
+    /// Show full output of this log, instead of listing logs.
+    #[arg(value_name = "ID")]
+    pub id: Option<String>,
+
+    /// Only list logs of hook scripts that failed.
+    #[arg(long)]
+    pub failed: bool,
+
+    /// Only list the last N logs.
+    #[arg(long, short = 'n', value_name = "N")]
+    pub limit: Option<usize>,
+}
+
+#[derive(Args, Debug)]
+pub struct SnapshotOptions {
+    /// Name of snapshot, used as tag name in every repository.
+    pub name: Option<String>,
+
+    /// Write snapshot archive into DIR.
+    #[arg(long, short, value_name = "DIR")]
+    pub output: Option<PathBuf>,
+
+    /// Also store a Git bundle of every repository in the archive.
+    #[arg(long)]
+    pub bundle: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct DeployOptions {
+    /// Target repository to deploy.
+    pub repo: String,
+}
+
+#[derive(Args, Debug)]
+pub struct UndeployOptions {
+    /// Target repository to undeploy.
+    pub repo: String,
+}
+
+#[derive(Args, Debug)]
+pub struct OwnsOptions {
+    /// Files, directories, or glob patterns to look up.
+    #[arg(required = true, value_name = "PATH")]
+    pub paths: Vec<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct DaemonOptions {
+    /// Fetch due repositories once, and exit.
+    #[arg(long)]
+    pub once: bool,
+
+    /// Print systemd user unit that runs the daemon, and exit.
+    #[arg(long, conflicts_with = "once")]
+    pub systemd: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct SedOptions {
+    /// Regular expression to search for.
+    pub pattern: String,
+
+    /// Replacement text, where `$1` or `${name}` refer to capture groups.
+    pub replacement: String,
+
+    /// Only replace in target repository, can be given more than once.
+    #[arg(long = "repo", short, value_name = "REPO")]
+    pub repos: Vec<String>,
+
+    /// Write replacements without asking for confirmation.
+    #[arg(long, short)]
+    pub yes: bool,
+
+    /// Commit replacements to each changed repository with MESSAGE.
+    #[arg(long, short, value_name = "MESSAGE")]
+    pub commit: Option<String>,
+
+    #[command(flatten)]
+    pub select: SelectOptions,
+}
+
+#[derive(Args, Debug)]
+pub struct HistoryOptions {
+    /// Only show commands that operated on target repository.
+    #[arg(long, short, value_name = "REPO")]
+    pub repo: Option<String>,
+
+    /// Only show invocations of target command.
+    #[arg(long, short, value_name = "COMMAND")]
+    pub command: Option<String>,
+
+    /// Only show commands that failed or were cancelled.
+    #[arg(long)]
+    pub failed: bool,
+
+    /// Only show the last N commands.
+    #[arg(long, short = 'n', value_name = "N")]
+    pub limit: Option<usize>,
+}
+
+#[derive(Args, Debug)]
+pub struct AddOptions {
+    /// Name of repository to stage changes in.
+    pub repo: String,
+
+    /// Files to stage, or every changed file with `--patch`.
+    #[arg(required_unless_present = "patch")]
+    pub paths: Vec<PathBuf>,
+
+    /// Interactively choose hunks of changes to stage.
+    #[arg(short, long)]
+    pub patch: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct SplitOptions {
+    /// Name of repository to split directory out of.
+    pub repo: String,
+
+    /// Directory to split out, relative to working directory of repository.
+    pub dir: PathBuf,
+
+    /// Name of new repository.
+    pub name: String,
+
+    /// Start new repository from current contents of directory only.
+    #[arg(long)]
+    pub no_history: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct CleanOptions {
+    /// Target repository to clean.
+    pub repo: String,
+
+    /// Only clean inside these paths, relative to working directory of
+    /// repository.
+    pub paths: Vec<PathBuf>,
+
+    /// Remove files Git ignores too.
+    #[arg(long, short = 'x')]
+    pub ignored: bool,
+
+    /// Remove files without asking.
+    #[arg(long, short)]
+    pub yes: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct SwitchOptions {
+    /// Target repository to switch.
+    pub repo: String,
+
+    /// Branch to switch to.
+    pub branch: String,
+
+    /// Remember branch for this host, so bootstrapping it checks out branch.
+    #[arg(long, short)]
+    pub save: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct RemotesOptions {
+    #[command(subcommand)]
+    pub action: RemotesAction,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum RemotesAction {
+    /// Rewrite remote URLs of repositories.
+    Rewrite(RemotesRewriteOptions),
+}
+
+#[derive(Args, Debug)]
+pub struct RemotesRewriteOptions {
+    /// Text of remote URLs to replace, e.g., `github.com`.
+    #[arg(long, value_name = "TEXT")]
+    pub from: String,
+
+    /// Text to replace it with, e.g., `codeberg.org`.
+    #[arg(long, value_name = "TEXT")]
+    pub to: String,
+
+    /// Only rewrite remotes of target repository, can be given more than once.
+    #[arg(long = "repo", short, value_name = "REPO")]
+    pub repos: Vec<String>,
+
+    /// Rewrite remotes without asking for confirmation.
+    #[arg(long, short)]
+    pub yes: bool,
+
+    #[command(flatten)]
+    pub select: SelectOptions,
+}
+
+#[derive(Args, Debug)]
+pub struct ForeachOptions {
+    /// Shell snippet, where `{name}`, `{path}`, `{gitdir}`, `{branch}`, and
+    /// `{remote}` refer to the repository it runs in.
+    pub command: String,
+
+    /// Only run in target repository, can be given more than once.
+    #[arg(long = "repo", short, value_name = "REPO")]
+    pub repos: Vec<String>,
+
+    /// Run in up to N repositories at once.
+    #[arg(long, short, value_name = "N", value_parser = clap::value_parser!(u16).range(1..))]
+    pub jobs: Option<u16>,
+
+    #[command(flatten)]
+    pub select: SelectOptions,
+}
+
+#[derive(Args, Debug)]
+pub struct SelectOptions {
+    /// Only operate on repositories matching name or glob pattern, e.g.,
+    /// `'dwm*'`.
+    #[arg(value_name = "PATTERN")]
+    pub patterns: Vec<String>,
+
+    /// Fail if any pattern matches no repository.
+    #[arg(long)]
+    pub strict: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct LockOptions;
+
+#[derive(Args, Debug)]
+pub struct UnlockOptions;
+
+#[derive(Args, Debug)]
+pub struct DoctorOptions {
+    /// Prune references to deleted repositories, groups, and hook scripts,
+    /// asking about each one first.
+    #[arg(long)]
+    pub fix: bool,
+
+    /// Prune references without asking for confirmation.
+    #[arg(long, short, requires = "fix")]
+    pub yes: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct ConfigOptions {
+    #[command(subcommand)]
+    pub action: ConfigAction,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ConfigAction {
+    /// Check configuration files again every time one of them is saved.
+    ///
+    /// Repository, hook, and Ricer configuration files are checked right
+    /// away, and again whenever one of them, or its local overlay, changes.
+    /// Problems are printed until interrupted through Ctrl-C.
+    Watch(ConfigWatchOptions),
+}
+
+#[derive(Args, Debug)]
+pub struct ConfigWatchOptions {
+    /// How often to look for changes, in milliseconds.
+    #[arg(long, value_name = "MILLIS", default_value_t = 500,
+          value_parser = clap::value_parser!(u64).range(1..))]
+    pub interval: u64,
+
+    /// Also show problems as desktop notifications through notify-send.
+    #[arg(long)]
+    pub notify: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct InfoOptions {
+    /// Target repository to report on.
+    pub repo: String,
+
+    /// Print report as JSON.
+    #[arg(long)]
+    pub json: bool,
+}
+
+#[derive(Args, Debug)]
+#[command(group(ArgGroup::new("change").args(["install", "uninstall"])))]
+pub struct CompletionsOptions {
+    /// Shell to generate completions for, detected through SHELL if omitted.
+    pub shell: Option<Shell>,
+
+    /// Write completions into the completion directory of the shell.
+    #[arg(long)]
+    pub install: bool,
+
+    /// Remove completions written through --install.
+    #[arg(long)]
+    pub uninstall: bool,
+
+    /// Show what would be installed or uninstalled without doing it.
+    #[arg(long, requires = "change")]
+    pub dry_run: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct StatsOptions {
+    /// Show statistics of hook scripts instead of commands.
+    #[arg(long)]
+    pub hooks: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct ExamplesOptions {
+    /// Topic to show examples of, or list every topic if omitted.
+    pub topic: Option<ExampleTopic>,
+}
+
+#[derive(Args, Debug)]
+pub struct IgnoreOptions {
+    #[command(subcommand)]
+    pub action: IgnoreAction,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum IgnoreAction {
+    /// Ignore files matching gitignore-style patterns.
+    Add(IgnorePatternOptions),
+
+    /// Stop ignoring files matching patterns.
+    Remove(IgnorePatternOptions),
+
+    /// List ignore patterns of repository.
+    List(IgnoreListOptions),
+}
+
+#[derive(Args, Debug)]
+pub struct IgnorePatternOptions {
+    /// Name of target repository.
+    pub repo: String,
+
+    /// Gitignore-style patterns, e.g., `.cache/`.
+    #[arg(required = true, value_name = "PATTERN")]
+    pub patterns: Vec<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct IgnoreListOptions {
+    /// Name of target repository.
+    pub repo: String,
+}
+
+#[derive(Args, Debug)]
+pub struct BugreportOptions {
+    /// Write bug report archive into DIR.
+    #[arg(long, short, value_name = "DIR")]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+pub struct MoveConfigDirOptions {
+    /// Move directories into DIR.
+    #[arg(value_name = "DIR")]
+    pub to: PathBuf,
+
+    /// Move data directory holding repositories too.
+    #[arg(long)]
+    pub data: bool,
+
+    /// Move state directory too.
+    #[arg(long)]
+    pub state: bool,
+
+    /// Move directories without asking for confirmation.
+    #[arg(long, short)]
+    pub yes: bool,
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    use clap::CommandFactory;
     use rstest::rstest;
 
     #[test]
@@ -255,17 +1065,77 @@ fn cli_verify_structure() {
 
     #[rstest]
     #[case::invalid_bootstrap_args(["ricer", "bootstrap", "--non-existent"])]
+    #[case::invalid_bootstrap_plan_from(["ricer", "bootstrap", "--plan", "--from", "url"])]
     #[case::invalid_commit_args(["ricer", "commit", "--non-existent"])]
     #[case::invalid_clone_args(["ricer", "clone", "--non-existent"])]
+    #[case::invalid_clone_forge_and_remote(["ricer", "clone", "url", "--from-github", "foo"])]
+    #[case::invalid_clone_two_forges(["ricer", "clone", "--from-github", "a", "--from-gitlab", "b"])]
+    #[case::invalid_clone_topic_without_forge(["ricer", "clone", "url", "--topic", "dotfiles"])]
+    #[case::invalid_clone_adopt_with_forge([
+        "ricer", "clone", "--from-github", "a", "--adopt-existing-files"
+    ])]
     #[case::invalid_delete_args(["ricer", "delete", "foo", "--non-existent"])]
     #[case::invalid_enter_args(["ricer", "enter", "foo", "--non-existent"])]
     #[case::invalid_init_args(["ricer", "init", "--non-existent"])]
     #[case::invalid_list_args(["ricer", "list", "--non-existent"])]
+    #[case::invalid_list_format(["ricer", "list", "--format", "yaml"])]
     #[case::invalid_push_args(["ricer", "push", "--non-existent"])]
     #[case::invalid_pull_args(["ricer", "pull", "--non-existent"])]
+    #[case::invalid_pull_no_remote(["ricer", "pull", "dwm", "--remote"])]
     #[case::invalid_rename_args(["ricer", "rename", "foo", "bar", "--non-existent"])]
     #[case::invalid_status_args(["ricer", "status", "--non-existent"])]
+    #[case::invalid_env_args(["ricer", "env", "--non-existent"])]
+    #[case::invalid_profile_args(["ricer", "profile", "fork", "work", "--non-existent"])]
+    #[case::invalid_profile_no_action(["ricer", "profile"])]
+    #[case::invalid_hook_args(["ricer", "hook", "list", "--non-existent"])]
+    #[case::invalid_hook_log_limit(["ricer", "hook", "log", "-n", "few"])]
+    #[case::invalid_hook_no_action(["ricer", "hook"])]
+    #[case::invalid_deploy_args(["ricer", "deploy", "foo", "--non-existent"])]
+    #[case::invalid_deploy_no_repo(["ricer", "deploy"])]
+    #[case::invalid_undeploy_args(["ricer", "undeploy", "foo", "--non-existent"])]
+    #[case::invalid_owns_args(["ricer", "owns", "foo", "--non-existent"])]
+    #[case::invalid_owns_no_path(["ricer", "owns"])]
+    #[case::invalid_daemon_args(["ricer", "daemon", "--non-existent"])]
+    #[case::invalid_daemon_once_and_systemd(["ricer", "daemon", "--once", "--systemd"])]
+    #[case::invalid_sed_args(["ricer", "sed", "a", "b", "--non-existent"])]
+    #[case::invalid_sed_no_replacement(["ricer", "sed", "a"])]
+    #[case::invalid_split_no_name(["ricer", "split", "dots", "vim"])]
+    #[case::invalid_split_args(["ricer", "split", "dots", "vim", "vim", "--non-existent"])]
+    #[case::invalid_clean_no_repo(["ricer", "clean"])]
+    #[case::invalid_clean_args(["ricer", "clean", "vim", "--non-existent"])]
+    #[case::invalid_switch_no_branch(["ricer", "switch", "vim"])]
+    #[case::invalid_switch_args(["ricer", "switch", "vim", "nord", "--non-existent"])]
+    #[case::invalid_add_no_paths(["ricer", "add", "vim"])]
+    #[case::invalid_add_args(["ricer", "add", "vim", "--non-existent"])]
+    #[case::invalid_remotes_no_action(["ricer", "remotes"])]
+    #[case::invalid_remotes_rewrite_no_to(["ricer", "remotes", "rewrite", "--from", "a"])]
+    #[case::invalid_foreach_no_command(["ricer", "foreach"])]
+    #[case::invalid_foreach_zero_jobs(["ricer", "foreach", "true", "--jobs", "0"])]
+    #[case::invalid_lock_args(["ricer", "lock", "--non-existent"])]
+    #[case::invalid_unlock_args(["ricer", "unlock", "vim"])]
+    #[case::invalid_doctor_args(["ricer", "doctor", "--non-existent"])]
+    #[case::invalid_doctor_yes_without_fix(["ricer", "doctor", "--yes"])]
+    #[case::invalid_config_no_action(["ricer", "config"])]
+    #[case::invalid_config_watch_zero_interval(["ricer", "config", "watch", "--interval", "0"])]
+    #[case::invalid_info_args(["ricer", "info", "vim", "--non-existent"])]
+    #[case::invalid_info_no_repo(["ricer", "info"])]
+    #[case::invalid_completions_shell(["ricer", "completions", "tcsh"])]
+    #[case::invalid_completions_both(["ricer", "completions", "--install", "--uninstall"])]
+    #[case::invalid_completions_dry_run(["ricer", "completions", "bash", "--dry-run"])]
+    #[case::invalid_stats_args(["ricer", "stats", "--non-existent"])]
+    #[case::invalid_examples_topic(["ricer", "examples", "plugins"])]
+    #[case::invalid_ignore_no_action(["ricer", "ignore"])]
+    #[case::invalid_ignore_add_no_patterns(["ricer", "ignore", "add", "vim"])]
+    #[case::invalid_bugreport_args(["ricer", "bugreport", "--non-existent"])]
+    #[case::invalid_bugreport_no_output(["ricer", "bugreport", "--output"])]
+    #[case::invalid_move_config_dir_no_dir(["ricer", "move-config-dir"])]
+    #[case::invalid_move_config_dir_args(["ricer", "move-config-dir", "sync", "--non-existent"])]
+    #[case::invalid_history_args(["ricer", "history", "--non-existent"])]
+    #[case::invalid_history_limit(["ricer", "history", "-n", "few"])]
     #[case::invalid_shared_opts(["ricer", "--not-shared", "bootstrap"])]
+    #[case::invalid_progress_mode(["ricer", "--progress", "sometimes", "bootstrap"])]
+    #[case::invalid_context_no_value(["ricer", "--context", "theme", "bootstrap"])]
+    #[case::invalid_context_bad_key(["ricer", "--context", "the me=nord", "bootstrap"])]
     fn cli_parse_args_catch_invalid_args<I, T>(#[case] args: I)
     where
         I: IntoIterator<Item = T>,
@@ -274,4 +1144,35 @@ fn cli_parse_args_catch_invalid_args<I, T>(#[case] args: I)
         let result = Cli::parse_args(args);
         assert!(matches!(result, Err(CliError::BadParse { .. })));
     }
+
+    #[rstest]
+    #[case::flag(vec!["ricer", "--run-hook=always", "commit"], vec![
+        "ricer", "--run-hooks=always", "commit"
+    ])]
+    #[case::flag_with_separate_value(vec!["ricer", "-q", "--run-hook", "always", "commit"], vec![
+        "ricer", "-q", "--run-hooks", "always", "commit"
+    ])]
+    #[case::short_flag_value(vec!["ricer", "-r", "never", "--run-hook", "always", "push"], vec![
+        "ricer", "-r", "never", "--run-hooks", "always", "push"
+    ])]
+    #[case::git_shortcut(vec!["ricer", "vim", "log", "--run-hook"], vec![
+        "ricer", "vim", "log", "--run-hook"
+    ])]
+    #[case::after_dashdash(vec!["ricer", "--", "commit", "--run-hook"], vec![
+        "ricer", "--", "commit", "--run-hook"
+    ])]
+    fn rewrite_deprecated_translate_flags_before_git_args(
+        #[case] args: Vec<&str>,
+        #[case] expect: Vec<&str>,
+    ) {
+        let result = rewrite_deprecated(args.into_iter().map(OsString::from));
+        assert_eq!(result, expect.into_iter().map(OsString::from).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn cli_parse_args_accept_deprecated_flag() -> Result<(), CliError> {
+        let cli = Cli::parse_args(["ricer", "--run-hook=always", "commit"])?;
+        assert_eq!(cli.shared_opts.run_hook, HookAction::Always);
+        Ok(())
+    }
 }
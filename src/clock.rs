@@ -0,0 +1,96 @@
+// SPDX-FileCopyrightText: 2024 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+//! Wall clock of Ricer.
+//!
+//! Everything Ricer timestamps, like run entries, history, state snapshots,
+//! hook runs, backups, and daemon fetches, reads the current time through
+//! [`now`]. Once [`install`] is called, that time comes from the installed
+//! [`Clock`] instead of the system clock, so tools embedding Ricer can run
+//! commands at a time of their choosing.
+//!
+//! Durations are still measured through [`std::time::Instant`], because they
+//! only ever compare two points of the same run.
+
+use log::debug;
+use std::{sync::OnceLock, time::SystemTime};
+
+/// Source of the current time.
+pub trait Clock: Send + Sync {
+    /// Current time.
+    fn now(&self) -> SystemTime;
+}
+
+impl<C: Clock + ?Sized> Clock for Box<C> {
+    fn now(&self) -> SystemTime {
+        self.as_ref().now()
+    }
+}
+
+/// Clock that reads the system time.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+/// Clock that always reads the same time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FixedClock {
+    time: SystemTime,
+}
+
+impl FixedClock {
+    pub fn new(time: SystemTime) -> Self {
+        Self { time }
+    }
+}
+
+impl Clock for FixedClock {
+    fn now(&self) -> SystemTime {
+        self.time
+    }
+}
+
+fn installed() -> &'static OnceLock<Box<dyn Clock>> {
+    static CLOCK: OnceLock<Box<dyn Clock>> = OnceLock::new();
+    &CLOCK
+}
+
+/// Read the current time through `clock` from now on.
+///
+/// Only the first call takes effect. Without it, the current time is read
+/// through [`SystemClock`].
+pub fn install(clock: impl Clock + 'static) {
+    if installed().set(Box::new(clock)).is_err() {
+        debug!("Clock was already installed");
+    }
+}
+
+/// Current time of the clock given to [`install`], if any.
+pub fn now() -> SystemTime {
+    match installed().get() {
+        Some(clock) => clock.now(),
+        None => SystemClock.now(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[rstest]
+    fn fixed_clock_now_return_same_time() {
+        let time = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let clock = FixedClock::new(time);
+        assert_eq!(clock.now(), time);
+        assert_eq!(clock.now(), time);
+    }
+}
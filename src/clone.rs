@@ -0,0 +1,642 @@
+// SPDX-FileCopyrightText: 2024 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+//! Single repository cloning.
+//!
+//! Cloning a repository through `ricer clone <REMOTE> [REPO]` places its Git
+//! directory in the repository directory and registers it in the repository
+//! configuration file. Repositories that are already registered, e.g., through
+//! a repository configuration file shared across machines, keep their
+//! settings. Thus, their `on_clone` setup hook can run right after cloning
+//! without going through the full bootstrap machinery.
+//!
+//! Registered repositories with `workdir_home` set use the home directory as
+//! their working directory. Cloning one onto a machine that already has some
+//! of its dotfiles is aborted before anything in the home directory changes,
+//! unless `--adopt-existing-files` is given. Then, every conflicting file is
+//! shown as a diff, and the local copy is either kept and committed, replaced
+//! by the remote copy, or backed up into the state directory first.
+
+use crate::{
+    cancel::CleanupGuard,
+    clock,
+    config::{
+        normalize_remote_url, validate_repo_name, BootstrapSettings, ConfigFile, ConfigFileError,
+        RemoteUrlError, RepoConfig, RepoNameError, RepoSettings, TomlError,
+    },
+    context::CloneContext,
+    ignore::{self, IgnoreError},
+    init::{DEFAULT_BRANCH, DEFAULT_REMOTE},
+    locate::Locator,
+    plan::{self, DryRunEffect},
+    ui::{Prompter, UiError},
+    vcs::{unified_diff, GitRepo, GitRepoError},
+};
+
+use log::info;
+use std::{
+    fmt, fs,
+    io::Error as IoError,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::UNIX_EPOCH,
+};
+
+/// Error types for [`clone_repo`].
+#[derive(Debug, thiserror::Error)]
+pub enum CloneError {
+    #[error("Repository '{name}' is already cloned")]
+    AlreadyExists { name: String },
+
+    #[error("Cannot determine repository name from '{remote}', name it explicitly")]
+    NoName { remote: String },
+
+    #[error("Invalid repository name '{name}'")]
+    BadName { source: RepoNameError, name: String },
+
+    #[error("Invalid remote '{remote}'")]
+    BadRemote { source: RemoteUrlError, remote: String },
+
+    #[error("Failed to clone repository '{name}'")]
+    Repo { source: GitRepoError, name: String },
+
+    #[error(
+        "Cloning '{name}' would overwrite {}, retry with --adopt-existing-files",
+        display_paths(paths)
+    )]
+    Conflicts { name: String, paths: Vec<PathBuf> },
+
+    #[error("Failed to back up '{}'", path.display())]
+    Backup { source: IoError, path: PathBuf },
+
+    #[error("Failed to register repository")]
+    Config { source: ConfigFileError },
+
+    #[error("Failed to ask what to do with existing file")]
+    Ui { source: UiError },
+
+    #[error("Failed to apply ignore file")]
+    Ignore { source: IgnoreError },
+}
+
+impl From<ConfigFileError> for CloneError {
+    fn from(err: ConfigFileError) -> Self {
+        CloneError::Config { source: err }
+    }
+}
+
+impl From<IgnoreError> for CloneError {
+    fn from(err: IgnoreError) -> Self {
+        CloneError::Ignore { source: err }
+    }
+}
+
+impl From<UiError> for CloneError {
+    fn from(err: UiError) -> Self {
+        CloneError::Ui { source: err }
+    }
+}
+
+fn display_paths(paths: &[PathBuf]) -> String {
+    paths.iter().map(|path| format!("'{}'", path.display())).collect::<Vec<_>>().join(", ")
+}
+
+/// Newly cloned repository.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClonedRepo {
+    /// Settings the repository is registered with.
+    pub settings: RepoSettings,
+
+    /// Path to Git directory of repository.
+    pub gitdir: PathBuf,
+
+    /// Path to working directory of repository.
+    pub workdir: Option<PathBuf>,
+
+    /// Existing files adopted while cloning, in tree order.
+    pub adopted: Vec<Adoption>,
+}
+
+impl ClonedRepo {
+    /// Setup hook script to execute now that repository is cloned.
+    pub fn on_clone(&self) -> Option<&str> {
+        self.settings.bootstrap.as_ref()?.on_clone.as_deref()
+    }
+}
+
+/// What to do with one existing file that [`clone_repo`] would overwrite.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AdoptChoice {
+    /// Keep local copy, and commit it on top of remote copy.
+    KeepLocal,
+
+    /// Replace local copy with remote copy.
+    TakeRemote,
+
+    /// Move local copy into state directory, then take remote copy.
+    Backup,
+}
+
+impl FromStr for AdoptChoice {
+    type Err = String;
+
+    fn from_str(choice: &str) -> Result<Self, Self::Err> {
+        match choice {
+            "l" => Ok(AdoptChoice::KeepLocal),
+            "r" => Ok(AdoptChoice::TakeRemote),
+            "b" => Ok(AdoptChoice::Backup),
+            _ => Err(format!("invalid choice '{choice}', expected 'l', 'r', or 'b'")),
+        }
+    }
+}
+
+/// Existing file adopted by [`clone_repo`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Adoption {
+    /// Path of file relative to working directory.
+    pub path: PathBuf,
+
+    /// What was done with local copy of file.
+    pub choice: AdoptChoice,
+
+    /// Where local copy was backed up to.
+    pub backup: Option<PathBuf>,
+}
+
+impl fmt::Display for Adoption {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let path = self.path.display();
+        match (&self.choice, &self.backup) {
+            (AdoptChoice::KeepLocal, _) => write!(f, "{path}: kept local copy"),
+            (AdoptChoice::Backup, Some(backup)) => {
+                write!(f, "{path}: backed up local copy to '{}'", backup.display())
+            }
+            _ => write!(f, "{path}: took remote copy"),
+        }
+    }
+}
+
+/// Clone repository from [`CloneContext`].
+///
+/// Repository is named after the last component of its remote, unless named
+/// explicitly, e.g., `https://host/user/vim.git` becomes "vim". Unregistered
+/// repositories are registered with `origin` as their remote, the branch
+/// checked out by the clone as their branch, and the remote as their bootstrap
+/// clone URL. Registered repositories pinned through `pin` are checked out at
+/// their pin instead of their branch.
+///
+/// Registered repositories with `workdir_home` set are checked out into
+/// `home`. If [`CloneContext::adopt`] is set, the diff of every file of `home`
+/// that would be overwritten is shown through `prompter`, asking the user what
+/// to do with it.
+///
+/// # Invariants
+///
+/// 1. Settings of registered repositories are left untouched.
+/// 1. Repository is only registered once it has been cloned.
+/// 1. Files of `home` are never overwritten without adopting them.
+///
+/// # Errors
+///
+/// - Return [`CloneError::BadRemote`] if remote is not a valid URL, scp-like
+///   syntax, or path.
+/// - Return [`CloneError::NoName`] if no repository name can be derived from
+///   remote.
+/// - Return [`CloneError::BadName`] if repository name is invalid, see
+///   [`validate_repo_name`].
+/// - Return [`CloneError::AlreadyExists`] if Git directory of repository
+///   already exists.
+/// - Return [`CloneError::Conflicts`] if files of `home` would be overwritten
+///   without adopting them.
+/// - Return [`CloneError::Backup`] if local copy of file cannot be backed up.
+/// - Return [`CloneError::Ui`] if `prompter` fails.
+/// - Return [`CloneError::Repo`] if repository cannot be cloned.
+/// - Return [`CloneError::Config`] if repository cannot be registered.
+pub fn clone_repo(
+    ctx: &CloneContext,
+    locator: &impl Locator,
+    home: &Path,
+    prompter: &impl Prompter,
+) -> Result<ClonedRepo, CloneError> {
+    // INVARIANT: command line parser requires remote unless forge is given.
+    let remote = ctx.remote.as_deref().unwrap_or_default();
+    let remote = normalize_remote_url(remote)
+        .map_err(|err| CloneError::BadRemote { source: err, remote: remote.into() })?;
+    let remote = remote.as_str();
+    let name = match &ctx.repo {
+        Some(name) => name.clone(),
+        None => repo_name(remote).ok_or_else(|| CloneError::NoName { remote: remote.into() })?,
+    };
+    validate_repo_name(&name)
+        .map_err(|err| CloneError::BadName { source: err, name: name.clone() })?;
+
+    let mut config = ConfigFile::load(RepoConfig, locator)?;
+    let registered = match config.get(&name) {
+        Ok(settings) => Some(settings),
+        Err(ConfigFileError::Toml {
+            source: TomlError::EntryNotFound { .. } | TomlError::TableNotFound { .. },
+            ..
+        }) => None,
+        Err(err) => return Err(err.into()),
+    };
+
+    let gitdir = locator.repos_dir().join(format!("{name}.git"));
+    if gitdir.exists() {
+        return Err(CloneError::AlreadyExists { name });
+    }
+
+    info!("Clone '{name}' from '{remote}'");
+    if !plan::perform(|| DryRunEffect::Clone { url: remote.into(), path: gitdir.clone() }) {
+        let settings = match registered {
+            Some(settings) => settings,
+            None => {
+                let settings = RepoSettings::new(&name)
+                    .branch(DEFAULT_BRANCH)
+                    .remote(DEFAULT_REMOTE)
+                    .bootstrap(BootstrapSettings::new().clone(remote));
+                config.add_new(settings.clone())?;
+                config.save()?;
+                settings
+            }
+        };
+        return Ok(ClonedRepo { settings, gitdir, workdir: None, adopted: Vec::new() });
+    }
+
+    let repo_err = |err| CloneError::Repo { source: err, name: name.clone() };
+    let into = locator.repos_dir().join(&name);
+    let (repo, adopted) = match registered.as_ref().is_some_and(|settings| settings.workdir_home) {
+        false => (GitRepo::clone(remote, into).map_err(repo_err)?, Vec::new()),
+        true => {
+            // INVARIANT: aborted adoptions leave no Git directory behind.
+            let guard = CleanupGuard::new(&gitdir);
+            let repo = GitRepo::clone_fake_bare(remote, into, home).map_err(repo_err)?;
+            let adopted = adopt_files(ctx, locator, &repo, &name, prompter)?;
+            guard.keep();
+            (repo, adopted)
+        }
+    };
+    let workdir = repo.workdir().map(PathBuf::from);
+
+    let settings = match registered {
+        Some(settings) => settings,
+        None => {
+            let settings = RepoSettings::new(&name)
+                .branch(repo.head_branch().as_deref().unwrap_or(DEFAULT_BRANCH))
+                .remote(DEFAULT_REMOTE)
+                .bootstrap(BootstrapSettings::new().clone(remote));
+            config.add_new(settings.clone())?;
+            config.save()?;
+            settings
+        }
+    };
+
+    if let Some(pin) = &settings.pin {
+        info!("Check out '{name}' at pin '{pin}'");
+        repo.checkout_rev(pin)
+            .map_err(|err| CloneError::Repo { source: err, name: name.clone() })?;
+    }
+    ignore::sync_excludes(locator, &settings)?;
+
+    Ok(ClonedRepo { settings, gitdir, workdir, adopted })
+}
+
+/// Check out fake bare `repo`, adopting files that are in the way.
+fn adopt_files(
+    ctx: &CloneContext,
+    locator: &impl Locator,
+    repo: &GitRepo,
+    name: &str,
+    prompter: &impl Prompter,
+) -> Result<Vec<Adoption>, CloneError> {
+    let repo_err = |err| CloneError::Repo { source: err, name: name.into() };
+    let paths = repo.conflicting_files().map_err(repo_err)?;
+    if !paths.is_empty() && !ctx.adopt {
+        return Err(CloneError::Conflicts { name: name.into(), paths });
+    }
+
+    // INVARIANT: fake bare clones always have a working directory.
+    let workdir = repo.workdir().map(PathBuf::from).unwrap_or_default();
+    let backup_dir = locator.state_dir().join("backups").join(format!(
+        "{name}-{}",
+        clock::now().duration_since(UNIX_EPOCH).map(|time| time.as_secs()).unwrap_or_default()
+    ));
+    let mut adopted = Vec::new();
+    let mut kept = Vec::new();
+    for path in paths {
+        let local = workdir.join(&path);
+        let ours = fs::read(&local).unwrap_or_default();
+        let theirs = repo.head_file(&path).map_err(repo_err)?.unwrap_or_default();
+        let diff =
+            unified_diff(&path, &String::from_utf8_lossy(&theirs), &String::from_utf8_lossy(&ours))
+                .map_err(repo_err)?;
+
+        prompter.show_diff(&diff)?;
+        let question =
+            format!("Keep local '{}', take remote, or back up local [l,r,b]?", path.display());
+        // INVARIANT: backing up is the only choice that loses neither copy.
+        let choice = prompter.choose(&question)?.unwrap_or(AdoptChoice::Backup);
+        let backup = match choice {
+            AdoptChoice::KeepLocal => {
+                kept.push((path.clone(), ours));
+                None
+            }
+            AdoptChoice::TakeRemote => None,
+            AdoptChoice::Backup => Some(back_up(&local, &backup_dir.join(&path))?),
+        };
+        adopted.push(Adoption { path, choice, backup });
+    }
+
+    repo.force_checkout_head().map_err(repo_err)?;
+    if !kept.is_empty() {
+        for (path, data) in &kept {
+            let path = workdir.join(path);
+            fs::write(&path, data).map_err(|err| CloneError::Backup { source: err, path })?;
+        }
+        repo.stage(kept.iter().map(|(path, _)| path)).map_err(repo_err)?;
+        repo.commit("Adopt existing files").map_err(repo_err)?;
+    }
+
+    Ok(adopted)
+}
+
+/// Move file at `from` to `to`, creating parent directories of `to`.
+fn back_up(from: &Path, to: &Path) -> Result<PathBuf, CloneError> {
+    info!("Back up '{}' to '{}'", from.display(), to.display());
+    let backup_err = |err| CloneError::Backup { source: err, path: from.into() };
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent).map_err(backup_err)?;
+    }
+    // INVARIANT: state directory may live on another file system than home.
+    if fs::rename(from, to).is_err() {
+        fs::copy(from, to).and_then(|_| fs::remove_file(from)).map_err(backup_err)?;
+    }
+
+    Ok(to.into())
+}
+
+/// Derive repository name from last component of `remote`.
+fn repo_name(remote: &str) -> Option<String> {
+    let last = remote.trim_end_matches('/').rsplit(['/', ':']).next()?;
+    let name = last.strip_suffix(".git").unwrap_or(last);
+    match name.is_empty() {
+        true => None,
+        false => Some(name.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testenv::{mock_locator, parse_ctx, FileKind, FixtureHarness, ScriptedPrompter};
+
+    use anyhow::Result;
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+    use rstest::{fixture, rstest};
+    use std::fs;
+
+    #[fixture]
+    fn rice_dir() -> Result<FixtureHarness> {
+        let harness = FixtureHarness::open()?
+            .with_file("config/repos.toml", |fixture| {
+                fixture
+                    .with_data(indoc! {r#"
+                        [repos.vim]
+                        branch = "stable"
+                        remote = "upstream"
+                        workdir_home = false
+
+                        [repos.vim.bootstrap]
+                        on_clone = "vim_plug.sh"
+
+                        [repos.bash]
+                        branch = "main"
+                        remote = "origin"
+                        workdir_home = true
+                    "#})
+                    .with_kind(FileKind::Normal)
+            })
+            .with_repo("remote/vim", |repo| {
+                let repo = repo.stage("vimrc", "config for vim!")?;
+                repo.commit("initial commit")?;
+                Ok(repo)
+            })?
+            .with_repo("remote/bash", |repo| {
+                let repo = repo.stage(".bashrc", "set -o vi")?.stage(".profile", "umask 022")?;
+                repo.commit("initial commit")?;
+                Ok(repo)
+            })?
+            .with_file("home/.bashrc", |fixture| {
+                fixture.with_data("set -o emacs").with_kind(FileKind::Normal)
+            })
+            .with_file("home/.profile", |fixture| {
+                fixture.with_data("umask 022").with_kind(FileKind::Normal)
+            })
+            .setup()?;
+        Ok(harness)
+    }
+
+    fn home(rice_dir: &FixtureHarness) -> PathBuf {
+        rice_dir.as_path().join("home")
+    }
+
+    #[rstest]
+    fn clone_repo_keep_registered_settings(rice_dir: Result<FixtureHarness>) -> Result<()> {
+        let rice_dir = rice_dir?;
+        let locator = mock_locator(&rice_dir);
+        let remote = rice_dir.get_repo("remote/vim")?.as_path().display().to_string();
+        let repo = clone_repo(
+            &parse_ctx::<CloneContext>("clone", &[&remote])?,
+            &locator,
+            &home(&rice_dir),
+            &ScriptedPrompter::default(),
+        )?;
+
+        let gitdir = rice_dir.as_path().join("repos/vim.git");
+        assert_eq!(repo.on_clone(), Some("vim_plug.sh"));
+        assert_eq!(repo.settings.branch, "stable");
+        assert_eq!(repo.gitdir, gitdir);
+        assert_eq!(repo.workdir, Some(gitdir.join("")));
+        assert!(gitdir.join("vimrc").exists());
+        Ok(())
+    }
+
+    #[rstest]
+    fn clone_repo_check_out_pin(rice_dir: Result<FixtureHarness>) -> Result<()> {
+        let rice_dir = rice_dir?;
+        let locator = mock_locator(&rice_dir);
+        let fixture = rice_dir.get_repo("remote/vim")?;
+        let pin = GitRepo::open(fixture.as_path())?.head_commit()?.unwrap();
+        let config = rice_dir.as_path().join("config/repos.toml");
+        fs::write(&config, format!("[repos.vim]\nbranch = \"main\"\npin = \"{pin}\"\n"))?;
+
+        let remote = fixture.as_path().display().to_string();
+        let repo = clone_repo(
+            &parse_ctx::<CloneContext>("clone", &[&remote])?,
+            &locator,
+            &home(&rice_dir),
+            &ScriptedPrompter::default(),
+        )?;
+        assert_eq!(repo.settings.pin, Some(pin.to_string()));
+        let cloned = GitRepo::open(&repo.gitdir)?;
+        assert_eq!(cloned.head_branch(), None);
+        assert_eq!(cloned.head_commit()?, Some(pin));
+        Ok(())
+    }
+
+    #[rstest]
+    fn clone_repo_register_new_repo(rice_dir: Result<FixtureHarness>) -> Result<()> {
+        let rice_dir = rice_dir?;
+        let locator = mock_locator(&rice_dir);
+        let remote = rice_dir.get_repo("remote/vim")?.as_path().display().to_string();
+        let repo = clone_repo(
+            &parse_ctx::<CloneContext>("clone", &[&remote, "neovim"])?,
+            &locator,
+            &home(&rice_dir),
+            &ScriptedPrompter::default(),
+        )?;
+
+        let expect = RepoSettings::new("neovim")
+            .branch("main")
+            .remote("origin")
+            .bootstrap(BootstrapSettings::new().clone(&remote));
+        assert_eq!(repo.settings, expect);
+        assert_eq!(repo.on_clone(), None);
+        let config = ConfigFile::load(RepoConfig, &locator)?;
+        assert_eq!(config.get("neovim")?, expect);
+        Ok(())
+    }
+
+    #[rstest]
+    fn clone_repo_return_err_already_exists(rice_dir: Result<FixtureHarness>) -> Result<()> {
+        let rice_dir = rice_dir?;
+        let locator = mock_locator(&rice_dir);
+        let remote = rice_dir.get_repo("remote/vim")?.as_path().display().to_string();
+        let ctx = parse_ctx::<CloneContext>("clone", &[&remote])?;
+        clone_repo(&ctx, &locator, &home(&rice_dir), &ScriptedPrompter::default())?;
+
+        let result = clone_repo(&ctx, &locator, &home(&rice_dir), &ScriptedPrompter::default());
+        assert!(matches!(result.unwrap_err(), CloneError::AlreadyExists { .. }));
+        Ok(())
+    }
+
+    #[rstest]
+    fn clone_repo_return_err_conflicts(rice_dir: Result<FixtureHarness>) -> Result<()> {
+        let rice_dir = rice_dir?;
+        let remote = rice_dir.get_repo("remote/bash")?.as_path().display().to_string();
+        let ctx = parse_ctx::<CloneContext>("clone", &[&remote])?;
+        let result = clone_repo(
+            &ctx,
+            &mock_locator(&rice_dir),
+            &home(&rice_dir),
+            &ScriptedPrompter::default(),
+        );
+        match result.unwrap_err() {
+            CloneError::Conflicts { paths, .. } => assert_eq!(paths, [PathBuf::from(".bashrc")]),
+            err => panic!("unexpected error {err:?}"),
+        }
+        assert!(!rice_dir.as_path().join("repos/bash.git").exists());
+        assert_eq!(fs::read_to_string(home(&rice_dir).join(".bashrc"))?, "set -o emacs");
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::take_remote(vec!["r"], "set -o vi", false)]
+    #[case::backup(vec!["b"], "set -o vi", true)]
+    #[case::retry_invalid(vec!["x", "r"], "set -o vi", false)]
+    #[case::no_answer(vec![], "set -o vi", true)]
+    fn clone_repo_adopt_existing_files(
+        rice_dir: Result<FixtureHarness>,
+        #[case] answers: Vec<&str>,
+        #[case] expect: &str,
+        #[case] backed_up: bool,
+    ) -> Result<()> {
+        let rice_dir = rice_dir?;
+        let remote = rice_dir.get_repo("remote/bash")?.as_path().display().to_string();
+        let ctx = parse_ctx::<CloneContext>("clone", &[&remote, "--adopt-existing-files"])?;
+        let prompter = ScriptedPrompter::new(answers);
+        let repo = clone_repo(&ctx, &mock_locator(&rice_dir), &home(&rice_dir), &prompter)?;
+
+        let diffs = prompter.diffs();
+        assert_eq!(diffs.len(), 1);
+        assert!(diffs[0].contains("-set -o vi"));
+        assert!(diffs[0].contains("+set -o emacs"));
+        assert_eq!(repo.workdir, Some(home(&rice_dir).join("")));
+        assert_eq!(fs::read_to_string(home(&rice_dir).join(".bashrc"))?, expect);
+        let backup = repo.adopted[0].backup.as_ref();
+        assert_eq!(backup.is_some(), backed_up);
+        if let Some(backup) = backup {
+            assert!(backup.starts_with(rice_dir.as_path().join("state/backups")));
+            assert_eq!(fs::read_to_string(backup)?, "set -o emacs");
+        }
+        let cloned = GitRepo::open(&repo.gitdir)?;
+        assert!(!cloned.status()?.dirty);
+        Ok(())
+    }
+
+    #[rstest]
+    fn clone_repo_keep_local_copy(rice_dir: Result<FixtureHarness>) -> Result<()> {
+        let rice_dir = rice_dir?;
+        let remote = rice_dir.get_repo("remote/bash")?.as_path().display().to_string();
+        let ctx = parse_ctx::<CloneContext>("clone", &[&remote, "--adopt-existing-files"])?;
+
+        // INVARIANT: committing local copy needs a Git identity, which test
+        // environments may lack, but local copy must survive either way.
+        let prompter = ScriptedPrompter::new(["l"]);
+        let result = clone_repo(&ctx, &mock_locator(&rice_dir), &home(&rice_dir), &prompter);
+        if let Ok(repo) = result {
+            assert_eq!(repo.adopted[0].to_string(), ".bashrc: kept local copy");
+            assert!(!GitRepo::open(&repo.gitdir)?.status()?.dirty);
+        }
+        assert_eq!(fs::read_to_string(home(&rice_dir).join(".bashrc"))?, "set -o emacs");
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::keep_local("l", Ok(AdoptChoice::KeepLocal))]
+    #[case::take_remote("r", Ok(AdoptChoice::TakeRemote))]
+    #[case::backup("b", Ok(AdoptChoice::Backup))]
+    #[case::invalid("x", Err("invalid choice 'x', expected 'l', 'r', or 'b'".into()))]
+    fn adopt_choice_from_str(#[case] input: &str, #[case] expect: Result<AdoptChoice, String>) {
+        assert_eq!(input.parse::<AdoptChoice>(), expect);
+    }
+
+    #[rstest]
+    fn clone_repo_return_err_bad_remote(rice_dir: Result<FixtureHarness>) -> Result<()> {
+        let rice_dir = rice_dir?;
+        let result = clone_repo(
+            &parse_ctx::<CloneContext>("clone", &["htps://host/user/vim.git"])?,
+            &mock_locator(&rice_dir),
+            &home(&rice_dir),
+            &ScriptedPrompter::default(),
+        );
+        assert!(matches!(result.unwrap_err(), CloneError::BadRemote { .. }));
+        assert!(!rice_dir.as_path().join("repos/vim.git").exists());
+        Ok(())
+    }
+
+    #[rstest]
+    fn clone_repo_return_err_bad_name(rice_dir: Result<FixtureHarness>) -> Result<()> {
+        let rice_dir = rice_dir?;
+        let remote = rice_dir.get_repo("remote/vim")?.as_path().display().to_string();
+        let result = clone_repo(
+            &parse_ctx::<CloneContext>("clone", &[&remote, "../vim"])?,
+            &mock_locator(&rice_dir),
+            &home(&rice_dir),
+            &ScriptedPrompter::default(),
+        );
+        assert!(matches!(result.unwrap_err(), CloneError::BadName { .. }));
+        assert!(!rice_dir.as_path().join("vim.git").exists());
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::https("https://host/user/vim.git", Some("vim"))]
+    #[case::scp_like("git@host:vim.git", Some("vim"))]
+    #[case::trailing_slash("/path/to/dwm/", Some("dwm"))]
+    #[case::no_suffix("https://host/user/st", Some("st"))]
+    #[case::empty(".git", None)]
+    fn repo_name_return_last_component(#[case] remote: &str, #[case] expect: Option<&str>) {
+        assert_eq!(repo_name(remote).as_deref(), expect);
+    }
+}
@@ -0,0 +1,1081 @@
+// SPDX-FileCopyrightText: 2024 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+//! Committing changes.
+//!
+//! Through `ricer commit --message <MSG>`, staged changes of every cloned
+//! repository are committed at once. Through `--fixup amend`, staged changes
+//! are folded into the latest commit instead, and through `--fixup reword`, only
+//! the message of the latest commit is replaced.
+//!
+//! Before anything is committed, the commit message is checked against the
+//! lint rules of [`LintSettings`] through [`lint_message`], unless
+//! `--no-verify` is given. Any violated rule stops the command before a single
+//! repository is touched.
+//!
+//! Repositories that should always be pushed right after being committed to
+//! set `push_on_commit = true` in the repository configuration file, and
+//! `--push` does the same for every repository committed to. Failing to push
+//! never undoes a commit, so both the commit and the push of each repository
+//! are reported together in a [`Committed`] summary.
+//!
+//! Through `--include-glob` and `--exclude-glob`, only changed tracked files
+//! matching gitignore-style patterns of a [`PathFilter`] are staged and
+//! committed, evaluated against paths relative to the working directory of
+//! each repository. Other staged changes stay staged for a later commit.
+//!
+//! Given paths instead of repository names, e.g., `ricer commit
+//! '~/.config/nvim/**'`, only repositories tracking matching files are
+//! committed to, as looked up through [`owned_files`], and only changed
+//! files matching those paths are staged and committed.
+//!
+//! Repositories can list gitignore-style patterns of files that must never be
+//! committed, like private keys, through `exclude` in the repository
+//! configuration file. Staged files matching them stop the command before a
+//! single repository is committed to, unless `--allow-excluded` is given.
+//!
+//! Staged files that are large or binary, like a forgotten cache, are flagged
+//! by the file checks of [`FileLint`], unless `--no-verify` is given. Flagged
+//! files are warned about, or stop the command before a single repository is
+//! committed to, with `action = "block"`.
+
+use crate::{
+    config::{
+        ConfigFile, ConfigFileError, FileLint, LintAction, LintRule, LintSettings, RepoConfig,
+        RepoSettings,
+    },
+    context::{CommitContext, FixupAction},
+    hook::PlanChanges,
+    locate::Locator,
+    owns::{owned_files, OwnsError},
+    util::{human_size, RepoSelector, SelectError},
+    vcs::{GitRepo, GitRepoError, StatusScan, UntrackedFiles},
+};
+
+use directories::BaseDirs;
+use git2::Oid;
+use glob::{MatchOptions, Pattern, PatternError};
+use log::{debug, info, warn};
+use regex::{Error as RegexError, Regex};
+use std::{
+    error::Error,
+    fmt,
+    path::{Path, PathBuf},
+};
+
+/// Error types for [`commit_all`].
+#[derive(Debug, thiserror::Error)]
+pub enum CommitError {
+    #[error("No commit message given through '--message'")]
+    NoMessage,
+
+    #[error("Commit message violates lint rules:\n{}", list_violations(.violations))]
+    Lint { violations: Vec<LintViolation> },
+
+    #[error("Lint rule '{rule}' has invalid pattern")]
+    BadPattern { source: RegexError, rule: String },
+
+    #[error("Invalid glob pattern '{pattern}'")]
+    BadGlob { source: PatternError, pattern: String },
+
+    #[error("Failed to commit to repository '{name}'")]
+    Repo { source: GitRepoError, name: String },
+
+    #[error(
+        "Refusing to commit excluded files to repository '{name}':\n{}",
+        list_paths(.paths)
+    )]
+    Excluded { name: String, paths: Vec<PathBuf> },
+
+    #[error(
+        "Refusing to commit large or binary files to repository '{name}':\n{}\n{}",
+        list_flagged(.files),
+        flagged_hint(.name)
+    )]
+    Flagged { name: String, files: Vec<FlaggedFile> },
+
+    #[error("Failed to select repositories")]
+    Select { source: SelectError },
+
+    #[error("Failed to read repository configuration")]
+    Config { source: ConfigFileError },
+
+    #[error("Failed to look up repositories tracking given paths")]
+    Owns { source: OwnsError },
+
+    #[error("No repository tracks files matching {}", .paths.join(", "))]
+    NoOwner { paths: Vec<String> },
+
+    #[error("Failed to determine home directory")]
+    NoWayHome,
+}
+
+impl From<ConfigFileError> for CommitError {
+    fn from(err: ConfigFileError) -> Self {
+        CommitError::Config { source: err }
+    }
+}
+
+impl From<SelectError> for CommitError {
+    fn from(err: SelectError) -> Self {
+        CommitError::Select { source: err }
+    }
+}
+
+/// Commit message breaking a lint rule.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LintViolation {
+    /// Name of violated rule.
+    pub rule: String,
+
+    /// What the message does wrong.
+    pub reason: String,
+}
+
+impl fmt::Display for LintViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "rule '{}': {}", self.rule, self.reason)
+    }
+}
+
+/// Staged file flagged by the file checks of [`FileLint`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FlaggedFile {
+    /// Path to file, relative to working directory.
+    pub path: PathBuf,
+
+    /// Size of staged content in bytes.
+    pub size: u64,
+
+    /// Size is over the maximum of [`FileLint`].
+    pub large: bool,
+
+    /// Staged content looks binary.
+    pub binary: bool,
+}
+
+impl fmt::Display for FlaggedFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({}", self.path.display(), human_size(self.size))?;
+        if self.large {
+            write!(f, ", too large")?;
+        }
+        if self.binary {
+            write!(f, ", binary")?;
+        }
+        write!(f, ")")
+    }
+}
+
+/// Repository committed to by [`commit_all`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Committed {
+    pub name: String,
+    pub oid: Oid,
+
+    /// Outcome of pushing commit, or `None` if it was not pushed.
+    pub push: Option<PushOutcome>,
+}
+
+impl fmt::Display for Committed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let oid = self.oid.to_string();
+        write!(f, "{}: committed {}", self.name, &oid[..oid.len().min(7)])?;
+        match &self.push {
+            Some(push) => write!(f, ", {push}"),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Outcome of pushing a commit of [`commit_all`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PushOutcome {
+    /// Commit was pushed to `branch` of `remote`.
+    Pushed { remote: String, branch: String },
+
+    /// Push to `remote` failed with the given reason.
+    Failed { remote: String, reason: String },
+}
+
+impl fmt::Display for PushOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PushOutcome::Pushed { remote, branch } => write!(f, "pushed to '{remote}/{branch}'"),
+            PushOutcome::Failed { remote, reason } => {
+                write!(f, "push to '{remote}' failed: {reason}")
+            }
+        }
+    }
+}
+
+const GLOB_OPTIONS: MatchOptions = MatchOptions {
+    case_sensitive: true,
+    require_literal_separator: true,
+    require_literal_leading_dot: false,
+};
+
+/// Include and exclude patterns selecting files [`commit_all`] commits.
+///
+/// Patterns follow gitignore syntax: a pattern without a slash matches a
+/// file or directory at any depth, a pattern with a leading or middle slash
+/// is relative to the working directory, and a pattern with a trailing slash
+/// only matches directories. Files inside a matching directory match too.
+#[derive(Debug, Clone, Default)]
+pub struct PathFilter {
+    include: Vec<PathPattern>,
+    exclude: Vec<PathPattern>,
+}
+
+impl PathFilter {
+    /// Construct new filter from `include` and `exclude` patterns.
+    ///
+    /// # Errors
+    ///
+    /// - Return [`CommitError::BadGlob`] if a pattern is not a valid glob.
+    pub fn new(include: &[String], exclude: &[String]) -> Result<Self, CommitError> {
+        let parse = |globs: &[String]| {
+            globs.iter().map(|glob| PathPattern::new(glob)).collect::<Result<Vec<_>, _>>()
+        };
+        Ok(Self { include: parse(include)?, exclude: parse(exclude)? })
+    }
+
+    /// Filter has no patterns, so every file matches.
+    pub fn is_empty(&self) -> bool {
+        self.include.is_empty() && self.exclude.is_empty()
+    }
+
+    /// Check if `path` matches any include pattern, or there are none, and no
+    /// exclude pattern.
+    pub fn is_match(&self, path: impl AsRef<Path>) -> bool {
+        let path = path.as_ref();
+        (self.include.is_empty() || self.include.iter().any(|pattern| pattern.matches(path)))
+            && !self.exclude.iter().any(|pattern| pattern.matches(path))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct PathPattern {
+    pattern: Pattern,
+    dir_only: bool,
+}
+
+impl PathPattern {
+    fn new(glob: &str) -> Result<Self, CommitError> {
+        let dir_only = glob.ends_with('/');
+        let trimmed = glob.trim_end_matches('/');
+        let pattern = match trimmed.contains('/') {
+            true => trimmed.trim_start_matches('/').to_string(),
+            false => format!("**/{trimmed}"),
+        };
+        let pattern = Pattern::new(&pattern)
+            .map_err(|err| CommitError::BadGlob { source: err, pattern: glob.into() })?;
+        Ok(Self { pattern, dir_only })
+    }
+
+    fn matches(&self, path: &Path) -> bool {
+        // INVARIANT: only ancestors of a file can be directories.
+        let skip = usize::from(self.dir_only);
+        path.ancestors()
+            .skip(skip)
+            .filter(|ancestor| !ancestor.as_os_str().is_empty())
+            .any(|ancestor| self.pattern.matches_path_with(ancestor, GLOB_OPTIONS))
+    }
+}
+
+/// Changes of a repository that [`commit_all`] commits.
+enum Staged {
+    /// Everything staged in index.
+    Index,
+
+    /// Tree of HEAD with only staged changes matching [`PathFilter`].
+    Tree(Oid),
+
+    /// Nothing staged.
+    Nothing,
+}
+
+fn stage_matching(repo: &GitRepo, selects: impl Fn(&Path) -> bool) -> Result<Staged, GitRepoError> {
+    let scan = StatusScan { untracked: Some(UntrackedFiles::No), ..Default::default() };
+    let paths =
+        repo.changed_files(scan)?.into_iter().filter(|path| selects(path)).collect::<Vec<_>>();
+    if paths.is_empty() {
+        return Ok(Staged::Nothing);
+    }
+
+    repo.stage_changes(&paths)?;
+    Ok(repo.staged_tree(&paths)?.map_or(Staged::Nothing, Staged::Tree))
+}
+
+/// List staged files of `repo` that `staged` changes would actually commit.
+fn committed_files(
+    repo: &GitRepo,
+    staged: &Staged,
+    selects: impl Fn(&Path) -> bool,
+) -> Result<Vec<PathBuf>, GitRepoError> {
+    let paths = match staged {
+        Staged::Nothing => Vec::new(),
+        Staged::Index => repo.staged_files()?,
+        Staged::Tree(_) => repo.staged_files()?.into_iter().filter(|path| selects(path)).collect(),
+    };
+    Ok(paths)
+}
+
+/// Find staged files of `repo` matching the exclude list of `settings`.
+///
+/// Only files that `staged` changes would actually commit are checked.
+fn excluded_files(
+    repo: &GitRepo,
+    settings: &RepoSettings,
+    staged: &Staged,
+    selects: impl Fn(&Path) -> bool,
+) -> Result<Vec<PathBuf>, CommitError> {
+    if settings.exclude.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let exclude = PathFilter::new(&settings.exclude, &[])?;
+    let repo_err = |err| CommitError::Repo { source: err, name: settings.name.clone() };
+    let paths = committed_files(repo, staged, selects).map_err(repo_err)?;
+    Ok(paths.into_iter().filter(|path| exclude.is_match(path)).collect())
+}
+
+/// Find staged files of `repo` that are too large or binary for `lint`.
+///
+/// Only files that `staged` changes would actually commit are checked, and
+/// files matching the allow list of `lint` never are.
+fn flagged_files(
+    repo: &GitRepo,
+    settings: &RepoSettings,
+    staged: &Staged,
+    selects: impl Fn(&Path) -> bool,
+    lint: &FileLint,
+) -> Result<Vec<FlaggedFile>, CommitError> {
+    let allow = PathFilter::new(&lint.allow, &[])?;
+    let repo_err = |err| CommitError::Repo { source: err, name: settings.name.clone() };
+    let paths = committed_files(repo, staged, selects)
+        .map_err(repo_err)?
+        .into_iter()
+        .filter(|path| lint.allow.is_empty() || !allow.is_match(path))
+        .collect::<Vec<_>>();
+
+    let flagged = repo
+        .staged_blobs(&paths)
+        .map_err(repo_err)?
+        .into_iter()
+        .map(|blob| FlaggedFile {
+            large: blob.size > lint.max_size,
+            binary: lint.binary && blob.binary,
+            path: blob.path,
+            size: blob.size,
+        })
+        .filter(|file| file.large || file.binary)
+        .collect();
+    Ok(flagged)
+}
+
+fn list_violations(violations: &[LintViolation]) -> String {
+    violations.iter().map(|violation| format!("  - {violation}")).collect::<Vec<_>>().join("\n")
+}
+
+fn list_flagged(files: &[FlaggedFile]) -> String {
+    files.iter().map(|file| format!("  - {file}")).collect::<Vec<_>>().join("\n")
+}
+
+fn flagged_hint(name: &str) -> String {
+    format!(
+        "Unstage them and keep them out through 'ricer ignore add {name} <PATH>', or allow \
+         them through 'allow' of '[lint.files]'"
+    )
+}
+
+fn list_paths(paths: &[PathBuf]) -> String {
+    paths.iter().map(|path| format!("  - {}", path.display())).collect::<Vec<_>>().join("\n")
+}
+
+/// Check commit `message` against every rule of `lint`.
+///
+/// Patterns are matched against the whole message, and maximum lengths are
+/// counted in characters of the summary line.
+///
+/// # Errors
+///
+/// - Return [`CommitError::Lint`] listing every violated rule.
+/// - Return [`CommitError::BadPattern`] if pattern of rule is not a valid
+///   regular expression.
+pub fn lint_message(message: &str, lint: &LintSettings) -> Result<(), CommitError> {
+    let mut violations = Vec::new();
+    for rule in &lint.commit {
+        violations.extend(check_rule(message, rule)?);
+    }
+
+    match violations.is_empty() {
+        true => Ok(()),
+        false => Err(CommitError::Lint { violations }),
+    }
+}
+
+fn check_rule(message: &str, rule: &LintRule) -> Result<Vec<LintViolation>, CommitError> {
+    let violation = |reason| LintViolation { rule: rule.name.clone(), reason };
+    let mut violations = Vec::new();
+    if let Some(pattern) = &rule.pattern {
+        let regex = Regex::new(pattern)
+            .map_err(|err| CommitError::BadPattern { source: err, rule: rule.name.clone() })?;
+        if !regex.is_match(message) {
+            violations.push(violation(format!("message does not match '{pattern}'")));
+        }
+    }
+
+    if let Some(max) = rule.max_length {
+        let len = message.lines().next().unwrap_or_default().chars().count();
+        if len > max {
+            violations.push(violation(format!("summary line is {len} characters, max is {max}")));
+        }
+    }
+
+    Ok(violations)
+}
+
+/// Commit to every cloned repository from [`CommitContext`].
+///
+/// Repositories without staged changes are left alone, unless the message of
+/// their latest commit is reworded. Given path filters, changed tracked files
+/// matching them are staged, and only those are committed. Once every
+/// repository is committed to, those with `push_on_commit` set, or all of them
+/// through `--push`, are pushed to their default remote. Will return every
+/// repository that was committed to, along with its new commit and the
+/// outcome of pushing it.
+///
+/// # Invariants
+///
+/// 1. Commit message is linted, path filters are parsed, and staged files are
+///    checked against exclude lists and file checks, before any repository
+///    is committed to.
+/// 1. Repositories that are read-only, not cloned, or have no commit to fix
+///    up, are skipped.
+/// 1. Nothing is pushed until every repository has been committed to, and a
+///    failed push does not stop the others.
+///
+/// # Errors
+///
+/// - Return [`CommitError::NoMessage`] if no message is given, unless staged
+///   changes are amended.
+/// - Return [`CommitError::Lint`] if commit message violates lint rules.
+/// - Return [`CommitError::BadPattern`] if a lint rule is invalid.
+/// - Return [`CommitError::BadGlob`] if a path filter is invalid.
+/// - Return [`CommitError::Repo`] if a repository cannot be committed to.
+/// - Return [`CommitError::Excluded`] if staged files match the exclude list
+///   of their repository, unless `--allow-excluded` is given.
+/// - Return [`CommitError::Flagged`] if staged files are too large or binary,
+///   and file checks block, unless `--no-verify` is given.
+/// - Return [`CommitError::Select`] if repository patterns select no
+///   registered repository.
+/// - Return [`CommitError::Config`] if repository configuration cannot be
+///   read.
+/// - Return [`CommitError::Owns`] if repositories tracking given paths
+///   cannot be looked up.
+/// - Return [`CommitError::NoOwner`] if no repository tracks files matching
+///   given paths.
+/// - Return [`CommitError::NoWayHome`] if paths are given, but the home
+///   directory cannot be determined.
+pub fn commit_all(
+    ctx: &CommitContext,
+    locator: &impl Locator,
+    lint: &LintSettings,
+) -> Result<Vec<Committed>, CommitError> {
+    commit_all_with(ctx, locator, lint, &PlanChanges::default())
+}
+
+/// Commit to every cloned repository like [`commit_all`], applying plan
+/// `changes` requested by pre hooks.
+///
+/// Skipped repositories are left alone, and trailers are added to the commit
+/// message after it is linted. Amends that keep the message of the latest
+/// commit get no trailers.
+///
+/// # Errors
+///
+/// Same as [`commit_all`].
+pub fn commit_all_with(
+    ctx: &CommitContext,
+    locator: &impl Locator,
+    lint: &LintSettings,
+    changes: &PlanChanges,
+) -> Result<Vec<Committed>, CommitError> {
+    if ctx.message.is_none() && ctx.fixup != Some(FixupAction::Amend) {
+        return Err(CommitError::NoMessage);
+    }
+
+    match (ctx.message.as_deref(), ctx.no_verify) {
+        (Some(message), false) => lint_message(message, lint)?,
+        (Some(_), true) => debug!("Skip lint rules of commit message"),
+        (None, _) => (),
+    }
+    let filter = PathFilter::new(&ctx.include_globs, &ctx.exclude_globs)?;
+    let message = ctx.message.as_deref().map(|message| changes.add_trailers(message));
+    if message.is_none() && !changes.trailers.is_empty() {
+        warn!("Skip trailers requested by hooks, because commit message is kept");
+    }
+    let message = message.as_deref();
+
+    let owned = match ctx.paths.is_empty() {
+        true => None,
+        false => {
+            let dirs = BaseDirs::new().ok_or(CommitError::NoWayHome)?;
+            let owned = owned_files(&ctx.paths, locator, dirs.home_dir())
+                .map_err(|err| CommitError::Owns { source: err })?;
+            if owned.is_empty() {
+                return Err(CommitError::NoOwner { paths: ctx.paths.clone() });
+            }
+            Some(owned)
+        }
+    };
+
+    let config = ConfigFile::load(RepoConfig, locator)?;
+    let selector = RepoSelector::new(&ctx.repos).strict(ctx.strict).groups(config.groups()?);
+    let mut staged_repos = Vec::new();
+    for settings in selector.select(config.entries()?)? {
+        if settings.readonly {
+            debug!("Skip '{}', because it is read-only", settings.name);
+            continue;
+        }
+
+        if changes.is_skipped(&settings.name) {
+            info!("Skip '{}', because a hook asked to", settings.name);
+            continue;
+        }
+
+        let owned_files = match &owned {
+            Some(owned) => match owned.iter().find(|(name, _)| *name == settings.name) {
+                Some((_, files)) => Some(files),
+                None => {
+                    debug!("Skip '{}', because it tracks no given path", settings.name);
+                    continue;
+                }
+            },
+            None => None,
+        };
+
+        let gitdir = settings.gitdir(locator.repos_dir());
+        if !gitdir.exists() {
+            debug!("Skip '{}', because it is not cloned", settings.name);
+            continue;
+        }
+
+        let repo_err = |err| CommitError::Repo { source: err, name: settings.name.clone() };
+        let repo = GitRepo::open(&gitdir).map_err(repo_err)?;
+        let has_head = repo.head_commit().map_err(repo_err)?.is_some();
+        let selects = |path: &Path| {
+            filter.is_match(path)
+                && owned_files.map_or(true, |files| files.iter().any(|f| f == path))
+        };
+        let staged = match (ctx.fixup, filter.is_empty() && owned_files.is_none()) {
+            (Some(FixupAction::Reword), _) => Staged::Nothing,
+            (_, true) if repo.has_staged().map_err(repo_err)? => Staged::Index,
+            (_, true) => Staged::Nothing,
+            (_, false) => stage_matching(&repo, selects).map_err(repo_err)?,
+        };
+
+        let excluded = excluded_files(&repo, &settings, &staged, selects)?;
+        match (excluded.is_empty(), ctx.allow_excluded) {
+            (true, _) => (),
+            (false, true) => warn!("Commit excluded files to '{}' anyway", settings.name),
+            (false, false) => {
+                return Err(CommitError::Excluded { name: settings.name, paths: excluded })
+            }
+        }
+
+        let flagged = match ctx.no_verify {
+            true => Vec::new(),
+            false => flagged_files(&repo, &settings, &staged, selects, &lint.files)?,
+        };
+        match (flagged.is_empty(), lint.files.action) {
+            (true, _) => (),
+            (false, LintAction::Warn) => warn!(
+                "Commit large or binary files to '{}':\n{}\n{}",
+                settings.name,
+                list_flagged(&flagged),
+                flagged_hint(&settings.name)
+            ),
+            (false, LintAction::Block) => {
+                return Err(CommitError::Flagged { name: settings.name, files: flagged })
+            }
+        }
+        staged_repos.push((settings, repo, has_head, staged));
+    }
+
+    let mut committed = Vec::new();
+    for (settings, repo, has_head, staged) in staged_repos {
+        let repo_err = |err| CommitError::Repo { source: err, name: settings.name.clone() };
+        let oid = match (ctx.fixup, message, staged) {
+            (Some(FixupAction::Reword), Some(message), _) if has_head => repo.reword(message),
+            (Some(FixupAction::Amend), _, Staged::Index) if has_head => repo.amend(message),
+            (Some(FixupAction::Amend), _, Staged::Tree(tree)) if has_head => {
+                repo.amend_tree(message, tree)
+            }
+            (None, Some(message), Staged::Index) => repo.commit(message),
+            (None, Some(message), Staged::Tree(tree)) => repo.commit_tree(message, tree),
+            _ => {
+                debug!("Skip '{}', because there is nothing to commit", settings.name);
+                continue;
+            }
+        }
+        .map_err(repo_err)?;
+
+        info!("Commit {oid} to '{}'", settings.name);
+        committed.push((settings, repo, oid));
+    }
+
+    let summary = committed
+        .into_iter()
+        .map(|(settings, repo, oid)| {
+            let push = (ctx.push || settings.push_on_commit).then(|| push_commit(&repo, &settings));
+            Committed { name: settings.name, oid, push }
+        })
+        .collect();
+
+    Ok(summary)
+}
+
+fn push_commit(repo: &GitRepo, settings: &RepoSettings) -> PushOutcome {
+    let remote = settings.remote.clone();
+    let branch = repo.head_branch().unwrap_or_else(|| settings.branch.clone());
+    info!("Push '{}' to '{remote}/{branch}'", settings.name);
+    match repo.push(&remote, &branch) {
+        Ok(()) => PushOutcome::Pushed { remote, branch },
+        Err(err) => {
+            let reason = match err.source() {
+                Some(source) => format!("{err}: {source}"),
+                None => err.to_string(),
+            };
+            warn!("Failed to push '{}': {reason}", settings.name);
+            PushOutcome::Failed { remote, reason }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testenv::{mock_locator, parse_ctx, FileKind, FixtureHarness};
+
+    use anyhow::Result;
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+    use rstest::{fixture, rstest};
+    use std::fs;
+
+    #[fixture]
+    fn rice_dir() -> Result<FixtureHarness> {
+        let harness = FixtureHarness::open()?
+            .with_file("config/repos.toml", |fixture| {
+                fixture
+                    .with_data(indoc! {r#"
+                        [repos.dwm]
+                        branch = "main"
+                        remote = "origin"
+                        workdir_home = false
+
+                        [repos.vim]
+                        branch = "main"
+                        remote = "origin"
+                        workdir_home = false
+
+                        [repos.st]
+                        branch = "main"
+                        remote = "origin"
+                        workdir_home = false
+                    "#})
+                    .with_kind(FileKind::Normal)
+            })
+            .with_repo("repos/dwm", |repo| {
+                let repo = repo.stage("config.h", "#define MODKEY Mod1Mask\n")?;
+                repo.commit("dwm: initial commit")?;
+                Ok(repo)
+            })?
+            .with_repo("repos/vim", |repo| {
+                let repo = repo.stage("vimrc", "set number\n")?;
+                repo.commit("vim: initial commit")?;
+                Ok(repo)
+            })?
+            .setup()?;
+        let dwm = harness.as_path().join("repos/dwm.git");
+        fs::write(dwm.join("config.h"), "#define MODKEY Mod4Mask\n")?;
+        GitRepo::open(&dwm)?.stage(["config.h"])?;
+        Ok(harness)
+    }
+
+    fn lint() -> LintSettings {
+        LintSettings::new()
+            .commit_rule(LintRule::new("scope").pattern("^[a-z0-9_-]+: "))
+            .commit_rule(LintRule::new("length").max_length(20))
+    }
+
+    fn last_message(rice_dir: &FixtureHarness, name: &str) -> Result<String> {
+        let repo = GitRepo::open(rice_dir.as_path().join(format!("repos/{name}.git")))?;
+        let oid = repo.head_commit()?.unwrap();
+        let message = repo.find_commit(oid)?.message().unwrap_or_default().to_string();
+        Ok(message)
+    }
+
+    #[rstest]
+    #[case::commit(&["-m", "dwm: use super key"], vec!["dwm"], Some("dwm: use super key"), None)]
+    #[case::amend(&["--fixup", "amend"], vec!["dwm"], None, None)]
+    #[case::reword(
+        &["--fixup", "reword", "-m", "rice: reword"],
+        vec!["dwm", "vim"],
+        Some("rice: reword"),
+        Some("rice: reword")
+    )]
+    #[case::no_verify(&["-n", "-m", "Use super key"], vec!["dwm"], Some("Use super key"), None)]
+    fn commit_all_commit_staged_changes(
+        rice_dir: Result<FixtureHarness>,
+        #[case] args: &[&str],
+        #[case] expect: Vec<&str>,
+        #[case] dwm_message: Option<&str>,
+        #[case] vim_message: Option<&str>,
+    ) -> Result<()> {
+        let rice_dir = rice_dir?;
+        let (dwm_before, vim_before) =
+            (last_message(&rice_dir, "dwm")?, last_message(&rice_dir, "vim")?);
+        let committed = commit_all(
+            &parse_ctx::<CommitContext>("commit", args)?,
+            &mock_locator(&rice_dir),
+            &lint(),
+        )?;
+
+        let names = committed.iter().map(|committed| committed.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, expect);
+        assert!(committed.iter().all(|committed| committed.push.is_none()));
+        assert_eq!(last_message(&rice_dir, "dwm")?, dwm_message.map_or(dwm_before, String::from));
+        assert_eq!(last_message(&rice_dir, "vim")?, vim_message.map_or(vim_before, String::from));
+        let dwm = GitRepo::open(rice_dir.as_path().join("repos/dwm.git"))?;
+        assert_eq!(dwm.has_staged()?, args[1] == "reword");
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::include(&["--include-glob", "vimrc"])]
+    #[case::exclude(&["--exclude-glob", "*.h"])]
+    fn commit_all_commit_only_matching_files(
+        rice_dir: Result<FixtureHarness>,
+        #[case] filter: &[&str],
+    ) -> Result<()> {
+        let rice_dir = rice_dir?;
+        fs::write(rice_dir.as_path().join("repos/vim.git/vimrc"), "set relativenumber\n")?;
+        let args = ["-m", "vim: relative lines"].iter().chain(filter).copied();
+        let ctx = parse_ctx::<CommitContext>("commit", &args.collect::<Vec<_>>())?;
+        let committed = commit_all(&ctx, &mock_locator(&rice_dir), &lint())?;
+
+        let names = committed.iter().map(|committed| committed.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, vec!["vim"]);
+        assert_eq!(last_message(&rice_dir, "vim")?, "vim: relative lines");
+        let vim = GitRepo::open(rice_dir.as_path().join("repos/vim.git"))?;
+        assert!(!vim.has_staged()?);
+        let dwm = GitRepo::open(rice_dir.as_path().join("repos/dwm.git"))?;
+        assert!(dwm.has_staged()?);
+        Ok(())
+    }
+
+    #[rstest]
+    fn commit_all_skip_readonly_repos(rice_dir: Result<FixtureHarness>) -> Result<()> {
+        let rice_dir = rice_dir?;
+        let config = rice_dir.as_path().join("config/repos.toml");
+        let data = fs::read_to_string(&config)?.replacen(
+            "workdir_home = false",
+            "workdir_home = false\nreadonly = true",
+            1,
+        );
+        fs::write(&config, data)?;
+
+        let ctx = parse_ctx::<CommitContext>("commit", &["-m", "dwm: use super key"])?;
+        let committed = commit_all(&ctx, &mock_locator(&rice_dir), &lint())?;
+        assert!(committed.is_empty());
+        let dwm = GitRepo::open(rice_dir.as_path().join("repos/dwm.git"))?;
+        assert!(dwm.has_staged()?);
+        Ok(())
+    }
+
+    #[rstest]
+    fn commit_all_with_apply_plan_changes(rice_dir: Result<FixtureHarness>) -> Result<()> {
+        let rice_dir = rice_dir?;
+        let ctx = parse_ctx::<CommitContext>("commit", &["-m", "dwm: use super key"])?;
+        let trailers = PlanChanges {
+            skip_repos: Vec::new(),
+            trailers: vec!["Reviewed-by: Policy Bot".into()],
+        };
+        commit_all_with(&ctx, &mock_locator(&rice_dir), &lint(), &trailers)?;
+        let expect = "dwm: use super key\n\nReviewed-by: Policy Bot";
+        assert_eq!(last_message(&rice_dir, "dwm")?, expect);
+
+        let ctx =
+            parse_ctx::<CommitContext>("commit", &["--fixup", "reword", "-m", "rice: reword"])?;
+        let skip = PlanChanges { skip_repos: vec!["vim".into()], trailers: Vec::new() };
+        let committed = commit_all_with(&ctx, &mock_locator(&rice_dir), &lint(), &skip)?;
+        let names = committed.iter().map(|repo| repo.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, vec!["dwm"]);
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::refuse(&[], None)]
+    #[case::allow(&["--allow-excluded"], Some(vec!["dwm", "vim"]))]
+    #[case::filtered_out(&["--exclude-glob", "vimrc"], Some(vec!["dwm"]))]
+    fn commit_all_check_exclude_list(
+        rice_dir: Result<FixtureHarness>,
+        #[case] args: &[&str],
+        #[case] expect: Option<Vec<&str>>,
+    ) -> Result<()> {
+        let rice_dir = rice_dir?;
+        let config = rice_dir.as_path().join("config/repos.toml");
+        let data = fs::read_to_string(&config)?
+            .replace("[repos.st]", "exclude = [\"vimrc\"]\n\n[repos.st]");
+        fs::write(&config, data)?;
+        let vim = GitRepo::open(rice_dir.as_path().join("repos/vim.git"))?;
+        fs::write(rice_dir.as_path().join("repos/vim.git/vimrc"), "set relativenumber\n")?;
+        vim.stage(["vimrc"])?;
+
+        let args = ["-m", "rice: update"].iter().chain(args).copied();
+        let ctx = parse_ctx::<CommitContext>("commit", &args.collect::<Vec<_>>())?;
+        let dwm_before = last_message(&rice_dir, "dwm")?;
+        match (commit_all(&ctx, &mock_locator(&rice_dir), &lint()), expect) {
+            (Ok(committed), Some(expect)) => {
+                let names = committed.iter().map(|repo| repo.name.as_str()).collect::<Vec<_>>();
+                assert_eq!(names, expect);
+            }
+            (Err(CommitError::Excluded { name, paths }), None) => {
+                assert_eq!(name, "vim");
+                assert_eq!(paths, vec![PathBuf::from("vimrc")]);
+                assert_eq!(last_message(&rice_dir, "dwm")?, dwm_before);
+            }
+            (result, _) => panic!("unexpected result {result:?}"),
+        }
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::warn(&[], FileLint::new(), Some(vec!["dwm", "vim"]))]
+    #[case::block(&[], FileLint::new().action(LintAction::Block), None)]
+    #[case::allow(
+        &[],
+        FileLint::new().action(LintAction::Block).allow("*.bin").allow("cache/"),
+        Some(vec!["dwm", "vim"])
+    )]
+    #[case::no_verify(&["-n"], FileLint::new().action(LintAction::Block), Some(vec!["dwm", "vim"]))]
+    fn commit_all_check_staged_files(
+        rice_dir: Result<FixtureHarness>,
+        #[case] args: &[&str],
+        #[case] files: FileLint,
+        #[case] expect: Option<Vec<&str>>,
+    ) -> Result<()> {
+        let rice_dir = rice_dir?;
+        let vim = rice_dir.as_path().join("repos/vim.git");
+        fs::create_dir_all(vim.join("cache"))?;
+        fs::write(vim.join("cache/big.txt"), "x".repeat(64))?;
+        fs::write(vim.join("logo.bin"), [0u8, 159, 146, 150])?;
+        GitRepo::open(&vim)?.stage(["cache/big.txt", "logo.bin"])?;
+
+        let args = ["-m", "rice: update"].iter().chain(args).copied();
+        let ctx = parse_ctx::<CommitContext>("commit", &args.collect::<Vec<_>>())?;
+        let lint = lint().files(files.max_size(32));
+        match (commit_all(&ctx, &mock_locator(&rice_dir), &lint), expect) {
+            (Ok(committed), Some(expect)) => {
+                let names = committed.iter().map(|repo| repo.name.as_str()).collect::<Vec<_>>();
+                assert_eq!(names, expect);
+            }
+            (Err(CommitError::Flagged { name, files }), None) => {
+                assert_eq!(name, "vim");
+                let files = files.iter().map(ToString::to_string).collect::<Vec<_>>();
+                assert_eq!(files, ["cache/big.txt (64 B, too large)", "logo.bin (4 B, binary)"]);
+                assert!(GitRepo::open(rice_dir.as_path().join("repos/dwm.git"))?.has_staged()?);
+            }
+            (result, _) => panic!("unexpected result {result:?}"),
+        }
+        Ok(())
+    }
+
+    #[rstest]
+    fn commit_all_commit_owners_of_paths(rice_dir: Result<FixtureHarness>) -> Result<()> {
+        let rice_dir = rice_dir?;
+        let root = rice_dir.as_path();
+        let mut locator = mock_locator(&rice_dir);
+        locator.expect_state_dir().return_const(root.join("state"));
+        fs::write(root.join("repos/vim.git/vimrc"), "set relativenumber\n")?;
+
+        let path = root.join("repos/vim.git/*rc").to_string_lossy().into_owned();
+        let ctx = parse_ctx::<CommitContext>("commit", &["-m", "vim: relative lines", &path])?;
+        assert_eq!(ctx.paths, vec![path]);
+        let committed = commit_all(&ctx, &locator, &lint())?;
+        let names = committed.iter().map(|committed| committed.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, vec!["vim"]);
+        assert_eq!(last_message(&rice_dir, "vim")?, "vim: relative lines");
+        let dwm = GitRepo::open(root.join("repos/dwm.git"))?;
+        assert!(dwm.has_staged()?);
+
+        let path = root.join("nothing/here").to_string_lossy().into_owned();
+        let ctx = parse_ctx::<CommitContext>("commit", &["-m", "vim: relative lines", &path])?;
+        let result = commit_all(&ctx, &locator, &lint());
+        assert!(matches!(result.unwrap_err(), CommitError::NoOwner { .. }));
+        Ok(())
+    }
+
+    #[rstest]
+    fn commit_all_return_err_bad_glob(rice_dir: Result<FixtureHarness>) -> Result<()> {
+        let rice_dir = rice_dir?;
+        let ctx = parse_ctx::<CommitContext>(
+            "commit",
+            &["-m", "dwm: use super key", "--include-glob", "[*"],
+        )?;
+        let result = commit_all(&ctx, &mock_locator(&rice_dir), &lint());
+        assert!(matches!(result.unwrap_err(), CommitError::BadGlob { .. }));
+        let dwm = GitRepo::open(rice_dir.as_path().join("repos/dwm.git"))?;
+        assert!(dwm.has_staged()?);
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::basename(&["*.sh"], &[], ".config/sh/aliases.sh", true)]
+    #[case::basename_no_match(&["*.sh"], &[], ".bashrc", false)]
+    #[case::anchored(&["/.bashrc"], &[], ".bashrc", true)]
+    #[case::anchored_nested(&["/.bashrc"], &[], "old/.bashrc", false)]
+    #[case::middle_slash(&[".config/sh"], &[], ".config/sh/env", true)]
+    #[case::dir_only(&["sh/"], &[], ".config/sh/env", true)]
+    #[case::dir_only_file(&["sh/"], &[], ".config/sh", false)]
+    #[case::star_within_dir(&[".config/*"], &[], ".config/sh/env", true)]
+    #[case::double_star(&[".config/**/env"], &[], ".config/sh/env", true)]
+    #[case::excluded(&["*.sh"], &["secret.sh"], ".config/sh/secret.sh", false)]
+    #[case::only_exclude(&[], &["*.h"], "dwm.c", true)]
+    fn path_filter_match_gitignore_style(
+        #[case] include: &[&str],
+        #[case] exclude: &[&str],
+        #[case] path: &str,
+        #[case] expect: bool,
+    ) -> Result<()> {
+        let owned = |globs: &[&str]| globs.iter().map(ToString::to_string).collect::<Vec<_>>();
+        let filter = PathFilter::new(&owned(include), &owned(exclude))?;
+        assert_eq!(filter.is_match(path), expect);
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::flag(&["-m", "dwm: use super key", "--push"], false)]
+    #[case::setting(&["-m", "dwm: use super key"], true)]
+    fn commit_all_push_after_commit(
+        rice_dir: Result<FixtureHarness>,
+        #[case] args: &[&str],
+        #[case] push_on_commit: bool,
+    ) -> Result<()> {
+        let rice_dir = rice_dir?;
+        let upstream = rice_dir.as_path().join("remote/dwm.git");
+        let upstream = git2::Repository::init_bare(upstream)?;
+        let dwm = rice_dir.as_path().join("repos/dwm.git");
+        git2::Repository::open(&dwm)?.remote("origin", &upstream.path().to_string_lossy())?;
+        if push_on_commit {
+            let config = rice_dir.as_path().join("config/repos.toml");
+            let data = fs::read_to_string(&config)?.replacen(
+                "workdir_home = false",
+                "workdir_home = false\npush_on_commit = true",
+                1,
+            );
+            fs::write(&config, data)?;
+        }
+
+        let committed = commit_all(
+            &parse_ctx::<CommitContext>("commit", args)?,
+            &mock_locator(&rice_dir),
+            &lint(),
+        )?;
+        let expect = PushOutcome::Pushed { remote: "origin".into(), branch: "main".into() };
+        assert_eq!(committed[0].push, Some(expect));
+        let pushed = upstream.find_reference("refs/heads/main")?.target();
+        assert_eq!(pushed, Some(committed[0].oid));
+        Ok(())
+    }
+
+    #[rstest]
+    fn commit_all_keep_commit_of_failed_push(rice_dir: Result<FixtureHarness>) -> Result<()> {
+        let rice_dir = rice_dir?;
+        let ctx = parse_ctx::<CommitContext>("commit", &["-m", "dwm: use super key", "--push"])?;
+        let committed = commit_all(&ctx, &mock_locator(&rice_dir), &lint())?;
+
+        assert_eq!(committed.len(), 1);
+        assert!(matches!(committed[0].push, Some(PushOutcome::Failed { .. })));
+        assert_eq!(last_message(&rice_dir, "dwm")?, "dwm: use super key");
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::not_pushed(None, "dwm: committed 0123456")]
+    #[case::pushed(
+        Some(PushOutcome::Pushed { remote: "origin".into(), branch: "main".into() }),
+        "dwm: committed 0123456, pushed to 'origin/main'"
+    )]
+    #[case::push_failed(
+        Some(PushOutcome::Failed { remote: "origin".into(), reason: "offline".into() }),
+        "dwm: committed 0123456, push to 'origin' failed: offline"
+    )]
+    fn committed_display(#[case] push: Option<PushOutcome>, #[case] expect: &str) -> Result<()> {
+        let oid = Oid::from_str("0123456789abcdef0123456789abcdef01234567")?;
+        let committed = Committed { name: "dwm".into(), oid, push };
+        assert_eq!(committed.to_string(), expect);
+        Ok(())
+    }
+
+    #[rstest]
+    fn commit_all_return_err_lint(rice_dir: Result<FixtureHarness>) -> Result<()> {
+        let rice_dir = rice_dir?;
+        let ctx = parse_ctx::<CommitContext>("commit", &["-m", "Use super key everywhere"])?;
+        let err = commit_all(&ctx, &mock_locator(&rice_dir), &lint()).unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            indoc! {"
+                Commit message violates lint rules:
+                  - rule 'scope': message does not match '^[a-z0-9_-]+: '
+                  - rule 'length': summary line is 24 characters, max is 20"
+            }
+        );
+        let dwm = GitRepo::open(rice_dir.as_path().join("repos/dwm.git"))?;
+        assert!(dwm.has_staged()?);
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::no_message(&[])]
+    #[case::reword_no_message(&["--fixup", "reword"])]
+    fn commit_all_return_err_no_message(
+        rice_dir: Result<FixtureHarness>,
+        #[case] args: &[&str],
+    ) -> Result<()> {
+        let rice_dir = rice_dir?;
+        let result = commit_all(
+            &parse_ctx::<CommitContext>("commit", args)?,
+            &mock_locator(&rice_dir),
+            &lint(),
+        );
+        assert!(matches!(result.unwrap_err(), CommitError::NoMessage));
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::valid("vim: add plugins\n\nLong description of changes.", vec![])]
+    #[case::bad_scope("Add plugins", vec!["scope"])]
+    #[case::too_long("vim: add plugins and colors", vec!["length"])]
+    fn lint_message_check_every_rule(#[case] message: &str, #[case] expect: Vec<&str>) {
+        let violated = match lint_message(message, &lint()) {
+            Ok(()) => Vec::new(),
+            Err(CommitError::Lint { violations }) => {
+                violations.into_iter().map(|v| v.rule).collect()
+            }
+            Err(err) => panic!("unexpected error: {err}"),
+        };
+        assert_eq!(violated, expect);
+    }
+
+    #[rstest]
+    fn lint_message_return_err_bad_pattern() {
+        let lint = LintSettings::new().commit_rule(LintRule::new("broken").pattern("(unclosed"));
+        let result = lint_message("vim: fix", &lint);
+        assert!(matches!(result.unwrap_err(), CommitError::BadPattern { .. }));
+    }
+}
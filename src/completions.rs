@@ -0,0 +1,352 @@
+// SPDX-FileCopyrightText: 2024 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+//! Shell completions.
+//!
+//! Completions are generated from the command-line interface itself, so they
+//! never fall behind it. They can be printed for any shell clap supports, or
+//! installed into the directory each shell loads completions from on its own:
+//!
+//! | Shell  | Completion file                                           |
+//! |--------|-----------------------------------------------------------|
+//! | bash   | `$XDG_DATA_HOME/bash-completion/completions/ricer`        |
+//! | zsh    | `$XDG_DATA_HOME/zsh/site-functions/_ricer`                |
+//! | fish   | `$XDG_CONFIG_HOME/fish/completions/ricer.fish`            |
+//! | elvish | `$XDG_CONFIG_HOME/elvish/lib/ricer.elv`                   |
+//!
+//! Bash honors `BASH_COMPLETION_USER_DIR` instead of `XDG_DATA_HOME`, just
+//! like bash-completion does. Zsh and elvish need one line of configuration
+//! to pick up their completion file, which is printed along with it.
+
+use crate::{cli::Cli, context::CompletionsContext};
+
+use clap::CommandFactory;
+use clap_complete::{generate, Shell};
+use directories::BaseDirs;
+use log::debug;
+use mkdirp::mkdirp;
+use std::{
+    env, fmt, fs,
+    io::{Error as IoError, ErrorKind},
+    path::{Path, PathBuf},
+};
+
+/// Error types for shell completions.
+#[derive(Debug, thiserror::Error)]
+pub enum CompletionsError {
+    #[error("Cannot detect shell through SHELL, so name it, e.g., 'ricer completions bash'")]
+    NoShell,
+
+    #[error("Cannot determine home directory of user")]
+    NoWayHome,
+
+    #[error(
+        "Cannot install completions for '{shell}', print them through 'ricer completions {shell}' \
+         instead"
+    )]
+    Unsupported { shell: Shell },
+
+    #[error("Failed to make completion directory '{path}'")]
+    MakeDir { source: IoError, path: PathBuf },
+
+    #[error("Failed to write completions to '{path}'")]
+    FileWrite { source: IoError, path: PathBuf },
+
+    #[error("Failed to remove completions at '{path}'")]
+    FileRemove { source: IoError, path: PathBuf },
+}
+
+/// Directories completion files are installed relative to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompletionDirs {
+    data_dir: PathBuf,
+    config_dir: PathBuf,
+    bash_dir: Option<PathBuf>,
+}
+
+impl CompletionDirs {
+    pub fn new(data_dir: impl Into<PathBuf>, config_dir: impl Into<PathBuf>) -> Self {
+        Self { data_dir: data_dir.into(), config_dir: config_dir.into(), bash_dir: None }
+    }
+
+    /// Use `dir` as user directory of bash-completion.
+    pub fn bash_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.bash_dir = Some(dir.into());
+        self
+    }
+
+    /// Locate directories of current user through XDG base directories.
+    ///
+    /// # Errors
+    ///
+    /// - Return [`CompletionsError::NoWayHome`] if home directory of user
+    ///   cannot be determined.
+    pub fn from_env() -> Result<Self, CompletionsError> {
+        let dirs = BaseDirs::new().ok_or(CompletionsError::NoWayHome)?;
+        let completion_dirs = Self::new(dirs.data_dir(), dirs.config_dir());
+        Ok(match env::var_os("BASH_COMPLETION_USER_DIR").filter(|dir| !dir.is_empty()) {
+            Some(dir) => completion_dirs.bash_dir(dir),
+            None => completion_dirs,
+        })
+    }
+
+    /// Get path that completion file of `shell` is installed to.
+    ///
+    /// # Errors
+    ///
+    /// - Return [`CompletionsError::Unsupported`] if `shell` has no standard
+    ///   completion directory.
+    pub fn completion_file(&self, shell: Shell) -> Result<PathBuf, CompletionsError> {
+        let path = match shell {
+            Shell::Bash => match &self.bash_dir {
+                Some(dir) => dir.join("completions/ricer"),
+                None => self.data_dir.join("bash-completion/completions/ricer"),
+            },
+            Shell::Zsh => self.data_dir.join("zsh/site-functions/_ricer"),
+            Shell::Fish => self.config_dir.join("fish/completions/ricer.fish"),
+            Shell::Elvish => self.config_dir.join("elvish/lib/ricer.elv"),
+            _ => return Err(CompletionsError::Unsupported { shell }),
+        };
+        Ok(path)
+    }
+}
+
+/// What happened to a completion file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompletionChange {
+    Installed,
+    Uninstalled,
+    NotInstalled,
+}
+
+/// Outcome of installing or uninstalling completions of a shell.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompletionReport {
+    pub shell: Shell,
+    pub path: PathBuf,
+    pub change: CompletionChange,
+    pub dry_run: bool,
+}
+
+impl fmt::Display for CompletionReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (shell, path) = (self.shell, self.path.display());
+        match (self.change, self.dry_run) {
+            (CompletionChange::Installed, false) => {
+                write!(f, "Installed {shell} completions into '{path}'")?
+            }
+            (CompletionChange::Installed, true) => {
+                write!(f, "Would install {shell} completions into '{path}'")?
+            }
+            (CompletionChange::Uninstalled, false) => {
+                return write!(f, "Uninstalled {shell} completions from '{path}'")
+            }
+            (CompletionChange::Uninstalled, true) => {
+                return write!(f, "Would uninstall {shell} completions from '{path}'")
+            }
+            (CompletionChange::NotInstalled, _) => {
+                return write!(f, "No {shell} completions installed at '{path}'")
+            }
+        }
+
+        // INVARIANT: completion file sits right inside the directory shells need to know about.
+        let dir = self.path.parent().unwrap_or(&self.path).display();
+        match self.shell {
+            Shell::Zsh => write!(f, "\nAdd 'fpath=({dir} $fpath)' to .zshrc before compinit"),
+            Shell::Elvish => write!(f, "\nAdd 'use ricer' to rc.elv"),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Pick shell of [`CompletionsContext`], or detect it through `SHELL`.
+///
+/// # Errors
+///
+/// - Return [`CompletionsError::NoShell`] if no shell was named and `SHELL`
+///   names none that completions can be generated for.
+pub fn select_shell(ctx: &CompletionsContext) -> Result<Shell, CompletionsError> {
+    ctx.shell.or_else(Shell::from_env).ok_or(CompletionsError::NoShell)
+}
+
+/// Generate completion script of `shell`.
+pub fn render(shell: Shell) -> String {
+    let mut script = Vec::new();
+    generate(shell, &mut Cli::command(), "ricer", &mut script);
+    String::from_utf8_lossy(&script).into_owned()
+}
+
+/// Install completions of shell selected by [`CompletionsContext`] into its
+/// completion directory.
+///
+/// Existing completions are replaced, so reinstalling after an upgrade of
+/// Ricer picks up new commands. Nothing is written through `--dry-run`.
+///
+/// # Errors
+///
+/// - Return [`CompletionsError::NoShell`] if no shell can be selected.
+/// - Return [`CompletionsError::Unsupported`] if shell has no standard
+///   completion directory.
+/// - Return [`CompletionsError::MakeDir`] if completion directory cannot be
+///   made.
+/// - Return [`CompletionsError::FileWrite`] if completion file cannot be
+///   written.
+pub fn install(
+    ctx: &CompletionsContext,
+    dirs: &CompletionDirs,
+) -> Result<CompletionReport, CompletionsError> {
+    let shell = select_shell(ctx)?;
+    let path = dirs.completion_file(shell)?;
+    let report =
+        CompletionReport { shell, path, change: CompletionChange::Installed, dry_run: ctx.dry_run };
+    if ctx.dry_run {
+        return Ok(report);
+    }
+
+    let dir = report.path.parent().unwrap_or(Path::new("."));
+    mkdirp(dir).map_err(|err| CompletionsError::MakeDir { source: err, path: dir.into() })?;
+    fs::write(&report.path, render(shell))
+        .map_err(|err| CompletionsError::FileWrite { source: err, path: report.path.clone() })?;
+    debug!("Install {shell} completions into '{}'", report.path.display());
+    Ok(report)
+}
+
+/// Remove completions of shell selected by [`CompletionsContext`] from its
+/// completion directory.
+///
+/// Nothing is removed through `--dry-run`.
+///
+/// # Errors
+///
+/// - Return [`CompletionsError::NoShell`] if no shell can be selected.
+/// - Return [`CompletionsError::Unsupported`] if shell has no standard
+///   completion directory.
+/// - Return [`CompletionsError::FileRemove`] if completion file exists, but
+///   cannot be removed.
+pub fn uninstall(
+    ctx: &CompletionsContext,
+    dirs: &CompletionDirs,
+) -> Result<CompletionReport, CompletionsError> {
+    let shell = select_shell(ctx)?;
+    let path = dirs.completion_file(shell)?;
+    let change = match path.exists() {
+        true => CompletionChange::Uninstalled,
+        false => CompletionChange::NotInstalled,
+    };
+    let report = CompletionReport { shell, path, change, dry_run: ctx.dry_run };
+    if ctx.dry_run || change == CompletionChange::NotInstalled {
+        return Ok(report);
+    }
+
+    match fs::remove_file(&report.path) {
+        Ok(_) => debug!("Uninstall {shell} completions from '{}'", report.path.display()),
+        Err(err) if err.kind() == ErrorKind::NotFound => (),
+        Err(err) => {
+            return Err(CompletionsError::FileRemove { source: err, path: report.path.clone() })
+        }
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testenv::{parse_ctx, FixtureHarness};
+
+    use anyhow::Result;
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    fn dirs(harness: &FixtureHarness) -> CompletionDirs {
+        CompletionDirs::new(harness.as_path().join("data"), harness.as_path().join("config"))
+    }
+
+    #[rstest]
+    #[case::bash(Shell::Bash, "data/bash-completion/completions/ricer")]
+    #[case::zsh(Shell::Zsh, "data/zsh/site-functions/_ricer")]
+    #[case::fish(Shell::Fish, "config/fish/completions/ricer.fish")]
+    #[case::elvish(Shell::Elvish, "config/elvish/lib/ricer.elv")]
+    fn completion_dirs_completion_file_follow_shell_conventions(
+        #[case] shell: Shell,
+        #[case] expect: &str,
+    ) -> Result<()> {
+        let dirs = CompletionDirs::new("data", "config");
+        assert_eq!(dirs.completion_file(shell)?, PathBuf::from(expect));
+        Ok(())
+    }
+
+    #[rstest]
+    fn completion_dirs_completion_file_honor_bash_dir() -> Result<()> {
+        let dirs = CompletionDirs::new("data", "config").bash_dir("bash");
+        assert_eq!(dirs.completion_file(Shell::Bash)?, PathBuf::from("bash/completions/ricer"));
+        Ok(())
+    }
+
+    #[rstest]
+    fn completion_dirs_completion_file_return_err_unsupported() {
+        let result = CompletionDirs::new("data", "config").completion_file(Shell::PowerShell);
+        assert!(matches!(result.unwrap_err(), CompletionsError::Unsupported { .. }));
+    }
+
+    #[rstest]
+    fn render_complete_commands() {
+        let script = render(Shell::Fish);
+        assert!(script.contains("complete -c ricer"));
+        assert!(script.contains("completions"));
+    }
+
+    #[rstest]
+    fn install_write_completion_file() -> Result<()> {
+        let harness = FixtureHarness::open()?;
+        let report = install(
+            &parse_ctx::<CompletionsContext>("completions", &["zsh", "--install"])?,
+            &dirs(&harness),
+        )?;
+        let expect = harness.as_path().join("data/zsh/site-functions/_ricer");
+        assert_eq!(report.path, expect);
+        assert_eq!(report.change, CompletionChange::Installed);
+        assert_eq!(fs::read_to_string(&expect)?, render(Shell::Zsh));
+        let hint = format!("Add 'fpath=({} $fpath)'", expect.parent().unwrap().display());
+        assert!(report.to_string().contains(&hint));
+        Ok(())
+    }
+
+    #[rstest]
+    fn install_dry_run_write_nothing() -> Result<()> {
+        let harness = FixtureHarness::open()?;
+        let ctx =
+            parse_ctx::<CompletionsContext>("completions", &["bash", "--install", "--dry-run"])?;
+        let report = install(&ctx, &dirs(&harness))?;
+        assert!(!report.path.exists());
+        assert!(report.to_string().starts_with("Would install bash completions into"));
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::uninstall(&["fish", "--uninstall"], CompletionChange::Uninstalled, false)]
+    #[case::dry_run(&["fish", "--uninstall", "--dry-run"], CompletionChange::Uninstalled, true)]
+    fn uninstall_remove_completion_file(
+        #[case] args: &[&str],
+        #[case] change: CompletionChange,
+        #[case] exists: bool,
+    ) -> Result<()> {
+        let harness = FixtureHarness::open()?;
+        let dirs = dirs(&harness);
+        install(&parse_ctx::<CompletionsContext>("completions", &["fish", "--install"])?, &dirs)?;
+        let report = uninstall(&parse_ctx::<CompletionsContext>("completions", args)?, &dirs)?;
+        assert_eq!(report.change, change);
+        assert_eq!(report.path.exists(), exists);
+        Ok(())
+    }
+
+    #[rstest]
+    fn uninstall_report_not_installed() -> Result<()> {
+        let harness = FixtureHarness::open()?;
+        let report = uninstall(
+            &parse_ctx::<CompletionsContext>("completions", &["bash", "--uninstall"])?,
+            &dirs(&harness),
+        )?;
+        assert_eq!(report.change, CompletionChange::NotInstalled);
+        Ok(())
+    }
+}
@@ -9,11 +9,22 @@
 //! format][toml-spec] as the main data exchange format for configuration file
 //! data. Thus, all logic in this module is centered around TOML.
 //!
-//! Ricer currently is expected to manage two types of configuration file:
-//! repository, and hook configurations. These configuration files are mainly
-//! located at whatever path is expected from any [`Locator`] implementation.
-//! Currently, expected location for these configuration files is in the
-//! `$XDG_CONFIG_HOME/ricer` directory.
+//! Ricer currently is expected to manage three types of configuration file:
+//! repository, hook, and general Ricer configurations. These configuration
+//! files are mainly located at whatever path is expected from any [`Locator`]
+//! implementation. Currently, expected location for these configuration files
+//! is in the `$XDG_CONFIG_HOME/ricer` directory.
+//!
+//! Tools embedding Ricer that only need to read configuration data should use
+//! [`ConfigSnapshot`], which never writes to or creates configuration files.
+//!
+//! Configuration files locked through `ricer lock` are decrypted in memory
+//! while they are read, and encrypted again while they are saved.
+//!
+//! Every configuration file can be overridden per machine through a local
+//! overlay next to it, e.g., `repos.local.toml` for `repos.toml`, see
+//! [`local_overlay_path`]. Overlays are merged in at load time, but are never
+//! written to.
 //!
 //! [toml-spec]: https://toml.io/en/v1.0.0
 //!
@@ -25,24 +36,39 @@
 //! [`XdgDirLayout`]: crate::locate::XdgDirLayout
 //! [`DefaultLocator`]: crate::locate::DefaultLocator
 
+mod expand;
 mod settings;
+mod snapshot;
 mod toml;
+mod validate;
+mod value;
 
+pub use expand::*;
 #[doc(inline)]
 pub use settings::*;
+pub use snapshot::*;
 pub use toml::*;
+pub use validate::*;
+pub use value::*;
 
-use crate::locate::Locator;
+use crate::{
+    crypt::{self, AgeCipher, CachedCipher, Cipher, CryptError},
+    locate::Locator,
+    plan::{self, DryRunEffect},
+};
 
-use log::debug;
+use log::{debug, warn};
 use mkdirp::mkdirp;
 use std::{
+    borrow::Cow,
     fmt,
-    fs::OpenOptions,
+    fs::{self, File, OpenOptions},
     io,
     io::{Read, Write},
     path::{Path, PathBuf},
 };
+use tempfile::NamedTempFile;
+use toml_edit::{value, Array, Item, Key};
 
 /// Error types for [`ConfigFile`].
 #[derive(Debug, thiserror::Error)]
@@ -61,8 +87,23 @@ pub enum ConfigFileError {
 
     #[error("Failed to parse '{path}'")]
     Toml { source: TomlError, path: PathBuf },
+
+    #[error("Configuration file '{path}' is larger than the limit of {limit} bytes")]
+    TooLarge { path: PathBuf, limit: u64 },
+
+    #[error("Failed to decrypt '{path}'")]
+    Decrypt { source: CryptError, path: PathBuf },
+
+    #[error("Failed to encrypt '{path}'")]
+    Encrypt { source: CryptError, path: PathBuf },
 }
 
+/// Maximum size of configuration file in bytes that will be read into memory.
+///
+/// Ricer's configuration files are expected to be small and hand-written, so
+/// any file larger than this is almost certainly a mistake.
+pub const MAX_CONFIG_SIZE: u64 = 4 * 1024 * 1024;
+
 /// Format preserving configuration file handler.
 ///
 /// Manage configuration file data by selecting which configuration startegy to
@@ -78,7 +119,11 @@ pub enum ConfigFileError {
 ///
 /// # Invariants
 ///
-/// Will preserve existing formatting of configuration file if any.
+/// 1. Will preserve existing formatting of configuration file if any.
+/// 1. Encrypted configuration files stay encrypted on disk.
+/// 1. Entries are read with the local overlay of the configuration file
+///    merged in, but values of the local overlay are never saved.
+/// 1. Saving never leaves a partially written configuration file behind.
 ///
 /// # See also
 ///
@@ -95,8 +140,10 @@ pub struct ConfigFile<'cfg, C, L>
     L: Locator,
 {
     doc: Toml,
+    overlay: Option<Toml>,
     config: C,
     locator: &'cfg L,
+    encrypted: bool,
 }
 
 impl<'cfg, C, L> ConfigFile<'cfg, C, L>
@@ -118,61 +165,76 @@ impl<'cfg, C, L> ConfigFile<'cfg, C, L>
     ///    could not be created when needed.
     /// 1. Return [`ConfigFileError::FileRead`] if target configuration file
     ///    could not be read.
+    /// 1. Return [`ConfigFileError::TooLarge`] if target configuration file
+    ///    is larger than [`MAX_CONFIG_SIZE`].
+    /// 1. Return [`ConfigFileError::Decrypt`] if target configuration file is
+    ///    encrypted, and could not be decrypted.
     /// 1. Return [`ConfigFileError::Toml`] if target configuration file
     ///    could not be parsed into TOML format.
+    ///
+    /// All of the above also apply to the local overlay of the configuration
+    /// file, except that it is never created.
+    ///
+    /// # Invariants
+    ///
+    /// 1. Unknown keys, values of the wrong type, and missing keys are logged
+    ///    as warnings through [`validate`], unless [`skip_validation`] was
+    ///    called. They never fail loading.
     pub fn load(config: C, locator: &'cfg L) -> Result<Self, ConfigFileError> {
         let path = config.location(locator);
         debug!("Load new configuration manager from '{}'", path.display());
         let root = path.parent().unwrap();
         mkdirp(root).map_err(|err| ConfigFileError::MakeDirP { source: err, path: root.into() })?;
 
-        let mut file = OpenOptions::new()
+        let file = OpenOptions::new()
             .write(true)
             .truncate(false)
             .read(true)
             .create(true)
             .open(path)
             .map_err(|err| ConfigFileError::FileOpen { source: err, path: path.into() })?;
-        let mut buffer = String::new();
-        file.read_to_string(&mut buffer)
-            .map_err(|err| ConfigFileError::FileRead { source: err, path: path.into() })?;
-        let doc: Toml = buffer
-            .parse()
-            .map_err(|err| ConfigFileError::Toml { source: err, path: path.into() })?;
-
-        Ok(Self { doc, config, locator })
+        let (data, encrypted) = read_plaintext(file, path)?;
+        let doc =
+            data.parse().map_err(|err| ConfigFileError::Toml { source: err, path: path.into() })?;
+        if is_validating() {
+            // INVARIANT: data was parsed above, so validation cannot fail to parse it.
+            for diagnostic in validate(C::SECTION, &data).unwrap_or_default() {
+                warn!("{}: {diagnostic}", path.display());
+            }
+        }
+        let overlay = read_toml_if_exists(&local_overlay_path(path))?;
+
+        Ok(Self { doc, overlay, config, locator, encrypted })
     }
 
     /// Save configuration data at expected location.
     ///
     /// If expected configuration file does not exist at location, then it will
-    /// be created and written into automatically.
+    /// be created and written into automatically. Data is written into a
+    /// temporary file next to the configuration file first, which then
+    /// atomically replaces it. Thus, a crash mid-write leaves the old
+    /// configuration file intact. Nothing is written through `--dry-run`, see
+    /// [`plan::perform`].
     ///
     /// # Errors
     ///
     /// 1. Return [`ConfigFileError::MakeDirP`] if parent directory to to
     ///    expected configuration file path could not be created when needed.
-    /// 1. Return [`ConfigFileError::FileOpen`] if target configuration file
-    ///    could not be created when needed.
+    /// 1. Return [`ConfigFileError::Encrypt`] if target configuration file
+    ///    was encrypted, and could not be encrypted again.
     /// 1. Return [`ConfigFileError::FileWrite`] if target configuration file
-    ///    cannot be written into.
+    ///    cannot be written into, or replaced.
     pub fn save(&mut self) -> Result<(), ConfigFileError> {
         debug!("Save configuration manager data to '{}'", self.as_path().display());
+        if !plan::perform(|| DryRunEffect::WriteFile { path: self.as_path().into() }) {
+            return Ok(());
+        }
+
         let root = self.as_path().parent().unwrap();
         mkdirp(root).map_err(|err| ConfigFileError::MakeDirP { source: err, path: root.into() })?;
 
-        let mut file = OpenOptions::new()
-            .write(true)
-            .truncate(true)
-            .read(true)
-            .create(true)
-            .open(self.as_path())
-            .map_err(|err| ConfigFileError::FileOpen {
-                source: err,
-                path: self.as_path().into(),
-            })?;
-        let buffer = self.doc.to_string();
-        file.write_all(buffer.as_bytes()).map_err(|err| ConfigFileError::FileWrite {
+        let buffer = encode_toml(&self.doc, self.encrypted, self.as_path())?;
+        write_atomic(self.as_path(), &buffer).map_err(|err| ConfigFileError::FileWrite {
             source: err,
             path: self.as_path().into(),
         })?;
@@ -180,6 +242,39 @@ pub fn save(&mut self) -> Result<(), ConfigFileError> {
         Ok(())
     }
 
+    /// Edit configuration data through `edit`, and save it once `edit` is done.
+    ///
+    /// # Errors
+    ///
+    /// 1. Return any error of `edit`.
+    /// 1. Return errors of [`ConfigFile::save`].
+    ///
+    /// # Invariants
+    ///
+    /// 1. If `edit` or saving fails, every edit made through `edit` is rolled
+    ///    back, so the configuration file stays as it was, on disk and in
+    ///    memory.
+    pub fn transaction<T, E>(
+        &mut self,
+        edit: impl FnOnce(&mut Self) -> Result<T, E>,
+    ) -> Result<T, E>
+    where
+        E: From<ConfigFileError>,
+    {
+        let snapshot = self.doc.clone();
+        let result = edit(self).and_then(|done| {
+            self.save()?;
+            Ok(done)
+        });
+
+        if result.is_err() {
+            debug!("Roll back edits of '{}'", self.as_path().display());
+            self.doc = snapshot;
+        }
+
+        result
+    }
+
     /// Get configuration entry in deserialized form.
     ///
     /// # Errors
@@ -187,18 +282,36 @@ pub fn save(&mut self) -> Result<(), ConfigFileError> {
     /// 1. Return [`ConfigFileError::Toml`] if entry cannot be deserialized.
     pub fn get(&self, key: impl AsRef<str>) -> Result<C::Entry, ConfigFileError> {
         self.config
-            .get(&self.doc, key.as_ref())
+            .get(&self.view(), key.as_ref())
             .map_err(|err| ConfigFileError::Toml { source: err, path: self.as_path().into() })
     }
 
     /// Add new configuration entry in serialized form.
     ///
+    /// # Invariants
+    ///
+    /// Existing entries are never replaced. Use [`ConfigFile::upsert`] to
+    /// replace them on purpose.
+    ///
+    /// # Errors
+    ///
+    /// 1. Return [`ConfigFileError::Toml`] if entry cannot be serialized, or
+    ///    an entry with the same key already exists.
+    pub fn add_new(&mut self, entry: C::Entry) -> Result<(), ConfigFileError> {
+        self.write(|config, doc| config.add_new(doc, entry))
+            .map_err(|err| ConfigFileError::Toml { source: err, path: self.as_path().into() })
+    }
+
+    /// Add configuration entry in serialized form, replacing any existing
+    /// entry with the same key.
+    ///
+    /// Returns the replaced entry, if any.
+    ///
     /// # Errors
     ///
     /// 1. Return [`ConfigFileError::Toml`] if entry cannot be serialized.
-    pub fn add(&mut self, entry: C::Entry) -> Result<Option<C::Entry>, ConfigFileError> {
-        self.config
-            .add(&mut self.doc, entry)
+    pub fn upsert(&mut self, entry: C::Entry) -> Result<Option<C::Entry>, ConfigFileError> {
+        self.write(|config, doc| config.upsert(doc, entry))
             .map_err(|err| ConfigFileError::Toml { source: err, path: self.as_path().into() })
     }
 
@@ -212,8 +325,7 @@ pub fn rename(
         from: impl AsRef<str>,
         to: impl AsRef<str>,
     ) -> Result<C::Entry, ConfigFileError> {
-        self.config
-            .rename(&mut self.doc, from.as_ref(), to.as_ref())
+        self.write(|config, doc| config.rename(doc, from.as_ref(), to.as_ref()))
             .map_err(|err| ConfigFileError::Toml { source: err, path: self.as_path().into() })
     }
 
@@ -223,14 +335,125 @@ pub fn rename(
     ///
     /// 1. Return [`ConfigFileError::Toml`] if entry cannot be removed.
     pub fn remove(&mut self, key: impl AsRef<str>) -> Result<C::Entry, ConfigFileError> {
-        self.config
-            .remove(&mut self.doc, key.as_ref())
+        self.write(|config, doc| config.remove(doc, key.as_ref()))
+            .map_err(|err| ConfigFileError::Toml { source: err, path: self.as_path().into() })
+    }
+
+    /// Get all configuration entries in deserialized form.
+    ///
+    /// Will return no entries if the configuration file does not define any.
+    ///
+    /// # Errors
+    ///
+    /// 1. Return [`ConfigFileError::Toml`] if entries cannot be deserialized.
+    pub fn entries(&self) -> Result<Vec<C::Entry>, ConfigFileError> {
+        entries_or_empty(&self.config, &self.view())
             .map_err(|err| ConfigFileError::Toml { source: err, path: self.as_path().into() })
     }
 
     pub fn as_path(&self) -> &Path {
         self.config.location(self.locator)
     }
+
+    /// Document with local overlay merged in, if any.
+    fn view(&self) -> Cow<'_, Toml> {
+        match &self.overlay {
+            Some(overlay) => {
+                let mut doc = self.doc.clone();
+                doc.merge(overlay);
+                Cow::Owned(doc)
+            }
+            None => Cow::Borrowed(&self.doc),
+        }
+    }
+
+    /// Modify document, keeping values of local overlay out of it.
+    fn write<T>(&mut self, modify: impl FnOnce(&C, &mut Toml) -> T) -> T {
+        let base = self.overlay.as_ref().map(|_| self.doc.clone());
+        let result = modify(&self.config, &mut self.doc);
+
+        // INVARIANT: entries read through the merged view never leak local overrides.
+        if let (Some(overlay), Some(base)) = (&self.overlay, base) {
+            self.doc.unmerge(overlay, &base);
+        }
+
+        result
+    }
+}
+
+impl<'cfg, L> ConfigFile<'cfg, RepoConfig, L>
+where
+    L: Locator,
+{
+    /// Get every repository group of the "groups" section, in order of
+    /// configuration file.
+    ///
+    /// Missing "groups" section yields no groups.
+    ///
+    /// # Errors
+    ///
+    /// 1. Return [`ConfigFileError::Toml`] if "groups" section is not a table.
+    pub fn groups(&self) -> Result<Vec<RepoGroup>, ConfigFileError> {
+        let view = self.view();
+        match view.entries(Section::Groups) {
+            Ok(entries) => Ok(entries.into_iter().map(RepoGroup::from).collect()),
+            Err(TomlError::TableNotFound { .. }) => Ok(Vec::new()),
+            Err(err) => Err(ConfigFileError::Toml { source: err, path: self.as_path().into() }),
+        }
+    }
+
+    /// Replace members of repository group named like `group` with members of
+    /// `group`, adding the group if it does not exist yet.
+    ///
+    /// # Errors
+    ///
+    /// 1. Return [`ConfigFileError::Toml`] if "groups" section, or the group
+    ///    itself, is not a table.
+    pub fn set_group(&mut self, group: &RepoGroup) -> Result<(), ConfigFileError> {
+        let path = [Section::Groups.as_str(), &group.name];
+        let members = group.members.iter().map(String::as_str).collect::<Array>();
+        self.write(|_, doc| doc.add_nested(&path, (Key::new("members"), value(members))))
+            .map(drop)
+            .map_err(|err| ConfigFileError::Toml { source: err, path: self.as_path().into() })
+    }
+
+    /// Set branch of repository `name` to `branch`.
+    ///
+    /// # Invariants
+    ///
+    /// 1. Only the "branch" key of the repository is touched, so every other
+    ///    key, comment, and the form of its table stay as they were.
+    /// 1. Replaced branch keeps its surrounding whitespace and comments.
+    ///
+    /// # Errors
+    ///
+    /// 1. Return [`ConfigFileError::Toml`] if "repos" section, or the
+    ///    repository itself, is not found or not a table.
+    pub fn set_branch(&mut self, name: &str, branch: &str) -> Result<(), ConfigFileError> {
+        debug!("Set branch of '{name}' to '{branch}'");
+        self.write(|_, doc| -> Result<(), TomlError> {
+            let repos = doc.get_table_mut(Section::Repos)?;
+            let repo = repos.get_mut(name).ok_or_else(|| TomlError::EntryNotFound {
+                table: Section::Repos.to_string(),
+                key: name.into(),
+            })?;
+            let repo = repo.as_table_like_mut().ok_or_else(|| TomlError::NotTable {
+                table: format!("{}.{name}", Section::Repos),
+            })?;
+            match repo.get_mut("branch").and_then(Item::as_value_mut) {
+                Some(old) => {
+                    let decor = old.decor().clone();
+                    *old = branch.into();
+                    *old.decor_mut() = decor;
+                }
+                None => {
+                    repo.insert("branch", value(branch));
+                }
+            }
+            Ok(())
+        })
+        .map_err(|err| ConfigFileError::Toml { source: err, path: self.as_path().into() })
+    }
 }
 
 impl<'cfg, C, L> fmt::Display for ConfigFile<'cfg, C, L>
@@ -243,6 +466,142 @@ fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
     }
 }
 
+/// Read and parse configuration file data up to [`MAX_CONFIG_SIZE`].
+pub(crate) fn read_toml(file: impl Read, path: &Path) -> Result<Toml, ConfigFileError> {
+    let (buffer, _) = read_plaintext(file, path)?;
+    buffer.parse().map_err(|err| ConfigFileError::Toml { source: err, path: path.into() })
+}
+
+/// Read configuration file data up to [`MAX_CONFIG_SIZE`], decrypting it if
+/// need be.
+///
+/// Will also return whether data was encrypted.
+pub(crate) fn read_plaintext(
+    file: impl Read,
+    path: &Path,
+) -> Result<(String, bool), ConfigFileError> {
+    let read_err = |err| ConfigFileError::FileRead { source: err, path: path.into() };
+    let mut buffer = Vec::new();
+    file.take(MAX_CONFIG_SIZE + 1).read_to_end(&mut buffer).map_err(read_err)?;
+    if buffer.len() as u64 > MAX_CONFIG_SIZE {
+        return Err(ConfigFileError::TooLarge { path: path.into(), limit: MAX_CONFIG_SIZE });
+    }
+
+    let encrypted = crypt::is_encrypted(&buffer);
+    if encrypted {
+        debug!("Decrypt '{}'", path.display());
+        buffer = CachedCipher::new(AgeCipher::from_env())
+            .decrypt(&buffer)
+            .map_err(|err| ConfigFileError::Decrypt { source: err, path: path.into() })?;
+    }
+    let buffer = String::from_utf8(buffer)
+        .map_err(|err| read_err(io::Error::new(io::ErrorKind::InvalidData, err)))?;
+
+    Ok((buffer, encrypted))
+}
+
+/// Serialize `doc` for configuration file at `path`, encrypting it if it was
+/// read `encrypted`.
+pub(crate) fn encode_toml(
+    doc: &Toml,
+    encrypted: bool,
+    path: &Path,
+) -> Result<Vec<u8>, ConfigFileError> {
+    let buffer = doc.to_string().into_bytes();
+    match encrypted {
+        true => CachedCipher::new(AgeCipher::from_env())
+            .encrypt(&buffer)
+            .map_err(|err| ConfigFileError::Encrypt { source: err, path: path.into() }),
+        false => Ok(buffer),
+    }
+}
+
+/// Replace file at `path` with `data` atomically.
+///
+/// Data is written and synced into a temporary file in the same directory,
+/// which is then renamed over the file at `path`. Symlinks are followed, so
+/// their target is replaced rather than the symlink itself, and the
+/// permissions of the replaced file are kept.
+pub(crate) fn write_atomic(path: &Path, data: &[u8]) -> io::Result<()> {
+    let path = match fs::symlink_metadata(path) {
+        Ok(meta) if meta.file_type().is_symlink() => fs::canonicalize(path)?,
+        _ => path.into(),
+    };
+
+    // INVARIANT: parent exists, because it was made before saving.
+    let parent = path.parent().unwrap_or(Path::new("."));
+    let mut file = NamedTempFile::new_in(parent)?;
+    if let Ok(meta) = fs::metadata(&path) {
+        file.as_file().set_permissions(meta.permissions())?;
+    }
+    file.write_all(data)?;
+    file.as_file().sync_all()?;
+    file.persist(&path).map_err(|err| err.error)?;
+    Ok(())
+}
+
+/// Expected path to local overlay of configuration file at `path`.
+///
+/// The local overlay has the same file stem with a `.local` suffix, e.g.,
+/// `repos.local.toml` for `repos.toml`. It holds machine-specific overrides,
+/// so it is merged in at load time, never written to, and left out of
+/// snapshot exports.
+pub fn local_overlay_path(path: &Path) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let name = match path.extension() {
+        Some(ext) => format!("{stem}.local.{}", ext.to_string_lossy()),
+        None => format!("{stem}.local"),
+    };
+    path.with_file_name(name)
+}
+
+/// Check if `path` is the local overlay of some configuration file.
+pub fn is_local_overlay(path: &Path) -> bool {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    name.ends_with(".local") || stem.ends_with(".local")
+}
+
+/// Read and parse configuration file at `path` without ever creating it.
+///
+/// Returns `None` if configuration file does not exist.
+pub(crate) fn read_toml_if_exists(path: &Path) -> Result<Option<Toml>, ConfigFileError> {
+    debug!("Read configuration from '{}'", path.display());
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(ConfigFileError::FileOpen { source: err, path: path.into() }),
+    };
+
+    read_toml(file, path).map(Some)
+}
+
+/// Read and parse configuration file at `path` with its local overlay merged
+/// in, without ever creating either of them.
+///
+/// Returns `None` if neither configuration file nor local overlay exist.
+pub(crate) fn read_merged_if_exists(path: &Path) -> Result<Option<Toml>, ConfigFileError> {
+    let doc = read_toml_if_exists(path)?;
+    let Some(overlay) = read_toml_if_exists(&local_overlay_path(path))? else {
+        return Ok(doc);
+    };
+
+    let mut doc = doc.unwrap_or_default();
+    doc.merge(&overlay);
+    Ok(Some(doc))
+}
+
+/// Get all entries of configuration, treating a missing table as empty.
+pub(crate) fn entries_or_empty<C: Config>(
+    config: &C,
+    doc: &Toml,
+) -> Result<Vec<C::Entry>, TomlError> {
+    match config.entries(doc) {
+        Err(TomlError::TableNotFound { .. }) => Ok(Vec::new()),
+        result => result,
+    }
+}
+
 /// TOML serialization and deserialization configuration.
 ///
 /// Interface to simplify serialization and deserialization of parsed TOML data.
@@ -253,10 +612,15 @@ fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
 pub trait Config: fmt::Debug {
     type Entry: Settings;
 
+    /// Section holding entries of configuration file.
+    const SECTION: Section;
+
     fn get(&self, doc: &Toml, key: &str) -> Result<Self::Entry, TomlError>;
-    fn add(&self, doc: &mut Toml, entry: Self::Entry) -> Result<Option<Self::Entry>, TomlError>;
+    fn add_new(&self, doc: &mut Toml, entry: Self::Entry) -> Result<(), TomlError>;
+    fn upsert(&self, doc: &mut Toml, entry: Self::Entry) -> Result<Option<Self::Entry>, TomlError>;
     fn remove(&self, doc: &mut Toml, key: &str) -> Result<Self::Entry, TomlError>;
     fn rename(&self, doc: &mut Toml, from: &str, to: &str) -> Result<Self::Entry, TomlError>;
+    fn entries(&self, doc: &Toml) -> Result<Vec<Self::Entry>, TomlError>;
     fn location<'cfg>(&self, locator: &'cfg impl Locator) -> &'cfg Path;
 }
 
@@ -268,7 +632,8 @@ pub trait Config: fmt::Debug {
 ///
 /// # Invariants
 ///
-/// Will preserve existing formatting of configuration file if any.
+/// 1. Will preserve existing formatting of configuration file if any.
+/// 1. Encrypted configuration files stay encrypted on disk.
 ///
 /// # See also
 ///
@@ -279,32 +644,54 @@ pub trait Config: fmt::Debug {
 
 impl Config for RepoConfig {
     type Entry = RepoSettings;
+    const SECTION: Section = Section::Repos;
 
     fn get(&self, doc: &Toml, key: &str) -> Result<Self::Entry, TomlError> {
-        let entry = doc.get("repos", key.as_ref())?;
+        let entry = doc.get(Self::SECTION, key)?;
+        validate_repo(entry)?;
         Ok(RepoSettings::from(entry))
     }
 
-    fn add(&self, doc: &mut Toml, entry: Self::Entry) -> Result<Option<Self::Entry>, TomlError> {
-        let entry = doc.add("repos", entry.to_toml())?.map(RepoSettings::from);
+    fn add_new(&self, doc: &mut Toml, entry: Self::Entry) -> Result<(), TomlError> {
+        doc.add_new(Self::SECTION, entry.to_toml())
+    }
+
+    fn upsert(&self, doc: &mut Toml, entry: Self::Entry) -> Result<Option<Self::Entry>, TomlError> {
+        let entry = doc.add(Self::SECTION, entry.to_toml())?.map(RepoSettings::from);
         Ok(entry)
     }
 
     fn remove(&self, doc: &mut Toml, key: &str) -> Result<Self::Entry, TomlError> {
-        let entry = doc.remove("repos", key.as_ref())?;
+        let entry = doc.remove(Self::SECTION, key)?;
         Ok(RepoSettings::from(entry))
     }
 
     fn rename(&self, doc: &mut Toml, from: &str, to: &str) -> Result<Self::Entry, TomlError> {
-        let entry = doc.rename("repos", from.as_ref(), to.as_ref())?;
+        let entry = doc.rename(Self::SECTION, from, to)?;
         Ok(RepoSettings::from(entry))
     }
 
+    fn entries(&self, doc: &Toml) -> Result<Vec<Self::Entry>, TomlError> {
+        let entries = doc.entries(Self::SECTION)?;
+        for entry in &entries {
+            validate_repo(*entry)?;
+        }
+        Ok(entries.into_iter().map(RepoSettings::from).collect())
+    }
+
     fn location<'cfg>(&self, locator: &'cfg impl Locator) -> &'cfg Path {
         locator.repos_config()
     }
 }
 
+fn validate_repo(entry: (&Key, &Item)) -> Result<(), TomlError> {
+    RepoSettings::validate(entry).map_err(|err| TomlError::BadSettings {
+        table: Section::Repos.to_string(),
+        key: entry.0.get().into(),
+        source: Box::new(err),
+    })
+}
+
 /// Command hook configuration management.
 ///
 /// Handles serialization and deserialization of command hook settings.
@@ -313,7 +700,8 @@ fn location<'cfg>(&self, locator: &'cfg impl Locator) -> &'cfg Path {
 ///
 /// # Invariants
 ///
-/// Will preserve existing formatting of configuration file if any.
+/// 1. Will preserve existing formatting of configuration file if any.
+/// 1. Encrypted configuration files stay encrypted on disk.
 ///
 /// # See also
 ///
@@ -324,77 +712,502 @@ fn location<'cfg>(&self, locator: &'cfg impl Locator) -> &'cfg Path {
 
 impl Config for CmdHookConfig {
     type Entry = CmdHookSettings;
+    const SECTION: Section = Section::Hooks;
 
     fn get(&self, doc: &Toml, key: &str) -> Result<Self::Entry, TomlError> {
-        let entry = doc.get("hooks", key.as_ref())?;
+        let entry = doc.get(Self::SECTION, key)?;
         Ok(CmdHookSettings::from(entry))
     }
 
-    fn add(&self, doc: &mut Toml, entry: Self::Entry) -> Result<Option<Self::Entry>, TomlError> {
-        let entry = doc.add("hooks", entry.to_toml())?.map(CmdHookSettings::from);
+    fn add_new(&self, doc: &mut Toml, entry: Self::Entry) -> Result<(), TomlError> {
+        doc.add_new(Self::SECTION, entry.to_toml())
+    }
+
+    fn upsert(&self, doc: &mut Toml, entry: Self::Entry) -> Result<Option<Self::Entry>, TomlError> {
+        let entry = doc.add(Self::SECTION, entry.to_toml())?.map(CmdHookSettings::from);
         Ok(entry)
     }
 
     fn remove(&self, doc: &mut Toml, key: &str) -> Result<Self::Entry, TomlError> {
-        let entry = doc.remove("hooks", key.as_ref())?;
+        let entry = doc.remove(Self::SECTION, key)?;
         Ok(CmdHookSettings::from(entry))
     }
 
     fn rename(&self, doc: &mut Toml, from: &str, to: &str) -> Result<Self::Entry, TomlError> {
-        let entry = doc.rename("hooks", from.as_ref(), to.as_ref())?;
+        let entry = doc.rename(Self::SECTION, from, to)?;
         Ok(CmdHookSettings::from(entry))
     }
 
-    fn location<'cfg>(&self, locator: &'cfg impl Locator) -> &'cfg Path {
-        locator.hooks_config()
-    }
-}
+    fn entries(&self, doc: &Toml) -> Result<Vec<Self::Entry>, TomlError> {
+        let entries = doc.entries(Self::SECTION)?.into_iter().map(CmdHookSettings::from).collect();
+        Ok(entries)
+    }
+
+    fn location<'cfg>(&self, locator: &'cfg impl Locator) -> &'cfg Path {
+        locator.hooks_config()
+    }
+}
+
+/// Feature toggle configuration management.
+///
+/// Handles serialization and deserialization of feature settings. Feature
+/// settings are held within the "features" section of Ricer's general
+/// configuration file.
+///
+/// # Invariants
+///
+/// 1. Will preserve existing formatting of configuration file if any.
+/// 1. Encrypted configuration files stay encrypted on disk.
+///
+/// # See also
+///
+/// - [`Toml`]
+/// - [`FeatureSettings`]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FeatureConfig;
+
+impl FeatureConfig {
+    /// Determine if feature is enabled.
+    ///
+    /// Features that are missing from the configuration file are enabled.
+    ///
+    /// # Errors
+    ///
+    /// 1. Return [`ConfigFileError`] if configuration file could not be
+    ///    loaded.
+    pub fn is_enabled(locator: &impl Locator, feature: &str) -> Result<bool, ConfigFileError> {
+        Ok(Self::configured(locator, feature)?.unwrap_or(true))
+    }
+
+    /// Determine if opt-in feature is enabled.
+    ///
+    /// Features that are missing from the configuration file are disabled.
+    ///
+    /// # Errors
+    ///
+    /// 1. Return [`ConfigFileError`] if configuration file could not be
+    ///    loaded.
+    pub fn is_opted_in(locator: &impl Locator, feature: &str) -> Result<bool, ConfigFileError> {
+        Ok(Self::configured(locator, feature)?.unwrap_or(false))
+    }
+
+    fn configured(locator: &impl Locator, feature: &str) -> Result<Option<bool>, ConfigFileError> {
+        let config = ConfigFile::load(FeatureConfig, locator)?;
+        match config.get(feature) {
+            Ok(settings) => Ok(Some(settings.enabled)),
+            Err(ConfigFileError::Toml {
+                source: TomlError::TableNotFound { .. } | TomlError::EntryNotFound { .. },
+                ..
+            }) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+}
+
+impl Config for FeatureConfig {
+    type Entry = FeatureSettings;
+    const SECTION: Section = Section::Features;
+
+    fn get(&self, doc: &Toml, key: &str) -> Result<Self::Entry, TomlError> {
+        let entry = doc.get(Self::SECTION, key)?;
+        Ok(FeatureSettings::from(entry))
+    }
+
+    fn add_new(&self, doc: &mut Toml, entry: Self::Entry) -> Result<(), TomlError> {
+        doc.add_new(Self::SECTION, entry.to_toml())
+    }
+
+    fn upsert(&self, doc: &mut Toml, entry: Self::Entry) -> Result<Option<Self::Entry>, TomlError> {
+        let entry = doc.add(Self::SECTION, entry.to_toml())?.map(FeatureSettings::from);
+        Ok(entry)
+    }
+
+    fn remove(&self, doc: &mut Toml, key: &str) -> Result<Self::Entry, TomlError> {
+        let entry = doc.remove(Self::SECTION, key)?;
+        Ok(FeatureSettings::from(entry))
+    }
+
+    fn rename(&self, doc: &mut Toml, from: &str, to: &str) -> Result<Self::Entry, TomlError> {
+        let entry = doc.rename(Self::SECTION, from, to)?;
+        Ok(FeatureSettings::from(entry))
+    }
+
+    fn entries(&self, doc: &Toml) -> Result<Vec<Self::Entry>, TomlError> {
+        let entries = doc.entries(Self::SECTION)?.into_iter().map(FeatureSettings::from).collect();
+        Ok(entries)
+    }
+
+    fn location<'cfg>(&self, locator: &'cfg impl Locator) -> &'cfg Path {
+        locator.ricer_config()
+    }
+}
+
+/// Terminal output configuration.
+///
+/// Reads [`UiSettings`] from the `[ui]` table of Ricer's general configuration
+/// file. Unlike [`ConfigFile`], the configuration file is never created, since
+/// output preferences are only ever read.
+///
+/// # See also
+///
+/// - [`UiSettings`]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct UiConfig;
+
+impl UiConfig {
+    /// Load terminal output settings.
+    ///
+    /// Missing configuration file or `[ui]` table yield default settings.
+    ///
+    /// # Errors
+    ///
+    /// 1. Return [`ConfigFileError`] if existing configuration file could not
+    ///    be read or parsed.
+    pub fn load(locator: &impl Locator) -> Result<UiSettings, ConfigFileError> {
+        let path = locator.ricer_config();
+        let doc = match read_merged_if_exists(path)? {
+            Some(doc) => doc,
+            None => return Ok(UiSettings::default()),
+        };
+
+        match doc.get_table(Section::Ui) {
+            Ok(table) => Ok(UiSettings::from(table)),
+            Err(TomlError::TableNotFound { .. }) => Ok(UiSettings::default()),
+            Err(err) => Err(ConfigFileError::Toml { source: err, path: path.into() }),
+        }
+    }
+}
+
+/// Background fetch daemon configuration.
+pub struct DaemonConfig;
+
+impl DaemonConfig {
+    /// Load daemon settings.
+    ///
+    /// Missing configuration file or `[daemon]` table yield default settings.
+    ///
+    /// # Errors
+    ///
+    /// 1. Return [`ConfigFileError`] if existing configuration file could not
+    ///    be read or parsed.
+    pub fn load(locator: &impl Locator) -> Result<DaemonSettings, ConfigFileError> {
+        let path = locator.ricer_config();
+        let doc = match read_merged_if_exists(path)? {
+            Some(doc) => doc,
+            None => return Ok(DaemonSettings::default()),
+        };
+
+        match doc.get_table(Section::Daemon) {
+            Ok(table) => Ok(DaemonSettings::from(table)),
+            Err(TomlError::TableNotFound { .. }) => Ok(DaemonSettings::default()),
+            Err(err) => Err(ConfigFileError::Toml { source: err, path: path.into() }),
+        }
+    }
+
+    /// Validate daemon settings of `doc`.
+    ///
+    /// Missing `[daemon]` table is valid.
+    ///
+    /// # Errors
+    ///
+    /// - Return [`TomlError::BadSettings`] if a daemon setting is invalid.
+    pub fn validate(doc: &Toml) -> Result<(), TomlError> {
+        let table = match doc.get_table(Section::Daemon) {
+            Ok(table) => table,
+            Err(TomlError::TableNotFound { .. }) => return Ok(()),
+            Err(err) => return Err(err),
+        };
+
+        DaemonSettings::validate(table).map_err(|err| TomlError::BadSettings {
+            table: Section::Daemon.to_string(),
+            key: "interval".into(),
+            source: Box::new(err),
+        })
+    }
+}
+
+/// Hook output log configuration.
+pub struct HookLogConfig;
+
+impl HookLogConfig {
+    /// Load hook output log settings.
+    ///
+    /// Missing configuration file or `[hook_logs]` table yield default
+    /// settings.
+    ///
+    /// # Errors
+    ///
+    /// 1. Return [`ConfigFileError`] if existing configuration file could not
+    ///    be read or parsed.
+    pub fn load(locator: &impl Locator) -> Result<HookLogSettings, ConfigFileError> {
+        let path = locator.ricer_config();
+        let doc = match read_merged_if_exists(path)? {
+            Some(doc) => doc,
+            None => return Ok(HookLogSettings::default()),
+        };
+
+        match doc.get_table(Section::HookLogs) {
+            Ok(table) => Ok(HookLogSettings::from(table)),
+            Err(TomlError::TableNotFound { .. }) => Ok(HookLogSettings::default()),
+            Err(err) => Err(ConfigFileError::Toml { source: err, path: path.into() }),
+        }
+    }
+}
+
+/// Commit message lint configuration.
+pub struct LintConfig;
+
+impl LintConfig {
+    /// Load lint rules.
+    ///
+    /// Missing configuration file or `[lint]` table yield no rules.
+    ///
+    /// # Errors
+    ///
+    /// 1. Return [`ConfigFileError`] if existing configuration file could not
+    ///    be read or parsed.
+    pub fn load(locator: &impl Locator) -> Result<LintSettings, ConfigFileError> {
+        let path = locator.ricer_config();
+        let doc = match read_merged_if_exists(path)? {
+            Some(doc) => doc,
+            None => return Ok(LintSettings::default()),
+        };
+
+        match doc.get_table(Section::Lint) {
+            Ok(table) => Ok(LintSettings::from(table)),
+            Err(TomlError::TableNotFound { .. }) => Ok(LintSettings::default()),
+            Err(err) => Err(ConfigFileError::Toml { source: err, path: path.into() }),
+        }
+    }
+}
+
+/// Per-command defaults configuration.
+///
+/// Reads [`CommandSettings`] from the `[commands]` table of Ricer's general
+/// configuration file. Like [`UiConfig`], the configuration file is never
+/// created, since defaults are only ever read.
+///
+/// # See also
+///
+/// - [`CommandSettings`]
+/// - [`Context::merge_defaults`]
+///
+/// [`Context::merge_defaults`]: crate::context::Context::merge_defaults
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CommandConfig;
+
+impl CommandConfig {
+    /// Load persistent defaults of `command`.
+    ///
+    /// Missing configuration file, `[commands]` table, or command entry yield
+    /// no defaults.
+    ///
+    /// # Errors
+    ///
+    /// 1. Return [`ConfigFileError`] if existing configuration file could not
+    ///    be read or parsed.
+    pub fn load(locator: &impl Locator, command: &str) -> Result<CommandSettings, ConfigFileError> {
+        let path = locator.ricer_config();
+        let doc = match read_merged_if_exists(path)? {
+            Some(doc) => doc,
+            None => return Ok(CommandSettings::new(command)),
+        };
+
+        match doc.get(Section::Commands, command) {
+            Ok(entry) => Ok(CommandSettings::from(entry)),
+            Err(TomlError::TableNotFound { .. } | TomlError::EntryNotFound { .. }) => {
+                Ok(CommandSettings::new(command))
+            }
+            Err(err) => Err(ConfigFileError::Toml { source: err, path: path.into() }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        locate::MockLocator,
+        testenv::{FileKind, FixtureHarness},
+    };
+
+    use anyhow::Result;
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+    use rstest::{fixture, rstest};
+    use std::time::Duration;
+
+    #[fixture]
+    fn config_dir() -> Result<FixtureHarness> {
+        let harness = FixtureHarness::open()?
+            .with_file("config.toml", |fixture| {
+                fixture
+                    .with_data(indoc! {r#"
+                        # Formatting should remain the same!
+
+                        [repos.vim]
+                        branch = "master"
+                        remote = "origin"
+                        workdir_home = true
+
+                        [hooks]
+                        bootstrap = [
+                            { pre = "hook.sh", post = "hook.sh", workdir = "/some/dir" },
+                            { pre = "hook.sh" }
+                        ]
+                    "#})
+                    .with_kind(FileKind::Normal)
+            })
+            .with_file("not_table.toml", |fixture| {
+                fixture
+                    .with_data(indoc! {r#"
+                        repos = 'not a table'
+                        hooks = 'not a table'
+                    "#})
+                    .with_kind(FileKind::Normal)
+            })
+            .with_file("features.toml", |fixture| {
+                fixture
+                    .with_data(indoc! {r#"
+                        [features.state]
+                        enabled = false
+
+                        [features.history]
+                        enabled = true
+                    "#})
+                    .with_kind(FileKind::Normal)
+            })
+            .with_file("ui.toml", |fixture| {
+                fixture
+                    .with_data(indoc! {r#"
+                        [ui]
+                        pager = "never"
+                    "#})
+                    .with_kind(FileKind::Normal)
+            })
+            .with_file("lint.toml", |fixture| {
+                fixture
+                    .with_data(indoc! {r#"
+                        [lint]
+                        commit = [
+                            { name = "scope", pattern = '^[a-z0-9_-]+: ' },
+                            { max_length = 72 },
+                            { name = "nothing" },
+                        ]
+                    "#})
+                    .with_kind(FileKind::Normal)
+            })
+            .with_file("lint_files.toml", |fixture| {
+                fixture
+                    .with_data(indoc! {r#"
+                        [lint.files]
+                        max_size = "1MiB"
+                        binary = false
+                        action = "block"
+                        allow = ["wallpapers/", "*.ttf"]
+                    "#})
+                    .with_kind(FileKind::Normal)
+            })
+            .with_file("daemon.toml", |fixture| {
+                fixture
+                    .with_data(indoc! {r#"
+                        [daemon]
+                        interval = 300
+                    "#})
+                    .with_kind(FileKind::Normal)
+            })
+            .with_file("hook_logs.toml", |fixture| {
+                fixture
+                    .with_data(indoc! {r#"
+                        [hook_logs]
+                        max_files = 20
+                        max_size = "1MiB"
+                    "#})
+                    .with_kind(FileKind::Normal)
+            })
+            .with_file("hook_logs_invalid.toml", |fixture| {
+                fixture
+                    .with_data(indoc! {r#"
+                        [hook_logs]
+                        max_files = -1
+                        max_size = "lots"
+                    "#})
+                    .with_kind(FileKind::Normal)
+            })
+            .with_file("daemon_duration.toml", |fixture| {
+                fixture
+                    .with_data(indoc! {r#"
+                        [daemon]
+                        interval = "2h"
+                    "#})
+                    .with_kind(FileKind::Normal)
+            })
+            .with_file("daemon_too_short.toml", |fixture| {
+                fixture
+                    .with_data(indoc! {r#"
+                        [daemon]
+                        interval = 1
+                    "#})
+                    .with_kind(FileKind::Normal)
+            })
+            .with_file("commands.toml", |fixture| {
+                fixture
+                    .with_data(indoc! {r#"
+                        [commands.status]
+                        terse = true
+
+                        [commands.pull]
+                        remote = "upstream"
+                        jobs = 8
+                        ignored = ["array", "values"]
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::{
-        locate::MockLocator,
-        testenv::{FileKind, FixtureHarness},
-    };
+                        [commands.pull.nested]
+                        fix = true
+                    "#})
+                    .with_kind(FileKind::Normal)
+            })
+            .with_file("groups.toml", |fixture| {
+                fixture
+                    .with_data(indoc! {r#"
+                        [repos.dwm]
+                        branch = "main"
+                        remote = "origin"
 
-    use anyhow::Result;
-    use indoc::indoc;
-    use pretty_assertions::assert_eq;
-    use rstest::{fixture, rstest};
+                        [groups.desktop]
+                        members = ["dwm", "x11"]
 
-    #[fixture]
-    fn config_dir() -> Result<FixtureHarness> {
-        let harness = FixtureHarness::open()?
-            .with_file("config.toml", |fixture| {
+                        [groups.x11]
+                        members = "st"
+                    "#})
+                    .with_kind(FileKind::Normal)
+            })
+            .with_file("overlaid.toml", |fixture| {
                 fixture
                     .with_data(indoc! {r#"
-                        # Formatting should remain the same!
-
                         [repos.vim]
                         branch = "master"
                         remote = "origin"
                         workdir_home = true
-
-                        [hooks]
-                        bootstrap = [
-                            { pre = "hook.sh", post = "hook.sh", workdir = "/some/dir" },
-                            { pre = "hook.sh" }
-                        ]
                     "#})
                     .with_kind(FileKind::Normal)
             })
-            .with_file("not_table.toml", |fixture| {
+            .with_file("overlaid.local.toml", |fixture| {
                 fixture
                     .with_data(indoc! {r#"
-                        repos = 'not a table'
-                        hooks = 'not a table'
+                        # Machine-specific remote.
+                        [repos.vim]
+                        remote = "mirror"
                     "#})
                     .with_kind(FileKind::Normal)
             })
             .with_file("bad_format.toml", |fixture| {
                 fixture.with_data("this 'will fail!").with_kind(FileKind::Normal)
             })
+            .with_file("too_large.toml", |fixture| {
+                fixture
+                    .with_data("#".repeat(MAX_CONFIG_SIZE as usize + 1))
+                    .with_kind(FileKind::Normal)
+            })
             .setup()?;
         Ok(harness)
     }
@@ -418,6 +1231,21 @@ fn config_file_load_parse_file(
         Ok(())
     }
 
+    #[rstest]
+    #[case::toml("/config/repos.toml", "/config/repos.local.toml", true)]
+    #[case::no_extension("/config/ricer", "/config/ricer.local", true)]
+    #[case::overlay("/config/repos.local.toml", "/config/repos.local.local.toml", false)]
+    fn local_overlay_path_add_local_suffix(
+        #[case] path: &str,
+        #[case] expect: &str,
+        #[case] base: bool,
+    ) {
+        let overlay = local_overlay_path(Path::new(path));
+        assert_eq!(overlay, PathBuf::from(expect));
+        assert!(is_local_overlay(&overlay));
+        assert_eq!(is_local_overlay(Path::new(path)), !base);
+    }
+
     #[rstest]
     #[case::repo_config(RepoConfig)]
     #[case::hook_cmd_config(CmdHookConfig)]
@@ -455,6 +1283,39 @@ fn config_file_load_return_err_toml(
         Ok(())
     }
 
+    #[rstest]
+    #[case::repo_config(RepoConfig)]
+    #[case::cmd_hook_config(CmdHookConfig)]
+    fn config_file_load_return_err_too_large(
+        config_dir: Result<FixtureHarness>,
+        #[case] config_kind: impl Config,
+    ) -> Result<()> {
+        let config_dir = config_dir?;
+        let fixture = config_dir.get_file("too_large.toml")?;
+        let mut locator = MockLocator::new();
+        locator.expect_repos_config().return_const(fixture.as_path().into());
+        locator.expect_hooks_config().return_const(fixture.as_path().into());
+
+        let result = ConfigFile::load(config_kind, &locator);
+        assert!(matches!(result.unwrap_err(), ConfigFileError::TooLarge { .. }));
+
+        Ok(())
+    }
+
+    #[rstest]
+    fn config_file_load_return_err_decrypt(config_dir: Result<FixtureHarness>) -> Result<()> {
+        let config_dir = config_dir?;
+        let path = config_dir.as_path().join("locked.toml");
+        std::fs::write(&path, "age-encryption.org/v1\nnot really encrypted\n")?;
+        let mut locator = MockLocator::new();
+        locator.expect_repos_config().return_const(path);
+
+        let result = ConfigFile::load(RepoConfig, &locator);
+        assert!(matches!(result.unwrap_err(), ConfigFileError::Decrypt { .. }));
+
+        Ok(())
+    }
+
     #[rstest]
     #[case::repo_config(
         RepoConfig,
@@ -480,7 +1341,7 @@ fn config_file_save_preserves_formatting<E, T>(
         locator.expect_hooks_config().return_const(fixture.as_path().into());
 
         let mut config = ConfigFile::load(config_kind, &locator)?;
-        config.add(expect)?;
+        config.upsert(expect)?;
         config.save()?;
         fixture.sync()?;
         assert_eq!(config.to_string(), fixture.as_str());
@@ -507,6 +1368,105 @@ fn config_file_save_create_new_file(
         Ok(())
     }
 
+    #[cfg(unix)]
+    #[rstest]
+    fn config_file_save_replace_symlink_target(config_dir: Result<FixtureHarness>) -> Result<()> {
+        let mut config_dir = config_dir?;
+        let link = config_dir.as_path().join("linked.toml");
+        std::os::unix::fs::symlink(config_dir.as_path().join("config.toml"), &link)?;
+        let mut locator = MockLocator::new();
+        locator.expect_repos_config().return_const(link.clone());
+
+        let mut config = ConfigFile::load(RepoConfig, &locator)?;
+        config.upsert(RepoSettings::new("dwm"))?;
+        config.save()?;
+
+        assert!(fs::symlink_metadata(&link)?.file_type().is_symlink());
+        let fixture = config_dir.get_file_mut("config.toml")?;
+        fixture.sync()?;
+        assert_eq!(config.to_string(), fixture.as_str());
+        let leftover = fs::read_dir(config_dir.as_path())?
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_name().to_string_lossy().starts_with(".tmp"))
+            .count();
+        assert_eq!(leftover, 0);
+
+        Ok(())
+    }
+
+    #[rstest]
+    fn config_file_transaction_save_edits(config_dir: Result<FixtureHarness>) -> Result<()> {
+        let mut config_dir = config_dir?;
+        let fixture = config_dir.get_file_mut("config.toml")?;
+        let mut locator = MockLocator::new();
+        locator.expect_repos_config().return_const(fixture.as_path().into());
+
+        let mut config = ConfigFile::load(RepoConfig, &locator)?;
+        config.transaction(|config| config.add_new(RepoSettings::new("dwm")))?;
+        fixture.sync()?;
+        assert_eq!(config.to_string(), fixture.as_str());
+        assert!(fixture.as_str().contains("[repos.dwm]"));
+
+        Ok(())
+    }
+
+    #[rstest]
+    fn config_file_upsert_keep_unexpanded_paths() -> Result<()> {
+        let mut harness = FixtureHarness::open()?
+            .with_file("repos.toml", |fixture| {
+                fixture
+                    .with_data(indoc! {r#"
+                        [repos.vim]
+                        branch = "main"
+                        remote = "origin"
+                        workdir_home = false
+                        path = "~/shared/vim.git"
+
+                        [repos.vim.bootstrap]
+                        clone = "$HOME/backup/vim.git"
+                    "#})
+                    .with_kind(FileKind::Normal)
+            })
+            .setup()?;
+        let fixture = harness.get_file_mut("repos.toml")?;
+        let mut locator = MockLocator::new();
+        locator.expect_repos_config().return_const(fixture.as_path().into());
+
+        let mut config = ConfigFile::load(RepoConfig, &locator)?;
+        let vim = config.get("vim")?.branch("master");
+        config.transaction(|config| config.upsert(vim).map(drop))?;
+        fixture.sync()?;
+        assert!(fixture.as_str().contains(r#"branch = "master""#));
+        assert!(fixture.as_str().contains(r#"path = "~/shared/vim.git""#));
+        assert!(fixture.as_str().contains(r#"clone = "$HOME/backup/vim.git""#));
+
+        Ok(())
+    }
+
+    #[rstest]
+    fn config_file_transaction_roll_back_on_err(config_dir: Result<FixtureHarness>) -> Result<()> {
+        let mut config_dir = config_dir?;
+        let fixture = config_dir.get_file_mut("config.toml")?;
+        let expect = fixture.as_str().to_string();
+        let mut locator = MockLocator::new();
+        locator.expect_repos_config().return_const(fixture.as_path().into());
+
+        let mut config = ConfigFile::load(RepoConfig, &locator)?;
+        let result: Result<()> = config.transaction(|config| {
+            config.add_new(RepoSettings::new("dwm"))?;
+            config.remove("vim")?;
+            anyhow::bail!("abort edits");
+        });
+
+        assert!(result.is_err());
+        assert_eq!(config.to_string(), expect);
+        assert!(config.get("vim").is_ok());
+        fixture.sync()?;
+        assert_eq!(fixture.as_str(), expect);
+
+        Ok(())
+    }
+
     #[rstest]
     #[case::repo_config(
         RepoConfig,
@@ -543,6 +1503,71 @@ fn config_file_get_return_setting<E, T>(
         Ok(())
     }
 
+    #[rstest]
+    fn config_file_get_merge_local_overlay(config_dir: Result<FixtureHarness>) -> Result<()> {
+        let config_dir = config_dir?;
+        let mut locator = MockLocator::new();
+        locator.expect_repos_config().return_const(config_dir.as_path().join("overlaid.toml"));
+
+        let config = ConfigFile::load(RepoConfig, &locator)?;
+        let expect = RepoSettings::new("vim").branch("master").remote("mirror").workdir_home(true);
+        assert_eq!(config.get("vim")?, expect);
+        assert_eq!(config.entries()?, vec![expect]);
+
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::groups("groups.toml", vec![
+        RepoGroup::new("desktop").members(["dwm", "x11"]),
+        RepoGroup::new("x11").members(["st"]),
+    ])]
+    #[case::no_groups("config.toml", Vec::new())]
+    fn config_file_groups_return_groups(
+        config_dir: Result<FixtureHarness>,
+        #[case] file: &str,
+        #[case] expect: Vec<RepoGroup>,
+    ) -> Result<()> {
+        let config_dir = config_dir?;
+        let mut locator = MockLocator::new();
+        locator.expect_repos_config().return_const(config_dir.as_path().join(file));
+
+        let config = ConfigFile::load(RepoConfig, &locator)?;
+        assert_eq!(config.groups()?, expect);
+
+        Ok(())
+    }
+
+    #[rstest]
+    fn config_file_save_keep_local_overlay_out(config_dir: Result<FixtureHarness>) -> Result<()> {
+        let mut config_dir = config_dir?;
+        let mut locator = MockLocator::new();
+        locator.expect_repos_config().return_const(config_dir.as_path().join("overlaid.toml"));
+
+        let mut config = ConfigFile::load(RepoConfig, &locator)?;
+        let settings = config.get("vim")?.branch("main");
+        config.upsert(settings)?;
+        config.save()?;
+
+        let expect = indoc! {r#"
+            [repos.vim]
+            branch = "main"
+            remote = "origin"
+            workdir_home = true
+        "#};
+        let fixture = config_dir.get_file_mut("overlaid.toml")?;
+        fixture.sync()?;
+        assert_eq!(fixture.as_str(), expect);
+        assert_eq!(config.get("vim")?.remote, "mirror");
+
+        let overlay = config_dir.get_file_mut("overlaid.local.toml")?;
+        let before = overlay.as_str().to_string();
+        overlay.sync()?;
+        assert_eq!(overlay.as_str(), before);
+
+        Ok(())
+    }
+
     #[rstest]
     #[case::repo_config(RepoConfig)]
     #[case::cmd_hook_config(CmdHookConfig)]
@@ -588,7 +1613,7 @@ fn config_file_new_return_none<E, T>(
         locator.expect_hooks_config().return_const(fixture.as_path().into());
 
         let mut config = ConfigFile::load(config_kind, &locator)?;
-        let result = config.add(entry)?;
+        let result = config.upsert(entry)?;
         config.save()?;
         fixture.sync()?;
         assert_eq!(result, None);
@@ -629,7 +1654,7 @@ fn config_file_new_return_some<E, T>(
         locator.expect_hooks_config().return_const(fixture.as_path().into());
 
         let mut config = ConfigFile::load(config_kind, &locator)?;
-        let result = config.add(entry)?;
+        let result = config.upsert(entry)?;
         config.save()?;
         fixture.sync()?;
         assert_eq!(result, expect);
@@ -652,11 +1677,42 @@ fn config_file_add_return_err_toml(
         locator.expect_hooks_config().return_const(fixture.as_path().into());
 
         let mut config = ConfigFile::load(config_kind, &locator)?;
-        let result = config.add(Default::default());
+        let result = config.upsert(Default::default());
         assert!(matches!(result.unwrap_err(), ConfigFileError::Toml { .. }));
         Ok(())
     }
 
+    #[rstest]
+    #[case::repo_config(RepoConfig, RepoSettings::new("vim").branch("main").remote("upstream"))]
+    #[case::cmd_hook_config(
+        CmdHookConfig,
+        CmdHookSettings::new("bootstrap").add_hook(HookSettings::new().pre("new_hook.sh")),
+    )]
+    fn config_file_add_new_return_err_entry_exists<E, T>(
+        config_dir: Result<FixtureHarness>,
+        #[case] config_kind: T,
+        #[case] entry: E,
+    ) -> Result<()>
+    where
+        E: Settings,
+        T: Config<Entry = E>,
+    {
+        let config_dir = config_dir?;
+        let fixture = config_dir.get_file("config.toml")?;
+        let mut locator = MockLocator::new();
+        locator.expect_repos_config().return_const(fixture.as_path().into());
+        locator.expect_hooks_config().return_const(fixture.as_path().into());
+
+        let mut config = ConfigFile::load(config_kind, &locator)?;
+        let result = config.add_new(entry);
+        assert!(matches!(
+            result.unwrap_err(),
+            ConfigFileError::Toml { source: TomlError::EntryExists { .. }, .. }
+        ));
+        assert_eq!(config.to_string(), fixture.as_str());
+        Ok(())
+    }
+
     #[rstest]
     #[case::repo_config(
         RepoConfig,
@@ -777,4 +1833,176 @@ fn config_file_remove_return_err_toml(
 
         Ok(())
     }
+
+    #[rstest]
+    #[case::disabled("features.toml", "state", false)]
+    #[case::missing_entry("features.toml", "daemon", true)]
+    #[case::missing_table("config.toml", "state", true)]
+    fn feature_config_is_enabled(
+        config_dir: Result<FixtureHarness>,
+        #[case] file: &str,
+        #[case] feature: &str,
+        #[case] expect: bool,
+    ) -> Result<()> {
+        let config_dir = config_dir?;
+        let fixture = config_dir.get_file(file)?;
+        let mut locator = MockLocator::new();
+        locator.expect_ricer_config().return_const(fixture.as_path().into());
+
+        let result = FeatureConfig::is_enabled(&locator, feature)?;
+        assert_eq!(result, expect);
+
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::enabled("features.toml", "history", true)]
+    #[case::missing_entry("features.toml", "daemon", false)]
+    #[case::missing_table("config.toml", "history", false)]
+    fn feature_config_is_opted_in(
+        config_dir: Result<FixtureHarness>,
+        #[case] file: &str,
+        #[case] feature: &str,
+        #[case] expect: bool,
+    ) -> Result<()> {
+        let config_dir = config_dir?;
+        let fixture = config_dir.get_file(file)?;
+        let mut locator = MockLocator::new();
+        locator.expect_ricer_config().return_const(fixture.as_path().into());
+
+        let result = FeatureConfig::is_opted_in(&locator, feature)?;
+        assert_eq!(result, expect);
+
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::configured("ui.toml", UiSettings::new().pager(PagerMode::Never))]
+    #[case::missing_table("features.toml", UiSettings::new())]
+    #[case::missing_file("missing.toml", UiSettings::new())]
+    fn ui_config_load_return_settings(
+        config_dir: Result<FixtureHarness>,
+        #[case] file: &str,
+        #[case] expect: UiSettings,
+    ) -> Result<()> {
+        let config_dir = config_dir?;
+        let path = config_dir.as_path().join(file);
+        let mut locator = MockLocator::new();
+        locator.expect_ricer_config().return_const(path.clone());
+
+        let result = UiConfig::load(&locator)?;
+        assert_eq!(result, expect);
+
+        // INVARIANT: missing configuration files must not be created.
+        assert_eq!(path.exists(), file != "missing.toml");
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::configured("daemon.toml", DaemonSettings::new().interval(Duration::from_secs(300)))]
+    #[case::duration("daemon_duration.toml", DaemonSettings::new().interval(Duration::from_secs(7200)))]
+    #[case::clamp_interval("daemon_too_short.toml", DaemonSettings::new().interval(Duration::ZERO))]
+    #[case::missing_table("ui.toml", DaemonSettings::new())]
+    #[case::missing_file("missing.toml", DaemonSettings::new())]
+    fn daemon_config_load_return_settings(
+        config_dir: Result<FixtureHarness>,
+        #[case] file: &str,
+        #[case] expect: DaemonSettings,
+    ) -> Result<()> {
+        let config_dir = config_dir?;
+        let mut locator = MockLocator::new();
+        locator.expect_ricer_config().return_const(config_dir.as_path().join(file));
+
+        let result = DaemonConfig::load(&locator)?;
+        assert_eq!(result, expect);
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::configured("hook_logs.toml", HookLogSettings::new().max_files(20).max_size(1 << 20))]
+    #[case::invalid("hook_logs_invalid.toml", HookLogSettings::new())]
+    #[case::missing_table("ui.toml", HookLogSettings::new())]
+    #[case::missing_file("missing.toml", HookLogSettings::new())]
+    fn hook_log_config_load_return_settings(
+        config_dir: Result<FixtureHarness>,
+        #[case] file: &str,
+        #[case] expect: HookLogSettings,
+    ) -> Result<()> {
+        let config_dir = config_dir?;
+        let mut locator = MockLocator::new();
+        locator.expect_ricer_config().return_const(config_dir.as_path().join(file));
+
+        let result = HookLogConfig::load(&locator)?;
+        assert_eq!(result, expect);
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::configured(
+        "lint.toml",
+        LintSettings::new()
+            .commit_rule(LintRule::new("scope").pattern("^[a-z0-9_-]+: "))
+            .commit_rule(LintRule::new("2").max_length(72))
+    )]
+    #[case::files(
+        "lint_files.toml",
+        LintSettings::new().files(
+            FileLint::new()
+                .max_size(1 << 20)
+                .binary(false)
+                .action(LintAction::Block)
+                .allow("wallpapers/")
+                .allow("*.ttf")
+        )
+    )]
+    #[case::missing_table("ui.toml", LintSettings::new())]
+    #[case::missing_file("missing.toml", LintSettings::new())]
+    fn lint_config_load_return_settings(
+        config_dir: Result<FixtureHarness>,
+        #[case] file: &str,
+        #[case] expect: LintSettings,
+    ) -> Result<()> {
+        let config_dir = config_dir?;
+        let mut locator = MockLocator::new();
+        locator.expect_ricer_config().return_const(config_dir.as_path().join(file));
+
+        let result = LintConfig::load(&locator)?;
+        assert_eq!(result, expect);
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::status(
+        "commands.toml",
+        "status",
+        CommandSettings::new("status").set("terse", DefaultValue::Bool(true))
+    )]
+    #[case::skip_unusable_values(
+        "commands.toml",
+        "pull",
+        CommandSettings::new("pull")
+            .set("remote", DefaultValue::Str("upstream".into()))
+            .set("jobs", DefaultValue::Int(8))
+    )]
+    #[case::missing_entry("commands.toml", "list", CommandSettings::new("list"))]
+    #[case::missing_table("ui.toml", "status", CommandSettings::new("status"))]
+    #[case::missing_file("missing.toml", "status", CommandSettings::new("status"))]
+    fn command_config_load_return_settings(
+        config_dir: Result<FixtureHarness>,
+        #[case] file: &str,
+        #[case] command: &str,
+        #[case] expect: CommandSettings,
+    ) -> Result<()> {
+        let config_dir = config_dir?;
+        let path = config_dir.as_path().join(file);
+        let mut locator = MockLocator::new();
+        locator.expect_ricer_config().return_const(path.clone());
+
+        let result = CommandConfig::load(&locator, command)?;
+        assert_eq!(result, expect);
+
+        // INVARIANT: missing configuration files must not be created.
+        assert_eq!(path.exists(), file != "missing.toml");
+        Ok(())
+    }
 }
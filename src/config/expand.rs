@@ -0,0 +1,123 @@
+// SPDX-FileCopyrightText: 2024 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+use crate::context::EnvSnapshot;
+
+use std::path::PathBuf;
+
+/// Expand path from configuration file data.
+///
+/// Every path that Ricer reads from its configuration files goes through this
+/// function during deserialization, so all of them support the same forms of
+/// expansion:
+///
+/// - Tilde expansion, e.g., `~/some/path`.
+/// - Environment expansion like `$A` or `${B}` or `${C:-default}`, e.g.,
+///   `$HOME/some/path`.
+/// - Windows environment expansion like `%USERPROFILE%`, but only on Windows.
+///
+/// # Invariants
+///
+/// 1. Undefined environment variables are left as-is instead of failing,
+///    because configuration files can be shared between machines whose
+///    environments differ.
+/// 1. Variables are looked up in the environment snapshot of the run, see
+///    [`EnvSnapshot::current`].
+pub fn expand_path(path: impl AsRef<str>) -> PathBuf {
+    let snapshot = EnvSnapshot::current();
+    PathBuf::from(expand_with(path.as_ref(), cfg!(windows), |var| {
+        snapshot.get(var).map(Into::into)
+    }))
+}
+
+/// Expand `input` through variable lookup `lookup`.
+///
+/// Percent form `%VAR%` is only expanded if `windows` is set.
+pub(crate) fn expand_with(
+    input: &str,
+    windows: bool,
+    lookup: impl Fn(&str) -> Option<String>,
+) -> String {
+    let input = match windows {
+        true => expand_percent(input, &lookup),
+        false => input.to_string(),
+    };
+
+    let home = || lookup("HOME").or_else(|| lookup("USERPROFILE"));
+    shellexpand::full_with_context_no_errors(&input, home, |var| lookup(var)).into_owned()
+}
+
+/// Expand Windows style `%VAR%` variables.
+fn expand_percent(input: &str, lookup: &impl Fn(&str) -> Option<String>) -> String {
+    let mut expanded = String::with_capacity(input.len());
+    let mut rest = input;
+    while let Some(start) = rest.find('%') {
+        expanded.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let value = after
+            .find('%')
+            .map(|end| &after[..end])
+            .filter(|name| is_var_name(name))
+            .and_then(|name| lookup(name).map(|value| (name, value)));
+
+        match value {
+            Some((name, value)) => {
+                expanded.push_str(&value);
+                rest = &after[name.len() + 1..];
+            }
+            // INVARIANT: leave undefined or malformed variables untouched.
+            None => {
+                expanded.push('%');
+                rest = after;
+            }
+        }
+    }
+    expanded.push_str(rest);
+    expanded
+}
+
+fn is_var_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.chars().all(|ch| ch.is_ascii_alphanumeric() || matches!(ch, '_' | '(' | ')'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    fn lookup(var: &str) -> Option<String> {
+        match var {
+            "HOME" => Some("/home/awkless".into()),
+            "USERPROFILE" => Some(r"C:\Users\awkless".into()),
+            "XDG_CONFIG_HOME" => Some("/home/awkless/.config".into()),
+            "ProgramFiles(x86)" => Some(r"C:\Program Files (x86)".into()),
+            _ => None,
+        }
+    }
+
+    #[rstest]
+    #[case::tilde("~/.vim", "/home/awkless/.vim")]
+    #[case::var("$XDG_CONFIG_HOME/nvim", "/home/awkless/.config/nvim")]
+    #[case::braced_var("${HOME}/src", "/home/awkless/src")]
+    #[case::default("${NOPE:-/opt}/dwm", "/opt/dwm")]
+    #[case::undefined("$NOPE/dwm", "$NOPE/dwm")]
+    #[case::percent_ignored("%USERPROFILE%/dwm", "%USERPROFILE%/dwm")]
+    #[case::plain("/etc/ricer", "/etc/ricer")]
+    fn expand_with_unix(#[case] input: &str, #[case] expect: &str) {
+        assert_eq!(expand_with(input, false, lookup), expect);
+    }
+
+    #[rstest]
+    #[case::percent(r"%USERPROFILE%\dotfiles", r"C:\Users\awkless\dotfiles")]
+    #[case::percent_parens(r"%ProgramFiles(x86)%\Git", r"C:\Program Files (x86)\Git")]
+    #[case::percent_undefined(r"%NOPE%\dwm", r"%NOPE%\dwm")]
+    #[case::percent_unclosed(r"100%\dwm", r"100%\dwm")]
+    #[case::percent_twice("%HOME%%HOME%", "/home/awkless/home/awkless")]
+    #[case::mixed(r"%USERPROFILE%\$NOPE", r"C:\Users\awkless\$NOPE")]
+    fn expand_with_windows(#[case] input: &str, #[case] expect: &str) {
+        assert_eq!(expand_with(input, true, lookup), expect);
+    }
+}
@@ -1,10 +1,24 @@
 // SPDX-FileCopyrightText: 2024 Jason Pena <jasonpena@awkless.com>
 // SPDX-License-Identifier: MIT
 
-use std::{cmp, fmt, path::PathBuf};
+use crate::{
+    config::{duration_item, expand_path, size_item, ValueError},
+    context::HookAction,
+    deprecate,
+    json::Json,
+    vcs::UntrackedFiles,
+};
+
+use log::warn;
+use std::{
+    cmp, fmt,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
 use toml_edit::{
-    visit::{visit_inline_table, visit_table_like_kv, Visit},
-    Array, InlineTable, Item, Key, Table, Value,
+    visit::{visit_table_like_kv, Visit},
+    Array, InlineTable, Item, Key, Table, TableLike, Value,
 };
 
 /// Serialize and deserialize configuration settings.
@@ -31,8 +45,43 @@ pub struct RepoSettings {
     /// directory through _bare_ technique.
     pub workdir_home: bool,
 
+    /// How files of repository are deployed into the user's home directory.
+    pub deploy_mode: DeployMode,
+
+    /// How status looks for untracked files, overriding the default of the
+    /// repository kind.
+    pub untracked: Option<UntrackedFiles>,
+
+    /// Most permissive hook action for commands that target repository.
+    pub hooks: Option<HookAction>,
+
     /// Bootstrap configuration for repository.
     pub bootstrap: Option<BootstrapSettings>,
+
+    /// Path to Git directory of repository, overriding its default location
+    /// in the repository directory.
+    ///
+    /// Kept as written, e.g., `~/shared/vim.git`, so saving settings never
+    /// turns it into a path of the current machine. Expanded through
+    /// [`RepoSettings::gitdir`].
+    pub path: Option<PathBuf>,
+
+    /// Commit or tag to keep repository at, instead of tracking its branch.
+    pub pin: Option<String>,
+
+    /// Push to default remote right after every commit.
+    pub push_on_commit: bool,
+
+    /// Refuse every command that would change repository.
+    pub readonly: bool,
+
+    /// Gitignore-style patterns of files that must never be committed, e.g.,
+    /// private keys.
+    pub exclude: Vec<String>,
+
+    /// Branches to use instead of default branch on some hosts, in the order
+    /// they were listed.
+    pub host_branches: Vec<(String, String)>,
 }
 
 impl RepoSettings {
@@ -42,7 +91,16 @@ pub fn new(name: impl Into<String>) -> Self {
             branch: Default::default(),
             remote: Default::default(),
             workdir_home: Default::default(),
+            deploy_mode: Default::default(),
+            untracked: Default::default(),
+            hooks: Default::default(),
             bootstrap: Default::default(),
+            path: Default::default(),
+            pin: Default::default(),
+            push_on_commit: Default::default(),
+            readonly: Default::default(),
+            exclude: Default::default(),
+            host_branches: Default::default(),
         }
     }
 
@@ -61,10 +119,110 @@ pub fn workdir_home(mut self, choice: bool) -> Self {
         self
     }
 
+    pub fn deploy_mode(mut self, mode: DeployMode) -> Self {
+        self.deploy_mode = mode;
+        self
+    }
+
+    pub fn untracked(mut self, untracked: UntrackedFiles) -> Self {
+        self.untracked = Some(untracked);
+        self
+    }
+
+    pub fn hooks(mut self, action: HookAction) -> Self {
+        self.hooks = Some(action);
+        self
+    }
+
     pub fn bootstrap(mut self, bootstrap: BootstrapSettings) -> Self {
         self.bootstrap = Some(bootstrap);
         self
     }
+
+    pub fn path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.path = Some(path.into());
+        self
+    }
+
+    pub fn pin(mut self, rev: impl Into<String>) -> Self {
+        self.pin = Some(rev.into());
+        self
+    }
+
+    pub fn push_on_commit(mut self, choice: bool) -> Self {
+        self.push_on_commit = choice;
+        self
+    }
+
+    pub fn readonly(mut self, choice: bool) -> Self {
+        self.readonly = choice;
+        self
+    }
+
+    /// Refuse to commit files matching gitignore-style `pattern`.
+    ///
+    /// Can be called multiple times to exclude more files.
+    pub fn exclude(mut self, pattern: impl Into<String>) -> Self {
+        self.exclude.push(pattern.into());
+        self
+    }
+
+    /// Use `branch` instead of default branch on `host`.
+    ///
+    /// Replaces branch set for `host` before, if any.
+    pub fn host_branch(mut self, host: impl Into<String>, branch: impl Into<String>) -> Self {
+        let (host, branch) = (host.into(), branch.into());
+        match self.host_branches.iter_mut().find(|(name, _)| *name == host) {
+            Some((_, current)) => *current = branch,
+            None => self.host_branches.push((host, branch)),
+        }
+        self
+    }
+
+    /// Branch to use on `host`, falling back to default branch.
+    pub fn branch_for(&self, host: Option<&str>) -> &str {
+        self.host_branches
+            .iter()
+            .find(|(name, _)| Some(name.as_str()) == host)
+            .map_or(self.branch.as_str(), |(_, branch)| branch.as_str())
+    }
+
+    /// Path to Git directory of repository in `repos_dir`, unless overridden
+    /// through [`RepoSettings::path`], which gets expanded, see
+    /// [`expand_path`].
+    pub fn gitdir(&self, repos_dir: &Path) -> PathBuf {
+        match &self.path {
+            Some(path) => expand_path(path.to_string_lossy()),
+            None => repos_dir.join(format!("{}.git", self.name)),
+        }
+    }
+
+    /// Serialize settings into JSON object for machine-readable output.
+    ///
+    /// Unlike [`Settings::to_toml`], every setting is present, with `null` for
+    /// settings that are not set.
+    pub fn to_json(&self) -> Json {
+        Json::object()
+            .with("name", &self.name)
+            .with("branch", &self.branch)
+            .with("remote", &self.remote)
+            .with("workdir_home", self.workdir_home)
+            .with("deploy_mode", self.deploy_mode.to_string())
+            .with("untracked", self.untracked.map(|untracked| untracked.to_string()))
+            .with("run_hooks", self.hooks.map(|action| action.to_string()))
+            .with("path", self.path.as_ref().map(|path| path.to_string_lossy().into_owned()))
+            .with("pin", self.pin.clone())
+            .with("push_on_commit", self.push_on_commit)
+            .with("readonly", self.readonly)
+            .with("exclude", self.exclude.clone())
+            .with(
+                "host_branches",
+                self.host_branches
+                    .iter()
+                    .fold(Json::object(), |json, (host, branch)| json.with(host, branch)),
+            )
+            .with("bootstrap", self.bootstrap.as_ref().map(BootstrapSettings::to_json))
+    }
 }
 
 impl Settings for RepoSettings {
@@ -75,6 +233,41 @@ fn to_toml(&self) -> (Key, Item) {
         repo.insert("branch", Item::Value(Value::from(&self.branch)));
         repo.insert("remote", Item::Value(Value::from(&self.remote)));
         repo.insert("workdir_home", Item::Value(Value::from(self.workdir_home)));
+        if self.deploy_mode != DeployMode::default() {
+            repo.insert("deploy_mode", Item::Value(Value::from(self.deploy_mode.to_string())));
+        }
+        if let Some(untracked) = &self.untracked {
+            repo.insert("untracked", Item::Value(Value::from(untracked.to_string())));
+        }
+        if let Some(hooks) = &self.hooks {
+            repo.insert("run_hooks", Item::Value(Value::from(hooks.to_string())));
+        }
+        if let Some(path) = &self.path {
+            repo.insert("path", Item::Value(Value::from(String::from(path.to_string_lossy()))));
+        }
+        if let Some(pin) = &self.pin {
+            repo.insert("pin", Item::Value(Value::from(pin)));
+        }
+        if self.push_on_commit {
+            repo.insert("push_on_commit", Item::Value(Value::from(true)));
+        }
+        if self.readonly {
+            repo.insert("readonly", Item::Value(Value::from(true)));
+        }
+        if !self.exclude.is_empty() {
+            let patterns = Array::from_iter(&self.exclude);
+            repo.insert("exclude", Item::Value(Value::Array(patterns)));
+        }
+        if !self.host_branches.is_empty() {
+            let branches = self
+                .host_branches
+                .iter()
+                .map(|(host, branch)| (Key::new(host), Value::from(branch)));
+            repo.insert(
+                "host_branches",
+                Item::Value(Value::InlineTable(InlineTable::from_iter(branches))),
+            );
+        }
         if let Some(bootstrap) = &self.bootstrap {
             if let Some(clone) = &bootstrap.clone {
                 repo_bootstrap.insert("clone", Item::Value(Value::from(clone)));
@@ -88,6 +281,9 @@ fn to_toml(&self) -> (Key, Item) {
             if let Some(hosts) = &bootstrap.hosts {
                 repo_bootstrap.insert("hosts", Item::Value(Value::Array(Array::from_iter(hosts))));
             }
+            if let Some(on_clone) = &bootstrap.on_clone {
+                repo_bootstrap.insert("on_clone", Item::Value(Value::from(on_clone)));
+            }
             repo.insert("bootstrap", Item::Table(repo_bootstrap));
         }
 
@@ -127,20 +323,75 @@ fn from(entry: (Key, Item)) -> Self {
 
 impl<'toml> Visit<'toml> for RepoSettings {
     fn visit_table_like_kv(&mut self, key: &'toml str, node: &'toml Item) {
-        match key {
+        match deprecate::current_key("repos.*", key) {
             "branch" => self.branch = node.as_str().unwrap_or_default().to_string(),
             "remote" => self.remote = node.as_str().unwrap_or_default().to_string(),
             "workdir_home" => self.workdir_home = node.as_bool().unwrap_or_default(),
+            "deploy_mode" => {
+                self.deploy_mode =
+                    node.as_str().and_then(|mode| mode.parse().ok()).unwrap_or_default()
+            }
+            "untracked" => self.untracked = node.as_str().and_then(|mode| mode.parse().ok()),
+            "run_hooks" => self.hooks = node.as_str().and_then(|action| action.parse().ok()),
+            "path" => self.path = node.as_str().map(PathBuf::from),
+            "pin" => self.pin = node.as_str().map(Into::into),
+            "push_on_commit" => self.push_on_commit = node.as_bool().unwrap_or_default(),
+            "readonly" => self.readonly = node.as_bool().unwrap_or_default(),
+            "exclude" => self.exclude = coerce_str_array(node).unwrap_or_default(),
+            "host_branches" => {
+                let branches = node.as_table_like().into_iter().flat_map(|table| table.iter());
+                self.host_branches = branches
+                    .filter_map(|(host, branch)| Some((host.into(), branch.as_str()?.into())))
+                    .collect();
+                // INVARIANT: never descend into host names, they are no settings.
+                return;
+            }
             &_ => visit_table_like_kv(self, key, node),
         }
         visit_table_like_kv(self, key, node);
     }
 }
 
+/// Deployment of repository files into the user's home directory.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum DeployMode {
+    /// Check out files directly into their working directory.
+    #[default]
+    Worktree,
+
+    /// Check out files into the repository directory, and link them into
+    /// the home directory, like GNU Stow.
+    Symlink,
+}
+
+impl FromStr for DeployMode {
+    type Err = String;
+
+    fn from_str(mode: &str) -> Result<Self, Self::Err> {
+        match mode {
+            "worktree" => Ok(DeployMode::Worktree),
+            "symlink" => Ok(DeployMode::Symlink),
+            _ => Err(format!("invalid deploy mode '{mode}'")),
+        }
+    }
+}
+
+impl fmt::Display for DeployMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeployMode::Worktree => write!(f, "worktree"),
+            DeployMode::Symlink => write!(f, "symlink"),
+        }
+    }
+}
+
 /// Repository bootstrap configuration settings.
 #[derive(Debug, Default, Eq, PartialEq, Clone)]
 pub struct BootstrapSettings {
     /// URL to clone repository from.
+    ///
+    /// Kept as written, like [`RepoSettings::path`]. Local paths are
+    /// expanded through [`BootstrapSettings::clone_url`].
     pub clone: Option<String>,
 
     /// Bootstrap repository if and only if user is using a specific OS.
@@ -153,6 +404,10 @@ pub struct BootstrapSettings {
     /// Bootstrap repository if and only if user is logged on to a specific
     /// set of hosts.
     pub hosts: Option<Vec<String>>,
+
+    /// Setup hook script in `hooks/` directory to execute once repository has
+    /// been cloned through `ricer clone`.
+    pub on_clone: Option<String>,
 }
 
 impl BootstrapSettings {
@@ -160,11 +415,34 @@ pub fn new() -> Self {
         Default::default()
     }
 
+    /// Serialize settings into JSON object, see [`RepoSettings::to_json`].
+    pub fn to_json(&self) -> Json {
+        Json::object()
+            .with("clone", self.clone.clone())
+            .with("os", self.os.map(|os| os.to_string()))
+            .with("users", self.users.clone())
+            .with("hosts", self.hosts.clone())
+            .with("on_clone", self.on_clone.clone())
+    }
+
     pub fn clone(mut self, url: impl Into<String>) -> Self {
         self.clone = Some(url.into());
         self
     }
 
+    /// URL to clone repository from, with local paths expanded, see
+    /// [`expand_path`].
+    ///
+    /// Remote URLs like `https://host/repo` or `git@host:repo` are left as-is.
+    /// Surrounding whitespace is trimmed either way.
+    pub fn clone_url(&self) -> Option<String> {
+        let source = self.clone.as_deref()?.trim();
+        match RemoteKind::of(source) {
+            RemoteKind::Local => Some(expand_path(source).to_string_lossy().into_owned()),
+            _ => Some(source.to_string()),
+        }
+    }
+
     pub fn os(mut self, os: OsType) -> Self {
         self.os = Some(os);
         self
@@ -192,49 +470,437 @@ pub fn hosts<I, S>(mut self, hosts: I) -> Self
         self
     }
 
+    pub fn on_clone(mut self, script: impl Into<String>) -> Self {
+        self.on_clone = Some(script.into());
+        self
+    }
+
     pub fn is_empty(&self) -> bool {
-        self.clone.is_none() && self.os.is_none() && self.users.is_none() && self.hosts.is_none()
+        self.clone.is_none()
+            && self.os.is_none()
+            && self.users.is_none()
+            && self.hosts.is_none()
+            && self.on_clone.is_none()
     }
 }
 
 impl<'toml> Visit<'toml> for BootstrapSettings {
     fn visit_table_like_kv(&mut self, key: &'toml str, node: &'toml Item) {
         match key {
-            "clone" => {
-                if let Some(clone) = node.as_str() {
-                    self.clone = Some(clone.to_string())
-                }
-            }
+            "clone" => self.clone = node.as_str().map(String::from),
             "os" => {
                 if let Some(os) = node.as_str() {
                     self.os = Some(OsType::from(os))
                 }
             }
-            "users" => {
-                if let Some(users) = node.as_array() {
-                    let data = users
-                        .into_iter()
-                        .map(|s| {
-                            s.as_str().unwrap().trim_matches(|c| c == '\"' || c == '\'').to_string()
-                        })
-                        .collect();
-                    self.users = Some(data)
+            "users" => self.users = coerce_str_array(node),
+            "hosts" => self.hosts = coerce_str_array(node),
+            "on_clone" => self.on_clone = node.as_str().map(String::from),
+            &_ => visit_table_like_kv(self, key, node),
+        }
+        visit_table_like_kv(self, key, node);
+    }
+}
+
+/// URL schemes Git remotes may use.
+const REMOTE_SCHEMES: &[&str] = &["https", "http", "ssh", "git", "git+ssh", "ssh+git", "file"];
+
+/// Syntax of a remote.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RemoteKind {
+    /// URL with a scheme, like `https://host/repo`.
+    Url,
+
+    /// scp-like syntax, like `git@host:repo`.
+    Scp,
+
+    /// Path on local file system.
+    Local,
+}
+
+impl RemoteKind {
+    fn of(remote: &str) -> Self {
+        if remote.contains("://") {
+            return RemoteKind::Url;
+        }
+        match remote.split_once(':') {
+            Some((host, _)) if !host.is_empty() && !host.contains('/') => RemoteKind::Scp,
+            _ => RemoteKind::Local,
+        }
+    }
+}
+
+/// Error types for [`normalize_remote_url`].
+#[derive(Debug, thiserror::Error, Eq, PartialEq)]
+pub enum RemoteUrlError {
+    #[error("Remote is empty")]
+    Empty,
+
+    #[error("Scheme '{scheme}' is not one of {}", REMOTE_SCHEMES.join(", "))]
+    BadScheme { scheme: String },
+
+    #[error("Remote has no host")]
+    NoHost,
+
+    #[error("Remote has no path")]
+    NoPath,
+
+    #[error("Remote contains whitespace")]
+    Whitespace,
+}
+
+/// Validate and normalize `remote` to clone or fetch from.
+///
+/// Accepts URLs of the schemes Git supports, scp-like syntax like
+/// `git@host:repo`, and local paths, which are left to fail when cloned.
+/// Will return `remote` without surrounding whitespace. Remotes using plain
+/// HTTP are accepted with a warning.
+///
+/// # Errors
+///
+/// - Return [`RemoteUrlError::Empty`] if `remote` is blank.
+/// - Return [`RemoteUrlError::BadScheme`] if URL scheme is not supported,
+///   e.g., a typo like `htps`.
+/// - Return [`RemoteUrlError::NoHost`] if URL or scp-like syntax has no host.
+/// - Return [`RemoteUrlError::NoPath`] if scp-like syntax has no path.
+/// - Return [`RemoteUrlError::Whitespace`] if URL or scp-like syntax contains
+///   whitespace.
+pub fn normalize_remote_url(remote: &str) -> Result<String, RemoteUrlError> {
+    let remote = remote.trim();
+    if remote.is_empty() {
+        return Err(RemoteUrlError::Empty);
+    }
+
+    let kind = RemoteKind::of(remote);
+    if kind != RemoteKind::Local && remote.contains(char::is_whitespace) {
+        return Err(RemoteUrlError::Whitespace);
+    }
+
+    match kind {
+        RemoteKind::Url => {
+            // INVARIANT: URL kind always contains a scheme separator.
+            let (scheme, rest) = remote.split_once("://").unwrap_or_default();
+            let scheme = scheme.to_ascii_lowercase();
+            if !REMOTE_SCHEMES.contains(&scheme.as_str()) {
+                return Err(RemoteUrlError::BadScheme { scheme });
+            }
+            let host = rest.split('/').next().unwrap_or_default();
+            if host.is_empty() && scheme != "file" {
+                return Err(RemoteUrlError::NoHost);
+            }
+            if scheme == "http" {
+                warn!("Remote '{remote}' uses plain HTTP, consider HTTPS instead");
+            }
+        }
+        RemoteKind::Scp => {
+            let (host, path) = remote.split_once(':').unwrap_or_default();
+            if host.ends_with('@') {
+                return Err(RemoteUrlError::NoHost);
+            }
+            if path.is_empty() {
+                return Err(RemoteUrlError::NoPath);
+            }
+        }
+        RemoteKind::Local => (),
+    }
+
+    Ok(remote.to_string())
+}
+
+/// Error types for [`validate_repo_name`].
+#[derive(Debug, thiserror::Error, Eq, PartialEq)]
+pub enum RepoNameError {
+    #[error("Repository name is empty")]
+    Empty,
+
+    #[error("Repository name starts with '.'")]
+    Hidden,
+
+    #[error("Repository name contains a path separator")]
+    Separator,
+
+    #[error("Repository name contains '..'")]
+    ParentDir,
+}
+
+/// Validate `name` of repository.
+///
+/// Repository names become file names in the repository directory, and keys
+/// of the repository configuration file, so they must stay a single plain
+/// file name.
+///
+/// # Errors
+///
+/// - Return [`RepoNameError::Empty`] if `name` is empty.
+/// - Return [`RepoNameError::Hidden`] if `name` starts with `.`.
+/// - Return [`RepoNameError::Separator`] if `name` contains `/` or `\`.
+/// - Return [`RepoNameError::ParentDir`] if `name` contains `..`.
+pub fn validate_repo_name(name: &str) -> Result<(), RepoNameError> {
+    if name.is_empty() {
+        return Err(RepoNameError::Empty);
+    }
+    if name.starts_with('.') {
+        return Err(RepoNameError::Hidden);
+    }
+    if name.contains(['/', '\\']) {
+        return Err(RepoNameError::Separator);
+    }
+    if name.contains("..") {
+        return Err(RepoNameError::ParentDir);
+    }
+    Ok(())
+}
+
+/// Coerce `node` into an array of strings.
+///
+/// A lone string is treated as an array of one, e.g., `users = "awkless"` is
+/// the same as `users = ["awkless"]`. Elements that are not strings are
+/// skipped, since [`RepoSettings::validate`] reports them.
+fn coerce_str_array(node: &Item) -> Option<Vec<String>> {
+    let trim = |s: &str| s.trim_matches(|c| c == '\"' || c == '\'').to_string();
+    match node.as_value()? {
+        Value::String(string) => Some(vec![trim(string.value())]),
+        Value::Array(array) => Some(array.iter().filter_map(Value::as_str).map(trim).collect()),
+        _ => None,
+    }
+}
+
+/// Named group of repositories, i.e., a meta-repository.
+///
+/// Defined through `[groups.<name>]` tables of the repository configuration
+/// file, whose `members` name registered repositories, glob patterns of them,
+/// or other groups.
+#[derive(Debug, Default, Eq, PartialEq, Clone)]
+pub struct RepoGroup {
+    /// Name of group.
+    pub name: String,
+
+    /// Repositories, patterns, or groups, in order of configuration file.
+    pub members: Vec<String>,
+}
+
+impl RepoGroup {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self { name: name.into(), members: Vec::new() }
+    }
+
+    pub fn members<I, S>(mut self, members: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.members = members.into_iter().map(Into::into).collect();
+        self
+    }
+}
+
+impl<'toml> From<(&'toml Key, &'toml Item)> for RepoGroup {
+    fn from(entry: (&'toml Key, &'toml Item)) -> Self {
+        let (key, value) = entry;
+        let members = value.get("members").and_then(coerce_str_array).unwrap_or_default();
+        Self { name: key.get().into(), members }
+    }
+}
+
+/// Error types for settings validation.
+///
+/// Every error carries the dotted path to the offending value, e.g.,
+/// `vim.bootstrap.users[0]`, so it can be found in the configuration file.
+#[derive(Debug, thiserror::Error, Eq, PartialEq)]
+pub enum SettingsError {
+    #[error("Expected '{key}' to be {expect}, found {found}")]
+    BadType { key: String, expect: &'static str, found: &'static str },
+
+    #[error("Expected element '{key}[{index}]' to be {expect}, found {found}")]
+    BadElement { key: String, index: usize, expect: &'static str, found: &'static str },
+
+    #[error("Invalid remote '{url}' at '{key}'")]
+    BadUrl { key: String, url: String, source: RemoteUrlError },
+
+    #[error("Invalid value at '{key}'")]
+    BadValue { key: String, source: ValueError },
+}
+
+/// Expected shape of a settings value.
+#[derive(Debug, Clone, Copy)]
+pub(super) enum Expect {
+    Str,
+    Bool,
+    /// Array of strings, or a lone string coerced into one.
+    StrArray,
+    /// String holding a remote to clone from.
+    Remote,
+    /// Integer of seconds, or string holding a duration like `"90s"`.
+    Duration,
+    Table(&'static [(&'static str, Expect)]),
+    /// Table of arbitrary keys, whose values all share one shape.
+    Map(&'static Expect),
+    /// Array, whose elements all share one shape.
+    Array(&'static Expect),
+}
+
+impl Expect {
+    pub(super) fn describe(self) -> &'static str {
+        match self {
+            Expect::Str | Expect::Remote => "a string",
+            Expect::Duration => "a duration",
+            Expect::Bool => "a boolean",
+            Expect::StrArray => "an array of strings",
+            Expect::Table(_) | Expect::Map(_) => "a table",
+            Expect::Array(_) => "an array",
+        }
+    }
+}
+
+pub(super) const REPO_SCHEMA: &[(&str, Expect)] = &[
+    ("branch", Expect::Str),
+    ("remote", Expect::Str),
+    ("workdir_home", Expect::Bool),
+    ("deploy_mode", Expect::Str),
+    ("untracked", Expect::Str),
+    ("run_hooks", Expect::Str),
+    ("hooks", Expect::Str),
+    ("path", Expect::Str),
+    ("pin", Expect::Str),
+    ("push_on_commit", Expect::Bool),
+    ("readonly", Expect::Bool),
+    ("exclude", Expect::StrArray),
+    ("host_branches", Expect::Map(&Expect::Str)),
+    ("bootstrap", Expect::Table(BOOTSTRAP_SCHEMA)),
+];
+
+const BOOTSTRAP_SCHEMA: &[(&str, Expect)] = &[
+    ("clone", Expect::Remote),
+    ("os", Expect::Str),
+    ("users", Expect::StrArray),
+    ("hosts", Expect::StrArray),
+    ("on_clone", Expect::Str),
+];
+
+pub(super) const GROUP_SCHEMA: &[(&str, Expect)] = &[("members", Expect::StrArray)];
+
+pub(super) const HOOK_SCHEMA: &[(&str, Expect)] = &[
+    ("pre", Expect::StrArray),
+    ("post", Expect::StrArray),
+    ("workdir", Expect::Str),
+    ("name", Expect::Str),
+    ("sandbox", Expect::Bool),
+    ("env_allow", Expect::StrArray),
+    ("env_deny", Expect::StrArray),
+    ("env", Expect::Map(&Expect::Str)),
+    ("scope", Expect::Str),
+    ("parallel", Expect::Bool),
+    ("depends", Expect::StrArray),
+    ("interpreter", Expect::Str),
+];
+
+impl RepoSettings {
+    /// Validate types of repository settings entry.
+    ///
+    /// Deserialization through [`From`] never fails, and falls back to
+    /// defaults for values of the wrong type. Validation catches these values
+    /// instead, so the user learns about mistakes like `users = [42]`.
+    ///
+    /// # Invariants
+    ///
+    /// Unknown keys are not validated, so newer configuration files still work
+    /// with older versions of Ricer.
+    ///
+    /// # Errors
+    ///
+    /// - Return [`SettingsError::BadType`] if a value has the wrong type.
+    /// - Return [`SettingsError::BadElement`] if an array element has the wrong
+    ///   type.
+    /// - Return [`SettingsError::BadUrl`] if bootstrap clone URL is invalid.
+    pub fn validate(entry: (&Key, &Item)) -> Result<(), SettingsError> {
+        let (key, value) = entry;
+        validate_item(key.get(), value, Expect::Table(REPO_SCHEMA))
+    }
+}
+
+pub(super) fn validate_item(key: &str, node: &Item, expect: Expect) -> Result<(), SettingsError> {
+    let bad_type = || SettingsError::BadType {
+        key: key.into(),
+        expect: expect.describe(),
+        found: item_kind(node),
+    };
+    match expect {
+        Expect::Str if node.is_str() => Ok(()),
+        Expect::Bool if node.is_bool() => Ok(()),
+        Expect::Remote if node.is_str() => {
+            let url = node.as_str().unwrap_or_default();
+            match normalize_remote_url(url) {
+                Ok(_) => Ok(()),
+                Err(err) => {
+                    Err(SettingsError::BadUrl { key: key.into(), url: url.into(), source: err })
+                }
+            }
+        }
+        Expect::Duration => match duration_item(node) {
+            Ok(_) => Ok(()),
+            Err(err) => Err(SettingsError::BadValue { key: key.into(), source: err }),
+        },
+        Expect::StrArray if node.is_str() => Ok(()),
+        Expect::StrArray => {
+            let array = node.as_array().ok_or_else(bad_type)?;
+            for (index, element) in array.iter().enumerate() {
+                if !element.is_str() {
+                    return Err(SettingsError::BadElement {
+                        key: key.into(),
+                        index,
+                        expect: "a string",
+                        found: value_kind(element),
+                    });
                 }
             }
-            "hosts" => {
-                if let Some(hosts) = node.as_array() {
-                    let data = hosts
-                        .into_iter()
-                        .map(|s| {
-                            s.as_str().unwrap().trim_matches(|c| c == '\"' || c == '\'').to_string()
-                        })
-                        .collect();
-                    self.hosts = Some(data)
+            Ok(())
+        }
+        Expect::Table(schema) => {
+            let table = node.as_table_like().ok_or_else(bad_type)?;
+            for (name, expect) in schema {
+                if let Some(node) = table.get(name) {
+                    validate_item(&format!("{key}.{name}"), node, *expect)?;
                 }
             }
-            &_ => visit_table_like_kv(self, key, node),
+            Ok(())
         }
-        visit_table_like_kv(self, key, node);
+        Expect::Map(expect) => {
+            let table = node.as_table_like().ok_or_else(bad_type)?;
+            for (name, node) in table.iter() {
+                validate_item(&format!("{key}.{name}"), node, *expect)?;
+            }
+            Ok(())
+        }
+        Expect::Array(expect) => {
+            let array = node.as_array().ok_or_else(bad_type)?;
+            for (index, element) in array.iter().enumerate() {
+                let element = Item::Value(element.clone());
+                validate_item(&format!("{key}[{index}]"), &element, *expect)?;
+            }
+            Ok(())
+        }
+        _ => Err(bad_type()),
+    }
+}
+
+fn item_kind(node: &Item) -> &'static str {
+    match node {
+        Item::None => "nothing",
+        Item::Value(value) => value_kind(value),
+        Item::Table(_) => "a table",
+        Item::ArrayOfTables(_) => "an array of tables",
+    }
+}
+
+fn value_kind(value: &Value) -> &'static str {
+    match value {
+        Value::String(_) => "a string",
+        Value::Integer(_) => "an integer",
+        Value::Float(_) => "a float",
+        Value::Boolean(_) => "a boolean",
+        Value::Datetime(_) => "a datetime",
+        Value::Array(_) => "an array",
+        Value::InlineTable(_) => "an inline table",
     }
 }
 
@@ -320,18 +986,58 @@ fn to_toml(&self) -> (Key, Item) {
                 decor.set_suffix("\n");
             }
 
-            if let Some(pre) = &hook.pre {
-                inline.insert("pre", Value::from(pre));
+            if let Some(pre) = scripts_to_value(&hook.pre) {
+                inline.insert("pre", pre);
             }
 
-            if let Some(post) = &hook.post {
-                inline.insert("post", Value::from(post));
+            if let Some(post) = scripts_to_value(&hook.post) {
+                inline.insert("post", post);
             }
 
             if let Some(workdir) = &hook.workdir {
                 inline.insert("workdir", Value::from(String::from(workdir.to_string_lossy())));
             }
 
+            if let Some(name) = &hook.name {
+                inline.insert("name", Value::from(name));
+            }
+
+            // INVARIANT: only opt-in sandboxing is serialized.
+            if hook.sandbox {
+                inline.insert("sandbox", Value::from(true));
+            }
+
+            if let Some(allow) = scripts_to_value(&hook.env_allow) {
+                inline.insert("env_allow", allow);
+            }
+
+            if let Some(deny) = scripts_to_value(&hook.env_deny) {
+                inline.insert("env_deny", deny);
+            }
+
+            if !hook.env.is_empty() {
+                let vars = hook.env.iter().map(|(key, value)| (Key::new(key), Value::from(value)));
+                inline.insert("env", Value::InlineTable(InlineTable::from_iter(vars)));
+            }
+
+            // INVARIANT: only opt-in repository scope is serialized.
+            if hook.scope != HookScope::Command {
+                inline.insert("scope", Value::from(hook.scope.to_string()));
+            }
+
+            // INVARIANT: only opt-in parallel execution is serialized.
+            if hook.parallel {
+                inline.insert("parallel", Value::from(true));
+            }
+
+            if let Some(depends) = scripts_to_value(&hook.depends) {
+                inline.insert("depends", depends);
+            }
+
+            if let Some(interpreter) = &hook.interpreter {
+                inline.insert("interpreter", Value::from(interpreter));
+            }
+
             tables.push_formatted(Value::from(inline));
         }
 
@@ -364,29 +1070,123 @@ fn from(entry: (Key, Item)) -> Self {
 impl<'toml> Visit<'toml> for CmdHookSettings {
     fn visit_inline_table(&mut self, node: &'toml InlineTable) {
         let hook = HookSettings {
-            pre: node.get("pre").and_then(|s| s.as_str().map(|s| s.into())),
-            post: node.get("post").and_then(|s| s.as_str().map(|s| s.into())),
-            workdir: node.get("workdir").and_then(|s| s.as_str().map(|s| s.into())),
+            pre: scripts_from_value(node.get("pre")),
+            post: scripts_from_value(node.get("post")),
+            workdir: node.get("workdir").and_then(|s| s.as_str().map(expand_path)),
+            name: node.get("name").and_then(|s| s.as_str().map(|s| s.into())),
+            sandbox: node.get("sandbox").and_then(|s| s.as_bool()).unwrap_or_default(),
+            env_allow: scripts_from_value(node.get("env_allow")),
+            env_deny: scripts_from_value(node.get("env_deny")),
+            env: vars_from_value(node.get("env")),
+            scope: node
+                .get("scope")
+                .and_then(|s| s.as_str())
+                .and_then(|s| s.parse().ok())
+                .unwrap_or_default(),
+            parallel: node.get("parallel").and_then(|s| s.as_bool()).unwrap_or_default(),
+            depends: scripts_from_value(node.get("depends")),
+            interpreter: node.get("interpreter").and_then(|s| s.as_str().map(|s| s.into())),
         };
+        // INVARIANT: never descend into the `env` table, it is no hook definition.
         self.hooks.push(hook);
-        visit_inline_table(self, node);
     }
 }
 
+/// Serialize hook script listing in string-or-array form.
+///
+/// A single script is serialized as a plain string to keep the original scalar
+/// form of hook entries intact. Multiple scripts are serialized as an array.
+fn scripts_to_value(scripts: &[String]) -> Option<Value> {
+    match scripts {
+        [] => None,
+        [script] => Some(Value::from(script)),
+        scripts => Some(Value::Array(Array::from_iter(scripts))),
+    }
+}
+
+/// Deserialize hook script listing in string-or-array form.
+///
+/// Non-string array elements are ignored.
+fn scripts_from_value(value: Option<&Value>) -> Vec<String> {
+    match value {
+        Some(Value::String(script)) => vec![script.value().clone()],
+        Some(Value::Array(scripts)) => {
+            scripts.iter().filter_map(|s| s.as_str().map(|s| s.to_string())).collect()
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// Deserialize variables of inline table.
+///
+/// Variables whose value is not a string are ignored.
+fn vars_from_value(value: Option<&Value>) -> Vec<(String, String)> {
+    let Some(vars) = value.and_then(Value::as_inline_table) else {
+        return Vec::new();
+    };
+    vars.iter().filter_map(|(key, value)| Some((key.to_string(), value.as_str()?.into()))).collect()
+}
+
 /// Hook definition settings.
 ///
 /// An intermediary structure to help deserialize and serialize hook entries
 /// for command hook settings in command hook configuration file.
+///
+/// Both `pre` and `post` accept either a single hook script, or an array of
+/// hook scripts, e.g., `pre = ["a.sh", "b.sh"]`. Hook scripts are executed in
+/// the order they are listed.
+///
+/// Hook definitions can be selected through `--only-hooks` and `--skip-hooks`
+/// by their optional `name`, by the file name of any of their hook scripts
+/// with or without extension, or by their position in the command's hook
+/// array starting from 1.
+///
+/// Hook definitions with `parallel = true` may run at the same time as other
+/// parallel hook definitions of their command. Through `depends`, they wait
+/// for other hook definitions of their command named by their `name`, or by
+/// the path of any of their hook scripts, e.g., `depends = ["fetch.sh"]`.
+///
+/// Hook scripts run through the POSIX shell, unless their shebang line names
+/// another program, or their hook definition sets `interpreter`, e.g.,
+/// `interpreter = "python3"`.
 #[derive(Clone, Debug, Default, Eq, PartialEq)]
 pub struct HookSettings {
-    /// Execute hook script _before_ command itself.
-    pub pre: Option<String>,
+    /// Execute hook scripts _before_ command itself.
+    pub pre: Vec<String>,
 
-    /// Execute hook script _after_ command itself.
-    pub post: Option<String>,
+    /// Execute hook scripts _after_ command itself.
+    pub post: Vec<String>,
 
     /// Set working directory of hook script.
     pub workdir: Option<PathBuf>,
+
+    /// Name to select hook definition by.
+    pub name: Option<String>,
+
+    /// Flag to determine if hook scripts run in a sandbox.
+    pub sandbox: bool,
+
+    /// Patterns of environment variables hook scripts may inherit.
+    pub env_allow: Vec<String>,
+
+    /// Patterns of environment variables hook scripts never inherit.
+    pub env_deny: Vec<String>,
+
+    /// Variables to set for hook scripts, in the order they were listed.
+    pub env: Vec<(String, String)>,
+
+    /// Run hook scripts once per command, or once per repository.
+    pub scope: HookScope,
+
+    /// Flag to determine if hook scripts may run alongside other parallel
+    /// hook definitions.
+    pub parallel: bool,
+
+    /// Hook definitions or hook scripts to wait for.
+    pub depends: Vec<String>,
+
+    /// Program and its arguments to run hook scripts through.
+    pub interpreter: Option<String>,
 }
 
 impl HookSettings {
@@ -394,13 +1194,19 @@ pub fn new() -> Self {
         Default::default()
     }
 
+    /// Add hook script to execute _before_ command.
+    ///
+    /// Can be called multiple times to add more hook scripts in order.
     pub fn pre(mut self, script: impl Into<String>) -> Self {
-        self.pre = Some(script.into());
+        self.pre.push(script.into());
         self
     }
 
+    /// Add hook script to execute _after_ command.
+    ///
+    /// Can be called multiple times to add more hook scripts in order.
     pub fn post(mut self, script: impl Into<String>) -> Self {
-        self.post = Some(script.into());
+        self.post.push(script.into());
         self
     }
 
@@ -408,17 +1214,815 @@ pub fn workdir(mut self, path: impl Into<PathBuf>) -> Self {
         self.workdir = Some(path.into());
         self
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
 
-    use anyhow::Result;
-    use indoc::indoc;
-    use pretty_assertions::assert_eq;
-    use rstest::{fixture, rstest};
-    use toml_edit::DocumentMut;
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    pub fn sandbox(mut self, choice: bool) -> Self {
+        self.sandbox = choice;
+        self
+    }
+
+    /// Allow hook scripts to inherit environment variables matching `pattern`.
+    ///
+    /// Can be called multiple times to allow more variables.
+    pub fn env_allow(mut self, pattern: impl Into<String>) -> Self {
+        self.env_allow.push(pattern.into());
+        self
+    }
+
+    /// Keep hook scripts from inheriting environment variables matching
+    /// `pattern`.
+    ///
+    /// Can be called multiple times to deny more variables.
+    pub fn env_deny(mut self, pattern: impl Into<String>) -> Self {
+        self.env_deny.push(pattern.into());
+        self
+    }
+
+    /// Set variable `name` to `value` for hook scripts.
+    ///
+    /// Can be called multiple times to set more variables.
+    pub fn env(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.env.push((name.into(), value.into()));
+        self
+    }
+
+    pub fn scope(mut self, scope: HookScope) -> Self {
+        self.scope = scope;
+        self
+    }
+
+    pub fn parallel(mut self, choice: bool) -> Self {
+        self.parallel = choice;
+        self
+    }
+
+    /// Wait for hook definition or hook script `hook` before executing.
+    ///
+    /// Can be called multiple times to wait for more hooks.
+    pub fn depends(mut self, hook: impl Into<String>) -> Self {
+        self.depends.push(hook.into());
+        self
+    }
+
+    pub fn interpreter(mut self, interpreter: impl Into<String>) -> Self {
+        self.interpreter = Some(interpreter.into());
+        self
+    }
+
+    /// Check if hook definition at `index` of its command is picked by
+    /// `selector`.
+    pub fn is_selected_by(&self, index: usize, selector: &str) -> bool {
+        if selector.parse::<usize>().is_ok_and(|position| position == index + 1) {
+            return true;
+        }
+
+        if self.name.as_deref() == Some(selector) {
+            return true;
+        }
+
+        self.pre.iter().chain(self.post.iter()).any(|script| {
+            let script = Path::new(script);
+            script.file_name().is_some_and(|name| name == selector)
+                || script.file_stem().is_some_and(|stem| stem == selector)
+        })
+    }
+}
+
+/// How often hook scripts of a hook definition run for their command.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum HookScope {
+    /// Run hook scripts once, before or after the whole command.
+    #[default]
+    Command,
+
+    /// Run hook scripts once for every repository the command operates on.
+    Repo,
+}
+
+impl FromStr for HookScope {
+    type Err = String;
+
+    fn from_str(scope: &str) -> Result<Self, Self::Err> {
+        match scope {
+            "command" => Ok(HookScope::Command),
+            "repo" => Ok(HookScope::Repo),
+            _ => Err(format!("invalid hook scope '{scope}'")),
+        }
+    }
+}
+
+impl fmt::Display for HookScope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HookScope::Command => write!(f, "command"),
+            HookScope::Repo => write!(f, "repo"),
+        }
+    }
+}
+
+/// Feature toggle settings.
+///
+/// An intermediary structure to help deserialize and serialize optional
+/// features of Ricer that can be switched off in Ricer's general configuration
+/// file, e.g., status snapshot publishing through `[features.state]`.
+///
+/// # Invariants
+///
+/// Features are enabled unless explicitly disabled.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FeatureSettings {
+    /// Name of feature.
+    pub name: String,
+
+    /// Flag to determine if feature is enabled.
+    pub enabled: bool,
+}
+
+impl FeatureSettings {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self { name: name.into(), enabled: true }
+    }
+
+    pub fn enabled(mut self, choice: bool) -> Self {
+        self.enabled = choice;
+        self
+    }
+}
+
+impl Default for FeatureSettings {
+    fn default() -> Self {
+        Self::new(String::default())
+    }
+}
+
+impl Settings for FeatureSettings {
+    fn to_toml(&self) -> (Key, Item) {
+        let mut feature = Table::new();
+        feature.insert("enabled", Item::Value(Value::from(self.enabled)));
+
+        let key = Key::new(&self.name);
+        let value = Item::Table(feature);
+        (key, value)
+    }
+}
+
+fn feature_toml<'toml>(entry: (&'toml Key, &'toml Item)) -> FeatureSettings {
+    let (key, value) = entry;
+    let mut feature = FeatureSettings::new(key.get());
+    feature.visit_item(value);
+    feature
+}
+
+impl<'toml> From<(&'toml Key, &'toml Item)> for FeatureSettings {
+    fn from(entry: (&'toml Key, &'toml Item)) -> Self {
+        feature_toml(entry)
+    }
+}
+
+impl From<(Key, Item)> for FeatureSettings {
+    fn from(entry: (Key, Item)) -> Self {
+        let (key, value) = entry;
+        feature_toml((&key, &value))
+    }
+}
+
+impl<'toml> Visit<'toml> for FeatureSettings {
+    fn visit_table_like_kv(&mut self, key: &'toml str, node: &'toml Item) {
+        if key == "enabled" {
+            self.enabled = node.as_bool().unwrap_or(true);
+        }
+        visit_table_like_kv(self, key, node);
+    }
+}
+
+/// Paging behavior of command output.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum PagerMode {
+    /// Page output only if it does not fit into the terminal.
+    #[default]
+    Auto,
+
+    /// Always page output when writing to a terminal.
+    Always,
+
+    /// Never page output.
+    Never,
+}
+
+impl FromStr for PagerMode {
+    type Err = String;
+
+    fn from_str(mode: &str) -> Result<Self, Self::Err> {
+        match mode {
+            "auto" => Ok(PagerMode::Auto),
+            "always" => Ok(PagerMode::Always),
+            "never" => Ok(PagerMode::Never),
+            _ => Err(format!("invalid pager mode '{mode}'")),
+        }
+    }
+}
+
+impl fmt::Display for PagerMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PagerMode::Auto => write!(f, "auto"),
+            PagerMode::Always => write!(f, "always"),
+            PagerMode::Never => write!(f, "never"),
+        }
+    }
+}
+
+/// Terminal output settings.
+///
+/// An intermediary structure to help deserialize the `[ui]` table of Ricer's
+/// general configuration file:
+///
+/// ```toml
+/// [ui]
+/// pager = "never"
+/// ```
+///
+/// # Invariants
+///
+/// Invalid or missing values fall back to their defaults.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct UiSettings {
+    /// When to page command output.
+    pub pager: PagerMode,
+}
+
+impl UiSettings {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn pager(mut self, mode: PagerMode) -> Self {
+        self.pager = mode;
+        self
+    }
+}
+
+impl From<&Table> for UiSettings {
+    fn from(table: &Table) -> Self {
+        let mut ui = UiSettings::new();
+        ui.visit_table(table);
+        ui
+    }
+}
+
+impl<'toml> Visit<'toml> for UiSettings {
+    fn visit_table_like_kv(&mut self, key: &'toml str, node: &'toml Item) {
+        if key == "pager" {
+            self.pager = node.as_str().and_then(|mode| mode.parse().ok()).unwrap_or_default();
+        }
+        visit_table_like_kv(self, key, node);
+    }
+}
+
+/// Background fetch daemon settings.
+///
+/// An intermediary structure to help deserialize the `[daemon]` table of
+/// Ricer's general configuration file:
+///
+/// ```toml
+/// [daemon]
+/// interval = "15m"
+/// ```
+///
+/// # Invariants
+///
+/// Interval is given as a duration, see [`parse_duration`], or in seconds,
+/// and never drops below [`DaemonSettings::MIN_INTERVAL`], so a typo cannot
+/// make the daemon hammer remotes. Invalid or missing values fall back to
+/// their defaults.
+///
+/// [`parse_duration`]: crate::config::parse_duration
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DaemonSettings {
+    /// Time between fetches of each repository.
+    pub interval: Duration,
+}
+
+impl DaemonSettings {
+    /// Shortest allowed interval between fetches.
+    pub const MIN_INTERVAL: Duration = Duration::from_secs(60);
+
+    /// Interval between fetches if none is configured.
+    pub const DEFAULT_INTERVAL: Duration = Duration::from_secs(15 * 60);
+
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = cmp::max(interval, Self::MIN_INTERVAL);
+        self
+    }
+
+    /// Validate types of daemon settings `table`, see
+    /// [`RepoSettings::validate`].
+    ///
+    /// # Errors
+    ///
+    /// - Return [`SettingsError::BadValue`] if interval is not a duration.
+    pub fn validate(table: &Table) -> Result<(), SettingsError> {
+        for (name, expect) in DAEMON_SCHEMA {
+            if let Some(node) = table.get(name) {
+                validate_item(&format!("daemon.{name}"), node, *expect)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+const DAEMON_SCHEMA: &[(&str, Expect)] = &[("interval", Expect::Duration)];
+
+impl Default for DaemonSettings {
+    fn default() -> Self {
+        Self { interval: Self::DEFAULT_INTERVAL }
+    }
+}
+
+impl From<&Table> for DaemonSettings {
+    fn from(table: &Table) -> Self {
+        let mut daemon = DaemonSettings::new();
+        daemon.visit_table(table);
+        daemon
+    }
+}
+
+impl<'toml> Visit<'toml> for DaemonSettings {
+    fn visit_table_like_kv(&mut self, key: &'toml str, node: &'toml Item) {
+        if key == "interval" {
+            self.interval = match duration_item(node) {
+                Ok(interval) => cmp::max(interval, Self::MIN_INTERVAL),
+                Err(err) => {
+                    warn!("Invalid value at 'daemon.interval', using default: {err}");
+                    Self::DEFAULT_INTERVAL
+                }
+            };
+        }
+        visit_table_like_kv(self, key, node);
+    }
+}
+
+/// Hook output log settings.
+///
+/// An intermediary structure to help deserialize the `[hook_logs]` table of
+/// Ricer's general configuration file, which limits how many logs of hook
+/// output are kept:
+///
+/// ```toml
+/// [hook_logs]
+/// max_files = 50
+/// max_size = "5MB"
+/// ```
+///
+/// # Invariants
+///
+/// Size is given as a size, see [`parse_size`], or in bytes. Invalid or
+/// missing values fall back to their defaults.
+///
+/// [`parse_size`]: crate::config::parse_size
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct HookLogSettings {
+    /// Most logs to keep.
+    pub max_files: usize,
+
+    /// Most bytes all logs may take up together.
+    pub max_size: u64,
+}
+
+impl HookLogSettings {
+    /// Most logs to keep if not configured.
+    pub const DEFAULT_MAX_FILES: usize = 100;
+
+    /// Most bytes of all logs if not configured.
+    pub const DEFAULT_MAX_SIZE: u64 = 10_000_000;
+
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn max_files(mut self, count: usize) -> Self {
+        self.max_files = count;
+        self
+    }
+
+    pub fn max_size(mut self, bytes: u64) -> Self {
+        self.max_size = bytes;
+        self
+    }
+}
+
+impl Default for HookLogSettings {
+    fn default() -> Self {
+        Self { max_files: Self::DEFAULT_MAX_FILES, max_size: Self::DEFAULT_MAX_SIZE }
+    }
+}
+
+impl From<&Table> for HookLogSettings {
+    fn from(table: &Table) -> Self {
+        let mut logs = HookLogSettings::new();
+        logs.visit_table(table);
+        logs
+    }
+}
+
+impl<'toml> Visit<'toml> for HookLogSettings {
+    fn visit_table_like_kv(&mut self, key: &'toml str, node: &'toml Item) {
+        match key {
+            "max_files" => match node.as_integer().and_then(|count| usize::try_from(count).ok()) {
+                Some(count) => self.max_files = count,
+                None => warn!("Invalid value at 'hook_logs.max_files', using default"),
+            },
+            "max_size" => match size_item(node) {
+                Ok(bytes) => self.max_size = bytes,
+                Err(err) => warn!("Invalid value at 'hook_logs.max_size', using default: {err}"),
+            },
+            _ => (),
+        }
+        visit_table_like_kv(self, key, node);
+    }
+}
+
+/// Lint settings of commit messages.
+///
+/// An intermediary structure to help deserialize the `[lint]` table of
+/// Ricer's general configuration file, where every rule of the `commit` array
+/// is checked against commit messages of `ricer commit`:
+///
+/// ```toml
+/// [lint]
+/// commit = [
+///     { name = "scope", pattern = '^[a-z0-9_-]+: ' },
+///     { name = "summary-length", max_length = 72 },
+/// ]
+/// ```
+///
+/// Staged files of `ricer commit` are checked by the `[lint.files]` table,
+/// see [`FileLint`].
+///
+/// # Invariants
+///
+/// Rules without a name are named by their position, counting from 1. Rules
+/// that neither set `pattern` nor `max_length` check nothing, and are dropped.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct LintSettings {
+    /// Rules commit messages must follow.
+    pub commit: Vec<LintRule>,
+
+    /// Checks of staged files.
+    pub files: FileLint,
+}
+
+impl LintSettings {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Add rule for commit messages.
+    ///
+    /// Can be called multiple times to add more rules in order.
+    pub fn commit_rule(mut self, rule: LintRule) -> Self {
+        self.commit.push(rule);
+        self
+    }
+
+    pub fn files(mut self, files: FileLint) -> Self {
+        self.files = files;
+        self
+    }
+}
+
+impl From<&Table> for LintSettings {
+    fn from(table: &Table) -> Self {
+        let mut lint = LintSettings::new();
+        lint.visit_table(table);
+        lint
+    }
+}
+
+impl<'toml> Visit<'toml> for LintSettings {
+    fn visit_table_like_kv(&mut self, key: &'toml str, node: &'toml Item) {
+        if key == "commit" {
+            let rules =
+                node.as_array().into_iter().flatten().filter_map(|rule| rule.as_inline_table());
+            for (index, rule) in rules.enumerate() {
+                let lint = LintRule {
+                    name: rule
+                        .get("name")
+                        .and_then(|name| name.as_str().map(String::from))
+                        .unwrap_or_else(|| (index + 1).to_string()),
+                    pattern: rule.get("pattern").and_then(|regex| regex.as_str().map(String::from)),
+                    max_length: rule
+                        .get("max_length")
+                        .and_then(|len| len.as_integer())
+                        .and_then(|len| usize::try_from(len).ok()),
+                };
+                if lint.pattern.is_some() || lint.max_length.is_some() {
+                    self.commit.push(lint);
+                }
+            }
+        }
+
+        if key == "files" {
+            if let Some(files) = node.as_table_like() {
+                self.files = FileLint::from_table_like(files);
+            }
+        }
+        visit_table_like_kv(self, key, node);
+    }
+}
+
+/// Checks of files staged for `ricer commit`.
+///
+/// An intermediary structure to help deserialize the `[lint.files]` table of
+/// Ricer's general configuration file, which catches files that rarely belong
+/// into dotfiles, like caches:
+///
+/// ```toml
+/// [lint.files]
+/// max_size = "10MB"
+/// binary = true
+/// action = "block"
+/// allow = ["wallpapers/"]
+/// ```
+///
+/// Sizes are given as in [`parse_size`], or in bytes. Files matching the
+/// gitignore-style patterns of `allow` are never checked.
+///
+/// # Invariants
+///
+/// Invalid sizes and actions fall back to their defaults.
+///
+/// [`parse_size`]: crate::config::parse_size
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FileLint {
+    /// Maximum size of staged files in bytes.
+    pub max_size: u64,
+
+    /// Flag to determine if binary files are flagged.
+    pub binary: bool,
+
+    /// What happens to commits with flagged files.
+    pub action: LintAction,
+
+    /// Patterns of files that are never flagged.
+    pub allow: Vec<String>,
+}
+
+impl FileLint {
+    pub const DEFAULT_MAX_SIZE: u64 = 10_000_000;
+
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn max_size(mut self, bytes: u64) -> Self {
+        self.max_size = bytes;
+        self
+    }
+
+    pub fn binary(mut self, choice: bool) -> Self {
+        self.binary = choice;
+        self
+    }
+
+    pub fn action(mut self, action: LintAction) -> Self {
+        self.action = action;
+        self
+    }
+
+    /// Never flag files matching gitignore-style `pattern`.
+    ///
+    /// Can be called multiple times to allow more files.
+    pub fn allow(mut self, pattern: impl Into<String>) -> Self {
+        self.allow.push(pattern.into());
+        self
+    }
+
+    fn from_table_like(table: &dyn TableLike) -> Self {
+        let mut files = FileLint::new();
+        if let Some(node) = table.get("max_size") {
+            match size_item(node) {
+                Ok(bytes) => files.max_size = bytes,
+                Err(err) => warn!("Invalid value at 'lint.files.max_size', using default: {err}"),
+            }
+        }
+        if let Some(binary) = table.get("binary").and_then(Item::as_bool) {
+            files.binary = binary;
+        }
+        if let Some(action) = table.get("action").and_then(Item::as_str) {
+            match action.parse() {
+                Ok(action) => files.action = action,
+                Err(err) => warn!("Invalid value at 'lint.files.action', using default: {err}"),
+            }
+        }
+        files.allow = scripts_from_value(table.get("allow").and_then(Item::as_value));
+        files
+    }
+}
+
+impl Default for FileLint {
+    fn default() -> Self {
+        Self {
+            max_size: Self::DEFAULT_MAX_SIZE,
+            binary: true,
+            action: LintAction::default(),
+            allow: Vec::new(),
+        }
+    }
+}
+
+/// What happens when a lint check fails.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum LintAction {
+    /// Warn about it, but go on.
+    #[default]
+    Warn,
+
+    /// Stop the command.
+    Block,
+}
+
+impl FromStr for LintAction {
+    type Err = String;
+
+    fn from_str(action: &str) -> Result<Self, Self::Err> {
+        match action {
+            "warn" => Ok(LintAction::Warn),
+            "block" => Ok(LintAction::Block),
+            _ => Err(format!("invalid lint action '{action}'")),
+        }
+    }
+}
+
+impl fmt::Display for LintAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LintAction::Warn => write!(f, "warn"),
+            LintAction::Block => write!(f, "block"),
+        }
+    }
+}
+
+/// Lint rule of commit messages.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct LintRule {
+    /// Name to report rule by.
+    pub name: String,
+
+    /// Regular expression commit messages must match.
+    pub pattern: Option<String>,
+
+    /// Maximum length of summary line of commit messages.
+    pub max_length: Option<usize>,
+}
+
+impl LintRule {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self { name: name.into(), ..Default::default() }
+    }
+
+    pub fn pattern(mut self, regex: impl Into<String>) -> Self {
+        self.pattern = Some(regex.into());
+        self
+    }
+
+    pub fn max_length(mut self, len: usize) -> Self {
+        self.max_length = Some(len);
+        self
+    }
+}
+
+/// Value of a persistent command default.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum DefaultValue {
+    Bool(bool),
+    Int(i64),
+    Str(String),
+}
+
+impl fmt::Display for DefaultValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DefaultValue::Bool(choice) => write!(f, "{choice}"),
+            DefaultValue::Int(number) => write!(f, "{number}"),
+            DefaultValue::Str(string) => write!(f, "{string:?}"),
+        }
+    }
+}
+
+/// Persistent defaults of a command.
+///
+/// An intermediary structure to help deserialize and serialize the
+/// `[commands]` table of Ricer's general configuration file, where each
+/// command can preset its own options:
+///
+/// ```toml
+/// [commands.status]
+/// terse = true
+/// ```
+///
+/// # Invariants
+///
+/// Only booleans, integers, and strings are kept. Values of any other type
+/// are dropped, because no command option could take them.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct CommandSettings {
+    /// Name of command.
+    pub name: String,
+
+    /// Option defaults in the order they were defined.
+    pub defaults: Vec<(String, DefaultValue)>,
+}
+
+impl CommandSettings {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self { name: name.into(), defaults: Default::default() }
+    }
+
+    pub fn set(mut self, key: impl Into<String>, value: DefaultValue) -> Self {
+        let key = key.into();
+        self.defaults.retain(|(name, _)| *name != key);
+        self.defaults.push((key, value));
+        self
+    }
+
+    pub fn get(&self, key: &str) -> Option<&DefaultValue> {
+        self.defaults.iter().find(|(name, _)| name == key).map(|(_, value)| value)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.defaults.is_empty()
+    }
+}
+
+impl Settings for CommandSettings {
+    fn to_toml(&self) -> (Key, Item) {
+        let mut command = Table::new();
+        for (key, value) in &self.defaults {
+            let value = match value {
+                DefaultValue::Bool(choice) => Value::from(*choice),
+                DefaultValue::Int(number) => Value::from(*number),
+                DefaultValue::Str(string) => Value::from(string),
+            };
+            command.insert(key, Item::Value(value));
+        }
+
+        let key = Key::new(&self.name);
+        let value = Item::Table(command);
+        (key, value)
+    }
+}
+
+fn command_toml<'toml>(entry: (&'toml Key, &'toml Item)) -> CommandSettings {
+    let (key, value) = entry;
+    let mut command = CommandSettings::new(key.get());
+    command.visit_item(value);
+    command
+}
+
+impl<'toml> From<(&'toml Key, &'toml Item)> for CommandSettings {
+    fn from(entry: (&'toml Key, &'toml Item)) -> Self {
+        command_toml(entry)
+    }
+}
+
+impl From<(Key, Item)> for CommandSettings {
+    fn from(entry: (Key, Item)) -> Self {
+        let (key, value) = entry;
+        command_toml((&key, &value))
+    }
+}
+
+impl<'toml> Visit<'toml> for CommandSettings {
+    fn visit_table_like_kv(&mut self, key: &'toml str, node: &'toml Item) {
+        // INVARIANT: do not descend, nested tables are not options of this command.
+        let value = match node.as_value() {
+            Some(Value::Boolean(choice)) => DefaultValue::Bool(*choice.value()),
+            Some(Value::Integer(number)) => DefaultValue::Int(*number.value()),
+            Some(Value::String(string)) => DefaultValue::Str(string.value().clone()),
+            _ => return,
+        };
+        self.defaults.push((key.into(), value));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use anyhow::Result;
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+    use rstest::{fixture, rstest};
+    use toml_edit::DocumentMut;
 
     #[fixture]
     fn repo_settings_doc() -> Result<DocumentMut> {
@@ -438,6 +2042,67 @@ fn repo_settings_doc() -> Result<DocumentMut> {
             os = "unix"
             users = ["awkless", "sedgwick"]
             hosts = ["lovelace", "turing"]
+            on_clone = "bar_setup.sh"
+
+            [baz]
+            branch = "main"
+            remote = "origin"
+            workdir_home = false
+            path = "/shared/baz.git"
+
+            [dots]
+            branch = "main"
+            remote = "origin"
+            workdir_home = false
+            deploy_mode = "symlink"
+
+            [home]
+            branch = "main"
+            remote = "origin"
+            workdir_home = true
+            untracked = "no"
+
+            [work]
+            branch = "main"
+            remote = "origin"
+            workdir_home = false
+            run_hooks = "never"
+
+            [kiosk]
+            branch = "main"
+            remote = "origin"
+            workdir_home = false
+            pin = "v1.2"
+
+            [notes]
+            branch = "main"
+            remote = "origin"
+            workdir_home = false
+            push_on_commit = true
+
+            [base]
+            branch = "main"
+            remote = "origin"
+            workdir_home = false
+            readonly = true
+
+            [legacy]
+            branch = "main"
+            remote = "origin"
+            workdir_home = false
+            hooks = "prompt"
+
+            [keys]
+            branch = "main"
+            remote = "origin"
+            workdir_home = false
+            exclude = ["*.pem", ".ssh/"]
+
+            [themes]
+            branch = "main"
+            remote = "origin"
+            workdir_home = false
+            host_branches = { laptop = "nord", branch = "gruvbox" }
         "#}
         .parse()?;
         Ok(doc)
@@ -451,6 +2116,32 @@ fn cmd_hook_settings_doc() -> Result<DocumentMut> {
                 { pre = "hook.sh" },
                 { post = "hook.sh" }
             ]
+            push = [
+                { pre = ["hook1.sh", "hook2.sh"], post = "hook.sh" },
+                { post = ["hook1.sh", "hook2.sh"] }
+            ]
+            pull = [
+                { pre = "hook.sh", name = "greet" }
+            ]
+            clone = [
+                { post = "hook.sh", sandbox = true }
+            ]
+            deploy = [
+                { post = "hook.sh", env_allow = ["PATH", "LC_*"], env_deny = "*_TOKEN" }
+            ]
+            undeploy = [
+                { pre = "hook.sh", env = { TARGET = "laptop", JOBS = 4, MODE = "fast" } }
+            ]
+
+            sed = [
+                { post = "hook.sh", scope = "repo" }
+            ]
+            fetch = [
+                { pre = "b.sh", parallel = true, depends = ["a.sh", "lock"] }
+            ]
+            status = [
+                { post = "report.py", interpreter = "python3 -u" }
+            ]
         "#}
         .parse()?;
         Ok(doc)
@@ -474,8 +2165,39 @@ fn cmd_hook_settings_doc() -> Result<DocumentMut> {
                     .os(OsType::Unix)
                     .users(["awkless", "sedgwick"])
                     .hosts(["lovelace", "turing"])
+                    .on_clone("bar_setup.sh")
             ),
     )]
+    #[case::with_path(RepoSettings::new("baz").branch("main").remote("origin").path("/shared/baz.git"))]
+    #[case::with_deploy_mode(
+        RepoSettings::new("dots").branch("main").remote("origin").deploy_mode(DeployMode::Symlink)
+    )]
+    #[case::with_untracked(
+        RepoSettings::new("home")
+            .branch("main")
+            .remote("origin")
+            .workdir_home(true)
+            .untracked(UntrackedFiles::No)
+    )]
+    #[case::with_hooks(RepoSettings::new("work").branch("main").remote("origin").hooks(HookAction::Never))]
+    #[case::with_pin(RepoSettings::new("kiosk").branch("main").remote("origin").pin("v1.2"))]
+    #[case::with_push_on_commit(
+        RepoSettings::new("notes").branch("main").remote("origin").push_on_commit(true)
+    )]
+    #[case::with_readonly(RepoSettings::new("base").branch("main").remote("origin").readonly(true))]
+    #[case::with_deprecated_hooks(
+        RepoSettings::new("legacy").branch("main").remote("origin").hooks(HookAction::Prompt)
+    )]
+    #[case::with_exclude(
+        RepoSettings::new("keys").branch("main").remote("origin").exclude("*.pem").exclude(".ssh/")
+    )]
+    #[case::with_host_branches(
+        RepoSettings::new("themes")
+            .branch("main")
+            .remote("origin")
+            .host_branch("laptop", "nord")
+            .host_branch("branch", "gruvbox")
+    )]
     fn repo_settings_from_key_item_return_self(
         repo_settings_doc: Result<DocumentMut>,
         #[case] expect: RepoSettings,
@@ -487,6 +2209,120 @@ fn repo_settings_from_key_item_return_self(
         Ok(())
     }
 
+    #[rstest]
+    #[case::lone_string(
+        indoc! {r#"
+            [vim.bootstrap]
+            users = "awkless"
+            hosts = ["lovelace"]
+        "#},
+        RepoSettings::new("vim")
+            .bootstrap(BootstrapSettings::new().users(["awkless"]).hosts(["lovelace"])),
+    )]
+    #[case::skip_bad_elements(
+        indoc! {r#"
+            [vim.bootstrap]
+            users = [42, "awkless"]
+        "#},
+        RepoSettings::new("vim").bootstrap(BootstrapSettings::new().users(["awkless"])),
+    )]
+    fn repo_settings_from_key_item_coerce_arrays(
+        #[case] input: &str,
+        #[case] expect: RepoSettings,
+    ) -> Result<()> {
+        let doc: DocumentMut = input.parse()?;
+        let entry = doc.as_table().get_key_value("vim").unwrap();
+        assert_eq!(RepoSettings::from(entry), expect);
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::element("users = [\"awkless\", 42]", "vim.bootstrap.users", Some(1), "an integer")]
+    #[case::hosts_element("hosts = [true]", "vim.bootstrap.hosts", Some(0), "a boolean")]
+    #[case::not_array("users = 42", "vim.bootstrap.users", None, "an integer")]
+    #[case::not_string("clone = [\"url\"]", "vim.bootstrap.clone", None, "an array")]
+    #[case::on_clone("on_clone = true", "vim.bootstrap.on_clone", None, "a boolean")]
+    fn repo_settings_validate_return_err(
+        #[case] bootstrap: &str,
+        #[case] key: &str,
+        #[case] index: Option<usize>,
+        #[case] found: &str,
+    ) -> Result<()> {
+        let doc: DocumentMut = format!("[vim.bootstrap]\n{bootstrap}\n").parse()?;
+        let result = RepoSettings::validate(doc.as_table().get_key_value("vim").unwrap());
+        match result.unwrap_err() {
+            SettingsError::BadElement { key: bad_key, index: bad_index, found: bad, .. } => {
+                assert_eq!((bad_key.as_str(), Some(bad_index), bad), (key, index, found))
+            }
+            SettingsError::BadType { key: bad_key, found: bad, .. } => {
+                assert_eq!((bad_key.as_str(), None, bad), (key, index, found))
+            }
+            err => panic!("Unexpected error: {err}"),
+        }
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::typo_scheme("htps://github.com/awkless/vim.git", RemoteUrlError::BadScheme { scheme: "htps".into() })]
+    #[case::no_host("https:///awkless/vim.git", RemoteUrlError::NoHost)]
+    #[case::scp_no_path("git@github.com:", RemoteUrlError::NoPath)]
+    #[case::scp_no_host("git@:awkless/vim.git", RemoteUrlError::NoHost)]
+    #[case::whitespace("https://github.com/awk less/vim.git", RemoteUrlError::Whitespace)]
+    #[case::empty("  ", RemoteUrlError::Empty)]
+    fn repo_settings_validate_return_err_bad_url(
+        #[case] url: &str,
+        #[case] expect: RemoteUrlError,
+    ) -> Result<()> {
+        let doc: DocumentMut = format!("[vim.bootstrap]\nclone = \"{url}\"\n").parse()?;
+        let result = RepoSettings::validate(doc.as_table().get_key_value("vim").unwrap());
+        assert_eq!(
+            result,
+            Err(SettingsError::BadUrl {
+                key: "vim.bootstrap.clone".into(),
+                url: url.into(),
+                source: expect
+            })
+        );
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::https(" https://github.com/awkless/vim.git\n", "https://github.com/awkless/vim.git")]
+    #[case::http("http://git.local/vim.git", "http://git.local/vim.git")]
+    #[case::ssh("ssh://git@github.com/awkless/vim.git", "ssh://git@github.com/awkless/vim.git")]
+    #[case::scp("git@github.com:awkless/vim.git", "git@github.com:awkless/vim.git")]
+    #[case::file("file:///srv/git/vim.git", "file:///srv/git/vim.git")]
+    #[case::local("/srv/git/my vim.git", "/srv/git/my vim.git")]
+    fn normalize_remote_url_return_trimmed(#[case] url: &str, #[case] expect: &str) -> Result<()> {
+        assert_eq!(normalize_remote_url(url)?, expect);
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::plain("vim", Ok(()))]
+    #[case::dotted("nvim.lua", Ok(()))]
+    #[case::empty("", Err(RepoNameError::Empty))]
+    #[case::hidden(".ssh", Err(RepoNameError::Hidden))]
+    #[case::parent("../../.ssh/x", Err(RepoNameError::Hidden))]
+    #[case::slash("a/b", Err(RepoNameError::Separator))]
+    #[case::backslash("a\\b", Err(RepoNameError::Separator))]
+    #[case::inner_parent("a..b", Err(RepoNameError::ParentDir))]
+    fn validate_repo_name_reject_paths(
+        #[case] name: &str,
+        #[case] expect: Result<(), RepoNameError>,
+    ) {
+        assert_eq!(validate_repo_name(name), expect);
+    }
+
+    #[rstest]
+    fn repo_settings_validate_return_ok(repo_settings_doc: Result<DocumentMut>) -> Result<()> {
+        let doc = repo_settings_doc?;
+        for entry in doc.as_table().iter() {
+            RepoSettings::validate((&Key::new(entry.0), entry.1))?;
+        }
+        Ok(())
+    }
+
     #[rstest]
     #[case::no_bootstrap(
         RepoSettings::new("foo")
@@ -511,6 +2347,7 @@ fn repo_settings_from_key_item_return_self(
                     .os(OsType::Unix)
                     .users(["awkless", "sedgwick"])
                     .hosts(["lovelace", "turing"])
+                    .on_clone("bar_setup.sh")
             ),
         indoc! {r#"
             [bar]
@@ -523,6 +2360,101 @@ fn repo_settings_from_key_item_return_self(
             os = "unix"
             users = ["awkless", "sedgwick"]
             hosts = ["lovelace", "turing"]
+            on_clone = "bar_setup.sh"
+        "#},
+    )]
+    #[case::with_path(
+        RepoSettings::new("baz").branch("main").remote("origin").path("/shared/baz.git"),
+        indoc! {r#"
+            [baz]
+            branch = "main"
+            remote = "origin"
+            workdir_home = false
+            path = "/shared/baz.git"
+        "#},
+    )]
+    #[case::with_push_on_commit(
+        RepoSettings::new("notes").branch("main").remote("origin").push_on_commit(true),
+        indoc! {r#"
+            [notes]
+            branch = "main"
+            remote = "origin"
+            workdir_home = false
+            push_on_commit = true
+        "#},
+    )]
+    #[case::with_readonly(
+        RepoSettings::new("base").branch("main").remote("origin").readonly(true),
+        indoc! {r#"
+            [base]
+            branch = "main"
+            remote = "origin"
+            workdir_home = false
+            readonly = true
+        "#},
+    )]
+    #[case::with_exclude(
+        RepoSettings::new("keys").branch("main").remote("origin").exclude("*.pem").exclude(".ssh/"),
+        indoc! {r#"
+            [keys]
+            branch = "main"
+            remote = "origin"
+            workdir_home = false
+            exclude = ["*.pem", ".ssh/"]
+        "#},
+    )]
+    #[case::with_host_branches(
+        RepoSettings::new("themes").branch("main").remote("origin").host_branch("laptop", "nord"),
+        indoc! {r#"
+            [themes]
+            branch = "main"
+            remote = "origin"
+            workdir_home = false
+            host_branches = { laptop = "nord" }
+        "#},
+    )]
+    #[case::with_pin(
+        RepoSettings::new("kiosk").branch("main").remote("origin").pin("v1.2"),
+        indoc! {r#"
+            [kiosk]
+            branch = "main"
+            remote = "origin"
+            workdir_home = false
+            pin = "v1.2"
+        "#},
+    )]
+    #[case::with_deploy_mode(
+        RepoSettings::new("dots").branch("main").remote("origin").deploy_mode(DeployMode::Symlink),
+        indoc! {r#"
+            [dots]
+            branch = "main"
+            remote = "origin"
+            workdir_home = false
+            deploy_mode = "symlink"
+        "#},
+    )]
+    #[case::with_untracked(
+        RepoSettings::new("home")
+            .branch("main")
+            .remote("origin")
+            .workdir_home(true)
+            .untracked(UntrackedFiles::All),
+        indoc! {r#"
+            [home]
+            branch = "main"
+            remote = "origin"
+            workdir_home = true
+            untracked = "all"
+        "#},
+    )]
+    #[case::with_hooks(
+        RepoSettings::new("work").branch("main").remote("origin").hooks(HookAction::Prompt),
+        indoc! {r#"
+            [work]
+            branch = "main"
+            remote = "origin"
+            workdir_home = false
+            run_hooks = "prompt"
         "#},
     )]
     fn repo_settings_to_toml_return_key_item(
@@ -545,6 +2477,31 @@ fn repo_settings_to_toml_return_key_item(
             .add_hook(HookSettings::new().pre("hook.sh"))
             .add_hook(HookSettings::new().post("hook.sh")),
     )]
+    #[case::script_arrays(
+        CmdHookSettings::new("push")
+            .add_hook(HookSettings::new().pre("hook1.sh").pre("hook2.sh").post("hook.sh"))
+            .add_hook(HookSettings::new().post("hook1.sh").post("hook2.sh")),
+    )]
+    #[case::named(CmdHookSettings::new("pull").add_hook(HookSettings::new().pre("hook.sh").name("greet")))]
+    #[case::sandbox(CmdHookSettings::new("clone").add_hook(HookSettings::new().post("hook.sh").sandbox(true)))]
+    #[case::env(
+        CmdHookSettings::new("deploy").add_hook(
+            HookSettings::new().post("hook.sh").env_allow("PATH").env_allow("LC_*").env_deny("*_TOKEN")
+        )
+    )]
+    #[case::env_vars(
+        CmdHookSettings::new("undeploy")
+            .add_hook(HookSettings::new().pre("hook.sh").env("TARGET", "laptop").env("MODE", "fast"))
+    )]
+    #[case::scope(CmdHookSettings::new("sed").add_hook(HookSettings::new().post("hook.sh").scope(HookScope::Repo)))]
+    #[case::parallel(
+        CmdHookSettings::new("fetch")
+            .add_hook(HookSettings::new().pre("b.sh").parallel(true).depends("a.sh").depends("lock"))
+    )]
+    #[case::interpreter(
+        CmdHookSettings::new("status")
+            .add_hook(HookSettings::new().post("report.py").interpreter("python3 -u"))
+    )]
     fn cmd_hook_settings_from_key_item_return_self(
         cmd_hook_settings_doc: Result<DocumentMut>,
         #[case] expect: CmdHookSettings,
@@ -570,6 +2527,78 @@ fn cmd_hook_settings_from_key_item_return_self(
             ]
         "#},
     )]
+    #[case::script_arrays(
+        CmdHookSettings::new("push")
+            .add_hook(HookSettings::new().pre("hook1.sh").pre("hook2.sh").post("hook.sh"))
+            .add_hook(HookSettings::new().post("hook1.sh").post("hook2.sh")),
+        indoc! {r#"
+            push = [
+                { pre = ["hook1.sh", "hook2.sh"], post = "hook.sh" },
+                { post = ["hook1.sh", "hook2.sh"] }
+            ]
+        "#},
+    )]
+    #[case::named(
+        CmdHookSettings::new("pull").add_hook(HookSettings::new().pre("hook.sh").name("greet")),
+        indoc! {r#"
+            pull = [
+                { pre = "hook.sh", name = "greet" }
+            ]
+        "#},
+    )]
+    #[case::sandbox(
+        CmdHookSettings::new("clone").add_hook(HookSettings::new().post("hook.sh").sandbox(true)),
+        indoc! {r#"
+            clone = [
+                { post = "hook.sh", sandbox = true }
+            ]
+        "#},
+    )]
+    #[case::env(
+        CmdHookSettings::new("deploy").add_hook(
+            HookSettings::new().post("hook.sh").env_allow("PATH").env_allow("LC_*").env_deny("*_TOKEN")
+        ),
+        indoc! {r#"
+            deploy = [
+                { post = "hook.sh", env_allow = ["PATH", "LC_*"], env_deny = "*_TOKEN" }
+            ]
+        "#},
+    )]
+    #[case::env_vars(
+        CmdHookSettings::new("undeploy")
+            .add_hook(HookSettings::new().pre("hook.sh").env("TARGET", "laptop").env("MODE", "fast")),
+        indoc! {r#"
+            undeploy = [
+                { pre = "hook.sh", env = { TARGET = "laptop", MODE = "fast" } }
+            ]
+        "#},
+    )]
+    #[case::scope(
+        CmdHookSettings::new("sed").add_hook(HookSettings::new().post("hook.sh").scope(HookScope::Repo)),
+        indoc! {r#"
+            sed = [
+                { post = "hook.sh", scope = "repo" }
+            ]
+        "#},
+    )]
+    #[case::parallel(
+        CmdHookSettings::new("fetch")
+            .add_hook(HookSettings::new().pre("b.sh").parallel(true).depends("a.sh").depends("lock")),
+        indoc! {r#"
+            fetch = [
+                { pre = "b.sh", parallel = true, depends = ["a.sh", "lock"] }
+            ]
+        "#},
+    )]
+    #[case::interpreter(
+        CmdHookSettings::new("status")
+            .add_hook(HookSettings::new().post("report.py").interpreter("python3 -u")),
+        indoc! {r#"
+            status = [
+                { post = "report.py", interpreter = "python3 -u" }
+            ]
+        "#},
+    )]
     fn cmd_hook_settings_to_toml_return_key_item(
         #[case] input: CmdHookSettings,
         #[case] expect: &str,
@@ -582,4 +2611,98 @@ fn cmd_hook_settings_to_toml_return_key_item(
         assert_eq!(doc.to_string(), expect);
         Ok(())
     }
+
+    #[rstest]
+    #[case::position("2", true)]
+    #[case::wrong_position("1", false)]
+    #[case::name("greet", true)]
+    #[case::file_name("hello.sh", true)]
+    #[case::file_stem("bye", true)]
+    #[case::no_match("nothing", false)]
+    fn hook_settings_is_selected_by(#[case] selector: &str, #[case] expect: bool) {
+        let hook = HookSettings::new().pre("hello.sh").post("scripts/bye.sh").name("greet");
+        assert_eq!(hook.is_selected_by(1, selector), expect);
+    }
+
+    #[rstest]
+    #[case::disabled(
+        indoc! {r#"
+            [state]
+            enabled = false
+        "#},
+        FeatureSettings::new("state").enabled(false),
+    )]
+    #[case::enabled_by_default(
+        indoc! {r#"
+            [state]
+        "#},
+        FeatureSettings::new("state"),
+    )]
+    fn feature_settings_from_key_item_return_self(
+        #[case] input: &str,
+        #[case] expect: FeatureSettings,
+    ) -> Result<()> {
+        let doc: DocumentMut = input.parse()?;
+        let result = FeatureSettings::from(doc.as_table().get_key_value("state").unwrap());
+        assert_eq!(result, expect);
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::never("pager = \"never\"", UiSettings::new().pager(PagerMode::Never))]
+    #[case::always("pager = \"always\"", UiSettings::new().pager(PagerMode::Always))]
+    #[case::invalid("pager = \"sometimes\"", UiSettings::new())]
+    #[case::missing("", UiSettings::new())]
+    fn ui_settings_from_table_return_self(
+        #[case] input: &str,
+        #[case] expect: UiSettings,
+    ) -> Result<()> {
+        let doc: DocumentMut = format!("[ui]\n{input}\n").parse()?;
+        let result = UiSettings::from(doc["ui"].as_table().unwrap());
+        assert_eq!(result, expect);
+        Ok(())
+    }
+
+    #[test]
+    fn settings_from_toml_keep_paths_expand_on_use() -> Result<()> {
+        let home = std::env::var("HOME")?;
+        let doc: DocumentMut = indoc! {r#"
+            [vim]
+            branch = "main"
+            remote = "origin"
+            workdir_home = false
+            path = "~/shared/vim.git"
+
+            [vim.bootstrap]
+            clone = "$HOME/backup/vim.git"
+
+            [dwm.bootstrap]
+            clone = "git@github.com:awkless/dwm.git"
+
+            [hooks]
+            commit = [
+                { pre = "hook.sh", workdir = "${HOME}/src" }
+            ]
+        "#}
+        .parse()?;
+
+        let vim = RepoSettings::from(doc.as_table().get_key_value("vim").unwrap());
+        assert_eq!(vim.path, Some(PathBuf::from("~/shared/vim.git")));
+        assert_eq!(
+            vim.gitdir(Path::new("/repos")),
+            PathBuf::from(format!("{home}/shared/vim.git"))
+        );
+        let bootstrap = vim.bootstrap.unwrap();
+        assert_eq!(bootstrap.clone.as_deref(), Some("$HOME/backup/vim.git"));
+        assert_eq!(bootstrap.clone_url(), Some(format!("{home}/backup/vim.git")));
+
+        let dwm = RepoSettings::from(doc.as_table().get_key_value("dwm").unwrap());
+        let url = dwm.bootstrap.unwrap().clone_url();
+        assert_eq!(url.as_deref(), Some("git@github.com:awkless/dwm.git"));
+
+        let hooks = doc["hooks"].as_table().unwrap();
+        let commit = CmdHookSettings::from(hooks.get_key_value("commit").unwrap());
+        assert_eq!(commit.hooks[0].workdir, Some(PathBuf::from(format!("{home}/src"))));
+        Ok(())
+    }
 }
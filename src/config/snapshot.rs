@@ -0,0 +1,193 @@
+// SPDX-FileCopyrightText: 2024 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+use crate::{
+    config::{
+        entries_or_empty, read_merged_if_exists, CmdHookConfig, CmdHookSettings, Config,
+        ConfigFileError, FeatureConfig, FeatureSettings, RepoConfig, RepoSettings, UiConfig,
+        UiSettings,
+    },
+    locate::Locator,
+};
+
+/// Read-only snapshot of Ricer's configuration.
+///
+/// Meant for third-party tools like shell prompts that want to inspect
+/// Ricer's configuration without any risk of mutating it. A snapshot holds
+/// fully deserialized settings only, so it is cheap to clone and can be sent
+/// across threads freely.
+///
+/// # Invariants
+///
+/// 1. Never creates or writes to configuration files. Missing configuration
+///    files are treated as empty.
+/// 1. Entries are listed in the order they appear in their configuration
+///    file.
+/// 1. Local overlays of configuration files are merged in, just like
+///    [`ConfigFile`] does.
+///
+/// # See also
+///
+/// - [`ConfigFile`]
+///
+/// [`ConfigFile`]: crate::config::ConfigFile
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ConfigSnapshot {
+    repos: Vec<RepoSettings>,
+    hooks: Vec<CmdHookSettings>,
+    features: Vec<FeatureSettings>,
+    ui: UiSettings,
+}
+
+impl ConfigSnapshot {
+    /// Take snapshot of all configuration files at expected locations.
+    ///
+    /// # Errors
+    ///
+    /// 1. Return [`ConfigFileError::FileOpen`] or [`ConfigFileError::FileRead`]
+    ///    if an existing configuration file could not be read.
+    /// 1. Return [`ConfigFileError::TooLarge`] if a configuration file is
+    ///    larger than [`MAX_CONFIG_SIZE`].
+    /// 1. Return [`ConfigFileError::Toml`] if a configuration file could not
+    ///    be parsed.
+    ///
+    /// [`MAX_CONFIG_SIZE`]: crate::config::MAX_CONFIG_SIZE
+    pub fn load(locator: &impl Locator) -> Result<Self, ConfigFileError> {
+        Ok(Self {
+            repos: read_entries(RepoConfig, locator)?,
+            hooks: read_entries(CmdHookConfig, locator)?,
+            features: read_entries(FeatureConfig, locator)?,
+            ui: UiConfig::load(locator)?,
+        })
+    }
+
+    pub fn repos(&self) -> &[RepoSettings] {
+        self.repos.as_slice()
+    }
+
+    pub fn repo(&self, name: impl AsRef<str>) -> Option<&RepoSettings> {
+        self.repos.iter().find(|repo| repo.name == name.as_ref())
+    }
+
+    pub fn cmd_hooks(&self) -> &[CmdHookSettings] {
+        self.hooks.as_slice()
+    }
+
+    pub fn cmd_hook(&self, cmd: impl AsRef<str>) -> Option<&CmdHookSettings> {
+        self.hooks.iter().find(|hook| hook.cmd == cmd.as_ref())
+    }
+
+    pub fn ui(&self) -> &UiSettings {
+        &self.ui
+    }
+
+    /// Determine if feature is enabled.
+    ///
+    /// Features that are not configured are enabled.
+    pub fn is_enabled(&self, feature: impl AsRef<str>) -> bool {
+        self.features
+            .iter()
+            .find(|settings| settings.name == feature.as_ref())
+            .map_or(true, |settings| settings.enabled)
+    }
+}
+
+fn read_entries<C: Config>(
+    config: C,
+    locator: &impl Locator,
+) -> Result<Vec<C::Entry>, ConfigFileError> {
+    let path = config.location(locator);
+    let doc = match read_merged_if_exists(path)? {
+        Some(doc) => doc,
+        None => return Ok(Vec::new()),
+    };
+
+    entries_or_empty(&config, &doc)
+        .map_err(|err| ConfigFileError::Toml { source: err, path: path.into() })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::{BootstrapSettings, HookSettings},
+        locate::MockLocator,
+        testenv::{FileKind, FixtureHarness},
+    };
+
+    use anyhow::Result;
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+    use rstest::{fixture, rstest};
+
+    #[fixture]
+    fn config_dir() -> Result<FixtureHarness> {
+        let harness = FixtureHarness::open()?
+            .with_file("repos.toml", |fixture| {
+                fixture
+                    .with_data(indoc! {r#"
+                        [repos.vim]
+                        branch = "master"
+                        remote = "origin"
+                        workdir_home = true
+
+                        [repos.dwm]
+                        branch = "main"
+                        remote = "origin"
+                        workdir_home = false
+
+                        [repos.dwm.bootstrap]
+                        clone = "https://some/url"
+                    "#})
+                    .with_kind(FileKind::Normal)
+            })
+            .with_file("hooks.toml", |fixture| {
+                fixture
+                    .with_data(indoc! {r#"
+                        [hooks]
+                        commit = [
+                            { pre = "hook.sh" }
+                        ]
+                    "#})
+                    .with_kind(FileKind::Normal)
+            })
+            .setup()?;
+        Ok(harness)
+    }
+
+    fn assert_embeddable<T: Clone + Send + Sync>() {}
+
+    #[test]
+    fn config_snapshot_is_embeddable() {
+        assert_embeddable::<ConfigSnapshot>();
+    }
+
+    #[rstest]
+    fn config_snapshot_load_return_self(config_dir: Result<FixtureHarness>) -> Result<()> {
+        let config_dir = config_dir?;
+        let mut locator = MockLocator::new();
+        locator.expect_repos_config().return_const(config_dir.as_path().join("repos.toml"));
+        locator.expect_hooks_config().return_const(config_dir.as_path().join("hooks.toml"));
+        locator.expect_ricer_config().return_const(config_dir.as_path().join("config.toml"));
+
+        let snapshot = ConfigSnapshot::load(&locator)?;
+        let expect = vec![
+            RepoSettings::new("vim").branch("master").remote("origin").workdir_home(true),
+            RepoSettings::new("dwm")
+                .branch("main")
+                .remote("origin")
+                .bootstrap(BootstrapSettings::new().clone("https://some/url")),
+        ];
+        assert_eq!(snapshot.repos(), expect);
+        assert_eq!(snapshot.repo("dwm"), Some(&expect[1]));
+        assert_eq!(
+            snapshot.cmd_hook("commit"),
+            Some(&CmdHookSettings::new("commit").add_hook(HookSettings::new().pre("hook.sh")))
+        );
+        assert!(snapshot.is_enabled("state"));
+
+        // INVARIANT: missing configuration files must not be created.
+        assert!(!config_dir.as_path().join("config.toml").exists());
+        Ok(())
+    }
+}
@@ -1,20 +1,98 @@
 // SPDX-FileCopyrightText: 2024 Jason Pena <jasonpena@awkless.com>
 // SPDX-License-Identifier: MIT
 
+use crate::config::SettingsError;
+
 use log::{debug, info, trace};
 use std::{fmt, str::FromStr};
-use toml_edit::{DocumentMut, Item, Key, Table};
+use toml_edit::{
+    visit_mut::{self, VisitMut},
+    DocumentMut, Formatted, Item, Key, Table, TableLike, Value,
+};
+
+/// Top-level section of Ricer's configuration files.
+///
+/// Every table of configuration data that Ricer reads or writes is named
+/// through a section, so a misspelled section name is a compile error instead
+/// of a silently missing table. Adding a section is a matter of adding a
+/// variant here.
+///
+/// Sections are accepted wherever [`Toml`] expects a table name.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum Section {
+    /// Repository settings of the repository configuration file.
+    Repos,
+
+    /// Repository groups of the repository configuration file.
+    Groups,
+
+    /// Command hook definitions of the command hook configuration file.
+    Hooks,
+
+    /// Feature toggles of Ricer's general configuration file.
+    Features,
+
+    /// Terminal output settings of Ricer's general configuration file.
+    Ui,
+
+    /// Background fetch daemon settings of Ricer's general configuration
+    /// file.
+    Daemon,
+
+    /// Hook output log settings of Ricer's general configuration file.
+    HookLogs,
+
+    /// Commit message lint rules of Ricer's general configuration file.
+    Lint,
+
+    /// Per-command defaults of Ricer's general configuration file.
+    Commands,
+}
+
+impl Section {
+    /// Name of section's table in configuration file.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Section::Repos => "repos",
+            Section::Groups => "groups",
+            Section::Hooks => "hooks",
+            Section::Features => "features",
+            Section::Ui => "ui",
+            Section::Daemon => "daemon",
+            Section::HookLogs => "hook_logs",
+            Section::Lint => "lint",
+            Section::Commands => "commands",
+        }
+    }
+}
+
+impl AsRef<str> for Section {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl fmt::Display for Section {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
 
 /// TOML parser.
 ///
 /// Offers basic CRUD interface for TOML parsing. Expects TOML data in string
 /// form. Leaves file handling to caller. Mainly operates on whole tables for
 /// key-value pair manipulation. Note, that `document` is terminology used to
-/// refer to parsed TOML data.
+/// refer to parsed TOML data. Nested tables, e.g., `[repos.vim.bootstrap]`, or
+/// tables in arrays of tables are reached through paths of key segments
+/// instead, see [`Toml::get_nested`].
 ///
 /// # Invariants
 ///
 /// 1. Preserve original formatting of document.
+/// 1. Entries added into a table whose keys are sorted keep it sorted, so
+///    entries added in bulk always end up in the same order. Tables that
+///    are not sorted, e.g., ordered by hand, only ever get entries appended.
 ///
 /// # See also
 ///
@@ -39,6 +117,12 @@ pub fn new() -> Self {
     /// old entry that was replaced. If no replacement took place, then `None`
     /// is returned instead.
     ///
+    /// # Invariants
+    ///
+    /// 1. Replaced entries keep their position.
+    /// 1. New entries are inserted before the first entry whose key sorts
+    ///    after theirs if the table is sorted, and appended otherwise.
+    ///
     /// # Errors
     ///
     /// - Return [`TomlError::NotTable`] if target table was not defined as
@@ -62,10 +146,39 @@ pub fn add(
             }
             Err(err) => return Err(err),
         };
-        let entry = entry.insert(key.get(), value).map(|old| (key, old));
+        let entry = insert_ordered(entry, &key, value).map(|old| (key, old));
         Ok(entry)
     }
 
+    /// Add TOML entry into document, unless its key is already taken.
+    ///
+    /// Behaves like [`Toml::add`], but never replaces existing entries.
+    ///
+    /// # Errors
+    ///
+    /// - Return [`TomlError::EntryExists`] if target table already has an
+    ///   entry with the key of `entry`.
+    /// - Return [`TomlError::NotTable`] if target table was not defined as
+    ///   a table.
+    ///
+    /// [`TomlError::EntryExists`]: crate::config::TomlError::EntryExists
+    /// [`TomlError::NotTable`]: crate::config::TomlError::NotTable
+    pub fn add_new(&mut self, table: impl AsRef<str>, entry: (Key, Item)) -> Result<(), TomlError> {
+        match self.get(table.as_ref(), entry.0.get()) {
+            Ok(_) => {
+                return Err(TomlError::EntryExists {
+                    table: table.as_ref().into(),
+                    key: entry.0.get().into(),
+                })
+            }
+            Err(TomlError::EntryNotFound { .. } | TomlError::TableNotFound { .. }) => (),
+            Err(err) => return Err(err),
+        }
+
+        self.add(table, entry)?;
+        Ok(())
+    }
+
     /// Get entry from target table in document.
     ///
     /// Return reference to full key-value pair in document.
@@ -82,10 +195,11 @@ pub fn add(
     /// [`TomlError::TableNotFound`]: crate::config::TomlError::TableNotFound
     /// [`TomlError::NotTable`]: crate::config::TomlError::NotTable
     /// [`TomlError::EntryNotFound`]: crate::config::TomlError::EntryNotFound
-    pub fn get<S>(&self, table: S, key: S) -> Result<(&Key, &Item), TomlError>
-    where
-        S: AsRef<str>,
-    {
+    pub fn get(
+        &self,
+        table: impl AsRef<str>,
+        key: impl AsRef<str>,
+    ) -> Result<(&Key, &Item), TomlError> {
         info!("Get TOML entry '{}' from '{}' table", key.as_ref(), table.as_ref());
         let entry = self.get_table(table.as_ref())?;
         let entry = entry.get_key_value(key.as_ref()).ok_or_else(|| TomlError::EntryNotFound {
@@ -111,10 +225,12 @@ pub fn get<S>(&self, table: S, key: S) -> Result<(&Key, &Item), TomlError>
     /// [`TomlError::TableNotFound`]: crate::config::TomlError::TableNotFound
     /// [`TomlError::NotTable`]: crate::config::TomlError::NotTable
     /// [`TomlError::EntryNotFound`]: crate::config::TomlError::EntryNotFound
-    pub fn rename<S>(&mut self, table: S, from: S, to: S) -> Result<(Key, Item), TomlError>
-    where
-        S: AsRef<str>,
-    {
+    pub fn rename(
+        &mut self,
+        table: impl AsRef<str>,
+        from: impl AsRef<str>,
+        to: impl AsRef<str>,
+    ) -> Result<(Key, Item), TomlError> {
         let entry = self.get_table_mut(table.as_ref())?;
         let (old_key, old_item) = entry.remove_entry(from.as_ref()).ok_or_else(|| {
             TomlError::EntryNotFound { table: table.as_ref().into(), key: from.as_ref().into() }
@@ -143,10 +259,11 @@ pub fn rename<S>(&mut self, table: S, from: S, to: S) -> Result<(Key, Item), Tom
     /// [`TomlError::TableNotFound`]: crate::config::TomlError::TableNotFound
     /// [`TomlError::NotTable`]: crate::config::TomlError::NotTable
     /// [`TomlError::EntryNotFound`]: crate::config::TomlError::EntryNotFound
-    pub fn remove<S>(&mut self, table: S, key: S) -> Result<(Key, Item), TomlError>
-    where
-        S: AsRef<str>,
-    {
+    pub fn remove(
+        &mut self,
+        table: impl AsRef<str>,
+        key: impl AsRef<str>,
+    ) -> Result<(Key, Item), TomlError> {
         let entry = self.get_table_mut(table.as_ref())?;
         let entry = entry.remove_entry(key.as_ref()).ok_or_else(|| TomlError::EntryNotFound {
             table: table.as_ref().into(),
@@ -155,6 +272,169 @@ pub fn remove<S>(&mut self, table: S, key: S) -> Result<(Key, Item), TomlError>
         Ok(entry)
     }
 
+    /// Get entry from nested table at `path` in document.
+    ///
+    /// Every segment of `path` names a sub-table of the table before it, e.g.,
+    /// `["repos", "vim", "bootstrap"]`. Segments are taken as is, so keys that
+    /// hold dots like `"nvim.lua"` need no quoting. A segment naming an array
+    /// of tables must be followed by the index of one of its tables counting
+    /// from 0, e.g., `["hooks", "0"]` for the first `[[hooks]]` table.
+    ///
+    /// # Errors
+    ///
+    /// - Return [`TomlError::TableNotFound`] if a table of `path` is not
+    ///   found in document.
+    /// - Return [`TomlError::NotTable`] if a table of `path` was not defined
+    ///   as a table, or array of tables.
+    /// - Return [`TomlError::EntryNotFound`] if target key-value pair
+    ///   is not found in document.
+    ///
+    /// [`TomlError::TableNotFound`]: crate::config::TomlError::TableNotFound
+    /// [`TomlError::NotTable`]: crate::config::TomlError::NotTable
+    /// [`TomlError::EntryNotFound`]: crate::config::TomlError::EntryNotFound
+    pub fn get_nested(
+        &self,
+        path: &[&str],
+        key: impl AsRef<str>,
+    ) -> Result<(&Key, &Item), TomlError> {
+        let table = path_prefix(path, path.len());
+        info!("Get TOML entry '{}' from '{table}' table", key.as_ref());
+        let entry = nested_table(self.doc.as_table(), path)?;
+        let entry = entry
+            .get_key_value(key.as_ref())
+            .ok_or_else(|| TomlError::EntryNotFound { table, key: key.as_ref().into() })?;
+        Ok(entry)
+    }
+
+    /// Add TOML entry into nested table at `path` in document, see
+    /// [`Toml::get_nested`] for segments of `path`.
+    ///
+    /// Behaves like [`Toml::add`], but creates every missing table of `path`
+    /// along the way. Indexing one past the last table of an array of tables
+    /// appends a new table to it.
+    ///
+    /// # Invariants
+    ///
+    /// 1. Replaced entries keep their position.
+    /// 1. Tables created along `path` are implicit, so only tables that
+    ///    actually hold entries show up in the document.
+    ///
+    /// # Errors
+    ///
+    /// - Return [`TomlError::TableNotFound`] if an index of `path` is out of
+    ///   bounds of its array of tables.
+    /// - Return [`TomlError::NotTable`] if a table of `path` was not defined
+    ///   as a table, or array of tables.
+    ///
+    /// [`TomlError::TableNotFound`]: crate::config::TomlError::TableNotFound
+    /// [`TomlError::NotTable`]: crate::config::TomlError::NotTable
+    pub fn add_nested(
+        &mut self,
+        path: &[&str],
+        entry: (Key, Item),
+    ) -> Result<Option<(Key, Item)>, TomlError> {
+        let (key, value) = entry;
+        info!("Add TOML entry '{}' to '{}' table", key.get(), path_prefix(path, path.len()));
+        let table = nested_table_mut(self.doc.as_table_mut(), path, true)?;
+        let entry = insert_ordered(table, &key, value).map(|old| (key, old));
+        Ok(entry)
+    }
+
+    /// Remove TOML entry from nested table at `path` in document, see
+    /// [`Toml::get_nested`] for segments of `path`.
+    ///
+    /// Remove `key` from target table. Returns removed entry. Tables of `path`
+    /// are kept, even if they end up empty.
+    ///
+    /// # Errors
+    ///
+    /// - Return [`TomlError::TableNotFound`] if a table of `path` is not
+    ///   found in document.
+    /// - Return [`TomlError::NotTable`] if a table of `path` was not defined
+    ///   as a table, or array of tables.
+    /// - Return [`TomlError::EntryNotFound`] if target key-value pair
+    ///   is not found in document.
+    ///
+    /// [`TomlError::TableNotFound`]: crate::config::TomlError::TableNotFound
+    /// [`TomlError::NotTable`]: crate::config::TomlError::NotTable
+    /// [`TomlError::EntryNotFound`]: crate::config::TomlError::EntryNotFound
+    pub fn remove_nested(
+        &mut self,
+        path: &[&str],
+        key: impl AsRef<str>,
+    ) -> Result<(Key, Item), TomlError> {
+        let table = path_prefix(path, path.len());
+        info!("Remove TOML entry '{}' from '{table}' table", key.as_ref());
+        let entry = nested_table_mut(self.doc.as_table_mut(), path, false)?;
+        let entry = entry
+            .remove_entry(key.as_ref())
+            .ok_or_else(|| TomlError::EntryNotFound { table, key: key.as_ref().into() })?;
+        Ok(entry)
+    }
+
+    /// Get all entries of target table in document.
+    ///
+    /// Entries are listed in the order they appear in the document.
+    ///
+    /// # Errors
+    ///
+    /// - Return [`TomlError::TableNotFound`] if target table is not found
+    ///   in document.
+    /// - Return [`TomlError::NotTable`] if target table was not defined as
+    ///   a table.
+    ///
+    /// [`TomlError::TableNotFound`]: crate::config::TomlError::TableNotFound
+    /// [`TomlError::NotTable`]: crate::config::TomlError::NotTable
+    pub fn entries(&self, table: impl AsRef<str>) -> Result<Vec<(&Key, &Item)>, TomlError> {
+        let table = self.get_table(table.as_ref())?;
+        let entries = table.iter().filter_map(|(key, _)| table.get_key_value(key)).collect();
+        Ok(entries)
+    }
+
+    /// List dotted path of every key in document, including keys of tables.
+    ///
+    /// Keys of inline tables are listed too, but keys of tables inside arrays
+    /// are not.
+    pub fn key_paths(&self) -> Vec<Vec<&str>> {
+        let mut paths = Vec::new();
+        collect_key_paths(self.doc.as_table(), &mut Vec::new(), &mut paths);
+        paths
+    }
+
+    /// Merge `overlay` into document.
+    ///
+    /// Tables of `overlay` are merged into tables of document key by key, while
+    /// any other item of `overlay` replaces the item of document with the same
+    /// key.
+    pub fn merge(&mut self, overlay: &Toml) {
+        merge_table(self.doc.as_table_mut(), overlay.doc.as_table());
+    }
+
+    /// Undo values of `overlay` that leaked into document since it was `base`.
+    ///
+    /// Entries read through a document merged with `overlay`, and then written
+    /// back, carry the overridden values with them. Every value of document
+    /// that is equal to the value of `overlay` is restored to the value of
+    /// `base`, or removed if `base` did not have it. Values that `base` already
+    /// had are kept as is.
+    pub(crate) fn unmerge(&mut self, overlay: &Toml, base: &Toml) {
+        unmerge_table(self.doc.as_table_mut(), overlay.doc.as_table(), Some(base.doc.as_table()));
+    }
+
+    /// Rewrite every string value of document through `rewrite`, which gives
+    /// the new value, or `None` to keep the value as is.
+    ///
+    /// Will return how many values were rewritten.
+    ///
+    /// # Invariants
+    ///
+    /// 1. Rewritten values keep their surrounding whitespace and comments.
+    pub fn rewrite_strings(&mut self, rewrite: impl FnMut(&str) -> Option<String>) -> usize {
+        let mut rewriter = StringRewriter { rewrite, count: 0 };
+        rewriter.visit_document_mut(&mut self.doc);
+        rewriter.count
+    }
+
     /// Get target table in document.
     ///
     /// Return reference to target table in document.
@@ -168,7 +448,8 @@ pub fn remove<S>(&mut self, table: S, key: S) -> Result<(Key, Item), TomlError>
     ///
     /// [`TomlError::TableNotFound`]: crate::config::TomlError::TableNotFound
     /// [`TomlError::NotTable`]: crate::config::TomlError::NotTable
-    pub(crate) fn get_table(&self, key: &str) -> Result<&Table, TomlError> {
+    pub(crate) fn get_table(&self, key: impl AsRef<str>) -> Result<&Table, TomlError> {
+        let key = key.as_ref();
         debug!("Get TOML table '{key}'");
         let table =
             self.doc.get(key).ok_or_else(|| TomlError::TableNotFound { table: key.into() })?;
@@ -189,7 +470,8 @@ pub(crate) fn get_table(&self, key: &str) -> Result<&Table, TomlError> {
     ///
     /// [`TomlError::TableNotFound`]: crate::config::TomlError::TableNotFound
     /// [`TomlError::NotTable`]: crate::config::TomlError::NotTable
-    pub(crate) fn get_table_mut(&mut self, key: &str) -> Result<&mut Table, TomlError> {
+    pub(crate) fn get_table_mut(&mut self, key: impl AsRef<str>) -> Result<&mut Table, TomlError> {
+        let key = key.as_ref();
         debug!("Get mutable TOML table '{key}'");
         let table =
             self.doc.get_mut(key).ok_or_else(|| TomlError::TableNotFound { table: key.into() })?;
@@ -199,6 +481,222 @@ pub(crate) fn get_table_mut(&mut self, key: &str) -> Result<&mut Table, TomlErro
     }
 }
 
+/// Visitor of [`Toml::rewrite_strings`].
+struct StringRewriter<F> {
+    rewrite: F,
+    count: usize,
+}
+
+impl<F> VisitMut for StringRewriter<F>
+where
+    F: FnMut(&str) -> Option<String>,
+{
+    fn visit_value_mut(&mut self, node: &mut Value) {
+        if let Value::String(string) = node {
+            if let Some(value) = (self.rewrite)(string.value()) {
+                let decor = string.decor().clone();
+                *string = Formatted::new(value);
+                *string.decor_mut() = decor;
+                self.count += 1;
+            }
+        }
+        visit_mut::visit_value_mut(self, node);
+    }
+}
+
+/// Join first `len` segments of table path in dotted key syntax, quoting
+/// segments that are not bare keys.
+fn path_prefix(segments: &[&str], len: usize) -> String {
+    let segments = segments[..len.min(segments.len())].iter();
+    segments
+        .map(|segment| Key::new(*segment).display_repr().into_owned())
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/// Index of array of tables named by segment `at` of `path`, given by the
+/// segment after it.
+fn array_index(path: &[&str], at: usize) -> Result<usize, TomlError> {
+    let index = path.get(at + 1).and_then(|index| index.parse::<usize>().ok());
+    index.ok_or_else(|| TomlError::NotTable { table: path_prefix(path, at + 1) })
+}
+
+/// Get nested table at `path` of `table`.
+///
+/// Inline tables are not walked into, since [`Toml::add_nested`] cannot add
+/// sub-tables to them without changing their form.
+fn nested_table<'toml>(mut table: &'toml Table, path: &[&str]) -> Result<&'toml Table, TomlError> {
+    let mut at = 0;
+    while at < path.len() {
+        let item = table
+            .get(path[at])
+            .ok_or_else(|| TomlError::TableNotFound { table: path_prefix(path, at + 1) })?;
+        table = match item {
+            Item::Table(next) => next,
+            Item::ArrayOfTables(array) => {
+                let index = array_index(path, at)?;
+                at += 1;
+                array
+                    .get(index)
+                    .ok_or_else(|| TomlError::TableNotFound { table: path_prefix(path, at + 1) })?
+            }
+            _ => return Err(TomlError::NotTable { table: path_prefix(path, at + 1) }),
+        };
+        at += 1;
+    }
+    Ok(table)
+}
+
+/// Get mutable nested table at `path` of `table`, creating missing tables
+/// along the way if asked to.
+fn nested_table_mut<'toml>(
+    mut table: &'toml mut Table,
+    path: &[&str],
+    create: bool,
+) -> Result<&'toml mut Table, TomlError> {
+    let mut at = 0;
+    while at < path.len() {
+        let key = path[at];
+        if create && !table.contains_key(key) {
+            let mut new_table = Table::new();
+            new_table.set_implicit(true);
+            table.insert(key, Item::Table(new_table));
+        }
+
+        let item = table
+            .get_mut(key)
+            .ok_or_else(|| TomlError::TableNotFound { table: path_prefix(path, at + 1) })?;
+        table = match item {
+            Item::Table(next) => next,
+            Item::ArrayOfTables(array) => {
+                let index = array_index(path, at)?;
+                at += 1;
+                if create && index == array.len() {
+                    array.push(Table::new());
+                }
+                array
+                    .get_mut(index)
+                    .ok_or_else(|| TomlError::TableNotFound { table: path_prefix(path, at + 1) })?
+            }
+            _ => return Err(TomlError::NotTable { table: path_prefix(path, at + 1) }),
+        };
+        at += 1;
+    }
+    Ok(table)
+}
+
+fn merge_table(doc: &mut dyn TableLike, overlay: &dyn TableLike) {
+    for (key, item) in overlay.iter() {
+        let current = doc.get_mut(key);
+        match (current.and_then(Item::as_table_like_mut), item.as_table_like()) {
+            (Some(current), Some(item)) => merge_table(current, item),
+            _ => {
+                doc.insert(key, item.clone());
+            }
+        }
+    }
+}
+
+fn unmerge_table(doc: &mut dyn TableLike, overlay: &dyn TableLike, base: Option<&dyn TableLike>) {
+    for (key, item) in overlay.iter() {
+        let prev = base.and_then(|base| base.get(key));
+        let Some(current) = doc.get_mut(key) else {
+            continue;
+        };
+
+        if let (Some(current), Some(item)) = (current.as_table_like_mut(), item.as_table_like()) {
+            unmerge_table(current, item, prev.and_then(Item::as_table_like));
+            continue;
+        }
+
+        if !item_eq(current, item) || prev.is_some_and(|prev| item_eq(prev, item)) {
+            continue;
+        }
+        match prev {
+            Some(prev) => *current = prev.clone(),
+            None => {
+                doc.remove(key);
+            }
+        }
+    }
+}
+
+/// Insert `item` at `key` of `table`, keeping sorted tables sorted.
+fn insert_ordered(table: &mut Table, key: &Key, item: Item) -> Option<Item> {
+    let keys = table.iter().map(|(key, _)| key.to_string()).collect::<Vec<_>>();
+    let sorted = keys.windows(2).all(|pair| pair[0] <= pair[1]);
+    let index = keys.iter().position(|next| next.as_str() > key.get());
+    let index = match (table.contains_key(key.get()), sorted, index) {
+        (false, true, Some(index)) => index,
+        _ => return table.insert(key.get(), item),
+    };
+
+    // INVARIANT: move later entries behind new entry with their formatting intact.
+    let mut later =
+        keys[index..].iter().filter_map(|key| table.remove_entry(key)).collect::<Vec<_>>();
+    if let Some(first) = later.first_mut().and_then(|(_, item)| item.as_table_mut()) {
+        // INVARIANT: former first table gets a blank line above it like any other table.
+        let bare = first.decor().prefix().and_then(|prefix| prefix.as_str()) == Some("");
+        if index == 0 && bare {
+            first.decor_mut().clear();
+        }
+    }
+    table.insert(key.get(), item);
+    for (key, item) in later {
+        table.insert_formatted(&key, item);
+    }
+    None
+}
+
+/// Compare items by value, ignoring formatting.
+fn item_eq(a: &Item, b: &Item) -> bool {
+    match (a, b) {
+        (Item::Value(a), Item::Value(b)) => value_eq(a, b),
+        (Item::ArrayOfTables(a), Item::ArrayOfTables(b)) => {
+            a.len() == b.len() && a.iter().zip(b.iter()).all(|(a, b)| table_eq(a, b))
+        }
+        (Item::None, Item::None) => true,
+        _ => match (a.as_table_like(), b.as_table_like()) {
+            (Some(a), Some(b)) => table_eq(a, b),
+            _ => false,
+        },
+    }
+}
+
+fn value_eq(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::String(a), Value::String(b)) => a.value() == b.value(),
+        (Value::Integer(a), Value::Integer(b)) => a.value() == b.value(),
+        (Value::Float(a), Value::Float(b)) => a.value() == b.value(),
+        (Value::Boolean(a), Value::Boolean(b)) => a.value() == b.value(),
+        (Value::Datetime(a), Value::Datetime(b)) => a.value() == b.value(),
+        (Value::Array(a), Value::Array(b)) => {
+            a.len() == b.len() && a.iter().zip(b.iter()).all(|(a, b)| value_eq(a, b))
+        }
+        (Value::InlineTable(a), Value::InlineTable(b)) => table_eq(a, b),
+        _ => false,
+    }
+}
+
+fn table_eq(a: &dyn TableLike, b: &dyn TableLike) -> bool {
+    a.len() == b.len() && a.iter().all(|(key, a)| b.get(key).is_some_and(|b| item_eq(a, b)))
+}
+
+fn collect_key_paths<'toml>(
+    table: &'toml dyn TableLike,
+    parent: &mut Vec<&'toml str>,
+    paths: &mut Vec<Vec<&'toml str>>,
+) {
+    for (key, item) in table.iter() {
+        parent.push(key);
+        paths.push(parent.clone());
+        if let Some(table) = item.as_table_like() {
+            collect_key_paths(table, parent, paths);
+        }
+        parent.pop();
+    }
+}
+
 impl fmt::Display for Toml {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}", self.doc)
@@ -228,6 +726,12 @@ pub enum TomlError {
 
     #[error("TOML entry '{key}' not found in table '{table}'")]
     EntryNotFound { table: String, key: String },
+
+    #[error("TOML entry '{key}' already exists in table '{table}'")]
+    EntryExists { table: String, key: String },
+
+    #[error("TOML entry '{key}' in table '{table}' has invalid settings")]
+    BadSettings { table: String, key: String, source: Box<SettingsError> },
 }
 
 #[cfg(test)]
@@ -238,7 +742,6 @@ mod tests {
     use indoc::{formatdoc, indoc};
     use pretty_assertions::assert_eq;
     use rstest::{fixture, rstest};
-    use toml_edit::Value;
 
     #[fixture]
     fn toml_input() -> String {
@@ -268,6 +771,21 @@ fn toml_parse_str_return_err_bad_parse(
         assert!(matches!(result.unwrap_err(), TomlError::BadParse { .. }));
     }
 
+    #[rstest]
+    #[case::repos(Section::Repos, "[repos.vim]\nbranch = 'main'\n", "vim")]
+    #[case::commands(Section::Commands, "[commands.fetch]\njobs = 4\n", "fetch")]
+    fn toml_get_reach_section_by_name(
+        #[case] section: Section,
+        #[case] input: &str,
+        #[case] key: &str,
+    ) -> Result<()> {
+        let toml: Toml = input.parse()?;
+        let (result, _) = toml.get(section, key)?;
+        assert_eq!(result.get(), key);
+        assert_eq!(section.to_string(), section.as_str());
+        Ok(())
+    }
+
     #[rstest]
     #[case("test", "foo", (Key::new("foo"), Item::Value(Value::from("hello"))))]
     #[case("test", "bar", (Key::new("bar"), Item::Value(Value::from(true))))]
@@ -357,6 +875,70 @@ fn toml_add_return_some_key_item(
         Ok(())
     }
 
+    #[rstest]
+    #[case::sorted(
+        indoc! {r#"
+            [repos.bar]
+            url = "bar"
+
+            # keep me with baz
+            [repos.baz]
+            url = "baz"
+        "#},
+        indoc! {r#"
+            [repos.aaa]
+            url = "aaa"
+
+            [repos.bar]
+            url = "bar"
+
+            [repos.bat]
+            url = "bat"
+
+            # keep me with baz
+            [repos.baz]
+            url = "baz"
+
+            [repos.zzz]
+            url = "zzz"
+        "#}
+    )]
+    #[case::unsorted(
+        indoc! {r#"
+            [repos.baz]
+            url = "baz"
+
+            [repos.bar]
+            url = "bar"
+        "#},
+        indoc! {r#"
+            [repos.baz]
+            url = "baz"
+
+            [repos.bar]
+            url = "bar"
+
+            [repos.zzz]
+            url = "zzz"
+
+            [repos.aaa]
+            url = "aaa"
+
+            [repos.bat]
+            url = "bat"
+        "#}
+    )]
+    fn toml_add_keep_sorted_tables_sorted(#[case] input: &str, #[case] expect: &str) -> Result<()> {
+        let mut toml: Toml = input.parse()?;
+        for name in ["zzz", "aaa", "bat"] {
+            let mut table = Table::new();
+            table.insert("url", toml_edit::value(name));
+            toml.add(Section::Repos, (Key::new(name), Item::Table(table)))?;
+        }
+        assert_eq!(toml.to_string(), expect);
+        Ok(())
+    }
+
     #[rstest]
     #[case::not_table("foo = 'not a table'", TomlError::NotTable { table: "foo".into() })]
     fn toml_add_return_err(#[case] input: &str, #[case] expect: TomlError) -> Result<()> {
@@ -367,6 +949,29 @@ fn toml_add_return_err(#[case] input: &str, #[case] expect: TomlError) -> Result
         Ok(())
     }
 
+    #[rstest]
+    #[case::add_into_table("test", "baz", Ok(()), "new")]
+    #[case::create_new_table("new_test", "foo", Ok(()), "new")]
+    #[case::entry_exists(
+        "test",
+        "foo",
+        Err(TomlError::EntryExists { table: "test".into(), key: "foo".into() }),
+        "hello"
+    )]
+    fn toml_add_new_never_replace(
+        toml_input: String,
+        #[case] table: &str,
+        #[case] key: &str,
+        #[case] expect: Result<(), TomlError>,
+        #[case] value: &str,
+    ) -> Result<()> {
+        let mut toml: Toml = toml_input.parse()?;
+        let result = toml.add_new(table, (Key::new(key), Item::Value(Value::from("new"))));
+        assert_eq!(result, expect);
+        assert_eq!(toml.get(table, key)?.1.as_str(), Some(value));
+        Ok(())
+    }
+
     #[rstest]
     #[case(
         toml_input(),
@@ -451,4 +1056,272 @@ fn toml_remove_return_err(#[case] input: &str, #[case] expect: TomlError) -> Res
         assert_eq!(result.unwrap_err(), expect);
         Ok(())
     }
+
+    #[fixture]
+    fn nested_input() -> String {
+        String::from(indoc! {r#"
+            # keep this comment!
+            [repos.vim]
+            branch = "main"
+
+            [repos.vim.bootstrap]
+            clone = "https://some/url"
+            os = "unix"
+
+            [[repos.vim.hooks]]
+            pre = "first.sh"
+
+            [[repos.vim.hooks]]
+            pre = "second.sh"
+        "#})
+    }
+
+    #[rstest]
+    #[case::table(&["repos", "vim"], "branch", "main")]
+    #[case::sub_table(&["repos", "vim", "bootstrap"], "os", "unix")]
+    #[case::array_of_tables(&["repos", "vim", "hooks", "1"], "pre", "second.sh")]
+    fn toml_get_nested_return_key_item(
+        nested_input: String,
+        #[case] path: &[&str],
+        #[case] key: &str,
+        #[case] expect: &str,
+    ) -> Result<()> {
+        let toml: Toml = nested_input.parse()?;
+        let (result_key, result_value) = toml.get_nested(path, key)?;
+        assert_eq!(result_key.get(), key);
+        assert_eq!(result_value.as_str(), Some(expect));
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::table_not_found(&["repos", "dwm"], TomlError::TableNotFound { table: "repos.dwm".into() })]
+    #[case::not_table(&["repos", "vim", "branch"], TomlError::NotTable { table: "repos.vim.branch".into() })]
+    #[case::no_index(&["repos", "vim", "hooks"], TomlError::NotTable { table: "repos.vim.hooks".into() })]
+    #[case::index_out_of_bounds(
+        &["repos", "vim", "hooks", "2"],
+        TomlError::TableNotFound { table: "repos.vim.hooks.2".into() }
+    )]
+    #[case::entry_not_found(
+        &["repos", "vim", "bootstrap"],
+        TomlError::EntryNotFound { table: "repos.vim.bootstrap".into(), key: "users".into() }
+    )]
+    fn toml_get_nested_return_err(
+        nested_input: String,
+        #[case] path: &[&str],
+        #[case] expect: TomlError,
+    ) -> Result<()> {
+        let toml: Toml = nested_input.parse()?;
+        let result = toml.get_nested(path, "users");
+        assert_eq!(result.unwrap_err(), expect);
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::replace_in_sub_table(
+        &["repos", "vim", "bootstrap"],
+        "os",
+        nested_input().replace(r#"os = "unix""#, r#"os = "users""#),
+    )]
+    #[case::add_into_sub_table(
+        &["repos", "vim", "bootstrap"],
+        "users",
+        nested_input().replace("os = \"unix\"\n", "os = \"unix\"\nusers = \"users\"\n"),
+    )]
+    #[case::add_into_array_of_tables(
+        &["repos", "vim", "hooks", "0"],
+        "users",
+        nested_input().replace("pre = \"first.sh\"\n", "pre = \"first.sh\"\nusers = \"users\"\n"),
+    )]
+    #[case::append_to_array_of_tables(
+        &["repos", "vim", "hooks", "2"],
+        "users",
+        formatdoc! {r#"
+            {}
+            [[repos.vim.hooks]]
+            users = "users"
+        "#, nested_input()},
+    )]
+    #[case::create_sub_tables(
+        &["repos", "dwm", "bootstrap"],
+        "users",
+        formatdoc! {r#"
+            {}
+            [repos.dwm.bootstrap]
+            users = "users"
+        "#, nested_input()},
+    )]
+    fn toml_add_nested_keep_formatting(
+        #[case] path: &[&str],
+        #[case] key: &str,
+        #[case] expect: String,
+    ) -> Result<()> {
+        let mut toml: Toml = nested_input().parse()?;
+        toml.add_nested(path, (Key::new(key), Item::Value(Value::from("users"))))?;
+        assert_eq!(toml.to_string(), expect);
+        Ok(())
+    }
+
+    #[rstest]
+    fn toml_add_nested_return_err_not_table(nested_input: String) -> Result<()> {
+        let mut toml: Toml = nested_input.parse()?;
+        let entry = (Key::new("foo"), Item::Value(Value::from("bar")));
+        let result = toml.add_nested(&["repos", "vim", "branch", "deep"], entry);
+        assert_eq!(result.unwrap_err(), TomlError::NotTable { table: "repos.vim.branch".into() });
+        Ok(())
+    }
+
+    #[rstest]
+    fn toml_remove_nested_return_deleted_key_item(nested_input: String) -> Result<()> {
+        let mut toml: Toml = nested_input.parse()?;
+        let (key, item) = toml.remove_nested(&["repos", "vim", "bootstrap"], "clone")?;
+        assert_eq!(key.get(), "clone");
+        assert_eq!(item.as_str(), Some("https://some/url"));
+        assert_eq!(toml.to_string(), nested_input.replace("clone = \"https://some/url\"\n", ""));
+
+        let result = toml.remove_nested(&["repos", "vim", "hooks", "5"], "pre");
+        let expect = TomlError::TableNotFound { table: "repos.vim.hooks.5".into() };
+        assert_eq!(result.unwrap_err(), expect);
+        Ok(())
+    }
+
+    #[rstest]
+    fn toml_nested_reach_quoted_keys() -> Result<()> {
+        let mut toml: Toml = indoc! {r#"
+            [repos."nvim.lua".bootstrap]
+            os = "unix"
+        "#}
+        .parse()?;
+        let path = ["repos", "nvim.lua", "bootstrap"];
+        assert_eq!(toml.get_nested(&path, "os")?.1.as_str(), Some("unix"));
+        toml.add_nested(&path, (Key::new("users"), Item::Value(Value::from("users"))))?;
+        toml.remove_nested(&path, "os")?;
+        let expect = indoc! {r#"
+            [repos."nvim.lua".bootstrap]
+            users = "users"
+        "#};
+        assert_eq!(toml.to_string(), expect);
+
+        let result = toml.get_nested(&["repos", "nvim.lua", "hooks"], "pre");
+        let expect = TomlError::TableNotFound { table: r#"repos."nvim.lua".hooks"#.into() };
+        assert_eq!(result.unwrap_err(), expect);
+        Ok(())
+    }
+
+    #[rstest]
+    fn toml_entries_return_in_order(toml_input: String) -> Result<()> {
+        let toml: Toml = toml_input.parse()?;
+        let result =
+            toml.entries("test")?.into_iter().map(|(key, _)| key.get()).collect::<Vec<_>>();
+        assert_eq!(result, ["foo", "bar"]);
+        Ok(())
+    }
+
+    #[test]
+    fn toml_key_paths_list_nested_keys() -> Result<()> {
+        let toml: Toml = indoc! {r#"
+            top = 1
+            hooks = [{ pre = "a.sh" }]
+
+            [repos.vim]
+            branch = "main"
+            bootstrap = { os = "unix" }
+        "#}
+        .parse()?;
+        let result = toml.key_paths();
+        let expect: Vec<Vec<&str>> = vec![
+            vec!["top"],
+            vec!["hooks"],
+            vec!["repos"],
+            vec!["repos", "vim"],
+            vec!["repos", "vim", "branch"],
+            vec!["repos", "vim", "bootstrap"],
+            vec!["repos", "vim", "bootstrap", "os"],
+        ];
+        assert_eq!(result, expect);
+        Ok(())
+    }
+
+    #[test]
+    fn toml_rewrite_strings_keep_formatting() -> Result<()> {
+        let mut toml: Toml = indoc! {r#"
+            [repos.vim]
+            path = "/old/vim.git"   # Shared with home profile.
+            bootstrap = { hooks = ["/old/hook.sh", "other.sh"] }
+        "#}
+        .parse()?;
+        let count = toml.rewrite_strings(|value| {
+            value.strip_prefix("/old/").map(|rest| format!("/new/{rest}"))
+        });
+        let expect = indoc! {r#"
+            [repos.vim]
+            path = "/new/vim.git"   # Shared with home profile.
+            bootstrap = { hooks = ["/new/hook.sh", "other.sh"] }
+        "#};
+        assert_eq!(count, 2);
+        assert_eq!(toml.to_string(), expect);
+        Ok(())
+    }
+
+    #[test]
+    fn toml_merge_override_values_key_by_key() -> Result<()> {
+        let mut toml: Toml = indoc! {r#"
+            [repos.vim]
+            branch = "main"
+            remote = "origin"
+            bootstrap = { os = "unix", users = ["awkless"] }
+        "#}
+        .parse()?;
+        let overlay: Toml = indoc! {r#"
+            [repos.vim]
+            remote = "mirror"
+            bootstrap = { users = ["guest"] }
+
+            [repos.dwm]
+            branch = "main"
+        "#}
+        .parse()?;
+        toml.merge(&overlay);
+        let expect = indoc! {r#"
+            [repos.vim]
+            branch = "main"
+            remote = "mirror"
+            bootstrap = { os = "unix", users = ["guest"] }
+
+            [repos.dwm]
+            branch = "main"
+        "#};
+        assert_eq!(toml.to_string(), expect);
+        Ok(())
+    }
+
+    #[test]
+    fn toml_unmerge_restore_overridden_values() -> Result<()> {
+        let base: Toml = indoc! {r#"
+            [repos.vim]
+            branch = "main"
+            remote = "origin"
+        "#}
+        .parse()?;
+        let overlay: Toml = indoc! {r#"
+            [repos.vim]
+            remote = "mirror"
+            frozen = true
+        "#}
+        .parse()?;
+        let mut toml: Toml = indoc! {r#"
+            [repos.vim]
+            branch = "dev"
+            remote = "mirror"
+            frozen = true
+        "#}
+        .parse()?;
+        toml.unmerge(&overlay, &base);
+        let expect = indoc! {r#"
+            [repos.vim]
+            branch = "dev"
+            remote = "origin"
+        "#};
+        assert_eq!(toml.to_string(), expect);
+        Ok(())
+    }
 }
@@ -0,0 +1,335 @@
+// SPDX-FileCopyrightText: 2024 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+use super::settings::{validate_item, Expect, GROUP_SCHEMA, HOOK_SCHEMA, REPO_SCHEMA};
+use super::{Section, TomlError};
+
+use std::{
+    fmt,
+    ops::Range,
+    sync::atomic::{AtomicBool, Ordering},
+};
+use toml_edit::{ImDocument, Item, TableLike};
+
+const REPOS_FILE_SCHEMA: &[(&str, Expect)] = &[
+    ("repos", Expect::Map(&Expect::Table(REPO_SCHEMA))),
+    ("groups", Expect::Map(&Expect::Table(GROUP_SCHEMA))),
+];
+
+const HOOKS_FILE_SCHEMA: &[(&str, Expect)] =
+    &[("hooks", Expect::Map(&Expect::Array(&Expect::Table(HOOK_SCHEMA))))];
+
+/// Keys that tables matching a pattern must have, any one of them if there
+/// are several.
+const REQUIRED: &[(&str, &[&str])] = &[
+    ("repos.*", &["branch"]),
+    ("repos.*", &["remote"]),
+    ("groups.*", &["members"]),
+    ("hooks.*[]", &["pre", "post"]),
+];
+
+static SKIP_VALIDATION: AtomicBool = AtomicBool::new(false);
+
+/// Stop [`ConfigFile::load`] from validating configuration files for the
+/// rest of the run.
+///
+/// [`ConfigFile::load`]: crate::config::ConfigFile::load
+pub fn skip_validation() {
+    SKIP_VALIDATION.store(true, Ordering::Relaxed);
+}
+
+/// Check if [`ConfigFile::load`] validates configuration files.
+///
+/// [`ConfigFile::load`]: crate::config::ConfigFile::load
+pub fn is_validating() -> bool {
+    !SKIP_VALIDATION.load(Ordering::Relaxed)
+}
+
+/// Kind of problem found through [`validate`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiagnosticKind {
+    /// Key that Ricer does not know, e.g., a typo like `brnch`.
+    UnknownKey,
+
+    /// Value of the wrong type, e.g., `readonly = "yes"`.
+    BadType,
+
+    /// Table that lacks a key it needs, e.g., repository without `branch`.
+    MissingKey,
+}
+
+/// Problem of configuration file found through [`validate`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub kind: DiagnosticKind,
+
+    /// Dotted path to offending key, e.g., `repos.vim.brnch`.
+    pub key: String,
+
+    /// Line of offending key, starting at 1, if it could be determined.
+    pub line: Option<usize>,
+
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "line {line}: {}", self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+/// Validate configuration file data holding `section` against the keys and
+/// value types Ricer expects.
+///
+/// Only the repository and command hook configuration files are validated,
+/// data of any other section yields no diagnostics.
+///
+/// # Invariants
+///
+/// 1. Diagnostics are ordered by their position in `data`.
+/// 1. Unknown keys are reported, but never rejected, so newer configuration
+///    files still work with older versions of Ricer.
+///
+/// # Errors
+///
+/// - Return [`TomlError::BadParse`] if `data` is not valid TOML.
+pub fn validate(section: Section, data: &str) -> Result<Vec<Diagnostic>, TomlError> {
+    let schema = match section {
+        Section::Repos | Section::Groups => REPOS_FILE_SCHEMA,
+        Section::Hooks => HOOKS_FILE_SCHEMA,
+        _ => return Ok(Vec::new()),
+    };
+    let doc = ImDocument::parse(data).map_err(|err| TomlError::BadParse { source: err })?;
+
+    let mut walk = Walk { data, found: Vec::new() };
+    walk.table("", "", doc.as_table(), None, schema);
+    walk.found.sort_by_key(|diagnostic| diagnostic.line);
+    Ok(walk.found)
+}
+
+struct Walk<'data> {
+    data: &'data str,
+    found: Vec<Diagnostic>,
+}
+
+impl Walk<'_> {
+    fn item(
+        &mut self,
+        key: &str,
+        pattern: &str,
+        node: &Item,
+        span: Option<Range<usize>>,
+        expect: Expect,
+    ) {
+        match expect {
+            Expect::Table(schema) => match node.as_table_like() {
+                Some(table) => self.table(key, pattern, table, span, schema),
+                None => self.check_value(key, node, span, expect),
+            },
+            Expect::Map(inner) => match node.as_table_like() {
+                Some(table) => {
+                    for (name, node, span) in entries(table) {
+                        let key = join(key, name);
+                        self.item(&key, &join(pattern, "*"), node, span, *inner);
+                    }
+                }
+                None => self.check_value(key, node, span, expect),
+            },
+            Expect::Array(inner) => match node.as_array() {
+                Some(array) => {
+                    for (index, element) in array.iter().enumerate() {
+                        let key = format!("{key}[{index}]");
+                        let span = element.span().or_else(|| span.clone());
+                        let element = Item::Value(element.clone());
+                        self.item(&key, &format!("{pattern}[]"), &element, span, *inner);
+                    }
+                }
+                None => self.check_value(key, node, span, expect),
+            },
+            _ => self.check_value(key, node, span, expect),
+        }
+    }
+
+    fn table(
+        &mut self,
+        key: &str,
+        pattern: &str,
+        table: &dyn TableLike,
+        span: Option<Range<usize>>,
+        schema: &'static [(&'static str, Expect)],
+    ) {
+        for (name, node, span) in entries(table) {
+            let path = join(key, name);
+            match schema.iter().find(|(known, _)| *known == name) {
+                Some((_, expect)) => self.item(&path, &join(pattern, name), node, span, *expect),
+                None => {
+                    let mut message = format!("Unknown key '{path}'");
+                    if let Some(similar) = similar_key(name, schema) {
+                        message.push_str(&format!(", did you mean '{similar}'?"));
+                    }
+                    self.push(DiagnosticKind::UnknownKey, path, span, message);
+                }
+            }
+        }
+
+        for (_, keys) in REQUIRED.iter().filter(|(required, _)| *required == pattern) {
+            if keys.iter().all(|name| !table.contains_key(name)) {
+                let names = keys.iter().map(|name| format!("'{name}'")).collect::<Vec<_>>();
+                let message = format!("Missing key {} in '{key}'", names.join(" or "));
+                self.push(DiagnosticKind::MissingKey, key.into(), span.clone(), message);
+            }
+        }
+    }
+
+    // INVARIANT: leaf values are checked through the same rules as `RepoSettings::validate`.
+    fn check_value(&mut self, key: &str, node: &Item, span: Option<Range<usize>>, expect: Expect) {
+        if let Err(err) = validate_item(key, node, expect) {
+            self.push(DiagnosticKind::BadType, key.into(), span, err.to_string());
+        }
+    }
+
+    fn push(
+        &mut self,
+        kind: DiagnosticKind,
+        key: String,
+        span: Option<Range<usize>>,
+        message: String,
+    ) {
+        let line = span.map(|span| self.data[..span.start].matches('\n').count() + 1);
+        self.found.push(Diagnostic { kind, key, line, message });
+    }
+}
+
+/// Entries of `table` along with the span of their key, or of their value if
+/// the key has none.
+fn entries(table: &dyn TableLike) -> Vec<(&str, &Item, Option<Range<usize>>)> {
+    table
+        .iter()
+        .map(|(name, node)| {
+            let span = table.get_key_value(name).and_then(|(key, _)| key.span());
+            (name, node, span.or_else(|| node.span()))
+        })
+        .collect()
+}
+
+fn join(key: &str, name: &str) -> String {
+    match key {
+        "" => name.into(),
+        key => format!("{key}.{name}"),
+    }
+}
+
+/// Known key of `schema` that `name` most likely is a typo of.
+fn similar_key(name: &str, schema: &[(&'static str, Expect)]) -> Option<&'static str> {
+    schema
+        .iter()
+        .map(|(known, _)| (distance(name, known), *known))
+        .filter(|(distance, _)| *distance <= 2)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, known)| known)
+}
+
+/// Edit distance between `a` and `b`.
+fn distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut row = (0..=b.len()).collect::<Vec<_>>();
+    for (i, a) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, b) in b.iter().enumerate() {
+            let substitute = diagonal + usize::from(a != *b);
+            diagonal = row[j + 1];
+            row[j + 1] = substitute.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use anyhow::Result;
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    fn report(diagnostics: Vec<Diagnostic>) -> Vec<String> {
+        diagnostics.into_iter().map(|diagnostic| diagnostic.to_string()).collect()
+    }
+
+    #[rstest]
+    fn validate_report_problems_of_repos_file() -> Result<()> {
+        let data = indoc! {r#"
+            [repos.vim]
+            brnch = "main"
+            remote = "origin"
+            readonly = "yes"
+
+            [repos.vim.host_branches]
+            lovelace = 42
+
+            [groups.desktop]
+            members = ["vim"]
+            color = "red"
+        "#};
+        assert_eq!(
+            report(validate(Section::Repos, data)?),
+            [
+                "line 1: Missing key 'branch' in 'repos.vim'",
+                "line 2: Unknown key 'repos.vim.brnch', did you mean 'branch'?",
+                "line 4: Expected 'repos.vim.readonly' to be a boolean, found a string",
+                "line 7: Expected 'repos.vim.host_branches.lovelace' to be a string, found an integer",
+                "line 11: Unknown key 'groups.desktop.color'",
+            ]
+        );
+        Ok(())
+    }
+
+    #[rstest]
+    fn validate_report_problems_of_hooks_file() -> Result<()> {
+        let data = indoc! {r#"
+            [hooks]
+            commit = [
+                { pre = "lint.sh", sandbox = "yes" },
+                { workdir = "~" },
+            ]
+            bootstrap = { post = "plug.sh" }
+        "#};
+        let diagnostics = validate(Section::Hooks, data)?;
+        assert_eq!(
+            report(diagnostics.clone()),
+            [
+                "line 3: Expected 'hooks.commit[0].sandbox' to be a boolean, found a string",
+                "line 4: Missing key 'pre' or 'post' in 'hooks.commit[1]'",
+                "line 6: Expected 'hooks.bootstrap' to be an array, found an inline table",
+            ]
+        );
+        assert_eq!(diagnostics[1].kind, DiagnosticKind::MissingKey);
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::repos(Section::Repos, indoc! {r#"
+        [repos.vim]
+        branch = "main"
+        remote = "origin"
+        hooks = "never"
+        bootstrap = { clone = "https://example.com/vim.git", users = ["awkless"] }
+    "#})]
+    #[case::hooks(Section::Hooks, "[hooks]\ncommit = [{ post = \"a.sh\", env = { X = \"1\" } }]\n")]
+    #[case::other_section(Section::Features, "[features]\nanything = 1\n")]
+    fn validate_accept_valid_data(#[case] section: Section, #[case] data: &str) -> Result<()> {
+        assert_eq!(validate(section, data)?, []);
+        Ok(())
+    }
+
+    #[rstest]
+    fn validate_return_err_bad_parse() {
+        let result = validate(Section::Repos, "[repos\n");
+        assert!(matches!(result, Err(TomlError::BadParse { .. })));
+    }
+}
@@ -0,0 +1,195 @@
+// SPDX-FileCopyrightText: 2024 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+use std::time::Duration;
+use toml_edit::Item;
+
+/// Error types for human-friendly configuration values.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ValueError {
+    #[error(
+        "'{value}' is not a duration, expected seconds, or numbers with units ms, s, m, h, or d, \
+         like \"90s\" or \"1h30m\""
+    )]
+    BadDuration { value: String },
+
+    #[error(
+        "'{value}' is not a size, expected bytes, or a number with unit B, KB, MB, GB, TB, KiB, \
+         MiB, GiB, or TiB, like \"10MB\""
+    )]
+    BadSize { value: String },
+}
+
+/// Parse human-friendly duration, e.g., `"90s"`, `"5m"`, or `"1h30m"`.
+///
+/// Numbers without a unit are seconds. Whitespace between numbers and units
+/// is allowed.
+///
+/// # Errors
+///
+/// - Return [`ValueError::BadDuration`] if `value` is not a duration, or does
+///   not fit into one.
+pub fn parse_duration(value: &str) -> Result<Duration, ValueError> {
+    let bad_duration = || ValueError::BadDuration { value: value.into() };
+    let mut rest = value.trim();
+    if rest.is_empty() {
+        return Err(bad_duration());
+    }
+
+    let mut total = Duration::ZERO;
+    while !rest.is_empty() {
+        let (number, after) = split_number(rest).ok_or_else(bad_duration)?;
+        let after = after.trim_start();
+        let unit_len = after.find(|c: char| c.is_ascii_digit() || c.is_whitespace());
+        let (unit, after) = after.split_at(unit_len.unwrap_or(after.len()));
+        let millis = match unit {
+            "ms" => Some(1),
+            "" | "s" => Some(1000),
+            "m" => Some(60 * 1000),
+            "h" => Some(60 * 60 * 1000),
+            "d" => Some(24 * 60 * 60 * 1000),
+            _ => None,
+        };
+        let part = millis.and_then(|millis| number.checked_mul(millis)).ok_or_else(bad_duration)?;
+        total = total.checked_add(Duration::from_millis(part)).ok_or_else(bad_duration)?;
+        rest = after.trim_start();
+    }
+
+    Ok(total)
+}
+
+/// Parse human-friendly size in bytes, e.g., `"512KiB"`, or `"10MB"`.
+///
+/// Numbers without a unit are bytes. Units are case-insensitive, where
+/// decimal units like `MB` count in powers of 1000, and binary units like
+/// `MiB` in powers of 1024.
+///
+/// # Errors
+///
+/// - Return [`ValueError::BadSize`] if `value` is not a size, or does not fit
+///   into 64 bits.
+pub fn parse_size(value: &str) -> Result<u64, ValueError> {
+    let bad_size = || ValueError::BadSize { value: value.into() };
+    let (number, unit) = split_number(value.trim()).ok_or_else(bad_size)?;
+    let scale: u64 = match unit.trim().to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "k" | "kb" => 1000,
+        "m" | "mb" => 1000u64.pow(2),
+        "g" | "gb" => 1000u64.pow(3),
+        "t" | "tb" => 1000u64.pow(4),
+        "kib" => 1 << 10,
+        "mib" => 1 << 20,
+        "gib" => 1 << 30,
+        "tib" => 1 << 40,
+        _ => return Err(bad_size()),
+    };
+    number.checked_mul(scale).ok_or_else(bad_size)
+}
+
+/// Read duration from TOML `node`, where integers are seconds, and strings
+/// are parsed through [`parse_duration`].
+///
+/// # Errors
+///
+/// - Return [`ValueError::BadDuration`] if `node` holds neither.
+pub fn duration_item(node: &Item) -> Result<Duration, ValueError> {
+    match (node.as_integer(), node.as_str()) {
+        (Some(secs), _) => u64::try_from(secs)
+            .map(Duration::from_secs)
+            .map_err(|_| ValueError::BadDuration { value: secs.to_string() }),
+        (_, Some(value)) => parse_duration(value),
+        _ => Err(ValueError::BadDuration { value: node.to_string().trim().into() }),
+    }
+}
+
+/// Read size from TOML `node`, where integers are bytes, and strings are
+/// parsed through [`parse_size`].
+///
+/// # Errors
+///
+/// - Return [`ValueError::BadSize`] if `node` holds neither.
+pub fn size_item(node: &Item) -> Result<u64, ValueError> {
+    match (node.as_integer(), node.as_str()) {
+        (Some(bytes), _) => {
+            u64::try_from(bytes).map_err(|_| ValueError::BadSize { value: bytes.to_string() })
+        }
+        (_, Some(value)) => parse_size(value),
+        _ => Err(ValueError::BadSize { value: node.to_string().trim().into() }),
+    }
+}
+
+/// Split leading digits of `value` off as a number.
+fn split_number(value: &str) -> Option<(u64, &str)> {
+    let len = value.find(|c: char| !c.is_ascii_digit()).unwrap_or(value.len());
+    let (digits, rest) = value.split_at(len);
+    Some((digits.parse().ok()?, rest))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+    use toml_edit::value;
+
+    #[rstest]
+    #[case::seconds("90s", Duration::from_secs(90))]
+    #[case::bare_number("90", Duration::from_secs(90))]
+    #[case::minutes("5m", Duration::from_secs(5 * 60))]
+    #[case::hours("1h", Duration::from_secs(60 * 60))]
+    #[case::days("2d", Duration::from_secs(2 * 24 * 60 * 60))]
+    #[case::millis("250ms", Duration::from_millis(250))]
+    #[case::compound("1h30m", Duration::from_secs(90 * 60))]
+    #[case::whitespace(" 1h 30 m ", Duration::from_secs(90 * 60))]
+    fn parse_duration_return_duration(#[case] input: &str, #[case] expect: Duration) {
+        assert_eq!(parse_duration(input), Ok(expect));
+    }
+
+    #[rstest]
+    #[case::empty("")]
+    #[case::unknown_unit("5w")]
+    #[case::no_number("s")]
+    #[case::negative("-5s")]
+    #[case::overflow("99999999999999999999d")]
+    fn parse_duration_return_err_bad_duration(#[case] input: &str) {
+        assert_eq!(parse_duration(input), Err(ValueError::BadDuration { value: input.into() }));
+    }
+
+    #[rstest]
+    #[case::bytes("512", 512)]
+    #[case::decimal_unit("10MB", 10_000_000)]
+    #[case::binary_unit("512KiB", 512 * 1024)]
+    #[case::lowercase("1gib", 1 << 30)]
+    #[case::whitespace("10 MB", 10_000_000)]
+    fn parse_size_return_bytes(#[case] input: &str, #[case] expect: u64) {
+        assert_eq!(parse_size(input), Ok(expect));
+    }
+
+    #[rstest]
+    #[case::empty("")]
+    #[case::unknown_unit("10XB")]
+    #[case::fraction("1.5MB")]
+    #[case::overflow("99999999TiB")]
+    fn parse_size_return_err_bad_size(#[case] input: &str) {
+        assert_eq!(parse_size(input), Err(ValueError::BadSize { value: input.into() }));
+    }
+
+    #[rstest]
+    #[case::integer(value(900), Ok(Duration::from_secs(900)))]
+    #[case::string(value("15m"), Ok(Duration::from_secs(900)))]
+    #[case::negative(value(-1), Err(ValueError::BadDuration { value: "-1".into() }))]
+    #[case::boolean(value(true), Err(ValueError::BadDuration { value: "true".into() }))]
+    fn duration_item_read_integers_and_strings(
+        #[case] node: Item,
+        #[case] expect: Result<Duration, ValueError>,
+    ) {
+        assert_eq!(duration_item(&node), expect);
+    }
+
+    #[rstest]
+    fn value_error_name_accepted_formats() {
+        let err = ValueError::BadDuration { value: "soon".into() };
+        assert!(err.to_string().contains("like \"90s\" or \"1h30m\""));
+    }
+}
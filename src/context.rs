@@ -14,12 +14,49 @@
 //! Command context also provides a layer of abstraction between the CLI and
 //! command set implementations. So, changes to the CLI will not directly effect
 //! any implementations of the command set in the codebase.
+//!
+//! Next to command context, the context layer also holds the environment of
+//! a run through [`EnvSnapshot`].
+
+mod env;
+
+pub use env::*;
 
 use clap::ValueEnum;
+use clap_complete::Shell;
+use log::warn;
 use std::ffi::OsString;
 use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::cli::{
+    Cli, CommandSet, ConfigAction, HookCmdAction, IgnoreAction, ProfileAction, RemotesAction,
+    SharedOptions,
+};
+use crate::config::{expand_path, CommandSettings, DefaultValue};
 
-use crate::cli::{Cli, CommandSet, SharedOptions};
+/// Git commands that change the repository they run in, refused by the Git
+/// shortcut for read-only repositories.
+pub const MUTATING_GIT_COMMANDS: &[&str] = &[
+    "add",
+    "am",
+    "apply",
+    "checkout",
+    "cherry-pick",
+    "clean",
+    "commit",
+    "merge",
+    "mv",
+    "push",
+    "rebase",
+    "reset",
+    "restore",
+    "revert",
+    "rm",
+    "switch",
+];
 
 #[derive(Debug, Eq, PartialEq)]
 pub enum Context {
@@ -34,6 +71,33 @@ pub enum Context {
     Pull(PullContext),
     Rename(RenameContext),
     Status(StatusContext),
+    Env(EnvContext),
+    Profile(ProfileContext),
+    Hook(HookContext),
+    Snapshot(SnapshotContext),
+    Deploy(DeployContext),
+    Undeploy(UndeployContext),
+    Owns(OwnsContext),
+    Daemon(DaemonContext),
+    Sed(SedContext),
+    History(HistoryContext),
+    Add(AddContext),
+    Split(SplitContext),
+    Clean(CleanContext),
+    Switch(SwitchContext),
+    Remotes(RemotesContext),
+    Foreach(ForeachContext),
+    Lock(LockContext),
+    Unlock(UnlockContext),
+    Doctor(DoctorContext),
+    Config(ConfigContext),
+    Info(InfoContext),
+    Completions(CompletionsContext),
+    Stats(StatsContext),
+    Examples(ExamplesContext),
+    Ignore(IgnoreContext),
+    Bugreport(BugreportContext),
+    MoveConfigDir(MoveConfigDirContext),
     Git(GitContext),
 }
 
@@ -51,11 +115,348 @@ fn from(opts: Cli) -> Self {
             CommandSet::Pull(_) => Self::Pull(PullContext::from(opts)),
             CommandSet::Rename(_) => Self::Rename(RenameContext::from(opts)),
             CommandSet::Status(_) => Self::Status(StatusContext::from(opts)),
+            CommandSet::Env(_) => Self::Env(EnvContext::from(opts)),
+            CommandSet::Profile(_) => Self::Profile(ProfileContext::from(opts)),
+            CommandSet::Hook(_) => Self::Hook(HookContext::from(opts)),
+            CommandSet::Snapshot(_) => Self::Snapshot(SnapshotContext::from(opts)),
+            CommandSet::Deploy(_) => Self::Deploy(DeployContext::from(opts)),
+            CommandSet::Undeploy(_) => Self::Undeploy(UndeployContext::from(opts)),
+            CommandSet::Owns(_) => Self::Owns(OwnsContext::from(opts)),
+            CommandSet::Daemon(_) => Self::Daemon(DaemonContext::from(opts)),
+            CommandSet::Sed(_) => Self::Sed(SedContext::from(opts)),
+            CommandSet::History(_) => Self::History(HistoryContext::from(opts)),
+            CommandSet::Add(_) => Self::Add(AddContext::from(opts)),
+            CommandSet::Split(_) => Self::Split(SplitContext::from(opts)),
+            CommandSet::Clean(_) => Self::Clean(CleanContext::from(opts)),
+            CommandSet::Switch(_) => Self::Switch(SwitchContext::from(opts)),
+            CommandSet::Remotes(_) => Self::Remotes(RemotesContext::from(opts)),
+            CommandSet::Foreach(_) => Self::Foreach(ForeachContext::from(opts)),
+            CommandSet::Lock(_) => Self::Lock(LockContext::from(opts)),
+            CommandSet::Unlock(_) => Self::Unlock(UnlockContext::from(opts)),
+            CommandSet::Doctor(_) => Self::Doctor(DoctorContext::from(opts)),
+            CommandSet::Config(_) => Self::Config(ConfigContext::from(opts)),
+            CommandSet::Info(_) => Self::Info(InfoContext::from(opts)),
+            CommandSet::Completions(_) => Self::Completions(CompletionsContext::from(opts)),
+            CommandSet::Stats(_) => Self::Stats(StatsContext::from(opts)),
+            CommandSet::Examples(_) => Self::Examples(ExamplesContext::from(opts)),
+            CommandSet::Ignore(_) => Self::Ignore(IgnoreContext::from(opts)),
+            CommandSet::Bugreport(_) => Self::Bugreport(BugreportContext::from(opts)),
+            CommandSet::MoveConfigDir(_) => Self::MoveConfigDir(MoveConfigDirContext::from(opts)),
             CommandSet::Git(_) => Self::Git(GitContext::from(opts)),
         }
     }
 }
 
+impl Context {
+    /// Get shareable context of command.
+    ///
+    /// Will return `None` for [`GitContext`], because the Git shortcut does not
+    /// use shareable context.
+    pub fn shared(&self) -> Option<&SharedContext> {
+        match self {
+            Context::Bootstrap(ctx) => Some(&ctx.shared),
+            Context::Clone(ctx) => Some(&ctx.shared),
+            Context::Commit(ctx) => Some(&ctx.shared),
+            Context::Delete(ctx) => Some(&ctx.shared),
+            Context::Enter(ctx) => Some(&ctx.shared),
+            Context::Init(ctx) => Some(&ctx.shared),
+            Context::List(ctx) => Some(&ctx.shared),
+            Context::Pull(ctx) => Some(&ctx.shared),
+            Context::Push(ctx) => Some(&ctx.shared),
+            Context::Rename(ctx) => Some(&ctx.shared),
+            Context::Status(ctx) => Some(&ctx.shared),
+            Context::Env(ctx) => Some(&ctx.shared),
+            Context::Profile(ctx) => Some(&ctx.shared),
+            Context::Hook(ctx) => Some(&ctx.shared),
+            Context::Snapshot(ctx) => Some(&ctx.shared),
+            Context::Deploy(ctx) => Some(&ctx.shared),
+            Context::Undeploy(ctx) => Some(&ctx.shared),
+            Context::Owns(ctx) => Some(&ctx.shared),
+            Context::Daemon(ctx) => Some(&ctx.shared),
+            Context::Sed(ctx) => Some(&ctx.shared),
+            Context::History(ctx) => Some(&ctx.shared),
+            Context::Add(ctx) => Some(&ctx.shared),
+            Context::Split(ctx) => Some(&ctx.shared),
+            Context::Clean(ctx) => Some(&ctx.shared),
+            Context::Switch(ctx) => Some(&ctx.shared),
+            Context::Remotes(ctx) => Some(&ctx.shared),
+            Context::Foreach(ctx) => Some(&ctx.shared),
+            Context::Lock(ctx) => Some(&ctx.shared),
+            Context::Unlock(ctx) => Some(&ctx.shared),
+            Context::Doctor(ctx) => Some(&ctx.shared),
+            Context::Config(ctx) => Some(&ctx.shared),
+            Context::Info(ctx) => Some(&ctx.shared),
+            Context::Completions(ctx) => Some(&ctx.shared),
+            Context::Stats(ctx) => Some(&ctx.shared),
+            Context::Examples(ctx) => Some(&ctx.shared),
+            Context::Bugreport(ctx) => Some(&ctx.shared),
+            Context::MoveConfigDir(ctx) => Some(&ctx.shared),
+            Context::Ignore(ctx) => Some(&ctx.shared),
+            Context::Git(_) => None,
+        }
+    }
+
+    /// Get names of repositories command explicitly targets.
+    ///
+    /// Commands that operate on every repository, like commit or push,
+    /// target none.
+    pub fn target_repos(&self) -> Vec<&str> {
+        match self {
+            Context::Bootstrap(ctx) => ctx.config.iter().map(String::as_str).collect(),
+            Context::Clone(ctx) => ctx.repo.iter().map(String::as_str).collect(),
+            Context::Commit(ctx) => ctx.repos.iter().map(String::as_str).collect(),
+            Context::Pull(ctx) => ctx.repos.iter().map(String::as_str).collect(),
+            Context::Status(ctx) => ctx.repos.iter().map(String::as_str).collect(),
+            Context::Delete(ctx) => vec![ctx.repo.as_str()],
+            Context::Enter(ctx) => vec![ctx.repo.as_str()],
+            Context::Init(ctx) => vec![ctx.name.as_str()],
+            Context::Rename(ctx) => vec![ctx.from.as_str()],
+            Context::Deploy(ctx) => vec![ctx.repo.as_str()],
+            Context::Undeploy(ctx) => vec![ctx.repo.as_str()],
+            Context::Sed(ctx) => ctx.repos.iter().map(String::as_str).collect(),
+            Context::Add(ctx) => vec![ctx.repo.as_str()],
+            Context::Split(ctx) => vec![ctx.repo.as_str(), ctx.name.as_str()],
+            Context::Clean(ctx) => vec![ctx.repo.as_str()],
+            Context::Switch(ctx) => vec![ctx.repo.as_str()],
+            Context::Remotes(RemotesContext { op: RemotesOp::Rewrite { repos, .. }, .. }) => {
+                repos.iter().map(String::as_str).collect()
+            }
+            Context::Foreach(ctx) => ctx.repos.iter().map(String::as_str).collect(),
+            Context::Info(ctx) => vec![ctx.repo.as_str()],
+            Context::Ignore(ctx) => vec![ctx.op.repo()],
+            _ => Vec::new(),
+        }
+    }
+
+    /// Check if command changes repositories it operates on, e.g., by
+    /// committing to them or rewriting their files.
+    ///
+    /// Dry runs never change anything, and the Git shortcut only changes its
+    /// repository through one of [`MUTATING_GIT_COMMANDS`].
+    pub fn is_mutating(&self) -> bool {
+        if self.shared().is_some_and(|shared| shared.dry_run) {
+            return false;
+        }
+
+        match self {
+            Context::Add(_)
+            | Context::Commit(_)
+            | Context::Delete(_)
+            | Context::Push(_)
+            | Context::Sed(_)
+            | Context::Split(_)
+            | Context::Clean(_)
+            | Context::Switch(_) => true,
+            Context::Rename(ctx) => !ctx.dry_run,
+            Context::Remotes(RemotesContext { op: RemotesOp::Rewrite { .. }, .. }) => true,
+            Context::Git(ctx) => ctx
+                .git_args
+                .first()
+                .and_then(|arg| arg.to_str())
+                .is_some_and(|cmd| MUTATING_GIT_COMMANDS.contains(&cmd)),
+            _ => false,
+        }
+    }
+
+    /// Merge persistent command defaults into context.
+    ///
+    /// Defaults come from the `[commands.<command>]` table of Ricer's general
+    /// configuration file, and use the long names of the command's options
+    /// with dashes replaced by underscores, e.g., `dry_run = true`. Shared
+    /// flags `no_hooks`, `reload_hooks`, `no_pager`, and `timings` can be
+    /// preset for any command.
+    ///
+    /// # Invariants
+    ///
+    /// 1. Options given on the command-line always take precedence, so
+    ///    defaults only fill in options that were left out.
+    /// 1. Unknown options and values of the wrong type are skipped with a
+    ///    warning instead of failing the command.
+    /// 1. Git shortcut never takes defaults.
+    pub fn merge_defaults(&mut self, settings: &CommandSettings) {
+        let mut defaults = Defaults::new(settings);
+        match self {
+            Context::Bootstrap(ctx) => {
+                defaults.string("from", &mut ctx.from);
+            }
+            Context::Commit(ctx) => defaults.flag("push", &mut ctx.push),
+            Context::Init(ctx) => {
+                defaults.flag("workdir_home", &mut ctx.workdir_home);
+                defaults.string("branch", &mut ctx.branch);
+                defaults.string("remote", &mut ctx.remote);
+                defaults.string("template", &mut ctx.template);
+                defaults.flag("initial_commit", &mut ctx.initial_commit);
+            }
+            Context::List(ctx) => {
+                defaults.flag("tracked", &mut ctx.tracked);
+                defaults.flag("untracked", &mut ctx.untracked);
+            }
+            Context::Push(ctx) => {
+                defaults.string("remote", &mut ctx.remote);
+                defaults.string("branch", &mut ctx.branch);
+            }
+            Context::Pull(ctx) => {
+                defaults.string("remote", &mut ctx.remote);
+                defaults.string("branch", &mut ctx.branch);
+                defaults.flag("fix", &mut ctx.fix);
+                defaults.count("jobs", &mut ctx.jobs);
+            }
+            Context::Rename(ctx) => defaults.flag("dry_run", &mut ctx.dry_run),
+            Context::Status(ctx) => {
+                defaults.flag("terse", &mut ctx.terse);
+                defaults.flag("assume_clean", &mut ctx.assume_clean);
+            }
+            Context::Profile(ProfileContext {
+                op: ProfileOp::Fork { share_repos, .. }, ..
+            }) => defaults.flag("share_repos", share_repos),
+            Context::Hook(HookContext { op: HookOp::List { effective, .. }, .. }) => {
+                defaults.flag("effective", effective)
+            }
+            Context::Snapshot(ctx) => {
+                defaults.path("output", &mut ctx.output);
+                defaults.flag("bundle", &mut ctx.bundle);
+            }
+            Context::Bugreport(ctx) => defaults.path("output", &mut ctx.output),
+            Context::MoveConfigDir(ctx) => {
+                defaults.flag("data", &mut ctx.data);
+                defaults.flag("state", &mut ctx.state);
+            }
+            Context::Daemon(ctx) => defaults.flag("once", &mut ctx.once),
+            Context::Add(ctx) => defaults.flag("patch", &mut ctx.patch),
+            Context::Split(ctx) => defaults.flag("no_history", &mut ctx.no_history),
+            Context::Clean(ctx) => defaults.flag("ignored", &mut ctx.ignored),
+            Context::Switch(ctx) => defaults.flag("save", &mut ctx.save),
+            Context::Foreach(ctx) => defaults.count("jobs", &mut ctx.jobs),
+            Context::Info(ctx) => defaults.flag("json", &mut ctx.json),
+            Context::Config(ConfigContext { op: ConfigOp::Watch { notify, .. }, .. }) => {
+                defaults.flag("notify", notify)
+            }
+            Context::Git(_) => return,
+            _ => (),
+        }
+
+        if let Some(shared) = self.shared_mut() {
+            defaults.flag("no_hooks", &mut shared.no_hooks);
+            defaults.flag("reload_hooks", &mut shared.reload_hooks);
+            defaults.flag("no_pager", &mut shared.no_pager);
+            defaults.flag("timings", &mut shared.timings);
+        }
+        defaults.finish();
+    }
+
+    fn shared_mut(&mut self) -> Option<&mut SharedContext> {
+        match self {
+            Context::Bootstrap(ctx) => Some(&mut ctx.shared),
+            Context::Clone(ctx) => Some(&mut ctx.shared),
+            Context::Commit(ctx) => Some(&mut ctx.shared),
+            Context::Delete(ctx) => Some(&mut ctx.shared),
+            Context::Enter(ctx) => Some(&mut ctx.shared),
+            Context::Init(ctx) => Some(&mut ctx.shared),
+            Context::List(ctx) => Some(&mut ctx.shared),
+            Context::Pull(ctx) => Some(&mut ctx.shared),
+            Context::Push(ctx) => Some(&mut ctx.shared),
+            Context::Rename(ctx) => Some(&mut ctx.shared),
+            Context::Status(ctx) => Some(&mut ctx.shared),
+            Context::Env(ctx) => Some(&mut ctx.shared),
+            Context::Profile(ctx) => Some(&mut ctx.shared),
+            Context::Hook(ctx) => Some(&mut ctx.shared),
+            Context::Snapshot(ctx) => Some(&mut ctx.shared),
+            Context::Deploy(ctx) => Some(&mut ctx.shared),
+            Context::Undeploy(ctx) => Some(&mut ctx.shared),
+            Context::Owns(ctx) => Some(&mut ctx.shared),
+            Context::Daemon(ctx) => Some(&mut ctx.shared),
+            Context::Sed(ctx) => Some(&mut ctx.shared),
+            Context::History(ctx) => Some(&mut ctx.shared),
+            Context::Add(ctx) => Some(&mut ctx.shared),
+            Context::Split(ctx) => Some(&mut ctx.shared),
+            Context::Clean(ctx) => Some(&mut ctx.shared),
+            Context::Switch(ctx) => Some(&mut ctx.shared),
+            Context::Remotes(ctx) => Some(&mut ctx.shared),
+            Context::Foreach(ctx) => Some(&mut ctx.shared),
+            Context::Lock(ctx) => Some(&mut ctx.shared),
+            Context::Unlock(ctx) => Some(&mut ctx.shared),
+            Context::Doctor(ctx) => Some(&mut ctx.shared),
+            Context::Config(ctx) => Some(&mut ctx.shared),
+            Context::Info(ctx) => Some(&mut ctx.shared),
+            Context::Completions(ctx) => Some(&mut ctx.shared),
+            Context::Stats(ctx) => Some(&mut ctx.shared),
+            Context::Examples(ctx) => Some(&mut ctx.shared),
+            Context::Bugreport(ctx) => Some(&mut ctx.shared),
+            Context::MoveConfigDir(ctx) => Some(&mut ctx.shared),
+            Context::Ignore(ctx) => Some(&mut ctx.shared),
+            Context::Git(_) => None,
+        }
+    }
+}
+
+/// Track which command defaults were merged into a context.
+struct Defaults<'cfg> {
+    settings: &'cfg CommandSettings,
+    used: Vec<&'cfg str>,
+}
+
+impl<'cfg> Defaults<'cfg> {
+    fn new(settings: &'cfg CommandSettings) -> Self {
+        Self { settings, used: Vec::new() }
+    }
+
+    fn take(&mut self, key: &'cfg str) -> Option<&'cfg DefaultValue> {
+        let value = self.settings.get(key)?;
+        self.used.push(key);
+        Some(value)
+    }
+
+    fn mismatch(&self, key: &str, kind: &str, value: &DefaultValue) {
+        warn!("Default '{key}' of command '{}' must be a {kind}, not {value}", self.settings.name);
+    }
+
+    fn flag(&mut self, key: &'cfg str, field: &mut bool) {
+        match self.take(key) {
+            Some(DefaultValue::Bool(choice)) => *field |= *choice,
+            Some(value) => self.mismatch(key, "boolean", value),
+            None => (),
+        }
+    }
+
+    fn string(&mut self, key: &'cfg str, field: &mut Option<String>) {
+        match self.take(key) {
+            Some(DefaultValue::Str(string)) => {
+                field.get_or_insert_with(|| string.clone());
+            }
+            Some(value) => self.mismatch(key, "string", value),
+            None => (),
+        }
+    }
+
+    fn count(&mut self, key: &'cfg str, field: &mut Option<u16>) {
+        match self.take(key) {
+            Some(DefaultValue::Int(number)) if (1..=i64::from(u16::MAX)).contains(number) => {
+                field.get_or_insert(*number as u16);
+            }
+            Some(value) => self.mismatch(key, "positive integer", value),
+            None => (),
+        }
+    }
+
+    fn path(&mut self, key: &'cfg str, field: &mut Option<PathBuf>) {
+        match self.take(key) {
+            Some(DefaultValue::Str(path)) => {
+                field.get_or_insert_with(|| expand_path(path));
+            }
+            Some(value) => self.mismatch(key, "path", value),
+            None => (),
+        }
+    }
+
+    fn finish(self) {
+        for (key, _) in &self.settings.defaults {
+            if !self.used.contains(&key.as_str()) {
+                warn!("Command '{}' has no option '{key}' to set default of", self.settings.name);
+            }
+        }
+    }
+}
+
 impl fmt::Display for Context {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -70,6 +471,33 @@ fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
             Context::Push(_) => write!(f, "push"),
             Context::Rename(_) => write!(f, "rename"),
             Context::Status(_) => write!(f, "status"),
+            Context::Env(_) => write!(f, "env"),
+            Context::Profile(_) => write!(f, "profile"),
+            Context::Hook(_) => write!(f, "hook"),
+            Context::Snapshot(_) => write!(f, "snapshot"),
+            Context::Deploy(_) => write!(f, "deploy"),
+            Context::Undeploy(_) => write!(f, "undeploy"),
+            Context::Owns(_) => write!(f, "owns"),
+            Context::Daemon(_) => write!(f, "daemon"),
+            Context::Sed(_) => write!(f, "sed"),
+            Context::History(_) => write!(f, "history"),
+            Context::Add(_) => write!(f, "add"),
+            Context::Split(_) => write!(f, "split"),
+            Context::Clean(_) => write!(f, "clean"),
+            Context::Switch(_) => write!(f, "switch"),
+            Context::Remotes(_) => write!(f, "remotes"),
+            Context::Foreach(_) => write!(f, "foreach"),
+            Context::Lock(_) => write!(f, "lock"),
+            Context::Unlock(_) => write!(f, "unlock"),
+            Context::Doctor(_) => write!(f, "doctor"),
+            Context::Config(_) => write!(f, "config"),
+            Context::Info(_) => write!(f, "info"),
+            Context::Completions(_) => write!(f, "completions"),
+            Context::Stats(_) => write!(f, "stats"),
+            Context::Examples(_) => write!(f, "examples"),
+            Context::Bugreport(_) => write!(f, "bugreport"),
+            Context::MoveConfigDir(_) => write!(f, "move-config-dir"),
+            Context::Ignore(_) => write!(f, "ignore"),
             Context::Git(_) => {
                 unreachable!("This should not happen. Cannot convert Git context to string")
             }
@@ -82,6 +510,7 @@ pub struct BootstrapContext {
     pub config: Option<String>,
     pub from: Option<String>,
     pub only: Option<Vec<String>>,
+    pub plan: bool,
     pub shared: SharedContext,
 }
 
@@ -97,6 +526,7 @@ fn from(opts: Cli) -> Self {
             config: cmd_set.config,
             from: cmd_set.from,
             only: cmd_set.only,
+            plan: cmd_set.plan,
             shared: shared_opts.into(),
         }
     }
@@ -104,8 +534,10 @@ fn from(opts: Cli) -> Self {
 
 #[derive(Debug, Eq, PartialEq)]
 pub struct CloneContext {
-    pub remote: String,
+    pub remote: Option<String>,
     pub repo: Option<String>,
+    pub forge: Option<ForgeContext>,
+    pub adopt: bool,
     pub shared: SharedContext,
 }
 
@@ -117,7 +549,25 @@ fn from(opts: Cli) -> Self {
             _ => unreachable!("This should never happen. The command is not 'clone'!"),
         };
 
-        Self { remote: cmd_set.remote, repo: cmd_set.repo, shared: shared_opts.into() }
+        let forge = match (cmd_set.from_github, cmd_set.from_gitlab) {
+            (Some(namespace), _) => Some((ForgeKind::GitHub, namespace)),
+            (_, Some(namespace)) => Some((ForgeKind::GitLab, namespace)),
+            (None, None) => None,
+        }
+        .map(|(kind, namespace)| ForgeContext {
+            kind,
+            namespace,
+            topic: cmd_set.topic,
+            pattern: cmd_set.pattern,
+        });
+
+        Self {
+            remote: cmd_set.remote,
+            repo: cmd_set.repo,
+            forge,
+            adopt: cmd_set.adopt_existing_files,
+            shared: shared_opts.into(),
+        }
     }
 }
 
@@ -125,6 +575,17 @@ fn from(opts: Cli) -> Self {
 pub struct CommitContext {
     pub fixup: Option<FixupAction>,
     pub message: Option<String>,
+    pub no_verify: bool,
+    pub push: bool,
+    pub include_globs: Vec<String>,
+    pub exclude_globs: Vec<String>,
+    pub allow_excluded: bool,
+    pub repos: Vec<String>,
+
+    /// Paths or glob patterns of files to commit, whose owning repositories
+    /// are committed to.
+    pub paths: Vec<String>,
+    pub strict: bool,
     pub shared: SharedContext,
 }
 
@@ -135,14 +596,38 @@ fn from(opts: Cli) -> Self {
             CommandSet::Commit(opts) => opts,
             _ => unreachable!("This should never happen. The command is not 'commit'!"),
         };
+        let (paths, repos) = cmd_set.select.patterns.into_iter().partition(|p| is_pathspec(p));
 
-        Self { fixup: cmd_set.fixup, message: cmd_set.message, shared: shared_opts.into() }
+        Self {
+            fixup: cmd_set.fixup,
+            message: cmd_set.message,
+            no_verify: cmd_set.no_verify,
+            push: cmd_set.push,
+            include_globs: cmd_set.include_glob,
+            exclude_globs: cmd_set.exclude_glob,
+            allow_excluded: cmd_set.allow_excluded,
+            repos,
+            paths,
+            strict: cmd_set.select.strict,
+            shared: shared_opts.into(),
+        }
     }
 }
 
+/// Check if `pattern` names files rather than repositories.
+///
+/// Repository names never contain path separators, so anything that does, or
+/// starts like a path, is taken as a path.
+fn is_pathspec(pattern: &str) -> bool {
+    pattern.contains(std::path::is_separator)
+        || pattern.starts_with('~')
+        || matches!(pattern, "." | "..")
+}
+
 #[derive(Debug, Eq, PartialEq)]
 pub struct DeleteContext {
     pub repo: String,
+    pub keep_files: bool,
     pub shared: SharedContext,
 }
 
@@ -154,7 +639,7 @@ fn from(opts: Cli) -> Self {
             _ => unreachable!("This should never happen. The command is not 'delete'!"),
         };
 
-        Self { repo: cmd_set.repo, shared: shared_opts.into() }
+        Self { repo: cmd_set.repo, keep_files: cmd_set.keep_files, shared: shared_opts.into() }
     }
 }
 
@@ -182,6 +667,9 @@ pub struct InitContext {
     pub workdir_home: bool,
     pub branch: Option<String>,
     pub remote: Option<String>,
+    pub template: Option<String>,
+    pub initial_commit: bool,
+    pub force: bool,
     pub shared: SharedContext,
 }
 
@@ -198,6 +686,9 @@ fn from(opts: Cli) -> Self {
             workdir_home: cmd_set.workdir_home,
             branch: cmd_set.branch,
             remote: cmd_set.remote,
+            template: cmd_set.template,
+            initial_commit: cmd_set.initial_commit,
+            force: cmd_set.force,
             shared: shared_opts.into(),
         }
     }
@@ -207,6 +698,7 @@ fn from(opts: Cli) -> Self {
 pub struct ListContext {
     pub tracked: bool,
     pub untracked: bool,
+    pub format: ListFormat,
     pub shared: SharedContext,
 }
 
@@ -218,7 +710,12 @@ fn from(opts: Cli) -> Self {
             _ => unreachable!("This should never happen. The command is not 'list'!"),
         };
 
-        Self { tracked: cmd_set.tracked, untracked: cmd_set.untracked, shared: shared_opts.into() }
+        Self {
+            tracked: cmd_set.tracked,
+            untracked: cmd_set.untracked,
+            format: cmd_set.format,
+            shared: shared_opts.into(),
+        }
     }
 }
 
@@ -245,6 +742,10 @@ fn from(opts: Cli) -> Self {
 pub struct PullContext {
     pub branch: Option<String>,
     pub remote: Option<String>,
+    pub fix: bool,
+    pub jobs: Option<u16>,
+    pub repos: Vec<String>,
+    pub strict: bool,
     pub shared: SharedContext,
 }
 
@@ -256,7 +757,15 @@ fn from(opts: Cli) -> Self {
             _ => unreachable!("This should never happen. The command is not 'pull'!"),
         };
 
-        Self { remote: cmd_set.remote, branch: cmd_set.branch, shared: shared_opts.into() }
+        Self {
+            remote: cmd_set.remote,
+            branch: cmd_set.branch,
+            fix: cmd_set.fix,
+            jobs: cmd_set.jobs,
+            repos: cmd_set.select.patterns,
+            strict: cmd_set.select.strict,
+            shared: shared_opts.into(),
+        }
     }
 }
 
@@ -264,6 +773,7 @@ fn from(opts: Cli) -> Self {
 pub struct RenameContext {
     pub from: String,
     pub to: String,
+    pub dry_run: bool,
     pub shared: SharedContext,
 }
 
@@ -275,13 +785,21 @@ fn from(opts: Cli) -> Self {
             _ => unreachable!("This should never happen. The command is not 'rename'!"),
         };
 
-        Self { from: cmd_set.from, to: cmd_set.to, shared: shared_opts.into() }
+        Self {
+            from: cmd_set.from,
+            to: cmd_set.to,
+            dry_run: cmd_set.dry_run,
+            shared: shared_opts.into(),
+        }
     }
 }
 
 #[derive(Debug, Eq, PartialEq)]
 pub struct StatusContext {
     pub terse: bool,
+    pub assume_clean: bool,
+    pub repos: Vec<String>,
+    pub strict: bool,
     pub shared: SharedContext,
 }
 
@@ -293,150 +811,1109 @@ fn from(opts: Cli) -> Self {
             _ => unreachable!("This should never happen. The command is not 'status'!"),
         };
 
-        Self { terse: cmd_set.terse, shared: shared_opts.into() }
+        Self {
+            terse: cmd_set.terse,
+            assume_clean: cmd_set.assume_clean,
+            repos: cmd_set.select.patterns,
+            strict: cmd_set.select.strict,
+            shared: shared_opts.into(),
+        }
     }
 }
 
-/// Git shorcut context.
-///
-/// Does not use shareable context, because the Git shortcut is a system call
-/// to Git binary on user's system. Thus, this shortcut cannot be made to
-/// reliably use shareable context without somehow making the Git binary aware
-/// of said shareable context.
-///
-/// # Invariant
-///
-/// - Will not use [`SharedContext`].
 #[derive(Debug, Eq, PartialEq)]
-pub struct GitContext {
-    pub repo: OsString,
-    pub git_args: Vec<OsString>,
+pub struct EnvContext {
+    pub shared: SharedContext,
 }
 
-impl From<Cli> for GitContext {
+impl From<Cli> for EnvContext {
     fn from(opts: Cli) -> Self {
-        let Cli { cmd_set, .. } = opts;
+        let Cli { shared_opts, cmd_set, .. } = opts;
+        match cmd_set {
+            CommandSet::Env(_) => (),
+            _ => unreachable!("This should never happen. The command is not 'env'!"),
+        };
+
+        Self { shared: shared_opts.into() }
+    }
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct ProfileContext {
+    pub op: ProfileOp,
+    pub shared: SharedContext,
+}
+
+/// Operation to perform on profiles.
+#[derive(Debug, Eq, PartialEq)]
+pub enum ProfileOp {
+    Fork { name: String, from: Option<String>, share_repos: bool },
+}
+
+impl From<Cli> for ProfileContext {
+    fn from(opts: Cli) -> Self {
+        let Cli { shared_opts, cmd_set, .. } = opts;
         let cmd_set = match cmd_set {
-            CommandSet::Git(opts) => opts,
-            _ => unreachable!("This should not happen. The command is not git shortcut!"),
+            CommandSet::Profile(opts) => opts,
+            _ => unreachable!("This should never happen. The command is not 'profile'!"),
         };
 
-        Self { repo: cmd_set[0].clone(), git_args: cmd_set[1..].to_vec() }
+        let op = match cmd_set.action {
+            ProfileAction::Fork(opts) => {
+                ProfileOp::Fork { name: opts.name, from: opts.from, share_repos: opts.share_repos }
+            }
+        };
+
+        Self { op, shared: shared_opts.into() }
     }
 }
 
-/// Context for shareable options between commands.
-///
-/// # Invariant
-///
-/// - [`GitContext`] will not have shareable context.
 #[derive(Debug, Eq, PartialEq)]
-pub struct SharedContext {
-    pub run_hook: HookAction,
+pub struct HookContext {
+    pub op: HookOp,
+    pub shared: SharedContext,
 }
 
-impl From<SharedOptions> for SharedContext {
-    fn from(opts: SharedOptions) -> Self {
-        Self { run_hook: opts.run_hook }
+/// Operation to perform on command hooks.
+#[derive(Debug, Eq, PartialEq)]
+pub enum HookOp {
+    List { cmd: Option<String>, effective: bool },
+    Log { id: Option<String>, failed: bool, limit: Option<usize> },
+}
+
+impl From<Cli> for HookContext {
+    fn from(opts: Cli) -> Self {
+        let Cli { shared_opts, cmd_set, .. } = opts;
+        let cmd_set = match cmd_set {
+            CommandSet::Hook(opts) => opts,
+            _ => unreachable!("This should never happen. The command is not 'hook'!"),
+        };
+
+        let op = match cmd_set.action {
+            HookCmdAction::List(opts) => HookOp::List { cmd: opts.cmd, effective: opts.effective },
+            HookCmdAction::Log(opts) => {
+                HookOp::Log { id: opts.id, failed: opts.failed, limit: opts.limit }
+            }
+        };
+
+        Self { op, shared: shared_opts.into() }
     }
 }
 
-/// Behavior types for hook execution in shareable `--run-hook` flag.
-#[derive(Default, Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
-pub enum HookAction {
-    /// Always execute hooks no questions asked.
-    Always,
+#[derive(Debug, Eq, PartialEq)]
+pub struct SnapshotContext {
+    pub name: Option<String>,
+    pub output: Option<PathBuf>,
+    pub bundle: bool,
+    pub shared: SharedContext,
+}
 
-    /// Prompt user with hook's contents, and execute it if and only if user accepts it.
-    #[default]
-    Prompt,
+impl From<Cli> for SnapshotContext {
+    fn from(opts: Cli) -> Self {
+        let Cli { shared_opts, cmd_set, .. } = opts;
+        let cmd_set = match cmd_set {
+            CommandSet::Snapshot(opts) => opts,
+            _ => unreachable!("This should never happen. The command is not 'snapshot'!"),
+        };
 
-    /// Never execute hooks no questions asked.
-    Never,
+        Self {
+            name: cmd_set.name,
+            output: cmd_set.output,
+            bundle: cmd_set.bundle,
+            shared: shared_opts.into(),
+        }
+    }
 }
 
-/// Fixup actions for `--fixup` flag in commit command.
-#[derive(Default, Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
-pub enum FixupAction {
-    /// Amend changes in latest commit to all repositories.
-    #[default]
-    Amend,
+#[derive(Debug, Eq, PartialEq)]
+pub struct DeployContext {
+    pub repo: String,
+    pub shared: SharedContext,
+}
 
-    /// Fix text of latest commit to all repositories.
-    Reword,
+impl From<Cli> for DeployContext {
+    fn from(opts: Cli) -> Self {
+        let Cli { shared_opts, cmd_set, .. } = opts;
+        let cmd_set = match cmd_set {
+            CommandSet::Deploy(opts) => opts,
+            _ => unreachable!("This should never happen. The command is not 'deploy'!"),
+        };
+
+        Self { repo: cmd_set.repo, shared: shared_opts.into() }
+    }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+#[derive(Debug, Eq, PartialEq)]
+pub struct UndeployContext {
+    pub repo: String,
+    pub shared: SharedContext,
+}
 
-    use anyhow::Result;
-    use pretty_assertions::assert_eq;
-    use rstest::rstest;
-    use std::ffi::OsString;
+impl From<Cli> for UndeployContext {
+    fn from(opts: Cli) -> Self {
+        let Cli { shared_opts, cmd_set, .. } = opts;
+        let cmd_set = match cmd_set {
+            CommandSet::Undeploy(opts) => opts,
+            _ => unreachable!("This should never happen. The command is not 'undeploy'!"),
+        };
 
-    #[rstest]
-    #[case::shared_run_hook(
-        ["ricer", "--run-hook", "always", "enter", "foo"],
-        Context::Enter(EnterContext {
-            repo: "foo".into(),
-            shared: SharedContext { run_hook: HookAction::Always },
-        })
-    )]
-    #[case::bootstrap(
-        ["ricer", "bootstrap", "--config", "vim", "--only", "sh,mutt,vim", "--from", "url"],
-        Context::Bootstrap(BootstrapContext {
-            config: Some("vim".into()),
-            from: Some("url".into()),
-            only: Some(vec!["sh".into(), "mutt".into(), "vim".into()]),
-            shared: SharedContext { run_hook: HookAction::default() },
-        })
-    )]
-    #[case::commit(["ricer", "commit", "--fixup", "amend", "--message", "hello world"],
-        Context::Commit(CommitContext {
-            fixup: Some(FixupAction::Amend),
-            message: Some("hello world".into()),
-            shared: SharedContext { run_hook: HookAction::default() },
-        })
-    )]
-    #[case::clone(
-        ["never", "clone", "url", "foo"],
-        Context::Clone(CloneContext {
-            remote: "url".into(),
-            repo: Some("foo".into()),
-            shared: SharedContext { run_hook: HookAction::default() },
-        })
-    )]
-    #[case::delete(
-        ["ricer", "delete", "foo"],
-        Context::Delete( DeleteContext {
-            repo: "foo".into(),
-            shared: SharedContext { run_hook: HookAction::default() },
-        })
-    )]
-    #[case::enter(
-        ["ricer", "enter", "foo"],
-        Context::Enter(EnterContext {
-            repo: "foo".into(),
-            shared: SharedContext { run_hook: HookAction::default() },
-        })
-    )]
-    #[case::init(
-        ["ricer", "init", "foo", "--workdir-home", "--branch", "main", "--remote", "origin"],
-        Context::Init(InitContext {
-            name: "foo".into(),
-            workdir_home: true,
-            branch: Some("main".into()),
-            remote: Some("origin".into()),
-            shared: SharedContext { run_hook: HookAction::default() },
-        })
-    )]
+        Self { repo: cmd_set.repo, shared: shared_opts.into() }
+    }
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct OwnsContext {
+    pub paths: Vec<String>,
+    pub shared: SharedContext,
+}
+
+impl From<Cli> for OwnsContext {
+    fn from(opts: Cli) -> Self {
+        let Cli { shared_opts, cmd_set, .. } = opts;
+        let cmd_set = match cmd_set {
+            CommandSet::Owns(opts) => opts,
+            _ => unreachable!("This should never happen. The command is not 'owns'!"),
+        };
+
+        Self { paths: cmd_set.paths, shared: shared_opts.into() }
+    }
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct DaemonContext {
+    pub once: bool,
+    pub systemd: bool,
+    pub shared: SharedContext,
+}
+
+impl From<Cli> for DaemonContext {
+    fn from(opts: Cli) -> Self {
+        let Cli { shared_opts, cmd_set, .. } = opts;
+        let cmd_set = match cmd_set {
+            CommandSet::Daemon(opts) => opts,
+            _ => unreachable!("This should never happen. The command is not 'daemon'!"),
+        };
+
+        Self { once: cmd_set.once, systemd: cmd_set.systemd, shared: shared_opts.into() }
+    }
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct SedContext {
+    pub pattern: String,
+    pub replacement: String,
+    pub repos: Vec<String>,
+    pub strict: bool,
+    pub yes: bool,
+    pub commit: Option<String>,
+    pub shared: SharedContext,
+}
+
+impl From<Cli> for SedContext {
+    fn from(opts: Cli) -> Self {
+        let Cli { shared_opts, cmd_set, .. } = opts;
+        let cmd_set = match cmd_set {
+            CommandSet::Sed(opts) => opts,
+            _ => unreachable!("This should never happen. The command is not 'sed'!"),
+        };
+
+        Self {
+            pattern: cmd_set.pattern,
+            replacement: cmd_set.replacement,
+            repos: cmd_set.repos.into_iter().chain(cmd_set.select.patterns).collect(),
+            strict: cmd_set.select.strict,
+            yes: cmd_set.yes,
+            commit: cmd_set.commit,
+            shared: shared_opts.into(),
+        }
+    }
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct HistoryContext {
+    pub repo: Option<String>,
+    pub command: Option<String>,
+    pub failed: bool,
+    pub limit: Option<usize>,
+    pub shared: SharedContext,
+}
+
+impl From<Cli> for HistoryContext {
+    fn from(opts: Cli) -> Self {
+        let Cli { shared_opts, cmd_set, .. } = opts;
+        let cmd_set = match cmd_set {
+            CommandSet::History(opts) => opts,
+            _ => unreachable!("This should never happen. The command is not 'history'!"),
+        };
+
+        Self {
+            repo: cmd_set.repo,
+            command: cmd_set.command,
+            failed: cmd_set.failed,
+            limit: cmd_set.limit,
+            shared: shared_opts.into(),
+        }
+    }
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct AddContext {
+    pub repo: String,
+    pub paths: Vec<PathBuf>,
+    pub patch: bool,
+    pub shared: SharedContext,
+}
+
+impl From<Cli> for AddContext {
+    fn from(opts: Cli) -> Self {
+        let Cli { shared_opts, cmd_set, .. } = opts;
+        let cmd_set = match cmd_set {
+            CommandSet::Add(opts) => opts,
+            _ => unreachable!("This should never happen. The command is not 'add'!"),
+        };
+
+        Self {
+            repo: cmd_set.repo,
+            paths: cmd_set.paths,
+            patch: cmd_set.patch,
+            shared: shared_opts.into(),
+        }
+    }
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct SplitContext {
+    pub repo: String,
+    pub dir: PathBuf,
+    pub name: String,
+    pub no_history: bool,
+    pub shared: SharedContext,
+}
+
+impl From<Cli> for SplitContext {
+    fn from(opts: Cli) -> Self {
+        let Cli { shared_opts, cmd_set, .. } = opts;
+        let cmd_set = match cmd_set {
+            CommandSet::Split(opts) => opts,
+            _ => unreachable!("This should never happen. The command is not 'split'!"),
+        };
+
+        Self {
+            repo: cmd_set.repo,
+            dir: cmd_set.dir,
+            name: cmd_set.name,
+            no_history: cmd_set.no_history,
+            shared: shared_opts.into(),
+        }
+    }
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct CleanContext {
+    pub repo: String,
+    pub paths: Vec<PathBuf>,
+    pub ignored: bool,
+    pub yes: bool,
+    pub shared: SharedContext,
+}
+
+impl From<Cli> for CleanContext {
+    fn from(opts: Cli) -> Self {
+        let Cli { shared_opts, cmd_set, .. } = opts;
+        let cmd_set = match cmd_set {
+            CommandSet::Clean(opts) => opts,
+            _ => unreachable!("This should never happen. The command is not 'clean'!"),
+        };
+
+        Self {
+            repo: cmd_set.repo,
+            paths: cmd_set.paths,
+            ignored: cmd_set.ignored,
+            yes: cmd_set.yes,
+            shared: shared_opts.into(),
+        }
+    }
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct SwitchContext {
+    pub repo: String,
+    pub branch: String,
+    pub save: bool,
+    pub shared: SharedContext,
+}
+
+impl From<Cli> for SwitchContext {
+    fn from(opts: Cli) -> Self {
+        let Cli { shared_opts, cmd_set, .. } = opts;
+        let cmd_set = match cmd_set {
+            CommandSet::Switch(opts) => opts,
+            _ => unreachable!("This should never happen. The command is not 'switch'!"),
+        };
+
+        Self {
+            repo: cmd_set.repo,
+            branch: cmd_set.branch,
+            save: cmd_set.save,
+            shared: shared_opts.into(),
+        }
+    }
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct RemotesContext {
+    pub op: RemotesOp,
+    pub shared: SharedContext,
+}
+
+/// Operation to perform on remotes of repositories.
+#[derive(Debug, Eq, PartialEq)]
+pub enum RemotesOp {
+    Rewrite { from: String, to: String, repos: Vec<String>, strict: bool, yes: bool },
+}
+
+impl From<Cli> for RemotesContext {
+    fn from(opts: Cli) -> Self {
+        let Cli { shared_opts, cmd_set, .. } = opts;
+        let cmd_set = match cmd_set {
+            CommandSet::Remotes(opts) => opts,
+            _ => unreachable!("This should never happen. The command is not 'remotes'!"),
+        };
+
+        let op = match cmd_set.action {
+            RemotesAction::Rewrite(opts) => RemotesOp::Rewrite {
+                from: opts.from,
+                to: opts.to,
+                repos: opts.repos.into_iter().chain(opts.select.patterns).collect(),
+                strict: opts.select.strict,
+                yes: opts.yes,
+            },
+        };
+
+        Self { op, shared: shared_opts.into() }
+    }
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct IgnoreContext {
+    pub op: IgnoreOp,
+    pub shared: SharedContext,
+}
+
+/// Operation to perform on ignore file of repository.
+#[derive(Debug, Eq, PartialEq)]
+pub enum IgnoreOp {
+    Add { repo: String, patterns: Vec<String> },
+    Remove { repo: String, patterns: Vec<String> },
+    List { repo: String },
+}
+
+impl IgnoreOp {
+    /// Name of target repository.
+    pub fn repo(&self) -> &str {
+        match self {
+            IgnoreOp::Add { repo, .. }
+            | IgnoreOp::Remove { repo, .. }
+            | IgnoreOp::List { repo } => repo,
+        }
+    }
+}
+
+impl From<Cli> for IgnoreContext {
+    fn from(opts: Cli) -> Self {
+        let Cli { shared_opts, cmd_set, .. } = opts;
+        let cmd_set = match cmd_set {
+            CommandSet::Ignore(opts) => opts,
+            _ => unreachable!("This should never happen. The command is not 'ignore'!"),
+        };
+
+        let op = match cmd_set.action {
+            IgnoreAction::Add(opts) => IgnoreOp::Add { repo: opts.repo, patterns: opts.patterns },
+            IgnoreAction::Remove(opts) => {
+                IgnoreOp::Remove { repo: opts.repo, patterns: opts.patterns }
+            }
+            IgnoreAction::List(opts) => IgnoreOp::List { repo: opts.repo },
+        };
+
+        Self { op, shared: shared_opts.into() }
+    }
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct ForeachContext {
+    pub command: String,
+    pub repos: Vec<String>,
+    pub strict: bool,
+    pub jobs: Option<u16>,
+    pub shared: SharedContext,
+}
+
+impl From<Cli> for ForeachContext {
+    fn from(opts: Cli) -> Self {
+        let Cli { shared_opts, cmd_set, .. } = opts;
+        let cmd_set = match cmd_set {
+            CommandSet::Foreach(opts) => opts,
+            _ => unreachable!("This should never happen. The command is not 'foreach'!"),
+        };
+
+        Self {
+            command: cmd_set.command,
+            repos: cmd_set.repos.into_iter().chain(cmd_set.select.patterns).collect(),
+            strict: cmd_set.select.strict,
+            jobs: cmd_set.jobs,
+            shared: shared_opts.into(),
+        }
+    }
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct LockContext {
+    pub shared: SharedContext,
+}
+
+impl From<Cli> for LockContext {
+    fn from(opts: Cli) -> Self {
+        let Cli { shared_opts, cmd_set, .. } = opts;
+        match cmd_set {
+            CommandSet::Lock(_) => (),
+            _ => unreachable!("This should never happen. The command is not 'lock'!"),
+        };
+
+        Self { shared: shared_opts.into() }
+    }
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct UnlockContext {
+    pub shared: SharedContext,
+}
+
+impl From<Cli> for UnlockContext {
+    fn from(opts: Cli) -> Self {
+        let Cli { shared_opts, cmd_set, .. } = opts;
+        match cmd_set {
+            CommandSet::Unlock(_) => (),
+            _ => unreachable!("This should never happen. The command is not 'unlock'!"),
+        };
+
+        Self { shared: shared_opts.into() }
+    }
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct DoctorContext {
+    pub fix: bool,
+    pub yes: bool,
+    pub shared: SharedContext,
+}
+
+impl From<Cli> for DoctorContext {
+    fn from(opts: Cli) -> Self {
+        let Cli { shared_opts, cmd_set, .. } = opts;
+        let opts = match cmd_set {
+            CommandSet::Doctor(opts) => opts,
+            _ => unreachable!("This should never happen. The command is not 'doctor'!"),
+        };
+
+        Self { fix: opts.fix, yes: opts.yes, shared: shared_opts.into() }
+    }
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct ConfigContext {
+    pub op: ConfigOp,
+    pub shared: SharedContext,
+}
+
+/// Operation to perform on configuration files.
+#[derive(Debug, Eq, PartialEq)]
+pub enum ConfigOp {
+    Watch { interval: Duration, notify: bool },
+}
+
+impl From<Cli> for ConfigContext {
+    fn from(opts: Cli) -> Self {
+        let Cli { shared_opts, cmd_set, .. } = opts;
+        let cmd_set = match cmd_set {
+            CommandSet::Config(opts) => opts,
+            _ => unreachable!("This should never happen. The command is not 'config'!"),
+        };
+
+        let op = match cmd_set.action {
+            ConfigAction::Watch(opts) => ConfigOp::Watch {
+                interval: Duration::from_millis(opts.interval),
+                notify: opts.notify,
+            },
+        };
+
+        Self { op, shared: shared_opts.into() }
+    }
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct InfoContext {
+    pub repo: String,
+    pub json: bool,
+    pub shared: SharedContext,
+}
+
+impl From<Cli> for InfoContext {
+    fn from(opts: Cli) -> Self {
+        let Cli { shared_opts, cmd_set, .. } = opts;
+        let cmd_set = match cmd_set {
+            CommandSet::Info(opts) => opts,
+            _ => unreachable!("This should never happen. The command is not 'info'!"),
+        };
+
+        Self { repo: cmd_set.repo, json: cmd_set.json, shared: shared_opts.into() }
+    }
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct CompletionsContext {
+    pub shell: Option<Shell>,
+    pub install: bool,
+    pub uninstall: bool,
+    pub dry_run: bool,
+    pub shared: SharedContext,
+}
+
+impl From<Cli> for CompletionsContext {
+    fn from(opts: Cli) -> Self {
+        let Cli { shared_opts, cmd_set, .. } = opts;
+        let cmd_set = match cmd_set {
+            CommandSet::Completions(opts) => opts,
+            _ => unreachable!("This should never happen. The command is not 'completions'!"),
+        };
+
+        Self {
+            shell: cmd_set.shell,
+            install: cmd_set.install,
+            uninstall: cmd_set.uninstall,
+            dry_run: cmd_set.dry_run,
+            shared: shared_opts.into(),
+        }
+    }
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct StatsContext {
+    pub hooks: bool,
+    pub shared: SharedContext,
+}
+
+impl From<Cli> for StatsContext {
+    fn from(opts: Cli) -> Self {
+        let Cli { shared_opts, cmd_set, .. } = opts;
+        let cmd_set = match cmd_set {
+            CommandSet::Stats(opts) => opts,
+            _ => unreachable!("This should never happen. The command is not 'stats'!"),
+        };
+
+        Self { hooks: cmd_set.hooks, shared: shared_opts.into() }
+    }
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct ExamplesContext {
+    pub topic: Option<ExampleTopic>,
+    pub shared: SharedContext,
+}
+
+impl From<Cli> for ExamplesContext {
+    fn from(opts: Cli) -> Self {
+        let Cli { shared_opts, cmd_set, .. } = opts;
+        let cmd_set = match cmd_set {
+            CommandSet::Examples(opts) => opts,
+            _ => unreachable!("This should never happen. The command is not 'examples'!"),
+        };
+
+        Self { topic: cmd_set.topic, shared: shared_opts.into() }
+    }
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct BugreportContext {
+    pub output: Option<PathBuf>,
+    pub shared: SharedContext,
+}
+
+impl From<Cli> for BugreportContext {
+    fn from(opts: Cli) -> Self {
+        let Cli { shared_opts, cmd_set, .. } = opts;
+        let cmd_set = match cmd_set {
+            CommandSet::Bugreport(opts) => opts,
+            _ => unreachable!("This should never happen. The command is not 'bugreport'!"),
+        };
+
+        Self { output: cmd_set.output, shared: shared_opts.into() }
+    }
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct MoveConfigDirContext {
+    pub to: PathBuf,
+    pub data: bool,
+    pub state: bool,
+    pub yes: bool,
+    pub shared: SharedContext,
+}
+
+impl From<Cli> for MoveConfigDirContext {
+    fn from(opts: Cli) -> Self {
+        let Cli { shared_opts, cmd_set, .. } = opts;
+        let cmd_set = match cmd_set {
+            CommandSet::MoveConfigDir(opts) => opts,
+            _ => unreachable!("This should never happen. The command is not 'move-config-dir'!"),
+        };
+
+        Self {
+            to: cmd_set.to,
+            data: cmd_set.data,
+            state: cmd_set.state,
+            yes: cmd_set.yes,
+            shared: shared_opts.into(),
+        }
+    }
+}
+
+/// Git shorcut context.
+///
+/// Does not use shareable context, because the Git shortcut is a system call
+/// to Git binary on user's system. Thus, this shortcut cannot be made to
+/// reliably use shareable context without somehow making the Git binary aware
+/// of said shareable context.
+///
+/// # Invariant
+///
+/// - Will not use [`SharedContext`].
+#[derive(Debug, Eq, PartialEq)]
+pub struct GitContext {
+    pub repo: OsString,
+    pub git_args: Vec<OsString>,
+}
+
+impl From<Cli> for GitContext {
+    fn from(opts: Cli) -> Self {
+        let Cli { cmd_set, .. } = opts;
+        let cmd_set = match cmd_set {
+            CommandSet::Git(opts) => opts,
+            _ => unreachable!("This should not happen. The command is not git shortcut!"),
+        };
+
+        Self { repo: cmd_set[0].clone(), git_args: cmd_set[1..].to_vec() }
+    }
+}
+
+/// Context for shareable options between commands.
+///
+/// # Invariant
+///
+/// - [`GitContext`] will not have shareable context.
+#[derive(Debug, Default, Eq, PartialEq)]
+pub struct SharedContext {
+    pub run_hook: HookAction,
+
+    /// Kill switch that prevents any hook from executing. Takes precedence
+    /// over every other hook setting.
+    pub no_hooks: bool,
+
+    /// Only execute hooks matching any of these selectors.
+    pub only_hooks: Vec<String>,
+
+    /// Skip hooks matching any of these selectors.
+    pub skip_hooks: Vec<String>,
+
+    /// Re-read hook configuration file between hook executions once it
+    /// changes, so later hooks pick up edits.
+    pub reload_hooks: bool,
+
+    /// Never page command output, regardless of pager configuration.
+    pub no_pager: bool,
+
+    /// Report duration of each command phase once command finishes.
+    pub timings: bool,
+
+    /// Accept SSH host keys of unknown hosts without confirmation.
+    pub no_verify_host: bool,
+
+    /// When to draw progress of remote operations.
+    pub progress: ProgressMode,
+
+    /// Ad-hoc variables passed on to hooks and templates, in the order given.
+    /// Later values of a key replace earlier ones.
+    pub vars: Vec<(String, String)>,
+
+    /// Record side effects instead of performing them.
+    pub dry_run: bool,
+}
+
+impl From<SharedOptions> for SharedContext {
+    fn from(opts: SharedOptions) -> Self {
+        Self {
+            run_hook: opts.run_hook,
+            no_hooks: opts.no_hooks,
+            only_hooks: opts.only_hooks,
+            skip_hooks: opts.skip_hooks,
+            reload_hooks: opts.reload_hooks,
+            no_pager: opts.no_pager,
+            timings: opts.timings,
+            no_verify_host: opts.no_verify_host,
+            progress: opts.progress,
+            vars: opts.context,
+            dry_run: opts.dry_run,
+        }
+    }
+}
+
+/// Forge namespace to clone repositories from.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ForgeContext {
+    pub kind: ForgeKind,
+    pub namespace: String,
+    pub topic: Option<String>,
+    pub pattern: Option<String>,
+}
+
+/// Supported forge types for `clone --from-*` flags.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ForgeKind {
+    GitHub,
+    GitLab,
+}
+
+impl fmt::Display for ForgeKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ForgeKind::GitHub => write!(f, "GitHub"),
+            ForgeKind::GitLab => write!(f, "GitLab"),
+        }
+    }
+}
+
+/// Behavior types for hook execution in shareable `--run-hooks` flag.
+#[derive(Default, Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+pub enum HookAction {
+    /// Always execute hooks no questions asked.
+    Always,
+
+    /// Prompt user with hook's contents, and execute it if and only if user accepts it.
+    #[default]
+    Prompt,
+
+    /// Never execute hooks no questions asked.
+    Never,
+}
+
+impl HookAction {
+    /// Get the more cautious out of this and `other` action.
+    ///
+    /// Never is more cautious than prompt, which is more cautious than always.
+    pub fn restrict(self, other: HookAction) -> HookAction {
+        match (self, other) {
+            (HookAction::Never, _) | (_, HookAction::Never) => HookAction::Never,
+            (HookAction::Prompt, _) | (_, HookAction::Prompt) => HookAction::Prompt,
+            _ => HookAction::Always,
+        }
+    }
+}
+
+impl FromStr for HookAction {
+    type Err = String;
+
+    fn from_str(action: &str) -> Result<Self, Self::Err> {
+        match action {
+            "always" => Ok(HookAction::Always),
+            "prompt" => Ok(HookAction::Prompt),
+            "never" => Ok(HookAction::Never),
+            _ => Err(format!("invalid hook action '{action}'")),
+        }
+    }
+}
+
+impl fmt::Display for HookAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HookAction::Always => write!(f, "always"),
+            HookAction::Prompt => write!(f, "prompt"),
+            HookAction::Never => write!(f, "never"),
+        }
+    }
+}
+
+/// When to draw progress of clone, fetch, pull, and push in shareable
+/// `--progress` flag.
+#[derive(Default, Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ProgressMode {
+    /// Draw progress if standard error is a terminal.
+    #[default]
+    Auto,
+
+    /// Always draw progress.
+    Always,
+
+    /// Never draw progress.
+    Never,
+}
+
+impl ProgressMode {
+    /// Check if progress should be drawn, where `is_terminal` tells whether
+    /// standard error is a terminal.
+    pub fn enabled(self, is_terminal: bool) -> bool {
+        match self {
+            ProgressMode::Auto => is_terminal,
+            ProgressMode::Always => true,
+            ProgressMode::Never => false,
+        }
+    }
+}
+
+/// Fixup actions for `--fixup` flag in commit command.
+#[derive(Default, Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+pub enum FixupAction {
+    /// Amend changes in latest commit to all repositories.
+    #[default]
+    Amend,
+
+    /// Fix text of latest commit to all repositories.
+    Reword,
+}
+
+/// Output formats of `ricer list`.
+#[derive(Default, Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ListFormat {
+    /// One repository per line, followed by its files.
+    #[default]
+    Plain,
+
+    /// JSON array with one object per repository.
+    Json,
+
+    /// TOML document laid out like the repository configuration file.
+    Toml,
+}
+
+/// Topics of `ricer examples`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ExampleTopic {
+    /// Command hooks, their scripts, and how to run them.
+    Hooks,
+
+    /// Registering repositories, and fake bare repositories.
+    Repos,
+
+    /// Bootstrapping new machines.
+    Bootstrap,
+}
+
+impl fmt::Display for ExampleTopic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExampleTopic::Hooks => write!(f, "hooks"),
+            ExampleTopic::Repos => write!(f, "repos"),
+            ExampleTopic::Bootstrap => write!(f, "bootstrap"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use anyhow::Result;
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+    use std::ffi::OsString;
+
+    #[rstest]
+    #[case::shared_run_hook(
+        ["ricer", "--run-hooks", "always", "enter", "foo"],
+        Context::Enter(EnterContext {
+            repo: "foo".into(),
+            shared: SharedContext { run_hook: HookAction::Always, ..Default::default() },
+        })
+    )]
+    #[case::shared_no_hooks(
+        ["ricer", "--no-hooks", "enter", "foo"],
+        Context::Enter(EnterContext {
+            repo: "foo".into(),
+            shared: SharedContext { no_hooks: true, ..Default::default() },
+        })
+    )]
+    #[case::shared_no_pager(
+        ["ricer", "--no-pager", "enter", "foo"],
+        Context::Enter(EnterContext {
+            repo: "foo".into(),
+            shared: SharedContext { no_pager: true, ..Default::default() },
+        })
+    )]
+    #[case::shared_timings(
+        ["ricer", "--timings", "enter", "foo"],
+        Context::Enter(EnterContext {
+            repo: "foo".into(),
+            shared: SharedContext { timings: true, ..Default::default() },
+        })
+    )]
+    #[case::shared_no_verify_host(
+        ["ricer", "--no-verify-host", "enter", "foo"],
+        Context::Enter(EnterContext {
+            repo: "foo".into(),
+            shared: SharedContext { no_verify_host: true, ..Default::default() },
+        })
+    )]
+    #[case::shared_progress(
+        ["ricer", "--progress", "never", "enter", "foo"],
+        Context::Enter(EnterContext {
+            repo: "foo".into(),
+            shared: SharedContext { progress: ProgressMode::Never, ..Default::default() },
+        })
+    )]
+    #[case::shared_context_vars(
+        ["ricer", "--context", "theme=nord", "--context", "bar=a=b", "enter", "foo"],
+        Context::Enter(EnterContext {
+            repo: "foo".into(),
+            shared: SharedContext {
+                vars: vec![("theme".into(), "nord".into()), ("bar".into(), "a=b".into())],
+                ..Default::default()
+            },
+        })
+    )]
+    #[case::shared_dry_run(
+        ["ricer", "--dry-run", "enter", "foo"],
+        Context::Enter(EnterContext {
+            repo: "foo".into(),
+            shared: SharedContext { dry_run: true, ..Default::default() },
+        })
+    )]
+    #[case::shared_reload_hooks(
+        ["ricer", "--reload-hooks", "enter", "foo"],
+        Context::Enter(EnterContext {
+            repo: "foo".into(),
+            shared: SharedContext { reload_hooks: true, ..Default::default() },
+        })
+    )]
+    #[case::shared_hook_filters(
+        ["ricer", "--only-hooks", "a.sh,2", "--skip-hooks", "b", "--skip-hooks", "c", "enter", "foo"],
+        Context::Enter(EnterContext {
+            repo: "foo".into(),
+            shared: SharedContext {
+                only_hooks: vec!["a.sh".into(), "2".into()],
+                skip_hooks: vec!["b".into(), "c".into()],
+                ..Default::default()
+            },
+        })
+    )]
+    #[case::bootstrap(
+        ["ricer", "bootstrap", "--config", "vim", "--only", "sh,mutt,vim", "--from", "url"],
+        Context::Bootstrap(BootstrapContext {
+            config: Some("vim".into()),
+            from: Some("url".into()),
+            only: Some(vec!["sh".into(), "mutt".into(), "vim".into()]),
+            plan: false,
+            shared: SharedContext::default(),
+        })
+    )]
+    #[case::bootstrap_plan(
+        ["ricer", "bootstrap", "--plan", "--only", "vim"],
+        Context::Bootstrap(BootstrapContext {
+            config: None,
+            from: None,
+            only: Some(vec!["vim".into()]),
+            plan: true,
+            shared: SharedContext::default(),
+        })
+    )]
+    #[case::commit(
+        [
+            "ricer", "commit", "--fixup", "amend", "--message", "hello world", "-n", "-p",
+            "--include-glob", "*.sh", "--include-glob", ".bashrc", "--exclude-glob", "/tmp/",
+            "--allow-excluded", "dwm*", "st", "~/.config/nvim/**", "--strict",
+        ],
+        Context::Commit(CommitContext {
+            fixup: Some(FixupAction::Amend),
+            message: Some("hello world".into()),
+            no_verify: true,
+            push: true,
+            include_globs: vec!["*.sh".into(), ".bashrc".into()],
+            exclude_globs: vec!["/tmp/".into()],
+            allow_excluded: true,
+            repos: vec!["dwm*".into(), "st".into()],
+            paths: vec!["~/.config/nvim/**".into()],
+            strict: true,
+            shared: SharedContext::default(),
+        })
+    )]
+    #[case::clone(
+        ["never", "clone", "url", "foo", "--adopt-existing-files"],
+        Context::Clone(CloneContext {
+            remote: Some("url".into()),
+            repo: Some("foo".into()),
+            forge: None,
+            adopt: true,
+            shared: SharedContext::default(),
+        })
+    )]
+    #[case::clone_forge(
+        ["ricer", "clone", "--from-gitlab", "awkless/", "--topic", "dotfiles", "--pattern", "v*"],
+        Context::Clone(CloneContext {
+            remote: None,
+            repo: None,
+            forge: Some(ForgeContext {
+                kind: ForgeKind::GitLab,
+                namespace: "awkless/".into(),
+                topic: Some("dotfiles".into()),
+                pattern: Some("v*".into()),
+            }),
+            adopt: false,
+            shared: SharedContext::default(),
+        })
+    )]
+    #[case::delete(
+        ["ricer", "delete", "foo"],
+        Context::Delete( DeleteContext {
+            repo: "foo".into(),
+            keep_files: false,
+            shared: SharedContext::default(),
+        })
+    )]
+    #[case::delete_keep_files(
+        ["ricer", "delete", "foo", "--keep-files"],
+        Context::Delete( DeleteContext {
+            repo: "foo".into(),
+            keep_files: true,
+            shared: SharedContext::default(),
+        })
+    )]
+    #[case::enter(
+        ["ricer", "enter", "foo"],
+        Context::Enter(EnterContext {
+            repo: "foo".into(),
+            shared: SharedContext::default(),
+        })
+    )]
+    #[case::init(
+        [
+            "ricer", "init", "foo", "--workdir-home", "--branch", "main", "--remote", "origin",
+            "--template", "dotfiles", "--initial-commit", "--force",
+        ],
+        Context::Init(InitContext {
+            name: "foo".into(),
+            workdir_home: true,
+            branch: Some("main".into()),
+            remote: Some("origin".into()),
+            template: Some("dotfiles".into()),
+            initial_commit: true,
+            force: true,
+            shared: SharedContext::default(),
+        })
+    )]
     #[case::list(
-        ["ricer", "list", "--tracked", "--untracked"],
+        ["ricer", "list", "--tracked", "--untracked", "--format", "json"],
         Context::List(ListContext {
             tracked: true,
             untracked: true,
-            shared: SharedContext { run_hook: HookAction::default() },
+            format: ListFormat::Json,
+            shared: SharedContext::default(),
         })
     )]
     #[case::push(
@@ -444,30 +1921,260 @@ mod tests {
         Context::Push(PushContext {
             remote: Some("origin".into()),
             branch: Some("main".into()),
-            shared: SharedContext { run_hook: HookAction::default() },
+            shared: SharedContext::default(),
         })
     )]
     #[case::pull(
-        ["ricer", "pull", "origin", "main"],
+        ["ricer", "pull", "--remote", "origin", "--branch", "main", "--fix", "-j", "2", "dwm*"],
         Context::Pull(PullContext {
             remote: Some("origin".into()),
             branch: Some("main".into()),
-            shared: SharedContext { run_hook: HookAction::default() },
+            fix: true,
+            jobs: Some(2),
+            repos: vec!["dwm*".into()],
+            strict: false,
+            shared: SharedContext::default(),
         })
     )]
     #[case::rename(
-        ["ricer", "rename", "foo", "bar"],
+        ["ricer", "rename", "foo", "bar", "--dry-run"],
         Context::Rename(RenameContext {
             from: "foo".into(),
             to: "bar".into(),
-            shared: SharedContext { run_hook: HookAction::default() },
+            dry_run: true,
+            shared: SharedContext::default(),
         })
     )]
     #[case::status(
-        ["ricer", "status", "--terse"],
+        ["ricer", "status", "--terse", "--assume-clean", "vim"],
         Context::Status(StatusContext {
             terse: true,
-            shared: SharedContext { run_hook: HookAction::default() },
+            assume_clean: true,
+            repos: vec!["vim".into()],
+            strict: false,
+            shared: SharedContext::default(),
+        })
+    )]
+    #[case::env(
+        ["ricer", "env"],
+        Context::Env(EnvContext { shared: SharedContext::default() })
+    )]
+    #[case::profile_fork(
+        ["ricer", "profile", "fork", "work", "--from", "home", "--share-repos"],
+        Context::Profile(ProfileContext {
+            op: ProfileOp::Fork { name: "work".into(), from: Some("home".into()), share_repos: true },
+            shared: SharedContext::default(),
+        })
+    )]
+    #[case::hook_list(
+        ["ricer", "hook", "list", "commit", "--effective"],
+        Context::Hook(HookContext {
+            op: HookOp::List { cmd: Some("commit".into()), effective: true },
+            shared: SharedContext::default(),
+        })
+    )]
+    #[case::hook_log(
+        ["ricer", "hook", "log", "--failed", "-n", "5"],
+        Context::Hook(HookContext {
+            op: HookOp::Log { id: None, failed: true, limit: Some(5) },
+            shared: SharedContext::default(),
+        })
+    )]
+    #[case::hooks_log(
+        ["ricer", "hooks", "log", "1700000000250-commit-pre-0"],
+        Context::Hook(HookContext {
+            op: HookOp::Log { id: Some("1700000000250-commit-pre-0".into()), failed: false, limit: None },
+            shared: SharedContext::default(),
+        })
+    )]
+    #[case::snapshot(
+        ["ricer", "snapshot", "known-good", "--output", "/backup", "--bundle"],
+        Context::Snapshot(SnapshotContext {
+            name: Some("known-good".into()),
+            output: Some("/backup".into()),
+            bundle: true,
+            shared: SharedContext::default(),
+        })
+    )]
+    #[case::deploy(
+        ["ricer", "deploy", "vim"],
+        Context::Deploy(DeployContext { repo: "vim".into(), shared: SharedContext::default() })
+    )]
+    #[case::undeploy(
+        ["ricer", "undeploy", "vim"],
+        Context::Undeploy(UndeployContext { repo: "vim".into(), shared: SharedContext::default() })
+    )]
+    #[case::owns(
+        ["ricer", "owns", "~/.vimrc", "~/.config/*.conf"],
+        Context::Owns(OwnsContext {
+            paths: vec!["~/.vimrc".into(), "~/.config/*.conf".into()],
+            shared: SharedContext::default(),
+        })
+    )]
+    #[case::daemon(
+        ["ricer", "daemon", "--once"],
+        Context::Daemon(DaemonContext {
+            once: true,
+            systemd: false,
+            shared: SharedContext::default(),
+        })
+    )]
+    #[case::sed(
+        [
+            "ricer", "sed", "#222222", "#1d2021", "-r", "dwm", "--repo", "st", "-y", "-c",
+            "Recolor", "d*", "--strict",
+        ],
+        Context::Sed(SedContext {
+            pattern: "#222222".into(),
+            replacement: "#1d2021".into(),
+            repos: vec!["dwm".into(), "st".into(), "d*".into()],
+            strict: true,
+            yes: true,
+            commit: Some("Recolor".into()),
+            shared: SharedContext::default(),
+        })
+    )]
+    #[case::history(
+        ["ricer", "history", "--repo", "vim", "-c", "commit", "--failed", "-n", "5"],
+        Context::History(HistoryContext {
+            repo: Some("vim".into()),
+            command: Some("commit".into()),
+            failed: true,
+            limit: Some(5),
+            shared: SharedContext::default(),
+        })
+    )]
+    #[case::add(
+        ["ricer", "add", "vim", "--patch", ".vimrc"],
+        Context::Add(AddContext {
+            repo: "vim".into(),
+            paths: vec![".vimrc".into()],
+            patch: true,
+            shared: SharedContext::default(),
+        })
+    )]
+    #[case::split(
+        ["ricer", "split", "dots", "vim", "vim", "--no-history"],
+        Context::Split(SplitContext {
+            repo: "dots".into(),
+            dir: "vim".into(),
+            name: "vim".into(),
+            no_history: true,
+            shared: SharedContext::default(),
+        })
+    )]
+    #[case::clean(
+        ["ricer", "clean", "dots", ".cache/vim", "-x", "--yes"],
+        Context::Clean(CleanContext {
+            repo: "dots".into(),
+            paths: vec![".cache/vim".into()],
+            ignored: true,
+            yes: true,
+            shared: SharedContext::default(),
+        })
+    )]
+    #[case::switch(
+        ["ricer", "switch", "dots", "nord", "--save"],
+        Context::Switch(SwitchContext {
+            repo: "dots".into(),
+            branch: "nord".into(),
+            save: true,
+            shared: SharedContext::default(),
+        })
+    )]
+    #[case::remotes_rewrite(
+        ["ricer", "remotes", "rewrite", "--from", "github.com", "--to", "codeberg.org", "-r", "vim"],
+        Context::Remotes(RemotesContext {
+            op: RemotesOp::Rewrite {
+                from: "github.com".into(),
+                to: "codeberg.org".into(),
+                repos: vec!["vim".into()],
+                strict: false,
+                yes: false,
+            },
+            shared: SharedContext::default(),
+        })
+    )]
+    #[case::foreach(
+        ["ricer", "foreach", "echo {name}", "-r", "vim", "--jobs", "4", "st*"],
+        Context::Foreach(ForeachContext {
+            command: "echo {name}".into(),
+            repos: vec!["vim".into(), "st*".into()],
+            strict: false,
+            jobs: Some(4),
+            shared: SharedContext::default(),
+        })
+    )]
+    #[case::lock(["ricer", "lock"], Context::Lock(LockContext { shared: SharedContext::default() }))]
+    #[case::unlock(
+        ["ricer", "unlock"],
+        Context::Unlock(UnlockContext { shared: SharedContext::default() })
+    )]
+    #[case::doctor(
+        ["ricer", "doctor"],
+        Context::Doctor(DoctorContext { fix: false, yes: false, shared: SharedContext::default() })
+    )]
+    #[case::doctor_fix(
+        ["ricer", "doctor", "--fix", "--yes"],
+        Context::Doctor(DoctorContext { fix: true, yes: true, shared: SharedContext::default() })
+    )]
+    #[case::config_watch(
+        ["ricer", "config", "watch", "--interval", "200", "--notify"],
+        Context::Config(ConfigContext {
+            op: ConfigOp::Watch { interval: Duration::from_millis(200), notify: true },
+            shared: SharedContext::default(),
+        })
+    )]
+    #[case::info(
+        ["ricer", "info", "vim", "--json"],
+        Context::Info(InfoContext { repo: "vim".into(), json: true, shared: SharedContext::default() })
+    )]
+    #[case::completions(
+        ["ricer", "completions", "zsh", "--install", "--dry-run"],
+        Context::Completions(CompletionsContext {
+            shell: Some(Shell::Zsh),
+            install: true,
+            uninstall: false,
+            dry_run: true,
+            shared: SharedContext::default(),
+        })
+    )]
+    #[case::stats(
+        ["ricer", "stats", "--hooks"],
+        Context::Stats(StatsContext { hooks: true, shared: SharedContext::default() })
+    )]
+    #[case::ignore_add(
+        ["ricer", "ignore", "add", "vim", ".cache/", "Downloads/"],
+        Context::Ignore(IgnoreContext {
+            op: IgnoreOp::Add {
+                repo: "vim".into(),
+                patterns: vec![".cache/".into(), "Downloads/".into()],
+            },
+            shared: SharedContext::default(),
+        })
+    )]
+    #[case::examples(
+        ["ricer", "examples", "hooks"],
+        Context::Examples(ExamplesContext {
+            topic: Some(ExampleTopic::Hooks),
+            shared: SharedContext::default(),
+        })
+    )]
+    #[case::bugreport(
+        ["ricer", "bugreport", "--output", "/tmp"],
+        Context::Bugreport(BugreportContext {
+            output: Some("/tmp".into()),
+            shared: SharedContext::default(),
+        })
+    )]
+    #[case::move_config_dir(
+        ["ricer", "move-config-dir", "/sync/ricer", "--data", "-y"],
+        Context::MoveConfigDir(MoveConfigDirContext {
+            to: "/sync/ricer".into(),
+            data: true,
+            state: false,
+            yes: true,
+            shared: SharedContext::default(),
         })
     )]
     #[case::git_shortcut(
@@ -477,6 +2184,48 @@ mod tests {
             git_args: vec!["add".into(), "file.txt".into()]
         })
     )]
+    #[case::git_shortcut_flags(
+        ["ricer", "foo", "commit", "-m", "fix: quoted \"msg\"", "--amend", "-v"],
+        Context::Git(GitContext {
+            repo: "foo".into(),
+            git_args: vec![
+                "commit".into(),
+                "-m".into(),
+                "fix: quoted \"msg\"".into(),
+                "--amend".into(),
+                "-v".into(),
+            ]
+        })
+    )]
+    #[case::git_shortcut_help(
+        ["ricer", "foo", "--help"],
+        Context::Git(GitContext { repo: "foo".into(), git_args: vec!["--help".into()] })
+    )]
+    #[case::git_shortcut_version(
+        ["ricer", "foo", "--version"],
+        Context::Git(GitContext { repo: "foo".into(), git_args: vec!["--version".into()] })
+    )]
+    #[case::git_shortcut_pathspec(
+        ["ricer", "foo", "log", "--", "file.txt"],
+        Context::Git(GitContext {
+            repo: "foo".into(),
+            git_args: vec!["log".into(), "--".into(), "file.txt".into()]
+        })
+    )]
+    #[case::git_shortcut_separator(
+        ["ricer", "--", "init", "log", "--oneline"],
+        Context::Git(GitContext {
+            repo: "init".into(),
+            git_args: vec!["log".into(), "--oneline".into()]
+        })
+    )]
+    #[case::git_shortcut_ricer_opts_before_repo(
+        ["ricer", "--run-hooks", "never", "foo", "--run-hook", "always"],
+        Context::Git(GitContext {
+            repo: "foo".into(),
+            git_args: vec!["--run-hook".into(), "always".into()]
+        })
+    )]
     fn valid_ctx_from_cli<I, T>(#[case] args: I, #[case] expect: Context) -> Result<()>
     where
         I: IntoIterator<Item = T>,
@@ -487,4 +2236,104 @@ fn valid_ctx_from_cli<I, T>(#[case] args: I, #[case] expect: Context) -> Result<
         assert_eq!(expect, result);
         Ok(())
     }
+
+    #[rstest]
+    #[case::fill_flag(
+        vec!["ricer", "status"],
+        CommandSettings::new("status").set("terse", DefaultValue::Bool(true)),
+        Context::Status(StatusContext {
+            terse: true,
+            assume_clean: false,
+            repos: Vec::new(),
+            strict: false,
+            shared: SharedContext::default(),
+        })
+    )]
+    #[case::cli_takes_precedence(
+        vec!["ricer", "pull", "--remote", "origin"],
+        CommandSettings::new("pull")
+            .set("remote", DefaultValue::Str("upstream".into()))
+            .set("branch", DefaultValue::Str("main".into()))
+            .set("jobs", DefaultValue::Int(8)),
+        Context::Pull(PullContext {
+            remote: Some("origin".into()),
+            branch: Some("main".into()),
+            fix: false,
+            jobs: Some(8),
+            repos: Vec::new(),
+            strict: false,
+            shared: SharedContext::default(),
+        })
+    )]
+    #[case::skip_wrong_type(
+        vec!["ricer", "rename", "foo", "bar"],
+        CommandSettings::new("rename").set("dry_run", DefaultValue::Str("yes".into())),
+        Context::Rename(RenameContext {
+            from: "foo".into(),
+            to: "bar".into(),
+            dry_run: false,
+            shared: SharedContext::default(),
+        })
+    )]
+    #[case::shared_flags(
+        vec!["ricer", "env"],
+        CommandSettings::new("env")
+            .set("no_pager", DefaultValue::Bool(true))
+            .set("timings", DefaultValue::Bool(true)),
+        Context::Env(EnvContext {
+            shared: SharedContext { no_pager: true, timings: true, ..Default::default() },
+        })
+    )]
+    #[case::fill_count(
+        vec!["ricer", "foreach", "true"],
+        CommandSettings::new("foreach").set("jobs", DefaultValue::Int(4)),
+        Context::Foreach(ForeachContext {
+            command: "true".into(),
+            repos: Vec::new(),
+            strict: false,
+            jobs: Some(4),
+            shared: SharedContext::default(),
+        })
+    )]
+    #[case::git_shortcut(
+        vec!["ricer", "foo", "status"],
+        CommandSettings::new("foo").set("terse", DefaultValue::Bool(true)),
+        Context::Git(GitContext { repo: "foo".into(), git_args: vec!["status".into()] })
+    )]
+    fn context_merge_defaults(
+        #[case] args: Vec<&str>,
+        #[case] defaults: CommandSettings,
+        #[case] expect: Context,
+    ) -> Result<()> {
+        let mut result = Context::from(Cli::parse_args(args)?);
+        result.merge_defaults(&defaults);
+        assert_eq!(result, expect);
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::single(vec!["ricer", "deploy", "vim"], vec!["vim"])]
+    #[case::rename(vec!["ricer", "rename", "vim", "nvim"], vec!["vim"])]
+    #[case::many(vec!["ricer", "sed", "a", "b", "-r", "dwm", "-r", "st"], vec!["dwm", "st"])]
+    #[case::optional(vec!["ricer", "clone", "url"], vec![])]
+    #[case::every_repo(vec!["ricer", "commit"], vec![])]
+    #[case::patterns(vec!["ricer", "pull", "dwm*", "st"], vec!["dwm*", "st"])]
+    fn context_target_repos(#[case] args: Vec<&str>, #[case] expect: Vec<&str>) -> Result<()> {
+        let ctx = Context::from(Cli::parse_args(args)?);
+        assert_eq!(ctx.target_repos(), expect);
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::always(HookAction::Always, HookAction::Always, HookAction::Always)]
+    #[case::prompt(HookAction::Always, HookAction::Prompt, HookAction::Prompt)]
+    #[case::never(HookAction::Never, HookAction::Always, HookAction::Never)]
+    fn hook_action_restrict_return_cautious(
+        #[case] action: HookAction,
+        #[case] other: HookAction,
+        #[case] expect: HookAction,
+    ) {
+        assert_eq!(action.restrict(other), expect);
+        assert_eq!(other.restrict(action), expect);
+    }
 }
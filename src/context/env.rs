@@ -0,0 +1,141 @@
+// SPDX-FileCopyrightText: 2024 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+use log::debug;
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::sync::OnceLock;
+use std::{env, fs};
+
+use crate::config::OsType;
+
+/// Snapshot of the environment Ricer runs in.
+///
+/// Bootstrap filters, path expansion of configuration files, hooks, and
+/// templates all ask the same snapshot about the user, host, operating
+/// system, and environment variables, instead of querying the process
+/// environment each time on their own. Thus, one run of Ricer sees one
+/// consistent environment, and tests can hand over an environment of their
+/// own through the builder methods.
+///
+/// # Invariants
+///
+/// 1. Once [`EnvSnapshot::install`] was called, [`EnvSnapshot::current`]
+///    always returns the installed snapshot. Without it, every call captures
+///    the process environment anew.
+/// 1. Variables that are not valid Unicode are left out.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnvSnapshot {
+    pub os: OsType,
+
+    /// Name of user account, if it could be determined.
+    pub user: Option<String>,
+
+    /// Name of host, if it could be determined.
+    pub host: Option<String>,
+
+    vars: BTreeMap<String, String>,
+}
+
+impl EnvSnapshot {
+    /// Construct empty snapshot of `os`, without user, host, or variables.
+    pub fn new(os: OsType) -> Self {
+        Self { os, user: None, host: None, vars: BTreeMap::new() }
+    }
+
+    pub fn user(mut self, user: impl Into<String>) -> Self {
+        self.user = Some(user.into());
+        self
+    }
+
+    pub fn host(mut self, host: impl Into<String>) -> Self {
+        self.host = Some(host.into());
+        self
+    }
+
+    /// Set variable `name` to `value`, replacing any earlier value of it.
+    pub fn set(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.vars.insert(name.into(), value.into());
+        self
+    }
+
+    /// Capture environment of current process.
+    ///
+    /// User is taken from `USER`, or `USERNAME` on Windows. Host is taken
+    /// from `HOSTNAME`, `/etc/hostname`, or `COMPUTERNAME` on Windows,
+    /// whichever is found first.
+    pub fn capture() -> Self {
+        let os = match env::consts::OS {
+            "macos" => OsType::MacOs,
+            "windows" => OsType::Windows,
+            _ => OsType::Unix,
+        };
+        let vars = env::vars_os()
+            .filter_map(|(key, value)| Some((key.into_string().ok()?, value.into_string().ok()?)))
+            .collect::<BTreeMap<_, _>>();
+        let var = |name| vars.get(name).cloned();
+        let user = var("USER").or_else(|| var("USERNAME"));
+        let host = var("HOSTNAME")
+            .or_else(|| fs::read_to_string("/etc/hostname").ok())
+            .or_else(|| var("COMPUTERNAME"));
+
+        let known = |name: String| Some(name.trim().to_string()).filter(|name| !name.is_empty());
+        Self { os, user: user.and_then(known), host: host.and_then(known), vars }
+    }
+
+    /// Use this snapshot for the rest of the run, see
+    /// [`EnvSnapshot::current`].
+    ///
+    /// Only the first call takes effect.
+    pub fn install(self) {
+        if installed().set(self).is_err() {
+            debug!("Environment snapshot was already installed");
+        }
+    }
+
+    /// Snapshot of the current run, or of the process environment right now
+    /// if none was installed.
+    pub fn current() -> Cow<'static, EnvSnapshot> {
+        match installed().get() {
+            Some(snapshot) => Cow::Borrowed(snapshot),
+            None => Cow::Owned(Self::capture()),
+        }
+    }
+
+    /// Value of variable `name`, if set.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.vars.get(name).map(String::as_str)
+    }
+
+    /// Every variable, ordered by name.
+    pub fn vars(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.vars.iter().map(|(key, value)| (key.as_str(), value.as_str()))
+    }
+}
+
+fn installed() -> &'static OnceLock<EnvSnapshot> {
+    static SNAPSHOT: OnceLock<EnvSnapshot> = OnceLock::new();
+    &SNAPSHOT
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    #[rstest]
+    fn env_snapshot_capture_process_env() {
+        let snapshot = EnvSnapshot::capture();
+        assert_eq!(snapshot.get("PATH"), env::var("PATH").ok().as_deref());
+        assert!(snapshot.host.as_deref().map_or(true, |host| host == host.trim()));
+    }
+
+    #[rstest]
+    fn env_snapshot_set_replace_value() {
+        let snapshot = EnvSnapshot::new(OsType::Unix).set("THEME", "nord").set("THEME", "gruvbox");
+        assert_eq!(snapshot.get("THEME"), Some("gruvbox"));
+        assert_eq!(snapshot.vars().collect::<Vec<_>>(), [("THEME", "gruvbox")]);
+    }
+}
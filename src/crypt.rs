@@ -0,0 +1,439 @@
+// SPDX-FileCopyrightText: 2024 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+//! Encryption at rest of configuration files.
+//!
+//! On shared machines, `ricer lock` encrypts every configuration file of
+//! Ricer's configuration directory through the user's [age][age] binary, and
+//! `ricer unlock` decrypts them again. Locked configuration files are
+//! decrypted whenever Ricer loads them, so their plaintext only ever lives in
+//! memory, and are encrypted again whenever Ricer saves them.
+//!
+//! Files are encrypted to the age identity file named by `RICER_AGE_IDENTITY`.
+//! Without it, age asks for a passphrase on the terminal instead, once for
+//! every file it encrypts, and once for every file it decrypts in a run.
+//! Plaintext of decrypted files is kept in memory for the rest of the run, so
+//! loading the same file again never asks twice. Hook scripts and templates
+//! are left alone, because they are executed or copied as they are.
+//!
+//! [age]: https://age-encryption.org
+
+use crate::{locate::Locator, relocate::LOCATION_FILE};
+
+use log::{debug, info};
+use std::{
+    collections::BTreeMap,
+    env, fs,
+    io::{Error as IoError, Write},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::{Mutex, MutexGuard},
+    thread,
+};
+use tempfile::NamedTempFile;
+
+/// Environment variable naming age identity file to encrypt to.
+pub const IDENTITY_ENV: &str = "RICER_AGE_IDENTITY";
+
+/// Magic bytes that start every age encrypted file, armored or not.
+const AGE_MAGIC: [&[u8]; 2] = [b"age-encryption.org/", b"-----BEGIN AGE ENCRYPTED FILE-----"];
+
+/// Error types for [`Cipher`], [`lock_config`], and [`unlock_config`].
+#[derive(Debug, thiserror::Error)]
+pub enum CryptError {
+    #[error("Failed to run 'age'")]
+    Syscall { source: IoError },
+
+    #[error("age failed: {msg}")]
+    Age { msg: String },
+
+    #[error("Failed to read '{path}'")]
+    Read { source: IoError, path: PathBuf },
+
+    #[error("Failed to write '{path}'")]
+    Write { source: IoError, path: PathBuf },
+}
+
+/// Encrypt and decrypt configuration file data.
+pub trait Cipher {
+    /// Encrypt `data`.
+    ///
+    /// # Errors
+    ///
+    /// Return [`CryptError`] if `data` cannot be encrypted.
+    fn encrypt(&self, data: &[u8]) -> Result<Vec<u8>, CryptError>;
+
+    /// Decrypt `data`.
+    ///
+    /// # Errors
+    ///
+    /// Return [`CryptError`] if `data` cannot be decrypted.
+    fn decrypt(&self, data: &[u8]) -> Result<Vec<u8>, CryptError>;
+}
+
+/// Cipher that calls the user's `age` binary.
+///
+/// Data is piped through age, so plaintext never touches the disk.
+#[derive(Debug, Default, Clone)]
+pub struct AgeCipher {
+    identity: Option<PathBuf>,
+}
+
+impl AgeCipher {
+    /// Encrypt to age `identity` file, or ask for a passphrase without one.
+    pub fn new(identity: Option<PathBuf>) -> Self {
+        Self { identity }
+    }
+
+    /// Encrypt to age identity file named by [`IDENTITY_ENV`], if any.
+    pub fn from_env() -> Self {
+        Self::new(env::var_os(IDENTITY_ENV).filter(|path| !path.is_empty()).map(PathBuf::from))
+    }
+
+    fn run(&self, mode: &str, data: &[u8]) -> Result<Vec<u8>, CryptError> {
+        let mut cmd = Command::new("age");
+        cmd.arg(mode);
+        match (&self.identity, mode) {
+            (Some(identity), _) => cmd.arg("--identity").arg(identity),
+            (None, "--encrypt") => cmd.arg("--passphrase"),
+            (None, _) => &mut cmd,
+        };
+        let mut child = cmd
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|err| CryptError::Syscall { source: err })?;
+
+        // INVARIANT: feed age from another thread, so neither side blocks on a full pipe.
+        let mut stdin = child.stdin.take().unwrap();
+        let output = thread::scope(|scope| {
+            // Write errors surface as a failing exit status of age.
+            scope.spawn(move || stdin.write_all(data));
+            child.wait_with_output()
+        })
+        .map_err(|err| CryptError::Syscall { source: err })?;
+
+        if !output.status.success() {
+            let msg = String::from_utf8_lossy(&output.stderr).trim().to_string();
+            return Err(CryptError::Age { msg });
+        }
+
+        Ok(output.stdout)
+    }
+}
+
+impl Cipher for AgeCipher {
+    fn encrypt(&self, data: &[u8]) -> Result<Vec<u8>, CryptError> {
+        self.run("--encrypt", data)
+    }
+
+    fn decrypt(&self, data: &[u8]) -> Result<Vec<u8>, CryptError> {
+        self.run("--decrypt", data)
+    }
+}
+
+/// Cipher that decrypts every payload at most once per run.
+///
+/// Plaintext is remembered by payload, both for payloads decrypted and
+/// encrypted through any `CachedCipher`, so files saved during a run are not
+/// decrypted again when loaded after.
+#[derive(Debug, Default, Clone)]
+pub struct CachedCipher<C> {
+    inner: C,
+}
+
+impl<C: Cipher> CachedCipher<C> {
+    pub fn new(inner: C) -> Self {
+        Self { inner }
+    }
+}
+
+impl<C: Cipher> Cipher for CachedCipher<C> {
+    fn encrypt(&self, data: &[u8]) -> Result<Vec<u8>, CryptError> {
+        let payload = self.inner.encrypt(data)?;
+        plaintexts().insert(payload.clone(), data.to_vec());
+        Ok(payload)
+    }
+
+    fn decrypt(&self, data: &[u8]) -> Result<Vec<u8>, CryptError> {
+        if let Some(plaintext) = plaintexts().get(data) {
+            debug!("Reuse plaintext decrypted earlier this run");
+            return Ok(plaintext.clone());
+        }
+        let plaintext = self.inner.decrypt(data)?;
+        plaintexts().insert(data.to_vec(), plaintext.clone());
+        Ok(plaintext)
+    }
+}
+
+fn plaintexts() -> MutexGuard<'static, BTreeMap<Vec<u8>, Vec<u8>>> {
+    static PLAINTEXTS: Mutex<BTreeMap<Vec<u8>, Vec<u8>>> = Mutex::new(BTreeMap::new());
+
+    // INVARIANT: a poisoned cache only ever holds complete entries, so keep using it.
+    PLAINTEXTS.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+/// Check if `data` is an age encrypted payload.
+pub fn is_encrypted(data: &[u8]) -> bool {
+    AGE_MAGIC.iter().any(|magic| data.starts_with(magic))
+}
+
+/// Encrypt every plaintext configuration file of configuration directory.
+///
+/// Returns paths of newly encrypted files, in sorted order.
+///
+/// # Invariants
+///
+/// 1. Already encrypted files are left untouched.
+/// 1. Location file of `move-config-dir` stays plaintext, because it is read
+///    before any other configuration file to find them, see [`Location`].
+/// 1. Nothing is written until every file was encrypted.
+///
+/// [`Location`]: crate::relocate::Location
+///
+/// # Errors
+///
+/// - Return [`CryptError::Read`] if a configuration file cannot be read.
+/// - Return [`CryptError::Write`] if a configuration file cannot be replaced.
+/// - Return [`CryptError::Syscall`] or [`CryptError::Age`] if a configuration
+///   file cannot be encrypted.
+pub fn lock_config(
+    locator: &impl Locator,
+    cipher: &impl Cipher,
+) -> Result<Vec<PathBuf>, CryptError> {
+    let location = locator.config_dir().join(LOCATION_FILE);
+    transform_config(locator, |path, data| match path == location || is_encrypted(data) {
+        true => Ok(None),
+        false => cipher.encrypt(data).map(Some),
+    })
+}
+
+/// Decrypt every encrypted configuration file of configuration directory.
+///
+/// Returns paths of newly decrypted files, in sorted order.
+///
+/// # Invariants
+///
+/// 1. Plaintext files are left untouched.
+/// 1. Nothing is written until every file was decrypted.
+///
+/// # Errors
+///
+/// - Return [`CryptError::Read`] if a configuration file cannot be read.
+/// - Return [`CryptError::Write`] if a configuration file cannot be replaced.
+/// - Return [`CryptError::Syscall`] or [`CryptError::Age`] if a configuration
+///   file cannot be decrypted.
+pub fn unlock_config(
+    locator: &impl Locator,
+    cipher: &impl Cipher,
+) -> Result<Vec<PathBuf>, CryptError> {
+    transform_config(locator, |_, data| match is_encrypted(data) {
+        true => cipher.decrypt(data).map(Some),
+        false => Ok(None),
+    })
+}
+
+fn transform_config(
+    locator: &impl Locator,
+    mut transform: impl FnMut(&Path, &[u8]) -> Result<Option<Vec<u8>>, CryptError>,
+) -> Result<Vec<PathBuf>, CryptError> {
+    let skip = [locator.hooks_dir(), locator.templates_dir()];
+    let mut paths = Vec::new();
+    config_files(locator.config_dir(), &skip, &mut paths)?;
+    paths.sort();
+
+    let mut changed = Vec::new();
+    for path in paths {
+        let data =
+            fs::read(&path).map_err(|err| CryptError::Read { source: err, path: path.clone() })?;
+        if let Some(data) = transform(&path, &data)? {
+            changed.push((path, data));
+        }
+    }
+
+    for (path, data) in &changed {
+        info!("Replace '{}'", path.display());
+        write_atomic(path, data)?;
+    }
+
+    Ok(changed.into_iter().map(|(path, _)| path).collect())
+}
+
+/// Collect TOML files of `dir` recursively, except those under `skip`.
+fn config_files(dir: &Path, skip: &[&Path], paths: &mut Vec<PathBuf>) -> Result<(), CryptError> {
+    let read_err = |err| CryptError::Read { source: err, path: dir.into() };
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(err) => return Err(read_err(err)),
+    };
+
+    for entry in entries {
+        let entry = entry.map_err(read_err)?;
+        let path = entry.path();
+        let kind = entry.file_type().map_err(read_err)?;
+        if kind.is_dir() && !skip.contains(&path.as_path()) {
+            config_files(&path, skip, paths)?;
+        } else if kind.is_file() && path.extension().is_some_and(|ext| ext == "toml") {
+            debug!("Found configuration file '{}'", path.display());
+            paths.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+/// Replace file at `path` with `data` all at once.
+pub(crate) fn write_atomic(path: &Path, data: &[u8]) -> Result<(), CryptError> {
+    let write_err = |err| CryptError::Write { source: err, path: path.into() };
+    // INVARIANT: parent exists, because the file is being replaced.
+    let parent = path.parent().unwrap_or(Path::new("."));
+    let mut file = NamedTempFile::new_in(parent).map_err(write_err)?;
+    file.write_all(data).map_err(write_err)?;
+    file.persist(path).map_err(|err| write_err(err.error))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        locate::MockLocator,
+        relocate::Location,
+        testenv::{FileKind, FixtureHarness},
+    };
+
+    use anyhow::Result;
+    use pretty_assertions::assert_eq;
+    use rstest::{fixture, rstest};
+
+    /// Reverses data behind age magic, so tests never need the age binary.
+    struct FakeCipher;
+
+    impl Cipher for FakeCipher {
+        fn encrypt(&self, data: &[u8]) -> Result<Vec<u8>, CryptError> {
+            Ok(AGE_MAGIC[0].iter().chain(data.iter().rev()).copied().collect())
+        }
+
+        fn decrypt(&self, data: &[u8]) -> Result<Vec<u8>, CryptError> {
+            Ok(data[AGE_MAGIC[0].len()..].iter().rev().copied().collect())
+        }
+    }
+
+    /// Counts decryptions of [`FakeCipher`].
+    #[derive(Default)]
+    struct CountingCipher {
+        decrypted: std::cell::Cell<usize>,
+    }
+
+    impl Cipher for &CountingCipher {
+        fn encrypt(&self, data: &[u8]) -> Result<Vec<u8>, CryptError> {
+            FakeCipher.encrypt(data)
+        }
+
+        fn decrypt(&self, data: &[u8]) -> Result<Vec<u8>, CryptError> {
+            self.decrypted.set(self.decrypted.get() + 1);
+            FakeCipher.decrypt(data)
+        }
+    }
+
+    #[rstest]
+    fn cached_cipher_decrypt_once_per_payload() -> Result<()> {
+        let counter = CountingCipher::default();
+        let cipher = CachedCipher::new(&counter);
+        let loaded = FakeCipher.encrypt(b"[repos.cached_cipher_decrypt_once]\n")?;
+        assert_eq!(cipher.decrypt(&loaded)?, b"[repos.cached_cipher_decrypt_once]\n");
+        assert_eq!(cipher.decrypt(&loaded)?, b"[repos.cached_cipher_decrypt_once]\n");
+        assert_eq!(counter.decrypted.get(), 1);
+
+        let saved = cipher.encrypt(b"[repos.cached_cipher_encrypt_once]\n")?;
+        assert_eq!(cipher.decrypt(&saved)?, b"[repos.cached_cipher_encrypt_once]\n");
+        assert_eq!(counter.decrypted.get(), 1);
+        Ok(())
+    }
+
+    #[fixture]
+    fn config_dir() -> Result<FixtureHarness> {
+        let harness = FixtureHarness::open()?
+            .with_file("repos.toml", |fixture| {
+                fixture.with_data("[repos.vim]\n").with_kind(FileKind::Normal)
+            })
+            .with_file("nested/config.toml", |fixture| {
+                fixture.with_data("[ui]\n").with_kind(FileKind::Normal)
+            })
+            .with_file("hooks/hooks.toml", |fixture| {
+                fixture.with_data("# not a config\n").with_kind(FileKind::Normal)
+            })
+            .with_file("templates/dots/Cargo.toml", |fixture| {
+                fixture.with_data("[package]\n").with_kind(FileKind::Normal)
+            })
+            .with_file("setup.sh", |fixture| {
+                fixture.with_data("#!/bin/sh\n").with_kind(FileKind::Script)
+            })
+            .setup()?;
+        Ok(harness)
+    }
+
+    fn locator(config_dir: &FixtureHarness) -> MockLocator {
+        let root = config_dir.as_path();
+        let mut locator = MockLocator::new();
+        locator.expect_config_dir().return_const(root.into());
+        locator.expect_hooks_dir().return_const(root.join("hooks"));
+        locator.expect_templates_dir().return_const(root.join("templates"));
+        locator
+    }
+
+    #[rstest]
+    fn lock_config_encrypt_config_files_only(config_dir: Result<FixtureHarness>) -> Result<()> {
+        let config_dir = config_dir?;
+        let root = config_dir.as_path();
+        let locator = locator(&config_dir);
+
+        let locked = lock_config(&locator, &FakeCipher)?;
+        assert_eq!(locked, vec![root.join("nested/config.toml"), root.join("repos.toml")]);
+        assert!(is_encrypted(&fs::read(root.join("repos.toml"))?));
+        assert_eq!(fs::read_to_string(root.join("hooks/hooks.toml"))?, "# not a config\n");
+        assert_eq!(fs::read_to_string(root.join("templates/dots/Cargo.toml"))?, "[package]\n");
+        assert_eq!(fs::read_to_string(root.join("setup.sh"))?, "#!/bin/sh\n");
+
+        assert!(lock_config(&locator, &FakeCipher)?.is_empty());
+        Ok(())
+    }
+
+    #[rstest]
+    fn lock_config_keep_location_file_loadable(config_dir: Result<FixtureHarness>) -> Result<()> {
+        let config_dir = config_dir?;
+        let root = config_dir.as_path();
+        fs::write(root.join(LOCATION_FILE), "config_dir = \"/elsewhere\"\n")?;
+
+        let locked = lock_config(&locator(&config_dir), &FakeCipher)?;
+        assert!(!locked.contains(&root.join(LOCATION_FILE)));
+        let location = Location::load(root)?;
+        assert_eq!(location.config_dir, Some(PathBuf::from("/elsewhere")));
+        Ok(())
+    }
+
+    #[rstest]
+    fn unlock_config_decrypt_locked_files(config_dir: Result<FixtureHarness>) -> Result<()> {
+        let config_dir = config_dir?;
+        let root = config_dir.as_path();
+        let locator = locator(&config_dir);
+        write_atomic(&root.join("repos.toml"), &FakeCipher.encrypt(b"[repos.vim]\n")?)?;
+
+        let unlocked = unlock_config(&locator, &FakeCipher)?;
+        assert_eq!(unlocked, vec![root.join("repos.toml")]);
+        assert_eq!(fs::read_to_string(root.join("repos.toml"))?, "[repos.vim]\n");
+        assert!(unlock_config(&locator, &FakeCipher)?.is_empty());
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::binary(b"age-encryption.org/v1\n-> X25519 abc\n", true)]
+    #[case::armored(b"-----BEGIN AGE ENCRYPTED FILE-----\nYWdl\n", true)]
+    #[case::plaintext(b"[repos.vim]\n", false)]
+    #[case::empty(b"", false)]
+    fn is_encrypted_detect_age_payloads(#[case] data: &[u8], #[case] expect: bool) {
+        assert_eq!(is_encrypted(data), expect);
+    }
+}
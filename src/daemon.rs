@@ -0,0 +1,458 @@
+// SPDX-FileCopyrightText: 2024 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+//! Background fetch daemon.
+//!
+//! Through `ricer daemon`, Ricer stays in the background and periodically
+//! fetches every repository from its remote, without ever merging anything.
+//! After each round of fetches, the divergence of every repository is written
+//! into the state file of the state directory, like [`StateSubscriber`] does
+//! after each command.
+//!
+//! Shell prompts and status bars can query the daemon through a Unix socket at
+//! `daemon.sock` of the state directory. Each connection sends one query line,
+//! and receives one answer line:
+//!
+//! - `ping` answers `pong`.
+//! - `state` answers the state snapshot as JSON.
+//! - `repo <NAME>` answers the state of one repository as JSON.
+//!
+//! The fetch interval is configured in Ricer's general configuration file:
+//!
+//! ```toml
+//! [daemon]
+//! interval = 900
+//! ```
+//!
+//! [`StateSubscriber`]: crate::state::StateSubscriber
+
+use crate::{
+    cancel, clock,
+    config::{ConfigFile, ConfigFileError, DaemonSettings, RepoConfig},
+    json::Json,
+    locate::Locator,
+    state::{StateError, StateSnapshot},
+    vcs::{GitRepo, GitRepoError},
+};
+
+use log::{debug, info, warn};
+use mkdirp::mkdirp;
+use std::{
+    fmt, fs,
+    io::{BufRead, BufReader, Error as IoError, Write},
+    os::unix::net::{UnixListener, UnixStream},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, SystemTime},
+};
+
+/// Error types for [`run_daemon`].
+#[derive(Debug, thiserror::Error)]
+pub enum DaemonError {
+    #[error("Daemon is already running on socket '{path}'")]
+    AlreadyRunning { path: PathBuf },
+
+    #[error("Failed to listen on socket '{path}'")]
+    Socket { source: IoError, path: PathBuf },
+
+    #[error("Failed to read repository configuration")]
+    Config { source: ConfigFileError },
+
+    #[error("Failed to publish repository state")]
+    State { source: StateError },
+}
+
+impl From<ConfigFileError> for DaemonError {
+    fn from(err: ConfigFileError) -> Self {
+        DaemonError::Config { source: err }
+    }
+}
+
+impl From<StateError> for DaemonError {
+    fn from(err: StateError) -> Self {
+        DaemonError::State { source: err }
+    }
+}
+
+/// Outcome of fetching one repository.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FetchOutcome {
+    /// Repository was fetched.
+    Fetched,
+
+    /// Repository was synced within the fetch interval, so it was skipped.
+    Throttled,
+
+    /// Repository does not exist in repository directory.
+    Missing,
+
+    /// Fetch failed with the given reason.
+    Failed(String),
+}
+
+impl fmt::Display for FetchOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FetchOutcome::Fetched => write!(f, "fetched"),
+            FetchOutcome::Throttled => write!(f, "throttled"),
+            FetchOutcome::Missing => write!(f, "missing"),
+            FetchOutcome::Failed(reason) => write!(f, "failed: {reason}"),
+        }
+    }
+}
+
+/// Fetch every registered repository whose last sync is older than interval.
+///
+/// Returns outcome of each repository in configuration order.
+///
+/// # Invariants
+///
+/// 1. Repositories are only fetched, never merged, so the daemon cannot
+///    change any working directory behind the user's back.
+/// 1. A failed fetch does not stop the other repositories from being fetched.
+/// 1. Remaining repositories are skipped once cancelled.
+///
+/// # Errors
+///
+/// - Return [`DaemonError::Config`] if repository configuration cannot be
+///   read.
+pub fn fetch_all(
+    locator: &impl Locator,
+    settings: &DaemonSettings,
+    now: SystemTime,
+) -> Result<Vec<(String, FetchOutcome)>, DaemonError> {
+    let mut outcomes = Vec::new();
+    for repo in ConfigFile::load(RepoConfig, locator)?.entries()? {
+        if cancel::is_cancelled() {
+            break;
+        }
+
+        let gitdir = repo.gitdir(locator.repos_dir());
+        let outcome = match gitdir.exists() {
+            false => FetchOutcome::Missing,
+            true => match fetch_repo(&gitdir, &repo.remote, &repo.branch, settings, now) {
+                Ok(true) => FetchOutcome::Fetched,
+                Ok(false) => FetchOutcome::Throttled,
+                Err(err) => FetchOutcome::Failed(err.to_string()),
+            },
+        };
+        debug!("Fetch of '{}' {outcome}", repo.name);
+        outcomes.push((repo.name, outcome));
+    }
+
+    Ok(outcomes)
+}
+
+fn fetch_repo(
+    gitdir: &Path,
+    remote: &str,
+    branch: &str,
+    settings: &DaemonSettings,
+    now: SystemTime,
+) -> Result<bool, GitRepoError> {
+    let repo = GitRepo::open(gitdir)?;
+    if !fetch_due(repo.status()?.last_sync, now, settings.interval) {
+        return Ok(false);
+    }
+
+    repo.fetch_remote(remote, branch)?;
+    Ok(true)
+}
+
+/// Determine if a repository last synced at `last_sync` should be fetched.
+///
+/// Syncs from the future, e.g., after the clock was turned back, count as
+/// due, so a wrong clock cannot stop fetches for good.
+pub fn fetch_due(last_sync: Option<SystemTime>, now: SystemTime, interval: Duration) -> bool {
+    match last_sync {
+        Some(last_sync) => now.duration_since(last_sync).map_or(true, |age| age >= interval),
+        None => true,
+    }
+}
+
+/// Run one round of fetches, and publish resulting state snapshot.
+///
+/// # Errors
+///
+/// - Return [`DaemonError::Config`] if repository configuration cannot be
+///   read.
+/// - Return [`DaemonError::State`] if state snapshot cannot be written.
+pub fn run_once(
+    locator: &impl Locator,
+    settings: &DaemonSettings,
+) -> Result<StateSnapshot, DaemonError> {
+    for (name, outcome) in fetch_all(locator, settings, clock::now())? {
+        if let FetchOutcome::Failed(reason) = &outcome {
+            warn!("Failed to fetch '{name}': {reason}");
+        }
+    }
+
+    let snapshot = StateSnapshot::capture(locator)?;
+    snapshot.write(locator)?;
+    Ok(snapshot)
+}
+
+/// Fetch repositories until cancelled, answering queries on the daemon
+/// socket.
+///
+/// Daemon socket is removed once cancelled.
+///
+/// # Errors
+///
+/// - Return [`DaemonError::AlreadyRunning`] if another daemon listens on the
+///   daemon socket.
+/// - Return [`DaemonError::Socket`] if daemon socket cannot be made.
+/// - Return [`DaemonError::Config`] if repository configuration cannot be
+///   read.
+/// - Return [`DaemonError::State`] if state snapshot cannot be written.
+pub fn run_daemon(locator: &impl Locator, settings: &DaemonSettings) -> Result<(), DaemonError> {
+    let path = socket_path(locator);
+    let listener = bind_socket(&path)?;
+    let state = Arc::new(Mutex::new(StateSnapshot::capture(locator)?));
+    let served = Arc::clone(&state);
+    thread::spawn(move || serve(listener, &served));
+
+    info!(
+        "Fetch repositories every {}s, answer queries on '{}'",
+        settings.interval.as_secs(),
+        path.display()
+    );
+    loop {
+        let snapshot = run_once(locator, settings)?;
+        *state.lock().unwrap() = snapshot;
+        if !cancel::sleep(settings.interval) {
+            break;
+        }
+    }
+
+    debug!("Remove daemon socket '{}'", path.display());
+    fs::remove_file(&path).map_err(|err| DaemonError::Socket { source: err, path })
+}
+
+/// Expected path to daemon socket.
+pub fn socket_path(locator: &impl Locator) -> PathBuf {
+    locator.state_dir().join("daemon.sock")
+}
+
+/// Bind daemon socket at `path`.
+///
+/// # Invariants
+///
+/// Sockets left behind by a daemon that did not shut down cleanly are
+/// replaced, but sockets of a running daemon never are.
+fn bind_socket(path: &Path) -> Result<UnixListener, DaemonError> {
+    let socket_err = |err| DaemonError::Socket { source: err, path: path.into() };
+    if path.exists() {
+        if UnixStream::connect(path).is_ok() {
+            return Err(DaemonError::AlreadyRunning { path: path.into() });
+        }
+        debug!("Remove stale socket '{}'", path.display());
+        fs::remove_file(path).map_err(socket_err)?;
+    }
+
+    mkdirp(path.parent().unwrap()).map_err(socket_err)?;
+    UnixListener::bind(path).map_err(socket_err)
+}
+
+fn serve(listener: UnixListener, state: &Mutex<StateSnapshot>) {
+    for stream in listener.incoming() {
+        let result = stream.and_then(|stream| reply(stream, state));
+        if let Err(err) = result {
+            warn!("Failed to answer query: {err}");
+        }
+    }
+}
+
+fn reply(stream: UnixStream, state: &Mutex<StateSnapshot>) -> Result<(), IoError> {
+    stream.set_read_timeout(Some(Duration::from_secs(1)))?;
+    let mut query = String::new();
+    BufReader::new(&stream).read_line(&mut query)?;
+    let answer = answer(&query, &state.lock().unwrap());
+    writeln!(&stream, "{answer}")
+}
+
+/// Answer one query line of the daemon socket from state `snapshot`.
+///
+/// Unknown queries are answered with a JSON object carrying an `error`
+/// member, so clients always receive something they can parse.
+pub fn answer(query: &str, snapshot: &StateSnapshot) -> String {
+    let error = |msg: String| Json::object().with("error", msg).to_string();
+    let query = query.trim();
+    match query.split_once(' ').unwrap_or((query, "")) {
+        ("ping", "") => "pong".into(),
+        ("state", "") => snapshot.to_json().to_string(),
+        ("repo", name) => match snapshot.repos.iter().find(|repo| repo.name == name.trim()) {
+            Some(repo) => repo.to_json().to_string(),
+            None => error(format!("unknown repository '{}'", name.trim())),
+        },
+        _ => error(format!("unknown query '{query}'")),
+    }
+}
+
+/// Systemd user unit that runs the daemon through Ricer binary `exe`.
+///
+/// Meant to be written into `~/.config/systemd/user/ricer.service`.
+pub fn systemd_unit(exe: &Path) -> String {
+    let exe = exe.display().to_string();
+    let exe = match exe.contains(char::is_whitespace) {
+        true => format!("\"{exe}\""),
+        false => exe,
+    };
+
+    format!(
+        "[Unit]\n\
+         Description=Ricer background fetch daemon\n\
+         After=network-online.target\n\
+         Wants=network-online.target\n\
+         \n\
+         [Service]\n\
+         Type=simple\n\
+         ExecStart={exe} daemon\n\
+         Restart=on-failure\n\
+         \n\
+         [Install]\n\
+         WantedBy=default.target\n"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        state::RepoState,
+        testenv::{mock_locator, FileKind, FixtureHarness},
+        vcs::RepoStatus,
+    };
+
+    use anyhow::Result;
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+    use rstest::{fixture, rstest};
+    use std::time::UNIX_EPOCH;
+
+    #[fixture]
+    fn rice_dir() -> Result<FixtureHarness> {
+        let harness = FixtureHarness::open()?
+            .with_file("config/repos.toml", |fixture| {
+                fixture
+                    .with_data(indoc! {r#"
+                        [repos.vim]
+                        branch = "main"
+                        remote = "origin"
+
+                        [repos.gone]
+                        branch = "main"
+                        remote = "origin"
+                    "#})
+                    .with_kind(FileKind::Normal)
+            })
+            .with_repo("upstream/vim", |repo| {
+                let repo = repo.stage(".vimrc", "set number")?;
+                repo.commit("initial commit")?;
+                Ok(repo)
+            })?
+            .with_repo("repos/vim", Ok)?
+            .setup()?;
+        let remote = harness.as_path().join("upstream/vim.git");
+        git2::Repository::open(harness.as_path().join("repos/vim.git"))?
+            .remote("origin", &remote.to_string_lossy())?;
+        Ok(harness)
+    }
+
+    #[rstest]
+    fn fetch_all_throttle_recently_synced_repos(rice_dir: Result<FixtureHarness>) -> Result<()> {
+        let rice_dir = rice_dir?;
+        let locator = mock_locator(&rice_dir);
+        let settings = DaemonSettings::new();
+
+        let outcomes = fetch_all(&locator, &settings, SystemTime::now())?;
+        assert_eq!(
+            outcomes,
+            vec![("vim".into(), FetchOutcome::Fetched), ("gone".into(), FetchOutcome::Missing)]
+        );
+        let repo = git2::Repository::open(rice_dir.as_path().join("repos/vim.git"))?;
+        assert!(repo.find_reference("refs/remotes/origin/main").is_ok());
+
+        let outcomes = fetch_all(&locator, &settings, SystemTime::now())?;
+        assert_eq!(outcomes[0], ("vim".into(), FetchOutcome::Throttled));
+        Ok(())
+    }
+
+    #[rstest]
+    fn run_once_publish_state(rice_dir: Result<FixtureHarness>) -> Result<()> {
+        let rice_dir = rice_dir?;
+        let locator = mock_locator(&rice_dir);
+        let snapshot = run_once(&locator, &DaemonSettings::new())?;
+        assert_eq!(snapshot.repos[0].name, "vim");
+        assert!(snapshot.repos[0].status.last_sync.is_some());
+        assert!(rice_dir.as_path().join("state/state.json").exists());
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::never_synced(None, 0, true)]
+    #[case::recently_synced(Some(100), 150, false)]
+    #[case::interval_passed(Some(100), 160, true)]
+    #[case::synced_in_future(Some(200), 100, true)]
+    fn fetch_due_after_interval(
+        #[case] last_sync: Option<u64>,
+        #[case] now: u64,
+        #[case] expect: bool,
+    ) {
+        let at = |secs| UNIX_EPOCH + Duration::from_secs(secs);
+        assert_eq!(fetch_due(last_sync.map(at), at(now), Duration::from_secs(60)), expect);
+    }
+
+    fn snapshot() -> StateSnapshot {
+        let status = RepoStatus {
+            branch: Some("main".into()),
+            dirty: true,
+            ahead: 1,
+            behind: 0,
+            last_sync: None,
+        };
+        StateSnapshot {
+            generated: UNIX_EPOCH,
+            repos: vec![RepoState { name: "vim".into(), status }],
+        }
+    }
+
+    #[rstest]
+    #[case::ping("ping\n", "pong")]
+    #[case::repo(
+        "repo vim\n",
+        r#"{"name":"vim","branch":"main","dirty":true,"ahead":1,"behind":0,"last_sync":null}"#
+    )]
+    #[case::unknown_repo("repo dwm\n", r#"{"error":"unknown repository 'dwm'"}"#)]
+    #[case::unknown_query("pong\n", r#"{"error":"unknown query 'pong'"}"#)]
+    fn answer_query(#[case] query: &str, #[case] expect: &str) {
+        assert_eq!(answer(query, &snapshot()), expect);
+    }
+
+    #[rstest]
+    fn serve_answer_over_socket() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("daemon.sock");
+        let listener = bind_socket(&path)?;
+        thread::spawn(move || serve(listener, &Mutex::new(snapshot())));
+        assert!(matches!(bind_socket(&path).unwrap_err(), DaemonError::AlreadyRunning { .. }));
+
+        let mut stream = UnixStream::connect(&path)?;
+        writeln!(stream, "ping")?;
+        let mut answer = String::new();
+        BufReader::new(&stream).read_line(&mut answer)?;
+        assert_eq!(answer, "pong\n");
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::plain("/usr/bin/ricer", "ExecStart=/usr/bin/ricer daemon\n")]
+    #[case::quote_spaces("/opt/my apps/ricer", "ExecStart=\"/opt/my apps/ricer\" daemon\n")]
+    fn systemd_unit_run_daemon(#[case] exe: &str, #[case] exec_start: &str) {
+        let unit = systemd_unit(Path::new(exe));
+        assert!(unit.starts_with("[Unit]\n"));
+        assert!(unit.contains(exec_start));
+        assert!(unit.ends_with("[Install]\nWantedBy=default.target\n"));
+    }
+}
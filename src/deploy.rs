@@ -0,0 +1,432 @@
+// SPDX-FileCopyrightText: 2024 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+//! Symlink deployment of repositories.
+//!
+//! Repositories normally deploy their files by using the home directory as
+//! their working directory through the fake bare technique. Repositories can
+//! instead opt into symlink deployment, where files are checked out into the
+//! repository directory, and linked into the home directory like GNU Stow
+//! does:
+//!
+//! ```toml
+//! [repos.vim]
+//! branch = "main"
+//! remote = "origin"
+//! deploy_mode = "symlink"
+//! ```
+//!
+//! Every link made by `ricer deploy` is recorded in a link manifest of the
+//! state directory, e.g., `deploy/vim.toml`. Thus, `ricer undeploy` only ever
+//! removes links that Ricer made itself.
+
+use crate::{
+    config::{ConfigFile, ConfigFileError, DeployMode, RepoConfig},
+    locate::Locator,
+    vcs::{GitRepo, GitRepoError},
+};
+
+use log::{debug, info, warn};
+use mkdirp::mkdirp;
+use std::{
+    fs,
+    io::{Error as IoError, ErrorKind},
+    os::unix::fs::symlink,
+    path::{Path, PathBuf},
+};
+use toml_edit::{DocumentMut, Item, Table, Value};
+
+/// Error types for [`deploy_repo`] and [`undeploy_repo`].
+#[derive(Debug, thiserror::Error)]
+pub enum DeployError {
+    #[error("Repository '{name}' does not use symlink deployment")]
+    NotSymlinkMode { name: String },
+
+    #[error("Repository '{name}' does not exist at '{path}'")]
+    MissingRepo { name: String, path: PathBuf },
+
+    #[error("Repository '{name}' has no working directory of its own to link from")]
+    NoWorkdir { name: String },
+
+    #[error("Failed to inspect repository '{name}'")]
+    Repo { source: GitRepoError, name: String },
+
+    #[error("Deployment would overwrite existing file '{path}'")]
+    Conflict { path: PathBuf },
+
+    #[error("Failed to read '{path}'")]
+    FileRead { source: IoError, path: PathBuf },
+
+    #[error("Failed to write '{path}'")]
+    FileWrite { source: IoError, path: PathBuf },
+
+    #[error("Link manifest '{path}' is malformed")]
+    BadManifest { path: PathBuf },
+
+    #[error("Failed to read repository configuration")]
+    Config { source: ConfigFileError },
+}
+
+impl From<ConfigFileError> for DeployError {
+    fn from(err: ConfigFileError) -> Self {
+        DeployError::Config { source: err }
+    }
+}
+
+/// Outcome of [`deploy_repo`].
+///
+/// All paths are relative to the home directory.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Deployment {
+    /// Links that were made or updated.
+    pub linked: Vec<PathBuf>,
+
+    /// Links that already pointed at the right file.
+    pub unchanged: Vec<PathBuf>,
+
+    /// Links of files that are no longer tracked, which were removed.
+    pub pruned: Vec<PathBuf>,
+}
+
+/// Link every tracked file of repository `name` into `home`.
+///
+/// Deploying again updates the existing deployment, i.e., links of newly
+/// tracked files are made, and links of untracked files are pruned.
+///
+/// # Invariants
+///
+/// 1. Every link is checked before anything is written, so deployment never
+///    replaces files or links that Ricer did not make.
+/// 1. Links that were made before a failure are still recorded, so they can
+///    be undeployed.
+///
+/// # Errors
+///
+/// - Return [`DeployError::NotSymlinkMode`] if repository does not use
+///   symlink deployment.
+/// - Return [`DeployError::MissingRepo`] if repository was never cloned or
+///   initialized.
+/// - Return [`DeployError::NoWorkdir`] if repository is bare or fake bare.
+/// - Return [`DeployError::Conflict`] if a file is in the way of a link.
+/// - Return [`DeployError::FileWrite`] if a link cannot be made.
+pub fn deploy_repo(
+    name: &str,
+    locator: &impl Locator,
+    home: &Path,
+) -> Result<Deployment, DeployError> {
+    let workdir = symlink_workdir(name, locator)?;
+    let repo_err = |err| DeployError::Repo { source: err, name: name.into() };
+    let files = GitRepo::open(&workdir).map_err(repo_err)?.tracked_files().map_err(repo_err)?;
+    let manifest_path = manifest_path(name, locator);
+    let mut manifest = LinkManifest::load(&manifest_path)?;
+
+    let mut deployment = Deployment::default();
+    let mut pending = Vec::new();
+    for file in &files {
+        let link = home.join(file);
+        let target = workdir.join(file);
+        match link_target(&link)? {
+            None => pending.push(file),
+            Some(current) if current == target => deployment.unchanged.push(file.clone()),
+            Some(current) if manifest.owns(file, &current) => pending.push(file),
+            Some(_) => return Err(DeployError::Conflict { path: link }),
+        }
+    }
+
+    for (file, target) in manifest.links.clone() {
+        if files.contains(&file) {
+            continue;
+        }
+        let link = home.join(&file);
+        if link_target(&link)? == Some(target) {
+            debug!("Prune link '{}'", link.display());
+            fs::remove_file(&link)
+                .map_err(|err| DeployError::FileWrite { source: err, path: link.clone() })?;
+            deployment.pruned.push(file.clone());
+        }
+        manifest.links.retain(|(owned, _)| owned != &file);
+    }
+
+    let mut result = Ok(());
+    for file in pending {
+        let link = home.join(file);
+        let target = workdir.join(file);
+        if let Err(err) = make_link(&link, &target) {
+            result = Err(err);
+            break;
+        }
+        manifest.links.retain(|(owned, _)| owned != file);
+        manifest.links.push((file.clone(), target));
+        deployment.linked.push(file.clone());
+    }
+    for file in &deployment.unchanged {
+        if !manifest.links.iter().any(|(owned, _)| owned == file) {
+            manifest.links.push((file.clone(), workdir.join(file)));
+        }
+    }
+
+    // INVARIANT: record links even on failure, so undeploy can clean them up.
+    manifest.save(&manifest_path)?;
+    result?;
+
+    info!("Deployed {} link(s) of repository '{name}'", files.len());
+    Ok(deployment)
+}
+
+/// Remove every link made by [`deploy_repo`] for repository `name`.
+///
+/// Returns links that were removed, relative to `home`.
+///
+/// # Invariants
+///
+/// Only links recorded in the link manifest that still point at the file
+/// they were made for are removed. Links or files that replaced them since
+/// are left alone.
+///
+/// # Errors
+///
+/// - Return [`DeployError::BadManifest`] if link manifest cannot be parsed.
+/// - Return [`DeployError::FileWrite`] if a link cannot be removed.
+pub fn undeploy_repo(
+    name: &str,
+    locator: &impl Locator,
+    home: &Path,
+) -> Result<Vec<PathBuf>, DeployError> {
+    let manifest_path = manifest_path(name, locator);
+    let manifest = LinkManifest::load(&manifest_path)?;
+
+    let mut removed = Vec::new();
+    for (file, target) in &manifest.links {
+        let link = home.join(file);
+        if link_target(&link)?.as_ref() != Some(target) {
+            warn!("Skip '{}', it no longer links to '{}'", link.display(), target.display());
+            continue;
+        }
+        debug!("Remove link '{}'", link.display());
+        fs::remove_file(&link)
+            .map_err(|err| DeployError::FileWrite { source: err, path: link.clone() })?;
+        removed.push(file.clone());
+    }
+
+    match fs::remove_file(&manifest_path) {
+        Err(err) if err.kind() != ErrorKind::NotFound => {
+            return Err(DeployError::FileWrite { source: err, path: manifest_path });
+        }
+        _ => (),
+    }
+
+    Ok(removed)
+}
+
+/// Links made by [`deploy_repo`], as pairs of link path relative to the home
+/// directory and absolute link target.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+struct LinkManifest {
+    links: Vec<(PathBuf, PathBuf)>,
+}
+
+impl LinkManifest {
+    fn load(path: &Path) -> Result<Self, DeployError> {
+        let data = match fs::read_to_string(path) {
+            Ok(data) => data,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(Self::default()),
+            Err(err) => return Err(DeployError::FileRead { source: err, path: path.into() }),
+        };
+        let bad_manifest = || DeployError::BadManifest { path: path.into() };
+        let doc: DocumentMut = data.parse().map_err(|_| bad_manifest())?;
+        let table = match doc.get("links") {
+            Some(links) => links.as_table().ok_or_else(bad_manifest)?,
+            None => return Ok(Self::default()),
+        };
+
+        let mut links = Vec::new();
+        for (file, target) in table.iter() {
+            let target = target.as_str().ok_or_else(bad_manifest)?;
+            links.push((PathBuf::from(file), PathBuf::from(target)));
+        }
+
+        Ok(Self { links })
+    }
+
+    fn save(&self, path: &Path) -> Result<(), DeployError> {
+        let write_err = |err| DeployError::FileWrite { source: err, path: path.into() };
+        let mut links = Table::new();
+        for (file, target) in &self.links {
+            let target = String::from(target.to_string_lossy());
+            links.insert(&file.to_string_lossy(), Item::Value(Value::from(target)));
+        }
+        let mut doc = DocumentMut::new();
+        doc.insert("links", Item::Table(links));
+
+        mkdirp(path.parent().unwrap()).map_err(write_err)?;
+        fs::write(path, doc.to_string()).map_err(write_err)?;
+        Ok(())
+    }
+
+    fn owns(&self, file: &Path, target: &Path) -> bool {
+        self.links.iter().any(|(owned, owned_target)| owned == file && owned_target == target)
+    }
+}
+
+fn manifest_path(name: &str, locator: &impl Locator) -> PathBuf {
+    locator.state_dir().join("deploy").join(format!("{name}.toml"))
+}
+
+fn symlink_workdir(name: &str, locator: &impl Locator) -> Result<PathBuf, DeployError> {
+    let settings = ConfigFile::load(RepoConfig, locator)?.get(name)?;
+    if settings.deploy_mode != DeployMode::Symlink {
+        return Err(DeployError::NotSymlinkMode { name: name.into() });
+    }
+
+    let gitdir = settings.gitdir(locator.repos_dir());
+    if !gitdir.exists() {
+        return Err(DeployError::MissingRepo { name: name.into(), path: gitdir });
+    }
+    let repo = GitRepo::open(&gitdir)
+        .map_err(|err| DeployError::Repo { source: err, name: name.into() })?;
+    match repo.workdir() {
+        Some(workdir) if !repo.is_fake_bare() => Ok(workdir.to_path_buf()),
+        _ => Err(DeployError::NoWorkdir { name: name.into() }),
+    }
+}
+
+/// Target of symbolic link at `link`, or `None` if nothing is at `link`.
+///
+/// Regular files and directories are reported with their own path as target,
+/// so they never compare equal to a link target in the repository.
+fn link_target(link: &Path) -> Result<Option<PathBuf>, DeployError> {
+    let read_err = |err| DeployError::FileRead { source: err, path: link.into() };
+    match link.symlink_metadata() {
+        Ok(meta) if meta.is_symlink() => Ok(Some(fs::read_link(link).map_err(read_err)?)),
+        Ok(_) => Ok(Some(link.into())),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(read_err(err)),
+    }
+}
+
+fn make_link(link: &Path, target: &Path) -> Result<(), DeployError> {
+    let write_err = |err| DeployError::FileWrite { source: err, path: link.into() };
+    debug!("Link '{}' to '{}'", link.display(), target.display());
+    mkdirp(link.parent().unwrap()).map_err(write_err)?;
+    if link.symlink_metadata().is_ok() {
+        fs::remove_file(link).map_err(write_err)?;
+    }
+    symlink(target, link).map_err(write_err)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testenv::{mock_locator, FileKind, FixtureHarness};
+
+    use anyhow::Result;
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+    use rstest::{fixture, rstest};
+
+    #[fixture]
+    fn rice_dir() -> Result<FixtureHarness> {
+        let harness = FixtureHarness::open()?
+            .with_file("config/repos.toml", |fixture| {
+                fixture
+                    .with_data(indoc! {r#"
+                        [repos.vim]
+                        branch = "main"
+                        remote = "origin"
+                        deploy_mode = "symlink"
+
+                        [repos.sh]
+                        branch = "main"
+                        remote = "origin"
+                    "#})
+                    .with_kind(FileKind::Normal)
+            })
+            .with_repo("repos/vim", |repo| {
+                let repo = repo.stage(".vimrc", "set number")?.stage(".vim/colors.vim", "")?;
+                repo.commit("initial commit")?;
+                Ok(repo)
+            })?
+            .with_file("home/.keep", |fixture| fixture)
+            .setup()?;
+        Ok(harness)
+    }
+
+    #[rstest]
+    fn deploy_repo_link_tracked_files(rice_dir: Result<FixtureHarness>) -> Result<()> {
+        let rice_dir = rice_dir?;
+        let locator = mock_locator(&rice_dir);
+        let home = rice_dir.as_path().join("home");
+        let workdir = rice_dir.as_path().join("repos/vim.git");
+
+        let deployment = deploy_repo("vim", &locator, &home)?;
+        let expect: Vec<PathBuf> = vec![".vim/colors.vim".into(), ".vimrc".into()];
+        assert_eq!(deployment.linked, expect);
+        assert_eq!(fs::read_link(home.join(".vimrc"))?, workdir.join(".vimrc"));
+        assert_eq!(fs::read_to_string(home.join(".vimrc"))?, "set number");
+
+        let deployment = deploy_repo("vim", &locator, &home)?;
+        assert_eq!(deployment.unchanged, expect);
+        assert!(deployment.linked.is_empty());
+        Ok(())
+    }
+
+    #[rstest]
+    fn deploy_repo_prune_untracked_files(rice_dir: Result<FixtureHarness>) -> Result<()> {
+        let rice_dir = rice_dir?;
+        let locator = mock_locator(&rice_dir);
+        let home = rice_dir.as_path().join("home");
+        deploy_repo("vim", &locator, &home)?;
+
+        let workdir = rice_dir.as_path().join("repos/vim.git");
+        let git = git2::Repository::open(&workdir)?;
+        let mut index = git.index()?;
+        index.remove_path(Path::new(".vimrc"))?;
+        index.write()?;
+
+        let deployment = deploy_repo("vim", &locator, &home)?;
+        assert_eq!(deployment.pruned, vec![PathBuf::from(".vimrc")]);
+        assert!(home.join(".vimrc").symlink_metadata().is_err());
+        Ok(())
+    }
+
+    #[rstest]
+    fn deploy_repo_return_err_conflict(rice_dir: Result<FixtureHarness>) -> Result<()> {
+        let rice_dir = rice_dir?;
+        let locator = mock_locator(&rice_dir);
+        let home = rice_dir.as_path().join("home");
+        fs::write(home.join(".vimrc"), "mine")?;
+
+        let result = deploy_repo("vim", &locator, &home);
+        assert!(matches!(result.unwrap_err(), DeployError::Conflict { .. }));
+        assert_eq!(fs::read_to_string(home.join(".vimrc"))?, "mine");
+        assert!(home.join(".vim/colors.vim").symlink_metadata().is_err());
+        Ok(())
+    }
+
+    #[rstest]
+    fn deploy_repo_return_err_not_symlink_mode(rice_dir: Result<FixtureHarness>) -> Result<()> {
+        let rice_dir = rice_dir?;
+        let home = rice_dir.as_path().join("home");
+        let result = deploy_repo("sh", &mock_locator(&rice_dir), &home);
+        assert!(matches!(result.unwrap_err(), DeployError::NotSymlinkMode { .. }));
+        Ok(())
+    }
+
+    #[rstest]
+    fn undeploy_repo_remove_only_ricer_links(rice_dir: Result<FixtureHarness>) -> Result<()> {
+        let rice_dir = rice_dir?;
+        let locator = mock_locator(&rice_dir);
+        let home = rice_dir.as_path().join("home");
+        deploy_repo("vim", &locator, &home)?;
+        fs::remove_file(home.join(".vimrc"))?;
+        fs::write(home.join(".vimrc"), "mine")?;
+
+        let removed = undeploy_repo("vim", &locator, &home)?;
+        assert_eq!(removed, vec![PathBuf::from(".vim/colors.vim")]);
+        assert_eq!(fs::read_to_string(home.join(".vimrc"))?, "mine");
+        assert!(home.join(".vim/colors.vim").symlink_metadata().is_err());
+        assert!(!rice_dir.as_path().join("state/deploy/vim.toml").exists());
+        Ok(())
+    }
+}
@@ -0,0 +1,235 @@
+// SPDX-FileCopyrightText: 2024 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+//! Deprecated command-line flags and configuration keys.
+//!
+//! Flags and keys that get renamed keep working for a while, so users can
+//! migrate at their own pace. Every rename is registered in [`DEPRECATIONS`].
+//! Deprecated flags are translated into their replacement before the
+//! command-line is parsed, and settings read deprecated keys like their
+//! replacement. Either way, Ricer warns once per run about each deprecation
+//! in use, naming its replacement.
+//!
+//! Deprecated keys of configuration files can be listed all at once through
+//! `ricer doctor`.
+
+use log::warn;
+use std::{
+    collections::BTreeSet,
+    ffi::{OsStr, OsString},
+    fmt,
+    sync::Mutex,
+};
+
+/// Every flag and key that was renamed, oldest first.
+pub const DEPRECATIONS: &[Deprecation] = &[
+    Deprecation {
+        kind: DeprecationKind::Flag,
+        old: "--run-hook",
+        new: "--run-hooks",
+        since: "0.5.0",
+    },
+    Deprecation {
+        kind: DeprecationKind::Key,
+        old: "repos.*.hooks",
+        new: "repos.*.run_hooks",
+        since: "0.5.0",
+    },
+];
+
+/// What was deprecated.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum DeprecationKind {
+    /// Command-line flag, named like `--flag` for top-level options, or like
+    /// `<COMMAND> --flag` for options of a command.
+    Flag,
+
+    /// Configuration file key, named by its dotted path, where `*` matches any
+    /// one table, e.g., `repos.*.hooks`.
+    Key,
+}
+
+impl fmt::Display for DeprecationKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeprecationKind::Flag => write!(f, "Flag"),
+            DeprecationKind::Key => write!(f, "Key"),
+        }
+    }
+}
+
+/// Renamed flag or key.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Deprecation {
+    /// Whether a flag or key was renamed.
+    pub kind: DeprecationKind,
+
+    /// Deprecated name.
+    pub old: &'static str,
+
+    /// Name that replaces it.
+    pub new: &'static str,
+
+    /// Version of Ricer that deprecated it.
+    pub since: &'static str,
+}
+
+impl Deprecation {
+    /// Name key at `path` would have after its rename, i.e., `path` with
+    /// every segment of [`Deprecation::new`] that is not `*` substituted in.
+    pub fn renamed_key(&self, path: &[&str]) -> String {
+        self.new
+            .split('.')
+            .zip(path)
+            .map(|(new, old)| if new == "*" { *old } else { new })
+            .collect::<Vec<_>>()
+            .join(".")
+    }
+
+    fn matches_key(&self, path: &[&str]) -> bool {
+        let pattern = self.old.split('.').collect::<Vec<_>>();
+        self.kind == DeprecationKind::Key
+            && pattern.len() == path.len()
+            && pattern.iter().zip(path).all(|(pattern, key)| *pattern == "*" || pattern == key)
+    }
+}
+
+impl fmt::Display for Deprecation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} '{}' is deprecated since {}, use '{}' instead",
+            self.kind, self.old, self.since, self.new
+        )
+    }
+}
+
+/// Warn about `deprecation`, unless it was already warned about this run.
+///
+/// Returns whether a warning was emitted.
+pub fn warn_once(deprecation: &'static Deprecation) -> bool {
+    static WARNED: Mutex<BTreeSet<&str>> = Mutex::new(BTreeSet::new());
+
+    // INVARIANT: a poisoned set only ever holds a few extra names, so keep using it.
+    let mut warned = WARNED.lock().unwrap_or_else(|err| err.into_inner());
+    let first = warned.insert(deprecation.old);
+    if first {
+        warn!("{deprecation}");
+    }
+    first
+}
+
+/// Translate command-line argument into the flag that replaces it.
+///
+/// Top-level options are looked up with no `command`, and options of a
+/// command with the name of that command. Both `--flag` and `--flag=VALUE`
+/// forms are translated. Returns `None` if `arg` is not deprecated.
+pub fn rewrite_flag(command: Option<&str>, arg: &OsStr) -> Option<OsString> {
+    let arg = arg.to_str()?;
+    let (flag, value) = match arg.split_once('=') {
+        Some((flag, value)) => (flag, Some(value)),
+        None => (arg, None),
+    };
+
+    let deprecation = DEPRECATIONS.iter().find(|deprecation| {
+        let (scope, old) = match deprecation.old.rsplit_once(' ') {
+            Some((scope, old)) => (Some(scope), old),
+            None => (None, deprecation.old),
+        };
+        deprecation.kind == DeprecationKind::Flag && scope == command && old == flag
+    })?;
+    warn_once(deprecation);
+
+    let new = deprecation.new.rsplit(' ').next().unwrap_or(deprecation.new);
+    Some(match value {
+        Some(value) => format!("{new}={value}").into(),
+        None => new.into(),
+    })
+}
+
+/// Name of `key` of `table` after its rename.
+///
+/// Tables are named like the parent of deprecated keys in [`DEPRECATIONS`],
+/// e.g., `repos.*` for repository settings. Warns about deprecated keys, and
+/// returns `key` as-is if it is not deprecated.
+pub fn current_key<'key>(table: &str, key: &'key str) -> &'key str {
+    let path = table.split('.').chain([key]).collect::<Vec<_>>();
+    let Some(deprecation) = DEPRECATIONS.iter().find(|deprecation| {
+        deprecation.kind == DeprecationKind::Key && deprecation.old == path.join(".")
+    }) else {
+        return key;
+    };
+
+    warn_once(deprecation);
+    deprecation.new.rsplit('.').next().unwrap_or(key)
+}
+
+/// Find deprecation of key at dotted `path` of a configuration file.
+pub fn find_key(path: &[&str]) -> Option<&'static Deprecation> {
+    DEPRECATIONS.iter().find(|deprecation| deprecation.matches_key(path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::flag(None, "--run-hook", Some("--run-hooks"))]
+    #[case::flag_with_value(None, "--run-hook=always", Some("--run-hooks=always"))]
+    #[case::current_flag(None, "--run-hooks=always", None)]
+    #[case::command_flag(Some("commit"), "--run-hook", None)]
+    #[case::value(None, "always", None)]
+    fn rewrite_flag_translate_deprecated_flags(
+        #[case] command: Option<&str>,
+        #[case] arg: &str,
+        #[case] expect: Option<&str>,
+    ) {
+        let result = rewrite_flag(command, OsStr::new(arg));
+        assert_eq!(result, expect.map(OsString::from));
+    }
+
+    #[rstest]
+    #[case::deprecated("repos.*", "hooks", "run_hooks")]
+    #[case::current("repos.*", "run_hooks", "run_hooks")]
+    #[case::other_table("hooks", "hooks", "hooks")]
+    fn current_key_return_renamed_key(
+        #[case] table: &str,
+        #[case] key: &str,
+        #[case] expect: &str,
+    ) {
+        assert_eq!(current_key(table, key), expect);
+    }
+
+    #[rstest]
+    #[case::deprecated(&["repos", "vim", "hooks"], Some("repos.vim.run_hooks"))]
+    #[case::current(&["repos", "vim", "run_hooks"], None)]
+    #[case::table(&["repos", "hooks"], None)]
+    #[case::nested(&["repos", "vim", "bootstrap", "hooks"], None)]
+    fn find_key_match_wildcard_tables(#[case] path: &[&str], #[case] expect: Option<&str>) {
+        let result = find_key(path).map(|deprecation| deprecation.renamed_key(path));
+        assert_eq!(result.as_deref(), expect);
+    }
+
+    #[test]
+    fn warn_once_warn_only_first_time() {
+        static DEPRECATION: Deprecation = Deprecation {
+            kind: DeprecationKind::Flag,
+            old: "--test-only",
+            new: "--test",
+            since: "0.0.0",
+        };
+        assert!(warn_once(&DEPRECATION));
+        assert!(!warn_once(&DEPRECATION));
+    }
+
+    #[test]
+    fn deprecation_display_name_replacement() {
+        assert_eq!(
+            DEPRECATIONS[0].to_string(),
+            "Flag '--run-hook' is deprecated since 0.5.0, use '--run-hooks' instead"
+        );
+    }
+}
@@ -0,0 +1,439 @@
+// SPDX-FileCopyrightText: 2024 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+//! Diagnose problems of Ricer's configuration.
+//!
+//! Deprecated keys keep working, but only warn when Ricer happens to read
+//! them. `ricer doctor` checks every configuration file for them at once, so
+//! they can all be migrated before support for them is dropped.
+//!
+//! Through [`watch_config`], `ricer config watch` checks configuration files
+//! with [`check_config`] every time one of them is saved, so mistakes made
+//! while editing them by hand show up right away.
+//!
+//! Entries of configuration files also point to each other, e.g., repository
+//! groups list repositories, and hook definitions name hook scripts. Through
+//! [`dangling_refs`], `ricer doctor` finds references to entries or hook
+//! scripts that were deleted since, which `ricer doctor --fix` prunes through
+//! [`prune_dangling_refs`].
+
+mod refs;
+
+pub use refs::*;
+
+use crate::{
+    cancel,
+    config::{
+        self, entries_or_empty, CmdHookConfig, ConfigFileError, DaemonConfig, FeatureConfig,
+        RepoConfig, Section, Toml, TomlError,
+    },
+    deprecate::{self, Deprecation},
+    locate::Locator,
+    ui::ErrorReport,
+};
+
+use log::{debug, info, warn};
+use std::{
+    fmt, fs,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    time::{Duration, SystemTime},
+};
+
+/// Error types for [`deprecated_keys`].
+#[derive(Debug, thiserror::Error)]
+pub enum DoctorError {
+    #[error("Failed to check configuration file")]
+    Config { source: ConfigFileError },
+}
+
+impl From<ConfigFileError> for DoctorError {
+    fn from(err: ConfigFileError) -> Self {
+        DoctorError::Config { source: err }
+    }
+}
+
+/// Deprecated key found in a configuration file.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct DeprecatedKey {
+    /// Configuration file key was found in.
+    pub path: PathBuf,
+
+    /// Dotted path of key.
+    pub key: String,
+
+    /// Key it should be renamed to.
+    pub renamed: String,
+
+    /// Deprecation of key.
+    pub deprecation: &'static Deprecation,
+}
+
+impl DeprecatedKey {
+    /// Describe deprecation without naming configuration file.
+    pub fn message(&self) -> String {
+        format!(
+            "'{}' is deprecated since {}, use '{}' instead",
+            self.key, self.deprecation.since, self.renamed
+        )
+    }
+}
+
+impl fmt::Display for DeprecatedKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path.display(), self.message())
+    }
+}
+
+/// Find every deprecated key of repository, hook, and Ricer configuration
+/// files.
+///
+/// Keys are reported in order of configuration file, and of their position
+/// in it. Missing configuration files are skipped.
+///
+/// # Errors
+///
+/// - Return [`DoctorError::Config`] if a configuration file cannot be read or
+///   parsed.
+pub fn deprecated_keys(locator: &impl Locator) -> Result<Vec<DeprecatedKey>, DoctorError> {
+    let mut found = Vec::new();
+    for path in [locator.repos_config(), locator.hooks_config(), locator.ricer_config()] {
+        let Some(doc) = config::read_toml_if_exists(path)? else {
+            continue;
+        };
+
+        for key in doc.key_paths() {
+            if let Some(deprecation) = deprecate::find_key(&key) {
+                found.push(DeprecatedKey {
+                    path: path.into(),
+                    key: key.join("."),
+                    renamed: deprecation.renamed_key(&key),
+                    deprecation,
+                });
+            }
+        }
+    }
+
+    Ok(found)
+}
+
+/// Problem found in a configuration file.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ConfigProblem {
+    /// Configuration file problem was found in.
+    pub path: PathBuf,
+
+    /// Description of problem, including its causes.
+    pub message: String,
+}
+
+impl fmt::Display for ConfigProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path.display(), self.message)
+    }
+}
+
+/// Check repository, hook, and Ricer configuration files for problems.
+///
+/// Configuration files are checked with their local overlay merged in, the
+/// same way commands read them. Files that cannot be parsed, or whose entries
+/// cannot be read as settings, are reported once along with the cause.
+/// Deprecated keys are reported for every file that parses, and so are
+/// references found through [`dangling_refs`]. Missing configuration files
+/// are skipped.
+pub fn check_config(locator: &impl Locator) -> Vec<ConfigProblem> {
+    type Check = fn(&Toml) -> Result<(), TomlError>;
+    let checks: [(&Path, Check); 3] = [
+        (locator.repos_config(), |doc| {
+            entries_or_empty(&RepoConfig, doc)?;
+            match doc.entries(Section::Groups) {
+                Err(TomlError::TableNotFound { .. }) => Ok(()),
+                result => result.map(drop),
+            }
+        }),
+        (locator.hooks_config(), |doc| entries_or_empty(&CmdHookConfig, doc).map(drop)),
+        (locator.ricer_config(), |doc| {
+            entries_or_empty(&FeatureConfig, doc)?;
+            DaemonConfig::validate(doc)
+        }),
+    ];
+
+    let mut problems = Vec::new();
+    for (path, check) in checks {
+        let problem = |message| ConfigProblem { path: path.into(), message };
+        let doc = match config::read_merged_if_exists(path) {
+            Ok(Some(doc)) => doc,
+            Ok(None) => continue,
+            Err(err) => {
+                problems.push(problem(ErrorReport::new(&err).to_string()));
+                continue;
+            }
+        };
+
+        if let Err(err) = check(&doc) {
+            problems.push(problem(ErrorReport::new(&err).to_string()));
+        }
+
+        for key in doc.key_paths() {
+            if let Some(deprecation) = deprecate::find_key(&key) {
+                let key = DeprecatedKey {
+                    path: path.into(),
+                    key: key.join("."),
+                    renamed: deprecation.renamed_key(&key),
+                    deprecation,
+                };
+                problems.push(problem(key.message()));
+            }
+        }
+    }
+
+    // INVARIANT: files that cannot be read were already reported above.
+    if let Ok(found) = dangling_refs(locator) {
+        problems.extend(found.into_iter().map(|dangling| ConfigProblem {
+            path: dangling.path,
+            message: dangling.kind.to_string(),
+        }));
+    }
+
+    problems
+}
+
+/// Watcher of configuration files.
+///
+/// Remembers modification time and size of repository, hook, and Ricer
+/// configuration files along with their local overlays, and reports the files
+/// that changed since it last looked.
+#[derive(Debug, Clone)]
+pub struct ConfigWatcher {
+    files: Vec<(PathBuf, Option<FileStamp>)>,
+}
+
+type FileStamp = (SystemTime, u64);
+
+impl ConfigWatcher {
+    /// Start watching configuration files of `locator`.
+    pub fn new(locator: &impl Locator) -> Self {
+        let paths = [locator.repos_config(), locator.hooks_config(), locator.ricer_config()];
+        let files = paths
+            .into_iter()
+            .flat_map(|path| [path.to_path_buf(), config::local_overlay_path(path)])
+            .map(|path| {
+                let stamp = file_stamp(&path);
+                (path, stamp)
+            })
+            .collect();
+        Self { files }
+    }
+
+    /// Get configuration files that were changed, made, or removed since the
+    /// last call.
+    pub fn changed(&mut self) -> Vec<PathBuf> {
+        let mut changed = Vec::new();
+        for (path, stamp) in &mut self.files {
+            let current = file_stamp(path);
+            if current != *stamp {
+                *stamp = current;
+                changed.push(path.clone());
+            }
+        }
+        changed
+    }
+}
+
+fn file_stamp(path: &Path) -> Option<FileStamp> {
+    let meta = fs::metadata(path).ok()?;
+    Some((meta.modified().ok()?, meta.len()))
+}
+
+/// Check configuration files of `locator` through [`check_config`] right
+/// away, and again each time one of them changes, until cancelled.
+///
+/// Configuration files are looked at every `interval`. Problems of each
+/// check are handed to `report`. With `notify`, checks after a change also
+/// show a desktop notification through `notify-send` when they find
+/// problems, or when they find that earlier problems were fixed.
+///
+/// # Errors
+///
+/// - Return `E` if `report` fails.
+pub fn watch_config<E>(
+    locator: &impl Locator,
+    interval: Duration,
+    notify: bool,
+    mut report: impl FnMut(&[ConfigProblem]) -> Result<(), E>,
+) -> Result<(), E> {
+    let mut watcher = ConfigWatcher::new(locator);
+    let mut problems = check_config(locator);
+    report(&problems)?;
+
+    let mut notify = notify;
+    while cancel::sleep(interval) {
+        let changed = watcher.changed();
+        if changed.is_empty() {
+            continue;
+        }
+        for path in &changed {
+            info!("Check configuration again, because '{}' changed", path.display());
+        }
+
+        let had_problems = !problems.is_empty();
+        problems = check_config(locator);
+        report(&problems)?;
+        if notify && (had_problems || !problems.is_empty()) {
+            // INVARIANT: stop notifying once notifications fail, but keep watching.
+            notify = notify_desktop(&problems);
+        }
+    }
+
+    Ok(())
+}
+
+/// Show desktop notification about `problems`.
+///
+/// Return whether notification could be shown.
+fn notify_desktop(problems: &[ConfigProblem]) -> bool {
+    let body = match problems.len() {
+        0 => "No problems found".to_string(),
+        1 => problems[0].to_string(),
+        count => format!("{count} problems found, first: {}", problems[0]),
+    };
+    debug!("Notify desktop: {body}");
+    let status = Command::new("notify-send")
+        .args(["--app-name=ricer", "Ricer configuration", &body])
+        .stdin(Stdio::null())
+        .status();
+    match status {
+        Ok(status) if status.success() => true,
+        Ok(status) => {
+            warn!("Stop desktop notifications, because 'notify-send' failed with {status}");
+            false
+        }
+        Err(err) => {
+            warn!("Stop desktop notifications, because 'notify-send' cannot run: {err}");
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        locate::MockLocator,
+        testenv::{FileKind, FixtureHarness},
+    };
+
+    use anyhow::Result;
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+    use rstest::{fixture, rstest};
+
+    #[fixture]
+    fn config_dir() -> Result<FixtureHarness> {
+        let harness = FixtureHarness::open()?
+            .with_file("repos.toml", |fixture| {
+                fixture
+                    .with_data(indoc! {r#"
+                        [repos.vim]
+                        branch = "main"
+                        hooks = "never"
+
+                        [repos.dwm]
+                        branch = "main"
+                        run_hooks = "prompt"
+                    "#})
+                    .with_kind(FileKind::Normal)
+            })
+            .with_file("hooks.toml", |fixture| {
+                fixture
+                    .with_data("[hooks]\ncommit = [{ pre = \"hook.sh\" }]\n")
+                    .with_kind(FileKind::Normal)
+            })
+            .setup()?;
+        Ok(harness)
+    }
+
+    #[rstest]
+    fn deprecated_keys_find_renamed_keys(config_dir: Result<FixtureHarness>) -> Result<()> {
+        let config_dir = config_dir?;
+        let root = config_dir.as_path();
+        let mut locator = MockLocator::new();
+        locator.expect_repos_config().return_const(root.join("repos.toml"));
+        locator.expect_hooks_config().return_const(root.join("hooks.toml"));
+        locator.expect_ricer_config().return_const(root.join("config.toml"));
+
+        let result = deprecated_keys(&locator)?;
+        assert_eq!(
+            result.iter().map(ToString::to_string).collect::<Vec<_>>(),
+            vec![format!(
+                "{}: 'repos.vim.hooks' is deprecated since 0.5.0, use 'repos.vim.run_hooks' \
+                 instead",
+                root.join("repos.toml").display()
+            )]
+        );
+        Ok(())
+    }
+
+    #[rstest]
+    fn check_config_report_bad_files_and_deprecated_keys() -> Result<()> {
+        let config_dir = FixtureHarness::open()?
+            .with_file("repos.toml", |fixture| {
+                fixture
+                    .with_data(indoc! {r#"
+                        [repos.vim]
+                        branch = 1
+
+                        [repos.dwm]
+                        branch = "main"
+                        hooks = "never"
+                    "#})
+                    .with_kind(FileKind::Normal)
+            })
+            .with_file("hooks.toml", |fixture| {
+                fixture.with_data("[hooks\n").with_kind(FileKind::Normal)
+            })
+            .with_file("config.toml", |fixture| {
+                fixture.with_data("[daemon]\ninterval = \"soon\"\n").with_kind(FileKind::Normal)
+            })
+            .setup()?;
+        let root = config_dir.as_path();
+        let mut locator = MockLocator::new();
+        locator.expect_repos_config().return_const(root.join("repos.toml"));
+        locator.expect_hooks_config().return_const(root.join("hooks.toml"));
+        locator.expect_ricer_config().return_const(root.join("config.toml"));
+
+        let problems = check_config(&locator);
+        let paths = problems.iter().map(|problem| problem.path.clone()).collect::<Vec<_>>();
+        let expect =
+            ["repos.toml", "repos.toml", "hooks.toml", "config.toml"].map(|file| root.join(file));
+        assert_eq!(paths, expect);
+        assert!(problems[0].message.contains("'vim.branch' to be a string"));
+        assert!(problems[3].message.contains("'soon' is not a duration"));
+        assert_eq!(
+            problems[1].message,
+            "'repos.dwm.hooks' is deprecated since 0.5.0, use 'repos.dwm.run_hooks' instead"
+        );
+        Ok(())
+    }
+
+    #[rstest]
+    fn config_watcher_changed_report_changed_files(
+        config_dir: Result<FixtureHarness>,
+    ) -> Result<()> {
+        let config_dir = config_dir?;
+        let root = config_dir.as_path();
+        let mut locator = MockLocator::new();
+        locator.expect_repos_config().return_const(root.join("repos.toml"));
+        locator.expect_hooks_config().return_const(root.join("hooks.toml"));
+        locator.expect_ricer_config().return_const(root.join("config.toml"));
+
+        let mut watcher = ConfigWatcher::new(&locator);
+        assert_eq!(watcher.changed(), Vec::<PathBuf>::new());
+
+        fs::write(root.join("hooks.toml"), "[hooks]\n")?;
+        fs::write(root.join("config.toml"), "[ui]\n")?;
+        assert_eq!(watcher.changed(), vec![root.join("hooks.toml"), root.join("config.toml")]);
+        assert_eq!(watcher.changed(), Vec::<PathBuf>::new());
+        Ok(())
+    }
+}
@@ -0,0 +1,405 @@
+// SPDX-FileCopyrightText: 2024 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+use super::DoctorError;
+use crate::{
+    config::{
+        self, entries_or_empty, CmdHookConfig, CmdHookSettings, ConfigFile, ConfigFileError,
+        RepoConfig, RepoGroup, Section, TomlError,
+    },
+    locate::Locator,
+    util::is_glob,
+};
+
+use std::{
+    collections::BTreeMap,
+    fmt,
+    path::{Path, PathBuf},
+};
+
+/// Kinds of references that point to nothing.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum DanglingKind {
+    /// Repository group lists a repository or group that does not exist.
+    GroupMember { group: String, member: String },
+
+    /// Repository names a setup hook script that does not exist.
+    OnClone { repo: String, script: String },
+
+    /// Hook definition names a hook script that does not exist.
+    HookScript { cmd: String, index: usize, script: String },
+
+    /// Hook definition depends on a hook definition or hook script that
+    /// its command does not have.
+    HookDepends { cmd: String, index: usize, dependency: String },
+}
+
+impl fmt::Display for DanglingKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DanglingKind::GroupMember { group, member } => {
+                write!(f, "group '{group}' lists '{member}', which is neither repository nor group")
+            }
+            DanglingKind::OnClone { repo, script } => {
+                write!(f, "repository '{repo}' runs missing hook script '{script}' on clone")
+            }
+            DanglingKind::HookScript { cmd, index, script } => {
+                write!(f, "hook {} of '{cmd}' runs missing hook script '{script}'", index + 1)
+            }
+            DanglingKind::HookDepends { cmd, index, dependency } => write!(
+                f,
+                "hook {} of '{cmd}' depends on '{dependency}', which '{cmd}' has no hook for",
+                index + 1
+            ),
+        }
+    }
+}
+
+/// Reference between entries of repository and hook configuration files
+/// that points to nothing, e.g., a hook script that was deleted.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct DanglingRef {
+    /// Configuration file reference was found in.
+    pub path: PathBuf,
+
+    /// What reference points to.
+    pub kind: DanglingKind,
+}
+
+impl fmt::Display for DanglingRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path.display(), self.kind)
+    }
+}
+
+/// Find every reference of repository and hook configuration files that
+/// points to nothing.
+///
+/// Group members that are glob patterns never dangle. Hook scripts dangle
+/// once they are missing from the hook directory. Missing configuration
+/// files are skipped.
+///
+/// # Errors
+///
+/// - Return [`DoctorError::Config`] if a configuration file cannot be read or
+///   parsed.
+pub fn dangling_refs(locator: &impl Locator) -> Result<Vec<DanglingRef>, DoctorError> {
+    let mut found = Vec::new();
+    let is_script = |script: &str| locator.hooks_dir().join(script).is_file();
+
+    let path = locator.repos_config();
+    if let Some(doc) = config::read_merged_if_exists(path)? {
+        let toml_err = |err| ConfigFileError::Toml { source: err, path: path.into() };
+        let repos = entries_or_empty(&RepoConfig, &doc).map_err(toml_err)?;
+        let groups = match doc.entries(Section::Groups) {
+            Ok(entries) => entries.into_iter().map(RepoGroup::from).collect(),
+            Err(TomlError::TableNotFound { .. }) => Vec::new(),
+            Err(err) => return Err(toml_err(err).into()),
+        };
+
+        let is_repo = |name: &str| repos.iter().any(|repo| repo.name == name);
+        let is_group = |name: &str| groups.iter().any(|group| group.name == name);
+        for group in &groups {
+            for member in &group.members {
+                if !is_glob(member) && !is_repo(member) && !is_group(member) {
+                    let kind = DanglingKind::GroupMember {
+                        group: group.name.clone(),
+                        member: member.clone(),
+                    };
+                    found.push(DanglingRef { path: path.into(), kind });
+                }
+            }
+        }
+
+        for repo in &repos {
+            let on_clone =
+                repo.bootstrap.as_ref().and_then(|bootstrap| bootstrap.on_clone.as_ref());
+            if let Some(script) = on_clone.filter(|script| !is_script(script)) {
+                let kind =
+                    DanglingKind::OnClone { repo: repo.name.clone(), script: script.clone() };
+                found.push(DanglingRef { path: path.into(), kind });
+            }
+        }
+    }
+
+    let path = locator.hooks_config();
+    if let Some(doc) = config::read_merged_if_exists(path)? {
+        let cmds = entries_or_empty(&CmdHookConfig, &doc)
+            .map_err(|err| ConfigFileError::Toml { source: err, path: path.into() })?;
+        for cmd_hook in &cmds {
+            for (index, hook) in cmd_hook.hooks.iter().enumerate() {
+                let cmd = cmd_hook.cmd.clone();
+                for script in hook.pre.iter().chain(&hook.post).filter(|script| !is_script(script))
+                {
+                    let kind = DanglingKind::HookScript {
+                        cmd: cmd.clone(),
+                        index,
+                        script: script.clone(),
+                    };
+                    found.push(DanglingRef { path: path.into(), kind });
+                }
+
+                for dependency in &hook.depends {
+                    // INVARIANT: resolve dependencies the same way hook schedules do.
+                    let resolves = cmd_hook.hooks.iter().enumerate().any(|(other, dep)| {
+                        other != index
+                            && (dep.name.as_ref() == Some(dependency)
+                                || dep
+                                    .pre
+                                    .iter()
+                                    .chain(&dep.post)
+                                    .any(|script| Path::new(script).ends_with(dependency)))
+                    });
+                    if !resolves {
+                        let kind = DanglingKind::HookDepends {
+                            cmd: cmd.clone(),
+                            index,
+                            dependency: dependency.clone(),
+                        };
+                        found.push(DanglingRef { path: path.into(), kind });
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(found)
+}
+
+/// Remove every reference of `refs` from its configuration file.
+///
+/// Group members, setup hook scripts, hook scripts, and dependencies are
+/// removed from the entries naming them. Hook definitions that lose their
+/// last hook script are removed along with them, and so are commands that
+/// lose their last hook definition.
+///
+/// # Invariants
+///
+/// 1. Every configuration file is edited in one transaction, so it is either
+///    pruned of every reference of `refs`, or left as it was.
+/// 1. References are found by their position within their command, so
+///    removing hook definitions never shifts which hook other references
+///    point to.
+///
+/// # Errors
+///
+/// - Return [`DoctorError::Config`] if a configuration file cannot be read,
+///   edited, or written.
+pub fn prune_dangling_refs(
+    locator: &impl Locator,
+    refs: &[DanglingRef],
+) -> Result<(), DoctorError> {
+    let is_repo_ref = |kind: &DanglingKind| {
+        matches!(kind, DanglingKind::GroupMember { .. } | DanglingKind::OnClone { .. })
+    };
+    if refs.iter().any(|dangling| is_repo_ref(&dangling.kind)) {
+        let mut repos = ConfigFile::load(RepoConfig, locator)?;
+        repos.transaction(|repos| -> Result<(), DoctorError> {
+            for dangling in refs {
+                match &dangling.kind {
+                    DanglingKind::GroupMember { group, member } => {
+                        let groups = repos.groups()?;
+                        if let Some(group) = groups.iter().find(|found| found.name == *group) {
+                            let members = group.members.iter().filter(|name| *name != member);
+                            repos.set_group(&RepoGroup::new(&group.name).members(members))?;
+                        }
+                    }
+                    DanglingKind::OnClone { repo, .. } => {
+                        let mut settings = repos.get(repo)?;
+                        if let Some(bootstrap) = settings.bootstrap.as_mut() {
+                            bootstrap.on_clone = None;
+                        }
+                        repos.upsert(settings)?;
+                    }
+                    _ => (),
+                }
+            }
+            Ok(())
+        })?;
+    }
+
+    // INVARIANT: prune each command once, so indices of hook definitions stay put.
+    let mut by_cmd: BTreeMap<&str, Vec<&DanglingKind>> = BTreeMap::new();
+    for dangling in refs {
+        match &dangling.kind {
+            DanglingKind::HookScript { cmd, .. } | DanglingKind::HookDepends { cmd, .. } => {
+                by_cmd.entry(cmd).or_default().push(&dangling.kind)
+            }
+            _ => (),
+        }
+    }
+    if by_cmd.is_empty() {
+        return Ok(());
+    }
+
+    let mut hooks = ConfigFile::load(CmdHookConfig, locator)?;
+    hooks.transaction(|hooks| -> Result<(), DoctorError> {
+        for (cmd, kinds) in by_cmd {
+            let settings = prune_cmd_hook(hooks.get(cmd)?, &kinds);
+            match settings.hooks.is_empty() {
+                true => drop(hooks.remove(cmd)?),
+                false => drop(hooks.upsert(settings)?),
+            }
+        }
+        Ok(())
+    })
+}
+
+/// Remove dangling references of `kinds` from hook definitions of `settings`.
+fn prune_cmd_hook(mut settings: CmdHookSettings, kinds: &[&DanglingKind]) -> CmdHookSettings {
+    let mut emptied = Vec::new();
+    for kind in kinds {
+        match kind {
+            DanglingKind::HookScript { index, script, .. } => {
+                if let Some(hook) = settings.hooks.get_mut(*index) {
+                    hook.pre.retain(|name| name != script);
+                    hook.post.retain(|name| name != script);
+                    if hook.pre.is_empty() && hook.post.is_empty() {
+                        emptied.push(*index);
+                    }
+                }
+            }
+            DanglingKind::HookDepends { index, dependency, .. } => {
+                if let Some(hook) = settings.hooks.get_mut(*index) {
+                    hook.depends.retain(|name| name != dependency);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    let mut index = 0;
+    settings.hooks.retain(|_| {
+        index += 1;
+        !emptied.contains(&(index - 1))
+    });
+    settings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        locate::MockLocator,
+        testenv::{FileKind, FixtureHarness},
+    };
+
+    use anyhow::Result;
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+    use rstest::{fixture, rstest};
+    use std::fs;
+
+    #[fixture]
+    fn config_dir() -> Result<FixtureHarness> {
+        let harness = FixtureHarness::open()?
+            .with_file("repos.toml", |fixture| {
+                fixture
+                    .with_data(indoc! {r#"
+                        [repos.vim]
+                        branch = "main"
+
+                        [repos.vim.bootstrap]
+                        clone = "https://example.com/vim.git"
+                        on_clone = "gone.sh"
+
+                        [groups.desktop]
+                        members = ["vim", "dwm", "st*", "editors"]
+
+                        [groups.editors]
+                        members = ["vim"]
+                    "#})
+                    .with_kind(FileKind::Normal)
+            })
+            .with_file("hooks.toml", |fixture| {
+                fixture
+                    .with_data(indoc! {r#"
+                        [hooks]
+                        commit = [
+                            { pre = "lint.sh", name = "lint" },
+                            { pre = "gone.sh" },
+                            { post = ["notify.sh", "gone.sh"], depends = ["lint", "fetch.sh"] },
+                        ]
+                    "#})
+                    .with_kind(FileKind::Normal)
+            })
+            .with_file("hooks/lint.sh", |fixture| fixture.with_data("#!/bin/sh\n"))
+            .with_file("hooks/notify.sh", |fixture| fixture.with_data("#!/bin/sh\n"))
+            .setup()?;
+        Ok(harness)
+    }
+
+    fn locator(config_dir: &FixtureHarness) -> MockLocator {
+        let root = config_dir.as_path();
+        let mut locator = MockLocator::new();
+        locator.expect_repos_config().return_const(root.join("repos.toml"));
+        locator.expect_hooks_config().return_const(root.join("hooks.toml"));
+        locator.expect_hooks_dir().return_const(root.join("hooks"));
+        locator
+    }
+
+    #[rstest]
+    fn dangling_refs_find_missing_targets(config_dir: Result<FixtureHarness>) -> Result<()> {
+        let config_dir = config_dir?;
+        let found = dangling_refs(&locator(&config_dir))?;
+        let kinds = found.into_iter().map(|dangling| dangling.kind).collect::<Vec<_>>();
+        assert_eq!(
+            kinds,
+            vec![
+                DanglingKind::GroupMember { group: "desktop".into(), member: "dwm".into() },
+                DanglingKind::OnClone { repo: "vim".into(), script: "gone.sh".into() },
+                DanglingKind::HookScript {
+                    cmd: "commit".into(),
+                    index: 1,
+                    script: "gone.sh".into()
+                },
+                DanglingKind::HookScript {
+                    cmd: "commit".into(),
+                    index: 2,
+                    script: "gone.sh".into()
+                },
+                DanglingKind::HookDepends {
+                    cmd: "commit".into(),
+                    index: 2,
+                    dependency: "fetch.sh".into()
+                },
+            ]
+        );
+        Ok(())
+    }
+
+    #[rstest]
+    fn prune_dangling_refs_leave_no_dangling_refs(
+        config_dir: Result<FixtureHarness>,
+    ) -> Result<()> {
+        let config_dir = config_dir?;
+        let locator = locator(&config_dir);
+        prune_dangling_refs(&locator, &dangling_refs(&locator)?)?;
+        assert_eq!(dangling_refs(&locator)?, Vec::new());
+
+        let hooks = ConfigFile::load(CmdHookConfig, &locator)?.get("commit")?;
+        assert_eq!(hooks.hooks.len(), 2);
+        assert_eq!(hooks.hooks[1].post, vec!["notify.sh".to_string()]);
+        assert_eq!(hooks.hooks[1].depends, vec!["lint".to_string()]);
+
+        let repos = fs::read_to_string(config_dir.as_path().join("repos.toml"))?;
+        assert!(repos.contains(r#"members = ["vim", "st*", "editors"]"#), "{repos}");
+        assert!(!repos.contains("on_clone"), "{repos}");
+        Ok(())
+    }
+
+    #[rstest]
+    fn prune_dangling_refs_remove_emptied_commands(
+        config_dir: Result<FixtureHarness>,
+    ) -> Result<()> {
+        let config_dir = config_dir?;
+        let locator = locator(&config_dir);
+        fs::write(
+            config_dir.as_path().join("hooks.toml"),
+            "[hooks]\npush = [{ pre = \"gone.sh\" }]\n",
+        )?;
+        prune_dangling_refs(&locator, &dangling_refs(&locator)?)?;
+        assert_eq!(ConfigFile::load(CmdHookConfig, &locator)?.entries()?, Vec::new());
+        Ok(())
+    }
+}
@@ -0,0 +1,87 @@
+// SPDX-FileCopyrightText: 2024 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+//! Annotated examples.
+//!
+//! Through `ricer examples <topic>`, the user gets copy-pastable snippets of
+//! configuration and command sequences for common tasks, like defining hooks,
+//! or tracking dotfiles through fake bare repositories. Examples are embedded
+//! into the binary from `assets/examples/`, so they always match the version
+//! of Ricer that prints them.
+
+use crate::context::{ExampleTopic, ExamplesContext};
+
+use clap::ValueEnum;
+use std::fmt::Write;
+
+/// Embedded examples of `topic`.
+pub fn topic_examples(topic: ExampleTopic) -> &'static str {
+    match topic {
+        ExampleTopic::Hooks => include_str!("../assets/examples/hooks.md"),
+        ExampleTopic::Repos => include_str!("../assets/examples/repos.md"),
+        ExampleTopic::Bootstrap => include_str!("../assets/examples/bootstrap.md"),
+    }
+}
+
+/// Render examples from [`ExamplesContext`].
+///
+/// Lists every topic along with its summary if no topic is given.
+pub fn render(ctx: &ExamplesContext) -> String {
+    if let Some(topic) = ctx.topic {
+        return topic_examples(topic).into();
+    }
+
+    let mut text = String::from("Examples are available for these topics:\n\n");
+    for topic in ExampleTopic::value_variants() {
+        let summary = topic.to_possible_value().and_then(|value| value.get_help().cloned());
+        let _ = writeln!(text, "  {:<10} {}", topic.to_string(), summary.unwrap_or_default());
+    }
+    text.push_str("\nShow one through 'ricer examples <topic>'.\n");
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rstest::rstest;
+    use toml_edit::DocumentMut;
+
+    /// Snippets between `` ```toml `` and `` ``` `` fences of `text`.
+    fn toml_snippets(text: &str) -> Vec<String> {
+        let mut snippets = Vec::new();
+        let mut snippet = None::<String>;
+        for line in text.lines() {
+            match (line.trim(), snippet.as_mut()) {
+                ("```toml", None) => snippet = Some(String::new()),
+                ("```", Some(_)) => snippets.extend(snippet.take()),
+                (_, Some(snippet)) => {
+                    snippet.push_str(line);
+                    snippet.push('\n');
+                }
+                _ => (),
+            }
+        }
+        snippets
+    }
+
+    #[rstest]
+    #[case::hooks(ExampleTopic::Hooks)]
+    #[case::repos(ExampleTopic::Repos)]
+    #[case::bootstrap(ExampleTopic::Bootstrap)]
+    fn topic_examples_contain_valid_toml(#[case] topic: ExampleTopic) {
+        let snippets = toml_snippets(topic_examples(topic));
+        assert!(!snippets.is_empty());
+        for snippet in snippets {
+            assert!(snippet.parse::<DocumentMut>().is_ok(), "{topic}: {snippet}");
+        }
+    }
+
+    #[rstest]
+    fn render_list_every_topic() {
+        let text = render(&ExamplesContext { topic: None, shared: Default::default() });
+        for topic in ExampleTopic::value_variants() {
+            assert!(text.contains(&format!("  {topic} ")), "{text}");
+        }
+    }
+}
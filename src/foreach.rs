@@ -0,0 +1,368 @@
+// SPDX-FileCopyrightText: 2024 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+//! Shell iteration over repositories.
+//!
+//! Through `ricer foreach <COMMAND>`, a shell snippet runs in the working
+//! directory of every cloned repository, or only of those selected through
+//! `--repo`. Variables in braces are replaced with details of the repository
+//! the snippet runs in:
+//!
+//! - `{name}` is the name of the repository.
+//! - `{path}` is its working directory, or its Git directory if it has none.
+//! - `{gitdir}` is its Git directory.
+//! - `{branch}` and `{remote}` are its configured branch and remote.
+//!
+//! Values are always shell-quoted, so they are safe to use as single words,
+//! e.g., `ricer foreach 'echo {name} is at {path} on {branch}'`. Literal
+//! braces are written as `{{` and `}}`.
+//!
+//! Snippets run one repository at a time, unless `--jobs N` runs them in up to
+//! N repositories at once. The exit code of each snippet ends up in a
+//! [`BatchReport`], so one failing repository never stops the others.
+
+use crate::{
+    cancel,
+    config::ConfigFileError,
+    context::ForeachContext,
+    locate::Locator,
+    ops::{BatchOutcome, BatchReport},
+    util::{RepoIter, RepoSelector, SelectError},
+    vcs::GitRepo,
+};
+
+use log::{debug, info};
+use std::{
+    io::{self, Write},
+    path::PathBuf,
+    process::{Command, Output, Stdio},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    thread,
+};
+
+/// Error types for [`foreach`].
+#[derive(Debug, thiserror::Error)]
+pub enum ForeachError {
+    #[error("Unknown variable '{{{var}}}' in command, expected one of {}", VARS.join(", "))]
+    UnknownVar { var: String },
+
+    #[error("Unbalanced brace in command '{command}', write literal braces as '{{{{' or '}}}}'")]
+    Unbalanced { command: String },
+
+    #[error("Failed to select repositories")]
+    Select { source: SelectError },
+
+    #[error("Failed to read repository configuration")]
+    Config { source: ConfigFileError },
+}
+
+impl From<ConfigFileError> for ForeachError {
+    fn from(err: ConfigFileError) -> Self {
+        ForeachError::Config { source: err }
+    }
+}
+
+impl From<SelectError> for ForeachError {
+    fn from(err: SelectError) -> Self {
+        ForeachError::Select { source: err }
+    }
+}
+
+/// Variables available to shell snippets of [`foreach`].
+pub const VARS: &[&str] = &["name", "path", "gitdir", "branch", "remote"];
+
+/// Shell snippet rendered for one repository.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Job {
+    name: String,
+    workdir: PathBuf,
+    script: String,
+}
+
+/// Run shell snippet of [`ForeachContext`] in every selected repository.
+///
+/// Returns report of every selected repository in configuration order, or in
+/// order of `--repo` if given.
+///
+/// # Invariants
+///
+/// 1. Snippet is rendered for every repository before it runs anywhere, so a
+///    bad snippet never runs in only some of them.
+/// 1. Repositories that are not cloned are skipped.
+/// 1. Snippets that have not started yet are skipped once cancelled.
+///
+/// # Errors
+///
+/// - Return [`ForeachError::UnknownVar`] if snippet uses a variable that is
+///   not one of [`VARS`].
+/// - Return [`ForeachError::Unbalanced`] if snippet has a lone brace.
+/// - Return [`ForeachError::Select`] if patterns select no registered
+///   repository.
+/// - Return [`ForeachError::Config`] if repository configuration cannot be
+///   read.
+pub fn foreach(ctx: &ForeachContext, locator: &impl Locator) -> Result<BatchReport, ForeachError> {
+    let mut entries = Vec::new();
+    let mut jobs = Vec::new();
+    let selector = RepoSelector::new(&ctx.repos).strict(ctx.strict);
+    for (settings, gitdir) in RepoIter::load::<ForeachError>(locator, selector)? {
+        let workdir = match GitRepo::open(&gitdir) {
+            Ok(repo) => repo.workdir().map(PathBuf::from).unwrap_or_else(|| gitdir.clone()),
+            Err(_) if !gitdir.exists() => {
+                entries.push((settings.name, Some(BatchOutcome::Skipped("not cloned".into()))));
+                continue;
+            }
+            Err(err) => {
+                entries.push((settings.name, Some(BatchOutcome::Failed(err.to_string()))));
+                continue;
+            }
+        };
+
+        let vars = [
+            ("name", settings.name.clone()),
+            ("path", workdir.to_string_lossy().into_owned()),
+            ("gitdir", gitdir.to_string_lossy().into_owned()),
+            ("branch", settings.branch.clone()),
+            ("remote", settings.remote.clone()),
+        ];
+        let script = render(&ctx.command, &vars)?;
+        entries.push((settings.name.clone(), None));
+        jobs.push(Job { name: settings.name, workdir, script });
+    }
+
+    let width = usize::from(ctx.jobs.unwrap_or(1)).min(jobs.len());
+    let mut outcomes = run_jobs(&jobs, width).into_iter();
+    let mut report = BatchReport::new();
+    for (repo, outcome) in entries {
+        let outcome = outcome
+            .or_else(|| outcomes.next().flatten())
+            .unwrap_or_else(|| BatchOutcome::Skipped("cancelled".into()));
+        report.push(repo, outcome);
+    }
+
+    Ok(report)
+}
+
+/// Run `jobs` on `width` threads at once, returning outcome of each job that
+/// started.
+fn run_jobs(jobs: &[Job], width: usize) -> Vec<Option<BatchOutcome>> {
+    if width <= 1 {
+        return jobs
+            .iter()
+            .map(|job| (!cancel::is_cancelled()).then(|| outcome(run_streamed(job))))
+            .collect();
+    }
+
+    let next = AtomicUsize::new(0);
+    let outcomes = Mutex::new(vec![None; jobs.len()]);
+    thread::scope(|scope| {
+        for _ in 0..width {
+            scope.spawn(|| loop {
+                let idx = next.fetch_add(1, Ordering::SeqCst);
+                if idx >= jobs.len() || cancel::is_cancelled() {
+                    break;
+                }
+                let outcome = outcome(run_captured(&jobs[idx]));
+                outcomes.lock().unwrap()[idx] = Some(outcome);
+            });
+        }
+    });
+    outcomes.into_inner().unwrap()
+}
+
+fn shell(job: &Job) -> Command {
+    info!("Run '{}' in '{}'", job.script, job.name);
+    let mut cmd = Command::new("sh");
+    cmd.arg("-c").arg(&job.script).current_dir(&job.workdir);
+    cmd
+}
+
+fn run_streamed(job: &Job) -> io::Result<Option<i32>> {
+    Ok(shell(job).stdin(Stdio::null()).status()?.code())
+}
+
+/// Run `job` with its output held back until it finishes, so output of jobs
+/// running at once never interleaves.
+fn run_captured(job: &Job) -> io::Result<Option<i32>> {
+    let Output { status, stdout, stderr } = shell(job).stdin(Stdio::null()).output()?;
+    io::stdout().lock().write_all(&stdout)?;
+    io::stderr().lock().write_all(&stderr)?;
+    Ok(status.code())
+}
+
+fn outcome(result: io::Result<Option<i32>>) -> BatchOutcome {
+    let outcome = match result {
+        Ok(Some(0)) => BatchOutcome::Done,
+        Ok(Some(code)) => BatchOutcome::Failed(format!("exit code {code}")),
+        Ok(None) => BatchOutcome::Failed("killed by signal".into()),
+        Err(err) => BatchOutcome::Failed(format!("cannot run shell: {err}")),
+    };
+    debug!("Snippet {outcome}");
+    outcome
+}
+
+/// Replace variables of `command` with shell-quoted values of `vars`.
+///
+/// # Errors
+///
+/// - Return [`ForeachError::UnknownVar`] if `command` uses a variable missing
+///   from `vars`.
+/// - Return [`ForeachError::Unbalanced`] if `command` has a lone brace.
+fn render(command: &str, vars: &[(&str, String)]) -> Result<String, ForeachError> {
+    let unbalanced = || ForeachError::Unbalanced { command: command.into() };
+    let mut rendered = String::new();
+    let mut chars = command.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.next_if_eq(&'{').is_some() => rendered.push('{'),
+            '}' if chars.next_if_eq(&'}').is_some() => rendered.push('}'),
+            '{' => {
+                let mut var = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some('{') | None => return Err(unbalanced()),
+                        Some(c) => var.push(c),
+                    }
+                }
+                let value = vars
+                    .iter()
+                    .find(|(name, _)| *name == var)
+                    .map(|(_, value)| value)
+                    .ok_or(ForeachError::UnknownVar { var })?;
+                rendered.push_str(&shell_quote(value));
+            }
+            '}' => return Err(unbalanced()),
+            c => rendered.push(c),
+        }
+    }
+
+    Ok(rendered)
+}
+
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testenv::{mock_locator, parse_ctx, FileKind, FixtureHarness};
+
+    use anyhow::Result;
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+    use rstest::{fixture, rstest};
+    use std::fs;
+
+    #[fixture]
+    fn rice_dir() -> Result<FixtureHarness> {
+        let harness = FixtureHarness::open()?
+            .with_file("config/repos.toml", |fixture| {
+                fixture
+                    .with_data(indoc! {r#"
+                        [repos.vim]
+                        branch = "main"
+                        remote = "origin"
+
+                        [repos.dwm]
+                        branch = "stable"
+                        remote = "upstream"
+
+                        [repos.gone]
+                        branch = "main"
+                        remote = "origin"
+                    "#})
+                    .with_kind(FileKind::Normal)
+            })
+            .with_repo("repos/vim", Ok)?
+            .with_repo("repos/dwm", Ok)?
+            .setup()?;
+        Ok(harness)
+    }
+
+    #[rstest]
+    #[case::sequential(&[])]
+    #[case::jobs(&["--jobs", "2"])]
+    fn foreach_report_exit_code_of_every_repo(
+        rice_dir: Result<FixtureHarness>,
+        #[case] args: &[&str],
+    ) -> Result<()> {
+        let rice_dir = rice_dir?;
+        let command = "echo {name} on {branch} of {remote} > out.txt; [ {name} != dwm ] || exit 3";
+        let ctx = parse_ctx::<ForeachContext>("foreach", &[&[command], args].concat())?;
+        let report = foreach(&ctx, &mock_locator(&rice_dir))?;
+
+        let expect = vec![
+            ("vim".to_string(), BatchOutcome::Done),
+            ("dwm".to_string(), BatchOutcome::Failed("exit code 3".into())),
+            ("gone".to_string(), BatchOutcome::Skipped("not cloned".into())),
+        ];
+        assert_eq!(report.outcomes(), expect);
+        let out = fs::read_to_string(rice_dir.as_path().join("repos/vim.git/out.txt"))?;
+        assert_eq!(out, "vim on main of origin\n");
+        let out = fs::read_to_string(rice_dir.as_path().join("repos/dwm.git/out.txt"))?;
+        assert_eq!(out, "dwm on stable of upstream\n");
+        Ok(())
+    }
+
+    #[rstest]
+    fn foreach_only_run_in_selected_repos(rice_dir: Result<FixtureHarness>) -> Result<()> {
+        let rice_dir = rice_dir?;
+        let ctx = parse_ctx::<ForeachContext>("foreach", &["touch ran", "--repo", "dwm"])?;
+        let report = foreach(&ctx, &mock_locator(&rice_dir))?;
+        assert_eq!(report.outcomes(), vec![("dwm".to_string(), BatchOutcome::Done)]);
+        assert!(!rice_dir.as_path().join("repos/vim.git/ran").exists());
+        Ok(())
+    }
+
+    #[rstest]
+    fn foreach_return_err_missing_repo(rice_dir: Result<FixtureHarness>) -> Result<()> {
+        let rice_dir = rice_dir?;
+        let ctx = parse_ctx::<ForeachContext>("foreach", &["true", "--repo", "st"])?;
+        let result = foreach(&ctx, &mock_locator(&rice_dir));
+        assert!(matches!(result.unwrap_err(), ForeachError::Select { .. }));
+        Ok(())
+    }
+
+    #[rstest]
+    fn foreach_never_run_bad_command(rice_dir: Result<FixtureHarness>) -> Result<()> {
+        let rice_dir = rice_dir?;
+        let ctx = parse_ctx::<ForeachContext>("foreach", &["touch ran {nmae}"])?;
+        let result = foreach(&ctx, &mock_locator(&rice_dir));
+        assert!(matches!(result.unwrap_err(), ForeachError::UnknownVar { .. }));
+        assert!(!rice_dir.as_path().join("repos/vim.git/ran").exists());
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::plain("git status", "git status")]
+    #[case::vars("echo {name} at {path}", "echo 'vim' at '/home/awkless/my vim'")]
+    #[case::quote_in_value("echo {branch}", r"echo 'it'\''s'")]
+    #[case::literal_braces("awk '{{print $1}}' {name}", "awk '{print $1}' 'vim'")]
+    fn render_substitute_quoted_vars(#[case] command: &str, #[case] expect: &str) -> Result<()> {
+        let vars = [
+            ("name", "vim".to_string()),
+            ("path", "/home/awkless/my vim".to_string()),
+            ("branch", "it's".to_string()),
+        ];
+        assert_eq!(render(command, &vars)?, expect);
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::unknown_var("echo {nmae}")]
+    #[case::unclosed("echo {name")]
+    #[case::lone_close("echo name}")]
+    #[case::nested("echo {na{me}")]
+    fn render_return_err(#[case] command: &str) {
+        let vars = [("name", "vim".to_string())];
+        let result = render(command, &vars);
+        assert!(matches!(
+            result.unwrap_err(),
+            ForeachError::UnknownVar { .. } | ForeachError::Unbalanced { .. }
+        ));
+    }
+}
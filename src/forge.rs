@@ -0,0 +1,746 @@
+// SPDX-FileCopyrightText: 2024 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+//! Forge integration.
+//!
+//! Quickly onboard an entire dotfile ecosystem by listing the repositories of
+//! a user or organization on a forge like GitHub or GitLab, and registering
+//! and cloning the ones that match a set of filters.
+//!
+//! Forge REST APIs are queried through the user's `curl` binary, similar to how
+//! [`GitRepo::syscall`] uses the user's Git binary. Access tokens are taken
+//! from the `GITHUB_TOKEN` and `GITLAB_TOKEN` environment variables, and are
+//! handed to `curl` through standard input so they never show up in the
+//! process listing.
+//!
+//! [`GitRepo::syscall`]: crate::vcs::GitRepo::syscall
+
+use crate::{
+    config::{
+        validate_repo_name, BootstrapSettings, ConfigFile, ConfigFileError, RepoConfig,
+        RepoNameError, RepoSettings, TomlError,
+    },
+    context::{ForgeContext, ForgeKind},
+    json::{Json, JsonError},
+    locate::Locator,
+    plan::{self, DryRunEffect},
+    vcs::{GitRepo, GitRepoError},
+};
+
+use glob::{Pattern, PatternError};
+use log::{debug, info, warn};
+use std::{
+    env,
+    io::{Error as IoError, Write},
+    process::{Command, Stdio},
+};
+
+/// Number of repositories to request per page from forge APIs.
+const PER_PAGE: usize = 100;
+
+/// Error types for [`Forge`].
+#[derive(Debug, thiserror::Error)]
+pub enum ForgeError {
+    #[error("Failed to call curl binary")]
+    Syscall { source: IoError },
+
+    #[error("Request to '{url}' failed: {msg}")]
+    Http { url: String, msg: String },
+
+    #[error("Failed to parse response from '{url}'")]
+    Json { source: JsonError, url: String },
+
+    #[error("Unexpected response from '{url}'")]
+    BadResponse { url: String },
+
+    #[error("Namespace '{namespace}' not found on {kind}")]
+    NamespaceNotFound { namespace: String, kind: ForgeKind },
+
+    #[error("Invalid repository name pattern")]
+    BadPattern { source: PatternError },
+
+    #[error("Forge listed invalid repository name '{name}'")]
+    BadRepoName { source: RepoNameError, name: String },
+
+    #[error("Failed to clone repository '{name}'")]
+    Clone { source: GitRepoError, name: String },
+
+    #[error("Failed to register cloned repository")]
+    Config { source: ConfigFileError },
+}
+
+impl From<ConfigFileError> for ForgeError {
+    fn from(err: ConfigFileError) -> Self {
+        ForgeError::Config { source: err }
+    }
+}
+
+/// HTTP response from [`HttpClient`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpResponse {
+    pub status: u16,
+    pub body: String,
+}
+
+/// Minimal HTTP client used to query forge APIs.
+pub trait HttpClient {
+    /// Perform GET request with optional bearer token.
+    ///
+    /// # Errors
+    ///
+    /// Return [`ForgeError`] if request could not be performed at all. HTTP
+    /// error statuses are not errors, and are reported through [`HttpResponse`].
+    fn get(&self, url: &str, accept: &str, token: Option<&str>)
+        -> Result<HttpResponse, ForgeError>;
+}
+
+/// HTTP client that calls the user's `curl` binary.
+#[derive(Debug, Default, Clone)]
+pub struct CurlClient;
+
+impl HttpClient for CurlClient {
+    fn get(
+        &self,
+        url: &str,
+        accept: &str,
+        token: Option<&str>,
+    ) -> Result<HttpResponse, ForgeError> {
+        debug!("GET '{url}'");
+        let mut cmd = Command::new("curl");
+        cmd.args(["--silent", "--show-error", "--location", "--write-out", "\n%{http_code}"])
+            .args(["--header", &format!("Accept: {accept}")]);
+        if token.is_some() {
+            // INVARIANT: never pass access tokens as arguments visible in process listing.
+            cmd.args(["--header", "@-"]);
+        }
+        let mut child = cmd
+            .arg(url)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|err| ForgeError::Syscall { source: err })?;
+
+        let mut stdin = child.stdin.take().unwrap();
+        if let Some(token) = token {
+            writeln!(stdin, "Authorization: Bearer {token}")
+                .map_err(|err| ForgeError::Syscall { source: err })?;
+        }
+        drop(stdin);
+
+        let output = child.wait_with_output().map_err(|err| ForgeError::Syscall { source: err })?;
+        if !output.status.success() {
+            let msg = String::from_utf8_lossy(output.stderr.as_slice()).trim().to_string();
+            return Err(ForgeError::Http { url: url.into(), msg });
+        }
+
+        let stdout = String::from_utf8_lossy(output.stdout.as_slice()).into_owned();
+        let (body, status) = stdout.rsplit_once('\n').unwrap_or(("", stdout.as_str()));
+        let status =
+            status.trim().parse().map_err(|_| ForgeError::BadResponse { url: url.into() })?;
+        Ok(HttpResponse { status, body: body.into() })
+    }
+}
+
+/// Repository listed by a forge.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ForgeRepo {
+    pub name: String,
+    pub clone_url: String,
+    pub default_branch: String,
+    pub topics: Vec<String>,
+}
+
+/// Filters to select which forge repositories to clone.
+#[derive(Debug, Clone, Default)]
+pub struct ForgeFilter {
+    topic: Option<String>,
+    pattern: Option<Pattern>,
+}
+
+impl ForgeFilter {
+    /// Construct new filter.
+    ///
+    /// # Errors
+    ///
+    /// - Return [`ForgeError::BadPattern`] if `pattern` is not a valid glob.
+    pub fn new(topic: Option<&str>, pattern: Option<&str>) -> Result<Self, ForgeError> {
+        let pattern = pattern
+            .map(Pattern::new)
+            .transpose()
+            .map_err(|err| ForgeError::BadPattern { source: err })?;
+        Ok(Self { topic: topic.map(Into::into), pattern })
+    }
+
+    pub fn matches(&self, repo: &ForgeRepo) -> bool {
+        let topic = match &self.topic {
+            Some(topic) => repo.topics.iter().any(|t| t == topic),
+            None => true,
+        };
+        let pattern = match &self.pattern {
+            Some(pattern) => pattern.matches(&repo.name),
+            None => true,
+        };
+        topic && pattern
+    }
+}
+
+/// Forge API handler.
+#[derive(Debug, Clone)]
+pub struct Forge<C>
+where
+    C: HttpClient,
+{
+    kind: ForgeKind,
+    api_url: String,
+    token: Option<String>,
+    client: C,
+}
+
+impl<C> Forge<C>
+where
+    C: HttpClient,
+{
+    pub fn new(kind: ForgeKind, client: C) -> Self {
+        let api_url = match kind {
+            ForgeKind::GitHub => "https://api.github.com",
+            ForgeKind::GitLab => "https://gitlab.com/api/v4",
+        };
+        Self { kind, api_url: api_url.into(), token: None, client }
+    }
+
+    /// Construct new forge handler with access token from environment.
+    ///
+    /// Uses `GITHUB_TOKEN` for GitHub, and `GITLAB_TOKEN` for GitLab. The API
+    /// URL of GitLab can be overridden through `GITLAB_API_URL` to support
+    /// self-hosted instances.
+    pub fn from_env(kind: ForgeKind, client: C) -> Self {
+        let mut forge = Self::new(kind, client);
+        let (token, api_url) = match kind {
+            ForgeKind::GitHub => ("GITHUB_TOKEN", None),
+            ForgeKind::GitLab => ("GITLAB_TOKEN", Some("GITLAB_API_URL")),
+        };
+        forge.token = env::var(token).ok().filter(|token| !token.is_empty());
+        if let Some(api_url) = api_url.and_then(|var| env::var(var).ok()) {
+            forge.api_url = api_url.trim_end_matches('/').into();
+        }
+        forge
+    }
+
+    pub fn api_url(mut self, url: impl Into<String>) -> Self {
+        self.api_url = url.into();
+        self
+    }
+
+    pub fn token(mut self, token: impl Into<String>) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+
+    /// List all repositories of user or organization `namespace`.
+    ///
+    /// # Errors
+    ///
+    /// - Return [`ForgeError::NamespaceNotFound`] if `namespace` does not
+    ///   exist on forge.
+    /// - Return [`ForgeError::Http`] if forge responded with an error.
+    /// - Return [`ForgeError::Json`] or [`ForgeError::BadResponse`] if forge
+    ///   response cannot be understood.
+    pub fn list_repos(&self, namespace: &str) -> Result<Vec<ForgeRepo>, ForgeError> {
+        let namespace = namespace.trim_matches('/');
+        match self.kind {
+            ForgeKind::GitHub => {
+                let url = format!("{}/users/{namespace}/repos", self.api_url);
+                self.list_pages(&url, namespace)
+            }
+            ForgeKind::GitLab => {
+                // INVARIANT: GitLab namespaces can be nested groups, e.g., "group/subgroup".
+                let encoded = namespace.replace('/', "%2F");
+                let url = format!("{}/groups/{encoded}/projects", self.api_url);
+                match self.list_pages(&url, namespace) {
+                    Err(ForgeError::NamespaceNotFound { .. }) => {
+                        let url = format!("{}/users/{encoded}/projects", self.api_url);
+                        self.list_pages(&url, namespace)
+                    }
+                    result => result,
+                }
+            }
+        }
+    }
+
+    /// Get size of repository at `path` on forge in bytes, e.g., for path
+    /// `awkless/vim`.
+    ///
+    /// Returns `None` if repository does not exist, or forge does not report
+    /// its size. GitLab only reports sizes to members of a project.
+    ///
+    /// # Errors
+    ///
+    /// - Return [`ForgeError::Http`] if forge responded with an error.
+    /// - Return [`ForgeError::Json`] if forge response cannot be understood.
+    pub fn repo_size(&self, path: &str) -> Result<Option<u64>, ForgeError> {
+        let path = path.trim_matches('/');
+        let url = match self.kind {
+            ForgeKind::GitHub => format!("{}/repos/{path}", self.api_url),
+            ForgeKind::GitLab => {
+                format!("{}/projects/{}?statistics=true", self.api_url, path.replace('/', "%2F"))
+            }
+        };
+        let response = self.client.get(&url, self.accept(), self.token.as_deref())?;
+        match response.status {
+            200..=299 => (),
+            404 => return Ok(None),
+            status => return Err(ForgeError::Http { url, msg: format!("HTTP status {status}") }),
+        }
+
+        let data: Json =
+            response.body.parse().map_err(|err| ForgeError::Json { source: err, url })?;
+        let size = match self.kind {
+            // INVARIANT: GitHub reports sizes in kibibytes.
+            ForgeKind::GitHub => data.get("size").and_then(Json::as_f64).map(|size| size * 1024.0),
+            ForgeKind::GitLab => data
+                .get("statistics")
+                .and_then(|stats| stats.get("repository_size"))
+                .and_then(Json::as_f64),
+        };
+        Ok(size.map(|size| size as u64))
+    }
+
+    fn accept(&self) -> &'static str {
+        match self.kind {
+            ForgeKind::GitHub => "application/vnd.github+json",
+            ForgeKind::GitLab => "application/json",
+        }
+    }
+
+    fn list_pages(&self, url: &str, namespace: &str) -> Result<Vec<ForgeRepo>, ForgeError> {
+        let accept = self.accept();
+        let mut repos = Vec::new();
+        for page in 1.. {
+            let page_url = format!("{url}?per_page={PER_PAGE}&page={page}");
+            let response = self.client.get(&page_url, accept, self.token.as_deref())?;
+            match response.status {
+                200..=299 => (),
+                404 => {
+                    return Err(ForgeError::NamespaceNotFound {
+                        namespace: namespace.into(),
+                        kind: self.kind,
+                    })
+                }
+                status => {
+                    return Err(ForgeError::Http {
+                        url: page_url,
+                        msg: format!("HTTP status {status}"),
+                    })
+                }
+            }
+
+            let data: Json = response
+                .body
+                .parse()
+                .map_err(|err| ForgeError::Json { source: err, url: page_url.clone() })?;
+            let entries =
+                data.as_array().ok_or(ForgeError::BadResponse { url: page_url.clone() })?;
+            for entry in entries {
+                repos.push(self.parse_repo(entry, &page_url)?);
+            }
+
+            if entries.len() < PER_PAGE {
+                break;
+            }
+        }
+
+        Ok(repos)
+    }
+
+    fn parse_repo(&self, entry: &Json, url: &str) -> Result<ForgeRepo, ForgeError> {
+        let (name, clone_url) = match self.kind {
+            ForgeKind::GitHub => ("name", "clone_url"),
+            ForgeKind::GitLab => ("path", "http_url_to_repo"),
+        };
+        let field = |key: &str| {
+            entry
+                .get(key)
+                .and_then(Json::as_str)
+                .map(String::from)
+                .ok_or(ForgeError::BadResponse { url: url.into() })
+        };
+
+        Ok(ForgeRepo {
+            name: field(name)?,
+            clone_url: field(clone_url)?,
+            // INVARIANT: empty repositories have no default branch.
+            default_branch: field("default_branch").unwrap_or_else(|_| "main".into()),
+            topics: entry
+                .get("topics")
+                .and_then(Json::as_array)
+                .unwrap_or_default()
+                .iter()
+                .filter_map(|topic| topic.as_str().map(String::from))
+                .collect(),
+        })
+    }
+}
+
+/// Find forge hosting clone `url`, along with path of repository on it.
+///
+/// Only repositories on `github.com` and `gitlab.com` are recognized, through
+/// both URL and scp-like syntax, e.g., `git@github.com:awkless/vim.git` is
+/// hosted on GitHub at path `awkless/vim`.
+pub fn hosted_repo(url: &str) -> Option<(ForgeKind, String)> {
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let rest = rest.split_once('@').map_or(rest, |(_, rest)| rest);
+    let (host, path) = rest.split_once(['/', ':'])?;
+    let kind = match host.to_ascii_lowercase().as_str() {
+        "github.com" => ForgeKind::GitHub,
+        "gitlab.com" => ForgeKind::GitLab,
+        _ => return None,
+    };
+
+    let path = path.trim_matches('/');
+    let path = path.strip_suffix(".git").unwrap_or(path);
+    (!path.is_empty()).then(|| (kind, path.to_string()))
+}
+
+/// Register and clone all matching repositories of forge namespace.
+///
+/// Repositories that are already registered in the repository configuration
+/// file are skipped. Each cloned repository is registered with `origin` as its
+/// remote, the forge's default branch as its branch, and its clone URL as its
+/// bootstrap clone URL.
+///
+/// Returns names of repositories that were cloned.
+///
+/// # Errors
+///
+/// - Return [`ForgeError::BadRepoName`] if any matching repository has a name
+///   that is not a plain file name, before anything is cloned or registered.
+/// - Return [`ForgeError`] if forge could not be queried, or any matching
+///   repository could not be cloned or registered.
+pub fn clone_namespace<C, L>(
+    forge: &Forge<C>,
+    ctx: &ForgeContext,
+    locator: &L,
+) -> Result<Vec<String>, ForgeError>
+where
+    C: HttpClient,
+    L: Locator,
+{
+    let filter = ForgeFilter::new(ctx.topic.as_deref(), ctx.pattern.as_deref())?;
+    let mut config = ConfigFile::load(RepoConfig, locator)?;
+    let mut cloned = Vec::new();
+
+    let repos = forge.list_repos(&ctx.namespace)?;
+    info!("Found {} repositories in '{}'", repos.len(), ctx.namespace);
+    let repos = repos.into_iter().filter(|repo| filter.matches(repo)).collect::<Vec<_>>();
+    // INVARIANT: names come from the forge, so never let them escape the repository directory.
+    for repo in &repos {
+        validate_repo_name(&repo.name)
+            .map_err(|err| ForgeError::BadRepoName { source: err, name: repo.name.clone() })?;
+    }
+
+    for repo in repos {
+        match config.get(&repo.name) {
+            Ok(_) => {
+                warn!("Skip '{}', because it is already registered", repo.name);
+                continue;
+            }
+            Err(ConfigFileError::Toml {
+                source: TomlError::EntryNotFound { .. } | TomlError::TableNotFound { .. },
+                ..
+            }) => (),
+            Err(err) => return Err(err.into()),
+        }
+
+        info!("Clone '{}' from '{}'", repo.name, repo.clone_url);
+        let gitdir = locator.repos_dir().join(format!("{}.git", repo.name));
+        if plan::perform(|| DryRunEffect::Clone { url: repo.clone_url.clone(), path: gitdir }) {
+            GitRepo::clone(&repo.clone_url, locator.repos_dir().join(&repo.name))
+                .map_err(|err| ForgeError::Clone { source: err, name: repo.name.clone() })?;
+        }
+
+        let settings = RepoSettings::new(&repo.name)
+            .branch(&repo.default_branch)
+            .remote("origin")
+            .bootstrap(BootstrapSettings::new().clone(&repo.clone_url));
+        config.add_new(settings)?;
+        config.save()?;
+        cloned.push(repo.name);
+    }
+
+    Ok(cloned)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        cli::Cli,
+        context::Context,
+        locate::MockLocator,
+        testenv::{FileKind, FixtureHarness},
+    };
+
+    use anyhow::Result;
+    use indoc::{formatdoc, indoc};
+    use pretty_assertions::assert_eq;
+    use rstest::{fixture, rstest};
+    use std::{cell::RefCell, collections::HashMap};
+
+    #[derive(Default)]
+    struct FakeClient {
+        responses: HashMap<String, HttpResponse>,
+        requests: RefCell<Vec<String>>,
+    }
+
+    impl FakeClient {
+        fn with(mut self, url: &str, status: u16, body: impl Into<String>) -> Self {
+            self.responses.insert(url.into(), HttpResponse { status, body: body.into() });
+            self
+        }
+    }
+
+    impl HttpClient for FakeClient {
+        fn get(
+            &self,
+            url: &str,
+            _accept: &str,
+            _token: Option<&str>,
+        ) -> Result<HttpResponse, ForgeError> {
+            self.requests.borrow_mut().push(url.into());
+            Ok(self
+                .responses
+                .get(url)
+                .cloned()
+                .unwrap_or(HttpResponse { status: 404, body: "{}".into() }))
+        }
+    }
+
+    fn forge_ctx(args: &[&str]) -> Result<ForgeContext> {
+        match Context::from(Cli::parse_args(args)?) {
+            Context::Clone(ctx) => Ok(ctx.forge.unwrap()),
+            _ => unreachable!(),
+        }
+    }
+
+    #[fixture]
+    fn repo_dir() -> Result<FixtureHarness> {
+        let harness = FixtureHarness::open()?
+            .with_repo("remote/vim", |repo| repo.stage("vimrc", "config for vim!"))?
+            .with_repo("remote/dwm", |repo| repo.stage("config.h", "configure DWM here"))?
+            .with_file("config/repos.toml", |fixture| {
+                fixture
+                    .with_data(indoc! {r#"
+                        [repos.dwm]
+                        branch = "main"
+                        remote = "origin"
+                        workdir_home = false
+                    "#})
+                    .with_kind(FileKind::Normal)
+            })
+            .setup()?;
+        Ok(harness)
+    }
+
+    #[test]
+    fn forge_list_repos_parse_github() -> Result<()> {
+        let client = FakeClient::default().with(
+            "https://api.github.com/users/awkless/repos?per_page=100&page=1",
+            200,
+            indoc! {r#"
+                [
+                    {
+                        "name": "vim",
+                        "clone_url": "https://github.com/awkless/vim.git",
+                        "default_branch": "master",
+                        "topics": ["dotfiles", "vim"]
+                    }
+                ]
+            "#},
+        );
+        let forge = Forge::new(ForgeKind::GitHub, client);
+        let result = forge.list_repos("awkless/")?;
+        let expect = vec![ForgeRepo {
+            name: "vim".into(),
+            clone_url: "https://github.com/awkless/vim.git".into(),
+            default_branch: "master".into(),
+            topics: vec!["dotfiles".into(), "vim".into()],
+        }];
+        assert_eq!(result, expect);
+        Ok(())
+    }
+
+    #[test]
+    fn forge_list_repos_gitlab_fallback_to_user() -> Result<()> {
+        let client = FakeClient::default().with(
+            "https://gitlab.com/api/v4/users/awkless/projects?per_page=100&page=1",
+            200,
+            r#"[{"path": "sh", "http_url_to_repo": "https://gitlab.com/awkless/sh.git"}]"#,
+        );
+        let forge = Forge::new(ForgeKind::GitLab, client);
+        let result = forge.list_repos("awkless")?;
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].name, "sh");
+        assert_eq!(result[0].default_branch, "main");
+        assert_eq!(
+            forge.client.requests.borrow().as_slice(),
+            [
+                "https://gitlab.com/api/v4/groups/awkless/projects?per_page=100&page=1",
+                "https://gitlab.com/api/v4/users/awkless/projects?per_page=100&page=1",
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn forge_list_repos_return_err_namespace_not_found() {
+        let forge = Forge::new(ForgeKind::GitHub, FakeClient::default());
+        let result = forge.list_repos("nobody");
+        assert!(matches!(result.unwrap_err(), ForgeError::NamespaceNotFound { .. }));
+    }
+
+    #[rstest]
+    #[case::github(
+        ForgeKind::GitHub,
+        "https://api.github.com/repos/awkless/vim",
+        r#"{"name": "vim", "size": 2}"#,
+        Some(2048)
+    )]
+    #[case::gitlab(
+        ForgeKind::GitLab,
+        "https://gitlab.com/api/v4/projects/awkless%2Fvim?statistics=true",
+        r#"{"path": "vim", "statistics": {"repository_size": 4096}}"#,
+        Some(4096)
+    )]
+    #[case::unreported(
+        ForgeKind::GitLab,
+        "https://gitlab.com/api/v4/projects/awkless%2Fvim?statistics=true",
+        r#"{"path": "vim"}"#,
+        None
+    )]
+    #[case::not_found(ForgeKind::GitHub, "https://api.github.com/repos/other", "{}", None)]
+    fn forge_repo_size_parse_response(
+        #[case] kind: ForgeKind,
+        #[case] url: &str,
+        #[case] body: &str,
+        #[case] expect: Option<u64>,
+    ) -> Result<()> {
+        let forge = Forge::new(kind, FakeClient::default().with(url, 200, body));
+        assert_eq!(forge.repo_size("awkless/vim")?, expect);
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::https("https://github.com/awkless/vim.git", Some((ForgeKind::GitHub, "awkless/vim")))]
+    #[case::scp("git@gitlab.com:group/sub/sh.git", Some((ForgeKind::GitLab, "group/sub/sh")))]
+    #[case::ssh("ssh://git@github.com/awkless/dwm", Some((ForgeKind::GitHub, "awkless/dwm")))]
+    #[case::other_host("https://codeberg.org/awkless/vim.git", None)]
+    #[case::no_path("https://github.com/", None)]
+    fn hosted_repo_find_forge_of_url(#[case] url: &str, #[case] expect: Option<(ForgeKind, &str)>) {
+        let expect = expect.map(|(kind, path)| (kind, path.to_string()));
+        assert_eq!(hosted_repo(url), expect);
+    }
+
+    #[rstest]
+    #[case::no_filter(None, None, true)]
+    #[case::topic_match(Some("dotfiles"), None, true)]
+    #[case::topic_mismatch(Some("rust"), None, false)]
+    #[case::pattern_match(None, Some("v*"), true)]
+    #[case::pattern_mismatch(None, Some("dwm*"), false)]
+    fn forge_filter_matches(
+        #[case] topic: Option<&str>,
+        #[case] pattern: Option<&str>,
+        #[case] expect: bool,
+    ) -> Result<()> {
+        let repo = ForgeRepo {
+            name: "vim".into(),
+            clone_url: "url".into(),
+            default_branch: "main".into(),
+            topics: vec!["dotfiles".into()],
+        };
+        let filter = ForgeFilter::new(topic, pattern)?;
+        assert_eq!(filter.matches(&repo), expect);
+        Ok(())
+    }
+
+    #[test]
+    fn forge_filter_new_return_err_bad_pattern() {
+        let result = ForgeFilter::new(None, Some("[bad"));
+        assert!(matches!(result.unwrap_err(), ForgeError::BadPattern { .. }));
+    }
+
+    #[rstest]
+    fn clone_namespace_register_and_clone_matches(repo_dir: Result<FixtureHarness>) -> Result<()> {
+        let mut repo_dir = repo_dir?;
+        let vim = repo_dir.get_repo("remote/vim")?.as_path().display().to_string();
+        let dwm = repo_dir.get_repo("remote/dwm")?.as_path().display().to_string();
+        let client = FakeClient::default().with(
+            "https://api.github.com/users/awkless/repos?per_page=100&page=1",
+            200,
+            formatdoc! {r#"
+                [
+                    {{ "name": "vim", "clone_url": "{vim}", "default_branch": "main" }},
+                    {{ "name": "dwm", "clone_url": "{dwm}", "default_branch": "main" }},
+                    {{ "name": "notes", "clone_url": "{vim}", "default_branch": "main" }}
+                ]
+            "#},
+        );
+        let forge = Forge::new(ForgeKind::GitHub, client);
+        let fixture = repo_dir.get_file("config/repos.toml")?;
+        let mut locator = MockLocator::new();
+        locator.expect_repos_config().return_const(fixture.as_path().into());
+        locator.expect_repos_dir().return_const(repo_dir.as_path().join("data"));
+
+        let ctx =
+            forge_ctx(&["ricer", "clone", "--from-github", "awkless/", "--pattern", "[dv]*"])?;
+        let result = clone_namespace(&forge, &ctx, &locator)?;
+        assert_eq!(result, vec!["vim".to_string()]);
+
+        repo_dir.sync_untracked()?;
+        assert!(repo_dir.get_repo("data/vim").is_ok());
+        let config = ConfigFile::load(RepoConfig, &locator)?;
+        let expect = RepoSettings::new("vim")
+            .branch("main")
+            .remote("origin")
+            .bootstrap(BootstrapSettings::new().clone(vim));
+        assert_eq!(config.get("vim")?, expect);
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::parent_dir("../../.ssh/x")]
+    #[case::nested("a/b")]
+    fn clone_namespace_return_err_bad_repo_name(
+        repo_dir: Result<FixtureHarness>,
+        #[case] name: &str,
+    ) -> Result<()> {
+        let mut repo_dir = repo_dir?;
+        let vim = repo_dir.get_repo("remote/vim")?.as_path().display().to_string();
+        let client = FakeClient::default().with(
+            "https://api.github.com/users/awkless/repos?per_page=100&page=1",
+            200,
+            formatdoc! {r#"
+                [
+                    {{ "name": "vim", "clone_url": "{vim}", "default_branch": "main" }},
+                    {{ "name": "{name}", "clone_url": "{vim}", "default_branch": "main" }}
+                ]
+            "#},
+        );
+        let forge = Forge::new(ForgeKind::GitHub, client);
+        let fixture = repo_dir.get_file("config/repos.toml")?;
+        let mut locator = MockLocator::new();
+        locator.expect_repos_config().return_const(fixture.as_path().into());
+        locator.expect_repos_dir().return_const(repo_dir.as_path().join("data"));
+
+        let ctx = forge_ctx(&["ricer", "clone", "--from-github", "awkless"])?;
+        let result = clone_namespace(&forge, &ctx, &locator);
+        assert!(matches!(result.unwrap_err(), ForgeError::BadRepoName { .. }));
+
+        repo_dir.sync_untracked()?;
+        assert!(repo_dir.get_repo("data/vim").is_err());
+        assert!(!repo_dir.as_path().join("data").exists());
+        let config = ConfigFile::load(RepoConfig, &locator)?;
+        assert!(config.get("vim").is_err());
+        Ok(())
+    }
+}
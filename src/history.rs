@@ -0,0 +1,516 @@
+// SPDX-FileCopyrightText: 2024 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+//! Command history.
+//!
+//! Record every Ricer command into `history.jsonl` of the state directory,
+//! along with the repositories it operated on, whether it succeeded, and how
+//! long it took. Like Git's reflog, the history is append-only, with one JSON
+//! object per line, so external tooling can read it just as easily as
+//! `ricer history` does.
+//!
+//! Command history is opt-in through Ricer's configuration file:
+//!
+//! ```toml
+//! [features.history]
+//! enabled = true
+//! ```
+//!
+//! Entries are recorded through [`HistorySubscriber`] on the event bus.
+
+use crate::{
+    cancel, clock,
+    context::{Context, HistoryContext},
+    json::Json,
+    locate::Locator,
+    ops::{Event, Subscriber, SubscriberError},
+};
+
+use log::{debug, warn};
+use mkdirp::mkdirp;
+use std::{
+    ffi::OsString,
+    fmt,
+    fs::{self, OpenOptions},
+    io::{Error as IoError, ErrorKind, Write},
+    path::PathBuf,
+    str::FromStr,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+/// Name of feature toggle for command history.
+pub const HISTORY_FEATURE: &str = "history";
+
+/// Error types for command history.
+#[derive(Debug, thiserror::Error)]
+pub enum HistoryError {
+    #[error("Failed to make state directory '{path}'")]
+    MakeDirP { source: IoError, path: PathBuf },
+
+    #[error("Failed to append to '{path}'")]
+    FileAppend { source: IoError, path: PathBuf },
+
+    #[error("Failed to read '{path}'")]
+    FileRead { source: IoError, path: PathBuf },
+}
+
+/// How a recorded command ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Success,
+    Failure,
+    Cancelled,
+}
+
+impl FromStr for Outcome {
+    type Err = String;
+
+    fn from_str(outcome: &str) -> Result<Self, Self::Err> {
+        match outcome {
+            "success" => Ok(Outcome::Success),
+            "failure" => Ok(Outcome::Failure),
+            "cancelled" => Ok(Outcome::Cancelled),
+            _ => Err(format!("invalid command outcome '{outcome}'")),
+        }
+    }
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Outcome::Success => write!(f, "success"),
+            Outcome::Failure => write!(f, "failure"),
+            Outcome::Cancelled => write!(f, "cancelled"),
+        }
+    }
+}
+
+/// One recorded command.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HistoryEntry {
+    /// When command started.
+    pub time: SystemTime,
+
+    /// Name of command, or "git" for Git shortcuts.
+    pub command: String,
+
+    /// Command-line arguments after the Ricer binary.
+    pub args: Vec<String>,
+
+    /// Repositories command operated on.
+    pub repos: Vec<String>,
+
+    pub outcome: Outcome,
+    pub duration: Duration,
+}
+
+impl HistoryEntry {
+    pub fn to_json(&self) -> Json {
+        Json::object()
+            .with("time", unix_secs(self.time))
+            .with("command", &self.command)
+            .with("args", self.args.clone())
+            .with("repos", self.repos.clone())
+            .with("outcome", self.outcome.to_string())
+            .with("duration_ms", self.duration.as_millis() as u64)
+    }
+
+    /// Read entry from JSON object, or `None` if it is malformed.
+    pub fn from_json(json: &Json) -> Option<Self> {
+        let strings = |key| -> Option<Vec<String>> {
+            json.get(key)?.as_array()?.iter().map(|s| s.as_str().map(String::from)).collect()
+        };
+
+        Some(Self {
+            time: UNIX_EPOCH + Duration::from_secs(json.get("time")?.as_f64()? as u64),
+            command: json.get("command")?.as_str()?.into(),
+            args: strings("args")?,
+            repos: strings("repos")?,
+            outcome: json.get("outcome")?.as_str()?.parse().ok()?,
+            duration: Duration::from_millis(json.get("duration_ms")?.as_f64()? as u64),
+        })
+    }
+}
+
+impl fmt::Display for HistoryEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let args = self.args.iter().map(|arg| shell_quote(arg)).collect::<Vec<_>>();
+        write!(
+            f,
+            "{} {:<9} {:>7.2}s  ricer {}",
+            utc_timestamp(unix_secs(self.time)),
+            self.outcome.to_string(),
+            self.duration.as_secs_f64(),
+            args.join(" ")
+        )?;
+        if !self.repos.is_empty() {
+            write!(f, "  ({})", self.repos.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Expected path to command history file.
+pub fn history_file(locator: &impl Locator) -> PathBuf {
+    locator.state_dir().join("history.jsonl")
+}
+
+/// Append `entry` to command history.
+///
+/// # Errors
+///
+/// - Return [`HistoryError::MakeDirP`] if state directory cannot be created.
+/// - Return [`HistoryError::FileAppend`] if entry cannot be appended.
+pub fn append(locator: &impl Locator, entry: &HistoryEntry) -> Result<(), HistoryError> {
+    let state_dir = locator.state_dir();
+    mkdirp(state_dir)
+        .map_err(|err| HistoryError::MakeDirP { source: err, path: state_dir.into() })?;
+
+    let path = history_file(locator);
+    debug!("Append '{}' command to '{}'", entry.command, path.display());
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .and_then(|mut file| writeln!(file, "{}", entry.to_json()))
+        .map_err(|err| HistoryError::FileAppend { source: err, path })
+}
+
+/// Load every entry of command history, oldest first.
+///
+/// Malformed lines, e.g., of a command that was killed while appending, are
+/// skipped with a warning.
+///
+/// # Errors
+///
+/// - Return [`HistoryError::FileRead`] if command history exists, but cannot
+///   be read.
+pub fn load(locator: &impl Locator) -> Result<Vec<HistoryEntry>, HistoryError> {
+    let path = history_file(locator);
+    let data = match fs::read_to_string(&path) {
+        Ok(data) => data,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(HistoryError::FileRead { source: err, path }),
+    };
+
+    let mut entries = Vec::new();
+    for (index, line) in data.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+        match line.parse::<Json>().ok().as_ref().and_then(HistoryEntry::from_json) {
+            Some(entry) => entries.push(entry),
+            None => warn!("Skip malformed line {} of '{}'", index + 1, path.display()),
+        }
+    }
+
+    Ok(entries)
+}
+
+/// Query command history with filters of [`HistoryContext`], oldest first.
+///
+/// # Errors
+///
+/// - Return [`HistoryError::FileRead`] if command history exists, but cannot
+///   be read.
+pub fn query(
+    ctx: &HistoryContext,
+    locator: &impl Locator,
+) -> Result<Vec<HistoryEntry>, HistoryError> {
+    let mut entries = load(locator)?
+        .into_iter()
+        .filter(|entry| ctx.command.as_ref().map_or(true, |cmd| &entry.command == cmd))
+        .filter(|entry| ctx.repo.as_ref().map_or(true, |repo| entry.repos.contains(repo)))
+        .filter(|entry| !ctx.failed || entry.outcome != Outcome::Success)
+        .collect::<Vec<_>>();
+
+    if let Some(limit) = ctx.limit {
+        entries.drain(..entries.len().saturating_sub(limit));
+    }
+
+    Ok(entries)
+}
+
+/// Record each command into command history.
+///
+/// Commands that stop through an error never emit
+/// [`Event::CommandFinished`], so they are recorded once the subscriber is
+/// dropped instead.
+///
+/// # Invariants
+///
+/// 1. Each command is recorded at most once.
+/// 1. The `history` command itself is never recorded.
+pub struct HistorySubscriber<'loc, L>
+where
+    L: Locator,
+{
+    locator: &'loc L,
+    args: Vec<String>,
+    running: Option<(HistoryEntry, Instant)>,
+}
+
+impl<'loc, L> HistorySubscriber<'loc, L>
+where
+    L: Locator,
+{
+    pub fn new<I, T>(locator: &'loc L, args: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<OsString>,
+    {
+        let args = args.into_iter().map(|arg| arg.into().to_string_lossy().into_owned()).collect();
+        Self { locator, args, running: None }
+    }
+
+    fn finish(&mut self, outcome: Outcome) -> Result<(), HistoryError> {
+        match self.running.take() {
+            Some((mut entry, start)) => {
+                entry.outcome = outcome;
+                entry.duration = start.elapsed();
+                append(self.locator, &entry)
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+impl<L> Subscriber for HistorySubscriber<'_, L>
+where
+    L: Locator,
+{
+    fn name(&self) -> &str {
+        "history"
+    }
+
+    fn notify(&mut self, event: &Event<'_>) -> Result<(), SubscriberError> {
+        match event {
+            Event::CommandStarted { context: Context::History(_) } => (),
+            Event::CommandStarted { context } => {
+                let command = match context {
+                    Context::Git(_) => "git".into(),
+                    _ => context.to_string(),
+                };
+                let entry = HistoryEntry {
+                    time: clock::now(),
+                    command,
+                    args: self.args.clone(),
+                    repos: Vec::new(),
+                    outcome: Outcome::Failure,
+                    duration: Duration::ZERO,
+                };
+                self.running = Some((entry, Instant::now()));
+            }
+            Event::RepoOperated { repo, .. } => {
+                if let Some((entry, _)) = &mut self.running {
+                    if !entry.repos.iter().any(|name| name == repo) {
+                        entry.repos.push(repo.to_string());
+                    }
+                }
+            }
+            Event::CommandFinished { success, .. } => {
+                let outcome = match success {
+                    true => Outcome::Success,
+                    false => Outcome::Failure,
+                };
+                self.finish(outcome)?;
+            }
+            _ => (),
+        }
+
+        Ok(())
+    }
+}
+
+impl<L> Drop for HistorySubscriber<'_, L>
+where
+    L: Locator,
+{
+    fn drop(&mut self) {
+        let outcome = match cancel::is_cancelled() {
+            true => Outcome::Cancelled,
+            false => Outcome::Failure,
+        };
+        if let Err(err) = self.finish(outcome) {
+            warn!("Failed to record command history: {err}");
+        }
+    }
+}
+
+pub(crate) fn unix_secs(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).map(|time| time.as_secs()).unwrap_or_default()
+}
+
+/// Format seconds since Unix epoch as UTC date and time.
+pub(crate) fn utc_timestamp(secs: u64) -> String {
+    let (days, secs) = (secs / 86400, secs % 86400);
+
+    // INVARIANT: convert days to civil date through eras of 400 years.
+    let days = days as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02} {:02}:{:02}:{:02}",
+        secs / 3600,
+        secs % 3600 / 60,
+        secs % 60
+    )
+}
+
+pub(crate) fn shell_quote(arg: &str) -> String {
+    match arg.is_empty() || arg.contains(|c: char| c.is_whitespace() || "'\"$\\".contains(c)) {
+        true => format!("'{}'", arg.replace('\'', r"'\''")),
+        false => arg.into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        cli::Cli,
+        locate::MockLocator,
+        testenv::{parse_ctx, FixtureHarness},
+    };
+
+    use anyhow::{anyhow, Result};
+    use pretty_assertions::assert_eq;
+    use rstest::{fixture, rstest};
+
+    #[fixture]
+    fn state_dir() -> Result<FixtureHarness> {
+        FixtureHarness::open()
+    }
+
+    fn locator(state_dir: &FixtureHarness) -> MockLocator {
+        let mut locator = MockLocator::new();
+        locator.expect_state_dir().return_const(state_dir.as_path().join("state"));
+        locator
+    }
+
+    fn entry(command: &str, repos: &[&str], outcome: Outcome) -> HistoryEntry {
+        HistoryEntry {
+            time: UNIX_EPOCH + Duration::from_secs(1_700_000_000),
+            command: command.into(),
+            args: vec![command.into()],
+            repos: repos.iter().map(|repo| repo.to_string()).collect(),
+            outcome,
+            duration: Duration::from_millis(1500),
+        }
+    }
+
+    #[rstest]
+    fn history_subscriber_record_each_command(state_dir: Result<FixtureHarness>) -> Result<()> {
+        let state_dir = state_dir?;
+        let locator = locator(&state_dir);
+        let args = ["commit", "-m", "fix vim"];
+        let ctx = Context::from(Cli::parse_args(["ricer"].iter().chain(&args))?);
+        let mut sub = HistorySubscriber::new(&locator, args);
+        sub.notify(&Event::CommandStarted { context: &ctx }).map_err(|err| anyhow!(err))?;
+        sub.notify(&Event::RepoOperated { context: &ctx, repo: "vim" })
+            .map_err(|err| anyhow!(err))?;
+        sub.notify(&Event::RepoOperated { context: &ctx, repo: "vim" })
+            .map_err(|err| anyhow!(err))?;
+        sub.notify(&Event::CommandFinished { context: &ctx, success: true })
+            .map_err(|err| anyhow!(err))?;
+        drop(sub);
+
+        let failed = Context::from(Cli::parse_args(["ricer", "push"])?);
+        let mut sub = HistorySubscriber::new(&locator, ["push"]);
+        sub.notify(&Event::CommandStarted { context: &failed }).map_err(|err| anyhow!(err))?;
+        drop(sub);
+
+        let entries = load(&locator)?;
+        let summary = entries
+            .iter()
+            .map(|entry| (entry.command.as_str(), entry.repos.clone(), entry.outcome))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            vec![
+                ("commit", vec!["vim".to_string()], Outcome::Success),
+                ("push", vec![], Outcome::Failure),
+            ]
+        );
+        assert_eq!(entries[0].args, args);
+        Ok(())
+    }
+
+    #[rstest]
+    fn history_subscriber_skip_history_command(state_dir: Result<FixtureHarness>) -> Result<()> {
+        let state_dir = state_dir?;
+        let locator = locator(&state_dir);
+        let ctx = Context::from(Cli::parse_args(["ricer", "history"])?);
+        let mut sub = HistorySubscriber::new(&locator, ["history"]);
+        sub.notify(&Event::CommandStarted { context: &ctx }).map_err(|err| anyhow!(err))?;
+        sub.notify(&Event::CommandFinished { context: &ctx, success: true })
+            .map_err(|err| anyhow!(err))?;
+        drop(sub);
+
+        assert!(!history_file(&locator).exists());
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::everything(&[], &["commit", "deploy", "push", "deploy"])]
+    #[case::command(&["--command", "deploy"], &["deploy", "deploy"])]
+    #[case::repo(&["--repo", "vim"], &["commit", "deploy"])]
+    #[case::failed(&["--failed"], &["push", "deploy"])]
+    #[case::limit(&["-n", "2"], &["push", "deploy"])]
+    fn query_apply_filters(
+        state_dir: Result<FixtureHarness>,
+        #[case] args: &[&str],
+        #[case] expect: &[&str],
+    ) -> Result<()> {
+        let state_dir = state_dir?;
+        let locator = locator(&state_dir);
+        append(&locator, &entry("commit", &["vim", "dwm"], Outcome::Success))?;
+        append(&locator, &entry("deploy", &["vim"], Outcome::Success))?;
+        append(&locator, &entry("push", &["dwm"], Outcome::Failure))?;
+        append(&locator, &entry("deploy", &["dwm"], Outcome::Cancelled))?;
+
+        let result = query(&parse_ctx::<HistoryContext>("history", args)?, &locator)?;
+        assert_eq!(result.iter().map(|entry| entry.command.as_str()).collect::<Vec<_>>(), expect);
+        Ok(())
+    }
+
+    #[rstest]
+    fn load_skip_malformed_lines(state_dir: Result<FixtureHarness>) -> Result<()> {
+        let state_dir = state_dir?;
+        let locator = locator(&state_dir);
+        append(&locator, &entry("commit", &[], Outcome::Success))?;
+        let mut file = OpenOptions::new().append(true).open(history_file(&locator))?;
+        writeln!(file, "{{\"time\": 17")?;
+        append(&locator, &entry("push", &[], Outcome::Success))?;
+
+        let result = load(&locator)?;
+        assert_eq!(
+            result,
+            vec![entry("commit", &[], Outcome::Success), entry("push", &[], Outcome::Success)]
+        );
+        Ok(())
+    }
+
+    #[rstest]
+    fn history_entry_display() {
+        let mut entry = entry("commit", &["vim", "dwm"], Outcome::Success);
+        entry.args = vec!["commit".into(), "-m".into(), "fix vim's config".into()];
+        assert_eq!(
+            entry.to_string(),
+            "2023-11-14 22:13:20 success      1.50s  ricer commit -m 'fix vim'\\''s config'  (vim, dwm)"
+        );
+    }
+
+    #[rstest]
+    #[case::epoch(0, "1970-01-01 00:00:00")]
+    #[case::leap_day(951_782_400, "2000-02-29 00:00:00")]
+    #[case::end_of_year(1_735_689_599, "2024-12-31 23:59:59")]
+    fn utc_timestamp_return_civil_time(#[case] secs: u64, #[case] expect: &str) {
+        assert_eq!(utc_timestamp(secs), expect);
+    }
+}
@@ -21,29 +21,97 @@
 //! in three ways: _always_ execute the hook no questions asked, _never_ execute
 //! the hook no questions asked, or page the hooks contents and _prompt_ the
 //! user about executing it.
+//!
+//! Hook definitions can also opt-in to sandboxing through `sandbox = true`,
+//! which runs their hook scripts through [`HookSandbox`], and restrict the
+//! environment their hook scripts inherit through [`HookEnv`]. Hook output is
+//! always redacted through [`HookEnv::redact`] before it is logged. Hook
+//! scripts learn what triggered them through [`COMMAND_ENV`],
+//! [`HOOK_KIND_ENV`], [`REPO_ENV`], and [`CONFIG_DIR_ENV`], next to the
+//! variables of the `env` table of their hook definition. Variables passed
+//! through `--context KEY=VALUE` reach hook scripts as `RICER_VAR_<KEY>`, see
+//! [`var_env`].
+//!
+//! Hook definitions run once per command by default. With `scope = "repo"`,
+//! their hook scripts instead run once for every repository the command
+//! operates on, with [`REPO_ENV`] naming one repository per run.
+//!
+//! Hook definitions also run one at a time by default. With `parallel = true`,
+//! their hook scripts execute alongside other parallel hook definitions, after
+//! the hook definitions named by their `depends` setting finished.
+//!
+//! Pre hooks that are not sandboxed can also request changes to the plan of
+//! their command, like skipping a repository, through [`PlanChanges`] written
+//! to the file named by [`PLAN_FILE_ENV`]. Requested changes are validated,
+//! and recorded into the [`HookPlan`] of the hook handler for the command to
+//! apply.
+//!
+//! Every executed hook script is recorded as a [`HookRun`] with its start and
+//! end time, and exit code. Post hooks that are not sandboxed can read runs of
+//! the hooks before them through the JSON file named by [`RUNS_FILE_ENV`].
+//! Through [`CmdHook::keep_logs`], the command line, exit code, and redacted
+//! output of every executed hook script is also kept as a [`HookLog`] in the
+//! data directory, where the oldest logs rotate out once there are too many,
+//! or they take up too much space.
+//!
+//! Through `--dry-run`, hook scripts are recorded as they would run, but never
+//! executed, see [`plan::perform`].
+//!
+//! Hook scripts run through the POSIX shell by default. Hook definitions can
+//! name another program through `interpreter`, e.g., `python3` or `pwsh`,
+//! or leave it to the shebang line of their hook scripts, see
+//! [`HookInterpreter`].
+
+mod env;
+mod handshake;
+mod interpreter;
+mod logs;
+mod record;
+mod sandbox;
+mod schedule;
+
+pub use env::*;
+pub use handshake::*;
+pub use interpreter::*;
+pub use logs::*;
+pub use record::*;
+pub use sandbox::*;
+pub use schedule::*;
 
 use crate::{
-    config::{CmdHookConfig, ConfigFile, ConfigFileError, TomlError},
-    context::{Context, HookAction},
+    clock,
+    config::{
+        CmdHookConfig, ConfigFile, ConfigFileError, HookLogSettings, HookScope, HookSettings,
+        RepoConfig, TomlError,
+    },
+    context::{Context, HookAction, SharedContext},
     locate::Locator,
+    plan::{self, DryRunEffect},
+    ui::{Prompter, TerminalPrompter, UiError},
+    util::{RepoSelector, Workspace, WorkspaceError},
 };
 
-use log::info;
+use log::{debug, info, warn};
 use minus::{
     error::MinusError,
     input::{HashedEventRegister, InputEvent},
     page_all, ExitStrategy, LineNumbers, Pager,
 };
 use run_script::{run_script, ScriptError, ScriptOptions};
-use shellexpand::{full as expand_var, LookupError};
 use std::{
-    env::VarError,
-    fs::read_to_string,
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    ffi::OsStr,
+    fmt,
+    fs::{self, read_to_string, File},
     hash::RandomState,
-    io::Error as IoError,
+    io::{BufRead, BufReader, Error as IoError, ErrorKind, Read},
     path::{Path, PathBuf},
+    process::Stdio,
     sync::atomic::{AtomicBool, Ordering},
-    sync::Arc,
+    sync::{mpsc, Arc},
+    thread,
+    time::SystemTime,
 };
 
 /// Error types for [`CmdHook`].
@@ -52,20 +120,53 @@ pub enum CmdHookError {
     #[error("Failed to load command hook configuration file")]
     LoadConfig { source: ConfigFileError },
 
+    #[error("Failed to load hook settings of repositories")]
+    RepoConfig { source: Box<ConfigFileError> },
+
     #[error("Failed to get command hook data")]
     GetCmdHook { source: TomlError },
 
     #[error("Failed to read hook '{path}'")]
     HookRead { source: IoError, path: PathBuf },
 
-    #[error("Failed to run hook")]
-    RunHook { source: ScriptError },
+    #[error("Hook '{path}' is larger than the limit of {limit} bytes")]
+    HookTooLarge { path: PathBuf, limit: u64 },
+
+    #[error("Hook '{path}' is not valid UTF-8, give it an interpreter to run it")]
+    HookNotUtf8 { path: PathBuf },
+
+    #[error("Failed to copy hook '{path}' for its interpreter")]
+    HookCopy { source: IoError, path: PathBuf },
+
+    #[error("Failed to run {hook}")]
+    RunHook { source: ScriptError, hook: Box<HookId> },
+
+    #[error("{hook} failed with exit code {code}")]
+    HookFailed { hook: Box<HookId>, code: i32, stderr: String },
 
     #[error("Failed to run pager")]
-    HookPager { source: HookPagerError },
+    HookPager { source: UiError },
+
+    #[error("Failed to run sandboxed {hook}")]
+    Sandbox { source: SandboxError, hook: Box<HookId> },
+
+    #[error("Failed to make workspace for plan changes of hooks")]
+    Workspace { source: WorkspaceError },
 
-    #[error("Failed to expand hook work directory path")]
-    ExpandPath { source: LookupError<VarError> },
+    #[error("Failed to read plan changes of {hook} from '{path}'")]
+    PlanRead { source: IoError, hook: Box<HookId>, path: PathBuf },
+
+    #[error("Refused plan changes of {hook}")]
+    Handshake { source: HandshakeError, hook: Box<HookId> },
+
+    #[error("Failed to make workspace for hook runs")]
+    RunsWorkspace { source: WorkspaceError },
+
+    #[error("Failed to write hook runs to '{path}'")]
+    RunsWrite { source: IoError, path: PathBuf },
+
+    #[error("Failed to order hooks by their dependencies")]
+    Schedule { source: ScheduleError },
 }
 
 impl From<ConfigFileError> for CmdHookError {
@@ -80,15 +181,25 @@ fn from(err: TomlError) -> Self {
     }
 }
 
-impl From<ScriptError> for CmdHookError {
-    fn from(err: ScriptError) -> Self {
-        CmdHookError::RunHook { source: err }
+impl From<UiError> for CmdHookError {
+    fn from(err: UiError) -> Self {
+        CmdHookError::HookPager { source: err }
     }
 }
 
-impl From<HookPagerError> for CmdHookError {
-    fn from(err: HookPagerError) -> Self {
-        CmdHookError::HookPager { source: err }
+impl From<ScheduleError> for CmdHookError {
+    fn from(err: ScheduleError) -> Self {
+        CmdHookError::Schedule { source: err }
+    }
+}
+
+impl CmdHookError {
+    /// Tail of standard error output of failed hook, if any.
+    pub fn stderr(&self) -> Option<&str> {
+        match self {
+            CmdHookError::HookFailed { stderr, .. } if !stderr.is_empty() => Some(stderr),
+            _ => None,
+        }
     }
 }
 
@@ -97,6 +208,9 @@ fn from(err: HookPagerError) -> Self {
 pub enum HookPagerError {
     #[error("Minus pager failed because '{source}'")]
     Minus { source: MinusError },
+
+    #[error("Failed to read hook data for pager")]
+    Read { source: IoError },
 }
 
 impl From<MinusError> for HookPagerError {
@@ -117,16 +231,22 @@ fn from(err: MinusError) -> Self {
 /// means that no hook can be executed no questions asked. Always action means
 /// that hooks are executed no questions asked. Finally, prompt action will page
 /// the contents of a hook script for the user to review, and prompt them about
-/// whether or not they want to execute it.
+/// whether or not they want to execute it through a [`Prompter`].
 #[derive(Debug)]
-pub struct CmdHook<'cfg, L>
+pub struct CmdHook<'cfg, L, P = TerminalPrompter>
 where
     L: Locator,
+    P: Prompter,
 {
     context: &'cfg Context,
     locator: &'cfg L,
-    config: ConfigFile<'cfg, CmdHookConfig, L>,
-    pager: HookPager,
+    config: RefCell<ConfigFile<'cfg, CmdHookConfig, L>>,
+    stamp: Cell<Option<ConfigStamp>>,
+    prompter: P,
+    plan: HookPlan,
+    runs: HookRuns,
+    log: bool,
+    logs: Option<HookLogSettings>,
 }
 
 impl<'cfg, L> CmdHook<'cfg, L>
@@ -149,229 +269,1152 @@ impl<'cfg, L> CmdHook<'cfg, L>
     /// - [`ConfigFile`]
     /// - [`Locator`]
     pub fn load(context: &'cfg Context, locator: &'cfg L) -> Result<Self, CmdHookError> {
-        let config = ConfigFile::load(CmdHookConfig, locator)?;
-        Ok(Self { context, locator, config, pager: Default::default() })
+        // INVARIANT: stamp before loading, so edits made while loading count as changes.
+        let stamp = Cell::new(config_stamp(locator.hooks_config()));
+        let config = RefCell::new(ConfigFile::load(CmdHookConfig, locator)?);
+        Ok(Self {
+            context,
+            locator,
+            config,
+            stamp,
+            prompter: TerminalPrompter,
+            plan: HookPlan::new(),
+            runs: HookRuns::new(),
+            log: false,
+            logs: None,
+        })
+    }
+}
+
+impl<'cfg, L, P> CmdHook<'cfg, L, P>
+where
+    L: Locator,
+    P: Prompter,
+{
+    /// Prompt user about hook scripts through `prompter` instead.
+    pub fn with_prompter<Q: Prompter>(self, prompter: Q) -> CmdHook<'cfg, L, Q> {
+        let Self { context, locator, config, stamp, plan, runs, log, logs, .. } = self;
+        CmdHook { context, locator, config, stamp, prompter, plan, runs, log, logs }
+    }
+
+    /// Append every hook run to hook run log of state directory.
+    pub fn log_runs(mut self, log: bool) -> Self {
+        self.log = log;
+        self
+    }
+
+    /// Write output of every executed hook script to hook logs of state
+    /// directory, rotating them according to `settings`, see [`write_log`].
+    pub fn keep_logs(mut self, settings: HookLogSettings) -> Self {
+        self.logs = Some(settings);
+        self
+    }
+
+    /// Get handle to plan changes requested by pre hooks run so far.
+    pub fn plan(&self) -> HookPlan {
+        self.plan.clone()
+    }
+
+    /// Get handle to hook scripts executed so far.
+    pub fn runs(&self) -> HookRuns {
+        self.runs.clone()
     }
 
     /// Run user-defined hooks.
     ///
     /// Run specific hook kind for given command that was selected through
-    /// [`Context`].
+    /// [`Context`]. Only runs hooks that [`CmdHook::resolved_hooks`] reports.
     ///
     /// # Errors
     ///
     /// 1. Return [`CmdHookError::GetCmdHook`] if current command hook
     ///    definition cannot be obtained through hook configuration file.
     /// 2. Return [`CmdHookError::HookRead`] if hook script cannot be read
-    ///    from `hooks/` directory.
+    ///    from `hooks/` directory, [`CmdHookError::HookTooLarge`] if it is
+    ///    larger than [`MAX_HOOK_SIZE`], or [`CmdHookError::HookNotUtf8`] if
+    ///    it runs through the shell, but is not UTF-8.
     /// 3. Return [`CmdHookError::RunHook`] if hook script cannot be executed
     ///    for whatever reason.
-    /// 4. Return [`CmdHookError::HookPager`] if pager cannot page hook script
+    /// 4. Return [`CmdHookError::HookFailed`] if hook script exits with a
+    ///    non-zero code. Remaining hooks are not executed.
+    /// 5. Return [`CmdHookError::HookPager`] if pager cannot page hook script
     ///    and prompt user.
+    /// 6. Return [`CmdHookError::LoadConfig`] if `--reload-hooks` was given,
+    ///    and changed command hook configuration file cannot be reloaded.
+    /// 7. Return [`CmdHookError::Schedule`] if hooks depend on each other in a
+    ///    cycle.
+    ///
+    /// # Invariants
+    ///
+    /// 1. With `--reload-hooks`, a changed command hook configuration file is
+    ///    re-read between hook executions. Hooks then continue from the same
+    ///    position of the reloaded hook list, so hooks that already ran never
+    ///    run again, but edits to later hooks take effect.
+    /// 1. Repository-scoped hooks run once for every repository selected by
+    ///    the target repositories of [`Context`].
+    /// 1. Parallel hooks execute at the same time, up to [`HOOK_JOBS`] at once,
+    ///    in the order given by [`HookSchedule`]. Once a hook fails, no further
+    ///    hook starts, but running hooks are waited for.
     pub fn run_hooks(&self, hook_kind: HookKind) -> Result<(), CmdHookError> {
+        self.run_hooks_for(hook_kind, None)
+    }
+
+    /// Run hooks of `hook_kind`, running repository-scoped hooks once for
+    /// every repository of `repos` instead.
+    ///
+    /// Used for post hooks, so repository-scoped hooks only run for the
+    /// repositories a command actually operated on.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`CmdHook::run_hooks`].
+    pub fn run_hooks_on(&self, hook_kind: HookKind, repos: &[String]) -> Result<(), CmdHookError> {
+        self.run_hooks_for(hook_kind, Some(repos))
+    }
+
+    fn run_hooks_for(
+        &self,
+        hook_kind: HookKind,
+        repos: Option<&[String]>,
+    ) -> Result<(), CmdHookError> {
         // INVARIANT: Git command shortcut cannot execute hooks.
         if matches!(self.context, Context::Git(..)) {
             return Ok(());
         }
 
-        let action = self.get_hook_action();
-        if action == &HookAction::Never {
-            return Ok(());
-        }
+        let cmd = self.context.to_string();
+        let resolve = || -> Result<Vec<ResolvedHook>, CmdHookError> {
+            let hooks = self.resolved_hooks(&cmd)?;
+            Ok(hooks.into_iter().filter(|hook| hook.kind == hook_kind).collect())
+        };
+        let mut selected = None;
+        let mut jobs = self.jobs(resolve()?, repos, &mut selected)?;
+        let mut schedule = schedule_jobs(&jobs)?;
 
-        let cmd_hook = match self.config.get(self.context.to_string()) {
-            Ok(entry) => entry,
-            // INVARIANT: Ricer commands are allowed not to have hooks.
-            Err(ConfigFileError::Toml { source: TomlError::EntryNotFound { .. }, .. }) => {
-                return Ok(())
+        // INVARIANT: scripts of one hook definition share one sandbox.
+        let mut sandboxes = HashMap::new();
+        thread::scope(|scope| {
+            let (sender, receiver) = mpsc::channel();
+            let mut pending = HashMap::new();
+            let mut failure = None;
+            loop {
+                // INVARIANT: prompt about hooks on this thread, so prompts never overlap.
+                while failure.is_none() && pending.len() < HOOK_JOBS {
+                    let Some(job) = schedule.start() else { break };
+                    let HookJob { hook, repos: names, .. } = &jobs[job];
+                    let names = names.iter().map(String::as_str).collect::<Vec<_>>();
+                    match self.prepare(hook, &names, &mut sandboxes) {
+                        Ok(Some((launch, wait))) => {
+                            let sender = sender.clone();
+                            scope.spawn(move || sender.send((job, launch.run())).ok());
+                            pending.insert(job, wait);
+                        }
+                        Ok(None) => schedule.done(job),
+                        Err(err) => failure = Some(err),
+                    }
+                }
+
+                if pending.is_empty() {
+                    break;
+                }
+                let Ok((job, done)) = receiver.recv() else { break };
+                let Some(wait) = pending.remove(&job) else { continue };
+                match self.finish(&jobs[job].hook, wait, done) {
+                    Ok(()) => schedule.done(job),
+                    Err(err) if failure.is_some() => warn!("{err}"),
+                    Err(err) => failure = Some(err),
+                }
+
+                // INVARIANT: only reload between hooks, while no hook is running.
+                if failure.is_none() && pending.is_empty() && self.reload_if_changed()? {
+                    // INVARIANT: hooks that already ran never run again.
+                    let done = (0..jobs.len()).filter(|job| schedule.is_done(*job));
+                    let done = done.map(|job| jobs[job].key()).collect::<Vec<_>>();
+                    jobs = self.jobs(resolve()?, repos, &mut selected)?;
+                    schedule = schedule_jobs(&jobs)?;
+                    for (job, _) in
+                        jobs.iter().enumerate().filter(|(_, job)| done.contains(&job.key()))
+                    {
+                        schedule.done(job);
+                    }
+                }
             }
-            Err(err) => return Err(err.into()),
-        };
 
-        for hook in cmd_hook.hooks {
-            let hook_name = match hook_kind {
-                HookKind::Pre => hook.pre.as_ref(),
-                HookKind::Post => hook.post.as_ref(),
-            };
-            let hook_name = match hook_name {
-                Some(name) => name,
-                None => continue, // Skip this iteration if no hook name is found.
-            };
+            failure.map_or(Ok(()), Err)
+        })
+    }
 
-            let hook_path = self.locator.hooks_dir().join(hook_name);
-            let hook_data = read_to_string(&hook_path)
-                .map_err(|err| CmdHookError::HookRead { source: err, path: hook_path.clone() })?;
-            // INVARIANT: all working directory paths must be shell expanded.
-            let hook_dir = self.expand_workdir(hook.workdir)?;
+    /// Expand `hooks` into hook jobs, running repository-scoped hooks once
+    /// for every repository of `repos`, or `selected` repositories.
+    fn jobs(
+        &self,
+        hooks: Vec<ResolvedHook>,
+        repos: Option<&[String]>,
+        selected: &mut Option<Vec<String>>,
+    ) -> Result<Vec<HookJob>, CmdHookError> {
+        let targets = self.context.target_repos();
+        let mut jobs = Vec::new();
+        for (position, hook) in hooks.into_iter().enumerate() {
+            match hook.scope {
+                HookScope::Command => {
+                    let repos = targets.iter().map(|repo| repo.to_string()).collect();
+                    jobs.push(HookJob { position, hook, repos });
+                }
+                HookScope::Repo => {
+                    // INVARIANT: select repositories at most once per hook kind.
+                    if selected.is_none() {
+                        *selected = Some(match repos {
+                            Some(repos) => repos.to_vec(),
+                            None => self.select_repos()?,
+                        });
+                    }
 
-            if action == &HookAction::Prompt {
-                self.pager.page_and_prompt(hook_path.as_path(), &hook_dir, &hook_data)?;
-                if !self.pager.choice() {
-                    continue; // Skip this iteration if user denied hook script.
+                    for repo in selected.as_deref().unwrap_or_default() {
+                        let action = hook.action.restrict(self.action(&[repo])?);
+                        if action == HookAction::Never {
+                            debug!("Skip {} for '{repo}'", HookId::from(&hook));
+                            continue;
+                        }
+                        let hook = ResolvedHook { action, ..hook.clone() };
+                        jobs.push(HookJob { position, hook, repos: vec![repo.clone()] });
+                    }
                 }
             }
+        }
 
-            let mut hook_opts = ScriptOptions::new();
-            hook_opts.working_directory = hook_dir;
-            let (code, out, err) = run_script!(hook_data, hook_opts)?;
-            info!("({code}) {}\nstdout: {out}\nstderr: {err}", hook_path.display());
+        Ok(jobs)
+    }
+
+    /// Select names of repositories targeted by [`Context`] for
+    /// repository-scoped hooks.
+    ///
+    /// Targets that cannot be selected, e.g., a repository a command is about
+    /// to create, are used as is.
+    fn select_repos(&self) -> Result<Vec<String>, CmdHookError> {
+        let repo_config = |err| CmdHookError::RepoConfig { source: Box::new(err) };
+        let config = ConfigFile::load(RepoConfig, self.locator).map_err(repo_config)?;
+        let targets = self.context.target_repos().into_iter().map(String::from).collect::<Vec<_>>();
+        let selector = RepoSelector::new(&targets).groups(config.groups().map_err(repo_config)?);
+        match selector.select(config.entries().map_err(repo_config)?) {
+            Ok(repos) => Ok(repos.into_iter().map(|repo| repo.name).collect()),
+            Err(err) => {
+                debug!("Run repository hooks on targets as is: {err}");
+                Ok(targets)
+            }
         }
+    }
 
-        Ok(())
+    /// Reload command hook configuration file if `--reload-hooks` was given,
+    /// and the file changed since it was last loaded.
+    ///
+    /// Return whether the file was reloaded.
+    fn reload_if_changed(&self) -> Result<bool, CmdHookError> {
+        if !self.context.shared().is_some_and(|shared| shared.reload_hooks) {
+            return Ok(false);
+        }
+
+        let path = self.locator.hooks_config();
+        let stamp = config_stamp(path);
+        if stamp == self.stamp.get() {
+            return Ok(false);
+        }
+
+        info!("Reload '{}', because it changed", path.display());
+        *self.config.borrow_mut() = ConfigFile::load(CmdHookConfig, self.locator)?;
+        self.stamp.set(stamp);
+        Ok(true)
     }
 
-    /// Perform shell expansion on working directory path.
+    /// Run `on_clone` setup hook `script` of repository `repo`.
+    ///
+    /// Setup hooks belong to a repository rather than a command hook
+    /// definition, so hook filters do not apply to them. However, they still
+    /// follow the hook action of [`Context`] restricted by the `run_hooks`
+    /// setting of `repo`, and `--no-hooks` keeps them from executing.
     ///
-    /// Provides the following forms of expansion:
+    /// # Errors
+    ///
+    /// 1. Return [`CmdHookError::HookRead`] if hook script cannot be read
+    ///    from `hooks/` directory, [`CmdHookError::HookTooLarge`] if it is
+    ///    larger than [`MAX_HOOK_SIZE`], or [`CmdHookError::HookNotUtf8`] if
+    ///    it runs through the shell, but is not UTF-8.
+    /// 2. Return [`CmdHookError::RunHook`] if hook script cannot be executed
+    ///    for whatever reason.
+    /// 3. Return [`CmdHookError::HookFailed`] if hook script exits with a
+    ///    non-zero code.
+    /// 4. Return [`CmdHookError::HookPager`] if pager cannot page hook script
+    ///    and prompt user.
+    /// 5. Return [`CmdHookError::RepoConfig`] if hook setting of `repo` cannot
+    ///    be loaded.
+    pub fn run_setup_hook(
+        &self,
+        repo: &str,
+        script: &str,
+        workdir: Option<&Path>,
+    ) -> Result<(), CmdHookError> {
+        let action = self.action(&[repo])?;
+        if action == HookAction::Never {
+            debug!("Skip setup hook '{script}' of '{repo}'");
+            return Ok(());
+        }
+
+        let hook = ResolvedHook {
+            cmd: self.context.to_string(),
+            index: 0,
+            name: Some(repo.into()),
+            kind: HookKind::Post,
+            script: self.locator.hooks_dir().join(script),
+            workdir: workdir.map(Path::to_path_buf),
+            sandbox: false,
+            env: HookEnv::default(),
+            scope: HookScope::Command,
+            parallel: false,
+            depends: Vec::new(),
+            interpreter: None,
+            action,
+            matched: Vec::new(),
+        };
+        self.execute(&hook, &[repo], &mut Sandboxes::new())
+    }
+
+    /// Get hooks that would run for `cmd` right now.
     ///
-    /// - Tilde expansion, e.g., `~/some/path`.
-    /// - Environment expansion like `$A` or `${B}` or `${C:32}`, e.g.,
-    ///   `$HOME/some/path`.
+    /// Resolves hook definitions of `cmd` against the hook options of
+    /// [`Context`], i.e., `--run-hooks`, `--no-hooks`, `--only-hooks`, and
+    /// `--skip-hooks`, without executing anything. The hook action is further
+    /// restricted by the `run_hooks` setting of every repository [`Context`]
+    /// targets. Hooks are listed in the order they would execute for each
+    /// [`HookKind`].
     ///
     /// # Errors
     ///
-    /// - Return [`CmdHookError::ExpandPath`] if path expansion failed for some reason.
-    fn expand_workdir(&self, workdir: Option<PathBuf>) -> Result<Option<PathBuf>, CmdHookError> {
-        match workdir {
-            Some(workdir) => {
-                let workdir = workdir.to_string_lossy().into_owned();
-                let workdir = expand_var(&workdir)
-                    .map_err(|err| CmdHookError::ExpandPath { source: err })?
-                    .into_owned();
-                Ok(Some(PathBuf::from(workdir)))
-            }
-            None => Ok(None),
-        }
-    }
-
-    fn get_hook_action(&self) -> &HookAction {
-        match self.context {
-            Context::Bootstrap(ctx) => &ctx.shared.run_hook,
-            Context::Clone(ctx) => &ctx.shared.run_hook,
-            Context::Commit(ctx) => &ctx.shared.run_hook,
-            Context::Delete(ctx) => &ctx.shared.run_hook,
-            Context::Enter(ctx) => &ctx.shared.run_hook,
-            Context::Init(ctx) => &ctx.shared.run_hook,
-            Context::List(ctx) => &ctx.shared.run_hook,
-            Context::Pull(ctx) => &ctx.shared.run_hook,
-            Context::Push(ctx) => &ctx.shared.run_hook,
-            Context::Rename(ctx) => &ctx.shared.run_hook,
-            Context::Status(ctx) => &ctx.shared.run_hook,
-
-            // INVARIANT: Git command shortcut cannot use hooks.
-            Context::Git(_) => {
-                unreachable!("This should not happen. Git shortcut cannot use hooks")
-            }
+    /// 1. Return [`CmdHookError::GetCmdHook`] if command hook definition
+    ///    cannot be obtained through hook configuration file.
+    /// 1. Return [`CmdHookError::RepoConfig`] if hook settings of targeted
+    ///    repositories cannot be loaded.
+    pub fn resolved_hooks(&self, cmd: &str) -> Result<Vec<ResolvedHook>, CmdHookError> {
+        let shared = match self.context.shared() {
+            Some(shared) => shared,
+            None => return Ok(Vec::new()),
+        };
+
+        let action = self.action(&self.context.target_repos())?;
+        if action == HookAction::Never {
+            return Ok(Vec::new());
         }
+
+        self.resolve(cmd, Some(shared), action)
     }
-}
 
-/// Hook type to execute.
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub enum HookKind {
-    /// Execute hooks _before_ command.
-    Pre,
+    /// Get every hook defined for `cmd`, regardless of hook options.
+    ///
+    /// # Errors
+    ///
+    /// 1. Return [`CmdHookError::GetCmdHook`] if command hook definition
+    ///    cannot be obtained through hook configuration file.
+    pub fn configured_hooks(&self, cmd: &str) -> Result<Vec<ResolvedHook>, CmdHookError> {
+        let action = self.context.shared().map(|shared| shared.run_hook).unwrap_or_default();
+        self.resolve(cmd, None, action)
+    }
 
-    /// Execute hooks _after_ command.
-    Post,
-}
+    /// Get names of commands that have hook definitions.
+    ///
+    /// # Errors
+    ///
+    /// 1. Return [`CmdHookError::GetCmdHook`] if command hook definitions
+    ///    cannot be obtained through hook configuration file.
+    pub fn commands(&self) -> Result<Vec<String>, CmdHookError> {
+        let entries = self.config.borrow().entries()?;
+        Ok(entries.into_iter().map(|entry| entry.cmd).collect())
+    }
 
-/// Pager for hook scripts.
-///
-/// Basic static pager that shows the current contents of a given hook script,
-/// and prompts the user about whether or not they want to execute it. User
-/// can accept or deny hook script by pressing "a" or "d".
-///
-/// # See also
-///
-/// - [Minus](https://docs.rs/minus/latest/minus/)
-#[derive(Debug, Default)]
-pub struct HookPager {
-    choice: Arc<AtomicBool>,
-}
+    /// Determine hook action for operating on `repos`.
+    ///
+    /// Starts from the `--run-hooks` action of [`Context`], and restricts it
+    /// by the `run_hooks` setting of every registered repository in `repos`.
+    /// Thus, a repository can make hooks more cautious, but never less
+    /// cautious than the user asked for.
+    ///
+    /// # Invariants
+    ///
+    /// 1. The `--no-hooks` kill switch takes precedence over everything.
+    /// 1. Repositories that are not registered do not restrict hooks.
+    fn action(&self, repos: &[&str]) -> Result<HookAction, CmdHookError> {
+        let shared = match self.context.shared() {
+            Some(shared) if !shared.no_hooks => shared,
+            _ => return Ok(HookAction::Never),
+        };
 
-impl HookPager {
-    pub fn new() -> Self {
-        Self { choice: Arc::new(AtomicBool::default()) }
+        let mut action = shared.run_hook;
+        if action == HookAction::Never || repos.is_empty() {
+            return Ok(action);
+        }
+
+        let config = ConfigFile::load(RepoConfig, self.locator)
+            .map_err(|err| CmdHookError::RepoConfig { source: Box::new(err) })?;
+        for repo in repos {
+            let hooks = match config.get(repo) {
+                Ok(settings) => settings.hooks,
+                Err(ConfigFileError::Toml {
+                    source: TomlError::EntryNotFound { .. } | TomlError::TableNotFound { .. },
+                    ..
+                }) => None,
+                Err(err) => return Err(CmdHookError::RepoConfig { source: Box::new(err) }),
+            };
+
+            if let Some(hooks) = hooks {
+                debug!("Restrict hook action to '{hooks}' for '{repo}'");
+                action = action.restrict(hooks);
+            }
+        }
+
+        Ok(action)
     }
 
-    pub fn choice(&self) -> bool {
-        self.choice.load(Ordering::Relaxed)
+    /// Execute `hook` on behalf of a command targeting `repos`, prompting the
+    /// user about it first if need be.
+    ///
+    /// Hook scripts of one hook definition share the same sandbox of
+    /// `sandboxes`.
+    fn execute(
+        &self,
+        hook: &ResolvedHook,
+        repos: &[&str],
+        sandboxes: &mut Sandboxes,
+    ) -> Result<(), CmdHookError> {
+        match self.prepare(hook, repos, sandboxes)? {
+            Some((launch, wait)) => self.finish(hook, wait, launch.run()),
+            None => Ok(()),
+        }
     }
 
-    /// Page hook script and prompt user about running it.
+    /// Prepare `hook` on behalf of a command targeting `repos` for launch,
+    /// prompting the user about it first if need be.
     ///
-    /// # Errors
+    /// Return `None` if the user denied `hook`, or if it was skipped through
+    /// `--dry-run`.
     ///
-    /// - Return [`HookPagerError::Minus`] for any issues encountered with
-    ///   [Minus](https://docs.rs/minus/latest/minus/).
-    pub fn page_and_prompt(
+    /// # Invariants
+    ///
+    /// Variables describing the hook, e.g., [`COMMAND_ENV`], take precedence
+    /// over variables of the same name set by its hook definition.
+    fn prepare(
         &self,
-        file_name: &Path,
-        workdir: &Option<PathBuf>,
-        file_data: &str,
-    ) -> Result<(), HookPagerError> {
-        let pager = Pager::new();
-        let workdir = match workdir {
-            Some(path) => path.clone(),
-            None => PathBuf::from("./"),
+        hook: &ResolvedHook,
+        repos: &[&str],
+        sandboxes: &mut Sandboxes,
+    ) -> Result<Option<(Launch, HookWait)>, CmdHookError> {
+        // INVARIANT: never ask about hooks that would not run anyway.
+        if !plan::perform(|| DryRunEffect::RunHook { script: hook.script.clone() }) {
+            return Ok(None);
+        }
+
+        // INVARIANT: read hook script once, so the data the user reviews is the data that runs.
+        let data = read_script(&hook.script)?;
+        if hook.action == HookAction::Prompt {
+            let workdir = hook.workdir.as_deref();
+            if !self.prompter.review_hook(&hook.script, workdir, &mut data.as_slice())? {
+                return Ok(None); // Skip hook script if user denied it.
+            }
+        }
+
+        let declared = hook.interpreter.as_deref();
+        let interpreter = HookInterpreter::resolve(declared, &String::from_utf8_lossy(&data));
+        let (data, copy) = match interpreter.is_shell() {
+            true => match String::from_utf8(data) {
+                Ok(data) => (data, None),
+                Err(_) => return Err(CmdHookError::HookNotUtf8 { path: hook.script.clone() }),
+            },
+            false => (String::new(), Some(copy_script(self.locator, &hook.script, &data)?)),
         };
+        debug!("Run '{}' through {interpreter}", hook.script.display());
+        let command = format!("{interpreter} {}", hook.script.display());
 
-        pager.set_prompt(format!(
-            "Run '{}' at '{}'? [a]ccept/[d]eny",
-            file_name.display(),
-            workdir.display(),
-        ))?;
-        pager.show_prompt(true)?;
-        pager.set_run_no_overflow(true)?;
-        pager.set_line_numbers(LineNumbers::Enabled)?;
-        pager.push_str(file_data)?;
-        pager.set_input_classifier(self.generate_key_bindings())?;
-        pager.set_exit_strategy(ExitStrategy::PagerQuit)?;
-        page_all(pager)?;
+        let hook_id = || Box::new(HookId::from(hook));
+        // INVARIANT: only pre hooks outside of sandboxes can change the plan of their command.
+        let handshake = match hook.kind == HookKind::Pre && !hook.sandbox {
+            true => {
+                let workspace = Workspace::new(self.locator, "hook-plan")
+                    .map_err(|err| CmdHookError::Workspace { source: err })?;
+                let path = workspace.join("plan");
+                Some((workspace, path))
+            }
+            false => None,
+        };
+        // INVARIANT: only post hooks outside of sandboxes can read runs of hooks before them.
+        let runs_file = match hook.kind == HookKind::Post && !hook.sandbox {
+            true => Some(self.write_runs()?),
+            false => None,
+        };
+
+        let sandbox = match (hook.sandbox, sandboxes.get(&hook.index)) {
+            (false, _) => None,
+            (true, Some(sandbox)) => Some(sandbox.clone()),
+            (true, None) => {
+                let sandbox = HookSandbox::new(SandboxBackend::detect())
+                    .map_err(|err| CmdHookError::Sandbox { source: err, hook: hook_id() })?;
+                let sandbox = Arc::new(sandbox);
+                sandboxes.insert(hook.index, sandbox.clone());
+                Some(sandbox)
+            }
+        };
+
+        let mut env = hook.env.clone();
+        // INVARIANT: `--context` variables take precedence over hook definitions.
+        for (key, value) in
+            self.context.shared().map(|shared| shared.vars.as_slice()).unwrap_or_default()
+        {
+            env = env.set(var_env(key), value);
+        }
+        let env = env
+            .set(COMMAND_ENV, &hook.cmd)
+            .set(HOOK_KIND_ENV, hook.kind.to_string())
+            .set(REPO_ENV, repos.join(" "))
+            .set(CONFIG_DIR_ENV, self.locator.config_dir().to_string_lossy());
+        let mut opts = ScriptOptions::new();
+        opts.working_directory = hook.workdir.clone();
+        let mut env_vars = env.vars().iter().cloned().collect::<HashMap<_, _>>();
+        if let Some((_, path)) = &handshake {
+            env_vars.insert(PLAN_FILE_ENV.into(), path.to_string_lossy().into_owned());
+        }
+        if let Some((_, path)) = &runs_file {
+            env_vars.insert(RUNS_FILE_ENV.into(), path.to_string_lossy().into_owned());
+        }
+        opts.env_vars = Some(env_vars);
+        // INVARIANT: denied variables are unset by name, so their values never show up in the
+        // argument list of the hook process.
+        let denied = hook.env.denied();
+        if !denied.is_empty() && interpreter.is_shell() {
+            let mut args = Vec::new();
+            for name in denied {
+                args.extend(["-u".into(), name.to_string_lossy().into_owned()]);
+            }
+            args.push("sh".into());
+            opts.runner = Some("env".into());
+            opts.runner_args = Some(args);
+        }
+
+        let launch = Launch {
+            hook: hook_id(),
+            data,
+            workdir: hook.workdir.clone(),
+            env: env.clone(),
+            opts,
+            interpreter,
+            copy,
+            sandbox,
+        };
+        Ok(Some((launch, HookWait { env, command, handshake, _runs_file: runs_file })))
+    }
+
+    /// Record and check outcome `done` of launched `hook`.
+    fn finish(
+        &self,
+        hook: &ResolvedHook,
+        wait: HookWait,
+        done: LaunchOutcome,
+    ) -> Result<(), CmdHookError> {
+        let LaunchOutcome { started, finished, result } = done;
+        let code = result.as_ref().ok().map(|(code, ..)| *code);
+        let run = HookRun { finished, ..HookRun::new(hook, started, code) };
+        self.record(run.clone());
+        let (code, out, err) = result?;
+        // INVARIANT: secrets never reach logs or error reports.
+        let (out, err) = (wait.env.redact(&out), wait.env.redact(&err));
+        info!("({code}) {}\nstdout: {out}\nstderr: {err}", hook.script.display());
+        if let Some(settings) = &self.logs {
+            let log = HookLog::new(&run, &wait.command, &out, &err);
+            if let Err(err) = write_log(self.locator, &log, settings) {
+                warn!("Failed to keep output of {}: {err}", run.label());
+            }
+        }
+
+        // INVARIANT: a failing hook stops remaining hooks and its command.
+        if code != 0 {
+            return Err(CmdHookError::HookFailed {
+                hook: Box::new(HookId::from(hook)),
+                code,
+                stderr: stderr_tail(&err),
+            });
+        }
+
+        match wait.handshake {
+            Some((_, path)) => self.request_changes(hook, &path),
+            None => Ok(()),
+        }
+    }
+
+    /// Write runs of hooks executed so far to new workspace, for post hooks to
+    /// read through [`RUNS_FILE_ENV`].
+    fn write_runs(&self) -> Result<(Workspace, PathBuf), CmdHookError> {
+        let workspace = Workspace::new(self.locator, "hook-runs")
+            .map_err(|err| CmdHookError::RunsWorkspace { source: err })?;
+        let path = workspace.join("runs.json");
+        fs::write(&path, self.runs.to_json().to_string())
+            .map_err(|err| CmdHookError::RunsWrite { source: err, path: path.clone() })?;
+        Ok((workspace, path))
+    }
+
+    /// Record `run`, and append it to hook run log if asked to.
+    fn record(&self, run: HookRun) {
+        if self.log {
+            if let Err(err) = append_run(self.locator, &run) {
+                warn!("Failed to record hook run: {err}");
+            }
+        }
+        self.runs.record(run);
+    }
+
+    /// Record plan changes `hook` wrote to `path`, if any.
+    fn request_changes(&self, hook: &ResolvedHook, path: &Path) -> Result<(), CmdHookError> {
+        let hook_id = || Box::new(HookId::from(hook));
+        let data = match read_to_string(path) {
+            Ok(data) => data,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(()),
+            Err(err) => {
+                return Err(CmdHookError::PlanRead {
+                    source: err,
+                    hook: hook_id(),
+                    path: path.into(),
+                })
+            }
+        };
+
+        let changes = data
+            .parse::<PlanChanges>()
+            .map_err(|err| CmdHookError::Handshake { source: err, hook: hook_id() })?;
+        if changes.is_empty() {
+            return Ok(());
+        }
+
+        let repos = ConfigFile::load(RepoConfig, self.locator)
+            .and_then(|config| config.entries())
+            .map_err(|err| CmdHookError::RepoConfig { source: Box::new(err) })?;
+        let names = repos.iter().map(|repo| repo.name.as_str()).collect::<Vec<_>>();
+        changes
+            .check(self.context, &names)
+            .map_err(|err| CmdHookError::Handshake { source: err, hook: hook_id() })?;
 
+        info!("{} requests to {changes}", HookId::from(hook));
+        self.plan.request(changes);
         Ok(())
     }
 
-    fn generate_key_bindings(&self) -> Box<HashedEventRegister<RandomState>> {
-        let mut input = HashedEventRegister::default();
+    /// Resolve hooks of `cmd` with `action`, only keeping those selected by
+    /// `filters`.
+    fn resolve(
+        &self,
+        cmd: &str,
+        filters: Option<&SharedContext>,
+        action: HookAction,
+    ) -> Result<Vec<ResolvedHook>, CmdHookError> {
+        let cmd_hook = match self.config.borrow().get(cmd) {
+            Ok(entry) => entry,
+            // INVARIANT: Ricer commands are allowed not to have hooks.
+            Err(ConfigFileError::Toml {
+                source: TomlError::EntryNotFound { .. } | TomlError::TableNotFound { .. },
+                ..
+            }) => return Ok(Vec::new()),
+            Err(err) => return Err(err.into()),
+        };
 
-        let response = self.choice.clone();
-        input.add_key_events(&["a"], move |_, _| {
-            response.store(true, Ordering::Relaxed);
-            InputEvent::Exit
-        });
+        let (mut pre, mut post) = (Vec::new(), Vec::new());
+        for (index, hook) in cmd_hook.hooks.iter().enumerate() {
+            let matched = match filters {
+                Some(shared) if !is_selected(shared, index, hook) => {
+                    debug!("Skip hook {} of '{cmd}' by hook filter", index + 1);
+                    continue;
+                }
+                Some(shared) => shared
+                    .only_hooks
+                    .iter()
+                    .filter(|selector| hook.is_selected_by(index, selector))
+                    .cloned()
+                    .collect(),
+                None => Vec::new(),
+            };
 
-        let response = self.choice.clone();
-        input.add_key_events(&["d"], move |_, _| {
-            response.store(false, Ordering::Relaxed);
-            InputEvent::Exit
-        });
+            for (kind, scripts, resolved) in
+                [(HookKind::Pre, &hook.pre, &mut pre), (HookKind::Post, &hook.post, &mut post)]
+            {
+                resolved.extend(scripts.iter().map(|script| ResolvedHook {
+                    cmd: cmd.into(),
+                    index,
+                    name: hook.name.clone(),
+                    kind,
+                    script: self.locator.hooks_dir().join(script),
+                    // INVARIANT: working directory path was already expanded by configuration.
+                    workdir: hook.workdir.clone(),
+                    sandbox: hook.sandbox,
+                    env: hook.env.iter().fold(
+                        HookEnv::new(&hook.env_allow, &hook.env_deny),
+                        |env, (key, value)| env.set(key, value),
+                    ),
+                    scope: hook.scope,
+                    parallel: hook.parallel,
+                    depends: hook.depends.clone(),
+                    interpreter: hook.interpreter.clone(),
+                    action,
+                    matched: matched.clone(),
+                }));
+            }
+        }
 
-        Box::new(input)
+        // INVARIANT: all pre hooks come before all post hooks.
+        let mut resolved = pre;
+        resolved.append(&mut post);
+        Ok(resolved)
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::{
-        cli::Cli,
-        context::Context,
-        locate::MockLocator,
-        testenv::{FileKind, FixtureHarness},
-    };
+/// Hook script resolved from command hook configuration.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedHook {
+    /// Command hook is bound to.
+    pub cmd: String,
 
-    use anyhow::Result;
-    use indoc::{formatdoc, indoc};
-    use pretty_assertions::assert_eq;
-    use rstest::{fixture, rstest};
+    /// Position of hook definition in command's hook array, counting from 0.
+    pub index: usize,
 
-    #[fixture]
-    fn config_dir() -> Result<FixtureHarness> {
-        let harness = FixtureHarness::open()?;
-        let root = harness.as_path().to_path_buf();
-        let harness = harness
+    /// Name of hook definition, if any.
+    pub name: Option<String>,
+
+    /// When hook script executes.
+    pub kind: HookKind,
+
+    /// Path to hook script in `hooks/` directory.
+    pub script: PathBuf,
+
+    /// Working directory to execute hook script in.
+    pub workdir: Option<PathBuf>,
+
+    /// Hook script executes through [`HookSandbox`].
+    pub sandbox: bool,
+
+    /// Environment hook script inherits.
+    pub env: HookEnv,
+
+    /// Hook script runs once per command, or once per repository.
+    pub scope: HookScope,
+
+    /// Hook script may run alongside other parallel hook scripts.
+    pub parallel: bool,
+
+    /// Hook definitions or hook scripts to wait for.
+    pub depends: Vec<String>,
+
+    /// Program declared to run hook script through, if any.
+    pub interpreter: Option<String>,
+
+    /// How the user is asked about executing hook script.
+    pub action: HookAction,
+
+    /// `--only-hooks` selectors that matched hook definition.
+    pub matched: Vec<String>,
+}
+
+impl fmt::Display for ResolvedHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} #{}", self.cmd, self.kind, self.index + 1)?;
+        if let Some(name) = &self.name {
+            write!(f, " '{name}'")?;
+        }
+        write!(f, ": {} ({}", self.script.display(), self.action)?;
+        if self.sandbox {
+            write!(f, ", sandboxed")?;
+        }
+        if self.scope == HookScope::Repo {
+            write!(f, ", per repository")?;
+        }
+        if self.parallel {
+            write!(f, ", parallel")?;
+        }
+        if !self.depends.is_empty() {
+            write!(f, ", after {}", self.depends.join(","))?;
+        }
+        if let Some(interpreter) = &self.interpreter {
+            write!(f, ", through {interpreter}")?;
+        }
+        if !self.env.is_empty() {
+            write!(f, ", restricted environment")?;
+        }
+        if let Some(workdir) = &self.workdir {
+            write!(f, ", in '{}'", workdir.display())?;
+        }
+        if !self.matched.is_empty() {
+            write!(f, ", matched {}", self.matched.join(","))?;
+        }
+        write!(f, ")")
+    }
+}
+
+/// Identity of hook script for error reporting.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HookId {
+    pub cmd: String,
+    pub kind: HookKind,
+    pub index: usize,
+    pub name: Option<String>,
+    pub script: PathBuf,
+}
+
+impl From<&ResolvedHook> for HookId {
+    fn from(hook: &ResolvedHook) -> Self {
+        Self {
+            cmd: hook.cmd.clone(),
+            kind: hook.kind,
+            index: hook.index,
+            name: hook.name.clone(),
+            script: hook.script.clone(),
+        }
+    }
+}
+
+impl fmt::Display for HookId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} hook #{}", self.kind, self.index + 1)?;
+        if let Some(name) = &self.name {
+            write!(f, " '{name}'")?;
+        }
+        write!(f, " of '{}' ('{}')", self.cmd, self.script.display())
+    }
+}
+
+/// Hook type to execute.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookKind {
+    /// Execute hooks _before_ command.
+    Pre,
+
+    /// Execute hooks _after_ command.
+    Post,
+}
+
+impl fmt::Display for HookKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HookKind::Pre => write!(f, "pre"),
+            HookKind::Post => write!(f, "post"),
+        }
+    }
+}
+
+/// Sandboxes of hook definitions by their position in the command's hook
+/// array.
+type Sandboxes = HashMap<usize, Arc<HookSandbox>>;
+
+/// Hook script to execute on behalf of `repos`.
+#[derive(Debug, Clone)]
+struct HookJob {
+    /// Position of hook script in resolved hook list.
+    position: usize,
+    hook: ResolvedHook,
+    repos: Vec<String>,
+}
+
+impl HookJob {
+    /// Identity of job that survives reloading hook configuration.
+    fn key(&self) -> (usize, Vec<String>) {
+        (self.position, self.repos.clone())
+    }
+}
+
+fn schedule_jobs(jobs: &[HookJob]) -> Result<HookSchedule, CmdHookError> {
+    Ok(HookSchedule::new(&jobs.iter().map(|job| &job.hook).collect::<Vec<_>>())?)
+}
+
+/// Hook script prepared to run on any thread.
+#[derive(Debug)]
+struct Launch {
+    hook: Box<HookId>,
+
+    /// Hook script data for the shell, empty if hook script runs through an
+    /// interpreter from `copy`.
+    data: String,
+    workdir: Option<PathBuf>,
+    env: HookEnv,
+    opts: ScriptOptions,
+    interpreter: HookInterpreter,
+
+    /// Copy of hook script for interpreters, see [`copy_script`].
+    copy: Option<(Workspace, PathBuf)>,
+    sandbox: Option<Arc<HookSandbox>>,
+}
+
+impl Launch {
+    /// Hook script file handed to interpreters.
+    fn script(&self) -> &Path {
+        self.copy.as_ref().map_or(&self.hook.script, |(_, path)| path)
+    }
+
+    fn run(self) -> LaunchOutcome {
+        let started = clock::now();
+        let workdir = self.workdir.as_deref();
+        let sandbox_err = |err| CmdHookError::Sandbox { source: err, hook: self.hook.clone() };
+        let result = match (&self.sandbox, self.interpreter.is_shell()) {
+            (Some(sandbox), true) => {
+                sandbox.run(&self.data, workdir, &self.env).map_err(sandbox_err)
+            }
+            (Some(sandbox), false) => sandbox
+                .run_with(&self.interpreter, self.script(), workdir, &self.env)
+                .map_err(sandbox_err),
+            (None, true) => run_script!(self.data, self.opts)
+                .map_err(|err| CmdHookError::RunHook { source: err, hook: self.hook.clone() }),
+            (None, false) => self.spawn().map_err(|err| CmdHookError::RunHook {
+                source: ScriptError::IOError(err),
+                hook: self.hook.clone(),
+            }),
+        };
+        LaunchOutcome { started, finished: clock::now(), result }
+    }
+
+    /// Run copy of hook script through its interpreter.
+    fn spawn(&self) -> Result<(i32, String, String), IoError> {
+        let mut cmd = self.interpreter.command(self.script());
+        if let Some(workdir) = &self.workdir {
+            cmd.current_dir(workdir);
+        }
+        for name in self.env.denied() {
+            cmd.env_remove(name);
+        }
+        cmd.envs(self.opts.env_vars.iter().flatten());
+        let output = cmd.stdin(Stdio::inherit()).output()?;
+
+        Ok((
+            output.status.code().unwrap_or(-1),
+            String::from_utf8_lossy(output.stdout.as_slice()).into_owned(),
+            String::from_utf8_lossy(output.stderr.as_slice()).into_owned(),
+        ))
+    }
+}
+
+/// Read hook script at `path` up to [`MAX_HOOK_SIZE`].
+fn read_script(path: &Path) -> Result<Vec<u8>, CmdHookError> {
+    let read_err = |err| CmdHookError::HookRead { source: err, path: path.into() };
+    let file = File::open(path).map_err(read_err)?;
+    let mut data = Vec::new();
+    file.take(MAX_HOOK_SIZE + 1).read_to_end(&mut data).map_err(read_err)?;
+    if data.len() as u64 > MAX_HOOK_SIZE {
+        return Err(CmdHookError::HookTooLarge { path: path.into(), limit: MAX_HOOK_SIZE });
+    }
+    Ok(data)
+}
+
+/// Copy hook script `data` read from `script` into a private workspace, for
+/// interpreters that need a file to run.
+///
+/// # Invariants
+///
+/// 1. Interpreters run the copy, never `script` itself, so edits made to
+///    `script` after it was read never run.
+/// 1. The copy keeps the file name of `script`, but lives in the state
+///    directory, so the hook and configuration directories are never written
+///    to, and copies left behind by killed runs are swept, see [`Workspace`].
+/// 1. The copy is removed once its workspace is dropped.
+fn copy_script(
+    locator: &impl Locator,
+    script: &Path,
+    data: &[u8],
+) -> Result<(Workspace, PathBuf), CmdHookError> {
+    let workspace = Workspace::new(locator, "hook-script")
+        .map_err(|err| CmdHookError::Workspace { source: err })?;
+    let path = workspace.join(script.file_name().unwrap_or(OsStr::new("hook")));
+    fs::write(&path, data)
+        .map_err(|err| CmdHookError::HookCopy { source: err, path: script.into() })?;
+    Ok((workspace, path))
+}
+
+/// Outcome of [`Launch::run`].
+#[derive(Debug)]
+struct LaunchOutcome {
+    started: SystemTime,
+    finished: SystemTime,
+    result: Result<(i32, String, String), CmdHookError>,
+}
+
+/// Parts of launched hook script that stay behind until it finishes.
+#[derive(Debug)]
+struct HookWait {
+    env: HookEnv,
+    command: String,
+    handshake: Option<(Workspace, PathBuf)>,
+    _runs_file: Option<(Workspace, PathBuf)>,
+}
+
+/// Pager for hook scripts.
+///
+/// Basic static pager that shows the current contents of a given hook script,
+/// and prompts the user about whether or not they want to execute it. User
+/// can accept or deny hook script by pressing "a" or "d".
+///
+/// # See also
+///
+/// - [Minus](https://docs.rs/minus/latest/minus/)
+#[derive(Debug, Default)]
+pub struct HookPager {
+    choice: Arc<AtomicBool>,
+}
+
+impl HookPager {
+    pub fn new() -> Self {
+        Self { choice: Arc::new(AtomicBool::default()) }
+    }
+
+    pub fn choice(&self) -> bool {
+        self.choice.load(Ordering::Relaxed)
+    }
+
+    /// Page hook script and prompt user about running it.
+    ///
+    /// Hook script data is read from `file_data` and pushed into the pager in
+    /// chunks of lines, so large hook scripts never need to be held in memory
+    /// as one giant string before paging begins.
+    ///
+    /// # Errors
+    ///
+    /// - Return [`HookPagerError::Minus`] for any issues encountered with
+    ///   [Minus](https://docs.rs/minus/latest/minus/).
+    /// - Return [`HookPagerError::Read`] if `file_data` cannot be read.
+    pub fn page_and_prompt(
+        &self,
+        file_name: &Path,
+        workdir: &Option<PathBuf>,
+        file_data: impl Read,
+    ) -> Result<(), HookPagerError> {
+        let pager = Pager::new();
+        let workdir = match workdir {
+            Some(path) => path.clone(),
+            None => PathBuf::from("./"),
+        };
+
+        pager.set_prompt(format!(
+            "Run '{}' at '{}'? [a]ccept/[d]eny",
+            file_name.display(),
+            workdir.display(),
+        ))?;
+        pager.show_prompt(true)?;
+        pager.set_run_no_overflow(true)?;
+        pager.set_line_numbers(LineNumbers::Enabled)?;
+        push_chunked(&pager, file_data)?;
+        pager.set_input_classifier(self.generate_key_bindings())?;
+        pager.set_exit_strategy(ExitStrategy::PagerQuit)?;
+        page_all(pager)?;
+
+        Ok(())
+    }
+
+    fn generate_key_bindings(&self) -> Box<HashedEventRegister<RandomState>> {
+        let mut input = HashedEventRegister::default();
+
+        let response = self.choice.clone();
+        input.add_key_events(&["a"], move |_, _| {
+            response.store(true, Ordering::Relaxed);
+            InputEvent::Exit
+        });
+
+        let response = self.choice.clone();
+        input.add_key_events(&["d"], move |_, _| {
+            response.store(false, Ordering::Relaxed);
+            InputEvent::Exit
+        });
+
+        Box::new(input)
+    }
+}
+
+/// Size in bytes of hook data to buffer before pushing it into the pager.
+const PAGER_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Push data into pager in chunks of whole lines.
+///
+/// Invalid UTF-8 is replaced, because the pager only needs to display the data.
+fn push_chunked(pager: &Pager, data: impl Read) -> Result<(), HookPagerError> {
+    let mut reader = BufReader::new(data);
+    let mut line = Vec::new();
+    let mut chunk = String::with_capacity(PAGER_CHUNK_SIZE);
+    loop {
+        line.clear();
+        let len = reader
+            .read_until(b'\n', &mut line)
+            .map_err(|err| HookPagerError::Read { source: err })?;
+        if len == 0 {
+            break;
+        }
+
+        chunk.push_str(&String::from_utf8_lossy(&line));
+        if chunk.len() >= PAGER_CHUNK_SIZE {
+            pager.push_str(&chunk)?;
+            chunk.clear();
+        }
+    }
+
+    if !chunk.is_empty() {
+        pager.push_str(&chunk)?;
+    }
+
+    Ok(())
+}
+
+/// Maximum number of standard error lines kept from failed hooks.
+const STDERR_TAIL: usize = 10;
+
+/// Most hook scripts executing at once.
+pub const HOOK_JOBS: usize = 8;
+
+/// Maximum size of hook scripts in bytes.
+///
+/// Hook scripts are read whole before they are reviewed or run, so any
+/// larger script is refused instead.
+pub const MAX_HOOK_SIZE: u64 = 4 * 1024 * 1024;
+
+/// Keep last [`STDERR_TAIL`] lines of `stderr`.
+fn stderr_tail(stderr: &str) -> String {
+    let lines = stderr.trim_end().lines().collect::<Vec<_>>();
+    lines[lines.len().saturating_sub(STDERR_TAIL)..].join("\n")
+}
+
+/// Modification time and size of a configuration file.
+type ConfigStamp = (SystemTime, u64);
+
+/// Stamp of configuration file at `path`, or `None` if it cannot be read.
+fn config_stamp(path: &Path) -> Option<ConfigStamp> {
+    let meta = fs::metadata(path).ok()?;
+    Some((meta.modified().ok()?, meta.len()))
+}
+
+/// Check if hook definition passes `--only-hooks` and `--skip-hooks` filters.
+///
+/// # Invariants
+///
+/// Skipping takes precedence over selecting.
+fn is_selected(shared: &SharedContext, index: usize, hook: &HookSettings) -> bool {
+    let only = shared.only_hooks.is_empty()
+        || shared.only_hooks.iter().any(|selector| hook.is_selected_by(index, selector));
+    let skip = shared.skip_hooks.iter().any(|selector| hook.is_selected_by(index, selector));
+    only && !skip
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        cli::Cli,
+        context::Context,
+        json::Json,
+        locate::MockLocator,
+        testenv::{FileKind, FixtureHarness, ScriptedPrompter},
+    };
+
+    use anyhow::Result;
+    use indoc::{formatdoc, indoc};
+    use pretty_assertions::assert_eq;
+    use rstest::{fixture, rstest};
+
+    #[fixture]
+    fn config_dir() -> Result<FixtureHarness> {
+        let harness = FixtureHarness::open()?;
+        let root = harness.as_path().to_path_buf();
+        let harness = harness
             .with_file("hooks.toml", |fixture| {
                 fixture
                     .with_data(indoc! {r#"
@@ -380,6 +1423,13 @@ fn config_dir() -> Result<FixtureHarness> {
                             { pre = "pre_hook.sh" },
                             { post = "post_hook.sh" },
                         ]
+                        push = [
+                            { pre = ["first_hook.sh", "second_hook.sh"] },
+                        ]
+                        commit = [
+                            { pre = "first_hook.sh" },
+                            { pre = "second_hook.sh", name = "second" },
+                        ]
                     "#})
                     .with_kind(FileKind::Normal)
             })
@@ -403,6 +1453,54 @@ fn config_dir() -> Result<FixtureHarness> {
                     "#, root.display()})
                     .with_kind(FileKind::Script)
             })
+            .with_file("hooks/first_hook.sh", |fixture| {
+                fixture
+                    .with_data(formatdoc! {r#"
+                        #!/bin/sh
+
+                        echo "hello from first hook" >> {}/out.txt
+                        exit 0
+                    "#, root.display()})
+                    .with_kind(FileKind::Script)
+            })
+            .with_file("hooks/second_hook.sh", |fixture| {
+                fixture
+                    .with_data(formatdoc! {r#"
+                        #!/bin/sh
+
+                        echo "hello from second hook" >> {}/out.txt
+                        exit 0
+                    "#, root.display()})
+                    .with_kind(FileKind::Script)
+            })
+            .with_file("hooks/fail_hook.sh", |fixture| {
+                fixture
+                    .with_data(indoc! {r#"
+                        #!/bin/sh
+
+                        echo "first line" >&2
+                        echo "cannot find plug.vim" >&2
+                        exit 3
+                    "#})
+                    .with_kind(FileKind::Script)
+            })
+            .with_file("repos.toml", |fixture| {
+                fixture
+                    .with_data(indoc! {r#"
+                        [repos.work]
+                        branch = "main"
+                        remote = "origin"
+                        workdir_home = false
+                        run_hooks = "never"
+
+                        [repos.dots]
+                        branch = "main"
+                        remote = "origin"
+                        workdir_home = false
+                        run_hooks = "prompt"
+                    "#})
+                    .with_kind(FileKind::Normal)
+            })
             .with_file("bad_hooks.toml", |fixture| {
                 fixture.with_data("should 'fail'").with_kind(FileKind::Normal)
             })
@@ -417,10 +1515,12 @@ fn cmd_hook_load_parses_config_file(config_dir: Result<FixtureHarness>) -> Resul
         let mut locator = MockLocator::new();
         locator.expect_hooks_config().return_const(fixture.as_path().into());
         locator.expect_hooks_dir().return_const(config_dir.as_path().join("hooks"));
+        locator.expect_config_dir().return_const(config_dir.as_path().into());
+        locator.expect_state_dir().return_const(config_dir.as_path().join("state"));
 
-        let ctx = Context::from(Cli::parse_args(["ricer", "--run-hook=always", "bootstrap"])?);
+        let ctx = Context::from(Cli::parse_args(["ricer", "--run-hooks=always", "bootstrap"])?);
         let cmd_hook = CmdHook::load(&ctx, &locator)?;
-        assert_eq!(fixture.as_str(), cmd_hook.config.to_string());
+        assert_eq!(fixture.as_str(), cmd_hook.config.borrow().to_string());
         Ok(())
     }
 
@@ -431,8 +1531,10 @@ fn cmd_hook_load_return_err_config_file(config_dir: Result<FixtureHarness>) -> R
         let mut locator = MockLocator::new();
         locator.expect_hooks_config().return_const(fixture.as_path().into());
         locator.expect_hooks_dir().return_const(config_dir.as_path().join("hooks"));
+        locator.expect_config_dir().return_const(config_dir.as_path().into());
+        locator.expect_state_dir().return_const(config_dir.as_path().join("state"));
 
-        let ctx = Context::from(Cli::parse_args(["ricer", "--run-hook=always", "bootstrap"])?);
+        let ctx = Context::from(Cli::parse_args(["ricer", "--run-hooks=always", "bootstrap"])?);
         let result = CmdHook::load(&ctx, &locator);
         assert!(matches!(result.unwrap_err(), CmdHookError::LoadConfig { .. }));
 
@@ -452,8 +1554,10 @@ fn cmd_hook_run_hooks_execute_pre_and_post_hooks(
         let mut locator = MockLocator::new();
         locator.expect_hooks_config().return_const(fixture.as_path().into());
         locator.expect_hooks_dir().return_const(config_dir.as_path().join("hooks"));
+        locator.expect_config_dir().return_const(config_dir.as_path().into());
+        locator.expect_state_dir().return_const(config_dir.as_path().join("state"));
 
-        let ctx = Context::from(Cli::parse_args(["ricer", "--run-hook=always", "bootstrap"])?);
+        let ctx = Context::from(Cli::parse_args(["ricer", "--run-hooks=always", "bootstrap"])?);
         let cmd_hook = CmdHook::load(&ctx, &locator)?;
         cmd_hook.run_hooks(hook_kind)?;
         config_dir.sync_untracked()?;
@@ -464,42 +1568,987 @@ fn cmd_hook_run_hooks_execute_pre_and_post_hooks(
     }
 
     #[rstest]
-    #[case::pre_hooks(HookKind::Pre)]
-    #[case::post_hooks(HookKind::Post)]
-    fn cmd_hook_run_hooks_ignore_git_shortcut(
+    fn cmd_hook_run_hooks_execute_script_array_in_order(
         config_dir: Result<FixtureHarness>,
-        #[case] hook_kind: HookKind,
     ) -> Result<()> {
-        let config_dir = config_dir?;
+        let mut config_dir = config_dir?;
         let fixture = config_dir.get_file("hooks.toml")?;
         let mut locator = MockLocator::new();
         locator.expect_hooks_config().return_const(fixture.as_path().into());
         locator.expect_hooks_dir().return_const(config_dir.as_path().join("hooks"));
+        locator.expect_config_dir().return_const(config_dir.as_path().into());
+        locator.expect_state_dir().return_const(config_dir.as_path().join("state"));
 
-        let ctx = Context::from(Cli::parse_args(["ricer", "--run-hook=always", "vim", "commit"])?);
+        let ctx = Context::from(Cli::parse_args(["ricer", "--run-hooks=always", "push"])?);
         let cmd_hook = CmdHook::load(&ctx, &locator)?;
-        assert!(cmd_hook.run_hooks(hook_kind).is_ok());
+        cmd_hook.run_hooks(HookKind::Pre)?;
+        config_dir.sync_untracked()?;
+        let result = config_dir.get_file("out.txt")?;
+        assert_eq!(result.as_str(), "hello from first hook\nhello from second hook\n");
 
         Ok(())
     }
 
     #[rstest]
-    #[case::pre_hooks(HookKind::Pre)]
-    #[case::post_hooks(HookKind::Post)]
-    fn cmd_hook_run_hooks_ignore_no_entry_for_cmd(
+    #[case::only_by_name(["--only-hooks", "second"], "hello from second hook\n")]
+    #[case::only_by_file_stem(["--only-hooks", "first_hook"], "hello from first hook\n")]
+    #[case::only_by_position(["--only-hooks", "2"], "hello from second hook\n")]
+    #[case::skip_by_file_name(["--skip-hooks", "first_hook.sh"], "hello from second hook\n")]
+    #[case::skip_over_only(["--only-hooks=1,2", "--skip-hooks=second"], "hello from first hook\n")]
+    fn cmd_hook_run_hooks_apply_hook_filters<const N: usize>(
         config_dir: Result<FixtureHarness>,
-        #[case] hook_kind: HookKind,
+        #[case] filters: [&str; N],
+        #[case] expect: &str,
     ) -> Result<()> {
-        let config_dir = config_dir?;
+        let mut config_dir = config_dir?;
         let fixture = config_dir.get_file("hooks.toml")?;
         let mut locator = MockLocator::new();
         locator.expect_hooks_config().return_const(fixture.as_path().into());
         locator.expect_hooks_dir().return_const(config_dir.as_path().join("hooks"));
+        locator.expect_config_dir().return_const(config_dir.as_path().into());
+        locator.expect_state_dir().return_const(config_dir.as_path().join("state"));
 
-        let ctx = Context::from(Cli::parse_args(["ricer", "--run-hook=always", "commit"])?);
+        let args = ["ricer", "--run-hooks=always"].into_iter().chain(filters).chain(["commit"]);
+        let ctx = Context::from(Cli::parse_args(args)?);
         let cmd_hook = CmdHook::load(&ctx, &locator)?;
-        assert!(cmd_hook.run_hooks(hook_kind).is_ok());
+        cmd_hook.run_hooks(HookKind::Pre)?;
+        config_dir.sync_untracked()?;
+        let result = config_dir.get_file("out.txt")?;
+        assert_eq!(result.as_str(), expect);
 
         Ok(())
     }
+
+    #[rstest]
+    #[case::pre_hooks(HookKind::Pre)]
+    #[case::post_hooks(HookKind::Post)]
+    fn cmd_hook_run_hooks_no_hooks_takes_precedence(
+        config_dir: Result<FixtureHarness>,
+        #[case] hook_kind: HookKind,
+    ) -> Result<()> {
+        let mut config_dir = config_dir?;
+        let fixture = config_dir.get_file("hooks.toml")?;
+        let mut locator = MockLocator::new();
+        locator.expect_hooks_config().return_const(fixture.as_path().into());
+        locator.expect_hooks_dir().return_const(config_dir.as_path().join("hooks"));
+        locator.expect_config_dir().return_const(config_dir.as_path().into());
+        locator.expect_state_dir().return_const(config_dir.as_path().join("state"));
+
+        let ctx = Context::from(Cli::parse_args([
+            "ricer",
+            "--run-hooks=always",
+            "--no-hooks",
+            "bootstrap",
+        ])?);
+        let cmd_hook = CmdHook::load(&ctx, &locator)?;
+        cmd_hook.run_hooks(hook_kind)?;
+        config_dir.sync_untracked()?;
+        assert!(config_dir.get_file("out.txt").is_err());
+
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::accept_first(["a", "d"], "hello from first hook\n")]
+    #[case::accept_second(["d", "a"], "hello from second hook\n")]
+    fn cmd_hook_run_hooks_prompt_for_each_script(
+        config_dir: Result<FixtureHarness>,
+        #[case] answers: [&str; 2],
+        #[case] expect: &str,
+    ) -> Result<()> {
+        let mut config_dir = config_dir?;
+        let fixture = config_dir.get_file("hooks.toml")?;
+        let mut locator = MockLocator::new();
+        locator.expect_hooks_config().return_const(fixture.as_path().into());
+        locator.expect_hooks_dir().return_const(config_dir.as_path().join("hooks"));
+        locator.expect_config_dir().return_const(config_dir.as_path().into());
+        locator.expect_state_dir().return_const(config_dir.as_path().join("state"));
+
+        let ctx = Context::from(Cli::parse_args(["ricer", "--run-hooks=prompt", "commit"])?);
+        let cmd_hook = CmdHook::load(&ctx, &locator)?.with_prompter(ScriptedPrompter::new(answers));
+        cmd_hook.run_hooks(HookKind::Pre)?;
+        config_dir.sync_untracked()?;
+        let result = config_dir.get_file("out.txt")?;
+        assert_eq!(result.as_str(), expect);
+        let hooks = config_dir.as_path().join("hooks");
+        let expect = ["first_hook.sh", "second_hook.sh"]
+            .map(|script| format!("Run '{}'?", hooks.join(script).display()));
+        assert_eq!(cmd_hook.prompter.questions(), expect);
+
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::pre_hooks(HookKind::Pre)]
+    #[case::post_hooks(HookKind::Post)]
+    fn cmd_hook_run_hooks_ignore_git_shortcut(
+        config_dir: Result<FixtureHarness>,
+        #[case] hook_kind: HookKind,
+    ) -> Result<()> {
+        let config_dir = config_dir?;
+        let fixture = config_dir.get_file("hooks.toml")?;
+        let mut locator = MockLocator::new();
+        locator.expect_hooks_config().return_const(fixture.as_path().into());
+        locator.expect_hooks_dir().return_const(config_dir.as_path().join("hooks"));
+        locator.expect_config_dir().return_const(config_dir.as_path().into());
+        locator.expect_state_dir().return_const(config_dir.as_path().join("state"));
+
+        let ctx = Context::from(Cli::parse_args(["ricer", "--run-hooks=always", "vim", "commit"])?);
+        let cmd_hook = CmdHook::load(&ctx, &locator)?;
+        assert!(cmd_hook.run_hooks(hook_kind).is_ok());
+
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::pre_hooks(HookKind::Pre)]
+    #[case::post_hooks(HookKind::Post)]
+    fn cmd_hook_run_hooks_ignore_no_entry_for_cmd(
+        config_dir: Result<FixtureHarness>,
+        #[case] hook_kind: HookKind,
+    ) -> Result<()> {
+        let config_dir = config_dir?;
+        let fixture = config_dir.get_file("hooks.toml")?;
+        let mut locator = MockLocator::new();
+        locator.expect_hooks_config().return_const(fixture.as_path().into());
+        locator.expect_hooks_dir().return_const(config_dir.as_path().join("hooks"));
+        locator.expect_config_dir().return_const(config_dir.as_path().into());
+        locator.expect_state_dir().return_const(config_dir.as_path().join("state"));
+
+        let ctx = Context::from(Cli::parse_args(["ricer", "--run-hooks=always", "commit"])?);
+        let cmd_hook = CmdHook::load(&ctx, &locator)?;
+        assert!(cmd_hook.run_hooks(hook_kind).is_ok());
+
+        Ok(())
+    }
+
+    #[rstest]
+    fn cmd_hook_run_hooks_restrict_env_and_redact_output() -> Result<()> {
+        let harness = FixtureHarness::open()?;
+        let root = harness.as_path().to_path_buf();
+        let harness = harness
+            .with_file("hooks.toml", |fixture| {
+                fixture
+                    .with_data(indoc! {r#"
+                        [hooks]
+                        status = [
+                            { pre = "env_hook.sh", env_deny = "RICER_HOOK_DENIED" },
+                        ]
+                    "#})
+                    .with_kind(FileKind::Normal)
+            })
+            .with_file("hooks/env_hook.sh", |fixture| {
+                fixture
+                    .with_data(formatdoc! {r#"
+                        #!/bin/sh
+
+                        echo "${{RICER_HOOK_DENIED-unset}}" > {}/out.txt
+                        echo "pushing with $RICER_HOOK_TOKEN" >&2
+                        exit 1
+                    "#, root.display()})
+                    .with_kind(FileKind::Script)
+            })
+            .setup()?;
+        std::env::set_var("RICER_HOOK_DENIED", "visible");
+        std::env::set_var("RICER_HOOK_TOKEN", "ghp_hunter2");
+        let mut locator = MockLocator::new();
+        locator.expect_hooks_config().return_const(harness.as_path().join("hooks.toml"));
+        locator.expect_hooks_dir().return_const(harness.as_path().join("hooks"));
+        locator.expect_config_dir().return_const(harness.as_path().into());
+        locator.expect_state_dir().return_const(harness.as_path().join("state"));
+
+        let ctx = Context::from(Cli::parse_args(["ricer", "--run-hooks=always", "status"])?);
+        let cmd_hook = CmdHook::load(&ctx, &locator)?;
+        let err = cmd_hook.run_hooks(HookKind::Pre).unwrap_err();
+        assert_eq!(err.stderr(), Some("pushing with [REDACTED]"));
+        assert_eq!(std::fs::read_to_string(root.join("out.txt"))?, "unset\n");
+
+        Ok(())
+    }
+
+    #[rstest]
+    fn cmd_hook_run_hooks_set_hook_env() -> Result<()> {
+        let harness = FixtureHarness::open()?;
+        let root = harness.as_path().to_path_buf();
+        let harness = harness
+            .with_file("hooks.toml", |fixture| {
+                fixture
+                    .with_data(indoc! {r#"
+                        [hooks]
+                        status = [
+                            { pre = "env_hook.sh", env = { TARGET = "laptop", RICER_COMMAND = "x" } },
+                        ]
+                    "#})
+                    .with_kind(FileKind::Normal)
+            })
+            .with_file("hooks/env_hook.sh", |fixture| {
+                fixture
+                    .with_data(formatdoc! {r#"
+                        #!/bin/sh
+
+                        echo "$RICER_COMMAND $RICER_HOOK_KIND $RICER_REPO $TARGET" > {0}/out.txt
+                        echo "$RICER_CONFIG_DIR" >> {0}/out.txt
+                    "#, root.display()})
+                    .with_kind(FileKind::Script)
+            })
+            .setup()?;
+        let mut locator = MockLocator::new();
+        locator.expect_hooks_config().return_const(harness.as_path().join("hooks.toml"));
+        locator.expect_hooks_dir().return_const(harness.as_path().join("hooks"));
+        locator.expect_config_dir().return_const(harness.as_path().into());
+        locator.expect_state_dir().return_const(harness.as_path().join("state"));
+        locator.expect_repos_config().return_const(harness.as_path().join("repos.toml"));
+
+        let args = ["ricer", "--run-hooks=always", "status", "vim", "dwm"];
+        let ctx = Context::from(Cli::parse_args(args)?);
+        CmdHook::load(&ctx, &locator)?.run_hooks(HookKind::Pre)?;
+        let expect = format!("status pre vim dwm laptop\n{}\n", root.display());
+        assert_eq!(std::fs::read_to_string(root.join("out.txt"))?, expect);
+
+        Ok(())
+    }
+
+    #[rstest]
+    fn cmd_hook_run_hooks_pass_context_vars() -> Result<()> {
+        let harness = FixtureHarness::open()?;
+        let root = harness.as_path().to_path_buf();
+        let harness = harness
+            .with_file("hooks.toml", |fixture| {
+                fixture
+                    .with_data(indoc! {r#"
+                        [hooks]
+                        status = [{ pre = "env_hook.sh", env = { RICER_VAR_THEME = "gruvbox" } }]
+                    "#})
+                    .with_kind(FileKind::Normal)
+            })
+            .with_file("hooks/env_hook.sh", |fixture| {
+                fixture
+                    .with_data(formatdoc! {r#"
+                        #!/bin/sh
+
+                        echo "$RICER_VAR_THEME $RICER_VAR_FONT_SIZE" > {}/out.txt
+                    "#, root.display()})
+                    .with_kind(FileKind::Script)
+            })
+            .setup()?;
+        let mut locator = MockLocator::new();
+        locator.expect_hooks_config().return_const(harness.as_path().join("hooks.toml"));
+        locator.expect_hooks_dir().return_const(harness.as_path().join("hooks"));
+        locator.expect_config_dir().return_const(harness.as_path().into());
+        locator.expect_state_dir().return_const(harness.as_path().join("state"));
+
+        let args = [
+            "ricer",
+            "--run-hooks=always",
+            "--context",
+            "theme=nord",
+            "--context",
+            "font-size=12",
+            "status",
+        ];
+        let ctx = Context::from(Cli::parse_args(args)?);
+        CmdHook::load(&ctx, &locator)?.run_hooks(HookKind::Pre)?;
+        assert_eq!(std::fs::read_to_string(root.join("out.txt"))?, "nord 12\n");
+
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::declared(r#", interpreter = "bash""#, "")]
+    #[case::shebang("", "#!/usr/bin/env bash\n")]
+    fn cmd_hook_run_hooks_run_through_interpreter(
+        #[case] setting: &str,
+        #[case] shebang: &str,
+    ) -> Result<()> {
+        let harness = FixtureHarness::open()?;
+        let root = harness.as_path().to_path_buf();
+        let harness = harness
+            .with_file("hooks.toml", |fixture| {
+                fixture
+                    .with_data(formatdoc! {r#"
+                        [hooks]
+                        status = [{{ pre = "bash_hook", env_deny = "RICER_HOOK_SHELL_DENIED"{setting} }}]
+                    "#})
+                    .with_kind(FileKind::Normal)
+            })
+            .with_file("hooks/bash_hook", |fixture| {
+                fixture
+                    .with_data(formatdoc! {r#"
+                        {shebang}[[ -n "$BASH_VERSION" ]] && echo "bash $(basename "$0")" > {0}/out.txt
+                        echo "${{RICER_HOOK_SHELL_DENIED-unset}} $RICER_COMMAND" >> {0}/out.txt
+                    "#, root.display()})
+                    .with_kind(FileKind::Script)
+            })
+            .setup()?;
+        std::env::set_var("RICER_HOOK_SHELL_DENIED", "visible");
+        let mut locator = MockLocator::new();
+        locator.expect_hooks_config().return_const(harness.as_path().join("hooks.toml"));
+        locator.expect_hooks_dir().return_const(harness.as_path().join("hooks"));
+        locator.expect_config_dir().return_const(harness.as_path().into());
+        locator.expect_state_dir().return_const(harness.as_path().join("state"));
+
+        let ctx = Context::from(Cli::parse_args(["ricer", "--run-hooks=always", "status"])?);
+        CmdHook::load(&ctx, &locator)?.run_hooks(HookKind::Pre)?;
+        let out = std::fs::read_to_string(root.join("out.txt"))?;
+        assert_eq!(out, "bash bash_hook\nunset status\n");
+
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::too_large("", vec![b'#'; MAX_HOOK_SIZE as usize + 1])]
+    #[case::not_utf8("", b"echo \xff\n".to_vec())]
+    #[case::not_utf8_interpreter(r#", interpreter = "sh -c true""#, b"echo \xff\n".to_vec())]
+    fn cmd_hook_run_hooks_check_script_data(
+        #[case] setting: &str,
+        #[case] data: Vec<u8>,
+    ) -> Result<()> {
+        let harness = FixtureHarness::open()?
+            .with_file("hooks.toml", |fixture| {
+                fixture
+                    .with_data(formatdoc! {r#"
+                        [hooks]
+                        status = [{{ pre = "hook.sh"{setting} }}]
+                    "#})
+                    .with_kind(FileKind::Normal)
+            })
+            .setup()?;
+        fs::create_dir_all(harness.as_path().join("hooks"))?;
+        fs::write(harness.as_path().join("hooks/hook.sh"), &data)?;
+        let locator = policy_locator(&harness);
+
+        let ctx = Context::from(Cli::parse_args(["ricer", "--run-hooks=always", "status"])?);
+        let result = CmdHook::load(&ctx, &locator)?.run_hooks(HookKind::Pre);
+        match (data.len() as u64 > MAX_HOOK_SIZE, setting.is_empty()) {
+            (true, _) => assert!(matches!(result, Err(CmdHookError::HookTooLarge { .. }))),
+            (false, true) => assert!(matches!(result, Err(CmdHookError::HookNotUtf8 { .. }))),
+            (false, false) => assert!(result.is_ok()),
+        }
+        Ok(())
+    }
+
+    /// Prompter that approves every hook script, but rewrites it right after
+    /// it was reviewed.
+    struct TamperPrompter {
+        reviewed: RefCell<String>,
+    }
+
+    impl Prompter for TamperPrompter {
+        fn ask(&self, _: &str) -> Result<Option<String>, UiError> {
+            Ok(None)
+        }
+
+        fn show_diff(&self, _: &str) -> Result<(), UiError> {
+            Ok(())
+        }
+
+        fn show_list(&self, _: &str, _: &[String]) -> Result<(), UiError> {
+            Ok(())
+        }
+
+        fn review_hook(
+            &self,
+            script: &Path,
+            _: Option<&Path>,
+            data: &mut dyn Read,
+        ) -> Result<bool, UiError> {
+            data.read_to_string(&mut self.reviewed.borrow_mut()).unwrap();
+            let tampered = self.reviewed.borrow().replace("reviewed", "tampered");
+            fs::write(script, tampered).unwrap();
+            Ok(true)
+        }
+    }
+
+    #[rstest]
+    #[case::shell("")]
+    #[case::interpreter(r#", interpreter = "bash""#)]
+    fn cmd_hook_run_hooks_run_reviewed_data(#[case] setting: &str) -> Result<()> {
+        let harness = FixtureHarness::open()?;
+        let root = harness.as_path().to_path_buf();
+        let script = format!("echo reviewed > {}/out.txt\n", root.display());
+        let harness = harness
+            .with_file("hooks.toml", |fixture| {
+                fixture
+                    .with_data(formatdoc! {r#"
+                        [hooks]
+                        status = [{{ pre = "hook.sh"{setting} }}]
+                    "#})
+                    .with_kind(FileKind::Normal)
+            })
+            .with_file("hooks/hook.sh", |fixture| {
+                fixture.with_data(script.as_str()).with_kind(FileKind::Script)
+            })
+            .setup()?;
+        let mut locator = MockLocator::new();
+        locator.expect_hooks_config().return_const(harness.as_path().join("hooks.toml"));
+        locator.expect_hooks_dir().return_const(harness.as_path().join("hooks"));
+        locator.expect_config_dir().return_const(harness.as_path().into());
+        locator.expect_state_dir().return_const(harness.as_path().join("state"));
+
+        let ctx = Context::from(Cli::parse_args(["ricer", "--run-hooks=prompt", "status"])?);
+        let prompter = TamperPrompter { reviewed: RefCell::new(String::new()) };
+        let cmd_hook = CmdHook::load(&ctx, &locator)?.with_prompter(prompter);
+        cmd_hook.run_hooks(HookKind::Pre)?;
+        assert_eq!(cmd_hook.prompter.reviewed.borrow().as_str(), script);
+        assert_eq!(fs::read_to_string(root.join("out.txt"))?, "reviewed\n");
+        assert_eq!(fs::read_dir(root.join("hooks"))?.count(), 1);
+        let workspaces = root.join("state/workspaces");
+        let leftovers = fs::read_dir(&workspaces).map_or(0, |entries| entries.count());
+        assert_eq!(leftovers, 0, "copy of hook script was not removed");
+
+        Ok(())
+    }
+
+    #[rstest]
+    fn cmd_hook_run_hooks_run_repo_hooks_per_repo() -> Result<()> {
+        let harness = FixtureHarness::open()?;
+        let root = harness.as_path().to_path_buf();
+        let harness = harness
+            .with_file("hooks.toml", |fixture| {
+                fixture
+                    .with_data(indoc! {r#"
+                        [hooks]
+                        status = [
+                            { pre = "cmd_hook.sh" },
+                            { pre = "repo_hook.sh", post = "repo_hook.sh", scope = "repo" },
+                        ]
+                    "#})
+                    .with_kind(FileKind::Normal)
+            })
+            .with_file("repos.toml", |fixture| {
+                fixture
+                    .with_data(indoc! {r#"
+                        [repos.vim]
+                        branch = "main"
+                        remote = "origin"
+                        workdir_home = false
+
+                        [repos.dwm]
+                        branch = "main"
+                        remote = "origin"
+                        workdir_home = false
+
+                        [repos.st]
+                        branch = "main"
+                        remote = "origin"
+                        workdir_home = false
+                        hooks = "never"
+                    "#})
+                    .with_kind(FileKind::Normal)
+            })
+            .with_file("hooks/cmd_hook.sh", |fixture| {
+                fixture
+                    .with_data(formatdoc! {r#"
+                        #!/bin/sh
+
+                        echo "command [$RICER_REPO]" >> {}/out.txt
+                    "#, root.display()})
+                    .with_kind(FileKind::Script)
+            })
+            .with_file("hooks/repo_hook.sh", |fixture| {
+                fixture
+                    .with_data(formatdoc! {r#"
+                        #!/bin/sh
+
+                        echo "$RICER_HOOK_KIND [$RICER_REPO]" >> {}/out.txt
+                    "#, root.display()})
+                    .with_kind(FileKind::Script)
+            })
+            .setup()?;
+        let mut locator = MockLocator::new();
+        locator.expect_hooks_config().return_const(harness.as_path().join("hooks.toml"));
+        locator.expect_hooks_dir().return_const(harness.as_path().join("hooks"));
+        locator.expect_config_dir().return_const(harness.as_path().into());
+        locator.expect_state_dir().return_const(harness.as_path().join("state"));
+        locator.expect_repos_config().return_const(harness.as_path().join("repos.toml"));
+
+        let ctx = Context::from(Cli::parse_args(["ricer", "--run-hooks=always", "status"])?);
+        let cmd_hook = CmdHook::load(&ctx, &locator)?;
+        cmd_hook.run_hooks(HookKind::Pre)?;
+        cmd_hook.run_hooks_on(HookKind::Post, &["dwm".into()])?;
+        let expect = indoc! {"
+            command []
+            pre [vim]
+            pre [dwm]
+            post [dwm]
+        "};
+        assert_eq!(std::fs::read_to_string(root.join("out.txt"))?, expect);
+
+        Ok(())
+    }
+
+    #[rstest]
+    fn cmd_hook_run_hooks_run_parallel_hooks_at_once() -> Result<()> {
+        let harness = FixtureHarness::open()?;
+        let root = harness.as_path().to_path_buf();
+        let harness = harness
+            .with_file("hooks.toml", |fixture| {
+                fixture
+                    .with_data(indoc! {r#"
+                        [hooks]
+                        status = [
+                            { pre = "last.sh", parallel = true, depends = ["left", "right"] },
+                            { pre = "meet.sh", parallel = true, name = "left", env = { SELF = "left", PEER = "right" } },
+                            { pre = "meet.sh", parallel = true, name = "right", env = { SELF = "right", PEER = "left" } },
+                        ]
+                    "#})
+                    .with_kind(FileKind::Normal)
+            })
+            .with_file("hooks/meet.sh", |fixture| {
+                fixture
+                    .with_data(formatdoc! {r#"
+                        #!/bin/sh
+
+                        touch {0}/$SELF
+                        tries=0
+                        while [ ! -f {0}/$PEER ]; do
+                            tries=$((tries + 1))
+                            [ $tries -gt 100 ] && exit 1
+                            sleep 0.05
+                        done
+                        echo met >> {0}/out.txt
+                    "#, root.display()})
+                    .with_kind(FileKind::Script)
+            })
+            .with_file("hooks/last.sh", |fixture| {
+                fixture
+                    .with_data(formatdoc! {r#"
+                        #!/bin/sh
+
+                        echo last >> {}/out.txt
+                    "#, root.display()})
+                    .with_kind(FileKind::Script)
+            })
+            .setup()?;
+        let mut locator = MockLocator::new();
+        locator.expect_hooks_config().return_const(harness.as_path().join("hooks.toml"));
+        locator.expect_hooks_dir().return_const(harness.as_path().join("hooks"));
+        locator.expect_config_dir().return_const(harness.as_path().into());
+        locator.expect_state_dir().return_const(harness.as_path().join("state"));
+        locator.expect_repos_config().return_const(harness.as_path().join("repos.toml"));
+
+        let ctx = Context::from(Cli::parse_args(["ricer", "--run-hooks=always", "status"])?);
+        let cmd_hook = CmdHook::load(&ctx, &locator)?;
+        cmd_hook.run_hooks(HookKind::Pre)?;
+        assert_eq!(std::fs::read_to_string(root.join("out.txt"))?, "met\nmet\nlast\n");
+        assert_eq!(cmd_hook.runs().runs().len(), 3);
+
+        Ok(())
+    }
+
+    fn policy_harness(plan: &str) -> Result<FixtureHarness> {
+        let harness = FixtureHarness::open()?
+            .with_file("hooks.toml", |fixture| {
+                fixture
+                    .with_data(indoc! {r#"
+                        [hooks]
+                        commit = [{ pre = "policy.sh" }]
+                        pull = [{ pre = "policy.sh" }]
+                    "#})
+                    .with_kind(FileKind::Normal)
+            })
+            .with_file("repos.toml", |fixture| {
+                fixture
+                    .with_data(indoc! {r#"
+                        [repos.vim]
+                        branch = "main"
+                        remote = "origin"
+
+                        [repos.dwm]
+                        branch = "main"
+                        remote = "origin"
+                    "#})
+                    .with_kind(FileKind::Normal)
+            })
+            .with_file("hooks/policy.sh", |fixture| {
+                fixture
+                    .with_data(formatdoc! {r#"
+                        #!/bin/sh
+
+                        cat > "$RICER_PLAN_FILE" <<'EOF'
+                        {plan}
+                        EOF
+                    "#})
+                    .with_kind(FileKind::Script)
+            })
+            .setup()?;
+        Ok(harness)
+    }
+
+    fn policy_locator(harness: &FixtureHarness) -> MockLocator {
+        let mut locator = MockLocator::new();
+        locator.expect_hooks_config().return_const(harness.as_path().join("hooks.toml"));
+        locator.expect_hooks_dir().return_const(harness.as_path().join("hooks"));
+        locator.expect_config_dir().return_const(harness.as_path().into());
+        locator.expect_repos_config().return_const(harness.as_path().join("repos.toml"));
+        locator.expect_state_dir().return_const(harness.as_path().join("state"));
+        locator
+    }
+
+    #[rstest]
+    fn cmd_hook_run_hooks_record_plan_changes() -> Result<()> {
+        let harness = policy_harness(indoc! {r#"
+            skip_repos = ["dwm"]
+            trailers = ["Reviewed-by: Policy Bot"]
+        "#})?;
+        let locator = policy_locator(&harness);
+        let ctx = Context::from(Cli::parse_args(["ricer", "--run-hooks=always", "commit"])?);
+        let cmd_hook = CmdHook::load(&ctx, &locator)?;
+        let plan = cmd_hook.plan();
+        cmd_hook.run_hooks(HookKind::Pre)?;
+
+        let expect = PlanChanges {
+            skip_repos: vec!["dwm".into()],
+            trailers: vec!["Reviewed-by: Policy Bot".into()],
+        };
+        assert_eq!(plan.changes(), expect);
+        Ok(())
+    }
+
+    #[rstest]
+    fn cmd_hook_run_hooks_record_runs_for_post_hooks() -> Result<()> {
+        let harness = FixtureHarness::open()?;
+        let seen = harness.as_path().join("seen.json");
+        let harness = harness
+            .with_file("hooks.toml", |fixture| {
+                fixture
+                    .with_data(indoc! {r#"
+                        [hooks]
+                        commit = [
+                            { name = "fmt", pre = "fmt.sh" },
+                            { post = "report.sh" },
+                        ]
+                    "#})
+                    .with_kind(FileKind::Normal)
+            })
+            .with_file("hooks/fmt.sh", |fixture| {
+                fixture.with_data("#!/bin/sh\nexit 0\n").with_kind(FileKind::Script)
+            })
+            .with_file("hooks/report.sh", |fixture| {
+                fixture
+                    .with_data(format!("#!/bin/sh\ncp \"$RICER_HOOK_RUNS\" '{}'\n", seen.display()))
+                    .with_kind(FileKind::Script)
+            })
+            .setup()?;
+        let locator = policy_locator(&harness);
+        let ctx = Context::from(Cli::parse_args(["ricer", "--run-hooks=always", "commit"])?);
+        let cmd_hook = CmdHook::load(&ctx, &locator)?.log_runs(true);
+        let runs = cmd_hook.runs();
+        cmd_hook.run_hooks(HookKind::Pre)?;
+        cmd_hook.run_hooks(HookKind::Post)?;
+
+        let labels = |runs: &[HookRun]| runs.iter().map(HookRun::label).collect::<Vec<_>>();
+        assert_eq!(labels(&runs.runs()), ["commit pre 'fmt'", "commit post 'report.sh'"]);
+        assert!(runs.runs().iter().all(HookRun::succeeded));
+        assert_eq!(labels(&load_runs(&locator)?), labels(&runs.runs()));
+
+        // INVARIANT: post hook only sees runs of hooks before it.
+        let seen = fs::read_to_string(seen)?.parse::<Json>()?;
+        let seen = seen.as_array().unwrap_or_default();
+        let seen = seen.iter().filter_map(HookRun::from_json).collect::<Vec<_>>();
+        assert_eq!(labels(&seen), ["commit pre 'fmt'"]);
+        Ok(())
+    }
+
+    #[rstest]
+    fn cmd_hook_run_hooks_keep_logs_of_output() -> Result<()> {
+        let harness = FixtureHarness::open()?
+            .with_file("hooks.toml", |fixture| {
+                fixture
+                    .with_data(indoc! {r#"
+                        [hooks]
+                        commit = [
+                            { name = "fmt", pre = "fmt.sh" },
+                            { name = "lint", pre = "lint.sh" },
+                        ]
+                    "#})
+                    .with_kind(FileKind::Normal)
+            })
+            .with_file("hooks/fmt.sh", |fixture| {
+                fixture.with_data("#!/bin/sh\necho formatted\n").with_kind(FileKind::Script)
+            })
+            .with_file("hooks/lint.sh", |fixture| {
+                fixture
+                    .with_data("#!/bin/sh\necho 'bad summary' >&2\nexit 3\n")
+                    .with_kind(FileKind::Script)
+            })
+            .setup()?;
+        let mut locator = policy_locator(&harness);
+        locator.expect_logs_dir().return_const(harness.as_path().join("data/logs"));
+        let ctx = Context::from(Cli::parse_args(["ricer", "--run-hooks=always", "commit"])?);
+        let cmd_hook = CmdHook::load(&ctx, &locator)?.keep_logs(HookLogSettings::new());
+        assert!(cmd_hook.run_hooks(HookKind::Pre).is_err());
+
+        // INVARIANT: output of failing hooks is kept too.
+        let logs = load_logs(&locator)?;
+        let summary = logs
+            .iter()
+            .map(|log| (log.label.as_str(), log.code, log.stdout.trim(), log.stderr.trim()))
+            .collect::<Vec<_>>();
+        let expect = [
+            ("commit pre 'fmt'", Some(0), "formatted", ""),
+            ("commit pre 'lint'", Some(3), "", "bad summary"),
+        ];
+        assert_eq!(summary, expect);
+        assert!(logs[1].command.ends_with("lint.sh"));
+        assert_eq!(load_log(&locator, &logs[1].id)?, logs[1]);
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::reload(true, "new")]
+    #[case::keep_loaded(false, "old")]
+    fn cmd_hook_run_hooks_reload_changed_config(
+        #[case] reload: bool,
+        #[case] expect: &str,
+    ) -> Result<()> {
+        let harness = FixtureHarness::open()?;
+        let config = harness.as_path().join("hooks.toml");
+        let harness = harness
+            .with_file("hooks.toml", |fixture| {
+                fixture
+                    .with_data(indoc! {r#"
+                        [hooks]
+                        commit = [{ pre = ["edit.sh", "old.sh"] }]
+                    "#})
+                    .with_kind(FileKind::Normal)
+            })
+            .with_file("hooks/edit.sh", |fixture| {
+                fixture
+                    .with_data(formatdoc! {r#"
+                        #!/bin/sh
+                        printf '[hooks]\ncommit = [{{ pre = ["edit.sh", "new.sh"] }}]\n' > '{}'
+                    "#, config.display()})
+                    .with_kind(FileKind::Script)
+            })
+            .with_file("hooks/old.sh", |fixture| {
+                fixture.with_data("#!/bin/sh\nexit 0\n").with_kind(FileKind::Script)
+            })
+            .with_file("hooks/new.sh", |fixture| {
+                fixture.with_data("#!/bin/sh\nexit 0\n").with_kind(FileKind::Script)
+            })
+            .setup()?;
+        let locator = policy_locator(&harness);
+        let mut args = vec!["ricer", "--run-hooks=always"];
+        if reload {
+            args.push("--reload-hooks");
+        }
+        args.push("commit");
+        let ctx = Context::from(Cli::parse_args(args)?);
+        let cmd_hook = CmdHook::load(&ctx, &locator)?;
+        cmd_hook.run_hooks(HookKind::Pre)?;
+
+        let scripts = cmd_hook.runs().runs().into_iter().map(|run| run.script).collect::<Vec<_>>();
+        assert_eq!(scripts, ["edit.sh".to_string(), format!("{expect}.sh")]);
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::unsupported(r#"trailers = ["Reviewed-by: Policy Bot"]"#)]
+    #[case::unknown_repo(r#"{"skip_repos": ["st"]}"#)]
+    #[case::malformed("skip_repos = [")]
+    fn cmd_hook_run_hooks_return_err_handshake(#[case] plan: &str) -> Result<()> {
+        let harness = policy_harness(plan)?;
+        let locator = policy_locator(&harness);
+        let ctx = Context::from(Cli::parse_args(["ricer", "--run-hooks=always", "pull"])?);
+        let cmd_hook = CmdHook::load(&ctx, &locator)?;
+        let result = cmd_hook.run_hooks(HookKind::Pre);
+        assert!(matches!(result.unwrap_err(), CmdHookError::Handshake { .. }));
+        assert!(cmd_hook.plan().changes().is_empty());
+        Ok(())
+    }
+
+    #[rstest]
+    fn cmd_hook_run_setup_hook_return_err_hook_failed(
+        config_dir: Result<FixtureHarness>,
+    ) -> Result<()> {
+        let config_dir = config_dir?;
+        let fixture = config_dir.get_file("hooks.toml")?;
+        let mut locator = MockLocator::new();
+        locator.expect_hooks_config().return_const(fixture.as_path().into());
+        locator.expect_hooks_dir().return_const(config_dir.as_path().join("hooks"));
+        locator.expect_config_dir().return_const(config_dir.as_path().into());
+        locator.expect_state_dir().return_const(config_dir.as_path().join("state"));
+        locator.expect_repos_config().return_const(config_dir.as_path().join("repos.toml"));
+
+        let ctx = Context::from(Cli::parse_args(["ricer", "--run-hooks=always", "clone", "url"])?);
+        let cmd_hook = CmdHook::load(&ctx, &locator)?;
+        let err = cmd_hook.run_setup_hook("vim", "fail_hook.sh", None).unwrap_err();
+        let script = config_dir.as_path().join("hooks/fail_hook.sh");
+        assert_eq!(err.stderr(), Some("first line\ncannot find plug.vim"));
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "post hook #1 'vim' of 'clone' ('{}') failed with exit code 3",
+                script.display()
+            )
+        );
+
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::short("one\ntwo\n", "one\ntwo")]
+    #[case::long(&"line\n".repeat(20), &["line"; STDERR_TAIL].join("\n"))]
+    #[case::empty("", "")]
+    fn stderr_tail_keep_last_lines(#[case] stderr: &str, #[case] expect: &str) {
+        assert_eq!(stderr_tail(stderr), expect);
+    }
+
+    #[rstest]
+    #[case::always(["--run-hooks=always"], "vim", Some("hello from pre hook\n"))]
+    #[case::never(["--run-hooks=never"], "vim", None)]
+    #[case::no_hooks(["--no-hooks"], "vim", None)]
+    #[case::repo_never(["--run-hooks=always"], "work", None)]
+    fn cmd_hook_run_setup_hook_honor_hook_action<const N: usize>(
+        config_dir: Result<FixtureHarness>,
+        #[case] opts: [&str; N],
+        #[case] repo: &str,
+        #[case] expect: Option<&str>,
+    ) -> Result<()> {
+        let mut config_dir = config_dir?;
+        let fixture = config_dir.get_file("hooks.toml")?;
+        let mut locator = MockLocator::new();
+        locator.expect_hooks_config().return_const(fixture.as_path().into());
+        locator.expect_hooks_dir().return_const(config_dir.as_path().join("hooks"));
+        locator.expect_config_dir().return_const(config_dir.as_path().into());
+        locator.expect_state_dir().return_const(config_dir.as_path().join("state"));
+        locator.expect_repos_config().return_const(config_dir.as_path().join("repos.toml"));
+
+        let args = ["ricer"].into_iter().chain(opts).chain(["clone", "https://some/url"]);
+        let ctx = Context::from(Cli::parse_args(args)?);
+        let cmd_hook = CmdHook::load(&ctx, &locator)?;
+        cmd_hook.run_setup_hook(repo, "pre_hook.sh", None)?;
+        config_dir.sync_untracked()?;
+        let result = config_dir.get_file("out.txt").ok().map(|file| file.as_str().to_string());
+        assert_eq!(result.as_deref(), expect);
+
+        Ok(())
+    }
+
+    fn scripts(hooks: &[ResolvedHook]) -> Vec<String> {
+        hooks
+            .iter()
+            .map(|hook| {
+                format!("{} {}", hook.kind, hook.script.file_name().unwrap().to_string_lossy())
+            })
+            .collect()
+    }
+
+    #[rstest]
+    #[case::push_in_order(vec!["hook", "list"], "push", vec!["pre first_hook.sh", "pre second_hook.sh"])]
+    #[case::pre_before_post(vec!["hook", "list"], "bootstrap", vec!["pre pre_hook.sh", "post post_hook.sh"])]
+    #[case::only_hooks(vec!["--only-hooks=second", "hook", "list"], "commit", vec!["pre second_hook.sh"])]
+    #[case::skip_hooks(vec!["--skip-hooks=1", "hook", "list"], "commit", vec!["pre second_hook.sh"])]
+    #[case::no_hooks(vec!["--no-hooks", "hook", "list"], "commit", vec![])]
+    #[case::never(vec!["--run-hooks=never", "hook", "list"], "commit", vec![])]
+    #[case::no_entry(vec!["hook", "list"], "status", vec![])]
+    fn cmd_hook_resolved_hooks_apply_hook_options(
+        config_dir: Result<FixtureHarness>,
+        #[case] args: Vec<&str>,
+        #[case] cmd: &str,
+        #[case] expect: Vec<&str>,
+    ) -> Result<()> {
+        let config_dir = config_dir?;
+        let fixture = config_dir.get_file("hooks.toml")?;
+        let mut locator = MockLocator::new();
+        locator.expect_hooks_config().return_const(fixture.as_path().into());
+        locator.expect_hooks_dir().return_const(config_dir.as_path().join("hooks"));
+        locator.expect_config_dir().return_const(config_dir.as_path().into());
+        locator.expect_state_dir().return_const(config_dir.as_path().join("state"));
+
+        let ctx = Context::from(Cli::parse_args(["ricer"].into_iter().chain(args))?);
+        let cmd_hook = CmdHook::load(&ctx, &locator)?;
+        assert_eq!(scripts(&cmd_hook.resolved_hooks(cmd)?), expect);
+
+        // INVARIANT: resolving hooks never runs them.
+        assert!(!config_dir.as_path().join("out.txt").exists());
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::unrestricted("vim", vec![HookAction::Always, HookAction::Always])]
+    #[case::repo_prompt("dots", vec![HookAction::Prompt, HookAction::Prompt])]
+    #[case::repo_never("work", vec![])]
+    fn cmd_hook_resolved_hooks_restrict_by_target_repo(
+        config_dir: Result<FixtureHarness>,
+        #[case] repo: &str,
+        #[case] expect: Vec<HookAction>,
+    ) -> Result<()> {
+        let config_dir = config_dir?;
+        let fixture = config_dir.get_file("hooks.toml")?;
+        let mut locator = MockLocator::new();
+        locator.expect_hooks_config().return_const(fixture.as_path().into());
+        locator.expect_hooks_dir().return_const(config_dir.as_path().join("hooks"));
+        locator.expect_config_dir().return_const(config_dir.as_path().into());
+        locator.expect_state_dir().return_const(config_dir.as_path().join("state"));
+        locator.expect_repos_config().return_const(config_dir.as_path().join("repos.toml"));
+
+        let args = ["ricer", "--run-hooks=always", "bootstrap", "--config", repo];
+        let ctx = Context::from(Cli::parse_args(args)?);
+        let cmd_hook = CmdHook::load(&ctx, &locator)?;
+        let result = cmd_hook.resolved_hooks("bootstrap")?;
+        assert_eq!(result.iter().map(|hook| hook.action).collect::<Vec<_>>(), expect);
+        Ok(())
+    }
+
+    #[rstest]
+    fn cmd_hook_resolved_hooks_describe_hook(config_dir: Result<FixtureHarness>) -> Result<()> {
+        let config_dir = config_dir?;
+        let fixture = config_dir.get_file("hooks.toml")?;
+        let mut locator = MockLocator::new();
+        locator.expect_hooks_config().return_const(fixture.as_path().into());
+        locator.expect_hooks_dir().return_const(config_dir.as_path().join("hooks"));
+        locator.expect_config_dir().return_const(config_dir.as_path().into());
+        locator.expect_state_dir().return_const(config_dir.as_path().join("state"));
+
+        let args = ["ricer", "--run-hooks=always", "--only-hooks=second,2", "hook", "list"];
+        let ctx = Context::from(Cli::parse_args(args)?);
+        let cmd_hook = CmdHook::load(&ctx, &locator)?;
+        let expect = ResolvedHook {
+            cmd: "commit".into(),
+            index: 1,
+            name: Some("second".into()),
+            kind: HookKind::Pre,
+            script: config_dir.as_path().join("hooks/second_hook.sh"),
+            workdir: None,
+            sandbox: false,
+            env: HookEnv::default(),
+            scope: HookScope::Command,
+            parallel: false,
+            depends: Vec::new(),
+            interpreter: None,
+            action: HookAction::Always,
+            matched: vec!["second".into(), "2".into()],
+        };
+        assert_eq!(cmd_hook.resolved_hooks("commit")?, vec![expect.clone()]);
+        assert_eq!(
+            expect.to_string(),
+            format!(
+                "commit pre #2 'second': {} (always, matched second,2)",
+                expect.script.display()
+            )
+        );
+        Ok(())
+    }
+
+    #[rstest]
+    fn cmd_hook_configured_hooks_ignore_hook_options(
+        config_dir: Result<FixtureHarness>,
+    ) -> Result<()> {
+        let config_dir = config_dir?;
+        let fixture = config_dir.get_file("hooks.toml")?;
+        let mut locator = MockLocator::new();
+        locator.expect_hooks_config().return_const(fixture.as_path().into());
+        locator.expect_hooks_dir().return_const(config_dir.as_path().join("hooks"));
+        locator.expect_config_dir().return_const(config_dir.as_path().into());
+        locator.expect_state_dir().return_const(config_dir.as_path().join("state"));
+
+        let ctx = Context::from(Cli::parse_args(["ricer", "--no-hooks", "hook", "list"])?);
+        let cmd_hook = CmdHook::load(&ctx, &locator)?;
+        let result = cmd_hook.configured_hooks("commit")?;
+        assert_eq!(scripts(&result), ["pre first_hook.sh", "pre second_hook.sh"]);
+        assert_eq!(cmd_hook.commands()?, ["bootstrap", "push", "commit"]);
+        Ok(())
+    }
 }
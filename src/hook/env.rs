@@ -0,0 +1,207 @@
+// SPDX-FileCopyrightText: 2024 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+pub use crate::locate::CONFIG_DIR_ENV;
+
+use crate::context::EnvSnapshot;
+
+use glob::Pattern;
+use std::{env, ffi::OsString};
+
+/// Patterns of environment variable names whose values never show up in
+/// logged hook output.
+pub const REDACT_ENV: &[&str] = &["*_TOKEN", "*_KEY", "*_SECRET", "*_PASSWORD"];
+
+/// Replacement for redacted values in hook output.
+pub const REDACTED: &str = "[REDACTED]";
+
+/// Variable naming the Ricer command a hook script runs for.
+pub const COMMAND_ENV: &str = "RICER_COMMAND";
+
+/// Variable telling whether a hook script runs before or after its command.
+pub const HOOK_KIND_ENV: &str = "RICER_HOOK_KIND";
+
+/// Variable listing repositories targeted by the command, separated by
+/// spaces.
+pub const REPO_ENV: &str = "RICER_REPO";
+
+/// Prefix of variables passed through `--context`, followed by their
+/// upper-cased key, e.g., `RICER_VAR_THEME` for `--context theme=nord`.
+pub const VAR_ENV_PREFIX: &str = "RICER_VAR_";
+
+/// Name of environment variable holding `--context` variable `key`.
+///
+/// Dashes become underscores, so `color-scheme` is `RICER_VAR_COLOR_SCHEME`.
+pub fn var_env(key: &str) -> String {
+    format!("{VAR_ENV_PREFIX}{}", key.to_ascii_uppercase().replace('-', "_"))
+}
+
+/// Values shorter than this are never redacted, because replacing every `1`
+/// or `yes` in hook output would make it unreadable.
+const REDACT_MIN_LEN: usize = 4;
+
+/// Environment of hook scripts.
+///
+/// Hook definitions can restrict which environment variables their hook
+/// scripts inherit through `env_allow` and `env_deny`, both taking glob
+/// patterns of variable names like `GITHUB_*`. Without `env_allow`, every
+/// variable is allowed. Patterns that are not valid globs only match names
+/// literally.
+///
+/// Variables set through [`HookEnv::set`], e.g., those of the `env` table of
+/// a hook definition, are passed on to hook scripts on top of what they
+/// inherit.
+///
+/// Before hook output is logged or kept for error reporting, it is passed
+/// through [`HookEnv::redact`], which replaces the values of variables named
+/// like [`REDACT_ENV`] or denied through `env_deny` with [`REDACTED`].
+///
+/// # Invariants
+///
+/// 1. Denying takes precedence over allowing.
+/// 1. Set variables are never filtered by `env_allow` or `env_deny`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HookEnv {
+    allow: Vec<String>,
+    deny: Vec<String>,
+    vars: Vec<(String, String)>,
+}
+
+impl HookEnv {
+    pub fn new(allow: &[String], deny: &[String]) -> Self {
+        Self { allow: allow.to_vec(), deny: deny.to_vec(), vars: Vec::new() }
+    }
+
+    /// Set variable `name` to `value` for hook scripts, replacing any earlier
+    /// value of it.
+    pub fn set(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        let (name, value) = (name.into(), value.into());
+        match self.vars.iter_mut().find(|(key, _)| *key == name) {
+            Some((_, old)) => *old = value,
+            None => self.vars.push((name, value)),
+        }
+        self
+    }
+
+    /// Variables set for hook scripts, in the order they were first set.
+    pub fn vars(&self) -> &[(String, String)] {
+        &self.vars
+    }
+
+    /// Check if hook definition does not restrict environment at all.
+    pub fn is_empty(&self) -> bool {
+        self.allow.is_empty() && self.deny.is_empty()
+    }
+
+    /// Check if variable `name` is passed on to hook scripts.
+    pub fn is_passed(&self, name: &str) -> bool {
+        let allowed = self.allow.is_empty() || matches_any(&self.allow, name);
+        allowed && !matches_any(&self.deny, name)
+    }
+
+    /// Names of variables of current environment that hook scripts must not
+    /// inherit.
+    ///
+    /// Unlike [`HookEnv::redact`], the process environment is read directly,
+    /// since hook scripts inherit it, including variables that are not valid
+    /// Unicode.
+    pub fn denied(&self) -> Vec<OsString> {
+        env::vars_os()
+            .map(|(key, _)| key)
+            .filter(|key| !self.is_passed(&key.to_string_lossy()))
+            .collect()
+    }
+
+    /// Redact secrets of current environment from hook `output`, see
+    /// [`EnvSnapshot::current`].
+    pub fn redact(&self, output: &str) -> String {
+        let snapshot = EnvSnapshot::current();
+        let vars = snapshot.vars().map(|(key, value)| (key.to_string(), value.to_string()));
+        self.redact_with(output, vars.chain(self.vars.iter().cloned()))
+    }
+
+    fn redact_with(
+        &self,
+        output: &str,
+        vars: impl IntoIterator<Item = (String, String)>,
+    ) -> String {
+        let mut secrets = vars
+            .into_iter()
+            .filter(|(key, value)| {
+                value.len() >= REDACT_MIN_LEN
+                    && (REDACT_ENV.iter().any(|pattern| matches(pattern, key))
+                        || matches_any(&self.deny, key))
+            })
+            .map(|(_, value)| value)
+            .collect::<Vec<_>>();
+
+        // INVARIANT: redact longer secrets first, so secrets inside them cannot split them.
+        secrets.sort_by_key(|secret| std::cmp::Reverse(secret.len()));
+        secrets
+            .into_iter()
+            .fold(output.to_string(), |output, secret| output.replace(&secret, REDACTED))
+    }
+}
+
+fn matches_any(patterns: &[String], name: &str) -> bool {
+    patterns.iter().any(|pattern| matches(pattern, name))
+}
+
+fn matches(pattern: &str, name: &str) -> bool {
+    Pattern::new(pattern).map_or(pattern == name, |pattern| pattern.matches(name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    fn hook_env(allow: &[&str], deny: &[&str]) -> HookEnv {
+        let into = |patterns: &[&str]| patterns.iter().map(|p| p.to_string()).collect::<Vec<_>>();
+        HookEnv::new(&into(allow), &into(deny))
+    }
+
+    #[rstest]
+    #[case::no_filter(&[], &[], "GITHUB_TOKEN", true)]
+    #[case::allowed(&["PATH", "LC_*"], &[], "LC_ALL", true)]
+    #[case::not_allowed(&["PATH", "LC_*"], &[], "GITHUB_TOKEN", false)]
+    #[case::denied(&[], &["*_TOKEN"], "GITHUB_TOKEN", false)]
+    #[case::deny_over_allow(&["GITHUB_*"], &["*_TOKEN"], "GITHUB_TOKEN", false)]
+    #[case::invalid_glob_literal(&["[PATH"], &[], "[PATH", true)]
+    fn hook_env_is_passed(
+        #[case] allow: &[&str],
+        #[case] deny: &[&str],
+        #[case] name: &str,
+        #[case] expect: bool,
+    ) {
+        assert_eq!(hook_env(allow, deny).is_passed(name), expect);
+    }
+
+    #[rstest]
+    fn hook_env_set_replace_earlier_value() {
+        let env = HookEnv::default().set("A", "1").set("B", "2").set("A", "3");
+        let expect = [("A".to_string(), "3".to_string()), ("B".to_string(), "2".to_string())];
+        assert_eq!(env.vars(), expect);
+    }
+
+    #[rstest]
+    #[case::token("push with ghp_abc123", "push with [REDACTED]")]
+    #[case::denied_var("user hunter22 logged in", "user [REDACTED] logged in")]
+    #[case::nested("key sk-longer-secret", "key [REDACTED]")]
+    #[case::short_value("exit 1", "exit 1")]
+    #[case::nothing_secret("all good", "all good")]
+    fn hook_env_redact_secrets(#[case] output: &str, #[case] expect: &str) {
+        let vars = [
+            ("GITHUB_TOKEN", "ghp_abc123"),
+            ("LOGIN", "hunter22"),
+            ("SHORT_KEY", "1"),
+            ("API_KEY", "sk-longer"),
+            ("API_SECRET", "sk-longer-secret"),
+            ("EDITOR", "vim"),
+        ];
+        let vars = vars.iter().map(|(key, value)| (key.to_string(), value.to_string()));
+        assert_eq!(hook_env(&[], &["LOGIN"]).redact_with(output, vars), expect);
+    }
+}
@@ -0,0 +1,371 @@
+// SPDX-FileCopyrightText: 2024 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+use crate::{
+    context::Context,
+    json::{Json, JsonError},
+};
+
+use std::{cell::RefCell, fmt, rc::Rc, str::FromStr};
+use toml_edit::{DocumentMut, Item, TomlError};
+
+/// Environment variable naming the file pre hooks can write plan changes to.
+pub const PLAN_FILE_ENV: &str = "RICER_PLAN_FILE";
+
+/// Only version of plan changes Ricer understands.
+pub const PLAN_VERSION: i64 = 1;
+
+/// Error types for [`PlanChanges`].
+#[derive(Debug, thiserror::Error)]
+pub enum HandshakeError {
+    #[error("Failed to parse plan changes as TOML")]
+    Toml { source: TomlError },
+
+    #[error("Failed to parse plan changes as JSON")]
+    Json { source: JsonError },
+
+    #[error("Plan changes must be a JSON object")]
+    NotObject,
+
+    #[error("Plan changes use version {version}, but only version {PLAN_VERSION} is supported")]
+    Version { version: String },
+
+    #[error("Unknown plan change '{key}'")]
+    UnknownKey { key: String },
+
+    #[error("Plan change '{key}' must be an array of strings")]
+    NotStrings { key: String },
+
+    #[error("Trailer '{trailer}' must look like 'Key: value'")]
+    BadTrailer { trailer: String },
+
+    #[error("Cannot skip '{name}', because it is not a registered repository")]
+    UnknownRepo { name: String },
+
+    #[error("Plan change '{key}' does not apply to '{command}'")]
+    Unsupported { key: String, command: String },
+}
+
+/// Changes pre hooks request to the plan of their command.
+///
+/// Pre hooks get the path of a file through [`PLAN_FILE_ENV`]. Whatever they
+/// write to it is read back once they exit successfully, as TOML, or as JSON
+/// if it starts with `{`:
+///
+/// ```toml
+/// version = 1
+/// skip_repos = ["vim"]
+/// trailers = ["Reviewed-by: Policy Bot <policy@example.com>"]
+/// ```
+///
+/// # Invariants
+///
+/// 1. Unknown keys are refused, so typos never get silently ignored.
+/// 1. Changes only ever narrow what a command does, or annotate it. Hooks
+///    cannot make a command touch repositories it would not have touched.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PlanChanges {
+    /// Repositories the command must leave alone.
+    pub skip_repos: Vec<String>,
+
+    /// Trailers to add to commit messages, e.g., `Reviewed-by: Policy Bot`.
+    pub trailers: Vec<String>,
+}
+
+impl PlanChanges {
+    pub fn is_empty(&self) -> bool {
+        self.skip_repos.is_empty() && self.trailers.is_empty()
+    }
+
+    /// Check if changes apply to command of `ctx`, and only skip repositories
+    /// out of `repos`.
+    ///
+    /// Only batch commands `commit` and `pull` can skip repositories, and
+    /// only `commit` can add trailers.
+    ///
+    /// # Errors
+    ///
+    /// - Return [`HandshakeError::Unsupported`] if a change does not apply to
+    ///   command of `ctx`.
+    /// - Return [`HandshakeError::UnknownRepo`] if a skipped repository is not
+    ///   in `repos`.
+    pub fn check(&self, ctx: &Context, repos: &[&str]) -> Result<(), HandshakeError> {
+        let unsupported =
+            |key: &str| HandshakeError::Unsupported { key: key.into(), command: ctx.to_string() };
+        if !self.skip_repos.is_empty() && !matches!(ctx, Context::Commit(_) | Context::Pull(_)) {
+            return Err(unsupported("skip_repos"));
+        }
+        if !self.trailers.is_empty() && !matches!(ctx, Context::Commit(_)) {
+            return Err(unsupported("trailers"));
+        }
+
+        match self.skip_repos.iter().find(|name| !repos.contains(&name.as_str())) {
+            Some(name) => Err(HandshakeError::UnknownRepo { name: name.clone() }),
+            None => Ok(()),
+        }
+    }
+
+    /// Add changes of `other` that are not requested yet.
+    pub fn merge(&mut self, other: PlanChanges) {
+        for (mine, theirs) in
+            [(&mut self.skip_repos, other.skip_repos), (&mut self.trailers, other.trailers)]
+        {
+            for change in theirs {
+                if !mine.contains(&change) {
+                    mine.push(change);
+                }
+            }
+        }
+    }
+
+    /// Check if repository `name` must be skipped.
+    pub fn is_skipped(&self, name: &str) -> bool {
+        self.skip_repos.iter().any(|skip| skip == name)
+    }
+
+    /// Add requested trailers to commit `message`.
+    ///
+    /// Trailers join the trailer block `message` already ends with, if any.
+    /// Trailers `message` already has are not added again.
+    pub fn add_trailers(&self, message: &str) -> String {
+        let message = message.trim_end();
+        let trailers = self
+            .trailers
+            .iter()
+            .filter(|trailer| !message.lines().any(|line| line == trailer.as_str()))
+            .map(String::as_str)
+            .collect::<Vec<_>>();
+        if trailers.is_empty() {
+            return message.into();
+        }
+
+        let separator = match message.rsplit_once("\n\n") {
+            Some((_, last)) if last.lines().all(is_trailer) => "\n",
+            _ => "\n\n",
+        };
+        format!("{message}{separator}{}", trailers.join("\n"))
+    }
+
+    fn set(&mut self, key: &str, values: Option<Vec<String>>) -> Result<(), HandshakeError> {
+        let values = values.ok_or_else(|| HandshakeError::NotStrings { key: key.into() })?;
+        match key {
+            "skip_repos" => self.skip_repos = values,
+            "trailers" => {
+                if let Some(trailer) = values.iter().find(|trailer| !is_trailer(trailer)) {
+                    return Err(HandshakeError::BadTrailer { trailer: trailer.clone() });
+                }
+                self.trailers = values;
+            }
+            _ => return Err(HandshakeError::UnknownKey { key: key.into() }),
+        }
+        Ok(())
+    }
+
+    fn from_toml(data: &str) -> Result<Self, HandshakeError> {
+        let doc =
+            data.parse::<DocumentMut>().map_err(|err| HandshakeError::Toml { source: err })?;
+        let mut changes = Self::default();
+        for (key, item) in doc.iter() {
+            match key {
+                "version" => check_version(item.as_integer(), item)?,
+                _ => changes.set(key, toml_strings(item))?,
+            }
+        }
+        Ok(changes)
+    }
+
+    fn from_json(data: &str) -> Result<Self, HandshakeError> {
+        let json = data.parse::<Json>().map_err(|err| HandshakeError::Json { source: err })?;
+        let Json::Object(members) = json else {
+            return Err(HandshakeError::NotObject);
+        };
+
+        let mut changes = Self::default();
+        for (key, value) in &members {
+            match key.as_str() {
+                "version" => {
+                    let version = value.as_f64().filter(|version| version.fract() == 0.0);
+                    check_version(version.map(|version| version as i64), value)?
+                }
+                _ => changes.set(key, json_strings(value))?,
+            }
+        }
+        Ok(changes)
+    }
+}
+
+impl FromStr for PlanChanges {
+    type Err = HandshakeError;
+
+    fn from_str(data: &str) -> Result<Self, Self::Err> {
+        match data.trim_start().starts_with('{') {
+            true => Self::from_json(data),
+            false => Self::from_toml(data),
+        }
+    }
+}
+
+impl fmt::Display for PlanChanges {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut changes = Vec::new();
+        if !self.skip_repos.is_empty() {
+            changes.push(format!("skip {}", self.skip_repos.join(", ")));
+        }
+        changes.extend(self.trailers.iter().map(|trailer| format!("add trailer '{trailer}'")));
+        write!(f, "{}", changes.join("; "))
+    }
+}
+
+/// Shared record of plan changes requested by pre hooks.
+///
+/// Cloning yields another handle to the same record.
+#[derive(Debug, Clone, Default)]
+pub struct HookPlan {
+    changes: Rc<RefCell<PlanChanges>>,
+}
+
+impl HookPlan {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record `changes` requested by a pre hook.
+    pub fn request(&self, changes: PlanChanges) {
+        self.changes.borrow_mut().merge(changes);
+    }
+
+    /// Every change requested so far.
+    pub fn changes(&self) -> PlanChanges {
+        self.changes.borrow().clone()
+    }
+}
+
+fn check_version(version: Option<i64>, raw: impl ToString) -> Result<(), HandshakeError> {
+    match version {
+        Some(PLAN_VERSION) => Ok(()),
+        _ => Err(HandshakeError::Version { version: raw.to_string().trim().into() }),
+    }
+}
+
+fn toml_strings(item: &Item) -> Option<Vec<String>> {
+    item.as_array()?.iter().map(|value| value.as_str().map(String::from)).collect()
+}
+
+fn json_strings(value: &Json) -> Option<Vec<String>> {
+    value.as_array()?.iter().map(|value| value.as_str().map(String::from)).collect()
+}
+
+fn is_trailer(line: &str) -> bool {
+    match line.split_once(": ") {
+        Some((key, value)) => {
+            !key.is_empty()
+                && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+                && !value.trim().is_empty()
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::Cli;
+
+    use anyhow::Result;
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    fn changes(skip_repos: &[&str], trailers: &[&str]) -> PlanChanges {
+        PlanChanges {
+            skip_repos: skip_repos.iter().map(|name| name.to_string()).collect(),
+            trailers: trailers.iter().map(|trailer| trailer.to_string()).collect(),
+        }
+    }
+
+    #[rstest]
+    #[case::toml(
+        indoc! {r#"
+            version = 1
+            skip_repos = ["vim"]
+            trailers = ["Reviewed-by: Policy Bot"]
+        "#},
+        changes(&["vim"], &["Reviewed-by: Policy Bot"]),
+    )]
+    #[case::json(
+        r#"{"version": 1, "skip_repos": ["vim", "dwm"]}"#,
+        changes(&["vim", "dwm"], &[]),
+    )]
+    #[case::empty("", PlanChanges::default())]
+    fn plan_changes_from_str_parse_toml_or_json(
+        #[case] data: &str,
+        #[case] expect: PlanChanges,
+    ) -> Result<()> {
+        assert_eq!(data.parse::<PlanChanges>()?, expect);
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::bad_toml("skip_repos = [", "Failed to parse plan changes as TOML")]
+    #[case::bad_json(r#"{"skip_repos": ["vim"]"#, "Failed to parse plan changes as JSON")]
+    #[case::version("version = 2", "Plan changes use version 2, but only version 1 is supported")]
+    #[case::unknown_key(r#"skip = ["vim"]"#, "Unknown plan change 'skip'")]
+    #[case::not_strings("skip_repos = [1]", "Plan change 'skip_repos' must be an array of strings")]
+    #[case::bad_trailer(
+        r#"trailers = ["reviewed by bot"]"#,
+        "Trailer 'reviewed by bot' must look like 'Key: value'"
+    )]
+    fn plan_changes_from_str_return_err(#[case] data: &str, #[case] expect: &str) {
+        let result = data.parse::<PlanChanges>();
+        assert_eq!(result.unwrap_err().to_string(), expect);
+    }
+
+    #[rstest]
+    #[case::commit(&["ricer", "commit", "-m", "all: update"], changes(&["vim"], &["A: b"]))]
+    #[case::pull(&["ricer", "pull"], changes(&["vim"], &[]))]
+    #[case::nothing(&["ricer", "status"], PlanChanges::default())]
+    fn plan_changes_check_pass(#[case] args: &[&str], #[case] changes: PlanChanges) -> Result<()> {
+        let ctx = Context::from(Cli::parse_args(args)?);
+        changes.check(&ctx, &["vim", "dwm"])?;
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::skip(&["ricer", "status"], changes(&["vim"], &[]), "Plan change 'skip_repos' does not apply to 'status'")]
+    #[case::trailers(&["ricer", "pull"], changes(&[], &["A: b"]), "Plan change 'trailers' does not apply to 'pull'")]
+    #[case::unknown_repo(&["ricer", "pull"], changes(&["st"], &[]), "Cannot skip 'st', because it is not a registered repository")]
+    fn plan_changes_check_return_err(
+        #[case] args: &[&str],
+        #[case] changes: PlanChanges,
+        #[case] expect: &str,
+    ) -> Result<()> {
+        let ctx = Context::from(Cli::parse_args(args)?);
+        let result = changes.check(&ctx, &["vim", "dwm"]);
+        assert_eq!(result.unwrap_err().to_string(), expect);
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::subject("vim: update", "vim: update\n\nReviewed-by: Bot")]
+    #[case::body("vim: update\n\nMore words.\n", "vim: update\n\nMore words.\n\nReviewed-by: Bot")]
+    #[case::trailer_block(
+        "vim: update\n\nSigned-off-by: Me",
+        "vim: update\n\nSigned-off-by: Me\nReviewed-by: Bot"
+    )]
+    #[case::already_there("vim: update\n\nReviewed-by: Bot", "vim: update\n\nReviewed-by: Bot")]
+    fn plan_changes_add_trailers(#[case] message: &str, #[case] expect: &str) {
+        let changes = changes(&[], &["Reviewed-by: Bot"]);
+        assert_eq!(changes.add_trailers(message), expect);
+    }
+
+    #[rstest]
+    fn hook_plan_request_merge_changes() {
+        let plan = HookPlan::new();
+        let handle = plan.clone();
+        handle.request(changes(&["vim"], &["A: b"]));
+        handle.request(changes(&["vim", "dwm"], &[]));
+        assert_eq!(plan.changes(), changes(&["vim", "dwm"], &["A: b"]));
+        assert!(plan.changes().is_skipped("dwm"));
+        assert_eq!(plan.changes().to_string(), "skip vim, dwm; add trailer 'A: b'");
+    }
+}
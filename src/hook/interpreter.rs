@@ -0,0 +1,123 @@
+// SPDX-FileCopyrightText: 2024 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+use std::{fmt, path::Path, process::Command};
+
+/// Program that hook scripts run through.
+///
+/// Hook scripts run through the `interpreter` of their hook definition, e.g.,
+/// `interpreter = "python3"` or `interpreter = "pwsh -File"`. Without one, the
+/// shebang line of the hook script decides, e.g., `#!/usr/bin/env python3`.
+/// Hook scripts with neither run through the POSIX shell, like before.
+///
+/// # Invariants
+///
+/// 1. Shebang lines that go through `env` name their program directly, so
+///    they also work on systems without `/usr/bin/env`.
+/// 1. Shebang programs that do not exist at their path are looked up by file
+///    name through `PATH` instead.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HookInterpreter {
+    /// Program and its arguments, empty for the POSIX shell.
+    argv: Vec<String>,
+}
+
+impl HookInterpreter {
+    /// Resolve interpreter of hook script `data`, preferring `declared`
+    /// interpreter of its hook definition over its shebang line.
+    pub fn resolve(declared: Option<&str>, data: &str) -> Self {
+        let argv = match declared {
+            Some(declared) => declared.split_whitespace().map(String::from).collect(),
+            None => shebang(data),
+        };
+
+        // INVARIANT: plain `sh` is the POSIX shell hook scripts run through anyway.
+        match argv.as_slice() {
+            [program] if file_name(program) == "sh" => Self::default(),
+            _ => Self { argv },
+        }
+    }
+
+    /// Check if hook scripts run through the POSIX shell.
+    pub fn is_shell(&self) -> bool {
+        self.argv.is_empty()
+    }
+
+    pub fn program(&self) -> &str {
+        self.argv.first().map_or("sh", String::as_str)
+    }
+
+    /// Arguments of program that come before the hook script.
+    pub fn args(&self) -> &[String] {
+        self.argv.get(1..).unwrap_or_default()
+    }
+
+    /// Build command that runs hook `script` through interpreter.
+    pub fn command(&self, script: &Path) -> Command {
+        let mut cmd = Command::new(self.program());
+        cmd.args(self.args()).arg(script);
+        cmd
+    }
+}
+
+impl fmt::Display for HookInterpreter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.is_shell() {
+            true => write!(f, "sh"),
+            false => write!(f, "{}", self.argv.join(" ")),
+        }
+    }
+}
+
+/// Program and arguments of shebang line of hook script `data`, if any.
+fn shebang(data: &str) -> Vec<String> {
+    let Some(line) = data.lines().next().and_then(|line| line.strip_prefix("#!")) else {
+        return Vec::new();
+    };
+
+    let mut argv = line.split_whitespace().map(String::from).collect::<Vec<_>>();
+    if argv.first().is_some_and(|program| file_name(program) == "env") {
+        argv.remove(0);
+        if argv.first().is_some_and(|arg| arg == "-S") {
+            argv.remove(0);
+        }
+    }
+
+    if let Some(program) = argv.first_mut() {
+        if !Path::new(program).is_file() {
+            *program = file_name(program).into();
+        }
+    }
+
+    argv
+}
+
+fn file_name(program: &str) -> &str {
+    Path::new(program).file_name().and_then(|name| name.to_str()).unwrap_or(program)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::no_shebang(None, "echo hi\n", "sh")]
+    #[case::sh_shebang(None, "#!/bin/sh\necho hi\n", "sh")]
+    #[case::env_shebang(None, "#!/usr/bin/env python3\nprint('hi')\n", "python3")]
+    #[case::env_split_shebang(None, "#!/usr/bin/env -S python3 -u\n", "python3 -u")]
+    #[case::missing_program(None, "#!/nowhere/bin/bash -e\n", "bash -e")]
+    #[case::declared(Some("pwsh -File"), "#!/usr/bin/env python3\n", "pwsh -File")]
+    #[case::declared_sh(Some("sh"), "#!/usr/bin/env python3\n", "sh")]
+    fn hook_interpreter_resolve_prefer_declared_over_shebang(
+        #[case] declared: Option<&str>,
+        #[case] data: &str,
+        #[case] expect: &str,
+    ) {
+        let interpreter = HookInterpreter::resolve(declared, data);
+        assert_eq!(interpreter.to_string(), expect);
+        assert_eq!(interpreter.is_shell(), expect == "sh");
+    }
+}
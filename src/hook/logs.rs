@@ -0,0 +1,405 @@
+// SPDX-FileCopyrightText: 2024 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+use super::{record::unix_millis, HookLogError, HookRun};
+use crate::{
+    config::HookLogSettings,
+    history::{unix_secs, utc_timestamp},
+    json::Json,
+    locate::Locator,
+};
+
+use log::{debug, warn};
+use mkdirp::mkdirp;
+use std::{
+    fmt,
+    fs::{self, OpenOptions},
+    io::{ErrorKind, Write},
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// Captured output of one executed hook script.
+///
+/// Every hook script that ran gets a log file of its own in the hook log
+/// directory, see [`hook_logs_dir`], named by its [`HookLog::id`].
+///
+/// # Invariants
+///
+/// 1. Identifiers start with the start time of the hook script in
+///    milliseconds, so sorting them by name sorts them by age.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HookLog {
+    /// Identifier of log, which is the file stem of its log file.
+    pub id: String,
+
+    /// Short description of hook, see [`HookRun::label`].
+    pub label: String,
+
+    /// Command line hook script ran through.
+    pub command: String,
+
+    pub started: SystemTime,
+    pub finished: SystemTime,
+
+    /// Exit code of hook script, or `None` if it was killed by a signal.
+    pub code: Option<i32>,
+
+    pub stdout: String,
+    pub stderr: String,
+}
+
+impl HookLog {
+    /// Log output of `run`, which ran through `command`.
+    pub fn new(
+        run: &HookRun,
+        command: impl Into<String>,
+        stdout: impl Into<String>,
+        stderr: impl Into<String>,
+    ) -> Self {
+        let name = format!("{}-{}-{}", run.command, run.kind, run.index);
+        let name = name.replace(|c: char| !c.is_ascii_alphanumeric() && c != '-', "_");
+        Self {
+            id: format!("{:013}-{name}", unix_millis(run.started)),
+            label: run.label(),
+            command: command.into(),
+            started: run.started,
+            finished: run.finished,
+            code: run.code,
+            stdout: stdout.into(),
+            stderr: stderr.into(),
+        }
+    }
+
+    /// How long hook script ran.
+    pub fn duration(&self) -> Duration {
+        self.finished.duration_since(self.started).unwrap_or_default()
+    }
+
+    /// Check if hook script exited with zero.
+    pub fn succeeded(&self) -> bool {
+        self.code == Some(0)
+    }
+
+    /// One line summary of log, e.g., `1700000000250-commit-pre-0  commit pre
+    /// 'fmt'  exit 0`.
+    pub fn summary(&self) -> String {
+        let code = self.code.map_or("-".into(), |code| code.to_string());
+        format!("{}  {}  exit {code}", self.id, self.label)
+    }
+
+    pub fn to_json(&self) -> Json {
+        Json::object()
+            .with("label", &self.label)
+            .with("command", &self.command)
+            .with("started_ms", unix_millis(self.started))
+            .with("finished_ms", unix_millis(self.finished))
+            .with("code", self.code.map(f64::from))
+            .with("stdout", &self.stdout)
+            .with("stderr", &self.stderr)
+    }
+
+    /// Read log `id` from JSON object, or `None` if it is malformed.
+    pub fn from_json(id: impl Into<String>, json: &Json) -> Option<Self> {
+        let time = |key| -> Option<SystemTime> {
+            Some(UNIX_EPOCH + Duration::from_millis(json.get(key)?.as_f64()? as u64))
+        };
+
+        Some(Self {
+            id: id.into(),
+            label: json.get("label")?.as_str()?.into(),
+            command: json.get("command")?.as_str()?.into(),
+            started: time("started_ms")?,
+            finished: time("finished_ms")?,
+            code: json.get("code")?.as_f64().map(|code| code as i32),
+            stdout: json.get("stdout")?.as_str()?.into(),
+            stderr: json.get("stderr")?.as_str()?.into(),
+        })
+    }
+}
+
+impl fmt::Display for HookLog {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let code = self.code.map_or("-".into(), |code| code.to_string());
+        writeln!(f, "Hook:     {}", self.label)?;
+        writeln!(f, "Command:  {}", self.command)?;
+        writeln!(f, "Started:  {} UTC", utc_timestamp(unix_secs(self.started)))?;
+        writeln!(f, "Duration: {:.1?}", self.duration())?;
+        writeln!(f, "Exit:     {code}")?;
+        writeln!(f, "--- stdout ---")?;
+        write_output(f, &self.stdout)?;
+        writeln!(f, "--- stderr ---")?;
+        write_output(f, &self.stderr)
+    }
+}
+
+fn write_output(f: &mut fmt::Formatter<'_>, output: &str) -> fmt::Result {
+    match output.is_empty() || output.ends_with('\n') {
+        true => write!(f, "{output}"),
+        false => writeln!(f, "{output}"),
+    }
+}
+
+/// Expected path to directory of hook logs.
+pub fn hook_logs_dir(locator: &impl Locator) -> PathBuf {
+    locator.logs_dir().join("hooks")
+}
+
+/// Write `log` into hook log directory, and rotate older logs out of it
+/// according to `settings`, see [`rotate_logs`].
+///
+/// Returns path to log file, or `None` if `settings` keep no logs at all.
+///
+/// # Errors
+///
+/// - Return [`HookLogError::MakeDirP`] if hook log directory cannot be
+///   created.
+/// - Return [`HookLogError::FileWrite`] if log file cannot be written.
+/// - Return errors of [`rotate_logs`].
+pub fn write_log(
+    locator: &impl Locator,
+    log: &HookLog,
+    settings: &HookLogSettings,
+) -> Result<Option<PathBuf>, HookLogError> {
+    if settings.max_files == 0 {
+        return Ok(None);
+    }
+
+    let dir = hook_logs_dir(locator);
+    mkdirp(&dir).map_err(|err| HookLogError::MakeDirP { source: err, path: dir.clone() })?;
+
+    // INVARIANT: hooks that start within the same millisecond never share a log file.
+    let mut path = dir.join(format!("{}.json", log.id));
+    for suffix in 1.. {
+        match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(mut file) => {
+                debug!("Write output of {} to '{}'", log.label, path.display());
+                writeln!(file, "{}", log.to_json())
+                    .map_err(|err| HookLogError::FileWrite { source: err, path: path.clone() })?;
+                break;
+            }
+            Err(err) if err.kind() == ErrorKind::AlreadyExists => {
+                path = dir.join(format!("{}-{suffix}.json", log.id));
+            }
+            Err(err) => return Err(HookLogError::FileWrite { source: err, path }),
+        }
+    }
+
+    rotate_logs(locator, settings)?;
+    Ok(Some(path))
+}
+
+/// Remove oldest hook logs until no more than [`HookLogSettings::max_files`]
+/// remain, taking up no more than [`HookLogSettings::max_size`] together.
+///
+/// Returns paths of removed log files, oldest first.
+///
+/// # Invariants
+///
+/// 1. Newest log is always kept, even if it alone is larger than allowed,
+///    unless no logs should be kept at all.
+///
+/// # Errors
+///
+/// - Return [`HookLogError::ReadDir`] if hook log directory cannot be read.
+/// - Return [`HookLogError::FileRemove`] if a log file cannot be removed.
+pub fn rotate_logs(
+    locator: &impl Locator,
+    settings: &HookLogSettings,
+) -> Result<Vec<PathBuf>, HookLogError> {
+    let mut files = log_files(&hook_logs_dir(locator))?;
+    let mut total = files.iter().map(|(_, size)| size).sum::<u64>();
+    let keep = usize::from(settings.max_files > 0);
+    let mut removed = Vec::new();
+    while files.len() > keep && (files.len() > settings.max_files || total > settings.max_size) {
+        let (path, size) = files.remove(0);
+        debug!("Rotate '{}' out of hook logs", path.display());
+        fs::remove_file(&path)
+            .map_err(|err| HookLogError::FileRemove { source: err, path: path.clone() })?;
+        total -= size;
+        removed.push(path);
+    }
+
+    Ok(removed)
+}
+
+/// Load every hook log, oldest first.
+///
+/// Malformed log files are skipped with a warning.
+///
+/// # Errors
+///
+/// - Return [`HookLogError::ReadDir`] if hook log directory cannot be read.
+/// - Return [`HookLogError::FileRead`] if a log file cannot be read.
+pub fn load_logs(locator: &impl Locator) -> Result<Vec<HookLog>, HookLogError> {
+    let mut logs = Vec::new();
+    for (path, _) in log_files(&hook_logs_dir(locator))? {
+        match read_log(&path) {
+            Ok(log) => logs.push(log),
+            Err(HookLogError::Malformed { path }) => {
+                warn!("Skip malformed hook log '{}'", path.display())
+            }
+            Err(err) => return Err(err),
+        }
+    }
+
+    Ok(logs)
+}
+
+/// Load hook log `id`.
+///
+/// # Errors
+///
+/// - Return [`HookLogError::MissingLog`] if there is no hook log `id`.
+/// - Return [`HookLogError::FileRead`] if log file cannot be read.
+/// - Return [`HookLogError::Malformed`] if log file is malformed.
+pub fn load_log(locator: &impl Locator, id: &str) -> Result<HookLog, HookLogError> {
+    // INVARIANT: identifiers never reach outside of hook log directory.
+    if id.is_empty() || id.starts_with('.') || id.contains(['/', '\\']) {
+        return Err(HookLogError::MissingLog { id: id.into() });
+    }
+
+    let path = hook_logs_dir(locator).join(format!("{id}.json"));
+    match read_log(&path) {
+        Err(HookLogError::FileRead { source, .. }) if source.kind() == ErrorKind::NotFound => {
+            Err(HookLogError::MissingLog { id: id.into() })
+        }
+        result => result,
+    }
+}
+
+fn read_log(path: &Path) -> Result<HookLog, HookLogError> {
+    let data = fs::read_to_string(path)
+        .map_err(|err| HookLogError::FileRead { source: err, path: path.into() })?;
+    let id = path.file_stem().unwrap_or_default().to_string_lossy();
+    data.parse::<Json>()
+        .ok()
+        .and_then(|json| HookLog::from_json(id, &json))
+        .ok_or_else(|| HookLogError::Malformed { path: path.into() })
+}
+
+/// Log files of `dir` with their sizes, oldest first.
+fn log_files(dir: &Path) -> Result<Vec<(PathBuf, u64)>, HookLogError> {
+    let read_dir_err = |err| HookLogError::ReadDir { source: err, path: dir.into() };
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(read_dir_err(err)),
+    };
+
+    let mut files = Vec::new();
+    for entry in entries {
+        let entry = entry.map_err(read_dir_err)?;
+        let path = entry.path();
+        if path.extension().is_some_and(|ext| ext == "json") {
+            let size = entry.metadata().map(|meta| meta.len()).unwrap_or_default();
+            files.push((path, size));
+        }
+    }
+    // INVARIANT: sort by file stem, so suffixed logs of the same millisecond come after.
+    files.sort_by(|(a, _), (b, _)| a.file_stem().cmp(&b.file_stem()));
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{hook::HookKind, locate::MockLocator, testenv::FixtureHarness};
+
+    use anyhow::Result;
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    fn log(secs: u64, stdout: &str) -> HookLog {
+        let started = UNIX_EPOCH + Duration::from_secs(secs);
+        let run = HookRun {
+            command: "commit".into(),
+            kind: HookKind::Pre,
+            index: 0,
+            name: Some("fmt".into()),
+            script: "fmt.sh".into(),
+            started,
+            finished: started + Duration::from_millis(1500),
+            code: Some(1),
+        };
+        HookLog::new(&run, "sh /hooks/fmt.sh", stdout, "bad format\n")
+    }
+
+    fn locator(harness: &FixtureHarness) -> MockLocator {
+        let mut locator = MockLocator::new();
+        locator.expect_logs_dir().return_const(harness.as_path().join("data/logs"));
+        locator
+    }
+
+    #[rstest]
+    fn hook_log_json_roundtrip() {
+        let log = log(1_700_000_000, "checked 3 files");
+        assert_eq!(log.id, "1700000000000-commit-pre-0");
+        assert_eq!(HookLog::from_json(&log.id, &log.to_json()), Some(log));
+    }
+
+    #[rstest]
+    fn hook_log_display_report() {
+        let expect = [
+            "Hook:     commit pre 'fmt'",
+            "Command:  sh /hooks/fmt.sh",
+            "Started:  2023-11-14 22:13:20 UTC",
+            "Duration: 1.5s",
+            "Exit:     1",
+            "--- stdout ---",
+            "checked 3 files",
+            "--- stderr ---",
+            "bad format",
+        ];
+        let report = log(1_700_000_000, "checked 3 files").to_string();
+        assert_eq!(report.lines().collect::<Vec<_>>(), expect);
+    }
+
+    #[rstest]
+    fn write_log_keep_logs_of_same_millisecond_apart() -> Result<()> {
+        let harness = FixtureHarness::open()?;
+        let locator = locator(&harness);
+        let settings = HookLogSettings::new();
+        write_log(&locator, &log(1_700_000_000, "first"), &settings)?;
+        write_log(&locator, &log(1_700_000_000, "second"), &settings)?;
+
+        let logs = load_logs(&locator)?;
+        let outputs = logs.iter().map(|log| log.stdout.as_str()).collect::<Vec<_>>();
+        assert_eq!(outputs, ["first", "second"]);
+        assert_eq!(load_log(&locator, "1700000000000-commit-pre-0-1")?.stdout, "second");
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::by_count(HookLogSettings::new().max_files(2), vec!["3", "4"])]
+    #[case::by_size(HookLogSettings::new().max_size(400), vec!["4"])]
+    #[case::keep_newest(HookLogSettings::new().max_size(0), vec!["4"])]
+    #[case::disabled(HookLogSettings::new().max_files(0), vec![])]
+    fn rotate_logs_remove_oldest_logs(
+        #[case] settings: HookLogSettings,
+        #[case] expect: Vec<&str>,
+    ) -> Result<()> {
+        let harness = FixtureHarness::open()?;
+        let locator = locator(&harness);
+        let data = "x".repeat(200);
+        for secs in 1..=4 {
+            write_log(&locator, &log(secs, &format!("{secs}{data}")), &HookLogSettings::new())?;
+        }
+
+        rotate_logs(&locator, &settings)?;
+        let logs = load_logs(&locator)?;
+        let kept = logs.iter().map(|log| &log.stdout[..1]).collect::<Vec<_>>();
+        assert_eq!(kept, expect);
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::unknown("1700000000000-commit-pre-9")]
+    #[case::outside("../hooks")]
+    fn load_log_return_err_missing_log(#[case] id: &str) -> Result<()> {
+        let harness = FixtureHarness::open()?;
+        let result = load_log(&locator(&harness), id);
+        assert!(matches!(result, Err(HookLogError::MissingLog { .. })));
+        Ok(())
+    }
+}
@@ -0,0 +1,287 @@
+// SPDX-FileCopyrightText: 2024 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+use super::{HookKind, ResolvedHook};
+use crate::{clock, json::Json, locate::Locator};
+
+use log::{debug, warn};
+use mkdirp::mkdirp;
+use std::{
+    cell::RefCell,
+    fmt,
+    fs::{self, OpenOptions},
+    io::{Error as IoError, ErrorKind, Write},
+    path::PathBuf,
+    rc::Rc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// Environment variable naming file of hook runs that post hooks can read.
+pub const RUNS_FILE_ENV: &str = "RICER_HOOK_RUNS";
+
+/// Error types for the hook run log, and logs of hook output.
+#[derive(Debug, thiserror::Error)]
+pub enum HookLogError {
+    #[error("Failed to make state directory '{path}'")]
+    MakeDirP { source: IoError, path: PathBuf },
+
+    #[error("Failed to append to '{path}'")]
+    FileAppend { source: IoError, path: PathBuf },
+
+    #[error("Failed to read '{path}'")]
+    FileRead { source: IoError, path: PathBuf },
+
+    #[error("Failed to write '{path}'")]
+    FileWrite { source: IoError, path: PathBuf },
+
+    #[error("Failed to remove '{path}'")]
+    FileRemove { source: IoError, path: PathBuf },
+
+    #[error("Failed to read directory '{path}'")]
+    ReadDir { source: IoError, path: PathBuf },
+
+    #[error("No hook log '{id}', see 'ricer hook log' for logs that exist")]
+    MissingLog { id: String },
+
+    #[error("Hook log '{path}' is malformed")]
+    Malformed { path: PathBuf },
+}
+
+/// One executed hook script.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HookRun {
+    /// Command hook is bound to.
+    pub command: String,
+
+    pub kind: HookKind,
+
+    /// Position of hook definition in command's hook array, counting from 0.
+    pub index: usize,
+
+    /// Name of hook definition, if any.
+    pub name: Option<String>,
+
+    /// File name of hook script.
+    pub script: String,
+
+    pub started: SystemTime,
+    pub finished: SystemTime,
+
+    /// Exit code of hook script, or `None` if it could not be executed.
+    pub code: Option<i32>,
+}
+
+impl HookRun {
+    /// Record run of `hook` from `started` until now.
+    pub fn new(hook: &ResolvedHook, started: SystemTime, code: Option<i32>) -> Self {
+        let script = hook.script.file_name().unwrap_or(hook.script.as_os_str());
+        Self {
+            command: hook.cmd.clone(),
+            kind: hook.kind,
+            index: hook.index,
+            name: hook.name.clone(),
+            script: script.to_string_lossy().into_owned(),
+            started,
+            finished: clock::now(),
+            code,
+        }
+    }
+
+    /// How long hook script ran.
+    pub fn duration(&self) -> Duration {
+        self.finished.duration_since(self.started).unwrap_or_default()
+    }
+
+    /// Check if hook script ran and exited with zero.
+    pub fn succeeded(&self) -> bool {
+        self.code == Some(0)
+    }
+
+    /// Short description of hook, e.g., `commit pre 'fmt'`, falling back to
+    /// file name of its script if the hook definition has no name.
+    pub fn label(&self) -> String {
+        let name = self.name.as_deref().unwrap_or(&self.script);
+        format!("{} {} '{name}'", self.command, self.kind)
+    }
+
+    pub fn to_json(&self) -> Json {
+        Json::object()
+            .with("command", &self.command)
+            .with("kind", self.kind.to_string())
+            .with("index", self.index)
+            .with("name", self.name.clone())
+            .with("script", &self.script)
+            .with("started_ms", unix_millis(self.started))
+            .with("finished_ms", unix_millis(self.finished))
+            .with("code", self.code.map(f64::from))
+    }
+
+    /// Read run from JSON object, or `None` if it is malformed.
+    pub fn from_json(json: &Json) -> Option<Self> {
+        let time = |key| -> Option<SystemTime> {
+            Some(UNIX_EPOCH + Duration::from_millis(json.get(key)?.as_f64()? as u64))
+        };
+        let kind = match json.get("kind")?.as_str()? {
+            "pre" => HookKind::Pre,
+            "post" => HookKind::Post,
+            _ => return None,
+        };
+
+        Some(Self {
+            command: json.get("command")?.as_str()?.into(),
+            kind,
+            index: json.get("index")?.as_f64()? as usize,
+            name: json.get("name").and_then(Json::as_str).map(String::from),
+            script: json.get("script")?.as_str()?.into(),
+            started: time("started_ms")?,
+            finished: time("finished_ms")?,
+            code: json.get("code")?.as_f64().map(|code| code as i32),
+        })
+    }
+}
+
+/// Shared record of hook scripts executed by one command.
+///
+/// Cloning yields another handle to the same record.
+#[derive(Debug, Clone, Default)]
+pub struct HookRuns {
+    runs: Rc<RefCell<Vec<HookRun>>>,
+}
+
+impl HookRuns {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&self, run: HookRun) {
+        self.runs.borrow_mut().push(run);
+    }
+
+    /// Runs in the order they finished.
+    pub fn runs(&self) -> Vec<HookRun> {
+        self.runs.borrow().clone()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.runs.borrow().is_empty()
+    }
+
+    /// Runs as JSON array, as given to post hooks through [`RUNS_FILE_ENV`].
+    pub fn to_json(&self) -> Json {
+        Json::Array(self.runs.borrow().iter().map(HookRun::to_json).collect())
+    }
+}
+
+impl fmt::Display for HookRuns {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let runs = self.runs();
+        let labels = runs.iter().map(HookRun::label).collect::<Vec<_>>();
+        let width = labels.iter().map(String::len).max().unwrap_or(0).max(4);
+        writeln!(f, "{:<width$}  {:>4}  {:>10}", "Hook", "Exit", "Time")?;
+        for (label, run) in labels.iter().zip(&runs) {
+            let code = run.code.map_or("-".into(), |code| code.to_string());
+            let duration = format!("{:.1?}", run.duration());
+            writeln!(f, "{label:<width$}  {code:>4}  {duration:>10}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Expected path to hook run log.
+pub fn hook_log_file(locator: &impl Locator) -> PathBuf {
+    locator.state_dir().join("hooks.jsonl")
+}
+
+/// Append `run` to hook run log.
+///
+/// # Errors
+///
+/// - Return [`HookLogError::MakeDirP`] if state directory cannot be created.
+/// - Return [`HookLogError::FileAppend`] if run cannot be appended.
+pub fn append_run(locator: &impl Locator, run: &HookRun) -> Result<(), HookLogError> {
+    let state_dir = locator.state_dir();
+    mkdirp(state_dir)
+        .map_err(|err| HookLogError::MakeDirP { source: err, path: state_dir.into() })?;
+
+    let path = hook_log_file(locator);
+    debug!("Append run of {} to '{}'", run.label(), path.display());
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .and_then(|mut file| writeln!(file, "{}", run.to_json()))
+        .map_err(|err| HookLogError::FileAppend { source: err, path })
+}
+
+/// Load every run of hook run log, oldest first.
+///
+/// Malformed lines are skipped with a warning.
+///
+/// # Errors
+///
+/// - Return [`HookLogError::FileRead`] if hook run log exists, but cannot be
+///   read.
+pub fn load_runs(locator: &impl Locator) -> Result<Vec<HookRun>, HookLogError> {
+    let path = hook_log_file(locator);
+    let data = match fs::read_to_string(&path) {
+        Ok(data) => data,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(HookLogError::FileRead { source: err, path }),
+    };
+
+    let mut runs = Vec::new();
+    for (index, line) in data.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+        match line.parse::<Json>().ok().as_ref().and_then(HookRun::from_json) {
+            Some(run) => runs.push(run),
+            None => warn!("Skip malformed line {} of '{}'", index + 1, path.display()),
+        }
+    }
+
+    Ok(runs)
+}
+
+pub(super) fn unix_millis(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).map(|time| time.as_millis() as u64).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    fn run(name: Option<&str>, code: Option<i32>) -> HookRun {
+        let started = UNIX_EPOCH + Duration::from_millis(1_700_000_000_250);
+        HookRun {
+            command: "pull".into(),
+            kind: HookKind::Post,
+            index: 1,
+            name: name.map(Into::into),
+            script: "notify.sh".into(),
+            started,
+            finished: started + Duration::from_millis(1500),
+            code,
+        }
+    }
+
+    #[rstest]
+    #[case::named(run(Some("notify"), Some(0)))]
+    #[case::not_executed(run(None, None))]
+    fn hook_run_json_roundtrip(#[case] run: HookRun) {
+        assert_eq!(HookRun::from_json(&run.to_json()), Some(run));
+    }
+
+    #[rstest]
+    fn hook_runs_display_table() {
+        let runs = HookRuns::new();
+        runs.record(run(Some("notify"), Some(2)));
+        runs.record(run(None, None));
+        let expect = [
+            "Hook                   Exit        Time",
+            "pull post 'notify'        2        1.5s",
+            "pull post 'notify.sh'     -        1.5s",
+        ];
+        assert_eq!(runs.to_string().lines().collect::<Vec<_>>(), expect);
+    }
+}
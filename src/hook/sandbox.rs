@@ -0,0 +1,297 @@
+// SPDX-FileCopyrightText: 2024 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+use super::{HookEnv, HookInterpreter};
+use crate::context::EnvSnapshot;
+
+use log::{debug, warn};
+use std::{
+    env, fmt, fs,
+    io::Error as IoError,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+use tempfile::TempDir;
+
+/// Environment variables that sandboxed hooks are allowed to inherit.
+///
+/// Everything else, e.g., access tokens or SSH agent sockets, is stripped.
+pub const SANDBOX_ENV: &[&str] =
+    &["PATH", "LANG", "LC_ALL", "LC_CTYPE", "LC_MESSAGES", "TERM", "TZ", "USER", "LOGNAME"];
+
+/// Error types for [`HookSandbox`].
+#[derive(Debug, thiserror::Error)]
+pub enum SandboxError {
+    #[error("Failed to make temporary home directory for sandbox")]
+    MakeHome { source: IoError },
+
+    #[error("Failed to write hook script into sandbox at '{path}'")]
+    FileWrite { source: IoError, path: PathBuf },
+
+    #[error("Failed to run sandboxed hook through '{backend}'")]
+    Spawn { source: IoError, backend: SandboxBackend },
+}
+
+/// Isolation mechanism of [`HookSandbox`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SandboxBackend {
+    /// Wrap hook in [bubblewrap](https://github.com/containers/bubblewrap)
+    /// with a read-only view of the file system.
+    Bwrap,
+
+    /// Wrap hook in new user, IPC, UTS, and PID namespaces through
+    /// `unshare(1)`. File system stays writable.
+    Unshare,
+
+    /// No isolation besides restricted environment and temporary HOME.
+    EnvOnly,
+}
+
+impl SandboxBackend {
+    /// Detect best available isolation mechanism.
+    ///
+    /// Namespace isolation is only attempted on Linux, and requires `bwrap`
+    /// or `unshare` to be somewhere in PATH. Falls back to
+    /// [`SandboxBackend::EnvOnly`] otherwise.
+    pub fn detect() -> Self {
+        if !cfg!(target_os = "linux") {
+            return SandboxBackend::EnvOnly;
+        }
+
+        if find_program("bwrap").is_some() {
+            SandboxBackend::Bwrap
+        } else if find_program("unshare").is_some() {
+            SandboxBackend::Unshare
+        } else {
+            SandboxBackend::EnvOnly
+        }
+    }
+}
+
+impl fmt::Display for SandboxBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SandboxBackend::Bwrap => write!(f, "bwrap"),
+            SandboxBackend::Unshare => write!(f, "unshare"),
+            SandboxBackend::EnvOnly => write!(f, "restricted environment"),
+        }
+    }
+}
+
+/// Sandbox for hook scripts.
+///
+/// Hook scripts that come from synced repositories cannot always be trusted.
+/// Hooks that opt-in to sandboxing through `sandbox = true` in their hook
+/// definition run with an environment stripped down to [`SANDBOX_ENV`], and
+/// with HOME pointing to a fresh temporary directory that is removed once the
+/// sandbox is dropped.
+///
+/// On Linux, hooks are additionally isolated through the best available
+/// [`SandboxBackend`]. With `bwrap`, the whole file system, including the real
+/// HOME, is mounted read-only, and only the temporary HOME is writable.
+///
+/// # Invariants
+///
+/// 1. Sandboxed hooks never inherit environment variables outside of
+///    [`SANDBOX_ENV`], even if [`HookEnv`] allows them.
+/// 1. User is warned whenever full isolation is not possible.
+#[derive(Debug)]
+pub struct HookSandbox {
+    backend: SandboxBackend,
+    home: TempDir,
+}
+
+impl HookSandbox {
+    /// Construct new sandbox with fresh temporary HOME.
+    ///
+    /// # Errors
+    ///
+    /// - Return [`SandboxError::MakeHome`] if temporary HOME cannot be made.
+    pub fn new(backend: SandboxBackend) -> Result<Self, SandboxError> {
+        let home = tempfile::Builder::new()
+            .prefix("ricer-hook-")
+            .tempdir()
+            .map_err(|err| SandboxError::MakeHome { source: err })?;
+
+        match backend {
+            SandboxBackend::Bwrap => (),
+            SandboxBackend::Unshare => warn!(
+                "Sandboxing is only partial: 'bwrap' not found, so file system cannot be made \
+                 read-only through 'unshare'"
+            ),
+            SandboxBackend::EnvOnly => warn!(
+                "Sandboxing is not possible on this system: neither 'bwrap' nor 'unshare' are \
+                 available, so only environment and HOME are restricted"
+            ),
+        }
+
+        Ok(Self { backend, home })
+    }
+
+    pub fn backend(&self) -> SandboxBackend {
+        self.backend
+    }
+
+    pub fn home(&self) -> &Path {
+        self.home.path()
+    }
+
+    /// Run hook script data inside sandbox with environment further restricted
+    /// by `env`.
+    ///
+    /// Returns exit code, standard output, and standard error of hook.
+    ///
+    /// # Errors
+    ///
+    /// - Return [`SandboxError::FileWrite`] if hook script cannot be placed
+    ///   into sandbox.
+    /// - Return [`SandboxError::Spawn`] if sandboxed hook cannot be spawned.
+    pub fn run(
+        &self,
+        script: &str,
+        workdir: Option<&Path>,
+        env: &HookEnv,
+    ) -> Result<(i32, String, String), SandboxError> {
+        let script_path = self.home().join(".ricer-hook.sh");
+        fs::write(&script_path, script)
+            .map_err(|err| SandboxError::FileWrite { source: err, path: script_path.clone() })?;
+
+        self.run_with(&HookInterpreter::default(), &script_path, workdir, env)
+    }
+
+    /// Run hook `script` file through `interpreter` inside sandbox with
+    /// environment further restricted by `env`.
+    ///
+    /// Returns exit code, standard output, and standard error of hook.
+    ///
+    /// # Errors
+    ///
+    /// - Return [`SandboxError::Spawn`] if sandboxed hook cannot be spawned.
+    pub fn run_with(
+        &self,
+        interpreter: &HookInterpreter,
+        script: &Path,
+        workdir: Option<&Path>,
+        env: &HookEnv,
+    ) -> Result<(i32, String, String), SandboxError> {
+        let mut cmd = self.command(interpreter, script, workdir, env);
+        debug!("Run sandboxed hook through {}: {cmd:?}", self.backend);
+        let output = cmd
+            .stdin(Stdio::null())
+            .output()
+            .map_err(|err| SandboxError::Spawn { source: err, backend: self.backend })?;
+
+        Ok((
+            output.status.code().unwrap_or(-1),
+            String::from_utf8_lossy(output.stdout.as_slice()).into_owned(),
+            String::from_utf8_lossy(output.stderr.as_slice()).into_owned(),
+        ))
+    }
+
+    fn command(
+        &self,
+        interpreter: &HookInterpreter,
+        script: &Path,
+        workdir: Option<&Path>,
+        hook_env: &HookEnv,
+    ) -> Command {
+        let home = self.home();
+        let mut cmd = match self.backend {
+            SandboxBackend::Bwrap => {
+                let mut cmd = Command::new("bwrap");
+                cmd.args(["--ro-bind", "/", "/", "--dev", "/dev", "--proc", "/proc"])
+                    .args(["--tmpfs", "/tmp"])
+                    .arg("--bind")
+                    .args([home, home])
+                    .args(["--unshare-all", "--share-net", "--die-with-parent"]);
+                if let Some(workdir) = workdir {
+                    cmd.arg("--chdir").arg(workdir);
+                }
+                cmd.arg("--").arg(interpreter.program());
+                cmd
+            }
+            SandboxBackend::Unshare => {
+                let mut cmd = Command::new("unshare");
+                cmd.args(["--user", "--ipc", "--uts", "--pid", "--fork", "--"]);
+                cmd.arg(interpreter.program());
+                cmd
+            }
+            SandboxBackend::EnvOnly => Command::new(interpreter.program()),
+        };
+        cmd.args(interpreter.args()).arg(script);
+
+        if let Some(workdir) = workdir {
+            cmd.current_dir(workdir);
+        }
+
+        // INVARIANT: only allow-listed environment variables reach the hook.
+        cmd.env_clear();
+        for (key, value) in EnvSnapshot::current().vars() {
+            let allowed = SANDBOX_ENV.contains(&key);
+            if allowed && hook_env.is_passed(key) {
+                cmd.env(key, value);
+            }
+        }
+        cmd.envs(hook_env.vars().iter().map(|(key, value)| (key, value)));
+        cmd.env("HOME", home);
+
+        cmd
+    }
+}
+
+fn find_program(name: &str) -> Option<PathBuf> {
+    let paths = env::var_os("PATH")?;
+    env::split_paths(&paths).map(|dir| dir.join(name)).find(|path| path.is_file())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use anyhow::Result;
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    #[rstest]
+    fn hook_sandbox_run_restrict_env() -> Result<()> {
+        let sandbox = HookSandbox::new(SandboxBackend::EnvOnly)?;
+        let script = r#"echo "$HOME:${RICER_SANDBOX_SECRET:-unset}:${PATH:+path}""#;
+        env::set_var("RICER_SANDBOX_SECRET", "hunter2");
+        let (code, out, _) = sandbox.run(script, None, &HookEnv::default())?;
+        assert_eq!(code, 0);
+        assert_eq!(out.trim(), format!("{}:unset:path", sandbox.home().display()));
+        Ok(())
+    }
+
+    #[rstest]
+    fn hook_sandbox_run_deny_env() -> Result<()> {
+        let sandbox = HookSandbox::new(SandboxBackend::EnvOnly)?;
+        let env = HookEnv::new(&[], &["LANG".into()]);
+        let (code, out, _) = sandbox.run(r#"echo "${LANG-unset}:${PATH:+path}""#, None, &env)?;
+        assert_eq!(code, 0);
+        assert_eq!(out.trim(), "unset:path");
+        Ok(())
+    }
+
+    #[rstest]
+    fn hook_sandbox_run_in_workdir() -> Result<()> {
+        let workdir = tempfile::tempdir()?;
+        let sandbox = HookSandbox::new(SandboxBackend::EnvOnly)?;
+        let (code, out, _) =
+            sandbox.run("pwd; exit 3", Some(workdir.path()), &HookEnv::default())?;
+        assert_eq!(code, 3);
+        assert_eq!(Path::new(out.trim()).canonicalize()?, workdir.path().canonicalize()?);
+        Ok(())
+    }
+
+    #[rstest]
+    fn hook_sandbox_drop_remove_home() -> Result<()> {
+        let sandbox = HookSandbox::new(SandboxBackend::EnvOnly)?;
+        let home = sandbox.home().to_path_buf();
+        sandbox.run("touch \"$HOME/scratch\"", None, &HookEnv::default())?;
+        assert!(home.join("scratch").exists());
+        drop(sandbox);
+        assert!(!home.exists());
+        Ok(())
+    }
+}
@@ -0,0 +1,193 @@
+// SPDX-FileCopyrightText: 2024 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+use super::{HookId, ResolvedHook};
+
+/// Error types for [`HookSchedule`].
+#[derive(Debug, thiserror::Error)]
+pub enum ScheduleError {
+    #[error("{hook} is part of a dependency cycle")]
+    Cycle { hook: Box<HookId> },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum JobState {
+    Waiting,
+    Running,
+    Done,
+}
+
+/// Order in which hook scripts of one hook kind may execute.
+///
+/// Jobs are hook scripts listed in the order they would execute one after
+/// another. A job becomes ready once every job it waits for is done, so
+/// independent parallel hooks can execute at the same time.
+///
+/// # Invariants
+///
+/// 1. Hooks that are not parallel wait for every job listed before them, and
+///    every job listed after them waits for them. Thus, they always execute
+///    alone, and in the order they were listed.
+/// 1. Hook scripts of one hook definition execute in the order they were
+///    listed.
+/// 1. Parallel hooks wait for every job of other hook definitions named by
+///    their `depends` setting. Dependencies that are not scheduled, e.g., hooks
+///    skipped by hook filters, are not waited for.
+#[derive(Debug, Clone)]
+pub struct HookSchedule {
+    waits: Vec<Vec<usize>>,
+    states: Vec<JobState>,
+}
+
+impl HookSchedule {
+    /// Schedule hook script `jobs`.
+    ///
+    /// # Errors
+    ///
+    /// - Return [`ScheduleError::Cycle`] if jobs wait for each other, so they
+    ///   could never execute.
+    pub fn new(jobs: &[&ResolvedHook]) -> Result<Self, ScheduleError> {
+        let mut waits = vec![Vec::new(); jobs.len()];
+        for (job, hook) in jobs.iter().enumerate() {
+            for (other, dep) in jobs.iter().enumerate() {
+                let listed_before = other < job;
+                let ordered = listed_before && (!hook.parallel || !dep.parallel);
+                let same_def = listed_before && hook.index == dep.index;
+                let depends =
+                    hook.index != dep.index && hook.depends.iter().any(|name| names(dep, name));
+                if ordered || same_def || depends {
+                    waits[job].push(other);
+                }
+            }
+        }
+
+        let schedule = Self { waits, states: vec![JobState::Waiting; jobs.len()] };
+        match schedule.blocked() {
+            Some(job) => Err(ScheduleError::Cycle { hook: Box::new(HookId::from(jobs[job])) }),
+            None => Ok(schedule),
+        }
+    }
+
+    /// Start next job that no longer waits for anything, if any.
+    pub fn start(&mut self) -> Option<usize> {
+        let job = (0..self.states.len()).find(|job| self.is_ready(*job))?;
+        self.states[job] = JobState::Running;
+        Some(job)
+    }
+
+    /// Mark `job` as done, so jobs waiting for it can start.
+    pub fn done(&mut self, job: usize) {
+        self.states[job] = JobState::Done;
+    }
+
+    pub fn is_done(&self, job: usize) -> bool {
+        self.states[job] == JobState::Done
+    }
+
+    fn is_ready(&self, job: usize) -> bool {
+        self.states[job] == JobState::Waiting
+            && self.waits[job].iter().all(|dep| self.states[*dep] == JobState::Done)
+    }
+
+    /// Find a job that can never start, because of a dependency cycle.
+    fn blocked(&self) -> Option<usize> {
+        let mut schedule = self.clone();
+        while let Some(job) = schedule.start() {
+            schedule.done(job);
+        }
+        (0..schedule.states.len()).find(|job| !schedule.is_done(*job))
+    }
+}
+
+/// Check if dependency `name` names `hook` by its hook definition name, or
+/// by the path of its hook script.
+fn names(hook: &ResolvedHook, name: &str) -> bool {
+    hook.name.as_deref() == Some(name) || hook.script.ends_with(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::HookScope,
+        context::HookAction,
+        hook::{HookEnv, HookKind},
+    };
+
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    fn hook(index: usize, script: &str, parallel: bool, depends: &[&str]) -> ResolvedHook {
+        ResolvedHook {
+            cmd: "commit".into(),
+            index,
+            name: None,
+            kind: HookKind::Pre,
+            script: format!("/hooks/{script}").into(),
+            workdir: None,
+            sandbox: false,
+            env: HookEnv::default(),
+            scope: HookScope::Command,
+            parallel,
+            depends: depends.iter().map(|name| name.to_string()).collect(),
+            interpreter: None,
+            action: HookAction::Always,
+            matched: Vec::new(),
+        }
+    }
+
+    /// Start every ready job at once, returning the jobs of each round.
+    fn rounds(schedule: &mut HookSchedule) -> Vec<Vec<usize>> {
+        let mut rounds = Vec::new();
+        loop {
+            let round = std::iter::from_fn(|| schedule.start()).collect::<Vec<_>>();
+            if round.is_empty() {
+                return rounds;
+            }
+            round.iter().for_each(|job| schedule.done(*job));
+            rounds.push(round);
+        }
+    }
+
+    #[rstest]
+    #[case::serial(
+        vec![hook(0, "a.sh", false, &[]), hook(1, "b.sh", false, &[]), hook(2, "c.sh", false, &[])],
+        vec![vec![0], vec![1], vec![2]],
+    )]
+    #[case::parallel(
+        vec![hook(0, "a.sh", true, &[]), hook(1, "b.sh", true, &[]), hook(2, "c.sh", true, &[])],
+        vec![vec![0, 1, 2]],
+    )]
+    #[case::depends(
+        vec![hook(0, "a.sh", true, &["c.sh"]), hook(1, "b.sh", true, &[]), hook(2, "c.sh", true, &[])],
+        vec![vec![1, 2], vec![0]],
+    )]
+    #[case::barrier(
+        vec![hook(0, "a.sh", true, &[]), hook(1, "b.sh", false, &[]), hook(2, "c.sh", true, &[])],
+        vec![vec![0], vec![1], vec![2]],
+    )]
+    #[case::same_definition(
+        vec![hook(0, "a.sh", true, &[]), hook(0, "b.sh", true, &[]), hook(1, "c.sh", true, &[])],
+        vec![vec![0, 2], vec![1]],
+    )]
+    #[case::unscheduled_dependency(
+        vec![hook(0, "a.sh", true, &["gone.sh"]), hook(1, "b.sh", true, &[])],
+        vec![vec![0, 1]],
+    )]
+    fn hook_schedule_next_honor_order(
+        #[case] hooks: Vec<ResolvedHook>,
+        #[case] expect: Vec<Vec<usize>>,
+    ) -> Result<(), ScheduleError> {
+        let mut schedule = HookSchedule::new(&hooks.iter().collect::<Vec<_>>())?;
+        assert_eq!(rounds(&mut schedule), expect);
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::mutual(vec![hook(0, "a.sh", true, &["b.sh"]), hook(1, "b.sh", true, &["a.sh"])])]
+    #[case::across_barrier(vec![hook(0, "a.sh", false, &["b.sh"]), hook(1, "b.sh", true, &[])])]
+    fn hook_schedule_new_return_err_cycle(#[case] hooks: Vec<ResolvedHook>) {
+        let result = HookSchedule::new(&hooks.iter().collect::<Vec<_>>());
+        assert!(matches!(result, Err(ScheduleError::Cycle { .. })));
+    }
+}
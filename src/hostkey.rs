@@ -0,0 +1,455 @@
+// SPDX-FileCopyrightText: 2024 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+//! SSH host key verification.
+//!
+//! libgit2 refuses SSH hosts that are missing from known hosts, so the first
+//! clone onto a fresh machine fails before anything is transferred. Once
+//! [`install`] is called, host keys of SSH remotes are checked by Ricer
+//! instead, against the known hosts file of OpenSSH, i.e.,
+//! `~/.ssh/known_hosts`:
+//!
+//! - Keys of known hosts are accepted.
+//! - Keys of unknown hosts are shown by their SHA256 fingerprint, and only
+//!   accepted once the user confirms them. Confirmed keys are added to known
+//!   hosts, like OpenSSH does.
+//! - Hosts offering another key than the one they are known by are always
+//!   refused.
+//!
+//! Passing `--no-verify-host` accepts keys of unknown hosts without asking,
+//! and without adding them to known hosts, for automation that cannot answer.
+//! Changed keys are still refused.
+//!
+//! Known hosts are looked up through `ssh-keygen -F`, so hashed entries match
+//! like they would for OpenSSH.
+
+use crate::ui::{Prompter, TerminalPrompter, UiError};
+
+use git2::{cert::Cert, CertificateCheckStatus, Error as Git2Error};
+use log::{debug, info, warn};
+use std::{
+    fs::{self, OpenOptions},
+    io::{Error as IoError, Write},
+    path::{Path, PathBuf},
+    process::Command,
+    sync::OnceLock,
+};
+
+/// Error types for [`HostKeyCheck`].
+#[derive(Debug, thiserror::Error)]
+pub enum HostKeyError {
+    #[error(
+        "Host key of '{host}' changed to {fingerprint}, refusing to connect; remove its old key \
+         from '{path}' if the change is expected"
+    )]
+    Changed { host: String, fingerprint: String, path: PathBuf },
+
+    #[error("Failed to look up '{host}' in '{path}': {msg}")]
+    Lookup { host: String, path: PathBuf, msg: String },
+
+    #[error("Failed to add host key to '{path}'")]
+    FileWrite { source: IoError, path: PathBuf },
+
+    #[error("Failed to confirm host key")]
+    Prompt { source: UiError },
+}
+
+impl From<UiError> for HostKeyError {
+    fn from(err: UiError) -> Self {
+        HostKeyError::Prompt { source: err }
+    }
+}
+
+/// Get path to known hosts file of OpenSSH in `home` directory.
+pub fn known_hosts_file(home: &Path) -> PathBuf {
+    home.join(".ssh").join("known_hosts")
+}
+
+/// Host key offered by an SSH remote.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HostKey {
+    /// Key type as written in known hosts, e.g., `ssh-ed25519`.
+    pub kind: String,
+
+    /// Base64 encoded key as written in known hosts.
+    pub key: String,
+
+    /// SHA256 fingerprint of key as shown by OpenSSH.
+    pub fingerprint: String,
+}
+
+impl HostKey {
+    /// Extract host key from SSH certificate `cert`.
+    ///
+    /// Will return `None` if `cert` is not an SSH certificate, or its raw key
+    /// is not available.
+    pub fn from_cert(cert: &Cert<'_>) -> Option<Self> {
+        let hostkey = cert.as_hostkey()?;
+        let kind = hostkey.hostkey_type()?.name().to_string();
+        let key = hostkey.hostkey()?;
+        let fingerprint = match hostkey.hash_sha256() {
+            Some(hash) => format!("SHA256:{}", base64(hash, false)),
+            None => format!("{kind} key"),
+        };
+        Some(Self { kind, key: base64(key, true), fingerprint })
+    }
+}
+
+/// Whether a host key is known for a host.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HostKeyStatus {
+    /// Host is known by this key.
+    Known,
+
+    /// Host is unknown, or only known by keys of other types.
+    Unknown,
+
+    /// Host is known by another key of the same type.
+    Changed,
+}
+
+/// Check host keys of SSH remotes against known hosts.
+///
+/// # Invariants
+///
+/// 1. Changed host keys are always refused.
+/// 1. Keys are only added to known hosts once the user confirmed them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HostKeyCheck {
+    known_hosts: PathBuf,
+    verify: bool,
+}
+
+impl HostKeyCheck {
+    /// Check host keys against `known_hosts` file.
+    pub fn new(known_hosts: impl Into<PathBuf>) -> Self {
+        Self { known_hosts: known_hosts.into(), verify: true }
+    }
+
+    /// Ask user to confirm keys of unknown hosts, or trust them without
+    /// asking.
+    pub fn verify(mut self, verify: bool) -> Self {
+        self.verify = verify;
+        self
+    }
+
+    /// Look up whether `host` is known by `key`.
+    ///
+    /// # Errors
+    ///
+    /// - Return [`HostKeyError::Lookup`] if `ssh-keygen` cannot search known
+    ///   hosts.
+    pub fn status(&self, host: &str, key: &HostKey) -> Result<HostKeyStatus, HostKeyError> {
+        if !self.known_hosts.exists() {
+            return Ok(HostKeyStatus::Unknown);
+        }
+
+        let lookup_err = |msg: String| HostKeyError::Lookup {
+            host: host.into(),
+            path: self.known_hosts.clone(),
+            msg,
+        };
+        let output = Command::new("ssh-keygen")
+            .arg("-F")
+            .arg(host)
+            .arg("-f")
+            .arg(&self.known_hosts)
+            .output()
+            .map_err(|err| lookup_err(format!("cannot run 'ssh-keygen': {err}")))?;
+
+        // INVARIANT: ssh-keygen exits with 1 when host is not found.
+        match output.status.code() {
+            Some(0) => (),
+            Some(1) => return Ok(HostKeyStatus::Unknown),
+            _ => return Err(lookup_err(String::from_utf8_lossy(&output.stderr).trim().into())),
+        }
+
+        let mut status = HostKeyStatus::Unknown;
+        for line in String::from_utf8_lossy(&output.stdout).lines() {
+            if line.starts_with('#') {
+                continue;
+            }
+
+            let fields = line.split_whitespace().collect::<Vec<_>>();
+            let [hosts, kind, known, ..] = fields[..] else { continue };
+            // INVARIANT: marked lines are revocations or certificate authorities, not host keys.
+            if hosts.starts_with('@') || kind != key.kind {
+                continue;
+            }
+            if known == key.key {
+                return Ok(HostKeyStatus::Known);
+            }
+            status = HostKeyStatus::Changed;
+        }
+
+        Ok(status)
+    }
+
+    /// Determine if `key` offered by `host` can be trusted, asking user
+    /// through `prompter` about keys of unknown hosts.
+    ///
+    /// Keys confirmed by the user are added to known hosts.
+    ///
+    /// # Errors
+    ///
+    /// - Return [`HostKeyError::Changed`] if `host` is known by another key.
+    /// - Return [`HostKeyError::Lookup`] if known hosts cannot be searched.
+    /// - Return [`HostKeyError::FileWrite`] if key cannot be added to known
+    ///   hosts.
+    /// - Return [`HostKeyError::Prompt`] if user cannot be asked.
+    pub fn check(
+        &self,
+        host: &str,
+        key: &HostKey,
+        prompter: &impl Prompter,
+    ) -> Result<bool, HostKeyError> {
+        match self.status(host, key)? {
+            HostKeyStatus::Known => {
+                debug!("Host key of '{host}' is known");
+                Ok(true)
+            }
+            HostKeyStatus::Changed => Err(HostKeyError::Changed {
+                host: host.into(),
+                fingerprint: key.fingerprint.clone(),
+                path: self.known_hosts.clone(),
+            }),
+            HostKeyStatus::Unknown if !self.verify => {
+                warn!("Trust unknown host '{host}' by {} without verifying", key.fingerprint);
+                Ok(true)
+            }
+            HostKeyStatus::Unknown => {
+                let question = format!(
+                    "Host '{host}' is unknown, its {} key fingerprint is {}. Trust it and add it \
+                     to '{}'?",
+                    key.kind,
+                    key.fingerprint,
+                    self.known_hosts.display()
+                );
+                if !prompter.confirm(&question)? {
+                    return Ok(false);
+                }
+
+                self.add(host, key)?;
+                info!("Added host key of '{host}' to '{}'", self.known_hosts.display());
+                Ok(true)
+            }
+        }
+    }
+
+    fn add(&self, host: &str, key: &HostKey) -> Result<(), HostKeyError> {
+        let write_err =
+            |err| HostKeyError::FileWrite { source: err, path: self.known_hosts.clone() };
+        if let Some(dir) = self.known_hosts.parent() {
+            create_ssh_dir(dir).map_err(write_err)?;
+        }
+
+        // INVARIANT: never glue new entry onto a last line without newline.
+        let data = fs::read(&self.known_hosts).unwrap_or_default();
+        let sep = match data.last() {
+            Some(b'\n') | None => "",
+            Some(_) => "\n",
+        };
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.known_hosts)
+            .map_err(write_err)?;
+        writeln!(file, "{sep}{host} {} {}", key.kind, key.key).map_err(write_err)
+    }
+}
+
+#[cfg(unix)]
+fn create_ssh_dir(dir: &Path) -> Result<(), IoError> {
+    use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+
+    if dir.exists() {
+        return Ok(());
+    }
+    fs::DirBuilder::new().recursive(true).mode(0o700).create(dir)?;
+    fs::set_permissions(dir, fs::Permissions::from_mode(0o700))
+}
+
+#[cfg(not(unix))]
+fn create_ssh_dir(dir: &Path) -> Result<(), IoError> {
+    fs::create_dir_all(dir)
+}
+
+fn installed() -> &'static OnceLock<HostKeyCheck> {
+    static CHECK: OnceLock<HostKeyCheck> = OnceLock::new();
+    &CHECK
+}
+
+/// Check host keys of every SSH remote through `check` from now on.
+///
+/// Only the first call takes effect. Without it, host keys are left to
+/// libgit2.
+pub fn install(check: HostKeyCheck) {
+    if installed().set(check).is_err() {
+        debug!("Host key check was already installed");
+    }
+}
+
+/// Certificate callback of remote operations, checking host keys through
+/// the [`HostKeyCheck`] given to [`install`].
+///
+/// Certificates that are not SSH host keys, e.g., those of HTTPS remotes, are
+/// left to libgit2.
+///
+/// # Errors
+///
+/// - Return [`Git2Error`] if host key is refused, or cannot be checked.
+pub fn certificate_check(cert: &Cert<'_>, host: &str) -> Result<CertificateCheckStatus, Git2Error> {
+    let (Some(check), Some(key)) = (installed().get(), HostKey::from_cert(cert)) else {
+        return Ok(CertificateCheckStatus::CertificatePassthrough);
+    };
+
+    match check.check(host, &key, &TerminalPrompter) {
+        Ok(true) => Ok(CertificateCheckStatus::CertificateOk),
+        Ok(false) => Err(Git2Error::from_str(&format!("Host key of '{host}' was not trusted"))),
+        Err(err) => Err(Git2Error::from_str(&err.to_string())),
+    }
+}
+
+/// Encode `data` as base64 through the standard alphabet.
+fn base64(data: &[u8], pad: bool) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut encoded = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let bytes = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let bits = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+        for i in 0..=chunk.len() {
+            encoded.push(ALPHABET[(bits >> (18 - 6 * i) & 0x3f) as usize] as char);
+        }
+        if pad {
+            (chunk.len()..3).for_each(|_| encoded.push('='));
+        }
+    }
+
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testenv::{FileKind, FixtureHarness, ScriptedPrompter};
+
+    use anyhow::Result;
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+    use rstest::{fixture, rstest};
+
+    #[fixture]
+    fn home() -> Result<FixtureHarness> {
+        let harness = FixtureHarness::open()?
+            .with_file(".ssh/known_hosts", |fixture| {
+                fixture
+                    .with_data(indoc! {"
+                        github.com ssh-ed25519 AAAAC3NzaC1lZDI1NTE5
+                        gitlab.com ssh-rsa AAAAB3NzaC1yc2E= old key
+                    "})
+                    .with_kind(FileKind::Normal)
+            })
+            .setup()?;
+        Ok(harness)
+    }
+
+    fn host_key(kind: &str, key: &str) -> HostKey {
+        HostKey { kind: kind.into(), key: key.into(), fingerprint: "SHA256:abc".into() }
+    }
+
+    #[rstest]
+    #[case::known(
+        "github.com",
+        host_key("ssh-ed25519", "AAAAC3NzaC1lZDI1NTE5"),
+        HostKeyStatus::Known
+    )]
+    #[case::unknown_host("codeberg.org", host_key("ssh-ed25519", "AAAA"), HostKeyStatus::Unknown)]
+    #[case::other_type("github.com", host_key("ssh-rsa", "AAAA"), HostKeyStatus::Unknown)]
+    #[case::changed("gitlab.com", host_key("ssh-rsa", "AAAAnew"), HostKeyStatus::Changed)]
+    fn host_key_check_status(
+        home: Result<FixtureHarness>,
+        #[case] host: &str,
+        #[case] key: HostKey,
+        #[case] expect: HostKeyStatus,
+    ) -> Result<()> {
+        let home = home?;
+        let check = HostKeyCheck::new(known_hosts_file(home.as_path()));
+        assert_eq!(check.status(host, &key)?, expect);
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::confirmed(["y"], true)]
+    #[case::declined(["n"], false)]
+    fn host_key_check_ask_about_unknown_hosts<const N: usize>(
+        home: Result<FixtureHarness>,
+        #[case] answers: [&str; N],
+        #[case] expect: bool,
+    ) -> Result<()> {
+        let home = home?;
+        let check = HostKeyCheck::new(known_hosts_file(home.as_path()));
+        let key = host_key("ssh-ed25519", "AAAAnew");
+        let prompter = ScriptedPrompter::new(answers);
+
+        assert_eq!(check.check("codeberg.org", &key, &prompter)?, expect);
+        assert_eq!(prompter.questions().len(), 1);
+        assert!(prompter.questions()[0].contains("SHA256:abc"));
+        let expect = match expect {
+            true => HostKeyStatus::Known,
+            false => HostKeyStatus::Unknown,
+        };
+        assert_eq!(check.status("codeberg.org", &key)?, expect);
+        Ok(())
+    }
+
+    #[rstest]
+    fn host_key_check_no_verify_trust_unknown_hosts(home: Result<FixtureHarness>) -> Result<()> {
+        let home = home?;
+        let check = HostKeyCheck::new(known_hosts_file(home.as_path())).verify(false);
+        let key = host_key("ssh-ed25519", "AAAAnew");
+        let prompter = ScriptedPrompter::default();
+
+        assert!(check.check("codeberg.org", &key, &prompter)?);
+        assert!(prompter.questions().is_empty());
+        assert_eq!(check.status("codeberg.org", &key)?, HostKeyStatus::Unknown);
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::verify(true)]
+    #[case::no_verify(false)]
+    fn host_key_check_return_err_changed(
+        home: Result<FixtureHarness>,
+        #[case] verify: bool,
+    ) -> Result<()> {
+        let home = home?;
+        let check = HostKeyCheck::new(known_hosts_file(home.as_path())).verify(verify);
+        let key = host_key("ssh-rsa", "AAAAnew");
+        let result = check.check("gitlab.com", &key, &ScriptedPrompter::new(["y"]));
+        assert!(matches!(result.unwrap_err(), HostKeyError::Changed { .. }));
+        Ok(())
+    }
+
+    #[rstest]
+    fn host_key_check_create_known_hosts(home: Result<FixtureHarness>) -> Result<()> {
+        let home = home?;
+        let path = home.as_path().join("fresh/.ssh/known_hosts");
+        let check = HostKeyCheck::new(&path);
+        let key = host_key("ssh-ed25519", "AAAAnew");
+
+        assert!(check.check("codeberg.org", &key, &ScriptedPrompter::new(["yes"]))?);
+        assert_eq!(fs::read_to_string(&path)?, "codeberg.org ssh-ed25519 AAAAnew\n");
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::empty(b"", true, "")]
+    #[case::one_byte(b"f", true, "Zg==")]
+    #[case::two_bytes(b"fo", true, "Zm8=")]
+    #[case::full_chunk(b"foo", true, "Zm9v")]
+    #[case::no_pad(b"foob", false, "Zm9vYg")]
+    fn base64_encode(#[case] data: &[u8], #[case] pad: bool, #[case] expect: &str) {
+        assert_eq!(base64(data, pad), expect);
+    }
+}
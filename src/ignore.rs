@@ -0,0 +1,344 @@
+// SPDX-FileCopyrightText: 2024 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+//! Ignore files of repositories.
+//!
+//! Fake bare repositories use the home directory as their working directory,
+//! so their status lists every untracked file of it. Through `ricer ignore`,
+//! the user keeps gitignore-style patterns of each repository in
+//! `<repo>.ignore` of the ignore file directory, next to the rest of Ricer's
+//! configuration, so they follow the user across machines.
+//!
+//! Patterns are written into a block of the `info/exclude` file of the
+//! repository that Ricer manages, which both Git and Ricer honor. Lines of
+//! `info/exclude` outside of that block are left alone. The block is written
+//! whenever patterns change, and whenever the repository is cloned.
+//!
+//! Sparse checkout is not supported by libgit2, so ignore files only keep
+//! untracked files out of status, they never hide tracked files.
+
+use crate::{
+    config::{ConfigFile, ConfigFileError, RepoConfig, RepoSettings},
+    context::{IgnoreContext, IgnoreOp},
+    locate::Locator,
+    vcs::{GitRepo, GitRepoError},
+};
+
+use log::{debug, info};
+use mkdirp::mkdirp;
+use std::{
+    fs,
+    io::{Error as IoError, ErrorKind},
+    path::{Path, PathBuf},
+};
+
+/// First line of block Ricer manages in `info/exclude`.
+pub const EXCLUDE_BEGIN: &str = "# BEGIN ricer ignores";
+
+/// Last line of block Ricer manages in `info/exclude`.
+pub const EXCLUDE_END: &str = "# END ricer ignores";
+
+/// Error types for ignore files.
+#[derive(Debug, thiserror::Error)]
+pub enum IgnoreError {
+    #[error("Failed to make directory '{path}'")]
+    MakeDirP { source: IoError, path: PathBuf },
+
+    #[error("Failed to read '{path}'")]
+    FileRead { source: IoError, path: PathBuf },
+
+    #[error("Failed to write '{path}'")]
+    FileWrite { source: IoError, path: PathBuf },
+
+    #[error("Failed to open repository '{name}'")]
+    Repo { source: GitRepoError, name: String },
+
+    #[error("Failed to read repository configuration")]
+    Config { source: ConfigFileError },
+}
+
+impl From<ConfigFileError> for IgnoreError {
+    fn from(err: ConfigFileError) -> Self {
+        IgnoreError::Config { source: err }
+    }
+}
+
+/// Expected path to ignore file of `repo`.
+pub fn ignore_file(locator: &impl Locator, repo: &str) -> PathBuf {
+    locator.ignores_dir().join(format!("{repo}.ignore"))
+}
+
+/// List patterns of ignore file of `repo`, in order of ignore file.
+///
+/// Blank lines and comments are left out. Missing ignore file yields no
+/// patterns.
+///
+/// # Errors
+///
+/// - Return [`IgnoreError::FileRead`] if ignore file exists, but cannot be
+///   read.
+pub fn list(locator: &impl Locator, repo: &str) -> Result<Vec<String>, IgnoreError> {
+    let lines = read_lines(&ignore_file(locator, repo))?;
+    Ok(lines.into_iter().filter(|line| is_pattern(line)).collect())
+}
+
+/// Add `patterns` to ignore file of `repo`, and sync its excludes.
+///
+/// Returns patterns that were not in the ignore file yet.
+///
+/// # Invariants
+///
+/// Existing lines of ignore file, including comments, are kept as-is.
+///
+/// # Errors
+///
+/// - Return [`IgnoreError::Config`] if `repo` is not registered.
+/// - Return [`IgnoreError::FileRead`] or [`IgnoreError::FileWrite`] if ignore
+///   file, or `info/exclude` of `repo`, cannot be read or written.
+/// - Return [`IgnoreError::Repo`] if `repo` cannot be opened.
+pub fn add(
+    locator: &impl Locator,
+    repo: &str,
+    patterns: &[String],
+) -> Result<Vec<String>, IgnoreError> {
+    let settings = ConfigFile::load(RepoConfig, locator)?.get(repo)?;
+    let path = ignore_file(locator, repo);
+    let mut lines = read_lines(&path)?;
+    let mut added = Vec::new();
+    for pattern in patterns.iter().map(|pattern| pattern.trim()).filter(|line| is_pattern(line)) {
+        if !lines.iter().any(|line| line == pattern) {
+            lines.push(pattern.into());
+            added.push(pattern.into());
+        }
+    }
+
+    write_lines(&path, &lines)?;
+    sync_excludes(locator, &settings)?;
+    Ok(added)
+}
+
+/// Remove `patterns` from ignore file of `repo`, and sync its excludes.
+///
+/// Returns patterns that were in the ignore file.
+///
+/// # Errors
+///
+/// - Return [`IgnoreError::Config`] if `repo` is not registered.
+/// - Return [`IgnoreError::FileRead`] or [`IgnoreError::FileWrite`] if ignore
+///   file, or `info/exclude` of `repo`, cannot be read or written.
+/// - Return [`IgnoreError::Repo`] if `repo` cannot be opened.
+pub fn remove(
+    locator: &impl Locator,
+    repo: &str,
+    patterns: &[String],
+) -> Result<Vec<String>, IgnoreError> {
+    let settings = ConfigFile::load(RepoConfig, locator)?.get(repo)?;
+    let path = ignore_file(locator, repo);
+    let (removed, kept) = read_lines(&path)?
+        .into_iter()
+        .partition::<Vec<_>, _>(|line| patterns.iter().any(|pattern| pattern.trim() == line));
+
+    write_lines(&path, &kept)?;
+    sync_excludes(locator, &settings)?;
+    Ok(removed)
+}
+
+/// Write patterns of ignore file into `info/exclude` of repository of
+/// `settings`.
+///
+/// Returns path to `info/exclude`, or `None` if repository is not cloned.
+///
+/// # Invariants
+///
+/// 1. Only the block between [`EXCLUDE_BEGIN`] and [`EXCLUDE_END`] is
+///    replaced, and it is removed altogether once there are no patterns.
+/// 1. Nothing is written if `info/exclude` would not change.
+///
+/// # Errors
+///
+/// - Return [`IgnoreError::Repo`] if repository cannot be opened.
+/// - Return [`IgnoreError::FileRead`] or [`IgnoreError::FileWrite`] if ignore
+///   file, or `info/exclude`, cannot be read or written.
+/// - Return [`IgnoreError::MakeDirP`] if `info` directory cannot be created.
+pub fn sync_excludes(
+    locator: &impl Locator,
+    settings: &RepoSettings,
+) -> Result<Option<PathBuf>, IgnoreError> {
+    let gitdir = settings.gitdir(locator.repos_dir());
+    if !gitdir.exists() {
+        debug!("Skip excludes of '{}', because it is not cloned", settings.name);
+        return Ok(None);
+    }
+
+    let repo = GitRepo::open(&gitdir)
+        .map_err(|err| IgnoreError::Repo { source: err, name: settings.name.clone() })?;
+    let info_dir = repo.gitdir().join("info");
+    let path = info_dir.join("exclude");
+    let old = read_lines(&path)?;
+    let new = merge_block(&old, &list(locator, &settings.name)?);
+    if new == old {
+        return Ok(Some(path));
+    }
+
+    info!("Sync ignore patterns of '{}' into '{}'", settings.name, path.display());
+    mkdirp(&info_dir)
+        .map_err(|err| IgnoreError::MakeDirP { source: err, path: info_dir.clone() })?;
+    write_lines(&path, &new)?;
+    Ok(Some(path))
+}
+
+/// Patterns listed, added, or removed through [`IgnoreContext`].
+///
+/// # Errors
+///
+/// - Return [`IgnoreError`] if ignore file cannot be read or written, or
+///   excludes cannot be synced.
+pub fn ignore(ctx: &IgnoreContext, locator: &impl Locator) -> Result<Vec<String>, IgnoreError> {
+    match &ctx.op {
+        IgnoreOp::Add { repo, patterns } => add(locator, repo, patterns),
+        IgnoreOp::Remove { repo, patterns } => remove(locator, repo, patterns),
+        IgnoreOp::List { repo } => list(locator, repo),
+    }
+}
+
+/// Replace block of `lines` Ricer manages with `patterns`.
+fn merge_block(lines: &[String], patterns: &[String]) -> Vec<String> {
+    let mut merged = Vec::new();
+    let mut inside = false;
+    for line in lines {
+        match line.as_str() {
+            EXCLUDE_BEGIN => inside = true,
+            EXCLUDE_END => inside = false,
+            _ if !inside => merged.push(line.clone()),
+            _ => (),
+        }
+    }
+
+    if !patterns.is_empty() {
+        merged.push(EXCLUDE_BEGIN.into());
+        merged.extend(patterns.iter().cloned());
+        merged.push(EXCLUDE_END.into());
+    }
+    merged
+}
+
+fn is_pattern(line: &str) -> bool {
+    !line.trim().is_empty() && !line.starts_with('#')
+}
+
+fn read_lines(path: &Path) -> Result<Vec<String>, IgnoreError> {
+    match fs::read_to_string(path) {
+        Ok(data) => Ok(data.lines().map(String::from).collect()),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(Vec::new()),
+        Err(err) => Err(IgnoreError::FileRead { source: err, path: path.into() }),
+    }
+}
+
+fn write_lines(path: &Path, lines: &[String]) -> Result<(), IgnoreError> {
+    if let Some(parent) = path.parent() {
+        mkdirp(parent).map_err(|err| IgnoreError::MakeDirP { source: err, path: parent.into() })?;
+    }
+
+    let mut data = lines.join("\n");
+    if !data.is_empty() {
+        data.push('\n');
+    }
+    fs::write(path, data).map_err(|err| IgnoreError::FileWrite { source: err, path: path.into() })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        testenv::{mock_locator, FileKind, FixtureHarness},
+        vcs::GitRepo,
+    };
+
+    use anyhow::Result;
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+    use rstest::{fixture, rstest};
+
+    #[fixture]
+    fn rice_dir() -> Result<FixtureHarness> {
+        let harness = FixtureHarness::open()?
+            .with_file("config/repos.toml", |fixture| {
+                fixture
+                    .with_data(indoc! {r#"
+                        [repos.vim]
+                        branch = "main"
+                        remote = "origin"
+                        workdir_home = true
+
+                        [repos.st]
+                        branch = "main"
+                        remote = "origin"
+                    "#})
+                    .with_kind(FileKind::Normal)
+            })
+            .with_file("config/ignores/vim.ignore", |fixture| {
+                fixture.with_data("# Caches\n.cache/\n").with_kind(FileKind::Normal)
+            })
+            .with_fake_bare_repo("repos/vim", Ok)?
+            .setup()?;
+        Ok(harness)
+    }
+
+    fn exclude(rice_dir: &FixtureHarness) -> Result<String> {
+        let repo = GitRepo::open(rice_dir.as_path().join("repos/vim.git"))?;
+        Ok(fs::read_to_string(repo.gitdir().join("info/exclude")).unwrap_or_default())
+    }
+
+    #[rstest]
+    fn add_keep_comments_and_sync_excludes(rice_dir: Result<FixtureHarness>) -> Result<()> {
+        let rice_dir = rice_dir?;
+        let locator = mock_locator(&rice_dir);
+        let added = add(&locator, "vim", &[".cache/".into(), "Downloads/".into()])?;
+        assert_eq!(added, vec!["Downloads/".to_string()]);
+        assert_eq!(list(&locator, "vim")?, vec![".cache/".to_string(), "Downloads/".into()]);
+        assert_eq!(
+            fs::read_to_string(ignore_file(&locator, "vim"))?,
+            "# Caches\n.cache/\nDownloads/\n"
+        );
+        assert!(exclude(&rice_dir)?
+            .ends_with(&format!("{EXCLUDE_BEGIN}\n.cache/\nDownloads/\n{EXCLUDE_END}\n")));
+        Ok(())
+    }
+
+    #[rstest]
+    fn remove_drop_block_once_empty(rice_dir: Result<FixtureHarness>) -> Result<()> {
+        let rice_dir = rice_dir?;
+        let locator = mock_locator(&rice_dir);
+        add(&locator, "vim", &[])?;
+        assert!(exclude(&rice_dir)?.contains(EXCLUDE_BEGIN));
+
+        let removed = remove(&locator, "vim", &[".cache/".into(), "nope".into()])?;
+        assert_eq!(removed, vec![".cache/".to_string()]);
+        assert!(!exclude(&rice_dir)?.contains(EXCLUDE_BEGIN));
+        Ok(())
+    }
+
+    #[rstest]
+    fn sync_excludes_skip_uncloned_repo(rice_dir: Result<FixtureHarness>) -> Result<()> {
+        let rice_dir = rice_dir?;
+        let locator = mock_locator(&rice_dir);
+        assert_eq!(add(&locator, "st", &["*.o".into()])?, vec!["*.o".to_string()]);
+        assert_eq!(sync_excludes(&locator, &RepoSettings::new("st"))?, None);
+        Ok(())
+    }
+
+    #[rstest]
+    fn add_return_err_missing_repo(rice_dir: Result<FixtureHarness>) -> Result<()> {
+        let rice_dir = rice_dir?;
+        let result = add(&mock_locator(&rice_dir), "nope", &["*.o".into()]);
+        assert!(matches!(result.unwrap_err(), IgnoreError::Config { .. }));
+        Ok(())
+    }
+
+    #[rstest]
+    fn merge_block_keep_foreign_lines() {
+        let lines = ["*.swp", EXCLUDE_BEGIN, "old/", EXCLUDE_END, "*.bak"].map(String::from);
+        let merged = merge_block(&lines, &["new/".into()]);
+        assert_eq!(merged, ["*.swp", "*.bak", EXCLUDE_BEGIN, "new/", EXCLUDE_END]);
+    }
+}
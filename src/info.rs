@@ -0,0 +1,438 @@
+// SPDX-FileCopyrightText: 2024 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+//! Detailed report of one repository.
+//!
+//! Debugging a single repository means piecing together its settings from
+//! `repos.toml`, where its Git directory and working directory ended up, what
+//! branch it is on, what changed, and which hooks would run for it. Through
+//! `ricer info <REPO>`, all of it is reported at once, and `--json` makes the
+//! report readable for external tooling.
+//!
+//! Repositories that are registered, but not cloned yet, are still reported,
+//! just without anything that needs their Git directory.
+
+use crate::{
+    config::{BootstrapSettings, ConfigFile, ConfigFileError, RepoConfig, RepoSettings, TomlError},
+    context::InfoContext,
+    hook::{CmdHook, CmdHookError, ResolvedHook},
+    json::Json,
+    locate::Locator,
+    ui::Prompter,
+    vcs::{CommitSummary, GitRepo, GitRepoError, RepoStatus, StatusScan},
+};
+
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Number of latest commits to report.
+pub const RECENT_COMMITS: usize = 5;
+
+/// Error types for [`repo_info`].
+#[derive(Debug, thiserror::Error)]
+pub enum InfoError {
+    #[error("Repository '{name}' is not registered")]
+    MissingRepo { name: String },
+
+    #[error("Failed to inspect repository '{name}'")]
+    Repo { source: GitRepoError, name: String },
+
+    #[error("Failed to resolve hooks of repository '{name}'")]
+    Hooks { source: Box<CmdHookError>, name: String },
+
+    #[error("Failed to read repository configuration")]
+    Config { source: ConfigFileError },
+}
+
+impl From<ConfigFileError> for InfoError {
+    fn from(err: ConfigFileError) -> Self {
+        InfoError::Config { source: err }
+    }
+}
+
+/// State of a cloned repository.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CloneInfo {
+    /// Working directory, or `None` for bare repositories.
+    pub workdir: Option<PathBuf>,
+
+    /// URL of default remote, if it is configured.
+    pub remote_url: Option<String>,
+
+    pub status: RepoStatus,
+
+    /// Latest commits of HEAD, newest first.
+    pub commits: Vec<CommitSummary>,
+
+    /// Files with uncommitted changes, relative to working directory.
+    pub changed: Vec<PathBuf>,
+}
+
+/// Everything Ricer knows about one repository.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RepoInfo {
+    pub settings: RepoSettings,
+
+    /// Location of Git directory, whether it exists or not.
+    pub gitdir: PathBuf,
+
+    /// State of repository, or `None` if it is not cloned.
+    pub clone: Option<CloneInfo>,
+
+    /// Hooks that would run for commands targeting repository.
+    pub hooks: Vec<ResolvedHook>,
+}
+
+impl RepoInfo {
+    pub fn to_json(&self) -> Json {
+        let settings = &self.settings;
+        let clone = self.clone.as_ref();
+        Json::object()
+            .with("name", &settings.name)
+            .with("gitdir", path_str(&self.gitdir))
+            .with("workdir", clone.and_then(|clone| clone.workdir.as_deref().map(path_str)))
+            .with("branch", &settings.branch)
+            .with("remote", &settings.remote)
+            .with("remote_url", clone.and_then(|clone| clone.remote_url.clone()))
+            .with("workdir_home", settings.workdir_home)
+            .with("deploy_mode", settings.deploy_mode.to_string())
+            .with("untracked", settings.untracked.map(|untracked| untracked.to_string()))
+            .with("run_hooks", settings.hooks.map(|action| action.to_string()))
+            .with("pin", settings.pin.clone())
+            .with("push_on_commit", settings.push_on_commit)
+            .with("readonly", settings.readonly)
+            .with("bootstrap", settings.bootstrap.as_ref().map(BootstrapSettings::to_json))
+            .with("status", clone.map(|clone| status_json(&clone.status)))
+            .with(
+                "commits",
+                clone
+                    .map_or_else(Vec::new, |clone| clone.commits.iter().map(commit_json).collect()),
+            )
+            .with(
+                "changed",
+                clone.map_or_else(Vec::new, |clone| {
+                    clone.changed.iter().map(|path| path_str(path)).collect()
+                }),
+            )
+            .with("hooks", self.hooks.iter().map(hook_json).collect::<Vec<_>>())
+    }
+}
+
+impl fmt::Display for RepoInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let settings = &self.settings;
+        writeln!(f, "Repository '{}'", settings.name)?;
+        writeln!(f, "  Git directory: {}", self.gitdir.display())?;
+        if let Some(CloneInfo { workdir: Some(workdir), .. }) = &self.clone {
+            writeln!(f, "  Working directory: {}", workdir.display())?;
+        }
+        writeln!(f, "  Branch: {}", settings.branch)?;
+        match self.clone.as_ref().and_then(|clone| clone.remote_url.as_deref()) {
+            Some(url) => writeln!(f, "  Remote: {} ({url})", settings.remote)?,
+            None => writeln!(f, "  Remote: {}", settings.remote)?,
+        }
+        match &self.clone {
+            Some(clone) => writeln!(f, "  Status: {}", StatusLine(&clone.status))?,
+            None => writeln!(f, "  Status: not cloned")?,
+        }
+        writeln!(f, "  Deploy mode: {}", settings.deploy_mode)?;
+        if let Some(untracked) = &settings.untracked {
+            writeln!(f, "  Untracked files: {untracked}")?;
+        }
+        if let Some(action) = &settings.hooks {
+            writeln!(f, "  Run hooks: {action}")?;
+        }
+        if let Some(pin) = &settings.pin {
+            writeln!(f, "  Pinned to: {pin}")?;
+        }
+        if settings.push_on_commit {
+            writeln!(f, "  Push on commit: yes")?;
+        }
+        if settings.readonly {
+            writeln!(f, "  Read-only: yes")?;
+        }
+        if let Some(bootstrap) = &settings.bootstrap {
+            write_bootstrap(f, bootstrap)?;
+        }
+
+        if let Some(clone) = self.clone.as_ref().filter(|clone| !clone.commits.is_empty()) {
+            writeln!(f, "Recent commits:")?;
+            for commit in &clone.commits {
+                writeln!(f, "  {commit}")?;
+            }
+        }
+        if let Some(clone) = self.clone.as_ref().filter(|clone| !clone.changed.is_empty()) {
+            writeln!(f, "Changed files:")?;
+            for path in &clone.changed {
+                writeln!(f, "  {}", path.display())?;
+            }
+        }
+        if !self.hooks.is_empty() {
+            writeln!(f, "Hooks:")?;
+            for hook in &self.hooks {
+                writeln!(f, "  {hook}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+struct StatusLine<'status>(&'status RepoStatus);
+
+impl fmt::Display for StatusLine<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let RepoStatus { branch, dirty, ahead, behind, .. } = self.0;
+        match branch {
+            Some(branch) => write!(f, "on branch '{branch}'")?,
+            None => write!(f, "detached HEAD")?,
+        }
+        match dirty {
+            true => write!(f, ", uncommitted changes")?,
+            false => write!(f, ", clean")?,
+        }
+        if *ahead > 0 {
+            write!(f, ", {ahead} ahead")?;
+        }
+        if *behind > 0 {
+            write!(f, ", {behind} behind")?;
+        }
+        Ok(())
+    }
+}
+
+fn write_bootstrap(f: &mut fmt::Formatter<'_>, bootstrap: &BootstrapSettings) -> fmt::Result {
+    if let Some(clone) = &bootstrap.clone {
+        writeln!(f, "  Bootstrap clone: {clone}")?;
+    }
+    if let Some(os) = &bootstrap.os {
+        writeln!(f, "  Bootstrap OS: {os}")?;
+    }
+    if let Some(users) = &bootstrap.users {
+        writeln!(f, "  Bootstrap users: {}", users.join(", "))?;
+    }
+    if let Some(hosts) = &bootstrap.hosts {
+        writeln!(f, "  Bootstrap hosts: {}", hosts.join(", "))?;
+    }
+    if let Some(script) = &bootstrap.on_clone {
+        writeln!(f, "  Bootstrap setup hook: {script}")?;
+    }
+    Ok(())
+}
+
+fn status_json(status: &RepoStatus) -> Json {
+    Json::object()
+        .with("branch", status.branch.clone())
+        .with("dirty", status.dirty)
+        .with("ahead", status.ahead)
+        .with("behind", status.behind)
+        .with("last_sync", status.last_sync.map(unix_secs))
+}
+
+fn commit_json(commit: &CommitSummary) -> Json {
+    Json::object()
+        .with("id", commit.id.to_string())
+        .with("summary", &commit.summary)
+        .with("author", &commit.author)
+}
+
+fn hook_json(hook: &ResolvedHook) -> Json {
+    Json::object()
+        .with("command", &hook.cmd)
+        .with("kind", hook.kind.to_string())
+        .with("index", hook.index)
+        .with("name", hook.name.clone())
+        .with("script", path_str(&hook.script))
+        .with("action", hook.action.to_string())
+}
+
+fn path_str(path: &Path) -> String {
+    path.to_string_lossy().into_owned()
+}
+
+fn unix_secs(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).map(|elapsed| elapsed.as_secs()).unwrap_or_default()
+}
+
+/// Report on repository of [`InfoContext`].
+///
+/// Hooks are resolved through `hooks` for every command that has hook
+/// definitions, as if that command targeted the repository, so they reflect
+/// its `run_hooks` setting as well as hook options given on the command-line.
+///
+/// # Errors
+///
+/// - Return [`InfoError::MissingRepo`] if repository is not registered.
+/// - Return [`InfoError::Repo`] if cloned repository cannot be inspected.
+/// - Return [`InfoError::Hooks`] if hooks cannot be resolved.
+/// - Return [`InfoError::Config`] if repository configuration cannot be
+///   read.
+pub fn repo_info<L, P>(
+    ctx: &InfoContext,
+    locator: &L,
+    hooks: &CmdHook<'_, L, P>,
+) -> Result<RepoInfo, InfoError>
+where
+    L: Locator,
+    P: Prompter,
+{
+    let settings = match ConfigFile::load(RepoConfig, locator)?.get(&ctx.repo) {
+        Ok(settings) => settings,
+        Err(ConfigFileError::Toml {
+            source: TomlError::EntryNotFound { .. } | TomlError::TableNotFound { .. },
+            ..
+        }) => return Err(InfoError::MissingRepo { name: ctx.repo.clone() }),
+        Err(err) => return Err(err.into()),
+    };
+
+    let gitdir = settings.gitdir(locator.repos_dir());
+    let clone = match gitdir.exists() {
+        true => Some(
+            inspect(&gitdir, &settings)
+                .map_err(|err| InfoError::Repo { source: err, name: ctx.repo.clone() })?,
+        ),
+        false => None,
+    };
+
+    let hooks_err = |err| InfoError::Hooks { source: Box::new(err), name: ctx.repo.clone() };
+    let mut resolved = Vec::new();
+    for cmd in hooks.commands().map_err(hooks_err)? {
+        resolved.extend(hooks.resolved_hooks(&cmd).map_err(hooks_err)?);
+    }
+
+    Ok(RepoInfo { settings, gitdir, clone, hooks: resolved })
+}
+
+fn inspect(gitdir: &Path, settings: &RepoSettings) -> Result<CloneInfo, GitRepoError> {
+    let repo = GitRepo::open(gitdir)?;
+    let scan = StatusScan { untracked: settings.untracked, assume_clean: false };
+    Ok(CloneInfo {
+        workdir: repo.workdir().map(Path::to_path_buf),
+        remote_url: repo.remote_url(&settings.remote),
+        status: repo.status_with(scan)?,
+        commits: repo.recent_commits(RECENT_COMMITS)?,
+        changed: repo.changed_files(scan)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        cli::Cli,
+        context::Context,
+        locate::MockLocator,
+        testenv::{mock_locator, FileKind, FixtureHarness},
+    };
+
+    use anyhow::Result;
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+    use rstest::{fixture, rstest};
+    use std::fs;
+
+    #[fixture]
+    fn rice_dir() -> Result<FixtureHarness> {
+        let harness = FixtureHarness::open()?
+            .with_file("config/repos.toml", |fixture| {
+                fixture
+                    .with_data(indoc! {r#"
+                        [repos.dwm]
+                        branch = "main"
+                        remote = "origin"
+                        workdir_home = false
+                        run_hooks = "prompt"
+
+                        [repos.st]
+                        branch = "main"
+                        remote = "origin"
+                        workdir_home = false
+
+                        [repos.st.bootstrap]
+                        clone = "https://example.com/st.git"
+                        os = "unix"
+                        hosts = ["lovelace"]
+                    "#})
+                    .with_kind(FileKind::Normal)
+            })
+            .with_file("config/hooks.toml", |fixture| {
+                fixture
+                    .with_data(indoc! {r#"
+                        [hooks]
+                        commit = [{ pre = "lint.sh", name = "lint" }]
+                    "#})
+                    .with_kind(FileKind::Normal)
+            })
+            .with_repo("repos/dwm", |repo| repo.stage("config.h", "configure DWM here"))?
+            .setup()?;
+        Ok(harness)
+    }
+
+    fn info(args: &[&str], locator: &MockLocator) -> Result<RepoInfo, InfoError> {
+        let ctx = Context::from(Cli::parse_args(args).unwrap());
+        let hooks = CmdHook::load(&ctx, locator).unwrap();
+        match &ctx {
+            Context::Info(info_ctx) => repo_info(info_ctx, locator, &hooks),
+            _ => unreachable!("Expected info context"),
+        }
+    }
+
+    #[rstest]
+    fn repo_info_report_cloned_repo(rice_dir: Result<FixtureHarness>) -> Result<()> {
+        let rice_dir = rice_dir?;
+        let locator = mock_locator(&rice_dir);
+        let gitdir = rice_dir.as_path().join("repos/dwm.git");
+        fs::write(gitdir.join("config.h"), "configure DWM elsewhere")?;
+
+        let result = info(&["ricer", "--run-hooks=always", "info", "dwm"], &locator)?;
+        let clone = result.clone.as_ref().unwrap();
+        assert_eq!(result.gitdir, gitdir);
+        assert_eq!(clone.status.branch.as_deref(), Some("main"));
+        assert!(clone.status.dirty);
+        assert_eq!(clone.changed, vec![PathBuf::from("config.h")]);
+        assert_eq!(clone.commits.len(), 1);
+        assert_eq!(clone.commits[0].summary, "Inital commit");
+
+        // INVARIANT: hooks are restricted by run_hooks setting of repository.
+        assert_eq!(result.hooks.len(), 1);
+        assert_eq!(result.hooks[0].action.to_string(), "prompt");
+
+        let text = result.to_string();
+        assert!(text.contains("  Status: on branch 'main', uncommitted changes\n"));
+        assert!(text.contains("Changed files:\n  config.h\n"));
+        assert!(text.contains("Hooks:\n  commit pre #1 'lint'"));
+        Ok(())
+    }
+
+    #[rstest]
+    fn repo_info_report_uncloned_repo(rice_dir: Result<FixtureHarness>) -> Result<()> {
+        let rice_dir = rice_dir?;
+        let locator = mock_locator(&rice_dir);
+        let result = info(&["ricer", "--no-hooks", "info", "st", "--json"], &locator)?;
+        assert_eq!(result.clone, None);
+        assert!(result.hooks.is_empty());
+
+        let json = result.to_json();
+        assert_eq!(json.get("status"), Some(&Json::Null));
+        assert_eq!(json.get("commits"), Some(&Json::Array(Vec::new())));
+        let bootstrap = json.get("bootstrap").unwrap();
+        assert_eq!(
+            bootstrap.get("clone").and_then(Json::as_str),
+            Some("https://example.com/st.git")
+        );
+        assert_eq!(bootstrap.get("hosts"), Some(&Json::from(vec!["lovelace"])));
+        assert_eq!(bootstrap.get("users"), Some(&Json::Null));
+        assert!(result.to_string().contains("  Status: not cloned\n  Deploy mode: worktree\n"));
+        Ok(())
+    }
+
+    #[rstest]
+    fn repo_info_return_err_missing_repo(rice_dir: Result<FixtureHarness>) -> Result<()> {
+        let rice_dir = rice_dir?;
+        let result = info(&["ricer", "info", "vim"], &mock_locator(&rice_dir));
+        assert!(matches!(result, Err(InfoError::MissingRepo { .. })));
+        Ok(())
+    }
+}
@@ -0,0 +1,332 @@
+// SPDX-FileCopyrightText: 2024 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+//! Repository initialization.
+//!
+//! Initializing a repository through `ricer init` creates its Git directory in
+//! the repository directory and registers it in the repository configuration
+//! file. Repositories can start from a [`RepoTemplate`] through `--template`,
+//! whose files are copied into the new working directory and staged for the
+//! first commit. Through `--initial-commit`, that first commit is made right
+//! away, even if there is nothing to commit yet.
+
+mod template;
+
+pub use template::*;
+
+use crate::{
+    config::{
+        validate_repo_name, ConfigFile, ConfigFileError, RepoConfig, RepoNameError, RepoSettings,
+    },
+    context::{EnvSnapshot, InitContext},
+    locate::Locator,
+    plan::{self, DryRunEffect},
+    util::remove_file_pruning,
+    vcs::{GitRepo, GitRepoError, RepoInit},
+};
+
+use git2::Oid;
+use log::info;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Default branch of new repositories.
+pub const DEFAULT_BRANCH: &str = "main";
+
+/// Default remote of new repositories.
+pub const DEFAULT_REMOTE: &str = "origin";
+
+/// Message of initial commit made through `--initial-commit`.
+pub const INITIAL_COMMIT_MESSAGE: &str = "Initial commit";
+
+/// Error types for [`init_repo`].
+#[derive(Debug, thiserror::Error)]
+pub enum InitError {
+    #[error("Repository '{name}' already exists")]
+    AlreadyExists { name: String },
+
+    #[error("Invalid repository name '{name}'")]
+    BadName { source: RepoNameError, name: String },
+
+    #[error("Failed to initialize repository '{name}'")]
+    Repo { source: GitRepoError, name: String },
+
+    #[error("Failed to apply template")]
+    Template { source: TemplateError },
+
+    #[error("Failed to register repository")]
+    Config { source: ConfigFileError },
+}
+
+impl From<TemplateError> for InitError {
+    fn from(err: TemplateError) -> Self {
+        InitError::Template { source: err }
+    }
+}
+
+impl From<ConfigFileError> for InitError {
+    fn from(err: ConfigFileError) -> Self {
+        InitError::Config { source: err }
+    }
+}
+
+/// Newly initialized repository.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NewRepo {
+    /// Settings the repository was registered with.
+    pub settings: RepoSettings,
+
+    /// Path to Git directory of repository.
+    pub gitdir: PathBuf,
+
+    /// Template files staged in repository, relative to its working directory.
+    pub files: Vec<PathBuf>,
+
+    /// Initial commit, if one was made.
+    pub commit: Option<Oid>,
+}
+
+/// Initialize new repository from [`InitContext`].
+///
+/// Repositories that use the home directory as their working directory are
+/// made through the fake bare technique with `home` as working directory.
+///
+/// # Invariants
+///
+/// 1. Template is located before anything is created, so a misspelled
+///    template name does not leave behind an empty repository.
+/// 1. Git directory and files copied from the template are removed again if
+///    the template cannot be applied, its files cannot be staged, or the
+///    initial commit cannot be made.
+/// 1. Repository is only registered once it has been fully initialized.
+///
+/// # Errors
+///
+/// - Return [`InitError::BadName`] if repository name is invalid, see
+///   [`validate_repo_name`].
+/// - Return [`InitError::AlreadyExists`] if its Git directory already exists,
+///   or repository is already registered without `--force`.
+/// - Return [`InitError::Template`] if template does not exist or cannot be
+///   applied.
+/// - Return [`InitError::Repo`] if Git repository cannot be initialized, or
+///   initial commit cannot be made, e.g., no Git identity is configured.
+/// - Return [`InitError::Config`] if repository cannot be registered.
+pub fn init_repo(
+    ctx: &InitContext,
+    locator: &impl Locator,
+    home: &Path,
+) -> Result<NewRepo, InitError> {
+    validate_repo_name(&ctx.name)
+        .map_err(|err| InitError::BadName { source: err, name: ctx.name.clone() })?;
+    let mut config = ConfigFile::load(RepoConfig, locator)?;
+    let gitdir = locator.repos_dir().join(format!("{}.git", ctx.name));
+    let registered = config.entries()?.iter().any(|repo| repo.name == ctx.name);
+    if gitdir.exists() || (registered && !ctx.force) {
+        return Err(InitError::AlreadyExists { name: ctx.name.clone() });
+    }
+
+    let settings = RepoSettings::new(&ctx.name)
+        .branch(ctx.branch.as_deref().unwrap_or(DEFAULT_BRANCH))
+        .remote(ctx.remote.as_deref().unwrap_or(DEFAULT_REMOTE))
+        .workdir_home(ctx.workdir_home);
+    let template = match &ctx.template {
+        Some(name) => Some(RepoTemplate::find(locator.templates_dir(), name)?),
+        None => None,
+    };
+
+    info!("Initialize repository '{}' at '{}'", ctx.name, gitdir.display());
+    let effect =
+        || DryRunEffect::Git { gitdir: gitdir.clone(), action: "Initialize repository".into() };
+    let mut files = Vec::new();
+    let result = match plan::perform(effect) {
+        true => init_gitdir(&settings, locator, home, template.as_ref(), ctx, &mut files),
+        false => Ok(None),
+    };
+    if result.is_err() {
+        // INVARIANT: never leave a half initialized repository behind, not
+        // even template files in the home directory.
+        if settings.workdir_home {
+            for file in &files {
+                let _ = remove_file_pruning(home, file);
+            }
+        }
+        if gitdir.exists() {
+            let _ = fs::remove_dir_all(&gitdir);
+        }
+    }
+    let commit = result?;
+
+    // INVARIANT: only replace existing registration when forced to.
+    match ctx.force {
+        true => {
+            config.upsert(settings.clone())?;
+        }
+        false => config.add_new(settings.clone())?,
+    }
+    config.save()?;
+
+    Ok(NewRepo { settings, gitdir, files, commit })
+}
+
+fn init_gitdir(
+    settings: &RepoSettings,
+    locator: &impl Locator,
+    home: &Path,
+    template: Option<&RepoTemplate>,
+    ctx: &InitContext,
+    files: &mut Vec<PathBuf>,
+) -> Result<Option<Oid>, InitError> {
+    let repo_err = |err| InitError::Repo { source: err, name: settings.name.clone() };
+    let init = RepoInit {
+        branch: Some(settings.branch.clone()),
+        workdir: settings.workdir_home.then(|| home.into()),
+    };
+    let repo =
+        GitRepo::init_with(locator.repos_dir().join(&settings.name), &init).map_err(repo_err)?;
+
+    if let Some(template) = template {
+        let vars = TemplateVars {
+            name: settings.name.clone(),
+            branch: settings.branch.clone(),
+            remote: settings.remote.clone(),
+            vars: ctx.shared.vars.clone(),
+            ..Default::default()
+        }
+        .machine(&EnvSnapshot::current());
+        let workdir = repo.workdir().unwrap_or(home).to_path_buf();
+        info!("Apply template '{}' to '{}'", template.name(), workdir.display());
+        *files = template.apply(&workdir, &vars)?;
+        repo.stage(files).map_err(repo_err)?;
+    }
+
+    let commit = match ctx.initial_commit {
+        true => {
+            info!("Make initial commit on '{}'", settings.branch);
+            Some(repo.commit(INITIAL_COMMIT_MESSAGE).map_err(repo_err)?)
+        }
+        false => None,
+    };
+
+    Ok(commit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testenv::{mock_locator, parse_ctx, FileKind, FixtureHarness};
+
+    use anyhow::Result;
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+    use rstest::{fixture, rstest};
+
+    #[fixture]
+    fn rice_dir() -> Result<FixtureHarness> {
+        let harness = FixtureHarness::open()?
+            .with_file("config/repos.toml", |fixture| {
+                fixture
+                    .with_data(indoc! {r#"
+                        [repos.vim]
+                        branch = "main"
+                        remote = "origin"
+                        workdir_home = false
+                    "#})
+                    .with_kind(FileKind::Normal)
+            })
+            .with_file("config/templates/dotfiles/README.md", |fixture| {
+                fixture.with_data("# {{name}}\n")
+            })
+            .with_file("config/templates/suckless/.config/{{name}}/config.h", |fixture| {
+                fixture.with_data("/* {{name}} */\n")
+            })
+            .with_file("home/.keep", |fixture| fixture)
+            .setup()?;
+        Ok(harness)
+    }
+
+    #[rstest]
+    fn init_repo_apply_template(rice_dir: Result<FixtureHarness>) -> Result<()> {
+        let rice_dir = rice_dir?;
+        let locator = mock_locator(&rice_dir);
+        let home = rice_dir.as_path().join("home");
+        let ctx = parse_ctx::<InitContext>(
+            "init",
+            &["dwm", "--branch", "dev", "--template", "dotfiles"],
+        )?;
+        let repo = init_repo(&ctx, &locator, &home)?;
+
+        assert_eq!(repo.settings, RepoSettings::new("dwm").branch("dev").remote("origin"));
+        assert_eq!(repo.files, vec![PathBuf::from("README.md")]);
+        assert_eq!(fs::read_to_string(repo.gitdir.join("README.md"))?, "# dwm\n");
+
+        let git = GitRepo::open(&repo.gitdir)?;
+        assert!(git.status()?.dirty);
+        assert_eq!(git.status()?.branch.as_deref(), Some("dev"));
+
+        let config = ConfigFile::load(RepoConfig, &locator)?;
+        assert_eq!(config.get("dwm")?, repo.settings);
+        Ok(())
+    }
+
+    #[rstest]
+    fn init_repo_workdir_home(rice_dir: Result<FixtureHarness>) -> Result<()> {
+        let rice_dir = rice_dir?;
+        let locator = mock_locator(&rice_dir);
+        let home = rice_dir.as_path().join("home");
+        let ctx =
+            parse_ctx::<InitContext>("init", &["dwm", "--workdir-home", "--template", "dotfiles"])?;
+        let repo = init_repo(&ctx, &locator, &home)?;
+
+        assert!(GitRepo::open(&repo.gitdir)?.is_fake_bare());
+        assert_eq!(fs::read_to_string(home.join("README.md"))?, "# dwm\n");
+        Ok(())
+    }
+
+    #[rstest]
+    fn init_repo_force_replace_registration(rice_dir: Result<FixtureHarness>) -> Result<()> {
+        let rice_dir = rice_dir?;
+        let locator = mock_locator(&rice_dir);
+        let home = rice_dir.as_path().join("home");
+        let ctx = parse_ctx::<InitContext>("init", &["vim", "--branch", "dev", "--force"])?;
+        let repo = init_repo(&ctx, &locator, &home)?;
+
+        let config = ConfigFile::load(RepoConfig, &locator)?;
+        assert_eq!(config.get("vim")?, RepoSettings::new("vim").branch("dev").remote("origin"));
+        assert_eq!(config.entries()?, vec![repo.settings]);
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::already_exists(vec!["vim"])]
+    #[case::missing_template(vec!["dwm", "--template", "nope"])]
+    #[case::bad_name(vec!["../dwm"])]
+    #[case::no_identity(vec!["dwm", "--workdir-home", "--template", "suckless", "--initial-commit"])]
+    fn init_repo_return_err(
+        rice_dir: Result<FixtureHarness>,
+        #[case] args: Vec<&str>,
+    ) -> Result<()> {
+        let rice_dir = rice_dir?;
+        let home = rice_dir.as_path().join("home");
+        let result =
+            init_repo(&parse_ctx::<InitContext>("init", &args)?, &mock_locator(&rice_dir), &home);
+
+        // INVARIANT: initial commit needs a Git identity, which only counts as
+        // missing when test environment lacks one.
+        let commit = args.contains(&"--initial-commit");
+        if commit && result.is_ok() {
+            return Ok(());
+        }
+        match args[0] {
+            "vim" => assert!(matches!(result.unwrap_err(), InitError::AlreadyExists { .. })),
+            "../dwm" => assert!(matches!(result.unwrap_err(), InitError::BadName { .. })),
+            _ if commit => assert!(matches!(result.unwrap_err(), InitError::Repo { .. })),
+            _ => assert!(matches!(result.unwrap_err(), InitError::Template { .. })),
+        }
+        assert!(!rice_dir.as_path().join("repos/dwm.git").exists());
+        assert!(!home.join(".config").exists());
+        assert!(home.join(".keep").exists());
+        Ok(())
+    }
+}
@@ -0,0 +1,304 @@
+// SPDX-FileCopyrightText: 2024 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+use crate::{context::EnvSnapshot, util::remove_file_pruning};
+
+use log::debug;
+use mkdirp::mkdirp;
+use std::{
+    fs,
+    io::Error as IoError,
+    path::{Path, PathBuf},
+};
+
+/// Error types for [`RepoTemplate`].
+#[derive(Debug, thiserror::Error)]
+pub enum TemplateError {
+    #[error("Template '{name}' does not exist at '{path}'")]
+    NotFound { name: String, path: PathBuf },
+
+    #[error("Template would overwrite existing file '{path}'")]
+    Conflict { path: PathBuf },
+
+    #[error("Failed to read template file '{path}'")]
+    FileRead { source: IoError, path: PathBuf },
+
+    #[error("Failed to write template file '{path}'")]
+    FileWrite { source: IoError, path: PathBuf },
+}
+
+/// Values of placeholders that templates can refer to.
+///
+/// Placeholders are written as `{{name}}`, `{{branch}}`, and `{{remote}}` in
+/// both the contents and the file names of a template. Name of user and host
+/// are written as `{{user}}` and `{{host}}`, see [`TemplateVars::machine`].
+/// Variables passed through `--context KEY=VALUE` are written as
+/// `{{var.KEY}}`.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct TemplateVars {
+    pub name: String,
+    pub branch: String,
+    pub remote: String,
+    pub user: Option<String>,
+    pub host: Option<String>,
+    pub vars: Vec<(String, String)>,
+}
+
+impl TemplateVars {
+    /// Expand every known placeholder in `input`.
+    ///
+    /// # Invariants
+    ///
+    /// Unknown placeholders are left as-is, so templates can still carry text
+    /// meant for other tools that use the same syntax. So are `{{user}}` and
+    /// `{{host}}` if their names are unknown.
+    pub fn expand(&self, input: &str) -> String {
+        let mut output = input
+            .replace("{{name}}", &self.name)
+            .replace("{{branch}}", &self.branch)
+            .replace("{{remote}}", &self.remote);
+        if let Some(user) = &self.user {
+            output = output.replace("{{user}}", user);
+        }
+        if let Some(host) = &self.host {
+            output = output.replace("{{host}}", host);
+        }
+        // INVARIANT: later values of a variable replace earlier ones.
+        for (key, value) in self.vars.iter().rev() {
+            output = output.replace(&format!("{{{{var.{key}}}}}"), value);
+        }
+        output
+    }
+
+    /// Take names of user and host from environment `snapshot`.
+    pub fn machine(mut self, snapshot: &EnvSnapshot) -> Self {
+        self.user = snapshot.user.clone();
+        self.host = snapshot.host.clone();
+        self
+    }
+}
+
+/// Skeleton of files for newly initialized repositories.
+///
+/// Templates live in the template directory of the configuration directory,
+/// one subdirectory per template, e.g., `templates/dotfiles/README.md`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RepoTemplate {
+    name: String,
+    root: PathBuf,
+}
+
+impl RepoTemplate {
+    /// Find template `name` in `templates_dir`.
+    ///
+    /// # Errors
+    ///
+    /// - Return [`TemplateError::NotFound`] if template does not exist.
+    pub fn find(templates_dir: &Path, name: impl Into<String>) -> Result<Self, TemplateError> {
+        let name = name.into();
+        let root = templates_dir.join(&name);
+        if !root.is_dir() {
+            return Err(TemplateError::NotFound { name, path: root });
+        }
+
+        Ok(Self { name, root })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Relative paths of every file in template, after placeholder expansion.
+    ///
+    /// # Errors
+    ///
+    /// - Return [`TemplateError::FileRead`] if template cannot be walked.
+    pub fn files(&self, vars: &TemplateVars) -> Result<Vec<PathBuf>, TemplateError> {
+        let mut files = Vec::new();
+        walk(&self.root, &self.root, &mut files)?;
+        Ok(files.iter().map(|path| expand_path(path, vars)).collect())
+    }
+
+    /// Copy template into `workdir` with placeholders expanded.
+    ///
+    /// Returns paths of copied files relative to `workdir`. File contents that
+    /// are not valid UTF-8 are copied verbatim. Executable permissions are
+    /// preserved, so templates can ship scripts.
+    ///
+    /// # Invariants
+    ///
+    /// Every destination is checked before anything is written, so a template
+    /// never partially overwrites existing files. Files already copied are
+    /// removed again if a later one cannot be copied.
+    ///
+    /// # Errors
+    ///
+    /// - Return [`TemplateError::Conflict`] if a template file already exists
+    ///   in `workdir`.
+    /// - Return [`TemplateError::FileRead`] if template cannot be read.
+    /// - Return [`TemplateError::FileWrite`] if template cannot be written.
+    pub fn apply(
+        &self,
+        workdir: &Path,
+        vars: &TemplateVars,
+    ) -> Result<Vec<PathBuf>, TemplateError> {
+        let mut sources = Vec::new();
+        walk(&self.root, &self.root, &mut sources)?;
+        let files = sources.iter().map(|path| expand_path(path, vars)).collect::<Vec<_>>();
+
+        if let Some(path) = files.iter().map(|file| workdir.join(file)).find(|path| path.exists()) {
+            return Err(TemplateError::Conflict { path });
+        }
+
+        let mut written = Vec::new();
+        let result = sources.iter().zip(&files).try_for_each(|(source, file)| {
+            let source = self.root.join(source);
+            let dest = workdir.join(file);
+            debug!("Copy template file '{}' to '{}'", source.display(), dest.display());
+            let read_err = |err| TemplateError::FileRead { source: err, path: source.clone() };
+            let write_err = |err| TemplateError::FileWrite { source: err, path: dest.clone() };
+
+            let data = fs::read(&source).map_err(read_err)?;
+            let data = match String::from_utf8(data) {
+                Ok(text) => vars.expand(&text).into_bytes(),
+                Err(err) => err.into_bytes(),
+            };
+            mkdirp(dest.parent().unwrap()).map_err(write_err)?;
+            written.push(file);
+            fs::write(&dest, data).map_err(write_err)?;
+            let perms = fs::metadata(&source).map_err(read_err)?.permissions();
+            fs::set_permissions(&dest, perms).map_err(write_err)
+        });
+        if let Err(err) = result {
+            for file in written {
+                let _ = remove_file_pruning(workdir, file);
+            }
+            return Err(err);
+        }
+
+        Ok(files)
+    }
+}
+
+/// Collect files below `dir` relative to `root`, sorted for stable output.
+fn walk(root: &Path, dir: &Path, files: &mut Vec<PathBuf>) -> Result<(), TemplateError> {
+    let read_err = |err| TemplateError::FileRead { source: err, path: dir.into() };
+    let mut paths = fs::read_dir(dir)
+        .map_err(read_err)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(read_err)?;
+    paths.sort();
+
+    for path in paths {
+        match path.is_dir() {
+            true => walk(root, &path, files)?,
+            false => files.push(path.strip_prefix(root).unwrap().to_path_buf()),
+        }
+    }
+
+    Ok(())
+}
+
+fn expand_path(path: &Path, vars: &TemplateVars) -> PathBuf {
+    path.components().map(|part| vars.expand(&part.as_os_str().to_string_lossy())).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::OsType,
+        testenv::{FileKind, FixtureHarness},
+    };
+
+    use anyhow::Result;
+    use is_executable::IsExecutable;
+    use pretty_assertions::assert_eq;
+    use rstest::{fixture, rstest};
+
+    #[fixture]
+    fn template_dir() -> Result<FixtureHarness> {
+        let harness = FixtureHarness::open()?
+            .with_file("templates/dotfiles/README.md", |fixture| {
+                fixture.with_data("# {{name}}\n\nTrack {{branch}} of {{remote}}. {{other}}\n")
+            })
+            .with_file("templates/dotfiles/.gitignore", |fixture| fixture.with_data("*.swp\n"))
+            .with_file("templates/dotfiles/hooks/{{name}}.sh", |fixture| {
+                fixture.with_data("#!/bin/sh\necho {{name}}\n").with_kind(FileKind::Script)
+            })
+            .setup()?;
+        Ok(harness)
+    }
+
+    fn vars() -> TemplateVars {
+        TemplateVars {
+            name: "vim".into(),
+            branch: "main".into(),
+            remote: "origin".into(),
+            ..Default::default()
+        }
+    }
+
+    #[rstest]
+    fn template_vars_expand_context_vars() {
+        let vars = TemplateVars {
+            vars: vec![("theme".into(), "gruvbox".into()), ("theme".into(), "nord".into())],
+            ..vars()
+        };
+        assert_eq!(
+            vars.expand("{{name}} in {{var.theme}}, {{var.font}}"),
+            "vim in nord, {{var.font}}"
+        );
+    }
+
+    #[rstest]
+    #[case::known(EnvSnapshot::new(OsType::Unix).user("awkless").host("lovelace"), "awkless@lovelace")]
+    #[case::unknown(EnvSnapshot::new(OsType::Unix), "{{user}}@{{host}}")]
+    fn template_vars_expand_machine(#[case] snapshot: EnvSnapshot, #[case] expect: &str) {
+        let vars = vars().machine(&snapshot);
+        assert_eq!(vars.expand("{{user}}@{{host}}"), expect);
+    }
+
+    #[rstest]
+    fn repo_template_apply_expand_placeholders(template_dir: Result<FixtureHarness>) -> Result<()> {
+        let template_dir = template_dir?;
+        let workdir = template_dir.as_path().join("vim.git");
+        let template = RepoTemplate::find(&template_dir.as_path().join("templates"), "dotfiles")?;
+        let files = template.apply(&workdir, &vars())?;
+
+        let expect: Vec<PathBuf> =
+            vec![".gitignore".into(), "README.md".into(), "hooks/vim.sh".into()];
+        assert_eq!(files, expect);
+        assert_eq!(template.files(&vars())?, expect);
+        assert_eq!(
+            fs::read_to_string(workdir.join("README.md"))?,
+            "# vim\n\nTrack main of origin. {{other}}\n"
+        );
+        assert!(workdir.join("hooks/vim.sh").is_executable());
+        Ok(())
+    }
+
+    #[rstest]
+    fn repo_template_apply_return_err_conflict(template_dir: Result<FixtureHarness>) -> Result<()> {
+        let template_dir = template_dir?;
+        let workdir = template_dir.as_path().join("vim.git");
+        mkdirp(&workdir)?;
+        fs::write(workdir.join("README.md"), "mine")?;
+
+        let template = RepoTemplate::find(&template_dir.as_path().join("templates"), "dotfiles")?;
+        let result = template.apply(&workdir, &vars());
+        assert!(matches!(result.unwrap_err(), TemplateError::Conflict { .. }));
+        assert_eq!(fs::read_to_string(workdir.join("README.md"))?, "mine");
+        assert!(!workdir.join(".gitignore").exists());
+        Ok(())
+    }
+
+    #[rstest]
+    fn repo_template_find_return_err_not_found(template_dir: Result<FixtureHarness>) -> Result<()> {
+        let template_dir = template_dir?;
+        let result = RepoTemplate::find(&template_dir.as_path().join("templates"), "nope");
+        assert!(matches!(result.unwrap_err(), TemplateError::NotFound { .. }));
+        Ok(())
+    }
+}
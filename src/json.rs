@@ -0,0 +1,478 @@
+// SPDX-FileCopyrightText: 2024 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+//! Minimal JSON data exchange support.
+//!
+//! Ricer uses TOML for its own configuration files, but some features need to
+//! talk JSON with the outside world, e.g., reading responses from forge APIs,
+//! or writing machine-readable output for external tooling. This module only
+//! provides what those features need: a JSON value type that can be parsed
+//! from a string, and serialized back into one.
+//!
+//! Object members keep the order they were inserted or parsed in, so
+//! serialized output is deterministic.
+//!
+//! JSON data may come from untrusted sources, like forge APIs, so parsing
+//! refuses arrays and objects nested deeper than [`MAX_DEPTH`], instead of
+//! overflowing the stack.
+
+use std::{fmt, str::FromStr};
+
+/// Deepest nesting of arrays and objects that parsing accepts.
+pub const MAX_DEPTH: usize = 128;
+
+/// Error types for [`Json`].
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+pub enum JsonError {
+    #[error("Failed to parse JSON data at byte {offset}: {msg}")]
+    BadParse { offset: usize, msg: String },
+
+    #[error("Failed to parse JSON data at byte {offset}: nested deeper than {limit} levels")]
+    TooDeep { offset: usize, limit: usize },
+}
+
+/// JSON value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    /// Construct empty JSON object.
+    pub fn object() -> Self {
+        Json::Object(Vec::new())
+    }
+
+    /// Insert member into JSON object.
+    ///
+    /// Will replace member if it already exists. Does nothing if `self` is not
+    /// an object.
+    pub fn insert(&mut self, key: impl Into<String>, value: impl Into<Json>) {
+        if let Json::Object(members) = self {
+            let key = key.into();
+            let value = value.into();
+            match members.iter_mut().find(|(name, _)| *name == key) {
+                Some((_, old)) => *old = value,
+                None => members.push((key, value)),
+            }
+        }
+    }
+
+    /// Builder form of [`Json::insert`].
+    pub fn with(mut self, key: impl Into<String>, value: impl Into<Json>) -> Self {
+        self.insert(key, value);
+        self
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(members) => members.iter().find(|(name, _)| name == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(data) => Some(data.as_str()),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Json::Bool(data) => Some(*data),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Json::Number(data) => Some(*data),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Json]> {
+        match self {
+            Json::Array(data) => Some(data.as_slice()),
+            _ => None,
+        }
+    }
+}
+
+impl From<bool> for Json {
+    fn from(data: bool) -> Self {
+        Json::Bool(data)
+    }
+}
+
+impl From<&str> for Json {
+    fn from(data: &str) -> Self {
+        Json::String(data.into())
+    }
+}
+
+impl From<String> for Json {
+    fn from(data: String) -> Self {
+        Json::String(data)
+    }
+}
+
+impl From<&String> for Json {
+    fn from(data: &String) -> Self {
+        Json::String(data.clone())
+    }
+}
+
+impl From<f64> for Json {
+    fn from(data: f64) -> Self {
+        Json::Number(data)
+    }
+}
+
+impl From<u64> for Json {
+    fn from(data: u64) -> Self {
+        Json::Number(data as f64)
+    }
+}
+
+impl From<usize> for Json {
+    fn from(data: usize) -> Self {
+        Json::Number(data as f64)
+    }
+}
+
+impl<T: Into<Json>> From<Option<T>> for Json {
+    fn from(data: Option<T>) -> Self {
+        match data {
+            Some(data) => data.into(),
+            None => Json::Null,
+        }
+    }
+}
+
+impl<T: Into<Json>> From<Vec<T>> for Json {
+    fn from(data: Vec<T>) -> Self {
+        Json::Array(data.into_iter().map(Into::into).collect())
+    }
+}
+
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Json::Null => write!(f, "null"),
+            Json::Bool(data) => write!(f, "{data}"),
+            Json::Number(data) if data.is_finite() => write!(f, "{data}"),
+            Json::Number(_) => write!(f, "null"),
+            Json::String(data) => write_escaped(f, data),
+            Json::Array(data) => {
+                write!(f, "[")?;
+                for (idx, value) in data.iter().enumerate() {
+                    if idx > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{value}")?;
+                }
+                write!(f, "]")
+            }
+            Json::Object(data) => {
+                write!(f, "{{")?;
+                for (idx, (key, value)) in data.iter().enumerate() {
+                    if idx > 0 {
+                        write!(f, ",")?;
+                    }
+                    write_escaped(f, key)?;
+                    write!(f, ":{value}")?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+fn write_escaped(f: &mut fmt::Formatter<'_>, data: &str) -> fmt::Result {
+    write!(f, "\"")?;
+    for c in data.chars() {
+        match c {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{c}")?,
+        }
+    }
+    write!(f, "\"")
+}
+
+impl FromStr for Json {
+    type Err = JsonError;
+
+    fn from_str(data: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser { data: data.as_bytes(), pos: 0, depth: 0 };
+        let value = parser.parse_value()?;
+        parser.skip_whitespace();
+        if parser.pos != parser.data.len() {
+            return Err(parser.error("trailing characters"));
+        }
+        Ok(value)
+    }
+}
+
+struct Parser<'data> {
+    data: &'data [u8],
+    pos: usize,
+
+    /// Arrays and objects currently open.
+    depth: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, msg: &str) -> JsonError {
+        JsonError::BadParse { offset: self.pos, msg: msg.into() }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.data.get(self.pos).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(b' ' | b'\t' | b'\n' | b'\r')) {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<(), JsonError> {
+        if self.peek() != Some(byte) {
+            return Err(self.error(&format!("expected '{}'", byte as char)));
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    fn expect_literal(&mut self, literal: &str, value: Json) -> Result<Json, JsonError> {
+        if !self.data[self.pos..].starts_with(literal.as_bytes()) {
+            return Err(self.error(&format!("expected '{literal}'")));
+        }
+        self.pos += literal.len();
+        Ok(value)
+    }
+
+    fn parse_value(&mut self) -> Result<Json, JsonError> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some(b'n') => self.expect_literal("null", Json::Null),
+            Some(b't') => self.expect_literal("true", Json::Bool(true)),
+            Some(b'f') => self.expect_literal("false", Json::Bool(false)),
+            Some(b'"') => Ok(Json::String(self.parse_string()?)),
+            Some(b'[') => self.nested(Self::parse_array),
+            Some(b'{') => self.nested(Self::parse_object),
+            Some(b'-' | b'0'..=b'9') => self.parse_number(),
+            Some(_) => Err(self.error("unexpected character")),
+            None => Err(self.error("unexpected end of data")),
+        }
+    }
+
+    // INVARIANT: recursion is bounded by `MAX_DEPTH`, whatever the data looks like.
+    fn nested(
+        &mut self,
+        parse: fn(&mut Self) -> Result<Json, JsonError>,
+    ) -> Result<Json, JsonError> {
+        if self.depth == MAX_DEPTH {
+            return Err(JsonError::TooDeep { offset: self.pos, limit: MAX_DEPTH });
+        }
+        self.depth += 1;
+        let value = parse(self);
+        self.depth -= 1;
+        value
+    }
+
+    fn parse_array(&mut self) -> Result<Json, JsonError> {
+        self.expect(b'[')?;
+        let mut values = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(b']') {
+            self.pos += 1;
+            return Ok(Json::Array(values));
+        }
+
+        loop {
+            values.push(self.parse_value()?);
+            self.skip_whitespace();
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                Some(b']') => {
+                    self.pos += 1;
+                    return Ok(Json::Array(values));
+                }
+                _ => return Err(self.error("expected ',' or ']'")),
+            }
+        }
+    }
+
+    fn parse_object(&mut self) -> Result<Json, JsonError> {
+        self.expect(b'{')?;
+        let mut members = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(b'}') {
+            self.pos += 1;
+            return Ok(Json::Object(members));
+        }
+
+        loop {
+            self.skip_whitespace();
+            let key = self.parse_string()?;
+            self.skip_whitespace();
+            self.expect(b':')?;
+            let value = self.parse_value()?;
+            members.push((key, value));
+            self.skip_whitespace();
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                Some(b'}') => {
+                    self.pos += 1;
+                    return Ok(Json::Object(members));
+                }
+                _ => return Err(self.error("expected ',' or '}'")),
+            }
+        }
+    }
+
+    fn parse_number(&mut self) -> Result<Json, JsonError> {
+        let start = self.pos;
+        while matches!(self.peek(), Some(b'-' | b'+' | b'.' | b'e' | b'E' | b'0'..=b'9')) {
+            self.pos += 1;
+        }
+        let text = std::str::from_utf8(&self.data[start..self.pos]).unwrap();
+        text.parse::<f64>().map(Json::Number).map_err(|_| self.error("invalid number"))
+    }
+
+    fn parse_hex4(&mut self) -> Result<u32, JsonError> {
+        let hex = self.data.get(self.pos..self.pos + 4).ok_or_else(|| self.error("bad escape"))?;
+        let hex = std::str::from_utf8(hex).map_err(|_| self.error("bad escape"))?;
+        let code = u32::from_str_radix(hex, 16).map_err(|_| self.error("bad escape"))?;
+        self.pos += 4;
+        Ok(code)
+    }
+
+    fn parse_string(&mut self) -> Result<String, JsonError> {
+        self.expect(b'"')?;
+        let mut buffer = Vec::new();
+        loop {
+            match self.peek() {
+                None => return Err(self.error("unterminated string")),
+                Some(b'"') => {
+                    self.pos += 1;
+                    break;
+                }
+                Some(b'\\') => {
+                    self.pos += 1;
+                    let escape = self.peek().ok_or_else(|| self.error("bad escape"))?;
+                    self.pos += 1;
+                    let c = match escape {
+                        b'"' => '"',
+                        b'\\' => '\\',
+                        b'/' => '/',
+                        b'b' => '\u{8}',
+                        b'f' => '\u{c}',
+                        b'n' => '\n',
+                        b'r' => '\r',
+                        b't' => '\t',
+                        b'u' => {
+                            let mut code = self.parse_hex4()?;
+                            // INVARIANT: combine UTF-16 surrogate pairs into one code point.
+                            if (0xd800..0xdc00).contains(&code)
+                                && self.data[self.pos..].starts_with(b"\\u")
+                            {
+                                self.pos += 2;
+                                let low = self.parse_hex4()?;
+                                code = 0x10000 + ((code - 0xd800) << 10) + (low - 0xdc00);
+                            }
+                            char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER)
+                        }
+                        _ => return Err(self.error("bad escape")),
+                    };
+                    let mut utf8 = [0; 4];
+                    buffer.extend_from_slice(c.encode_utf8(&mut utf8).as_bytes());
+                }
+                Some(byte) => {
+                    buffer.push(byte);
+                    self.pos += 1;
+                }
+            }
+        }
+
+        String::from_utf8(buffer).map_err(|_| self.error("invalid UTF-8"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use anyhow::Result;
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::null("null", Json::Null)]
+    #[case::bool(" true ", Json::Bool(true))]
+    #[case::number("-12.5e1", Json::Number(-125.0))]
+    #[case::string(r#""a\"bé\n""#, Json::String("a\"bé\n".into()))]
+    #[case::surrogate_pair(r#""😀""#, Json::String("😀".into()))]
+    #[case::array("[1, \"two\", []]", Json::Array(vec![
+        Json::Number(1.0), Json::String("two".into()), Json::Array(vec![]),
+    ]))]
+    #[case::object(r#"{"name": "vim", "topics": ["dotfiles"]}"#, Json::object()
+        .with("name", "vim")
+        .with("topics", vec!["dotfiles"])
+    )]
+    fn json_parse_str_return_value(#[case] input: &str, #[case] expect: Json) -> Result<()> {
+        let result: Json = input.parse()?;
+        assert_eq!(result, expect);
+        Ok(())
+    }
+
+    #[rstest]
+    fn json_parse_str_return_err_bad_parse(
+        #[values("", "[1,", "{\"a\" 1}", "nul", "\"open", "1 2")] input: &str,
+    ) {
+        let result: Result<Json, JsonError> = input.parse();
+        assert!(matches!(result.unwrap_err(), JsonError::BadParse { .. }));
+    }
+
+    #[rstest]
+    #[case::array("[", "]")]
+    #[case::object("{\"a\":", "}")]
+    fn json_parse_str_limit_nesting(#[case] open: &str, #[case] close: &str) -> Result<()> {
+        let nested = |depth| format!("{}1{}", open.repeat(depth), close.repeat(depth));
+        nested(MAX_DEPTH).parse::<Json>()?;
+
+        let result = "[".repeat(100_000).parse::<Json>();
+        assert_eq!(result, Err(JsonError::TooDeep { offset: MAX_DEPTH, limit: MAX_DEPTH }));
+        let result = nested(MAX_DEPTH + 1).parse::<Json>();
+        assert!(matches!(result, Err(JsonError::TooDeep { limit: MAX_DEPTH, .. })));
+        Ok(())
+    }
+
+    #[test]
+    fn json_display_round_trip() -> Result<()> {
+        let value = Json::object()
+            .with("name", "a \"quoted\"\tname")
+            .with("dirty", true)
+            .with("ahead", 3u64)
+            .with("branch", Json::Null)
+            .with("list", vec!["x", "y"]);
+        let expect = r#"{"name":"a \"quoted\"\tname","dirty":true,"ahead":3,"branch":null,"list":["x","y"]}"#;
+        assert_eq!(value.to_string(), expect);
+        let result: Json = value.to_string().parse()?;
+        assert_eq!(result, value);
+        Ok(())
+    }
+}
@@ -50,12 +50,57 @@
 //! [explain-ricing]: pesos.github.io/2020/07/14/what-is-ricing.html
 //! [contrib-guide]: https://github.com/rice-configs/ricer/blob/main/CONTRIBUTING.md
 
+pub mod add;
+pub mod bootstrap;
+pub mod bugreport;
+pub mod cancel;
+pub mod clean;
 pub mod cli;
+pub mod clock;
+pub mod clone;
+pub mod commit;
+pub mod completions;
 pub mod config;
 pub mod context;
+pub mod crypt;
+pub mod daemon;
+pub mod deploy;
+pub mod deprecate;
+pub mod doctor;
+pub mod examples;
+pub mod foreach;
+pub mod forge;
+pub mod history;
 pub mod hook;
+pub mod hostkey;
+pub mod ignore;
+pub mod info;
+pub mod init;
+pub mod json;
 pub mod locate;
+pub mod manager;
+pub mod ops;
+pub mod owns;
+pub mod plan;
+pub mod plugin;
+pub mod profile;
+pub mod pull;
+pub mod readonly;
+pub mod relocate;
+pub mod remotes;
+pub mod runtime;
+pub mod sed;
+pub mod snapshot;
+pub mod split;
+pub mod state;
+pub mod stats;
+pub mod status;
+pub mod switch;
+pub mod timing;
+pub mod ui;
+pub mod util;
 pub mod vcs;
+pub mod watchdog;
 
 #[cfg(test)]
 pub mod testenv;
@@ -16,11 +16,21 @@
 //!   and hook scripts.
 //! - `$XDG_DATA_HOME/ricer` contains tracked Git
 //!   repositories to manipulate.
+//! - `$XDG_STATE_HOME/ricer` contains state data generated by Ricer itself,
+//!   like status snapshots for external tooling.
 //!
 //! The [`DefaultLocator`] uses this directory layout information to properly
 //! locate expected paths for various standard configuration files, Git
 //! repositories, and hook scripts.
 //!
+//! Named profiles keep independent rice setups on one machine. A profile is
+//! simply another directory layout, [`ProfileLayout`], that places each
+//! directory of its base layout into a `profiles/<name>` subdirectory.
+//!
+//! Directories of the standard layout can also be replaced one by one through
+//! [`OverrideLayout`], e.g., by `--config-dir` or [`CONFIG_DIR_ENV`]. Profiles
+//! are then placed inside of the replaced directories.
+//!
 //! [xdg]: https://specifications.freedesktop.org/basedir-spec/latest/
 
 use directories::ProjectDirs;
@@ -30,10 +40,25 @@
 #[cfg(test)]
 use mockall::automock;
 
+/// Variable naming the configuration directory of Ricer.
+pub const CONFIG_DIR_ENV: &str = "RICER_CONFIG_DIR";
+
+/// Variable naming the data directory of Ricer, which holds repositories.
+pub const DATA_DIR_ENV: &str = "RICER_DATA_DIR";
+
+/// Variable naming the state directory of Ricer.
+pub const STATE_DIR_ENV: &str = "RICER_STATE_DIR";
+
+/// Variable naming the profile to use.
+pub const PROFILE_ENV: &str = "RICER_PROFILE";
+
 #[derive(Debug, PartialEq, Eq, thiserror::Error)]
 pub enum LocateError {
     #[error("Cannot determine path to home directory")]
     NoWayHome,
+
+    #[error("Invalid profile name '{name}'")]
+    BadProfileName { name: String },
 }
 
 /// Configuration data locator.
@@ -53,6 +78,24 @@ pub trait Locator {
 
     /// Expected absolute path to repository configuration file.
     fn repos_config(&self) -> &Path;
+
+    /// Expected absolute path to general Ricer configuration file.
+    fn ricer_config(&self) -> &Path;
+
+    /// Expected absolute path to state data directory.
+    fn state_dir(&self) -> &Path;
+
+    /// Expected absolute path to log directory.
+    fn logs_dir(&self) -> &Path;
+
+    /// Expected absolute path to lockfile of exact repository commits.
+    fn lock_file(&self) -> &Path;
+
+    /// Expected absolute path to repository template directory.
+    fn templates_dir(&self) -> &Path;
+
+    /// Expected absolute path to ignore file directory.
+    fn ignores_dir(&self) -> &Path;
 }
 
 /// Default configuration data locator.
@@ -67,6 +110,12 @@ pub struct DefaultLocator {
     hooks_config: PathBuf,
     repos_dir: PathBuf,
     repos_config: PathBuf,
+    ricer_config: PathBuf,
+    state_dir: PathBuf,
+    logs_dir: PathBuf,
+    lock_file: PathBuf,
+    templates_dir: PathBuf,
+    ignores_dir: PathBuf,
 }
 
 impl DefaultLocator {
@@ -77,13 +126,37 @@ pub fn locate(layout: impl DirLayout) -> Self {
         let hooks_config = config_dir.join("hooks.toml");
         let repos_dir = layout.repo_dir().join("ricer");
         let repos_config = config_dir.join("repos.toml");
+        let ricer_config = config_dir.join("config.toml");
+        let state_dir = layout.state_dir().to_path_buf();
+        let logs_dir = layout.repo_dir().join("logs");
+        let lock_file = config_dir.join("ricer.lock");
+        let templates_dir = config_dir.join("templates");
+        let ignores_dir = config_dir.join("ignores");
 
         debug!("Configuration directory located at '{}'", config_dir.display());
         debug!("Hook script directory located at '{}'", hooks_dir.display());
         debug!("Repository directory located at '{}'", repos_dir.display());
         debug!("Repository configuration file located at '{}'", repos_config.display());
         debug!("Hook configuration file located at '{}'", hooks_config.display());
-        Self { config_dir, hooks_dir, hooks_config, repos_dir, repos_config }
+        debug!("Ricer configuration file located at '{}'", ricer_config.display());
+        debug!("State directory located at '{}'", state_dir.display());
+        debug!("Log directory located at '{}'", logs_dir.display());
+        debug!("Lockfile located at '{}'", lock_file.display());
+        debug!("Template directory located at '{}'", templates_dir.display());
+        debug!("Ignore file directory located at '{}'", ignores_dir.display());
+        Self {
+            config_dir,
+            hooks_dir,
+            hooks_config,
+            repos_dir,
+            repos_config,
+            ricer_config,
+            state_dir,
+            logs_dir,
+            lock_file,
+            templates_dir,
+            ignores_dir,
+        }
     }
 }
 
@@ -107,6 +180,30 @@ fn repos_dir(&self) -> &Path {
     fn repos_config(&self) -> &Path {
         self.repos_config.as_path()
     }
+
+    fn ricer_config(&self) -> &Path {
+        self.ricer_config.as_path()
+    }
+
+    fn state_dir(&self) -> &Path {
+        self.state_dir.as_path()
+    }
+
+    fn logs_dir(&self) -> &Path {
+        self.logs_dir.as_path()
+    }
+
+    fn lock_file(&self) -> &Path {
+        self.lock_file.as_path()
+    }
+
+    fn templates_dir(&self) -> &Path {
+        self.templates_dir.as_path()
+    }
+
+    fn ignores_dir(&self) -> &Path {
+        self.ignores_dir.as_path()
+    }
 }
 
 /// Specify expected configuration directory layout.
@@ -117,6 +214,9 @@ pub trait DirLayout {
 
     /// Absolute path to directory where repository data will be stored.
     fn repo_dir(&self) -> &Path;
+
+    /// Absolute path to directory where state data will be stored.
+    fn state_dir(&self) -> &Path;
 }
 
 /// Configuration directory layout handler following [XDG Base Directory
@@ -147,4 +247,195 @@ fn config_dir(&self) -> &Path {
     fn repo_dir(&self) -> &Path {
         self.layout.data_dir()
     }
+
+    fn state_dir(&self) -> &Path {
+        // INVARIANT: XDG state directory only exists on Linux, so fallback to local data.
+        self.layout.state_dir().unwrap_or(self.layout.data_local_dir())
+    }
+}
+
+impl<T> DirLayout for &T
+where
+    T: DirLayout + ?Sized,
+{
+    fn config_dir(&self) -> &Path {
+        (**self).config_dir()
+    }
+
+    fn repo_dir(&self) -> &Path {
+        (**self).repo_dir()
+    }
+
+    fn state_dir(&self) -> &Path {
+        (**self).state_dir()
+    }
+}
+
+/// Directory layout with directories of a base layout replaced.
+///
+/// Directories that are not replaced stay those of the base layout.
+///
+/// # Invariants
+///
+/// 1. Caller must validate paths themselves.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OverrideLayout {
+    config_dir: PathBuf,
+    repo_dir: PathBuf,
+    state_dir: PathBuf,
+}
+
+impl OverrideLayout {
+    /// Construct layout with directories of `base` layout.
+    pub fn new(base: impl DirLayout) -> Self {
+        Self {
+            config_dir: base.config_dir().into(),
+            repo_dir: base.repo_dir().into(),
+            state_dir: base.state_dir().into(),
+        }
+    }
+
+    /// Replace configuration directory with `dir`, if any.
+    pub fn with_config_dir(mut self, dir: Option<PathBuf>) -> Self {
+        if let Some(dir) = dir {
+            self.config_dir = dir;
+        }
+        self
+    }
+
+    /// Replace repository data directory with `dir`, if any.
+    pub fn with_repo_dir(mut self, dir: Option<PathBuf>) -> Self {
+        if let Some(dir) = dir {
+            self.repo_dir = dir;
+        }
+        self
+    }
+
+    /// Replace state directory with `dir`, if any.
+    pub fn with_state_dir(mut self, dir: Option<PathBuf>) -> Self {
+        if let Some(dir) = dir {
+            self.state_dir = dir;
+        }
+        self
+    }
+}
+
+impl DirLayout for OverrideLayout {
+    fn config_dir(&self) -> &Path {
+        self.config_dir.as_path()
+    }
+
+    fn repo_dir(&self) -> &Path {
+        self.repo_dir.as_path()
+    }
+
+    fn state_dir(&self) -> &Path {
+        self.state_dir.as_path()
+    }
+}
+
+/// Directory layout of a named profile.
+///
+/// Places each directory of a base layout into a `profiles/<name>`
+/// subdirectory, e.g., `$XDG_CONFIG_HOME/ricer/profiles/work`.
+///
+/// # Invariants
+///
+/// 1. Profile names are valid directory names that do not start with a dot.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProfileLayout {
+    name: String,
+    config_dir: PathBuf,
+    repo_dir: PathBuf,
+    state_dir: PathBuf,
+}
+
+impl ProfileLayout {
+    /// Construct layout of profile `name` inside of `base` layout.
+    ///
+    /// # Errors
+    ///
+    /// - Return [`LocateError::BadProfileName`] if `name` is empty, starts
+    ///   with a dot, or contains path separators.
+    pub fn new(base: impl DirLayout, name: impl Into<String>) -> Result<Self, LocateError> {
+        let name = name.into();
+        if name.is_empty() || name.starts_with('.') || name.contains(['/', '\\']) {
+            return Err(LocateError::BadProfileName { name });
+        }
+
+        trace!("Construct directory layout for profile '{name}'");
+        let config_dir = base.config_dir().join("profiles").join(&name);
+        let repo_dir = base.repo_dir().join("profiles").join(&name);
+        let state_dir = base.state_dir().join("profiles").join(&name);
+        Ok(Self { name, config_dir, repo_dir, state_dir })
+    }
+
+    pub fn name(&self) -> &str {
+        self.name.as_str()
+    }
+}
+
+impl DirLayout for ProfileLayout {
+    fn config_dir(&self) -> &Path {
+        self.config_dir.as_path()
+    }
+
+    fn repo_dir(&self) -> &Path {
+        self.repo_dir.as_path()
+    }
+
+    fn state_dir(&self) -> &Path {
+        self.state_dir.as_path()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    #[test]
+    fn profile_layout_new_nest_base_layout() -> Result<(), LocateError> {
+        let mut base = MockDirLayout::new();
+        base.expect_config_dir().return_const("/config".into());
+        base.expect_repo_dir().return_const("/data".into());
+        base.expect_state_dir().return_const("/state".into());
+
+        let layout = ProfileLayout::new(&base, "work")?;
+        assert_eq!(layout.config_dir(), Path::new("/config/profiles/work"));
+        assert_eq!(layout.repo_dir(), Path::new("/data/profiles/work"));
+        assert_eq!(layout.state_dir(), Path::new("/state/profiles/work"));
+        Ok(())
+    }
+
+    #[test]
+    fn override_layout_with_dirs_replace_base_dirs() -> Result<(), LocateError> {
+        let mut base = MockDirLayout::new();
+        base.expect_config_dir().return_const("/config".into());
+        base.expect_repo_dir().return_const("/data".into());
+        base.expect_state_dir().return_const("/state".into());
+
+        let layout = OverrideLayout::new(&base)
+            .with_config_dir(Some("/rice/config".into()))
+            .with_repo_dir(None)
+            .with_state_dir(Some("/rice/state".into()));
+        assert_eq!(layout.config_dir(), Path::new("/rice/config"));
+        assert_eq!(layout.repo_dir(), Path::new("/data"));
+        assert_eq!(layout.state_dir(), Path::new("/rice/state"));
+
+        let profile = ProfileLayout::new(&layout, "work")?;
+        assert_eq!(profile.config_dir(), Path::new("/rice/config/profiles/work"));
+        Ok(())
+    }
+
+    #[rstest]
+    fn profile_layout_new_return_err_bad_profile_name(
+        #[values("", ".hidden", "nested/profile")] name: &str,
+    ) {
+        let base = MockDirLayout::new();
+        let result = ProfileLayout::new(&base, name);
+        assert_eq!(result.unwrap_err(), LocateError::BadProfileName { name: name.into() });
+    }
 }
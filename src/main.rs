@@ -1,14 +1,11 @@
 // SPDX-FileCopyrightText: 2024 Jason Pena <jasonpena@awkless.com>
 // SPDX-License-Identifier: MIT
 
-use ricer::cli::Cli;
-use ricer::context::Context;
-use ricer::hook::{CmdHook, HookKind};
-use ricer::locate::{DefaultLocator, XdgDirLayout};
+use ricer::cancel::{self, CANCELLED_EXIT_CODE};
+use ricer::runtime::{ExitCode, RicerBuilder};
+use ricer::ui;
 
-use anyhow::Result;
-use log::{error, LevelFilter};
-use std::ffi::OsString;
+use log::{debug, error, warn, LevelFilter};
 
 fn main() {
     env_logger::Builder::new()
@@ -18,10 +15,15 @@ fn main() {
         .format_indent(Some(8))
         .init();
 
-    let code = match run_ricer(std::env::args_os) {
+    let code = match RicerBuilder::new().run(std::env::args_os()) {
         Ok(code) => code,
+        Err(err) if cancel::is_cancelled() => {
+            debug!("{}", ui::ErrorReport::new(err.as_ref()));
+            warn!("Cancelled");
+            ExitCode::Code(CANCELLED_EXIT_CODE)
+        }
         Err(err) => {
-            error!("{:?}", err);
+            error!("{}", ui::ErrorReport::new(err.as_ref()));
             ExitCode::Failure
         }
     }
@@ -29,36 +31,3 @@ fn main() {
 
     std::process::exit(code);
 }
-
-fn run_ricer<I, F>(args: F) -> Result<ExitCode>
-where
-    I: IntoIterator<Item = OsString>,
-    F: FnOnce() -> I + Clone,
-{
-    let opts = Cli::parse_args(args())?;
-    log::set_max_level(opts.log_opts.log_level_filter());
-
-    let ctx = Context::from(opts);
-    let layout = XdgDirLayout::layout()?;
-    let locator = DefaultLocator::locate(layout);
-    let hook_mgr = CmdHook::load(&ctx, &locator)?;
-    hook_mgr.run_hooks(HookKind::Pre)?;
-    hook_mgr.run_hooks(HookKind::Post)?;
-
-    Ok(ExitCode::Success)
-}
-
-#[derive(Debug)]
-pub enum ExitCode {
-    Success,
-    Failure,
-}
-
-impl From<ExitCode> for i32 {
-    fn from(code: ExitCode) -> Self {
-        match code {
-            ExitCode::Success => 0,
-            ExitCode::Failure => 1,
-        }
-    }
-}
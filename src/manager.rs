@@ -0,0 +1,1031 @@
+// SPDX-FileCopyrightText: 2024 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+//! Repository management.
+//!
+//! Commands that operate on registered repositories directly, without a
+//! module of their own, are executed by [`RepoManager`]: `ricer list` lists
+//! registered repositories, and repositories of the repository directory that
+//! were never registered, as plain text, JSON, or TOML, `ricer push` pushes
+//! every cloned repository to its
+//! remote, `ricer delete` unregisters a repository and removes its Git
+//! directory, along with the files it deployed into the user's home directory
+//! if the user agrees, `ricer enter` opens a shell inside a repository, and
+//! `ricer <repo> <git-args>` runs Git on a repository.
+//!
+//! Running Git on a repository sets both its Git directory and its working
+//! directory, so fake bare repositories that use the user's home directory as
+//! their working directory work like any other repository, e.g.,
+//! `ricer vim add .vimrc`.
+
+use crate::{
+    config::{ConfigFile, ConfigFileError, RepoConfig, RepoSettings, Settings, TomlError},
+    context::{
+        Context, DeleteContext, EnterContext, GitContext, ListContext, ListFormat, PushContext,
+        MUTATING_GIT_COMMANDS,
+    },
+    hook::PlanChanges,
+    json::Json,
+    locate::Locator,
+    plan::{self, DryRunEffect},
+    ui::{Prompter, TerminalPrompter, UiError},
+    util::{remove_file_pruning, RepoIter, RepoResult, RepoSelector, SelectError},
+    vcs::{GitRepo, GitRepoError, StatusScan, UntrackedFiles},
+};
+
+use log::{debug, info, warn};
+use std::{
+    env,
+    ffi::{OsStr, OsString},
+    fmt, fs,
+    io::{Error as IoError, ErrorKind},
+    path::{Path, PathBuf},
+    process::Command,
+};
+use toml_edit::{value, Array, DocumentMut, Item, Key, Table};
+
+/// Error types for [`RepoManager`].
+#[derive(Debug, thiserror::Error)]
+pub enum ManagerError {
+    #[error("Repository '{name}' is not registered")]
+    MissingRepo { name: String },
+
+    #[error("Repository '{name}' was not found at '{path}'")]
+    NotCloned { name: String, path: PathBuf },
+
+    #[error("Failed to operate on repository '{name}'")]
+    Repo { source: GitRepoError, name: String },
+
+    #[error("Failed to remove '{path}'")]
+    RemoveDir { source: IoError, path: PathBuf },
+
+    #[error("Failed to remove file '{path}'")]
+    RemoveFile { source: IoError, path: PathBuf },
+
+    #[error("Failed to ask user")]
+    Prompt { source: UiError },
+
+    #[error("Failed to read directory '{path}'")]
+    ReadDir { source: IoError, path: PathBuf },
+
+    #[error("Failed to run '{program}'")]
+    Spawn { source: IoError, program: PathBuf },
+
+    #[error("Failed to read repository configuration")]
+    Config { source: ConfigFileError },
+
+    #[error("Failed to select repositories")]
+    Select { source: SelectError },
+}
+
+impl From<ConfigFileError> for ManagerError {
+    fn from(err: ConfigFileError) -> Self {
+        ManagerError::Config { source: err }
+    }
+}
+
+impl From<SelectError> for ManagerError {
+    fn from(err: SelectError) -> Self {
+        ManagerError::Select { source: err }
+    }
+}
+
+impl From<UiError> for ManagerError {
+    fn from(err: UiError) -> Self {
+        ManagerError::Prompt { source: err }
+    }
+}
+
+/// Repository listed by `ricer list`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RepoListing {
+    pub name: String,
+
+    /// Settings of repository, or `None` if repository was found in
+    /// repository directory without being registered.
+    pub settings: Option<RepoSettings>,
+
+    pub gitdir: PathBuf,
+
+    /// Repository exists in repository directory.
+    pub cloned: bool,
+
+    /// Tracked or untracked files asked for, relative to working directory.
+    pub files: Vec<PathBuf>,
+}
+
+impl RepoListing {
+    pub fn to_json(&self) -> Json {
+        Json::object()
+            .with("name", &self.name)
+            .with("registered", self.settings.is_some())
+            .with("cloned", self.cloned)
+            .with("gitdir", self.gitdir.to_string_lossy().into_owned())
+            .with("settings", self.settings.as_ref().map(RepoSettings::to_json))
+            .with(
+                "files",
+                self.files
+                    .iter()
+                    .map(|path| path.to_string_lossy().into_owned())
+                    .collect::<Vec<_>>(),
+            )
+    }
+
+    /// Serialize listing into TOML table laid out like entries of the
+    /// repository configuration file, along with `registered`, `cloned`, and
+    /// `files` keys.
+    fn to_toml(&self) -> (Key, Item) {
+        let (key, mut item) = match &self.settings {
+            Some(settings) => settings.to_toml(),
+            None => (Key::new(&self.name), Item::Table(Table::new())),
+        };
+        item["registered"] = value(self.settings.is_some());
+        item["cloned"] = value(self.cloned);
+        if !self.files.is_empty() {
+            let files = self.files.iter().map(|path| path.to_string_lossy().into_owned());
+            item["files"] = value(Array::from_iter(files));
+        }
+        (key, item)
+    }
+}
+
+impl fmt::Display for RepoListing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)?;
+        match (&self.settings, self.cloned) {
+            (None, _) => write!(f, " (not registered)")?,
+            (Some(_), false) => write!(f, " (not cloned)")?,
+            (Some(_), true) => (),
+        }
+        for file in &self.files {
+            write!(f, "\n  {}", file.display())?;
+        }
+        Ok(())
+    }
+}
+
+/// Branch of repository pushed to `remote`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pushed {
+    pub remote: String,
+    pub branch: String,
+}
+
+impl fmt::Display for Pushed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "pushed to '{}/{}'", self.remote, self.branch)
+    }
+}
+
+/// Result of pushing one repository.
+pub type PushResult = RepoResult<Pushed, GitRepoError>;
+
+/// Repository deleted by `ricer delete`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeletedRepo {
+    pub settings: RepoSettings,
+
+    /// Files of working directory that were removed along with repository.
+    pub removed: Vec<PathBuf>,
+}
+
+impl fmt::Display for DeletedRepo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Deleted '{}'", self.settings.name)?;
+        match self.removed.len() {
+            0 => Ok(()),
+            1 => write!(f, " and 1 file of its working directory"),
+            count => write!(f, " and {count} files of its working directory"),
+        }
+    }
+}
+
+/// Result of command executed by [`RepoManager::execute`].
+#[derive(Debug)]
+pub enum ManagerReport {
+    List(Vec<RepoListing>, ListFormat),
+    Push(Vec<(String, PushResult)>),
+    Delete(Box<DeletedRepo>),
+
+    /// Exit code of program ran on repository, i.e., a shell or Git.
+    Exited(i32),
+}
+
+impl ManagerReport {
+    /// Names of repositories operated on.
+    pub fn repos(&self) -> Vec<&str> {
+        match self {
+            ManagerReport::Push(results) => results
+                .iter()
+                .filter(|(_, result)| matches!(result, RepoResult::Done(_)))
+                .map(|(name, _)| name.as_str())
+                .collect(),
+            ManagerReport::Delete(deleted) => vec![deleted.settings.name.as_str()],
+            ManagerReport::List(..) | ManagerReport::Exited(_) => Vec::new(),
+        }
+    }
+
+    /// Command failed on at least one repository.
+    pub fn has_failed(&self) -> bool {
+        match self {
+            ManagerReport::Push(results) => results.iter().any(|(_, result)| result.is_failed()),
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for ManagerReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ManagerReport::List(listings, ListFormat::Plain) => {
+                listings.iter().try_for_each(|listing| writeln!(f, "{listing}"))
+            }
+            ManagerReport::List(listings, ListFormat::Json) => {
+                let listings = listings.iter().map(RepoListing::to_json).collect::<Vec<_>>();
+                writeln!(f, "{}", Json::from(listings))
+            }
+            ManagerReport::List(listings, ListFormat::Toml) => {
+                let mut repos = Table::new();
+                repos.set_implicit(true);
+                for listing in listings {
+                    let (key, item) = listing.to_toml();
+                    repos.insert_formatted(&key, item);
+                }
+                let mut document = DocumentMut::new();
+                document.insert("repos", Item::Table(repos));
+                write!(f, "{document}")
+            }
+            ManagerReport::Push(results) => {
+                results.iter().try_for_each(|(name, result)| writeln!(f, "{name}: {result}"))
+            }
+            ManagerReport::Delete(deleted) => writeln!(f, "{deleted}"),
+            ManagerReport::Exited(_) => Ok(()),
+        }
+    }
+}
+
+/// Execute commands on registered repositories.
+///
+/// Drives [`GitRepo`] and the repository configuration file for the commands
+/// listed in the [module documentation][self].
+///
+/// # Invariants
+///
+/// 1. Git directories outside of the repository directory, i.e., set through
+///    `path`, are never removed, only unregistered.
+/// 1. Repositories are only unregistered once their Git directory is gone.
+/// 1. Files of working directories are only removed once the user agreed to
+///    it through a [`Prompter`].
+#[derive(Debug)]
+pub struct RepoManager<'loc, L, P = TerminalPrompter>
+where
+    L: Locator,
+    P: Prompter,
+{
+    locator: &'loc L,
+    shell: PathBuf,
+    changes: PlanChanges,
+    prompter: P,
+}
+
+impl<'loc, L> RepoManager<'loc, L>
+where
+    L: Locator,
+{
+    /// Construct new repository manager that enters repositories through the
+    /// shell of `$SHELL`, or `/bin/sh` without one.
+    pub fn new(locator: &'loc L) -> Self {
+        let shell = env::var_os("SHELL").filter(|shell| !shell.is_empty());
+        Self {
+            locator,
+            shell: shell.map_or_else(|| PathBuf::from("/bin/sh"), PathBuf::from),
+            changes: PlanChanges::default(),
+            prompter: TerminalPrompter,
+        }
+    }
+}
+
+impl<'loc, L, P> RepoManager<'loc, L, P>
+where
+    L: Locator,
+    P: Prompter,
+{
+    /// Ask user about removing files through `prompter` instead.
+    pub fn with_prompter<Q: Prompter>(self, prompter: Q) -> RepoManager<'loc, L, Q> {
+        let Self { locator, shell, changes, .. } = self;
+        RepoManager { locator, shell, changes, prompter }
+    }
+
+    /// Enter repositories through `shell` instead.
+    pub fn shell(mut self, shell: impl Into<PathBuf>) -> Self {
+        self.shell = shell.into();
+        self
+    }
+
+    /// Leave repositories skipped by plan `changes` of pre hooks alone.
+    pub fn plan_changes(mut self, changes: PlanChanges) -> Self {
+        self.changes = changes;
+        self
+    }
+
+    /// Execute command of `ctx`.
+    ///
+    /// Returns `None` if command is not executed by repository manager, or if
+    /// Git is ran on an unregistered repository, which may name a plugin
+    /// instead.
+    ///
+    /// # Errors
+    ///
+    /// - Return [`ManagerError`] if command fails, see its methods.
+    pub fn execute(&self, ctx: &Context) -> Result<Option<ManagerReport>, ManagerError> {
+        let report = match ctx {
+            Context::List(ctx) => ManagerReport::List(self.list(ctx)?, ctx.format),
+            Context::Push(ctx) => ManagerReport::Push(self.push(ctx)?),
+            Context::Delete(ctx) => ManagerReport::Delete(Box::new(self.delete(ctx)?)),
+            Context::Enter(ctx) => ManagerReport::Exited(self.enter(ctx)?),
+            Context::Git(ctx) => match self.git(ctx)? {
+                Some(code) => ManagerReport::Exited(code),
+                None => return Ok(None),
+            },
+            _ => return Ok(None),
+        };
+
+        Ok(Some(report))
+    }
+
+    /// List every registered repository, along with its tracked or untracked
+    /// files if asked to.
+    ///
+    /// Git directories of the repository directory that no registered
+    /// repository uses are listed after registered repositories, sorted by
+    /// name. Untracked files are looked for according to the `untracked`
+    /// setting of each repository, or the default of its kind if it is not
+    /// registered.
+    ///
+    /// # Errors
+    ///
+    /// - Return [`ManagerError::Repo`] if files of repository cannot be
+    ///   listed.
+    /// - Return [`ManagerError::ReadDir`] if repository directory cannot be
+    ///   read.
+    /// - Return [`ManagerError::Config`] if repository configuration cannot be
+    ///   read.
+    pub fn list(&self, ctx: &ListContext) -> Result<Vec<RepoListing>, ManagerError> {
+        let mut listings = Vec::new();
+        for settings in ConfigFile::load(RepoConfig, self.locator)?.entries()? {
+            let gitdir = settings.gitdir(self.locator.repos_dir());
+            let cloned = gitdir.exists();
+            let files = match cloned {
+                true => self.list_files(ctx, &settings.name, &gitdir, settings.untracked)?,
+                false => Vec::new(),
+            };
+            listings.push(RepoListing {
+                name: settings.name.clone(),
+                settings: Some(settings),
+                gitdir,
+                cloned,
+                files,
+            });
+        }
+
+        let registered = listings.iter().map(|listing| listing.gitdir.clone()).collect::<Vec<_>>();
+        for gitdir in self.unregistered_gitdirs(&registered)? {
+            let name = gitdir.file_stem().unwrap_or_default().to_string_lossy().into_owned();
+            let files = self.list_files(ctx, &name, &gitdir, None)?;
+            listings.push(RepoListing { name, settings: None, gitdir, cloned: true, files });
+        }
+
+        Ok(listings)
+    }
+
+    fn list_files(
+        &self,
+        ctx: &ListContext,
+        name: &str,
+        gitdir: &Path,
+        untracked: Option<UntrackedFiles>,
+    ) -> Result<Vec<PathBuf>, ManagerError> {
+        let mut files = Vec::new();
+        if !ctx.tracked && !ctx.untracked {
+            return Ok(files);
+        }
+
+        let repo_err = |err| ManagerError::Repo { source: err, name: name.into() };
+        let repo = GitRepo::open(gitdir).map_err(repo_err)?;
+        if ctx.tracked {
+            files.extend(repo.tracked_files().map_err(repo_err)?);
+        }
+        if ctx.untracked {
+            let scan = StatusScan { untracked, assume_clean: false };
+            files.extend(repo.untracked_files(scan).map_err(repo_err)?);
+        }
+        Ok(files)
+    }
+
+    /// Git directories of repository directory not in `registered`.
+    fn unregistered_gitdirs(&self, registered: &[PathBuf]) -> Result<Vec<PathBuf>, ManagerError> {
+        let repos_dir = self.locator.repos_dir();
+        let read_err = |err| ManagerError::ReadDir { source: err, path: repos_dir.into() };
+        let entries = match fs::read_dir(repos_dir) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(read_err(err)),
+        };
+
+        let mut gitdirs = Vec::new();
+        for entry in entries {
+            let path = entry.map_err(read_err)?.path();
+            if path.is_dir()
+                && path.extension() == Some(OsStr::new("git"))
+                && !registered.contains(&path)
+            {
+                gitdirs.push(path);
+            }
+        }
+        gitdirs.sort();
+        Ok(gitdirs)
+    }
+
+    /// Push branch of every cloned repository to its remote, unless another
+    /// remote or branch is given on the command-line.
+    ///
+    /// Returns result of each repository in configuration order. Repositories
+    /// that cannot be pushed fail on their own without stopping the others.
+    ///
+    /// # Errors
+    ///
+    /// - Return [`ManagerError::Config`] if repository configuration cannot be
+    ///   read.
+    pub fn push(&self, ctx: &PushContext) -> Result<Vec<(String, PushResult)>, ManagerError> {
+        let repos = RepoIter::load::<ManagerError>(self.locator, RepoSelector::default())?
+            .changes(&self.changes);
+        let results = repos.try_each(|settings, repo| {
+            let remote = ctx.remote.clone().unwrap_or_else(|| settings.remote.clone());
+            let branch = ctx.branch.clone().unwrap_or_else(|| settings.branch.clone());
+            info!("Push '{branch}' of '{}' to '{remote}'", settings.name);
+            repo.push(&remote, &branch)?;
+            Ok(Pushed { remote, branch })
+        });
+
+        Ok(results.into_iter().map(|(settings, result)| (settings.name, result)).collect())
+    }
+
+    /// Unregister repository, and remove its Git directory.
+    ///
+    /// Repositories that use the user's home directory as their working
+    /// directory list the files they track first, and ask the user whether to
+    /// remove them too, unless `--keep-files` was given. Directories emptied
+    /// that way are removed along with them, up to the working directory.
+    ///
+    /// # Invariants
+    ///
+    /// 1. Files are removed before the Git directory, so a repository whose
+    ///    files could not be removed can be deleted again.
+    /// 1. Git directories outside of the repository directory, e.g., shared
+    ///    through `profile fork --share-repos`, are kept along with the files
+    ///    they track, since other profiles may still use them.
+    ///
+    /// # Errors
+    ///
+    /// - Return [`ManagerError::MissingRepo`] if repository is not registered.
+    /// - Return [`ManagerError::Repo`] if tracked files cannot be listed.
+    /// - Return [`ManagerError::Prompt`] if user cannot be asked.
+    /// - Return [`ManagerError::RemoveFile`] if a tracked file cannot be
+    ///   removed.
+    /// - Return [`ManagerError::RemoveDir`] if Git directory cannot be
+    ///   removed.
+    /// - Return [`ManagerError::Config`] if repository configuration cannot be
+    ///   read or written.
+    pub fn delete(&self, ctx: &DeleteContext) -> Result<DeletedRepo, ManagerError> {
+        let mut config = ConfigFile::load(RepoConfig, self.locator)?;
+        let settings = self.settings(&config, &ctx.repo)?;
+        let gitdir = settings.gitdir(self.locator.repos_dir());
+        // INVARIANT: never remove Git directories Ricer did not make, nor the files they track.
+        let owned = gitdir.starts_with(self.locator.repos_dir());
+        let removed = match owned && settings.workdir_home && !ctx.keep_files && gitdir.exists() {
+            true => self.remove_tracked_files(&ctx.repo, &gitdir)?,
+            false => Vec::new(),
+        };
+
+        if !owned {
+            warn!(
+                "Keep '{}' and its files, because it is outside of repository directory",
+                gitdir.display()
+            );
+        } else if gitdir.exists()
+            && plan::perform(|| DryRunEffect::RemoveDir { path: gitdir.clone() })
+        {
+            debug!("Remove '{}'", gitdir.display());
+            fs::remove_dir_all(&gitdir)
+                .map_err(|err| ManagerError::RemoveDir { source: err, path: gitdir })?;
+        }
+
+        config.remove(&ctx.repo)?;
+        config.save()?;
+        Ok(DeletedRepo { settings, removed })
+    }
+
+    /// Remove files that repository `name` at `gitdir` tracks from its working
+    /// directory, once the user agrees to it.
+    ///
+    /// Returns removed files relative to the working directory.
+    fn remove_tracked_files(
+        &self,
+        name: &str,
+        gitdir: &Path,
+    ) -> Result<Vec<PathBuf>, ManagerError> {
+        let repo_err = |err| ManagerError::Repo { source: err, name: name.into() };
+        let repo = GitRepo::open(gitdir).map_err(repo_err)?;
+        let Some(workdir) = repo.workdir().map(Path::to_path_buf) else {
+            return Ok(Vec::new());
+        };
+        let files = repo
+            .tracked_files()
+            .map_err(repo_err)?
+            .into_iter()
+            .filter(|path| workdir.join(path).symlink_metadata().is_ok())
+            .collect::<Vec<_>>();
+        if files.is_empty() {
+            return Ok(files);
+        }
+
+        // INVARIANT: dry runs list files that would be removed without asking.
+        if plan::is_dry_run() {
+            for path in &files {
+                plan::perform(|| DryRunEffect::RemoveFile { path: workdir.join(path) });
+            }
+            return Ok(Vec::new());
+        }
+
+        let heading = format!("Files of '{name}' in '{}':", workdir.display());
+        let items = files.iter().map(|path| path.display().to_string()).collect::<Vec<_>>();
+        self.prompter.show_list(&heading, &items)?;
+        let question = format!("Remove {} files of '{name}' too?", files.len());
+        if !self.prompter.confirm(&question)? {
+            info!("Keep files of '{name}' in '{}'", workdir.display());
+            return Ok(Vec::new());
+        }
+
+        for path in &files {
+            debug!("Remove '{}'", workdir.join(path).display());
+            remove_file_pruning(&workdir, path).map_err(|err| ManagerError::RemoveFile {
+                source: err,
+                path: workdir.join(path),
+            })?;
+        }
+
+        Ok(files)
+    }
+
+    /// Open shell inside working directory of repository, with Git pointed
+    /// at repository.
+    ///
+    /// Returns exit code of shell.
+    ///
+    /// # Errors
+    ///
+    /// - Return [`ManagerError::MissingRepo`] if repository is not registered.
+    /// - Return [`ManagerError::NotCloned`] if repository does not exist.
+    /// - Return [`ManagerError::Repo`] if repository cannot be opened.
+    /// - Return [`ManagerError::Spawn`] if shell cannot be executed.
+    pub fn enter(&self, ctx: &EnterContext) -> Result<i32, ManagerError> {
+        let repo = self.open(&ctx.repo)?;
+        let workdir = repo.workdir().unwrap_or(repo.gitdir());
+        info!("Enter '{}' through '{}'", ctx.repo, self.shell.display());
+        let status = Command::new(&self.shell)
+            .current_dir(workdir)
+            .env("GIT_DIR", repo.gitdir())
+            .env("GIT_WORK_TREE", workdir)
+            .status()
+            .map_err(|err| ManagerError::Spawn { source: err, program: self.shell.clone() })?;
+
+        Ok(status.code().unwrap_or(1))
+    }
+
+    /// Run Git with arguments of `ctx` on repository.
+    ///
+    /// Returns exit code of Git, or `None` if repository is not registered.
+    /// Through `--dry-run`, Git is only run for commands that never change
+    /// the repository, i.e., those outside of [`MUTATING_GIT_COMMANDS`].
+    ///
+    /// # Errors
+    ///
+    /// - Return [`ManagerError::NotCloned`] if repository does not exist.
+    /// - Return [`ManagerError::Repo`] if repository cannot be opened.
+    /// - Return [`ManagerError::Spawn`] if Git cannot be executed.
+    /// - Return [`ManagerError::Config`] if repository configuration cannot be
+    ///   read.
+    pub fn git(&self, ctx: &GitContext) -> Result<Option<i32>, ManagerError> {
+        let name = ctx.repo.to_string_lossy();
+        let repo = match self.open(&name) {
+            Ok(repo) => repo,
+            Err(ManagerError::MissingRepo { .. }) => return Ok(None),
+            Err(err) => return Err(err),
+        };
+
+        let mutating = ctx
+            .git_args
+            .first()
+            .and_then(|arg| arg.to_str())
+            .is_some_and(|cmd| MUTATING_GIT_COMMANDS.contains(&cmd));
+        let args = || ctx.git_args.iter().map(|arg| arg.to_string_lossy()).collect::<Vec<_>>();
+        if mutating
+            && !plan::perform(|| DryRunEffect::Git {
+                gitdir: repo.gitdir().into(),
+                action: format!("Run 'git {}'", args().join(" ")),
+            })
+        {
+            return Ok(Some(0));
+        }
+
+        let status = git_command(&repo, &ctx.git_args)
+            .status()
+            .map_err(|err| ManagerError::Spawn { source: err, program: "git".into() })?;
+        Ok(Some(status.code().unwrap_or(1)))
+    }
+
+    fn open(&self, name: &str) -> Result<GitRepo, ManagerError> {
+        let settings = self.settings(&ConfigFile::load(RepoConfig, self.locator)?, name)?;
+        let gitdir = settings.gitdir(self.locator.repos_dir());
+        if !gitdir.exists() {
+            return Err(ManagerError::NotCloned { name: name.into(), path: gitdir });
+        }
+
+        GitRepo::open(&gitdir).map_err(|err| ManagerError::Repo { source: err, name: name.into() })
+    }
+
+    fn settings(
+        &self,
+        config: &ConfigFile<'_, RepoConfig, L>,
+        name: &str,
+    ) -> Result<RepoSettings, ManagerError> {
+        match config.get(name) {
+            Ok(settings) => Ok(settings),
+            Err(ConfigFileError::Toml {
+                source: TomlError::EntryNotFound { .. } | TomlError::TableNotFound { .. },
+                ..
+            }) => Err(ManagerError::MissingRepo { name: name.into() }),
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
+/// Git command pointed at both Git directory and working directory of `repo`.
+fn git_command(repo: &GitRepo, args: &[OsString]) -> Command {
+    let mut git = Command::new("git");
+    git.arg(git_dir_arg("--git-dir", repo.gitdir()));
+    if let Some(workdir) = repo.workdir() {
+        git.arg(git_dir_arg("--work-tree", workdir));
+    }
+    git.args(args);
+    git
+}
+
+fn git_dir_arg(flag: &str, path: &Path) -> OsString {
+    let mut arg = OsString::from(flag);
+    arg.push(OsStr::new("="));
+    arg.push(path);
+    arg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        cli::Cli,
+        testenv::{mock_locator, FileKind, FixtureHarness, ScriptedPrompter},
+    };
+
+    use anyhow::Result;
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+    use rstest::{fixture, rstest};
+
+    #[fixture]
+    fn rice_dir() -> Result<FixtureHarness> {
+        let harness = FixtureHarness::open()?
+            .with_file("config/repos.toml", |fixture| {
+                fixture
+                    .with_data(indoc! {r#"
+                        [repos.dwm]
+                        branch = "main"
+                        remote = "origin"
+                        workdir_home = false
+
+                        [repos.vim]
+                        branch = "main"
+                        remote = "origin"
+                        workdir_home = false
+                    "#})
+                    .with_kind(FileKind::Normal)
+            })
+            .with_file("shell.sh", |fixture| {
+                fixture
+                    .with_data("#!/bin/sh\necho \"$GIT_DIR\" > entered.txt\nexit 3\n")
+                    .with_kind(FileKind::Script)
+            })
+            .with_repo("repos/dwm", |repo| {
+                let repo = repo.stage("config.h", "configure DWM here")?;
+                repo.commit("initial commit")?;
+                Ok(repo)
+            })?
+            .with_bare_repo("remote")?
+            .setup()?;
+        Ok(harness)
+    }
+
+    fn context(args: &[&str]) -> Result<Context> {
+        Ok(Context::from(Cli::parse_args(["ricer"].iter().chain(args))?))
+    }
+
+    #[rstest]
+    #[case::repos_only(&["list"], vec!["dwm", "vim (not cloned)"])]
+    #[case::tracked(&["list", "--tracked"], vec!["dwm", "  config.h", "vim (not cloned)"])]
+    #[case::untracked(&["list", "--untracked"], vec!["dwm", "  notes.txt", "vim (not cloned)"])]
+    fn repo_manager_execute_list(
+        rice_dir: Result<FixtureHarness>,
+        #[case] args: &[&str],
+        #[case] expect: Vec<&str>,
+    ) -> Result<()> {
+        let rice_dir = rice_dir?;
+        fs::write(rice_dir.as_path().join("repos/dwm.git/notes.txt"), "untracked")?;
+        let locator = mock_locator(&rice_dir);
+
+        let report = RepoManager::new(&locator).execute(&context(args)?)?.unwrap();
+        assert_eq!(report.to_string().lines().collect::<Vec<_>>(), expect);
+        Ok(())
+    }
+
+    #[rstest]
+    fn repo_manager_execute_list_unregistered_repos(
+        rice_dir: Result<FixtureHarness>,
+    ) -> Result<()> {
+        let rice_dir = rice_dir?;
+        GitRepo::init(rice_dir.as_path().join("repos/st"))?;
+        fs::create_dir_all(rice_dir.as_path().join("repos/not-a-repo"))?;
+        let locator = mock_locator(&rice_dir);
+
+        let report = RepoManager::new(&locator).execute(&context(&["list"])?)?.unwrap();
+        let expect = ["dwm", "vim (not cloned)", "st (not registered)"];
+        assert_eq!(report.to_string().lines().collect::<Vec<_>>(), expect);
+        Ok(())
+    }
+
+    #[rstest]
+    fn repo_manager_execute_list_format_json(rice_dir: Result<FixtureHarness>) -> Result<()> {
+        let rice_dir = rice_dir?;
+        GitRepo::init(rice_dir.as_path().join("repos/st"))?;
+        let locator = mock_locator(&rice_dir);
+
+        let ctx = context(&["list", "--tracked", "--format", "json"])?;
+        let report = RepoManager::new(&locator).execute(&ctx)?.unwrap();
+        let Json::Array(listings) = report.to_string().parse::<Json>()? else {
+            panic!("listing is not a JSON array");
+        };
+        let fields = |listing: &Json| {
+            let settings = listing.get("settings").and_then(|settings| settings.get("branch"));
+            (
+                listing.get("name").and_then(Json::as_str).map(String::from),
+                listing.get("cloned").and_then(Json::as_bool),
+                settings.and_then(Json::as_str).map(String::from),
+            )
+        };
+        let expect = vec![
+            (Some("dwm".into()), Some(true), Some("main".into())),
+            (Some("vim".into()), Some(false), Some("main".into())),
+            (Some("st".into()), Some(true), None),
+        ];
+        assert_eq!(listings.iter().map(fields).collect::<Vec<_>>(), expect);
+        assert_eq!(listings[0].get("files"), Some(&Json::Array(vec!["config.h".into()])));
+        Ok(())
+    }
+
+    #[rstest]
+    fn repo_manager_execute_list_format_toml(rice_dir: Result<FixtureHarness>) -> Result<()> {
+        let rice_dir = rice_dir?;
+        GitRepo::init(rice_dir.as_path().join("repos/st"))?;
+        let locator = mock_locator(&rice_dir);
+
+        let ctx = context(&["list", "--format", "toml"])?;
+        let report = RepoManager::new(&locator).execute(&ctx)?.unwrap();
+        let document = report.to_string().parse::<DocumentMut>()?;
+        assert_eq!(document["repos"]["dwm"]["branch"].as_str(), Some("main"));
+        assert_eq!(document["repos"]["dwm"]["cloned"].as_bool(), Some(true));
+        assert_eq!(document["repos"]["vim"]["cloned"].as_bool(), Some(false));
+        assert_eq!(document["repos"]["st"]["registered"].as_bool(), Some(false));
+        assert!(document["repos"]["dwm"].get("files").is_none());
+        Ok(())
+    }
+
+    #[rstest]
+    fn repo_manager_execute_push(rice_dir: Result<FixtureHarness>) -> Result<()> {
+        let rice_dir = rice_dir?;
+        let remote = rice_dir.as_path().join("remote.git");
+        let dwm = GitRepo::open(rice_dir.as_path().join("repos/dwm.git"))?;
+        dwm.syscall(["remote", "add", "origin", remote.to_str().unwrap()])?;
+        let locator = mock_locator(&rice_dir);
+
+        let report = RepoManager::new(&locator).execute(&context(&["push"])?)?.unwrap();
+        let expect = ["dwm: pushed to 'origin/main'", "vim: not cloned"];
+        assert_eq!(report.to_string().lines().collect::<Vec<_>>(), expect);
+        assert_eq!(report.repos(), ["dwm"]);
+        assert!(!report.has_failed());
+        assert_eq!(GitRepo::open(&remote)?.head_commit()?, dwm.head_commit()?);
+        Ok(())
+    }
+
+    #[rstest]
+    fn repo_manager_execute_delete(rice_dir: Result<FixtureHarness>) -> Result<()> {
+        let rice_dir = rice_dir?;
+        let locator = mock_locator(&rice_dir);
+
+        let report = RepoManager::new(&locator).execute(&context(&["delete", "dwm"])?)?.unwrap();
+        assert_eq!(report.repos(), ["dwm"]);
+        assert!(!rice_dir.as_path().join("repos/dwm.git").exists());
+        let config = ConfigFile::load(RepoConfig, &locator)?;
+        let names = config.entries()?.into_iter().map(|repo| repo.name).collect::<Vec<_>>();
+        assert_eq!(names, ["vim"]);
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::remove_files("y", true)]
+    #[case::decline("n", false)]
+    fn repo_manager_execute_delete_ask_to_remove_home_files(
+        rice_dir: Result<FixtureHarness>,
+        #[case] answer: &str,
+        #[case] removed: bool,
+    ) -> Result<()> {
+        let rice_dir = rice_dir?.with_fake_bare_repo("repos/st", |repo| {
+            let repo = repo.stage(".config/st/st.conf", "font = mono")?;
+            let repo = repo.stage("strc", "alpha = 0.9")?;
+            repo.commit("initial commit")?;
+            Ok(repo)
+        })?;
+        let root = rice_dir.as_path();
+        let mut config = fs::read_to_string(root.join("config/repos.toml"))?;
+        config.push_str(
+            "\n[repos.st]\nbranch = \"main\"\nremote = \"origin\"\nworkdir_home = true\n",
+        );
+        fs::write(root.join("config/repos.toml"), config)?;
+        let locator = mock_locator(&rice_dir);
+
+        let prompter = ScriptedPrompter::new([answer]);
+        let manager = RepoManager::new(&locator).with_prompter(prompter);
+        let report = manager.execute(&context(&["delete", "st"])?)?.unwrap();
+        assert_eq!(report.repos(), ["st"]);
+        assert!(!root.join("repos/st.git").exists());
+        assert_eq!(root.join("strc").exists(), !removed);
+        assert_eq!(root.join(".config").exists(), !removed);
+        assert!(root.join("config/repos.toml").exists());
+        let expect = match removed {
+            true => "Deleted 'st' and 2 files of its working directory\n",
+            false => "Deleted 'st'\n",
+        };
+        assert_eq!(report.to_string(), expect);
+        assert_eq!(manager.prompter.lists(), [vec![".config/st/st.conf", "strc"]]);
+        Ok(())
+    }
+
+    #[rstest]
+    fn repo_manager_execute_delete_keep_files_never_ask(
+        rice_dir: Result<FixtureHarness>,
+    ) -> Result<()> {
+        let rice_dir = rice_dir?.with_fake_bare_repo("repos/st", |repo| {
+            let repo = repo.stage("strc", "alpha = 0.9")?;
+            repo.commit("initial commit")?;
+            Ok(repo)
+        })?;
+        let root = rice_dir.as_path();
+        let mut config = fs::read_to_string(root.join("config/repos.toml"))?;
+        config.push_str(
+            "\n[repos.st]\nbranch = \"main\"\nremote = \"origin\"\nworkdir_home = true\n",
+        );
+        fs::write(root.join("config/repos.toml"), config)?;
+        let locator = mock_locator(&rice_dir);
+
+        let manager = RepoManager::new(&locator).with_prompter(ScriptedPrompter::default());
+        manager.execute(&context(&["delete", "st", "--keep-files"])?)?;
+        assert!(!root.join("repos/st.git").exists());
+        assert!(root.join("strc").exists());
+        assert!(manager.prompter.questions().is_empty());
+        Ok(())
+    }
+
+    #[rstest]
+    fn repo_manager_execute_delete_keep_shared_repo_files(
+        rice_dir: Result<FixtureHarness>,
+    ) -> Result<()> {
+        let rice_dir = rice_dir?.with_fake_bare_repo("shared/st", |repo| {
+            let repo = repo.stage("strc", "alpha = 0.9")?;
+            repo.commit("initial commit")?;
+            Ok(repo)
+        })?;
+        let root = rice_dir.as_path();
+        let gitdir = root.join("shared/st.git");
+        let mut config = fs::read_to_string(root.join("config/repos.toml"))?;
+        config.push_str(&format!(
+            "\n[repos.st]\nbranch = \"main\"\nremote = \"origin\"\nworkdir_home = true\npath = \"{}\"\n",
+            gitdir.display()
+        ));
+        fs::write(root.join("config/repos.toml"), config)?;
+        let locator = mock_locator(&rice_dir);
+
+        let manager = RepoManager::new(&locator).with_prompter(ScriptedPrompter::new(["y"]));
+        let report = manager.execute(&context(&["delete", "st"])?)?.unwrap();
+        assert_eq!(report.to_string(), "Deleted 'st'\n");
+        assert!(gitdir.exists());
+        assert!(root.join("strc").exists());
+        assert!(manager.prompter.questions().is_empty());
+        let config = ConfigFile::load(RepoConfig, &locator)?;
+        assert!(config.entries()?.iter().all(|repo| repo.name != "st"));
+        Ok(())
+    }
+
+    #[rstest]
+    fn repo_manager_execute_enter(rice_dir: Result<FixtureHarness>) -> Result<()> {
+        let rice_dir = rice_dir?;
+        let locator = mock_locator(&rice_dir);
+
+        let manager = RepoManager::new(&locator).shell(rice_dir.as_path().join("shell.sh"));
+        let report = manager.execute(&context(&["enter", "dwm"])?)?.unwrap();
+        assert!(matches!(report, ManagerReport::Exited(3)));
+
+        // INVARIANT: shell is started inside working directory.
+        let gitdir = fs::read_to_string(rice_dir.as_path().join("repos/dwm.git/entered.txt"))?;
+        assert!(Path::new(gitdir.trim()).starts_with(rice_dir.as_path().join("repos/dwm.git")));
+        Ok(())
+    }
+
+    #[rstest]
+    fn repo_manager_execute_enter_fake_bare() -> Result<()> {
+        let rice_dir = FixtureHarness::open()?
+            .with_file("config/repos.toml", |fixture| {
+                fixture
+                    .with_data(indoc! {r#"
+                        [repos.sh]
+                        branch = "main"
+                        remote = "origin"
+                        workdir_home = true
+                    "#})
+                    .with_kind(FileKind::Normal)
+            })
+            .with_file("shell.sh", |fixture| {
+                fixture
+                    .with_data("#!/bin/sh\necho \"$GIT_DIR:$GIT_WORK_TREE:$PWD\" > entered.txt\n")
+                    .with_kind(FileKind::Script)
+            })
+            .with_fake_bare_repo("repos/sh", Ok)?
+            .setup()?;
+        let locator = mock_locator(&rice_dir);
+
+        let manager = RepoManager::new(&locator).shell(rice_dir.as_path().join("shell.sh"));
+        let report = manager.execute(&context(&["enter", "sh"])?)?.unwrap();
+        assert!(matches!(report, ManagerReport::Exited(0)));
+
+        // INVARIANT: Git finds repository through environment, not through working directory.
+        let entered = fs::read_to_string(rice_dir.as_path().join("entered.txt"))?;
+        let entered = entered.trim().split(':').map(PathBuf::from).collect::<Vec<_>>();
+        let root = rice_dir.as_path().canonicalize()?;
+        let expect = [root.join("repos/sh.git"), root.clone(), root];
+        let entered =
+            entered.iter().map(|path| path.canonicalize()).collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(entered, expect);
+        Ok(())
+    }
+
+    #[rstest]
+    fn repo_manager_execute_git(rice_dir: Result<FixtureHarness>) -> Result<()> {
+        let rice_dir = rice_dir?;
+        let locator = mock_locator(&rice_dir);
+        let manager = RepoManager::new(&locator);
+
+        let report = manager.execute(&context(&["dwm", "tag", "v1"])?)?;
+        assert!(matches!(report, Some(ManagerReport::Exited(0))));
+        assert!(GitRepo::open(rice_dir.as_path().join("repos/dwm.git"))?.has_tag("v1")?);
+
+        let report = manager.execute(&context(&["dwm", "checkout", "nope"])?)?;
+        assert!(matches!(report, Some(ManagerReport::Exited(code)) if code != 0));
+        assert!(manager.execute(&context(&["plugin", "arg"])?)?.is_none());
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::delete(&["delete", "nope"])]
+    #[case::enter_missing(&["enter", "nope"])]
+    #[case::enter_not_cloned(&["enter", "vim"])]
+    #[case::git_not_cloned(&["vim", "status"])]
+    fn repo_manager_execute_return_err(
+        rice_dir: Result<FixtureHarness>,
+        #[case] args: &[&str],
+    ) -> Result<()> {
+        let rice_dir = rice_dir?;
+        let locator = mock_locator(&rice_dir);
+        let result = RepoManager::new(&locator).execute(&context(args)?);
+        match args[args.len() - 1] {
+            "nope" => assert!(matches!(result.unwrap_err(), ManagerError::MissingRepo { .. })),
+            _ => assert!(matches!(result.unwrap_err(), ManagerError::NotCloned { .. })),
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,559 @@
+// SPDX-FileCopyrightText: 2024 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+//! Command operation layer.
+//!
+//! Provides an internal event bus that command implementations use to tell the
+//! rest of Ricer about what they are doing. Cross-cutting features like command
+//! hooks and logging subscribe to the event bus, instead of being called
+//! directly by each command implementation.
+//!
+//! Commands emit [`Event`]s through [`EventBus`], which then notifies all
+//! registered [`Subscriber`]s in the order they were registered. Subscribers
+//! are free to react to whatever events they care about, and ignore the rest.
+
+use crate::{
+    context::Context,
+    hook::{var_env, CmdHook, HookKind},
+    locate::Locator,
+    ui::{Prompter, TerminalPrompter},
+};
+
+use log::{debug, info};
+use std::{error::Error, fmt};
+
+/// Error type that subscribers can return when handling events.
+pub type SubscriberError = Box<dyn Error + Send + Sync + 'static>;
+
+/// Error types for [`EventBus`].
+#[derive(Debug, thiserror::Error)]
+pub enum EventBusError {
+    #[error("Subscriber '{name}' failed to handle '{event}' event")]
+    Subscriber { name: String, event: String, source: SubscriberError },
+}
+
+/// Typed events emitted by command implementations.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event<'ctx> {
+    /// Command is about to start executing.
+    CommandStarted { context: &'ctx Context },
+
+    /// Command operated on a given repository.
+    RepoOperated { context: &'ctx Context, repo: &'ctx str },
+
+    /// Command entered a named phase, e.g., fetching a given repository.
+    PhaseStarted { context: &'ctx Context, phase: &'ctx str },
+
+    /// Command left a named phase.
+    PhaseFinished { context: &'ctx Context, phase: &'ctx str },
+
+    /// Command finished executing.
+    CommandFinished { context: &'ctx Context, success: bool },
+}
+
+impl<'ctx> Event<'ctx> {
+    pub fn context(&self) -> &'ctx Context {
+        match self {
+            Event::CommandStarted { context } => context,
+            Event::RepoOperated { context, .. } => context,
+            Event::PhaseStarted { context, .. } => context,
+            Event::PhaseFinished { context, .. } => context,
+            Event::CommandFinished { context, .. } => context,
+        }
+    }
+}
+
+impl fmt::Display for Event<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Event::CommandStarted { .. } => write!(f, "command started"),
+            Event::RepoOperated { .. } => write!(f, "repository operated"),
+            Event::PhaseStarted { .. } => write!(f, "phase started"),
+            Event::PhaseFinished { .. } => write!(f, "phase finished"),
+            Event::CommandFinished { .. } => write!(f, "command finished"),
+        }
+    }
+}
+
+/// Observer of events emitted through [`EventBus`].
+pub trait Subscriber {
+    /// Name of subscriber used for error reporting.
+    fn name(&self) -> &str;
+
+    /// Handle emitted event.
+    ///
+    /// # Errors
+    ///
+    /// Return [`SubscriberError`] if event could not be handled. This will stop
+    /// the event from being passed to any remaining subscribers.
+    fn notify(&mut self, event: &Event<'_>) -> Result<(), SubscriberError>;
+}
+
+/// Internal event bus.
+///
+/// Notifies registered subscribers of events in the order the subscribers were
+/// registered.
+#[derive(Default)]
+pub struct EventBus<'sub> {
+    subscribers: Vec<Box<dyn Subscriber + 'sub>>,
+}
+
+impl<'sub> EventBus<'sub> {
+    pub fn new() -> Self {
+        Self { subscribers: Vec::new() }
+    }
+
+    /// Register new subscriber.
+    pub fn subscribe(mut self, subscriber: impl Subscriber + 'sub) -> Self {
+        self.subscribers.push(Box::new(subscriber));
+        self
+    }
+
+    /// Emit event to all subscribers.
+    ///
+    /// # Errors
+    ///
+    /// - Return [`EventBusError::Subscriber`] if any subscriber failed to
+    ///   handle the event. Remaining subscribers will not be notified.
+    pub fn emit(&mut self, event: Event<'_>) -> Result<(), EventBusError> {
+        debug!("Emit '{event}' event");
+        for subscriber in self.subscribers.iter_mut() {
+            subscriber.notify(&event).map_err(|err| EventBusError::Subscriber {
+                name: subscriber.name().into(),
+                event: event.to_string(),
+                source: err,
+            })?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Debug for EventBus<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names: Vec<&str> = self.subscribers.iter().map(|sub| sub.name()).collect();
+        f.debug_struct("EventBus").field("subscribers", &names).finish()
+    }
+}
+
+/// Run command hooks in response to command events.
+///
+/// Pre hooks are executed when a command starts, and post hooks are executed
+/// when a command finishes successfully. Repository-scoped post hooks run
+/// once for every repository the command reported through
+/// [`Event::RepoOperated`].
+///
+/// Hooks are borrowed, so commands can still run setup hooks, or list hooks,
+/// through the same [`CmdHook`] while subscribed.
+#[derive(Debug)]
+pub struct HookSubscriber<'hook, 'cfg, L, P = TerminalPrompter>
+where
+    L: Locator,
+    P: Prompter,
+{
+    hooks: &'hook CmdHook<'cfg, L, P>,
+    operated: Vec<String>,
+}
+
+impl<'hook, 'cfg, L, P> HookSubscriber<'hook, 'cfg, L, P>
+where
+    L: Locator,
+    P: Prompter,
+{
+    pub fn new(hooks: &'hook CmdHook<'cfg, L, P>) -> Self {
+        Self { hooks, operated: Vec::new() }
+    }
+}
+
+impl<L, P> Subscriber for HookSubscriber<'_, '_, L, P>
+where
+    L: Locator,
+    P: Prompter,
+{
+    fn name(&self) -> &str {
+        "hook"
+    }
+
+    fn notify(&mut self, event: &Event<'_>) -> Result<(), SubscriberError> {
+        match event {
+            Event::CommandStarted { .. } => self.hooks.run_hooks(HookKind::Pre)?,
+            Event::RepoOperated { repo, .. } if !self.operated.iter().any(|name| name == repo) => {
+                self.operated.push(repo.to_string())
+            }
+            // INVARIANT: repository-scoped post hooks only run for repositories operated on.
+            Event::CommandFinished { success: true, .. } if !self.operated.is_empty() => {
+                self.hooks.run_hooks_on(HookKind::Post, &self.operated)?
+            }
+            Event::CommandFinished { success: true, .. } => self.hooks.run_hooks(HookKind::Post)?,
+            _ => (),
+        }
+
+        Ok(())
+    }
+}
+
+/// Log command events.
+#[derive(Debug, Default)]
+pub struct LogSubscriber;
+
+impl Subscriber for LogSubscriber {
+    fn name(&self) -> &str {
+        "log"
+    }
+
+    fn notify(&mut self, event: &Event<'_>) -> Result<(), SubscriberError> {
+        // INVARIANT: Git command shortcut has no name to log.
+        if matches!(event.context(), Context::Git(..)) {
+            return Ok(());
+        }
+
+        match event {
+            Event::CommandStarted { context } => debug!("Start '{context}' command"),
+            Event::RepoOperated { context, repo } => info!("Command '{context}' on '{repo}'"),
+            Event::PhaseStarted { context, phase } => debug!("Start '{context}' phase '{phase}'"),
+            Event::PhaseFinished { context, phase } => {
+                debug!("Finish '{context}' phase '{phase}'")
+            }
+            Event::CommandFinished { context, success } => {
+                debug!("Finish '{context}' command (success: {success})")
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Resolved environment report for `ricer env`.
+///
+/// Shows where Ricer expects its configuration data to be, the effective
+/// hook settings of the current invocation, and variables passed through
+/// `--context`, as `KEY=VALUE` lines.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnvReport {
+    entries: Vec<(String, String)>,
+}
+
+impl EnvReport {
+    pub fn new(context: &Context, locator: &impl Locator) -> Self {
+        let mut entries = vec![
+            ("RICER_CONFIG_DIR", locator.config_dir().display().to_string()),
+            ("RICER_HOOKS_DIR", locator.hooks_dir().display().to_string()),
+            ("RICER_HOOKS_CONFIG", locator.hooks_config().display().to_string()),
+            ("RICER_REPOS_DIR", locator.repos_dir().display().to_string()),
+            ("RICER_REPOS_CONFIG", locator.repos_config().display().to_string()),
+            ("RICER_CONFIG", locator.ricer_config().display().to_string()),
+            ("RICER_STATE_DIR", locator.state_dir().display().to_string()),
+            ("RICER_LOCK_FILE", locator.lock_file().display().to_string()),
+            ("RICER_TEMPLATES_DIR", locator.templates_dir().display().to_string()),
+            ("RICER_IGNORES_DIR", locator.ignores_dir().display().to_string()),
+        ];
+
+        if let Some(shared) = context.shared() {
+            entries.push(("RICER_RUN_HOOK", shared.run_hook.to_string()));
+            entries.push(("RICER_NO_HOOKS", shared.no_hooks.to_string()));
+        }
+
+        let mut entries =
+            entries.into_iter().map(|(key, value)| (key.to_string(), value)).collect::<Vec<_>>();
+        for (key, value) in
+            context.shared().map(|shared| shared.vars.as_slice()).unwrap_or_default()
+        {
+            let name = var_env(key);
+            // INVARIANT: later values of a variable replace earlier ones.
+            entries.retain(|(old, _)| *old != name);
+            entries.push((name, value.clone()));
+        }
+
+        Self { entries }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.entries.iter().find(|(name, _)| *name == key).map(|(_, value)| value.as_str())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.entries.iter().map(|(name, value)| (name.as_str(), value.as_str()))
+    }
+}
+
+impl fmt::Display for EnvReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (key, value) in &self.entries {
+            writeln!(f, "{key}={value}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Outcome of one repository of a [`BatchReport`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BatchOutcome {
+    /// Repository was operated on successfully.
+    Done,
+
+    /// Operation failed with the given reason.
+    Failed(String),
+
+    /// Repository was left alone for the given reason.
+    Skipped(String),
+}
+
+impl fmt::Display for BatchOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BatchOutcome::Done => write!(f, "done"),
+            BatchOutcome::Failed(reason) => write!(f, "failed: {reason}"),
+            BatchOutcome::Skipped(reason) => write!(f, "skipped: {reason}"),
+        }
+    }
+}
+
+/// Report of commands that operate on many repositories one by one.
+///
+/// Failing on one repository does not stop batch commands from operating on
+/// the others, so each repository gets a [`BatchOutcome`] of its own, which
+/// are displayed one line per repository, followed by a summary line.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BatchReport {
+    outcomes: Vec<(String, BatchOutcome)>,
+}
+
+impl BatchReport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, repo: impl Into<String>, outcome: BatchOutcome) {
+        self.outcomes.push((repo.into(), outcome));
+    }
+
+    pub fn outcomes(&self) -> &[(String, BatchOutcome)] {
+        &self.outcomes
+    }
+
+    /// Names of repositories that were operated on successfully.
+    pub fn done(&self) -> impl Iterator<Item = &str> {
+        self.outcomes
+            .iter()
+            .filter(|(_, outcome)| *outcome == BatchOutcome::Done)
+            .map(|(repo, _)| repo.as_str())
+    }
+
+    /// Check if operation failed on any repository.
+    pub fn has_failed(&self) -> bool {
+        self.outcomes.iter().any(|(_, outcome)| matches!(outcome, BatchOutcome::Failed(_)))
+    }
+}
+
+impl fmt::Display for BatchReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (mut done, mut failed, mut skipped) = (0, 0, 0);
+        for (repo, outcome) in &self.outcomes {
+            match outcome {
+                BatchOutcome::Done => done += 1,
+                BatchOutcome::Failed(_) => failed += 1,
+                BatchOutcome::Skipped(_) => skipped += 1,
+            }
+            writeln!(f, "{repo}: {outcome}")?;
+        }
+        writeln!(f, "{done} done, {failed} failed, {skipped} skipped")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        cli::Cli,
+        locate::MockLocator,
+        testenv::{FileKind, FixtureHarness},
+    };
+
+    use anyhow::Result;
+    use indoc::{formatdoc, indoc};
+    use pretty_assertions::assert_eq;
+    use rstest::{fixture, rstest};
+    use std::{cell::RefCell, rc::Rc};
+
+    struct RecordSubscriber {
+        name: String,
+        fail: bool,
+        record: Rc<RefCell<Vec<String>>>,
+    }
+
+    impl Subscriber for RecordSubscriber {
+        fn name(&self) -> &str {
+            &self.name
+        }
+
+        fn notify(&mut self, event: &Event<'_>) -> Result<(), SubscriberError> {
+            self.record.borrow_mut().push(format!("{}: {event}", self.name));
+            if self.fail {
+                return Err("subscriber failure".into());
+            }
+            Ok(())
+        }
+    }
+
+    #[fixture]
+    fn config_dir() -> Result<FixtureHarness> {
+        let harness = FixtureHarness::open()?;
+        let root = harness.as_path().to_path_buf();
+        let harness = harness
+            .with_file("hooks.toml", |fixture| {
+                fixture
+                    .with_data(indoc! {r#"
+                        [hooks]
+                        commit = [
+                            { pre = "pre_hook.sh", post = "post_hook.sh" },
+                        ]
+                    "#})
+                    .with_kind(FileKind::Normal)
+            })
+            .with_file("hooks/pre_hook.sh", |fixture| {
+                fixture
+                    .with_data(formatdoc! {r#"
+                        #!/bin/sh
+
+                        echo "pre" >> {}/out.txt
+                    "#, root.display()})
+                    .with_kind(FileKind::Script)
+            })
+            .with_file("hooks/post_hook.sh", |fixture| {
+                fixture
+                    .with_data(formatdoc! {r#"
+                        #!/bin/sh
+
+                        echo "post" >> {}/out.txt
+                    "#, root.display()})
+                    .with_kind(FileKind::Script)
+            })
+            .setup()?;
+        Ok(harness)
+    }
+
+    #[test]
+    fn event_bus_emit_notify_subscribers_in_order() -> Result<()> {
+        let record = Rc::new(RefCell::new(Vec::new()));
+        let ctx = Context::from(Cli::parse_args(["ricer", "commit"])?);
+        let mut bus = EventBus::new()
+            .subscribe(RecordSubscriber { name: "a".into(), fail: false, record: record.clone() })
+            .subscribe(RecordSubscriber { name: "b".into(), fail: false, record: record.clone() });
+        bus.emit(Event::CommandStarted { context: &ctx })?;
+        bus.emit(Event::CommandFinished { context: &ctx, success: true })?;
+        assert_eq!(
+            record.borrow().as_slice(),
+            [
+                "a: command started",
+                "b: command started",
+                "a: command finished",
+                "b: command finished"
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn event_bus_emit_return_err_subscriber() -> Result<()> {
+        let record = Rc::new(RefCell::new(Vec::new()));
+        let ctx = Context::from(Cli::parse_args(["ricer", "commit"])?);
+        let mut bus = EventBus::new()
+            .subscribe(RecordSubscriber { name: "a".into(), fail: true, record: record.clone() })
+            .subscribe(RecordSubscriber { name: "b".into(), fail: false, record: record.clone() });
+        let result = bus.emit(Event::CommandStarted { context: &ctx });
+        assert!(matches!(result.unwrap_err(), EventBusError::Subscriber { .. }));
+        assert_eq!(record.borrow().as_slice(), ["a: command started"]);
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::defaults(vec!["ricer", "env"], "prompt", "false", None)]
+    #[case::no_hooks(
+        vec!["ricer", "--run-hooks=always", "--no-hooks", "env"],
+        "always",
+        "true",
+        None
+    )]
+    #[case::context_vars(
+        vec!["ricer", "--context", "theme=gruvbox", "--context", "theme=nord", "env"],
+        "prompt",
+        "false",
+        Some("nord")
+    )]
+    fn env_report_new_show_hook_settings(
+        #[case] args: Vec<&str>,
+        #[case] run_hook: &str,
+        #[case] no_hooks: &str,
+        #[case] theme: Option<&str>,
+    ) -> Result<()> {
+        let mut locator = MockLocator::new();
+        locator.expect_config_dir().return_const("/config".into());
+        locator.expect_hooks_dir().return_const("/config/hooks".into());
+        locator.expect_hooks_config().return_const("/config/hooks.toml".into());
+        locator.expect_repos_dir().return_const("/data".into());
+        locator.expect_repos_config().return_const("/config/repos.toml".into());
+        locator.expect_ricer_config().return_const("/config/config.toml".into());
+        locator.expect_state_dir().return_const("/state".into());
+        locator.expect_lock_file().return_const("/config/ricer.lock".into());
+        locator.expect_templates_dir().return_const("/config/templates".into());
+        locator.expect_ignores_dir().return_const("/config/ignores".into());
+
+        let ctx = Context::from(Cli::parse_args(args)?);
+        let report = EnvReport::new(&ctx, &locator);
+        assert_eq!(report.get("RICER_CONFIG_DIR"), Some("/config"));
+        assert_eq!(report.get("RICER_STATE_DIR"), Some("/state"));
+        assert_eq!(report.get("RICER_RUN_HOOK"), Some(run_hook));
+        assert_eq!(report.get("RICER_NO_HOOKS"), Some(no_hooks));
+        assert_eq!(report.get("RICER_VAR_THEME"), theme);
+        assert_eq!(
+            report.iter().filter(|(key, _)| *key == "RICER_VAR_THEME").count(),
+            usize::from(theme.is_some())
+        );
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::success(true, "pre\npost\n")]
+    #[case::failure(false, "pre\n")]
+    fn hook_subscriber_notify_run_hooks(
+        config_dir: Result<FixtureHarness>,
+        #[case] success: bool,
+        #[case] expect: &str,
+    ) -> Result<()> {
+        let mut config_dir = config_dir?;
+        let fixture = config_dir.get_file("hooks.toml")?;
+        let mut locator = MockLocator::new();
+        locator.expect_hooks_config().return_const(fixture.as_path().into());
+        locator.expect_hooks_dir().return_const(config_dir.as_path().join("hooks"));
+        locator.expect_config_dir().return_const(config_dir.as_path().into());
+        locator.expect_state_dir().return_const(config_dir.as_path().join("state"));
+
+        let ctx = Context::from(Cli::parse_args(["ricer", "--run-hooks=always", "commit"])?);
+        let hooks = CmdHook::load(&ctx, &locator)?;
+        let mut bus = EventBus::new().subscribe(HookSubscriber::new(&hooks));
+        bus.emit(Event::CommandStarted { context: &ctx })?;
+        bus.emit(Event::CommandFinished { context: &ctx, success })?;
+        config_dir.sync_untracked()?;
+        let result = config_dir.get_file("out.txt")?;
+        assert_eq!(result.as_str(), expect);
+
+        Ok(())
+    }
+
+    #[rstest]
+    fn batch_report_display() {
+        let mut report = BatchReport::new();
+        report.push("vim", BatchOutcome::Done);
+        report.push("dwm", BatchOutcome::Failed("exit code 2".into()));
+        report.push("st", BatchOutcome::Skipped("not cloned".into()));
+        let expect = indoc! {"
+            vim: done
+            dwm: failed: exit code 2
+            st: skipped: not cloned
+            1 done, 1 failed, 1 skipped
+        "};
+        assert_eq!(report.to_string(), expect);
+        assert!(report.has_failed());
+        assert_eq!(report.done().collect::<Vec<_>>(), vec!["vim"]);
+    }
+}
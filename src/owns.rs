@@ -0,0 +1,452 @@
+// SPDX-FileCopyrightText: 2024 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+//! File-level provenance of repositories.
+//!
+//! With many repositories sharing the home directory as their working
+//! directory, it is not obvious which of them tracks a given file. Through
+//! `ricer owns <PATH>...`, the user can look up the owning repositories of
+//! files, every tracked file below a directory, or every tracked file that
+//! matches a glob pattern like `'~/.config/*.conf'`.
+//!
+//! Looking up owners needs the tracked file list of every repository. These
+//! lists are cached in the state directory per repository, and only rebuilt
+//! when the index of a repository changes. Commands can reuse these lists
+//! through [`owned_files`], e.g., to commit files by path without naming
+//! their repository.
+
+use crate::{
+    config::{expand_path, ConfigFile, ConfigFileError, DeployMode, RepoConfig, RepoSettings},
+    context::OwnsContext,
+    locate::Locator,
+    vcs::{GitRepo, GitRepoError},
+};
+
+use glob::{MatchOptions, Pattern, PatternError};
+use log::{debug, warn};
+use mkdirp::mkdirp;
+use std::{
+    collections::BTreeMap,
+    env, fmt, fs,
+    io::Error as IoError,
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+use toml_edit::{Array, DocumentMut, Item, Value};
+
+/// Error types for [`find_owners`].
+#[derive(Debug, thiserror::Error)]
+pub enum OwnsError {
+    #[error("Invalid glob pattern '{pattern}'")]
+    BadPattern { source: PatternError, pattern: String },
+
+    #[error("Failed to read tracked files of repository '{name}'")]
+    Repo { source: GitRepoError, name: String },
+
+    #[error("Failed to determine current directory")]
+    Cwd { source: IoError },
+
+    #[error("Failed to write tracked file cache '{path}'")]
+    FileWrite { source: IoError, path: PathBuf },
+
+    #[error("Failed to read repository configuration")]
+    Config { source: ConfigFileError },
+}
+
+impl From<ConfigFileError> for OwnsError {
+    fn from(err: ConfigFileError) -> Self {
+        OwnsError::Config { source: err }
+    }
+}
+
+/// Repositories that track a file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ownership {
+    pub path: PathBuf,
+
+    /// Names of owning repositories, empty if file is untracked.
+    pub owners: Vec<String>,
+}
+
+impl fmt::Display for Ownership {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.owners.is_empty() {
+            true => write!(f, "{}: untracked", self.path.display()),
+            false => write!(f, "{}: {}", self.path.display(), self.owners.join(", ")),
+        }
+    }
+}
+
+/// Look up owning repositories of every path in [`OwnsContext`].
+///
+/// Paths are expanded like configuration paths, and relative paths are taken
+/// relative to the current directory. A path containing glob characters
+/// reports every tracked file it matches, a directory reports every tracked
+/// file below it, and anything else reports the file itself. Queries that
+/// match no tracked file are reported as untracked.
+///
+/// Repositories are looked up through their working directory. Symlink mode
+/// repositories are also looked up through `home`, where their files are
+/// deployed.
+///
+/// # Invariants
+///
+/// Repositories that cannot be read are skipped with a warning, so one
+/// broken repository does not hide the owners of every other file.
+///
+/// # Errors
+///
+/// - Return [`OwnsError::BadPattern`] if a glob pattern is invalid.
+/// - Return [`OwnsError::Cwd`] if a relative path is given, but the current
+///   directory cannot be determined.
+/// - Return [`OwnsError::Config`] if repository configuration cannot be read.
+pub fn find_owners(
+    ctx: &OwnsContext,
+    locator: &impl Locator,
+    home: &Path,
+) -> Result<Vec<Ownership>, OwnsError> {
+    let repos = load_tracked(locator, home)?;
+    let mut found = Vec::new();
+    for query in &ctx.paths {
+        let (path, matches) = query_matcher(query)?;
+        let mut owners: BTreeMap<PathBuf, Vec<String>> = BTreeMap::new();
+        for repo in &repos {
+            for file in repo.paths().filter(|file| matches(file)) {
+                let names = owners.entry(file).or_default();
+                if !names.contains(&repo.name) {
+                    names.push(repo.name.clone());
+                }
+            }
+        }
+
+        match owners.is_empty() {
+            true => found.push(Ownership { path, owners: Vec::new() }),
+            false => {
+                found.extend(owners.into_iter().map(|(path, owners)| Ownership { path, owners }))
+            }
+        }
+    }
+
+    Ok(found)
+}
+
+/// Look up tracked files matching any of `queries` per repository.
+///
+/// Queries are resolved like those of [`find_owners`]. Will return the name
+/// of every repository tracking matching files in the order of repository
+/// configuration, along with those files relative to the working directory of
+/// the repository. Repositories without matching files are left out.
+///
+/// # Errors
+///
+/// Same as [`find_owners`].
+pub fn owned_files(
+    queries: &[String],
+    locator: &impl Locator,
+    home: &Path,
+) -> Result<Vec<(String, Vec<PathBuf>)>, OwnsError> {
+    let repos = load_tracked(locator, home)?;
+    let matchers =
+        queries.iter().map(|query| query_matcher(query)).collect::<Result<Vec<_>, _>>()?;
+    let owned = repos
+        .into_iter()
+        .filter_map(|repo| {
+            let files = repo
+                .files
+                .iter()
+                .filter(|file| {
+                    repo.roots.iter().any(|root| {
+                        let path = root.join(file);
+                        matchers.iter().any(|(_, matches)| matches(&path))
+                    })
+                })
+                .cloned()
+                .collect::<Vec<_>>();
+            (!files.is_empty()).then_some((repo.name, files))
+        })
+        .collect();
+    Ok(owned)
+}
+
+/// Load tracked files of every readable repository.
+fn load_tracked(locator: &impl Locator, home: &Path) -> Result<Vec<TrackedFiles>, OwnsError> {
+    let mut repos = Vec::new();
+    for settings in ConfigFile::load(RepoConfig, locator)?.entries()? {
+        match TrackedFiles::load(&settings, locator, home) {
+            Ok(Some(tracked)) => repos.push(tracked),
+            Ok(None) => debug!("Skip repository '{}', it does not exist", settings.name),
+            Err(err) => warn!("Skip repository '{}': {err}", settings.name),
+        }
+    }
+    Ok(repos)
+}
+
+type Matcher = Box<dyn Fn(&Path) -> bool>;
+
+/// Resolve `query` into its absolute path, and a matcher of the absolute
+/// paths of tracked files it reports.
+fn query_matcher(query: &str) -> Result<(PathBuf, Matcher), OwnsError> {
+    let path = expand_path(query);
+    let path = match path.is_relative() {
+        true => env::current_dir().map_err(|err| OwnsError::Cwd { source: err })?.join(path),
+        false => path,
+    };
+    let matches: Matcher = if is_glob(query) {
+        let pattern = Pattern::new(&path.to_string_lossy())
+            .map_err(|err| OwnsError::BadPattern { source: err, pattern: query.into() })?;
+        Box::new(move |file| pattern.matches_path_with(file, GLOB_OPTIONS))
+    } else if path.is_dir() {
+        let dir = path.clone();
+        Box::new(move |file| file.starts_with(&dir))
+    } else {
+        let path = path.clone();
+        Box::new(move |file| file == path)
+    };
+    Ok((path, matches))
+}
+
+/// Glob matching like shells do it, i.e., `*` stops at path separators and
+/// `**` crosses them. Leading dots match wildcards too, because most tracked
+/// files are dotfiles.
+const GLOB_OPTIONS: MatchOptions = MatchOptions {
+    case_sensitive: true,
+    require_literal_separator: true,
+    require_literal_leading_dot: false,
+};
+
+fn is_glob(query: &str) -> bool {
+    query.contains(['*', '?', '['])
+}
+
+/// Tracked files of one repository.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct TrackedFiles {
+    name: String,
+
+    /// Directories that tracked files are placed in.
+    roots: Vec<PathBuf>,
+
+    /// Tracked files relative to their root.
+    files: Vec<PathBuf>,
+}
+
+impl TrackedFiles {
+    /// Load tracked files of repository, or `None` if it does not exist.
+    ///
+    /// # Invariants
+    ///
+    /// Cache is keyed by size and modification time of the repository index,
+    /// because every change to the set of tracked files rewrites the index.
+    fn load(
+        settings: &RepoSettings,
+        locator: &impl Locator,
+        home: &Path,
+    ) -> Result<Option<Self>, OwnsError> {
+        let gitdir = settings.gitdir(locator.repos_dir());
+        if !gitdir.exists() {
+            return Ok(None);
+        }
+
+        let repo_err = |err| OwnsError::Repo { source: err, name: settings.name.clone() };
+        let repo = GitRepo::open(&gitdir).map_err(repo_err)?;
+        let mut roots: Vec<PathBuf> = repo.workdir().into_iter().map(Into::into).collect();
+        if settings.deploy_mode == DeployMode::Symlink {
+            roots.push(home.into());
+        }
+
+        let stamp = index_stamp(repo.gitdir());
+        let cache = locator.state_dir().join("owns").join(format!("{}.toml", settings.name));
+        let files = match read_cache(&cache, &stamp) {
+            Some(files) => files,
+            None => {
+                debug!("Rebuild tracked file cache of '{}'", settings.name);
+                let files = repo.tracked_files().map_err(repo_err)?;
+                if let Err(err) = write_cache(&cache, &stamp, &files) {
+                    // INVARIANT: an unwritable cache only costs speed, never the lookup.
+                    warn!("{err}");
+                }
+                files
+            }
+        };
+
+        Ok(Some(Self { name: settings.name.clone(), roots, files }))
+    }
+
+    /// Absolute paths of tracked files in every root.
+    fn paths(&self) -> impl Iterator<Item = PathBuf> + '_ {
+        self.roots.iter().flat_map(|root| self.files.iter().map(move |file| root.join(file)))
+    }
+}
+
+fn index_stamp(gitdir: &Path) -> String {
+    match fs::metadata(gitdir.join("index")) {
+        Ok(meta) => {
+            let mtime = meta
+                .modified()
+                .ok()
+                .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                .map(|time| time.as_nanos())
+                .unwrap_or_default();
+            format!("{}:{mtime}", meta.len())
+        }
+        Err(_) => "none".into(),
+    }
+}
+
+fn read_cache(path: &Path, stamp: &str) -> Option<Vec<PathBuf>> {
+    let doc: DocumentMut = fs::read_to_string(path).ok()?.parse().ok()?;
+    if doc.get("stamp")?.as_str()? != stamp {
+        return None;
+    }
+
+    let files = doc.get("files")?.as_array()?;
+    files.iter().map(|file| file.as_str().map(PathBuf::from)).collect()
+}
+
+fn write_cache(path: &Path, stamp: &str, files: &[PathBuf]) -> Result<(), OwnsError> {
+    let write_err = |err| OwnsError::FileWrite { source: err, path: path.into() };
+    let mut doc = DocumentMut::new();
+    doc.insert("stamp", Item::Value(Value::from(stamp)));
+    let files = files.iter().map(|file| String::from(file.to_string_lossy()));
+    doc.insert("files", Item::Value(Value::Array(Array::from_iter(files))));
+
+    mkdirp(path.parent().unwrap()).map_err(write_err)?;
+    fs::write(path, doc.to_string()).map_err(write_err)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        cli::Cli,
+        context::Context,
+        testenv::{mock_locator, FileKind, FixtureHarness},
+    };
+
+    use anyhow::Result;
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+    use rstest::{fixture, rstest};
+
+    #[fixture]
+    fn rice_dir() -> Result<FixtureHarness> {
+        let harness = FixtureHarness::open()?
+            .with_file("config/repos.toml", |fixture| {
+                fixture
+                    .with_data(indoc! {r#"
+                        [repos.vim]
+                        branch = "main"
+                        remote = "origin"
+
+                        [repos.sh]
+                        branch = "main"
+                        remote = "origin"
+                        workdir_home = true
+
+                        [repos.gone]
+                        branch = "main"
+                        remote = "origin"
+                    "#})
+                    .with_kind(FileKind::Normal)
+            })
+            .with_repo("repos/vim", |repo| {
+                let repo = repo.stage(".vimrc", "set number")?.stage(".vim/colors.vim", "")?;
+                repo.commit("initial commit")?;
+                Ok(repo)
+            })?
+            .with_fake_bare_repo("repos/sh", |repo| {
+                let repo = repo.stage(".bashrc", "")?.stage(".config/sh/aliases.conf", "")?;
+                repo.commit("initial commit")?;
+                Ok(repo)
+            })?
+            .with_file(".config/untracked.conf", |fixture| fixture.with_data(""))
+            .setup()?;
+        Ok(harness)
+    }
+
+    fn owns_ctx(paths: &[PathBuf]) -> Result<OwnsContext> {
+        let args = ["ricer".into(), "owns".into()].into_iter().chain(paths.iter().cloned());
+        match Context::from(Cli::parse_args(args.map(PathBuf::into_os_string))?) {
+            Context::Owns(ctx) => Ok(ctx),
+            _ => unreachable!(),
+        }
+    }
+
+    #[rstest]
+    #[case::file(vec![".bashrc"], vec![(".bashrc", "sh")])]
+    #[case::untracked_file(vec![".config/untracked.conf"], vec![(".config/untracked.conf", "")])]
+    #[case::directory(vec![".config"], vec![(".config/sh/aliases.conf", "sh")])]
+    #[case::glob(vec!["*rc"], vec![(".bashrc", "sh")])]
+    #[case::recursive_glob(
+        vec!["**/*.conf"],
+        vec![(".config/sh/aliases.conf", "sh")],
+    )]
+    #[case::many(
+        vec!["repos/vim.git/.vim", "nope"],
+        vec![("repos/vim.git/.vim/colors.vim", "vim"), ("nope", "")],
+    )]
+    fn find_owners_report_owning_repos(
+        rice_dir: Result<FixtureHarness>,
+        #[case] queries: Vec<&str>,
+        #[case] expect: Vec<(&str, &str)>,
+    ) -> Result<()> {
+        let rice_dir = rice_dir?;
+        let root = rice_dir.as_path();
+        let queries = queries.iter().map(|query| root.join(query)).collect::<Vec<_>>();
+        let result = find_owners(&owns_ctx(&queries)?, &mock_locator(&rice_dir), root)?;
+
+        let expect = expect
+            .into_iter()
+            .map(|(path, owner)| Ownership {
+                path: root.join(path),
+                owners: Some(owner.to_string())
+                    .filter(|owner| !owner.is_empty())
+                    .into_iter()
+                    .collect(),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(result, expect);
+        Ok(())
+    }
+
+    #[rstest]
+    fn find_owners_refresh_stale_cache(rice_dir: Result<FixtureHarness>) -> Result<()> {
+        let rice_dir = rice_dir?;
+        let root = rice_dir.as_path().to_path_buf();
+        let locator = mock_locator(&rice_dir);
+        let ctx = owns_ctx(&[root.join(".profile")])?;
+        assert!(find_owners(&ctx, &locator, &root)?[0].owners.is_empty());
+        assert!(root.join("state/owns/sh.toml").exists());
+
+        let repo = rice_dir.get_repo("repos/sh")?;
+        fs::write(root.join(".profile"), "")?;
+        repo.add(".profile")?;
+        assert_eq!(find_owners(&ctx, &locator, &root)?[0].owners, vec!["sh".to_string()]);
+        Ok(())
+    }
+
+    #[rstest]
+    fn owned_files_group_matches_by_repo(rice_dir: Result<FixtureHarness>) -> Result<()> {
+        let rice_dir = rice_dir?;
+        let root = rice_dir.as_path();
+        let queries = [".config/**", "repos/vim.git/.vimrc", ".profile"]
+            .map(|query| root.join(query).to_string_lossy().into_owned());
+        let result = owned_files(&queries, &mock_locator(&rice_dir), root)?;
+        let expect = vec![
+            ("vim".to_string(), vec![PathBuf::from(".vimrc")]),
+            ("sh".to_string(), vec![PathBuf::from(".config/sh/aliases.conf")]),
+        ];
+        assert_eq!(result, expect);
+        Ok(())
+    }
+
+    #[test]
+    fn ownership_display_owners_or_untracked() {
+        let ownership = Ownership { path: "/home/.vimrc".into(), owners: Vec::new() };
+        assert_eq!(ownership.to_string(), "/home/.vimrc: untracked");
+        let ownership =
+            Ownership { path: "/home/.vimrc".into(), owners: vec!["a".into(), "b".into()] };
+        assert_eq!(ownership.to_string(), "/home/.vimrc: a, b");
+    }
+}
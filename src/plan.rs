@@ -0,0 +1,507 @@
+// SPDX-FileCopyrightText: 2024 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+//! Effect planner.
+//!
+//! Commands that perform several side effects describe them up front as a
+//! [`Plan`] of [`Effect`]s. A plan can either be shown to the user as-is, e.g.,
+//! for `--dry-run`, or be applied in order. All validation happens while
+//! planning, so the user sees every problem before anything is touched.
+//!
+//! Commands that perform their side effects as they go instead ask
+//! [`perform`] before each one. Once [`install_dry_run`] was called for the
+//! global `--dry-run` flag, side effects are recorded as [`DryRunEffect`]s
+//! instead of being performed, so the user gets to see what Ricer would have
+//! done, e.g., which files it would have written, which repositories it would
+//! have cloned, and which hooks it would have run.
+
+use crate::{
+    cli::Cli,
+    config::{
+        validate_repo_name, ConfigFile, ConfigFileError, RepoConfig, RepoNameError, TomlError,
+    },
+    context::RenameContext,
+    locate::Locator,
+};
+
+use clap::CommandFactory;
+use log::{debug, info};
+use std::{
+    fmt, fs,
+    io::Error as IoError,
+    path::PathBuf,
+    sync::{Mutex, OnceLock},
+};
+
+/// Error types for [`Plan`].
+#[derive(Debug, thiserror::Error)]
+pub enum PlanError {
+    #[error("Repository '{name}' is not registered in '{path}'")]
+    NotRegistered { name: String, path: PathBuf },
+
+    #[error("Repository '{name}' is already registered in '{path}'")]
+    AlreadyRegistered { name: String, path: PathBuf },
+
+    #[error("Path '{path}' already exists")]
+    PathExists { path: PathBuf },
+
+    #[error("Repository name '{name}' collides with a Ricer command")]
+    ReservedName { name: String },
+
+    #[error("Invalid repository name '{name}'")]
+    BadName { source: RepoNameError, name: String },
+
+    #[error("Failed to move '{from}' to '{to}'")]
+    MoveDir { source: IoError, from: PathBuf, to: PathBuf },
+
+    #[error("Failed to update configuration file")]
+    Config { source: ConfigFileError },
+}
+
+impl From<ConfigFileError> for PlanError {
+    fn from(err: ConfigFileError) -> Self {
+        PlanError::Config { source: err }
+    }
+}
+
+/// Side effect of a command.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Effect {
+    /// Move directory `from` to `to`.
+    MoveDir { from: PathBuf, to: PathBuf },
+
+    /// Rename repository entry `from` to `to` in repository configuration file.
+    RenameRepoEntry { config: PathBuf, from: String, to: String },
+}
+
+impl Effect {
+    fn apply(&self, locator: &impl Locator) -> Result<(), PlanError> {
+        info!("{self}");
+        match self {
+            Effect::MoveDir { from, to } => fs::rename(from, to).map_err(|err| {
+                PlanError::MoveDir { source: err, from: from.clone(), to: to.clone() }
+            })?,
+            Effect::RenameRepoEntry { from, to, .. } => {
+                let mut config = ConfigFile::load(RepoConfig, locator)?;
+                config.rename(from, to)?;
+                config.save()?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for Effect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Effect::MoveDir { from, to } => {
+                write!(f, "Move directory '{}' to '{}'", from.display(), to.display())
+            }
+            Effect::RenameRepoEntry { config, from, to } => {
+                write!(f, "Rename entry '{from}' to '{to}' in '{}'", config.display())
+            }
+        }
+    }
+}
+
+/// Ordered listing of side effects to perform.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Plan {
+    effects: Vec<Effect>,
+}
+
+impl Plan {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn effect(mut self, effect: Effect) -> Self {
+        self.effects.push(effect);
+        self
+    }
+
+    pub fn effects(&self) -> &[Effect] {
+        self.effects.as_slice()
+    }
+
+    /// Apply all effects in order.
+    ///
+    /// # Errors
+    ///
+    /// - Return [`PlanError`] at the first effect that fails to apply. Effects
+    ///   that were already applied are not rolled back.
+    pub fn apply(&self, locator: &impl Locator) -> Result<(), PlanError> {
+        for effect in &self.effects {
+            effect.apply(locator)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for Plan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (step, effect) in self.effects.iter().enumerate() {
+            writeln!(f, "{}. {effect}", step + 1)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Side effect skipped through `--dry-run`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DryRunEffect {
+    /// Write file at `path`.
+    WriteFile { path: PathBuf },
+
+    /// Remove file at `path`.
+    RemoveFile { path: PathBuf },
+
+    /// Remove directory at `path` with everything inside it.
+    RemoveDir { path: PathBuf },
+
+    /// Run hook `script`.
+    RunHook { script: PathBuf },
+
+    /// Clone repository from `url` into `path`.
+    Clone { url: String, path: PathBuf },
+
+    /// Perform Git operation `action` on Git directory `gitdir`.
+    Git { gitdir: PathBuf, action: String },
+}
+
+impl fmt::Display for DryRunEffect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DryRunEffect::WriteFile { path } => write!(f, "Write file '{}'", path.display()),
+            DryRunEffect::RemoveFile { path } => write!(f, "Remove file '{}'", path.display()),
+            DryRunEffect::RemoveDir { path } => write!(f, "Remove directory '{}'", path.display()),
+            DryRunEffect::RunHook { script } => write!(f, "Run hook '{}'", script.display()),
+            DryRunEffect::Clone { url, path } => {
+                write!(f, "Clone '{url}' into '{}'", path.display())
+            }
+            DryRunEffect::Git { gitdir, action } => write!(f, "{action} in '{}'", gitdir.display()),
+        }
+    }
+}
+
+/// Recorder of side effects skipped through `--dry-run`.
+#[derive(Debug, Default)]
+pub struct DryRun {
+    effects: Mutex<Vec<DryRunEffect>>,
+}
+
+impl DryRun {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Record `effect` as skipped.
+    pub fn record(&self, effect: DryRunEffect) {
+        info!("Dry run, skip: {effect}");
+        self.effects.lock().unwrap_or_else(|err| err.into_inner()).push(effect);
+    }
+
+    /// Skipped effects in the order they were recorded.
+    pub fn effects(&self) -> Vec<DryRunEffect> {
+        self.effects.lock().unwrap_or_else(|err| err.into_inner()).clone()
+    }
+}
+
+impl fmt::Display for DryRun {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let effects = self.effects();
+        if effects.is_empty() {
+            return writeln!(f, "Dry run, nothing would be changed");
+        }
+
+        writeln!(f, "Dry run, nothing was changed. Ricer would:")?;
+        for (step, effect) in effects.iter().enumerate() {
+            writeln!(f, "{}. {effect}", step + 1)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn installed() -> &'static OnceLock<DryRun> {
+    static DRY_RUN: OnceLock<DryRun> = OnceLock::new();
+    &DRY_RUN
+}
+
+/// Record side effects passed to [`perform`] from now on, instead of
+/// performing them.
+///
+/// Only the first call takes effect.
+pub fn install_dry_run() {
+    if installed().set(DryRun::new()).is_err() {
+        debug!("Dry run was already installed");
+    }
+}
+
+/// Recorder given to [`install_dry_run`], if any.
+pub fn dry_run() -> Option<&'static DryRun> {
+    installed().get()
+}
+
+/// Check if side effects are recorded instead of performed.
+pub fn is_dry_run() -> bool {
+    dry_run().is_some()
+}
+
+/// Check if side effect described by `effect` should be performed.
+///
+/// Through [`install_dry_run`], `effect` is recorded instead, and `false` is
+/// returned, so the caller skips it.
+pub fn perform(effect: impl FnOnce() -> DryRunEffect) -> bool {
+    match dry_run() {
+        Some(dry_run) => {
+            dry_run.record(effect());
+            false
+        }
+        None => true,
+    }
+}
+
+/// Plan repository rename.
+///
+/// Renaming a repository moves its Git directory in the repository directory
+/// if it exists, and renames its entry in the repository configuration file.
+/// Git directories given through the `path` override of a repository are left
+/// where they are.
+///
+/// # Errors
+///
+/// - Return [`PlanError::NotRegistered`] if `from` is not registered.
+/// - Return [`PlanError::AlreadyRegistered`] if `to` is already registered.
+/// - Return [`PlanError::PathExists`] if `to` already exists in repository
+///   directory.
+/// - Return [`PlanError::BadName`] if `to` is not a valid repository name,
+///   see [`validate_repo_name`].
+/// - Return [`PlanError::ReservedName`] if `to` is the name of a Ricer command,
+///   because it would shadow that command for the Git shortcut.
+/// - Return [`PlanError::Config`] if repository configuration file could not
+///   be loaded.
+pub fn plan_rename(ctx: &RenameContext, locator: &impl Locator) -> Result<Plan, PlanError> {
+    let config = ConfigFile::load(RepoConfig, locator)?;
+    let path = config.as_path().to_path_buf();
+
+    validate_repo_name(&ctx.to)
+        .map_err(|err| PlanError::BadName { source: err, name: ctx.to.clone() })?;
+    if is_reserved(&ctx.to) {
+        return Err(PlanError::ReservedName { name: ctx.to.clone() });
+    }
+
+    let repo = match config.get(&ctx.from) {
+        Ok(repo) => repo,
+        Err(err) if is_not_found(&err) => {
+            return Err(PlanError::NotRegistered { name: ctx.from.clone(), path })
+        }
+        Err(err) => return Err(err.into()),
+    };
+
+    match config.get(&ctx.to) {
+        Ok(_) => return Err(PlanError::AlreadyRegistered { name: ctx.to.clone(), path }),
+        Err(err) if is_not_found(&err) => (),
+        Err(err) => return Err(err.into()),
+    }
+
+    let from_dir = locator.repos_dir().join(format!("{}.git", ctx.from));
+    let to_dir = locator.repos_dir().join(format!("{}.git", ctx.to));
+    if to_dir.exists() {
+        return Err(PlanError::PathExists { path: to_dir });
+    }
+
+    let mut plan = Plan::new();
+    // INVARIANT: move directory first, so a failed move leaves configuration untouched.
+    // INVARIANT: never move Git directories that are shared through a path override.
+    if repo.path.is_none() && from_dir.exists() {
+        plan = plan.effect(Effect::MoveDir { from: from_dir, to: to_dir });
+    }
+    plan = plan.effect(Effect::RenameRepoEntry {
+        config: path,
+        from: ctx.from.clone(),
+        to: ctx.to.clone(),
+    });
+
+    Ok(plan)
+}
+
+fn is_reserved(name: &str) -> bool {
+    name == "help"
+        || Cli::command()
+            .get_subcommands()
+            .any(|cmd| cmd.get_name() == name || cmd.get_all_aliases().any(|alias| alias == name))
+}
+
+fn is_not_found(err: &ConfigFileError) -> bool {
+    matches!(
+        err,
+        ConfigFileError::Toml {
+            source: TomlError::EntryNotFound { .. } | TomlError::TableNotFound { .. },
+            ..
+        }
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::RepoSettings,
+        context::Context,
+        locate::MockLocator,
+        testenv::{FileKind, FixtureHarness},
+    };
+
+    use anyhow::Result;
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+    use rstest::{fixture, rstest};
+
+    #[fixture]
+    fn repo_dir() -> Result<FixtureHarness> {
+        let harness = FixtureHarness::open()?
+            .with_repo("data/vim", |repo| repo.stage("vimrc", "config for vim!"))?
+            .with_repo("data/st", |repo| repo.stage("config.h", "configure st here"))?
+            .with_file("config/repos.toml", |fixture| {
+                fixture
+                    .with_data(indoc! {r#"
+                        [repos.vim]
+                        branch = "main"
+                        remote = "origin"
+                        workdir_home = false
+
+                        [repos.dwm]
+                        branch = "main"
+                        remote = "origin"
+                        workdir_home = false
+                    "#})
+                    .with_kind(FileKind::Normal)
+            })
+            .setup()?;
+        Ok(harness)
+    }
+
+    fn rename_ctx(from: &str, to: &str) -> Result<RenameContext> {
+        match Context::from(Cli::parse_args(["ricer", "rename", from, to])?) {
+            Context::Rename(ctx) => Ok(ctx),
+            _ => unreachable!(),
+        }
+    }
+
+    fn locator(repo_dir: &FixtureHarness) -> Result<MockLocator> {
+        let mut locator = MockLocator::new();
+        locator
+            .expect_repos_config()
+            .return_const(repo_dir.get_file("config/repos.toml")?.as_path().into());
+        locator.expect_repos_dir().return_const(repo_dir.as_path().join("data"));
+        Ok(locator)
+    }
+
+    #[rstest]
+    fn plan_rename_return_plan(repo_dir: Result<FixtureHarness>) -> Result<()> {
+        let repo_dir = repo_dir?;
+        let locator = locator(&repo_dir)?;
+        let plan = plan_rename(&rename_ctx("vim", "neovim")?, &locator)?;
+        let expect = Plan::new()
+            .effect(Effect::MoveDir {
+                from: repo_dir.as_path().join("data/vim.git"),
+                to: repo_dir.as_path().join("data/neovim.git"),
+            })
+            .effect(Effect::RenameRepoEntry {
+                config: locator.repos_config().into(),
+                from: "vim".into(),
+                to: "neovim".into(),
+            });
+        assert_eq!(plan, expect);
+        Ok(())
+    }
+
+    #[rstest]
+    fn plan_rename_skip_missing_dir(repo_dir: Result<FixtureHarness>) -> Result<()> {
+        let repo_dir = repo_dir?;
+        let locator = locator(&repo_dir)?;
+        let plan = plan_rename(&rename_ctx("dwm", "dwm-flexipatch")?, &locator)?;
+        assert_eq!(plan.effects().len(), 1);
+        assert!(matches!(plan.effects()[0], Effect::RenameRepoEntry { .. }));
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::not_registered("foo", "bar")]
+    #[case::already_registered("vim", "dwm")]
+    #[case::path_exists("vim", "st")]
+    #[case::reserved_name("vim", "commit")]
+    #[case::reserved_help("vim", "help")]
+    #[case::bad_name("vim", "../x")]
+    fn plan_rename_return_err(
+        repo_dir: Result<FixtureHarness>,
+        #[case] from: &str,
+        #[case] to: &str,
+    ) -> Result<()> {
+        let repo_dir = repo_dir?;
+        let locator = locator(&repo_dir)?;
+        let result = plan_rename(&rename_ctx(from, to)?, &locator);
+        let err = result.unwrap_err();
+        match (from, to) {
+            ("foo", _) => assert!(matches!(err, PlanError::NotRegistered { .. })),
+            (_, "dwm") => assert!(matches!(err, PlanError::AlreadyRegistered { .. })),
+            (_, "st") => assert!(matches!(err, PlanError::PathExists { .. })),
+            (_, "../x") => assert!(matches!(err, PlanError::BadName { .. })),
+            _ => assert!(matches!(err, PlanError::ReservedName { .. })),
+        }
+        Ok(())
+    }
+
+    #[rstest]
+    fn dry_run_display_list_skipped_effects() {
+        let dry_run = DryRun::new();
+        assert_eq!(dry_run.to_string(), "Dry run, nothing would be changed\n");
+
+        dry_run.record(DryRunEffect::Clone {
+            url: "https://a.org/vim.git".into(),
+            path: "vim.git".into(),
+        });
+        dry_run.record(DryRunEffect::RunHook { script: "hooks/setup.sh".into() });
+        dry_run.record(DryRunEffect::Git {
+            gitdir: "vim.git".into(),
+            action: "Commit staged changes".into(),
+        });
+        let expect = indoc! {"
+            Dry run, nothing was changed. Ricer would:
+            1. Clone 'https://a.org/vim.git' into 'vim.git'
+            2. Run hook 'hooks/setup.sh'
+            3. Commit staged changes in 'vim.git'
+        "};
+        assert_eq!(dry_run.to_string(), expect);
+    }
+
+    #[rstest]
+    fn perform_without_dry_run_return_true() {
+        // INVARIANT: tests never install a dry run, like runs without `--dry-run`.
+        assert!(!is_dry_run());
+        assert!(perform(|| DryRunEffect::WriteFile { path: "repos.toml".into() }));
+    }
+
+    #[rstest]
+    fn plan_apply_rename(repo_dir: Result<FixtureHarness>) -> Result<()> {
+        let mut repo_dir = repo_dir?;
+        let locator = locator(&repo_dir)?;
+        let plan = plan_rename(&rename_ctx("vim", "neovim")?, &locator)?;
+        plan.apply(&locator)?;
+
+        repo_dir.sync_untracked()?;
+        assert!(repo_dir.get_repo("data/neovim").is_ok());
+        assert!(!repo_dir.as_path().join("data/vim.git").exists());
+        let config = ConfigFile::load(RepoConfig, &locator)?;
+        assert_eq!(
+            config.get("neovim")?,
+            RepoSettings::new("neovim").branch("main").remote("origin")
+        );
+        assert!(config.get("vim").is_err());
+        Ok(())
+    }
+}
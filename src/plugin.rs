@@ -0,0 +1,236 @@
+// SPDX-FileCopyrightText: 2024 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+//! External subcommand plugins.
+//!
+//! Like Git and Cargo, Ricer can be extended without forking it. Any
+//! executable named `ricer-<NAME>` on `PATH` becomes available as
+//! `ricer <NAME>`. Arguments after `<NAME>` are forwarded to the plugin
+//! verbatim, and the plugin's exit code becomes Ricer's exit code.
+//!
+//! Plugins receive Ricer's resolved environment through environment
+//! variables, the same ones `ricer env` reports, like `RICER_CONFIG_DIR`,
+//! along with `RICER_BIN`, `RICER_LOG_LEVEL`, and `RICER_PAGER`, so they need
+//! not replicate how Ricer locates its data.
+//!
+//! # Invariants
+//!
+//! Registered repositories take precedence over plugins, so a plugin on `PATH`
+//! can never hijack the Git shortcut of an existing repository.
+
+use crate::{config::PagerMode, context::Context, locate::Locator, ops::EnvReport};
+
+use log::debug;
+use std::{
+    env,
+    ffi::{OsStr, OsString},
+    io::Error as IoError,
+    os::unix::fs::PermissionsExt,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+/// Prefix of plugin executable names.
+pub const PLUGIN_PREFIX: &str = "ricer-";
+
+/// Error types for [`Plugin`].
+#[derive(Debug, thiserror::Error)]
+pub enum PluginError {
+    #[error("No repository or command named '{name}'")]
+    NotFound { name: String },
+
+    #[error("Failed to run plugin '{path}'")]
+    Spawn { source: IoError, path: PathBuf },
+}
+
+/// Executable that implements an external subcommand.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Plugin {
+    name: String,
+    path: PathBuf,
+}
+
+impl Plugin {
+    /// Find plugin for subcommand `name` in directories of `search_path`.
+    ///
+    /// The first executable match wins, just like in shells. Names containing
+    /// path separators never match, so `ricer ../foo` cannot run arbitrary
+    /// executables.
+    ///
+    /// # Errors
+    ///
+    /// - Return [`PluginError::NotFound`] if no plugin exists for `name`.
+    pub fn find(name: &OsStr, search_path: Option<&OsStr>) -> Result<Self, PluginError> {
+        let name = name.to_string_lossy().into_owned();
+        let not_found = || PluginError::NotFound { name: name.clone() };
+        if name.is_empty() || name.contains(std::path::is_separator) || name.starts_with('-') {
+            return Err(not_found());
+        }
+
+        let file_name = format!("{PLUGIN_PREFIX}{name}");
+        let path = env::split_paths(search_path.ok_or_else(not_found)?)
+            .map(|dir| dir.join(&file_name))
+            .find(|path| is_executable(path))
+            .ok_or_else(not_found)?;
+
+        Ok(Self { name, path })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn as_path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Run plugin with `args` and environment `vars`.
+    ///
+    /// Standard input and output are inherited, so plugins can be
+    /// interactive. Returns exit code of plugin, where termination through a
+    /// signal counts as a failure.
+    ///
+    /// # Errors
+    ///
+    /// - Return [`PluginError::Spawn`] if plugin cannot be executed.
+    pub fn run<K, V>(
+        &self,
+        args: &[OsString],
+        vars: impl IntoIterator<Item = (K, V)>,
+    ) -> Result<i32, PluginError>
+    where
+        K: AsRef<OsStr>,
+        V: AsRef<OsStr>,
+    {
+        debug!("Run plugin '{}'", self.path.display());
+        let status = Command::new(&self.path)
+            .args(args)
+            .envs(vars)
+            .status()
+            .map_err(|err| PluginError::Spawn { source: err, path: self.path.clone() })?;
+
+        Ok(status.code().unwrap_or(1))
+    }
+}
+
+/// Environment variables describing resolved environment to plugins.
+pub fn plugin_env(
+    context: &Context,
+    locator: &impl Locator,
+    pager: PagerMode,
+) -> Vec<(String, String)> {
+    let mut vars = EnvReport::new(context, locator)
+        .iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect::<Vec<_>>();
+    if let Ok(exe) = env::current_exe() {
+        vars.push(("RICER_BIN".into(), exe.display().to_string()));
+    }
+    vars.push(("RICER_LOG_LEVEL".into(), log::max_level().as_str().to_lowercase()));
+    vars.push(("RICER_PAGER".into(), pager.to_string()));
+    vars
+}
+
+fn is_executable(path: &Path) -> bool {
+    path.metadata().is_ok_and(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        cli::Cli,
+        locate::MockLocator,
+        testenv::{FileKind, FixtureHarness},
+    };
+
+    use anyhow::Result;
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+    use rstest::{fixture, rstest};
+    use std::fs;
+
+    #[fixture]
+    fn plugin_dir() -> Result<FixtureHarness> {
+        let harness = FixtureHarness::open()?
+            .with_file("bin/ricer-hello", |fixture| {
+                fixture
+                    .with_data(indoc! {r#"
+                        #!/bin/sh
+                        echo "$RICER_CONFIG_DIR $RICER_PAGER $*" > "$1"
+                        exit 3
+                    "#})
+                    .with_kind(FileKind::Script)
+            })
+            .with_file("bin/ricer-notes", |fixture| fixture.with_data("not executable"))
+            .with_file("other/ricer-notes", |fixture| {
+                fixture.with_data("#!/bin/sh\n").with_kind(FileKind::Script)
+            })
+            .setup()?;
+        Ok(harness)
+    }
+
+    fn search_path(plugin_dir: &FixtureHarness) -> Result<OsString> {
+        let dirs = [plugin_dir.as_path().join("bin"), plugin_dir.as_path().join("other")];
+        Ok(env::join_paths(dirs)?)
+    }
+
+    #[rstest]
+    #[case::first_dir("hello", "bin/ricer-hello")]
+    #[case::skip_non_executable("notes", "other/ricer-notes")]
+    fn plugin_find_return_self(
+        plugin_dir: Result<FixtureHarness>,
+        #[case] name: &str,
+        #[case] expect: &str,
+    ) -> Result<()> {
+        let plugin_dir = plugin_dir?;
+        let search_path = search_path(&plugin_dir)?;
+        let plugin = Plugin::find(OsStr::new(name), Some(&search_path))?;
+        assert_eq!(plugin.name(), name);
+        assert_eq!(plugin.as_path(), plugin_dir.as_path().join(expect));
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::missing("nope")]
+    #[case::path_separator("../bin/ricer-hello")]
+    #[case::flag("--help")]
+    #[case::empty("")]
+    fn plugin_find_return_err_not_found(
+        plugin_dir: Result<FixtureHarness>,
+        #[case] name: &str,
+    ) -> Result<()> {
+        let plugin_dir = plugin_dir?;
+        let search_path = search_path(&plugin_dir)?;
+        let result = Plugin::find(OsStr::new(name), Some(&search_path));
+        assert!(matches!(result.unwrap_err(), PluginError::NotFound { .. }));
+        Ok(())
+    }
+
+    #[rstest]
+    fn plugin_run_forward_args_env_and_exit_code(plugin_dir: Result<FixtureHarness>) -> Result<()> {
+        let plugin_dir = plugin_dir?;
+        let search_path = search_path(&plugin_dir)?;
+        let out = plugin_dir.as_path().join("out.txt");
+        let mut locator = MockLocator::new();
+        locator.expect_config_dir().return_const("/config".into());
+        locator.expect_hooks_dir().return_const("/config/hooks".into());
+        locator.expect_hooks_config().return_const("/config/hooks.toml".into());
+        locator.expect_repos_dir().return_const("/data".into());
+        locator.expect_repos_config().return_const("/config/repos.toml".into());
+        locator.expect_ricer_config().return_const("/config/config.toml".into());
+        locator.expect_state_dir().return_const("/state".into());
+        locator.expect_lock_file().return_const("/config/ricer.lock".into());
+        locator.expect_templates_dir().return_const("/config/templates".into());
+        locator.expect_ignores_dir().return_const("/config/ignores".into());
+
+        let ctx = Context::from(Cli::parse_args(["ricer", "hello"])?);
+        let vars = plugin_env(&ctx, &locator, PagerMode::Never);
+        let plugin = Plugin::find(OsStr::new("hello"), Some(&search_path))?;
+        let code = plugin.run(&[out.clone().into(), "--flag".into()], vars)?;
+
+        assert_eq!(code, 3);
+        assert_eq!(fs::read_to_string(&out)?, format!("/config never {} --flag\n", out.display()));
+        Ok(())
+    }
+}
@@ -0,0 +1,317 @@
+// SPDX-FileCopyrightText: 2024 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+//! Profile management.
+//!
+//! Profiles keep independent rice setups on one machine, e.g., one for home
+//! and one for work. Each profile has its own configuration directory given by
+//! [`ProfileLayout`]. The default profile is the base directory layout itself.
+//!
+//! Creating a variant of an existing profile is done by _forking_ it, which
+//! duplicates its configuration files and hook scripts into the new profile.
+//! Forked repositories can optionally keep sharing the Git directories of the
+//! original profile through the `path` override of their repository settings,
+//! instead of requiring separate clones.
+
+use crate::{
+    config::{encode_toml, read_plaintext, Config, ConfigFileError, RepoConfig, TomlError},
+    locate::{DefaultLocator, DirLayout, LocateError, Locator, ProfileLayout},
+};
+
+use log::{debug, info};
+use mkdirp::mkdirp;
+use std::{
+    fs::{self, File},
+    io::Error as IoError,
+    path::{Path, PathBuf},
+};
+
+/// Error types for profile management.
+#[derive(Debug, thiserror::Error)]
+pub enum ProfileError {
+    #[error("Failed to locate profile")]
+    Locate { source: LocateError },
+
+    #[error("Profile '{name}' does not exist at '{path}'")]
+    NotFound { name: String, path: PathBuf },
+
+    #[error("Profile '{name}' already exists at '{path}'")]
+    AlreadyExists { name: String, path: PathBuf },
+
+    #[error("Failed to copy '{path}'")]
+    Copy { source: IoError, path: PathBuf },
+
+    #[error("Failed to update forked repository configuration")]
+    Config { source: ConfigFileError },
+}
+
+impl From<LocateError> for ProfileError {
+    fn from(err: LocateError) -> Self {
+        ProfileError::Locate { source: err }
+    }
+}
+
+impl From<ConfigFileError> for ProfileError {
+    fn from(err: ConfigFileError) -> Self {
+        ProfileError::Config { source: err }
+    }
+}
+
+/// Fork profile `from` into new profile `name`.
+///
+/// Forks the default profile if `from` is `None`. If `share_repos` is set,
+/// then every forked repository that does not already override its Git
+/// directory path will point to the Git directory of the original profile.
+///
+/// Returns directory layout of new profile.
+///
+/// # Invariants
+///
+/// The new profile is assembled in a temporary directory first, and is only
+/// moved into place once it is complete. Thus, a failed fork never leaves
+/// behind a partial profile.
+///
+/// # Errors
+///
+/// - Return [`ProfileError::Locate`] if a profile name is invalid.
+/// - Return [`ProfileError::NotFound`] if profile `from` does not exist.
+/// - Return [`ProfileError::AlreadyExists`] if profile `name` already exists.
+/// - Return [`ProfileError::Copy`] if configuration data could not be copied.
+/// - Return [`ProfileError::Config`] if forked repository configuration could
+///   not be updated.
+pub fn fork_profile(
+    base: impl DirLayout,
+    name: &str,
+    from: Option<&str>,
+    share_repos: bool,
+) -> Result<ProfileLayout, ProfileError> {
+    let target = ProfileLayout::new(&base, name)?;
+    let (source_config, source_repos, skip_profiles) = match from {
+        Some(from) => {
+            let source = ProfileLayout::new(&base, from)?;
+            let config_dir = source.config_dir().to_path_buf();
+            if !config_dir.is_dir() {
+                return Err(ProfileError::NotFound { name: from.into(), path: config_dir });
+            }
+            (config_dir, DefaultLocator::locate(source).repos_dir().to_path_buf(), false)
+        }
+        None => {
+            let config_dir = base.config_dir().to_path_buf();
+            (config_dir, DefaultLocator::locate(&base).repos_dir().to_path_buf(), true)
+        }
+    };
+
+    if target.config_dir().exists() {
+        return Err(ProfileError::AlreadyExists {
+            name: name.into(),
+            path: target.config_dir().into(),
+        });
+    }
+
+    let profiles_dir = base.config_dir().join("profiles");
+    let staging = profiles_dir.join(format!(".{name}.fork"));
+    info!("Fork profile '{}' into '{name}'", from.unwrap_or("default"));
+    let result = stage_fork(&source_config, &source_repos, &staging, skip_profiles, share_repos)
+        .and_then(|_| {
+            fs::rename(&staging, target.config_dir())
+                .map_err(|err| ProfileError::Copy { source: err, path: staging.clone() })
+        });
+
+    if result.is_err() && staging.exists() {
+        // INVARIANT: never leave a partial profile behind.
+        let _ = fs::remove_dir_all(&staging);
+    }
+    result?;
+
+    Ok(target)
+}
+
+fn stage_fork(
+    source_config: &Path,
+    source_repos: &Path,
+    staging: &Path,
+    skip_profiles: bool,
+    share_repos: bool,
+) -> Result<(), ProfileError> {
+    // INVARIANT: remove leftovers of an interrupted fork.
+    if staging.exists() {
+        fs::remove_dir_all(staging)
+            .map_err(|err| ProfileError::Copy { source: err, path: staging.into() })?;
+    }
+
+    mkdirp(staging).map_err(|err| ProfileError::Copy { source: err, path: staging.into() })?;
+    if source_config.is_dir() {
+        copy_dir(source_config, staging, skip_profiles)?;
+    }
+
+    if share_repos {
+        share_gitdirs(&staging.join("repos.toml"), source_repos)?;
+    }
+
+    Ok(())
+}
+
+fn copy_dir(from: &Path, to: &Path, skip_profiles: bool) -> Result<(), ProfileError> {
+    let entries =
+        fs::read_dir(from).map_err(|err| ProfileError::Copy { source: err, path: from.into() })?;
+    for entry in entries {
+        let entry = entry.map_err(|err| ProfileError::Copy { source: err, path: from.into() })?;
+        let path = entry.path();
+
+        // INVARIANT: named profiles are nested in default profile, so do not fork them.
+        if skip_profiles && entry.file_name() == "profiles" {
+            continue;
+        }
+
+        let dest = to.join(entry.file_name());
+        if path.is_dir() {
+            mkdirp(&dest).map_err(|err| ProfileError::Copy { source: err, path: dest.clone() })?;
+            copy_dir(&path, &dest, false)?;
+        } else {
+            debug!("Copy '{}' to '{}'", path.display(), dest.display());
+            fs::copy(&path, &dest).map_err(|err| ProfileError::Copy { source: err, path })?;
+        }
+    }
+
+    Ok(())
+}
+
+fn share_gitdirs(repos_config: &Path, source_repos: &Path) -> Result<(), ProfileError> {
+    let file = match File::open(repos_config) {
+        Ok(file) => file,
+        Err(_) => return Ok(()), // Nothing to share without repository configuration file.
+    };
+    let toml_err =
+        |err: TomlError| ConfigFileError::Toml { source: err, path: repos_config.into() };
+    let (data, encrypted) = read_plaintext(file, repos_config)?;
+    let mut doc = data.parse().map_err(toml_err)?;
+
+    let entries = match RepoConfig.entries(&doc) {
+        Ok(entries) => entries,
+        Err(TomlError::TableNotFound { .. }) => return Ok(()),
+        Err(err) => return Err(toml_err(err).into()),
+    };
+
+    for repo in entries.into_iter().filter(|repo| repo.path.is_none()) {
+        let gitdir = source_repos.join(format!("{}.git", repo.name));
+        if !gitdir.exists() {
+            continue;
+        }
+
+        debug!("Share '{}' of repository '{}'", gitdir.display(), repo.name);
+        let repo = repo.path(gitdir);
+        RepoConfig.upsert(&mut doc, repo).map_err(toml_err)?;
+    }
+
+    // INVARIANT: locked repository configuration stays locked in forked profile.
+    let data = encode_toml(&doc, encrypted, repos_config)?;
+    fs::write(repos_config, data).map_err(|err| ProfileError::Config {
+        source: ConfigFileError::FileWrite { source: err, path: repos_config.into() },
+    })?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::{ConfigFile, RepoSettings},
+        locate::MockDirLayout,
+        testenv::{FileKind, FixtureHarness},
+    };
+
+    use anyhow::Result;
+    use indoc::indoc;
+    use is_executable::IsExecutable;
+    use pretty_assertions::assert_eq;
+    use rstest::{fixture, rstest};
+
+    #[fixture]
+    fn profile_dir() -> Result<FixtureHarness> {
+        let harness = FixtureHarness::open()?
+            .with_file("config/repos.toml", |fixture| {
+                fixture
+                    .with_data(indoc! {r#"
+                        [repos.vim]
+                        branch = "main"
+                        remote = "origin"
+                        workdir_home = false
+
+                        [repos.dwm]
+                        branch = "main"
+                        remote = "origin"
+                        workdir_home = false
+                    "#})
+                    .with_kind(FileKind::Normal)
+            })
+            .with_file("config/hooks/hook.sh", |fixture| {
+                fixture.with_data("#!/bin/sh\nexit 0\n").with_kind(FileKind::Script)
+            })
+            .with_file("config/profiles/home/repos.toml", |fixture| {
+                fixture.with_data("[repos]\n").with_kind(FileKind::Normal)
+            })
+            .with_repo("data/ricer/vim", |repo| repo.stage("vimrc", "config for vim!"))?
+            .setup()?;
+        Ok(harness)
+    }
+
+    fn layout(profile_dir: &FixtureHarness) -> MockDirLayout {
+        let mut layout = MockDirLayout::new();
+        layout.expect_config_dir().return_const(profile_dir.as_path().join("config"));
+        layout.expect_repo_dir().return_const(profile_dir.as_path().join("data"));
+        layout.expect_state_dir().return_const(profile_dir.as_path().join("state"));
+        layout
+    }
+
+    #[rstest]
+    fn fork_profile_copy_config(profile_dir: Result<FixtureHarness>) -> Result<()> {
+        let profile_dir = profile_dir?;
+        let target = fork_profile(layout(&profile_dir), "work", None, false)?;
+        let config_dir = target.config_dir();
+        assert_eq!(
+            fs::read_to_string(config_dir.join("repos.toml"))?,
+            profile_dir.get_file("config/repos.toml")?.as_str()
+        );
+        assert!(config_dir.join("hooks/hook.sh").is_executable());
+        assert!(!config_dir.join("profiles").exists());
+        assert!(!profile_dir.as_path().join("config/profiles/.work.fork").exists());
+        Ok(())
+    }
+
+    #[rstest]
+    fn fork_profile_share_repos(profile_dir: Result<FixtureHarness>) -> Result<()> {
+        let profile_dir = profile_dir?;
+        let target = fork_profile(layout(&profile_dir), "work", None, true)?;
+        let locator = DefaultLocator::locate(target);
+        let config = ConfigFile::load(RepoConfig, &locator)?;
+        let gitdir = profile_dir.as_path().join("data/ricer/vim.git");
+        assert_eq!(
+            config.get("vim")?,
+            RepoSettings::new("vim").branch("main").remote("origin").path(gitdir)
+        );
+        assert_eq!(config.get("dwm")?.path, None);
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::already_exists("home", None)]
+    #[case::not_found("work", Some("nobody"))]
+    #[case::bad_name(".work", None)]
+    fn fork_profile_return_err(
+        profile_dir: Result<FixtureHarness>,
+        #[case] name: &str,
+        #[case] from: Option<&str>,
+    ) -> Result<()> {
+        let profile_dir = profile_dir?;
+        let result = fork_profile(layout(&profile_dir), name, from, false);
+        match (name, from) {
+            ("home", _) => {
+                assert!(matches!(result.unwrap_err(), ProfileError::AlreadyExists { .. }))
+            }
+            (_, Some(_)) => assert!(matches!(result.unwrap_err(), ProfileError::NotFound { .. })),
+            _ => assert!(matches!(result.unwrap_err(), ProfileError::Locate { .. })),
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,542 @@
+// SPDX-FileCopyrightText: 2024 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+//! Pulling changes from remotes.
+//!
+//! Through `ricer pull`, every cloned repository pulls its branch from its
+//! remote, unless another remote or branch is given on the command-line. Once
+//! the branch of a repository is renamed upstream, the user is asked whether
+//! to migrate it to the new default branch of its remote, which `--fix` does
+//! without asking. Migrated branches are saved to the repository
+//! configuration, so the next pull uses them right away.
+//!
+//! Repositories are pulled one at a time, unless `--jobs N` pulls up to N of
+//! them at once. Migrations are only asked about once every pull finished.
+//!
+//! Machines that should never update a configuration on their own can pin its
+//! repository to a commit or tag:
+//!
+//! ```toml
+//! [repos.vim]
+//! branch = "main"
+//! remote = "origin"
+//! pin = "v1.2"
+//! ```
+//!
+//! Pinned repositories are only fetched, and then checked out at exactly
+//! their pin. Upstream changes are reported, but never merged. Removing the
+//! pin puts the repository back on its branch at the next pull.
+
+use crate::{
+    cancel,
+    config::{ConfigFile, ConfigFileError, RepoConfig, RepoSettings},
+    context::PullContext,
+    hook::PlanChanges,
+    locate::Locator,
+    ui::{Prompter, UiError},
+    util::{RepoIter, RepoResult, RepoSelector, SelectError},
+    vcs::{BranchMigration, GitRepo, GitRepoError},
+};
+
+use log::{debug, info};
+use std::{
+    fmt,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    thread,
+};
+
+/// Error types for [`pull_all`].
+#[derive(Debug, thiserror::Error)]
+pub enum PullError {
+    #[error("Failed to select repositories")]
+    Select { source: SelectError },
+
+    #[error("Failed to read repository configuration")]
+    Config { source: ConfigFileError },
+
+    #[error("Failed to ask about branch migration")]
+    Ui { source: UiError },
+}
+
+impl From<ConfigFileError> for PullError {
+    fn from(err: ConfigFileError) -> Self {
+        PullError::Config { source: err }
+    }
+}
+
+impl From<UiError> for PullError {
+    fn from(err: UiError) -> Self {
+        PullError::Ui { source: err }
+    }
+}
+
+impl From<SelectError> for PullError {
+    fn from(err: SelectError) -> Self {
+        PullError::Select { source: err }
+    }
+}
+
+/// Result of pulling one repository.
+pub type PullResult = RepoResult<PullOutcome, GitRepoError>;
+
+/// Outcome of pulling one repository.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PullOutcome {
+    /// Branch of repository was pulled.
+    Pulled,
+
+    /// Branch `from` of repository was migrated to new default branch `to` of
+    /// its remote, and then pulled.
+    Migrated { from: String, to: String },
+
+    /// Repository was checked out at its pin, with `upstream` commits of its
+    /// branch left out.
+    Pinned { rev: String, upstream: usize },
+}
+
+impl fmt::Display for PullOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PullOutcome::Pulled => write!(f, "pulled"),
+            PullOutcome::Migrated { from, to } => {
+                write!(f, "pulled, migrated branch '{from}' to '{to}'")
+            }
+            PullOutcome::Pinned { rev, upstream: 0 } => write!(f, "pinned to '{rev}'"),
+            PullOutcome::Pinned { rev, upstream } => {
+                write!(f, "pinned to '{rev}', {upstream} upstream commits not pulled")
+            }
+        }
+    }
+}
+
+/// Pull every registered repository, or those matching patterns, from
+/// [`PullContext`].
+///
+/// Returns result of each repository in configuration order. Repositories
+/// that cannot be pulled, e.g., because their pin names no commit, or their
+/// branch was renamed upstream and `prompter` declined the migration, fail on
+/// their own without stopping the others.
+///
+/// # Invariants
+///
+/// 1. Pinned repositories are never merged into, so their working directory
+///    only ever changes when their pin does.
+/// 1. Repositories left detached by a removed pin are put back on their
+///    branch before pulling.
+/// 1. Configured branches are only replaced by migrations of that same branch
+///    of that same remote, never of branches or remotes given on the
+///    command-line.
+///
+/// # Errors
+///
+/// - Return [`PullError::Select`] if repository patterns select no registered
+///   repository.
+/// - Return [`PullError::Config`] if repository configuration cannot be
+///   read, or migrated branches cannot be saved.
+/// - Return [`PullError::Ui`] if `prompter` fails.
+pub fn pull_all(
+    ctx: &PullContext,
+    locator: &impl Locator,
+    prompter: &impl Prompter,
+) -> Result<Vec<(String, PullResult)>, PullError> {
+    pull_all_with(ctx, locator, &PlanChanges::default(), prompter)
+}
+
+/// Pull every repository like [`pull_all`], leaving those skipped by plan
+/// `changes` requested by pre hooks alone.
+///
+/// # Errors
+///
+/// Same as [`pull_all`].
+pub fn pull_all_with(
+    ctx: &PullContext,
+    locator: &impl Locator,
+    changes: &PlanChanges,
+    prompter: &impl Prompter,
+) -> Result<Vec<(String, PullResult)>, PullError> {
+    let selector = RepoSelector::new(&ctx.repos).strict(ctx.strict);
+    let repos =
+        RepoIter::load::<PullError>(locator, selector)?.changes(changes).collect::<Vec<_>>();
+    let width = usize::from(ctx.jobs.unwrap_or(1)).min(repos.len());
+    let mut results = pull_repos(ctx, &repos, width);
+
+    // INVARIANT: parallel pulls never ask, so migrations are asked about one at a time after.
+    for ((settings, gitdir), result) in repos.iter().zip(results.iter_mut()) {
+        let RepoResult::Failed(GitRepoError::BranchMigrated { remote, from, to }) = result else {
+            continue;
+        };
+        let question = format!(
+            "Branch '{from}' of '{}' is gone from '{remote}', migrate to '{to}'?",
+            settings.name
+        );
+        if !prompter.confirm(&question)? {
+            continue;
+        }
+        let migration =
+            BranchMigration { remote: remote.clone(), from: from.clone(), to: to.clone() };
+        *result = match GitRepo::open(gitdir).and_then(|repo| migrate(&repo, migration)) {
+            Ok(outcome) => RepoResult::Done(outcome),
+            Err(err) => RepoResult::Failed(err),
+        };
+    }
+
+    let migrated = repos
+        .iter()
+        .zip(&results)
+        .filter_map(|((settings, _), result)| match result {
+            RepoResult::Done(PullOutcome::Migrated { from, to })
+                if *from == settings.branch
+                    && ctx.remote.as_ref().map_or(true, |remote| *remote == settings.remote) =>
+            {
+                Some((settings.name.as_str(), to.as_str()))
+            }
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    if !migrated.is_empty() {
+        let mut config = ConfigFile::load(RepoConfig, locator)?;
+        config.transaction(|config| {
+            for (name, branch) in migrated {
+                info!("Save branch '{branch}' of '{name}'");
+                config.set_branch(name, branch)?;
+            }
+            Ok::<_, ConfigFileError>(())
+        })?;
+    }
+
+    let results = repos
+        .into_iter()
+        .zip(results)
+        .map(|((settings, _), result)| {
+            debug!("Pull of '{}' {result}", settings.name);
+            (settings.name, result)
+        })
+        .collect();
+
+    Ok(results)
+}
+
+/// Pull `repos` on `width` threads at once, returning result of each
+/// repository in order.
+///
+/// Repositories not started before cancellation fail as cancelled.
+fn pull_repos(
+    ctx: &PullContext,
+    repos: &[(RepoSettings, PathBuf)],
+    width: usize,
+) -> Vec<PullResult> {
+    let pull = |(settings, gitdir): &(RepoSettings, PathBuf)| match gitdir.exists() {
+        false => RepoResult::Missing,
+        true => match GitRepo::open(gitdir).and_then(|repo| pull_repo(ctx, &repo, settings)) {
+            Ok(outcome) => RepoResult::Done(outcome),
+            Err(err) => RepoResult::Failed(err),
+        },
+    };
+    if width <= 1 {
+        return repos.iter().map(pull).collect();
+    }
+
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new((0..repos.len()).map(|_| None).collect::<Vec<_>>());
+    thread::scope(|scope| {
+        for _ in 0..width {
+            scope.spawn(|| loop {
+                let idx = next.fetch_add(1, Ordering::SeqCst);
+                if idx >= repos.len() || cancel::is_cancelled() {
+                    break;
+                }
+                let result = pull(&repos[idx]);
+                results.lock().unwrap()[idx] = Some(result);
+            });
+        }
+    });
+    let results = results.into_inner().unwrap();
+    results
+        .into_iter()
+        .map(|result| result.unwrap_or(RepoResult::Failed(GitRepoError::Cancelled)))
+        .collect()
+}
+
+fn pull_repo(
+    ctx: &PullContext,
+    repo: &GitRepo,
+    settings: &RepoSettings,
+) -> Result<PullOutcome, GitRepoError> {
+    let remote = ctx.remote.as_deref().unwrap_or(&settings.remote);
+    let branch = ctx.branch.as_deref().unwrap_or(&settings.branch);
+
+    if let Some(pin) = &settings.pin {
+        info!("Check out '{}' at pin '{pin}'", settings.name);
+        repo.fetch_remote(remote, branch)?;
+        let oid = repo.checkout_rev(pin)?;
+        let upstream = repo.upstream_ahead_of(remote, branch, oid)?;
+        return Ok(PullOutcome::Pinned { rev: pin.clone(), upstream });
+    }
+
+    // INVARIANT: merging into a detached HEAD would leave the pulled branch behind.
+    if repo.head_branch().is_none() && repo.checkout_branch(branch).is_ok() {
+        info!("Put '{}' back on branch '{branch}'", settings.name);
+    }
+
+    match repo.pull(remote, branch) {
+        Err(GitRepoError::BranchMigrated { remote, from, to }) if ctx.fix => {
+            migrate(repo, BranchMigration { remote, from, to })
+        }
+        result => result.map(|_| PullOutcome::Pulled),
+    }
+}
+
+/// Migrate `repo` to new default branch of its remote, and pull it.
+fn migrate(repo: &GitRepo, migration: BranchMigration) -> Result<PullOutcome, GitRepoError> {
+    repo.migrate_branch(&migration)?;
+    repo.pull(&migration.remote, &migration.to)?;
+    let BranchMigration { from, to, .. } = migration;
+    Ok(PullOutcome::Migrated { from, to })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testenv::{mock_locator, parse_ctx, FileKind, FixtureHarness, ScriptedPrompter};
+
+    use anyhow::Result;
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+    use rstest::{fixture, rstest};
+    use std::fs;
+
+    #[fixture]
+    fn rice_dir() -> Result<FixtureHarness> {
+        let harness = FixtureHarness::open()?
+            .with_file("config/repos.toml", |fixture| {
+                fixture
+                    .with_data(indoc! {r#"
+                        [repos.vim]
+                        branch = "main"
+                        remote = "origin"
+
+                        [repos.gone]
+                        branch = "main"
+                        remote = "origin"
+                    "#})
+                    .with_kind(FileKind::Normal)
+            })
+            .with_repo("upstream/vim", |repo| {
+                let repo = repo.stage(".vimrc", "set number")?;
+                repo.commit("initial commit")?;
+                Ok(repo)
+            })?
+            .setup()?;
+        let upstream = git2::Repository::open(harness.as_path().join("upstream/vim.git"))?;
+        let head = upstream.head()?.peel_to_commit()?;
+        upstream.tag_lightweight("v1.0", head.as_object(), false)?;
+        let remote = harness.as_path().join("upstream/vim.git");
+        GitRepo::clone(remote.to_string_lossy(), harness.as_path().join("repos/vim"))?;
+        Ok(harness)
+    }
+
+    fn pin(rice_dir: &FixtureHarness, rev: Option<&str>) -> Result<()> {
+        let mut config = indoc! {r#"
+            [repos.vim]
+            branch = "main"
+            remote = "origin"
+        "#}
+        .to_string();
+        if let Some(rev) = rev {
+            config.push_str(&format!("pin = \"{rev}\"\n"));
+        }
+        fs::write(rice_dir.as_path().join("config/repos.toml"), config)?;
+        Ok(())
+    }
+
+    fn lines(outcomes: &[(String, PullResult)]) -> Vec<String> {
+        outcomes.iter().map(|(name, result)| format!("{name}: {result}")).collect()
+    }
+
+    fn commit_upstream(rice_dir: &FixtureHarness) -> Result<()> {
+        let upstream = GitRepo::open(rice_dir.as_path().join("upstream/vim.git"))?;
+        fs::write(rice_dir.as_path().join("upstream/vim.git/.vimrc"), "set relativenumber")?;
+        upstream.stage([".vimrc"])?;
+        upstream.commit("Use relative numbers")?;
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::one_at_a_time(&[])]
+    #[case::parallel(&["--jobs", "2"])]
+    fn pull_all_pull_unpinned_repos(
+        rice_dir: Result<FixtureHarness>,
+        #[case] args: &[&str],
+    ) -> Result<()> {
+        let rice_dir = rice_dir?;
+        let outcomes = pull_all(
+            &parse_ctx::<PullContext>("pull", args)?,
+            &mock_locator(&rice_dir),
+            &ScriptedPrompter::default(),
+        )?;
+        assert_eq!(lines(&outcomes), ["vim: pulled", "gone: not cloned"]);
+        let vimrc = fs::read_to_string(rice_dir.as_path().join("repos/vim.git/.vimrc"))?;
+        assert_eq!(vimrc, "set number");
+        Ok(())
+    }
+
+    #[rstest]
+    fn pull_all_report_upstream_changes_of_pinned_repos(
+        rice_dir: Result<FixtureHarness>,
+    ) -> Result<()> {
+        let rice_dir = rice_dir?;
+        let locator = mock_locator(&rice_dir);
+        pin(&rice_dir, Some("v1.0"))?;
+        commit_upstream(&rice_dir)?;
+
+        let outcomes = pull_all(
+            &parse_ctx::<PullContext>("pull", &[])?,
+            &locator,
+            &ScriptedPrompter::default(),
+        )?;
+        assert_eq!(lines(&outcomes), ["vim: pinned to 'v1.0', 1 upstream commits not pulled"]);
+        let vimrc = rice_dir.as_path().join("repos/vim.git/.vimrc");
+        assert_eq!(fs::read_to_string(&vimrc)?, "set number");
+        let repo = GitRepo::open(rice_dir.as_path().join("repos/vim.git"))?;
+        assert_eq!(repo.head_branch(), None);
+
+        pin(&rice_dir, None)?;
+        let outcomes = pull_all(
+            &parse_ctx::<PullContext>("pull", &[])?,
+            &locator,
+            &ScriptedPrompter::default(),
+        )?;
+        assert_eq!(lines(&outcomes), ["vim: pulled"]);
+        assert_eq!(fs::read_to_string(&vimrc)?, "set relativenumber");
+        assert_eq!(repo.head_branch().as_deref(), Some("main"));
+        Ok(())
+    }
+
+    #[rstest]
+    fn pull_all_fail_unknown_pin(rice_dir: Result<FixtureHarness>) -> Result<()> {
+        let rice_dir = rice_dir?;
+        pin(&rice_dir, Some("v9.9"))?;
+        fs::write(
+            rice_dir.as_path().join("config/repos.toml"),
+            indoc! {r#"
+                [repos.vim]
+                branch = "main"
+                remote = "origin"
+                pin = "v9.9"
+
+                [repos.gone]
+                branch = "main"
+                remote = "origin"
+            "#},
+        )?;
+        let outcomes = pull_all(
+            &parse_ctx::<PullContext>("pull", &[])?,
+            &mock_locator(&rice_dir),
+            &ScriptedPrompter::default(),
+        )?;
+        assert!(outcomes[0].1.is_failed());
+        assert_eq!(lines(&outcomes)[1], "gone: not cloned");
+        Ok(())
+    }
+
+    fn rename_upstream_branch(rice_dir: &FixtureHarness, to: &str) -> Result<()> {
+        let upstream = git2::Repository::open(rice_dir.as_path().join("upstream/vim.git"))?;
+        upstream.find_branch("main", git2::BranchType::Local)?.rename(to, false)?;
+        upstream.set_head(&format!("refs/heads/{to}"))?;
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::fix(&["--fix"], &[])]
+    #[case::approved(&[], &["y"])]
+    fn pull_all_migrate_renamed_branch(
+        rice_dir: Result<FixtureHarness>,
+        #[case] args: &[&str],
+        #[case] answers: &[&str],
+    ) -> Result<()> {
+        let rice_dir = rice_dir?;
+        let locator = mock_locator(&rice_dir);
+        pin(&rice_dir, None)?;
+        rename_upstream_branch(&rice_dir, "trunk")?;
+        commit_upstream(&rice_dir)?;
+
+        let prompter = ScriptedPrompter::new(answers.iter().copied());
+        let outcomes = pull_all(&parse_ctx::<PullContext>("pull", args)?, &locator, &prompter)?;
+        assert_eq!(lines(&outcomes), ["vim: pulled, migrated branch 'main' to 'trunk'"]);
+        assert_eq!(prompter.questions().len(), answers.len());
+        let vimrc = rice_dir.as_path().join("repos/vim.git/.vimrc");
+        assert_eq!(fs::read_to_string(vimrc)?, "set relativenumber");
+        let repo = GitRepo::open(rice_dir.as_path().join("repos/vim.git"))?;
+        assert_eq!(repo.head_branch().as_deref(), Some("trunk"));
+
+        let config = ConfigFile::load(RepoConfig, &locator)?;
+        assert_eq!(config.get("vim")?.branch, "trunk");
+        let prompter = ScriptedPrompter::default();
+        let outcomes = pull_all(&parse_ctx::<PullContext>("pull", &[])?, &locator, &prompter)?;
+        assert_eq!(lines(&outcomes), ["vim: pulled"]);
+        assert_eq!(prompter.questions(), Vec::<String>::new());
+        Ok(())
+    }
+
+    #[rstest]
+    fn pull_all_migrate_keep_repo_entry_as_written(rice_dir: Result<FixtureHarness>) -> Result<()> {
+        let rice_dir = rice_dir?;
+        let locator = mock_locator(&rice_dir);
+        let config = indoc! {r#"
+            # Editor of choice.
+            [repos.vim]
+            remote   = "origin" # fork
+            branch = "main"  # default of upstream
+            workdir_home = false
+        "#};
+        fs::write(rice_dir.as_path().join("config/repos.toml"), config)?;
+        rename_upstream_branch(&rice_dir, "trunk")?;
+
+        pull_all(
+            &parse_ctx::<PullContext>("pull", &["--fix"])?,
+            &locator,
+            &ScriptedPrompter::default(),
+        )?;
+        let expect = config.replace(r#""main""#, r#""trunk""#);
+        assert_eq!(fs::read_to_string(rice_dir.as_path().join("config/repos.toml"))?, expect);
+        Ok(())
+    }
+
+    #[rstest]
+    fn pull_all_fail_declined_migration(rice_dir: Result<FixtureHarness>) -> Result<()> {
+        let rice_dir = rice_dir?;
+        let locator = mock_locator(&rice_dir);
+        pin(&rice_dir, None)?;
+        rename_upstream_branch(&rice_dir, "trunk")?;
+
+        let prompter = ScriptedPrompter::new(["n"]);
+        let outcomes = pull_all(&parse_ctx::<PullContext>("pull", &[])?, &locator, &prompter)?;
+        assert!(outcomes[0].1.is_failed());
+        assert_eq!(
+            prompter.questions(),
+            ["Branch 'main' of 'vim' is gone from 'origin', migrate to 'trunk'? [y/N]"]
+        );
+        let config = ConfigFile::load(RepoConfig, &locator)?;
+        assert_eq!(config.get("vim")?.branch, "main");
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::pulled(PullOutcome::Pulled, "pulled")]
+    #[case::migrated(
+        PullOutcome::Migrated { from: "master".into(), to: "main".into() },
+        "pulled, migrated branch 'master' to 'main'"
+    )]
+    #[case::pinned(PullOutcome::Pinned { rev: "v1.2".into(), upstream: 0 }, "pinned to 'v1.2'")]
+    #[case::pinned_behind(
+        PullOutcome::Pinned { rev: "v1.2".into(), upstream: 3 },
+        "pinned to 'v1.2', 3 upstream commits not pulled"
+    )]
+    fn pull_outcome_display(#[case] outcome: PullOutcome, #[case] expect: &str) {
+        assert_eq!(outcome.to_string(), expect);
+    }
+}
@@ -0,0 +1,176 @@
+// SPDX-FileCopyrightText: 2024 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+//! Read-only repositories.
+//!
+//! Repositories that should never be changed locally, like a base
+//! configuration shared by a team, set `readonly = true` in the repository
+//! configuration file. They can still be pulled, inspected, and entered, but
+//! [`check_writable`] refuses every command that would change them before it
+//! starts. Commands operating on every repository at once, like `ricer
+//! commit`, skip read-only repositories instead.
+
+use crate::{
+    config::{ConfigFile, ConfigFileError, RepoConfig},
+    context::Context,
+    locate::Locator,
+};
+
+use log::debug;
+
+/// Error types for [`check_writable`].
+#[derive(Debug, thiserror::Error)]
+pub enum ReadonlyError {
+    #[error(
+        "Repository '{name}' is read-only, so '{command}' cannot change it. Set 'readonly = false' \
+         in repository configuration to allow changes"
+    )]
+    Readonly { name: String, command: String },
+
+    #[error("Failed to read repository configuration")]
+    Config { source: ConfigFileError },
+}
+
+impl From<ConfigFileError> for ReadonlyError {
+    fn from(err: ConfigFileError) -> Self {
+        ReadonlyError::Config { source: err }
+    }
+}
+
+/// Refuse command of `ctx` if it would change a read-only repository it
+/// targets.
+///
+/// # Invariants
+///
+/// 1. Commands that leave repositories as-is, e.g., pull, status, or enter,
+///    always pass. See [`Context::is_mutating`].
+/// 1. Target repositories not in the repository configuration file pass, so
+///    commands can report them missing themselves.
+///
+/// # Errors
+///
+/// - Return [`ReadonlyError::Readonly`] naming first read-only repository
+///   command would change.
+/// - Return [`ReadonlyError::Config`] if repository configuration cannot be
+///   read.
+pub fn check_writable(ctx: &Context, locator: &impl Locator) -> Result<(), ReadonlyError> {
+    if !ctx.is_mutating() {
+        return Ok(());
+    }
+
+    let git_repo;
+    let targets = match ctx {
+        Context::Git(git_ctx) => {
+            git_repo = git_ctx.repo.to_string_lossy();
+            vec![git_repo.as_ref()]
+        }
+        _ => ctx.target_repos(),
+    };
+    if targets.is_empty() {
+        return Ok(());
+    }
+
+    let repos = ConfigFile::load(RepoConfig, locator)?.entries()?;
+    for name in targets {
+        if repos.iter().any(|repo| repo.name == name && repo.readonly) {
+            let command = match ctx {
+                Context::Git(git_ctx) => git_ctx
+                    .git_args
+                    .first()
+                    .map(|arg| arg.to_string_lossy().into_owned())
+                    .unwrap_or_default(),
+                _ => ctx.to_string(),
+            };
+            return Err(ReadonlyError::Readonly { name: name.into(), command });
+        }
+    }
+
+    debug!("No read-only repository targeted");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        cli::Cli,
+        locate::MockLocator,
+        testenv::{FileKind, FixtureHarness},
+    };
+
+    use anyhow::Result;
+    use indoc::indoc;
+    use rstest::{fixture, rstest};
+
+    #[fixture]
+    fn config_dir() -> Result<FixtureHarness> {
+        let harness = FixtureHarness::open()?
+            .with_file("repos.toml", |fixture| {
+                fixture
+                    .with_data(indoc! {r#"
+                        [repos.base]
+                        branch = "main"
+                        remote = "origin"
+                        readonly = true
+
+                        [repos.vim]
+                        branch = "main"
+                        remote = "origin"
+                    "#})
+                    .with_kind(FileKind::Normal)
+            })
+            .setup()?;
+        Ok(harness)
+    }
+
+    fn locator(config_dir: &FixtureHarness) -> MockLocator {
+        let mut locator = MockLocator::new();
+        locator.expect_repos_config().return_const(config_dir.as_path().join("repos.toml"));
+        locator
+    }
+
+    #[rstest]
+    #[case::enter(&["ricer", "enter", "base"])]
+    #[case::info(&["ricer", "info", "base"])]
+    #[case::git_status(&["ricer", "base", "status"])]
+    #[case::git_pull(&["ricer", "base", "pull", "origin"])]
+    #[case::rename_dry_run(&["ricer", "rename", "base", "shared", "--dry-run"])]
+    #[case::shared_dry_run(&["ricer", "--dry-run", "delete", "base"])]
+    #[case::writable(&["ricer", "delete", "vim"])]
+    #[case::missing(&["ricer", "delete", "dwm"])]
+    #[case::batch(&["ricer", "commit", "-m", "all: update"])]
+    fn check_writable_pass(
+        config_dir: Result<FixtureHarness>,
+        #[case] args: &[&str],
+    ) -> Result<()> {
+        let config_dir = config_dir?;
+        let ctx = Context::from(Cli::parse_args(args)?);
+        check_writable(&ctx, &locator(&config_dir))?;
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::delete(&["ricer", "delete", "base"], "delete")]
+    #[case::rename(&["ricer", "rename", "base", "shared"], "rename")]
+    #[case::add(&["ricer", "add", "base", "file"], "add")]
+    #[case::sed(&["ricer", "sed", "foo", "bar", "-r", "vim", "-r", "base"], "sed")]
+    #[case::git_commit(&["ricer", "base", "commit", "-m", "test"], "commit")]
+    #[case::git_push(&["ricer", "base", "push"], "push")]
+    fn check_writable_return_err_readonly(
+        config_dir: Result<FixtureHarness>,
+        #[case] args: &[&str],
+        #[case] expect: &str,
+    ) -> Result<()> {
+        let config_dir = config_dir?;
+        let ctx = Context::from(Cli::parse_args(args)?);
+        let result = check_writable(&ctx, &locator(&config_dir));
+        match result.unwrap_err() {
+            ReadonlyError::Readonly { name, command } => {
+                assert_eq!(name, "base");
+                assert_eq!(command, expect);
+            }
+            err => panic!("unexpected error: {err}"),
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,689 @@
+// SPDX-FileCopyrightText: 2024 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+//! Assisted relocation of Ricer's directories.
+//!
+//! Keeping a rice in a folder synced between machines, e.g., inside of a
+//! cloud drive, means moving Ricer's directories there. Through `ricer
+//! move-config-dir <DIR>`, the configuration directory moves into
+//! `<DIR>/config`, and optionally the data directory into `<DIR>/data`, and
+//! the state directory into `<DIR>/state`. Named profiles move along, since
+//! they live inside of these directories.
+//!
+//! Moved directories are recorded in the _location file_ of the standard
+//! configuration directory, i.e., `$XDG_CONFIG_HOME/ricer/location.toml`:
+//!
+//! ```toml
+//! config_dir = "/home/awkless/Sync/ricer/config"
+//! data_dir = "/home/awkless/Sync/ricer/data"
+//! ```
+//!
+//! Every later invocation reads the location file first, while
+//! `--config-dir` and friends still take precedence over it.
+//!
+//! Like [`crate::remotes`], relocation is done in two steps. First,
+//! [`plan_relocation`] determines every move without changing anything, so
+//! the user can review them. Then, [`RelocationPlan::apply`] performs them,
+//! updates absolute paths stored in configuration files, records the new
+//! location, and verifies that everything still resolves.
+
+use crate::{
+    config::{
+        encode_toml, entries_or_empty, read_plaintext, read_toml_if_exists, write_atomic,
+        ConfigFileError, RepoConfig, Toml,
+    },
+    context::MoveConfigDirContext,
+    locate::{DefaultLocator, DirLayout, Locator, OverrideLayout},
+};
+
+use log::{debug, info, warn};
+use mkdirp::mkdirp;
+use std::{
+    env, fmt,
+    fs::{self, File},
+    io::Error as IoError,
+    path::{Path, PathBuf},
+};
+use toml_edit::{value, DocumentMut};
+
+/// Name of location file in the standard configuration directory.
+pub const LOCATION_FILE: &str = "location.toml";
+
+/// Error types for [`plan_relocation`] and [`RelocationPlan`].
+#[derive(Debug, thiserror::Error)]
+pub enum RelocateError {
+    #[error("Cannot move '{from}' into '{to}', which is inside of it")]
+    IntoItself { from: PathBuf, to: PathBuf },
+
+    #[error("Cannot move into '{path}', because it is not empty")]
+    NotEmpty { path: PathBuf },
+
+    #[error("Failed to determine current working directory")]
+    Cwd { source: IoError },
+
+    #[error("Failed to move '{path}'")]
+    Move { source: IoError, path: PathBuf },
+
+    #[error("Failed to parse location file '{path}'")]
+    Location { source: toml_edit::TomlError, path: PathBuf },
+
+    #[error("Failed to access configuration file")]
+    Config { source: ConfigFileError },
+}
+
+impl From<ConfigFileError> for RelocateError {
+    fn from(err: ConfigFileError) -> Self {
+        RelocateError::Config { source: err }
+    }
+}
+
+/// Directories moved away from the standard directory layout.
+///
+/// Directories that are `None` stay where the standard layout puts them.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Location {
+    pub config_dir: Option<PathBuf>,
+    pub data_dir: Option<PathBuf>,
+    pub state_dir: Option<PathBuf>,
+}
+
+impl Location {
+    /// Load location file of standard configuration directory `dir`.
+    ///
+    /// Missing location file moves nothing. Location files locked by older
+    /// versions of Ricer are decrypted while they are read.
+    ///
+    /// # Errors
+    ///
+    /// - Return [`RelocateError::Config`] if location file cannot be read.
+    /// - Return [`RelocateError::Location`] if location file cannot be parsed.
+    pub fn load(dir: &Path) -> Result<Self, RelocateError> {
+        let path = dir.join(LOCATION_FILE);
+        let file = match File::open(&path) {
+            Ok(file) => file,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(err) => return Err(ConfigFileError::FileRead { source: err, path }.into()),
+        };
+        let (data, _) = read_plaintext(file, &path)?;
+
+        let doc: DocumentMut =
+            data.parse().map_err(|err| RelocateError::Location { source: err, path })?;
+        let dir = |key: &str| doc.get(key).and_then(|item| item.as_str()).map(PathBuf::from);
+        Ok(Self {
+            config_dir: dir("config_dir"),
+            data_dir: dir("data_dir"),
+            state_dir: dir("state_dir"),
+        })
+    }
+
+    /// Save location file into standard configuration directory `dir`.
+    ///
+    /// Location file is removed if nothing is moved anymore.
+    ///
+    /// # Errors
+    ///
+    /// - Return [`RelocateError::Config`] if location file cannot be written.
+    pub fn save(&self, dir: &Path) -> Result<(), RelocateError> {
+        let path = dir.join(LOCATION_FILE);
+        if *self == Self::default() {
+            return match fs::remove_file(&path) {
+                Err(err) if err.kind() != std::io::ErrorKind::NotFound => {
+                    Err(ConfigFileError::FileWrite { source: err, path }.into())
+                }
+                _ => Ok(()),
+            };
+        }
+
+        let mut doc = DocumentMut::new();
+        let dirs = [
+            ("config_dir", &self.config_dir),
+            ("data_dir", &self.data_dir),
+            ("state_dir", &self.state_dir),
+        ];
+        for (key, dir) in dirs {
+            if let Some(dir) = dir {
+                doc[key] = value(dir.to_string_lossy().as_ref());
+            }
+        }
+
+        mkdirp(dir).map_err(|err| ConfigFileError::MakeDirP { source: err, path: dir.into() })?;
+        write_atomic(&path, doc.to_string().as_bytes())
+            .map_err(|err| ConfigFileError::FileWrite { source: err, path }.into())
+    }
+
+    /// Replace directories of `layout` with moved directories.
+    pub fn apply(&self, layout: OverrideLayout) -> OverrideLayout {
+        layout
+            .with_config_dir(self.config_dir.clone())
+            .with_repo_dir(self.data_dir.clone())
+            .with_state_dir(self.state_dir.clone())
+    }
+}
+
+/// One directory to move.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DirMove {
+    /// What the directory holds, e.g., "configuration".
+    pub kind: &'static str,
+
+    pub from: PathBuf,
+    pub to: PathBuf,
+}
+
+impl fmt::Display for DirMove {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} directory: '{}' -> '{}'", self.kind, self.from.display(), self.to.display())
+    }
+}
+
+/// Every move of a relocation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RelocationPlan {
+    pub moves: Vec<DirMove>,
+    standard_dir: PathBuf,
+    location: Location,
+    clones: Vec<String>,
+}
+
+/// Determine every move of relocation `ctx` of `base` layout, whose moved
+/// directories are recorded in location file of `standard` layout.
+///
+/// Relative target directories are relative to the current working
+/// directory. Directories already at their target are not moved.
+///
+/// # Errors
+///
+/// - Return [`RelocateError::IntoItself`] if a directory would move into
+///   itself.
+/// - Return [`RelocateError::NotEmpty`] if a target directory is not empty.
+/// - Return [`RelocateError::Cwd`] if current working directory is needed,
+///   but cannot be determined.
+/// - Return [`RelocateError::Config`] if location file or repository
+///   configuration cannot be read.
+pub fn plan_relocation(
+    ctx: &MoveConfigDirContext,
+    base: &impl DirLayout,
+    standard: &impl DirLayout,
+) -> Result<RelocationPlan, RelocateError> {
+    let to = match ctx.to.is_absolute() {
+        true => ctx.to.clone(),
+        false => {
+            env::current_dir().map_err(|err| RelocateError::Cwd { source: err })?.join(&ctx.to)
+        }
+    };
+
+    let standard_dir = standard.config_dir().to_path_buf();
+    let mut location = Location::load(&standard_dir)?;
+    let mut moves = Vec::new();
+    let wanted = [
+        ("configuration", true, base.config_dir(), standard.config_dir(), "config"),
+        ("data", ctx.data, base.repo_dir(), standard.repo_dir(), "data"),
+        ("state", ctx.state, base.state_dir(), standard.state_dir(), "state"),
+    ];
+    for (kind, wanted, from, standard, subdir) in wanted {
+        let target = to.join(subdir);
+        if !wanted || target == from {
+            continue;
+        }
+
+        if target.starts_with(from) {
+            return Err(RelocateError::IntoItself { from: from.into(), to: target });
+        }
+
+        if !is_empty_dir(&target, &standard_dir.join(LOCATION_FILE)) {
+            return Err(RelocateError::NotEmpty { path: target });
+        }
+
+        // INVARIANT: directories moved back into the standard layout are not recorded.
+        let moved = (target != standard).then(|| target.clone());
+        match kind {
+            "configuration" => location.config_dir = moved,
+            "data" => location.data_dir = moved,
+            _ => location.state_dir = moved,
+        }
+        moves.push(DirMove { kind, from: from.into(), to: target });
+    }
+
+    let locator = DefaultLocator::locate(base);
+    let clones = match read_toml_if_exists(locator.repos_config())? {
+        Some(doc) => entries_or_empty(&RepoConfig, &doc)
+            .map_err(|err| ConfigFileError::Toml {
+                source: err,
+                path: locator.repos_config().into(),
+            })?
+            .into_iter()
+            .filter(|repo| repo.gitdir(locator.repos_dir()).exists())
+            .map(|repo| repo.name)
+            .collect(),
+        None => Vec::new(),
+    };
+
+    Ok(RelocationPlan { moves, standard_dir, location, clones })
+}
+
+impl RelocationPlan {
+    pub fn is_empty(&self) -> bool {
+        self.moves.is_empty()
+    }
+
+    /// Perform every move, update absolute paths stored in configuration
+    /// files, record the new location, and verify that configuration files
+    /// and cloned repositories still resolve.
+    ///
+    /// Directories are moved entry by entry, falling back to copying entries
+    /// that cannot be renamed, e.g., across file systems.
+    ///
+    /// # Invariants
+    ///
+    /// 1. If any entry fails to move, every entry moved so far is moved back,
+    ///    and the location file is left as it was.
+    /// 1. The location file stays in the standard configuration directory,
+    ///    even if that directory moves.
+    ///
+    /// # Errors
+    ///
+    /// - Return [`RelocateError::Move`] if a directory cannot be moved.
+    /// - Return [`RelocateError::Config`] if a configuration file, or the
+    ///   location file cannot be updated.
+    pub fn apply(&self) -> Result<RelocationReport, RelocateError> {
+        let location_file = self.standard_dir.join(LOCATION_FILE);
+        let mut moved = Vec::new();
+        let result = self.moves.iter().try_for_each(|dir_move| {
+            info!("Move {dir_move}");
+            move_entries(&dir_move.from, &dir_move.to, &location_file, &mut moved)
+        });
+
+        if let Err(err) = result {
+            // INVARIANT: never leave directories half moved.
+            for (from, to) in moved.iter().rev() {
+                if let Err(err) = move_entry(to, from) {
+                    warn!("Failed to move '{}' back to '{}': {err}", to.display(), from.display());
+                }
+            }
+            return Err(err);
+        }
+
+        for dir_move in &self.moves {
+            // INVARIANT: standard configuration directory keeps the location file.
+            if dir_move.from != self.standard_dir {
+                let _ = fs::remove_dir(&dir_move.from);
+            }
+        }
+
+        let mut updated = Vec::new();
+        let config_dir = self.moves.iter().find(|dir_move| dir_move.kind == "configuration");
+        let config_dir = config_dir.map(|dir_move| dir_move.to.clone());
+        let config_dir = config_dir.or_else(|| self.location.config_dir.clone());
+        let config_dir = config_dir.unwrap_or_else(|| self.standard_dir.clone());
+        for path in toml_files(&config_dir) {
+            if rewrite_paths(&path, &self.moves)? {
+                updated.push(path);
+            }
+        }
+
+        self.location.save(&self.standard_dir)?;
+        Ok(RelocationReport {
+            moves: self.moves.clone(),
+            updated,
+            location_file,
+            problems: Vec::new(),
+        })
+    }
+
+    /// Verify that configuration files and every repository cloned before the
+    /// move still resolve through `locator` of the new location.
+    pub fn verify(&self, report: &mut RelocationReport, locator: &impl Locator) {
+        let configs = [locator.repos_config(), locator.hooks_config(), locator.ricer_config()];
+        let mut repos_doc = None;
+        for path in configs {
+            match read_toml_if_exists(path) {
+                Ok(doc) if path == locator.repos_config() => repos_doc = doc,
+                Ok(_) => (),
+                Err(err) => report.problems.push(format!("{err}")),
+            }
+        }
+
+        let repos = repos_doc
+            .map(|doc| entries_or_empty(&RepoConfig, &doc).unwrap_or_default())
+            .unwrap_or_default();
+        for name in &self.clones {
+            let gitdir = repos
+                .iter()
+                .find(|repo| &repo.name == name)
+                .map(|repo| repo.gitdir(locator.repos_dir()));
+            match gitdir {
+                Some(gitdir) if gitdir.exists() => (),
+                Some(gitdir) => report.problems.push(format!(
+                    "Repository '{name}' no longer resolves to '{}'",
+                    gitdir.display()
+                )),
+                None => {
+                    report.problems.push(format!("Repository '{name}' is no longer registered"))
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Display for RelocationPlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for dir_move in &self.moves {
+            writeln!(f, "{dir_move}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Outcome of [`RelocationPlan::apply`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RelocationReport {
+    pub moves: Vec<DirMove>,
+
+    /// Configuration files whose absolute paths were updated.
+    pub updated: Vec<PathBuf>,
+
+    pub location_file: PathBuf,
+
+    /// What no longer resolves after the move.
+    pub problems: Vec<String>,
+}
+
+impl RelocationReport {
+    pub fn has_problems(&self) -> bool {
+        !self.problems.is_empty()
+    }
+}
+
+impl fmt::Display for RelocationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for dir_move in &self.moves {
+            writeln!(f, "Moved {dir_move}")?;
+        }
+        for path in &self.updated {
+            writeln!(f, "Updated paths in '{}'", path.display())?;
+        }
+        writeln!(f, "Recorded location in '{}'", self.location_file.display())?;
+        match self.problems.is_empty() {
+            true => writeln!(f, "Every configuration file and repository still resolves"),
+            false => self.problems.iter().try_for_each(|problem| writeln!(f, "{problem}")),
+        }
+    }
+}
+
+/// Check if `dir` is missing, or empty except for `skip`.
+fn is_empty_dir(dir: &Path, skip: &Path) -> bool {
+    match fs::read_dir(dir) {
+        Ok(mut entries) => entries.all(|entry| entry.is_ok_and(|entry| entry.path() == skip)),
+        Err(_) => !dir.exists(),
+    }
+}
+
+/// Move every entry of `from` into `to` except for `skip`, tracking moved
+/// entries in `moved`.
+fn move_entries(
+    from: &Path,
+    to: &Path,
+    skip: &Path,
+    moved: &mut Vec<(PathBuf, PathBuf)>,
+) -> Result<(), RelocateError> {
+    let move_err = |err, path: &Path| RelocateError::Move { source: err, path: path.into() };
+    mkdirp(to).map_err(|err| move_err(err, to))?;
+    let entries = match fs::read_dir(from) {
+        Ok(entries) => entries,
+        Err(_) if !from.exists() => return Ok(()),
+        Err(err) => return Err(move_err(err, from)),
+    };
+
+    for entry in entries {
+        let entry = entry.map_err(|err| move_err(err, from))?;
+        let (source, target) = (entry.path(), to.join(entry.file_name()));
+        if source == skip {
+            continue;
+        }
+
+        debug!("Move '{}' to '{}'", source.display(), target.display());
+        move_entry(&source, &target).map_err(|err| move_err(err, &source))?;
+        moved.push((source, target));
+    }
+
+    Ok(())
+}
+
+/// Rename `from` to `to`, or copy and remove it if it cannot be renamed.
+fn move_entry(from: &Path, to: &Path) -> Result<(), IoError> {
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+
+    copy_entry(from, to)?;
+    match from.is_dir() {
+        true => fs::remove_dir_all(from),
+        false => fs::remove_file(from),
+    }
+}
+
+fn copy_entry(from: &Path, to: &Path) -> Result<(), IoError> {
+    if !from.is_dir() {
+        return fs::copy(from, to).map(|_| ());
+    }
+
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        copy_entry(&entry.path(), &to.join(entry.file_name()))?;
+    }
+    Ok(())
+}
+
+/// List TOML files inside of `dir`, recursively.
+fn toml_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let Ok(entries) = fs::read_dir(dir) else {
+        return files;
+    };
+
+    for entry in entries.filter_map(Result::ok) {
+        let path = entry.path();
+        if path.is_dir() {
+            files.extend(toml_files(&path));
+        } else if path.extension().is_some_and(|ext| ext == "toml") {
+            files.push(path);
+        }
+    }
+
+    files.sort();
+    files
+}
+
+/// Point absolute paths of configuration file at `path` into moved
+/// directories of `moves`.
+///
+/// Will return whether any path was updated. Files that are not valid TOML,
+/// e.g., templates, are left alone.
+fn rewrite_paths(path: &Path, moves: &[DirMove]) -> Result<bool, RelocateError> {
+    let file = File::open(path)
+        .map_err(|err| ConfigFileError::FileOpen { source: err, path: path.into() })?;
+    let (data, encrypted) = read_plaintext(file, path)?;
+    let Ok(mut doc) = data.parse::<Toml>() else {
+        debug!("Leave '{}' alone, because it is not valid TOML", path.display());
+        return Ok(false);
+    };
+
+    let count = doc.rewrite_strings(|value| {
+        moves.iter().find_map(|dir_move| {
+            let rest = Path::new(value).strip_prefix(&dir_move.from).ok()?;
+            Some(dir_move.to.join(rest).to_string_lossy().into_owned())
+        })
+    });
+    if count == 0 {
+        return Ok(false);
+    }
+
+    // INVARIANT: locked configuration files stay locked.
+    let data = encode_toml(&doc, encrypted, path)?;
+    write_atomic(path, &data)
+        .map_err(|err| ConfigFileError::FileWrite { source: err, path: path.into() })?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        locate::MockDirLayout,
+        testenv::{parse_ctx, FileKind, FixtureHarness},
+    };
+
+    use anyhow::Result;
+    use indoc::{formatdoc, indoc};
+    use pretty_assertions::assert_eq;
+    use rstest::{fixture, rstest};
+
+    #[fixture]
+    fn rice_dir() -> Result<FixtureHarness> {
+        let harness = FixtureHarness::open()?
+            .with_file("xdg/config/repos.toml", |fixture| {
+                fixture
+                    .with_data(indoc! {r#"
+                        [repos.vim]
+                        branch = "main"
+                        remote = "origin"
+                        workdir_home = true
+                    "#})
+                    .with_kind(FileKind::Normal)
+            })
+            .with_file("xdg/config/hooks/hook.sh", |fixture| {
+                fixture.with_data("#!/bin/sh\nexit 0\n").with_kind(FileKind::Script)
+            })
+            .with_repo("xdg/data/ricer/vim", |repo| repo.stage("vimrc", "config for vim!"))?
+            .setup()?;
+        Ok(harness)
+    }
+
+    fn layout(rice_dir: &FixtureHarness) -> OverrideLayout {
+        let mut layout = MockDirLayout::new();
+        layout.expect_config_dir().return_const(rice_dir.as_path().join("xdg/config"));
+        layout.expect_repo_dir().return_const(rice_dir.as_path().join("xdg/data"));
+        layout.expect_state_dir().return_const(rice_dir.as_path().join("xdg/state"));
+        OverrideLayout::new(layout)
+    }
+
+    #[rstest]
+    fn relocation_plan_apply_move_dirs_and_record_location(
+        rice_dir: Result<FixtureHarness>,
+    ) -> Result<()> {
+        let rice_dir = rice_dir?;
+        let standard = layout(&rice_dir);
+        let sync = rice_dir.as_path().join("sync");
+        let ctx = parse_ctx::<MoveConfigDirContext>(
+            "move-config-dir",
+            &[sync.to_str().unwrap(), "--data"],
+        )?;
+
+        let plan = plan_relocation(&ctx, &standard, &standard)?;
+        assert_eq!(plan.moves.len(), 2);
+        let mut report = plan.apply()?;
+
+        let location = Location::load(standard.config_dir())?;
+        assert_eq!(location.config_dir, Some(sync.join("config")));
+        assert_eq!(location.data_dir, Some(sync.join("data")));
+        assert_eq!(location.state_dir, None);
+        assert!(sync.join("config/hooks/hook.sh").exists());
+        assert!(!standard.repo_dir().exists());
+
+        let locator = DefaultLocator::locate(location.apply(standard.clone()));
+        plan.verify(&mut report, &locator);
+        assert!(!report.has_problems(), "{report}");
+        assert!(locator.repos_dir().join("vim.git").exists());
+
+        Ok(())
+    }
+
+    #[rstest]
+    fn relocation_plan_apply_update_absolute_paths(rice_dir: Result<FixtureHarness>) -> Result<()> {
+        let rice_dir = rice_dir?;
+        let standard = layout(&rice_dir);
+        let old_gitdir = standard.repo_dir().join("ricer/vim.git");
+        let repos_config = standard.config_dir().join("repos.toml");
+        fs::write(
+            &repos_config,
+            formatdoc! {r#"
+                [repos.vim]
+                branch = "main"
+                remote = "origin"
+                workdir_home = true
+                path = "{}"
+            "#, old_gitdir.display()},
+        )?;
+        let sync = rice_dir.as_path().join("sync");
+        let ctx = parse_ctx::<MoveConfigDirContext>(
+            "move-config-dir",
+            &[sync.to_str().unwrap(), "--data"],
+        )?;
+
+        let report = plan_relocation(&ctx, &standard, &standard)?.apply()?;
+        let repos_config = sync.join("config/repos.toml");
+        assert_eq!(report.updated, vec![repos_config.clone()]);
+        let data = fs::read_to_string(repos_config)?;
+        let expect = sync.join("data/ricer/vim.git");
+        assert!(data.contains(&format!("path = \"{}\"", expect.display())), "{data}");
+
+        Ok(())
+    }
+
+    #[rstest]
+    fn relocation_plan_verify_report_missing_repos(rice_dir: Result<FixtureHarness>) -> Result<()> {
+        let rice_dir = rice_dir?;
+        let standard = layout(&rice_dir);
+        let sync = rice_dir.as_path().join("sync");
+        let ctx = parse_ctx::<MoveConfigDirContext>("move-config-dir", &[sync.to_str().unwrap()])?;
+
+        let plan = plan_relocation(&ctx, &standard, &standard)?;
+        let mut report = plan.apply()?;
+        fs::remove_dir_all(standard.repo_dir().join("ricer/vim.git"))?;
+        let location = Location::load(standard.config_dir())?;
+        plan.verify(&mut report, &DefaultLocator::locate(location.apply(standard.clone())));
+        assert!(report.has_problems());
+
+        Ok(())
+    }
+
+    #[rstest]
+    fn plan_relocation_return_err_not_empty(rice_dir: Result<FixtureHarness>) -> Result<()> {
+        let rice_dir = rice_dir?;
+        let standard = layout(&rice_dir);
+        let sync = rice_dir.as_path().join("sync");
+        fs::create_dir_all(sync.join("config"))?;
+        fs::write(sync.join("config/repos.toml"), "")?;
+        let ctx = parse_ctx::<MoveConfigDirContext>("move-config-dir", &[sync.to_str().unwrap()])?;
+
+        let result = plan_relocation(&ctx, &standard, &standard);
+        assert!(matches!(result, Err(RelocateError::NotEmpty { .. })));
+        Ok(())
+    }
+
+    #[rstest]
+    fn plan_relocation_return_err_into_itself(rice_dir: Result<FixtureHarness>) -> Result<()> {
+        let rice_dir = rice_dir?;
+        let standard = layout(&rice_dir);
+        let inside = standard.config_dir().join("sync");
+        let ctx =
+            parse_ctx::<MoveConfigDirContext>("move-config-dir", &[inside.to_str().unwrap()])?;
+
+        let result = plan_relocation(&ctx, &standard, &standard);
+        assert!(matches!(result, Err(RelocateError::IntoItself { .. })));
+        Ok(())
+    }
+
+    #[rstest]
+    fn location_save_remove_file_once_nothing_moves() -> Result<()> {
+        let dir = FixtureHarness::open()?;
+        let location = Location { config_dir: Some("/sync/config".into()), ..Default::default() };
+        location.save(dir.as_path())?;
+        assert_eq!(Location::load(dir.as_path())?, location);
+
+        Location::default().save(dir.as_path())?;
+        assert!(!dir.as_path().join(LOCATION_FILE).exists());
+        Ok(())
+    }
+}
@@ -0,0 +1,370 @@
+// SPDX-FileCopyrightText: 2024 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+//! Batch remote rewriting.
+//!
+//! Moving a rice from one forge to another means pointing the remote of every
+//! repository somewhere else. Through `ricer remotes rewrite --from <OLD> --to
+//! <NEW>`, every occurrence of `OLD` in the remote URLs of all, or only
+//! selected, repositories is replaced with `NEW` at once. Both the remotes of
+//! each Git repository and the `bootstrap.clone` URLs of the repository
+//! configuration file are rewritten.
+//!
+//! Like [`crate::sed`], rewriting is done in two steps. First,
+//! [`plan_rewrite`] determines every rewrite without changing anything, so the
+//! user can review them. Then, [`RewritePlan::apply`] performs them.
+
+use crate::{
+    config::{
+        normalize_remote_url, ConfigFile, ConfigFileError, RemoteUrlError, RepoConfig, RepoSettings,
+    },
+    locate::Locator,
+    util::{RepoSelector, SelectError},
+    vcs::{GitRepo, GitRepoError},
+};
+
+use log::{debug, info};
+use std::{fmt, path::PathBuf};
+
+/// Error types for [`plan_rewrite`] and [`RewritePlan`].
+#[derive(Debug, thiserror::Error)]
+pub enum RemotesError {
+    #[error("Text to rewrite must not be empty")]
+    EmptyPattern,
+
+    #[error("Failed to select repositories")]
+    Select { source: SelectError },
+
+    #[error("Rewritten remote '{url}' of repository '{name}' is invalid")]
+    BadUrl { source: RemoteUrlError, name: String, url: String },
+
+    #[error("Failed to operate on repository '{name}'")]
+    Repo { source: GitRepoError, name: String },
+
+    #[error("Failed to access repository configuration")]
+    Config { source: ConfigFileError },
+}
+
+impl From<ConfigFileError> for RemotesError {
+    fn from(err: ConfigFileError) -> Self {
+        RemotesError::Config { source: err }
+    }
+}
+
+impl From<SelectError> for RemotesError {
+    fn from(err: SelectError) -> Self {
+        RemotesError::Select { source: err }
+    }
+}
+
+/// Where a rewritten remote URL is stored.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RemoteTarget {
+    /// Remote of Git repository.
+    Git { remote: String, gitdir: PathBuf },
+
+    /// Bootstrap clone URL of repository configuration file.
+    Bootstrap,
+}
+
+/// Rewrite of one remote URL.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemoteRewrite {
+    /// Name of repository owning remote.
+    pub repo: String,
+
+    pub target: RemoteTarget,
+    pub old: String,
+    pub new: String,
+}
+
+impl fmt::Display for RemoteRewrite {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.target {
+            RemoteTarget::Git { remote, .. } => write!(f, "{} remote '{remote}'", self.repo)?,
+            RemoteTarget::Bootstrap => write!(f, "{} bootstrap.clone", self.repo)?,
+        }
+        write!(f, ": {} -> {}", self.old, self.new)
+    }
+}
+
+/// Every rewrite of a batch remote rewrite, in order of repository.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RewritePlan {
+    pub rewrites: Vec<RemoteRewrite>,
+}
+
+impl RewritePlan {
+    pub fn is_empty(&self) -> bool {
+        self.rewrites.is_empty()
+    }
+
+    /// Names of repositories with at least one rewrite.
+    pub fn repos(&self) -> Vec<&str> {
+        let mut repos = self.rewrites.iter().map(|rw| rw.repo.as_str()).collect::<Vec<_>>();
+        repos.dedup();
+        repos
+    }
+
+    /// Perform every rewrite.
+    ///
+    /// # Invariants
+    ///
+    /// Repository configuration file is only written once every Git remote
+    /// has been rewritten.
+    ///
+    /// # Errors
+    ///
+    /// - Return [`RemotesError::Repo`] if remote of Git repository cannot be
+    ///   rewritten.
+    /// - Return [`RemotesError::Config`] if repository configuration cannot be
+    ///   updated.
+    pub fn apply(&self, locator: &impl Locator) -> Result<(), RemotesError> {
+        let mut config = None;
+        for rewrite in &self.rewrites {
+            info!("Rewrite {rewrite}");
+            match &rewrite.target {
+                RemoteTarget::Git { remote, gitdir } => {
+                    let repo_err =
+                        |err| RemotesError::Repo { source: err, name: rewrite.repo.clone() };
+                    let repo = GitRepo::open(gitdir).map_err(repo_err)?;
+                    repo.set_remote_url(remote, &rewrite.new).map_err(repo_err)?;
+                }
+                RemoteTarget::Bootstrap => {
+                    let config = match &mut config {
+                        Some(config) => config,
+                        None => config.insert(ConfigFile::load(RepoConfig, locator)?),
+                    };
+                    let mut settings = config.get(&rewrite.repo)?;
+                    if let Some(bootstrap) = &mut settings.bootstrap {
+                        bootstrap.clone = Some(rewrite.new.clone());
+                    }
+                    config.upsert(settings)?;
+                }
+            }
+        }
+
+        if let Some(mut config) = config {
+            config.save()?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for RewritePlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for rewrite in &self.rewrites {
+            writeln!(f, "{rewrite}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Determine rewrites of `from` to `to` in remote URLs of `repos`, or of every
+/// repository if none are given.
+///
+/// # Invariants
+///
+/// 1. Nothing is changed until [`RewritePlan::apply`] is called.
+/// 1. Repositories that are not cloned only get their bootstrap clone URL
+///    rewritten.
+///
+/// # Errors
+///
+/// - Return [`RemotesError::EmptyPattern`] if `from` is empty.
+/// - Return [`RemotesError::Select`] if `selector` selects no registered
+///   repository.
+/// - Return [`RemotesError::BadUrl`] if a rewritten URL is invalid.
+/// - Return [`RemotesError::Repo`] if remotes of a repository cannot be read.
+/// - Return [`RemotesError::Config`] if repository configuration cannot be
+///   read.
+pub fn plan_rewrite(
+    from: &str,
+    to: &str,
+    selector: &RepoSelector,
+    locator: &impl Locator,
+) -> Result<RewritePlan, RemotesError> {
+    if from.is_empty() {
+        return Err(RemotesError::EmptyPattern);
+    }
+
+    let rewrite = |repo: &str, target: RemoteTarget, old: &str| -> Result<_, RemotesError> {
+        if !old.contains(from) {
+            return Ok(None);
+        }
+        let url = old.replace(from, to);
+        let new = normalize_remote_url(&url).map_err(|err| RemotesError::BadUrl {
+            source: err,
+            name: repo.into(),
+            url,
+        })?;
+        Ok(Some(RemoteRewrite { repo: repo.into(), target, old: old.into(), new }))
+    };
+
+    let mut plan = RewritePlan::default();
+    for settings in selected_repos(selector, locator)? {
+        let gitdir = settings.gitdir(locator.repos_dir());
+        match gitdir.exists() {
+            true => {
+                let repo_err =
+                    |err| RemotesError::Repo { source: err, name: settings.name.clone() };
+                let repo = GitRepo::open(&gitdir).map_err(repo_err)?;
+                for remote in repo.remotes().map_err(repo_err)? {
+                    let old = match repo.remote_url(&remote) {
+                        Some(old) => old,
+                        None => continue,
+                    };
+                    let target = RemoteTarget::Git { remote, gitdir: gitdir.clone() };
+                    plan.rewrites.extend(rewrite(&settings.name, target, &old)?);
+                }
+            }
+            false => debug!("Skip remotes of '{}', because it is not cloned", settings.name),
+        }
+
+        if let Some(old) = settings.bootstrap.as_ref().and_then(|b| b.clone.as_deref()) {
+            plan.rewrites.extend(rewrite(&settings.name, RemoteTarget::Bootstrap, old)?);
+        }
+    }
+
+    Ok(plan)
+}
+
+fn selected_repos(
+    selector: &RepoSelector,
+    locator: &impl Locator,
+) -> Result<Vec<RepoSettings>, RemotesError> {
+    let config = ConfigFile::load(RepoConfig, locator)?;
+    let selector = selector.clone().groups(config.groups()?);
+    let (readonly, writable) = selector
+        .select(config.entries()?)?
+        .into_iter()
+        .partition::<Vec<_>, _>(|settings| settings.readonly);
+    for settings in readonly {
+        debug!("Skip '{}', because it is read-only", settings.name);
+    }
+    Ok(writable)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testenv::{mock_locator, FileKind, FixtureHarness};
+
+    use anyhow::Result;
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+    use rstest::{fixture, rstest};
+
+    #[fixture]
+    fn rice_dir() -> Result<FixtureHarness> {
+        let harness = FixtureHarness::open()?
+            .with_file("config/repos.toml", |fixture| {
+                fixture
+                    .with_data(indoc! {r#"
+                        [repos.dwm]
+                        branch = "main"
+                        remote = "origin"
+                        workdir_home = false
+
+                        [repos.dwm.bootstrap]
+                        clone = "https://github.com/awkless/dwm.git"
+
+                        [repos.vim]
+                        branch = "main"
+                        remote = "origin"
+                        workdir_home = false
+
+                        [repos.vim.bootstrap]
+                        clone = "git@github.com:awkless/vim.git"
+
+                        [repos.st]
+                        branch = "main"
+                        remote = "origin"
+                        workdir_home = false
+
+                        [repos.st.bootstrap]
+                        clone = "https://git.suckless.org/st"
+                    "#})
+                    .with_kind(FileKind::Normal)
+            })
+            .with_repo("repos/dwm", |repo| {
+                let repo = repo.stage("config.h", "#define MODKEY Mod4Mask\n")?;
+                repo.commit("initial commit")?;
+                Ok(repo)
+            })?
+            .setup()?;
+        let dwm = GitRepo::open(harness.as_path().join("repos/dwm.git"))?;
+        dwm.syscall(["remote", "add", "origin", "https://github.com/awkless/dwm.git"])?;
+        dwm.syscall(["remote", "add", "upstream", "https://git.suckless.org/dwm"])?;
+        Ok(harness)
+    }
+
+    #[rstest]
+    fn plan_rewrite_preview_without_changing(rice_dir: Result<FixtureHarness>) -> Result<()> {
+        let rice_dir = rice_dir?;
+        let locator = mock_locator(&rice_dir);
+        let plan = plan_rewrite("github.com", "codeberg.org", &RepoSelector::default(), &locator)?;
+
+        assert_eq!(plan.repos(), vec!["dwm", "vim"]);
+        assert_eq!(
+            plan.to_string(),
+            indoc! {"
+                dwm remote 'origin': https://github.com/awkless/dwm.git -> https://codeberg.org/awkless/dwm.git
+                dwm bootstrap.clone: https://github.com/awkless/dwm.git -> https://codeberg.org/awkless/dwm.git
+                vim bootstrap.clone: git@github.com:awkless/vim.git -> git@codeberg.org:awkless/vim.git
+            "}
+        );
+        let dwm = GitRepo::open(rice_dir.as_path().join("repos/dwm.git"))?;
+        assert_eq!(dwm.remote_url("origin").as_deref(), Some("https://github.com/awkless/dwm.git"));
+        Ok(())
+    }
+
+    #[rstest]
+    fn rewrite_plan_apply_update_git_and_config(rice_dir: Result<FixtureHarness>) -> Result<()> {
+        let rice_dir = rice_dir?;
+        let locator = mock_locator(&rice_dir);
+        let plan = plan_rewrite(
+            "github.com",
+            "codeberg.org",
+            &RepoSelector::new(&["dwm".into()]),
+            &locator,
+        )?;
+        plan.apply(&locator)?;
+
+        let dwm = GitRepo::open(rice_dir.as_path().join("repos/dwm.git"))?;
+        assert_eq!(
+            dwm.remote_url("origin").as_deref(),
+            Some("https://codeberg.org/awkless/dwm.git")
+        );
+        assert_eq!(dwm.remote_url("upstream").as_deref(), Some("https://git.suckless.org/dwm"));
+
+        let config = ConfigFile::load(RepoConfig, &locator)?;
+        let clone = |name| -> Result<Option<String>> {
+            Ok(config.get(name)?.bootstrap.and_then(|bootstrap| bootstrap.clone))
+        };
+        assert_eq!(clone("dwm")?.as_deref(), Some("https://codeberg.org/awkless/dwm.git"));
+        assert_eq!(clone("vim")?.as_deref(), Some("git@github.com:awkless/vim.git"));
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::empty_pattern("", "x", vec![])]
+    #[case::missing_repo("github.com", "codeberg.org", vec!["nope"])]
+    #[case::bad_url("https", "htps", vec![])]
+    fn plan_rewrite_return_err(
+        rice_dir: Result<FixtureHarness>,
+        #[case] from: &str,
+        #[case] to: &str,
+        #[case] repos: Vec<&str>,
+    ) -> Result<()> {
+        let rice_dir = rice_dir?;
+        let repos = repos.into_iter().map(String::from).collect::<Vec<_>>();
+        let result = plan_rewrite(from, to, &RepoSelector::new(&repos), &mock_locator(&rice_dir));
+        match (from, repos.is_empty()) {
+            ("", _) => assert!(matches!(result.unwrap_err(), RemotesError::EmptyPattern)),
+            (_, false) => assert!(matches!(result.unwrap_err(), RemotesError::Select { .. })),
+            _ => assert!(matches!(result.unwrap_err(), RemotesError::BadUrl { .. })),
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,987 @@
+// SPDX-FileCopyrightText: 2024 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+//! Fully wired runtime of Ricer.
+//!
+//! Every command needs the same runtime wired up in the same order: command
+//! line parsed into a [`Context`], directory layout resolved into a
+//! [`Locator`], defaults of `config.toml` merged in, hooks loaded from
+//! `hooks.toml`, and subscribers of command events registered. Through
+//! [`RicerBuilder`], the `ricer` binary, tests, and tools embedding Ricer all
+//! share this wiring, and only swap out the parts they need to, like the
+//! [`Locator`] or the [`Prompter`] that asks the user questions.
+//!
+//! Git operations are swapped out at their remote access, i.e., through the
+//! credentials, host key checks, and progress reporting they use, while
+//! repositories themselves are always operated on through libgit2. The
+//! current time is swapped out through a [`Clock`].
+//!
+//! [`Context`]: crate::context::Context
+
+use crate::{
+    add,
+    bootstrap::{self, BootstrapOutcome, Machine},
+    bugreport,
+    cancel::{self, CANCELLED_EXIT_CODE},
+    clean,
+    cli::{Cli, LocateOptions},
+    clock::{self, Clock},
+    clone,
+    commit::{self, PushOutcome},
+    completions::{self, CompletionDirs},
+    config::{
+        self, CommandConfig, DaemonConfig, FeatureConfig, HookLogConfig, LintConfig, PagerMode,
+        UiConfig,
+    },
+    context::{
+        BootstrapContext, CloneContext, ConfigContext, ConfigOp, Context, EnvSnapshot, HookContext,
+        HookOp, IgnoreOp, MoveConfigDirContext, ProfileContext, ProfileOp, RemotesContext,
+        RemotesOp,
+    },
+    crypt::{self, AgeCipher, CachedCipher},
+    daemon, deploy, doctor, examples, foreach,
+    forge::{self, CurlClient, Forge},
+    history::{self, HistorySubscriber, HISTORY_FEATURE},
+    hook::{self, CmdHook},
+    hostkey::{self, HostKeyCheck},
+    ignore, info, init,
+    locate::{
+        DefaultLocator, DirLayout, LocateError, Locator, OverrideLayout, ProfileLayout,
+        XdgDirLayout, CONFIG_DIR_ENV, DATA_DIR_ENV, PROFILE_ENV, STATE_DIR_ENV,
+    },
+    manager::{ManagerReport, RepoManager},
+    ops::{EnvReport, Event, EventBus, HookSubscriber, LogSubscriber},
+    owns, plan,
+    plugin::{self, Plugin},
+    profile, pull, readonly,
+    relocate::{self, Location},
+    remotes, sed, snapshot, split,
+    state::{StateSubscriber, STATE_FEATURE},
+    stats, status, switch,
+    timing::{Timed, TimingSubscriber, Timings},
+    ui::{self, Output, Prompter, Style, TerminalPrompter},
+    util::{RepoResult, RepoSelector},
+    vcs::{self, CredentialProvider, Progress, RepoCache, TerminalProgress},
+    watchdog::{self, RunEntry, RunGuard},
+};
+
+use anyhow::Result;
+use directories::BaseDirs;
+use log::{info, warn};
+use std::{
+    ffi::OsString,
+    fmt::Write,
+    io::{self, IsTerminal},
+    path::PathBuf,
+};
+
+/// Builder of Ricer's runtime.
+///
+/// Runs commands just like the `ricer` binary by default. Parts of the
+/// runtime can be replaced through the builder methods, e.g., tests use a
+/// [`Locator`] of a temporary directory, and a [`Prompter`] with scripted
+/// answers.
+///
+/// # Invariants
+///
+/// 1. Through [`RicerBuilder::install_globals`], process-wide state is left
+///    alone, i.e., signal handlers, panic hook, log level, environment
+///    snapshot, environment variables of profile directories, and default
+///    credentials, host key checks, and progress of Git operations. Host key
+///    checks, credentials, progress, and clocks given to the builder are still
+///    installed.
+/// 1. Flags that change how the whole run behaves, like `--dry-run` or
+///    `--no-validate`, are installed for the rest of the process either way.
+pub struct RicerBuilder<L = DefaultLocator, P = TerminalPrompter> {
+    locate: Box<dyn FnOnce(&OverrideLayout) -> L>,
+    prompter: P,
+    layout: Option<OverrideLayout>,
+    home: Option<PathBuf>,
+    credentials: Option<CredentialProvider>,
+    host_keys: Option<HostKeyCheck>,
+    progress: Option<Box<dyn Progress>>,
+    clock: Option<Box<dyn Clock>>,
+    globals: bool,
+}
+
+impl RicerBuilder {
+    /// Construct builder of the standard runtime of the `ricer` binary.
+    pub fn new() -> Self {
+        Self {
+            locate: Box::new(|profile| DefaultLocator::locate(profile)),
+            prompter: TerminalPrompter,
+            layout: None,
+            home: None,
+            credentials: None,
+            host_keys: None,
+            progress: None,
+            clock: None,
+            globals: true,
+        }
+    }
+}
+
+impl Default for RicerBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<L, P> RicerBuilder<L, P>
+where
+    L: Locator,
+    P: Prompter,
+{
+    /// Locate configuration files and repositories through `locator`,
+    /// instead of the directories of the selected profile.
+    pub fn locator<M: Locator + 'static>(self, locator: M) -> RicerBuilder<M, P> {
+        RicerBuilder {
+            locate: Box::new(move |_| locator),
+            prompter: self.prompter,
+            layout: self.layout,
+            home: self.home,
+            credentials: self.credentials,
+            host_keys: self.host_keys,
+            progress: self.progress,
+            clock: self.clock,
+            globals: self.globals,
+        }
+    }
+
+    /// Ask the user questions through `prompter`.
+    pub fn prompter<Q: Prompter>(self, prompter: Q) -> RicerBuilder<L, Q> {
+        RicerBuilder {
+            locate: self.locate,
+            prompter,
+            layout: self.layout,
+            home: self.home,
+            credentials: self.credentials,
+            host_keys: self.host_keys,
+            progress: self.progress,
+            clock: self.clock,
+            globals: self.globals,
+        }
+    }
+
+    /// Use `layout` as standard directory layout, instead of the XDG
+    /// directories of the user.
+    ///
+    /// Directories moved through `move-config-dir`, location options, and
+    /// profiles still apply on top of it.
+    pub fn layout(mut self, layout: impl DirLayout) -> Self {
+        self.layout = Some(OverrideLayout::new(layout));
+        self
+    }
+
+    /// Use `home` as home directory of the user.
+    pub fn home(mut self, home: impl Into<PathBuf>) -> Self {
+        self.home = Some(home.into());
+        self
+    }
+
+    /// Authenticate Git operations through `provider`.
+    pub fn credentials(mut self, provider: CredentialProvider) -> Self {
+        self.credentials = Some(provider);
+        self
+    }
+
+    /// Verify SSH host keys through `check`.
+    ///
+    /// Through `--no-verify-host`, unknown hosts are still trusted.
+    pub fn host_keys(mut self, check: HostKeyCheck) -> Self {
+        self.host_keys = Some(check);
+        self
+    }
+
+    /// Report progress of Git transfers through `progress`.
+    ///
+    /// Progress is reported whatever `--progress` says.
+    pub fn progress(mut self, progress: impl Progress + 'static) -> Self {
+        self.progress = Some(Box::new(progress));
+        self
+    }
+
+    /// Read the current time through `clock`, see [`crate::clock`].
+    pub fn clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = Some(Box::new(clock));
+        self
+    }
+
+    /// Set whether process-wide state is installed, see [`RicerBuilder`].
+    ///
+    /// Tools that run Ricer several times within one process, or that
+    /// install this state on their own, should turn it off.
+    pub fn install_globals(mut self, globals: bool) -> Self {
+        self.globals = globals;
+        self
+    }
+
+    /// Run command of command line `args`, whose first argument is the name
+    /// of the program, like [`std::env::args_os`].
+    ///
+    /// # Errors
+    ///
+    /// - Return any error of parsing `args`, or of the command itself.
+    pub fn run<I, T>(self, args: I) -> Result<ExitCode>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<OsString>,
+    {
+        let Self {
+            locate,
+            prompter,
+            layout,
+            home,
+            credentials,
+            host_keys,
+            progress,
+            clock,
+            globals,
+        } = self;
+        if let Some(clock) = clock {
+            clock::install(clock);
+        }
+        let args = args.into_iter().map(Into::into).collect::<Vec<OsString>>();
+        let argv = args.iter().skip(1).cloned().collect::<Vec<_>>();
+        let opts = Cli::parse_args(args)?;
+        if globals {
+            log::set_max_level(opts.log_opts.log_level_filter());
+            cancel::install()?;
+        }
+        // INVARIANT: the Git shortcut has no shared context, yet honors `--dry-run` too.
+        if opts.shared_opts.dry_run {
+            plan::install_dry_run();
+        }
+        if opts.shared_opts.no_validate {
+            config::skip_validation();
+        }
+
+        let standard = match layout {
+            Some(layout) => layout,
+            None => OverrideLayout::new(XdgDirLayout::layout()?),
+        };
+        let (layout, profile) = dir_layout(&opts.locate_opts, &standard, globals)?;
+        if globals {
+            // INVARIANT: snapshot after profile directories reached the environment.
+            EnvSnapshot::capture().install();
+        }
+        let mut ctx = Context::from(opts);
+        let timings = Timings::new();
+        let locator = locate(&profile);
+        let home = home.or_else(|| BaseDirs::new().map(|dirs| dirs.home_dir().into()));
+        let home_dir = || home.as_deref().ok_or(LocateError::NoWayHome);
+        if globals {
+            let crash_log = bugreport::crash_log_file(&locator);
+            let default_hook = std::panic::take_hook();
+            std::panic::set_hook(Box::new(move |panic| {
+                default_hook(panic);
+                let _ = bugreport::record_crash(&crash_log, &panic.to_string());
+            }));
+        }
+        // INVARIANT: injected host key checks and credentials win over the default ones.
+        let default_vcs = home.as_deref().filter(|_| globals);
+        let host_keys = host_keys
+            .or_else(|| default_vcs.map(|home| HostKeyCheck::new(hostkey::known_hosts_file(home))));
+        if let Some(check) = host_keys {
+            match ctx.shared().is_some_and(|shared| shared.no_verify_host) {
+                true => hostkey::install(check.verify(false)),
+                false => hostkey::install(check),
+            }
+        }
+        let credentials = credentials.or_else(|| {
+            default_vcs.map(|home| CredentialProvider::new(home).prompt(io::stdin().is_terminal()))
+        });
+        if let Some(provider) = credentials {
+            vcs::install_credentials(provider);
+        }
+        let progress_mode = ctx.shared().map(|shared| shared.progress).unwrap_or_default();
+        match progress {
+            Some(progress) => vcs::install_progress(progress),
+            None if globals && progress_mode.enabled(io::stderr().is_terminal()) => {
+                vcs::install_progress(TerminalProgress::new(io::stderr()))
+            }
+            None => (),
+        }
+        // INVARIANT: dry runs leave run entries, history, and state files alone.
+        let dry_run = plan::is_dry_run();
+        if !dry_run {
+            for run in watchdog::recover(&locator) {
+                warn!("{run}");
+            }
+        }
+        if !matches!(ctx, Context::Git(_)) {
+            ctx.merge_defaults(&CommandConfig::load(&locator, &ctx.to_string())?);
+        }
+        readonly::check_writable(&ctx, &locator)?;
+        // INVARIANT: directories move away underneath run entries, history, and hooks.
+        if let Context::MoveConfigDir(move_ctx) = &ctx {
+            return move_config_dir(move_ctx, &layout, &standard, &prompter);
+        }
+        let _run = match dry_run {
+            true => None,
+            false => RunGuard::start(&locator, &RunEntry::new(&ctx, &argv))
+                .map_err(|err| warn!("{}", ui::ErrorReport::new(&err)))
+                .ok(),
+        };
+        let history = !dry_run && FeatureConfig::is_opted_in(&locator, HISTORY_FEATURE)?;
+        let hook_mgr = timings.time("config load", || CmdHook::load(&ctx, &locator))?;
+        let hook_mgr = hook_mgr.log_runs(history).keep_logs(HookLogConfig::load(&locator)?);
+        let hook_plan = hook_mgr.plan();
+        let hook_runs = hook_mgr.runs();
+        let mut bus = EventBus::new().subscribe(LogSubscriber);
+        if history {
+            bus = bus.subscribe(HistorySubscriber::new(&locator, argv));
+        }
+        bus = bus.subscribe(TimingSubscriber::new(timings.clone()));
+        // INVARIANT: plans only show which hooks would run, so never run them.
+        if !matches!(&ctx, Context::Bootstrap(BootstrapContext { plan: true, .. })) {
+            bus = bus.subscribe(Timed::new(HookSubscriber::new(&hook_mgr), timings.clone()));
+        }
+        let repo_cache = RepoCache::new();
+        if !dry_run && FeatureConfig::is_enabled(&locator, STATE_FEATURE)? {
+            bus = bus.subscribe(StateSubscriber::new(&locator).repo_cache(repo_cache.clone()));
+        }
+        let pager = ui::pager_mode(&ctx, &UiConfig::load(&locator)?);
+        let mut out = Output::new(pager);
+        // INVARIANT: every command that started reaches `CommandFinished`, unless cancelled.
+        let result = (|| -> Result<ExitCode> {
+            bus.emit(Event::CommandStarted { context: &ctx })?;
+            match &ctx {
+                Context::Env(_) => {
+                    for (key, value) in EnvReport::new(&ctx, &locator).iter() {
+                        writeln!(out, "{}={value}", out.paint(Style::Cyan, key))?;
+                    }
+                }
+                Context::Bootstrap(bootstrap_ctx) if bootstrap_ctx.plan => {
+                    let machine = Machine::current();
+                    let plan = bootstrap::plan_bootstrap(
+                        bootstrap_ctx,
+                        &locator,
+                        &hook_mgr,
+                        &machine,
+                        &CurlClient,
+                    )?;
+                    write!(out, "{plan}")?;
+                }
+                Context::Bootstrap(bootstrap_ctx) => {
+                    let home = home_dir()?;
+                    let machine = Machine::current();
+                    let outcomes =
+                        bootstrap::bootstrap(bootstrap_ctx, &locator, &hook_mgr, &machine, home)?;
+                    for (repo, outcome) in &outcomes {
+                        writeln!(out, "{repo}: {outcome}")?;
+                        if *outcome == BootstrapOutcome::Cloned {
+                            bus.emit(Event::RepoOperated { context: &ctx, repo })?;
+                        }
+                    }
+                    if outcomes
+                        .iter()
+                        .any(|(_, outcome)| matches!(outcome, BootstrapOutcome::Failed { .. }))
+                    {
+                        return Ok(ExitCode::Failure);
+                    }
+                }
+                Context::Init(init_ctx) => {
+                    let home = home_dir()?;
+                    let repo = init::init_repo(init_ctx, &locator, home)?;
+                    for file in &repo.files {
+                        writeln!(out, "{}", out.paint(Style::Green, file.display()))?;
+                    }
+                    bus.emit(Event::RepoOperated { context: &ctx, repo: &repo.settings.name })?;
+                }
+                Context::Clone(CloneContext { forge: Some(forge_ctx), .. }) => {
+                    let forge = Forge::from_env(forge_ctx.kind, CurlClient);
+                    let phase = format!("clone '{}'", forge_ctx.namespace);
+                    bus.emit(Event::PhaseStarted { context: &ctx, phase: &phase })?;
+                    let repos = forge::clone_namespace(&forge, forge_ctx, &locator)?;
+                    bus.emit(Event::PhaseFinished { context: &ctx, phase: &phase })?;
+                    for repo in repos {
+                        bus.emit(Event::RepoOperated { context: &ctx, repo: &repo })?;
+                    }
+                }
+                Context::Clone(clone_ctx) => {
+                    let home = home_dir()?;
+                    let repo = clone::clone_repo(clone_ctx, &locator, home, &prompter)?;
+                    for adoption in &repo.adopted {
+                        writeln!(out, "{adoption}")?;
+                    }
+                    if let Some(script) = repo.on_clone() {
+                        hook_mgr.run_setup_hook(
+                            &repo.settings.name,
+                            script,
+                            repo.workdir.as_deref(),
+                        )?;
+                    }
+                    bus.emit(Event::RepoOperated { context: &ctx, repo: &repo.settings.name })?;
+                }
+                Context::Commit(commit_ctx) => {
+                    let lint = LintConfig::load(&locator)?;
+                    let committed =
+                        commit::commit_all_with(commit_ctx, &locator, &lint, &hook_plan.changes())?;
+                    for repo in &committed {
+                        writeln!(out, "{repo}")?;
+                        bus.emit(Event::RepoOperated { context: &ctx, repo: &repo.name })?;
+                    }
+                    if committed
+                        .iter()
+                        .any(|repo| matches!(repo.push, Some(PushOutcome::Failed { .. })))
+                    {
+                        return Ok(ExitCode::Failure);
+                    }
+                }
+                Context::Pull(pull_ctx) => {
+                    let results =
+                        pull::pull_all_with(pull_ctx, &locator, &hook_plan.changes(), &prompter)?;
+                    for (repo, result) in &results {
+                        writeln!(out, "{repo}: {result}")?;
+                        if let RepoResult::Done(_) = result {
+                            bus.emit(Event::RepoOperated { context: &ctx, repo })?;
+                        }
+                    }
+                    if results.iter().any(|(_, result)| result.is_failed()) {
+                        return Ok(ExitCode::Failure);
+                    }
+                }
+                Context::Status(status_ctx) => {
+                    let table = status::repo_statuses_with(status_ctx, &locator, &repo_cache)?;
+                    write!(out, "{table}")?;
+                    if table.has_failed() {
+                        return Ok(ExitCode::Failure);
+                    }
+                }
+                Context::Rename(rename_ctx) => {
+                    let plan = plan::plan_rename(rename_ctx, &locator)?;
+                    if rename_ctx.dry_run || dry_run {
+                        write!(out, "{plan}")?;
+                    } else {
+                        bus.emit(Event::PhaseStarted { context: &ctx, phase: "apply plan" })?;
+                        plan.apply(&locator)?;
+                        bus.emit(Event::PhaseFinished { context: &ctx, phase: "apply plan" })?;
+                        bus.emit(Event::RepoOperated { context: &ctx, repo: &rename_ctx.to })?;
+                    }
+                }
+                Context::Profile(ProfileContext {
+                    op: ProfileOp::Fork { name, from, share_repos },
+                    ..
+                }) => {
+                    let profile =
+                        profile::fork_profile(&layout, name, from.as_deref(), *share_repos)?;
+                    info!("Forked profile '{name}' into '{}'", profile.config_dir().display());
+                }
+                Context::Hook(HookContext { op: HookOp::List { cmd, effective }, .. }) => {
+                    let cmds = match cmd {
+                        Some(cmd) => vec![cmd.clone()],
+                        None => hook_mgr.commands()?,
+                    };
+                    for cmd in cmds {
+                        let resolved = match effective {
+                            true => hook_mgr.resolved_hooks(&cmd)?,
+                            false => hook_mgr.configured_hooks(&cmd)?,
+                        };
+                        for hook in resolved {
+                            writeln!(out, "{hook}")?;
+                        }
+                    }
+                }
+                Context::Hook(HookContext { op: HookOp::Log { id: Some(id), .. }, .. }) => {
+                    write!(out, "{}", hook::load_log(&locator, id)?)?;
+                }
+                Context::Hook(HookContext {
+                    op: HookOp::Log { id: None, failed, limit }, ..
+                }) => {
+                    let logs = hook::load_logs(&locator)?
+                        .into_iter()
+                        .filter(|log| !failed || !log.succeeded())
+                        .collect::<Vec<_>>();
+                    let skip = limit.map_or(0, |limit| logs.len().saturating_sub(limit));
+                    for log in &logs[skip..] {
+                        writeln!(out, "{}", log.summary())?;
+                    }
+                }
+                Context::Snapshot(snapshot_ctx) => {
+                    let snapshot = snapshot::take_snapshot(snapshot_ctx, &locator)?;
+                    for repo in &snapshot.lock.repos {
+                        bus.emit(Event::RepoOperated { context: &ctx, repo: &repo.name })?;
+                    }
+                    writeln!(out, "{}", snapshot.archive.display())?;
+                }
+                Context::Deploy(deploy_ctx) => {
+                    let home = home_dir()?;
+                    let deployment = deploy::deploy_repo(&deploy_ctx.repo, &locator, home)?;
+                    for file in &deployment.linked {
+                        writeln!(out, "{}", out.paint(Style::Green, file.display()))?;
+                    }
+                    for file in &deployment.pruned {
+                        writeln!(out, "{}", out.paint(Style::Red, file.display()))?;
+                    }
+                    bus.emit(Event::RepoOperated { context: &ctx, repo: &deploy_ctx.repo })?;
+                }
+                Context::Undeploy(undeploy_ctx) => {
+                    let home = home_dir()?;
+                    for file in deploy::undeploy_repo(&undeploy_ctx.repo, &locator, home)? {
+                        writeln!(out, "{}", out.paint(Style::Red, file.display()))?;
+                    }
+                    bus.emit(Event::RepoOperated { context: &ctx, repo: &undeploy_ctx.repo })?;
+                }
+                Context::Owns(owns_ctx) => {
+                    let home = home_dir()?;
+                    for ownership in owns::find_owners(owns_ctx, &locator, home)? {
+                        writeln!(out, "{ownership}")?;
+                    }
+                }
+                Context::Add(add_ctx) => {
+                    let cwd = std::env::current_dir()?;
+                    let files = match add_ctx.patch {
+                        true => add::add_patch(add_ctx, &locator, &cwd, &prompter)?,
+                        false => add::add_files(add_ctx, &locator, &cwd)?,
+                    };
+                    for file in &files {
+                        writeln!(out, "{}", out.paint(Style::Green, file.display()))?;
+                    }
+                    bus.emit(Event::RepoOperated { context: &ctx, repo: &add_ctx.repo })?;
+                }
+                Context::Split(split_ctx) => {
+                    let repo = split::split_repo(split_ctx, &locator)?;
+                    info!("Split {} commits into '{}'", repo.commits, repo.settings.name);
+                    bus.emit(Event::RepoOperated { context: &ctx, repo: &split_ctx.repo })?;
+                    bus.emit(Event::RepoOperated { context: &ctx, repo: &repo.settings.name })?;
+                }
+                Context::Clean(clean_ctx) => {
+                    let cleaned = clean::clean_repo(clean_ctx, &locator, &prompter)?;
+                    info!("{cleaned}");
+                    bus.emit(Event::RepoOperated { context: &ctx, repo: &clean_ctx.repo })?;
+                }
+                Context::Switch(switch_ctx) => {
+                    let switched = switch::switch_repo(switch_ctx, &locator, &Machine::current())?;
+                    info!("{switched}");
+                    bus.emit(Event::RepoOperated { context: &ctx, repo: &switch_ctx.repo })?;
+                }
+                Context::Sed(sed_ctx) => {
+                    let plan = sed::plan_sed(sed_ctx, &locator)?;
+                    if plan.is_empty() {
+                        info!("No matches for '{}'", sed_ctx.pattern);
+                    } else {
+                        // INVARIANT: never page preview, so the question comes after it.
+                        let mut preview = Output::new(PagerMode::Never);
+                        preview.write_diff(&plan.to_string());
+                        preview.finish()?;
+
+                        if sed_ctx.yes || prompter.confirm("Write replacements?")? {
+                            plan.apply(sed_ctx.commit.as_deref())?;
+                            for repo in plan.repos() {
+                                bus.emit(Event::RepoOperated { context: &ctx, repo })?;
+                            }
+                        }
+                    }
+                }
+                Context::Remotes(RemotesContext {
+                    op: RemotesOp::Rewrite { from, to, repos, strict, yes },
+                    ..
+                }) => {
+                    let selector = RepoSelector::new(repos).strict(*strict);
+                    let plan = remotes::plan_rewrite(from, to, &selector, &locator)?;
+                    if plan.is_empty() {
+                        info!("No remotes match '{from}'");
+                    } else {
+                        // INVARIANT: never page preview, so the question comes after it.
+                        let mut preview = Output::new(PagerMode::Never);
+                        write!(preview, "{plan}")?;
+                        preview.finish()?;
+
+                        if *yes || prompter.confirm("Rewrite remotes?")? {
+                            plan.apply(&locator)?;
+                            for repo in plan.repos() {
+                                bus.emit(Event::RepoOperated { context: &ctx, repo })?;
+                            }
+                        }
+                    }
+                }
+                Context::Foreach(foreach_ctx) => {
+                    let report = foreach::foreach(foreach_ctx, &locator)?;
+                    write!(out, "{report}")?;
+                    for repo in report.done() {
+                        bus.emit(Event::RepoOperated { context: &ctx, repo })?;
+                    }
+                    if report.has_failed() {
+                        return Ok(ExitCode::Failure);
+                    }
+                }
+                Context::Lock(_) => {
+                    let cipher = CachedCipher::new(AgeCipher::from_env());
+                    for path in crypt::lock_config(&locator, &cipher)? {
+                        writeln!(out, "{}", out.paint(Style::Green, path.display()))?;
+                    }
+                }
+                Context::Unlock(_) => {
+                    let cipher = CachedCipher::new(AgeCipher::from_env());
+                    for path in crypt::unlock_config(&locator, &cipher)? {
+                        writeln!(out, "{}", out.paint(Style::Green, path.display()))?;
+                    }
+                }
+                Context::Doctor(doctor_ctx) => {
+                    let found = doctor::deprecated_keys(&locator)?;
+                    for key in &found {
+                        writeln!(out, "{}", out.paint(Style::Yellow, key))?;
+                    }
+
+                    let mut prune = Vec::new();
+                    let mut dangling = Vec::new();
+                    for dangling_ref in doctor::dangling_refs(&locator)? {
+                        writeln!(out, "{}", out.paint(Style::Yellow, &dangling_ref))?;
+                        let confirm = || prompter.confirm("Prune this reference?");
+                        match doctor_ctx.fix && (doctor_ctx.yes || confirm()?) {
+                            true => prune.push(dangling_ref),
+                            false => dangling.push(dangling_ref),
+                        }
+                    }
+                    doctor::prune_dangling_refs(&locator, &prune)?;
+                    for dangling_ref in &prune {
+                        writeln!(
+                            out,
+                            "{}",
+                            out.paint(
+                                Style::Green,
+                                format!("Pruned reference: {}", dangling_ref.kind)
+                            )
+                        )?;
+                    }
+
+                    if found.is_empty() && dangling.is_empty() {
+                        info!("No problems found");
+                    }
+                    if !found.is_empty() || !dangling.is_empty() {
+                        return Ok(ExitCode::Failure);
+                    }
+                }
+                Context::Config(ConfigContext {
+                    op: ConfigOp::Watch { interval, notify }, ..
+                }) => {
+                    doctor::watch_config(&locator, *interval, *notify, |problems| -> Result<()> {
+                        // INVARIANT: never page reports, so each one shows up right away.
+                        let mut report = Output::new(PagerMode::Never);
+                        if problems.is_empty() {
+                            writeln!(
+                                report,
+                                "{}",
+                                report.paint(Style::Green, "No problems found")
+                            )?;
+                        }
+                        for problem in problems {
+                            writeln!(report, "{}", report.paint(Style::Yellow, problem))?;
+                        }
+                        report.finish()?;
+                        Ok(())
+                    })?;
+                }
+                Context::Info(info_ctx) => {
+                    let report = info::repo_info(info_ctx, &locator, &hook_mgr)?;
+                    match info_ctx.json {
+                        true => writeln!(out, "{}", report.to_json())?,
+                        false => write!(out, "{report}")?,
+                    }
+                }
+                Context::Completions(completions_ctx) if completions_ctx.install => {
+                    let dirs = CompletionDirs::from_env()?;
+                    writeln!(out, "{}", completions::install(completions_ctx, &dirs)?)?;
+                }
+                Context::Completions(completions_ctx) if completions_ctx.uninstall => {
+                    let dirs = CompletionDirs::from_env()?;
+                    writeln!(out, "{}", completions::uninstall(completions_ctx, &dirs)?)?;
+                }
+                Context::Ignore(ignore_ctx) => {
+                    for pattern in ignore::ignore(ignore_ctx, &locator)? {
+                        writeln!(out, "{pattern}")?;
+                    }
+                    if !matches!(ignore_ctx.op, IgnoreOp::List { .. }) {
+                        bus.emit(Event::RepoOperated {
+                            context: &ctx,
+                            repo: ignore_ctx.op.repo(),
+                        })?;
+                    }
+                }
+                Context::MoveConfigDir(_) => unreachable!("Directories were already moved"),
+                Context::Bugreport(bugreport_ctx) => {
+                    let env = EnvReport::new(&ctx, &locator);
+                    let report =
+                        bugreport::bugreport(bugreport_ctx, &locator, &env, home.as_deref())?;
+                    for file in &report.files {
+                        writeln!(out, "{}", out.paint(Style::Green, file))?;
+                    }
+                    writeln!(out, "{}", report.archive.display())?;
+                }
+                Context::Examples(examples_ctx) => {
+                    write!(out, "{}", examples::render(examples_ctx))?
+                }
+                Context::Completions(completions_ctx) => {
+                    let shell = completions::select_shell(completions_ctx)?;
+                    write!(out, "{}", completions::render(shell))?;
+                }
+                Context::Stats(stats_ctx) => write!(out, "{}", stats::stats(stats_ctx, &locator)?)?,
+                Context::History(history_ctx) => {
+                    for entry in history::query(history_ctx, &locator)? {
+                        writeln!(out, "{entry}")?;
+                    }
+                }
+                Context::Daemon(daemon_ctx) if daemon_ctx.systemd => {
+                    write!(out, "{}", daemon::systemd_unit(&std::env::current_exe()?))?;
+                }
+                Context::Daemon(daemon_ctx) => {
+                    let settings = DaemonConfig::load(&locator)?;
+                    match daemon_ctx.once {
+                        true => {
+                            daemon::run_once(&locator, &settings)?;
+                        }
+                        false => daemon::run_daemon(&locator, &settings)?,
+                    }
+                }
+                Context::List(_)
+                | Context::Push(_)
+                | Context::Delete(_)
+                | Context::Enter(_)
+                | Context::Git(_) => {
+                    let manager = RepoManager::new(&locator).plan_changes(hook_plan.changes());
+                    let code = match manager.execute(&ctx)? {
+                        Some(report) => {
+                            write!(out, "{report}")?;
+                            for repo in report.repos() {
+                                bus.emit(Event::RepoOperated { context: &ctx, repo })?;
+                            }
+                            match report {
+                                ManagerReport::Exited(code) => code,
+                                _ if report.has_failed() => 1,
+                                _ => 0,
+                            }
+                        }
+                        None => {
+                            // INVARIANT: unregistered repositories may name plugins instead.
+                            let Context::Git(git_ctx) = &ctx else { unreachable!() };
+                            let plugin =
+                                Plugin::find(&git_ctx.repo, std::env::var_os("PATH").as_deref())?;
+                            let vars = plugin::plugin_env(&ctx, &locator, pager);
+                            plugin.run(&git_ctx.git_args, vars)?
+                        }
+                    };
+                    if code != 0 {
+                        return Ok(ExitCode::Code(code));
+                    }
+                }
+            }
+            Ok(ExitCode::Success)
+        })();
+
+        let code = match result {
+            // INVARIANT: cancelled commands never run post hooks.
+            Ok(ExitCode::Success) if cancel::is_cancelled() => {
+                warn!("Cancelled");
+                return Ok(ExitCode::Code(CANCELLED_EXIT_CODE));
+            }
+            Ok(code) => code,
+            Err(err) => {
+                let finished = Event::CommandFinished { context: &ctx, success: false };
+                if let Err(finish_err) = bus.emit(finished) {
+                    warn!("{}", ui::ErrorReport::new(&finish_err));
+                }
+                return Err(err);
+            }
+        };
+        // INVARIANT: renames already showed their plan instead.
+        let rename = matches!(ctx, Context::Rename(_));
+        if let (Some(dry_run), ExitCode::Success, false) = (plan::dry_run(), code, rename) {
+            write!(out, "{dry_run}")?;
+        }
+        out.finish()?;
+        bus.emit(Event::CommandFinished { context: &ctx, success: code == ExitCode::Success })?;
+        if ctx.shared().is_some_and(|shared| shared.timings) {
+            let mut report = Output::stderr();
+            write!(report, "{timings}")?;
+            if !hook_runs.is_empty() {
+                write!(report, "\n{hook_runs}")?;
+            }
+            report.finish()?;
+        }
+
+        Ok(code)
+    }
+}
+
+/// Move directories of `base` layout as asked by `ctx`, once the user
+/// confirms the moves.
+///
+/// Commands run through the moved directories afterwards.
+fn move_config_dir(
+    ctx: &MoveConfigDirContext,
+    base: &OverrideLayout,
+    standard: &OverrideLayout,
+    prompter: &impl Prompter,
+) -> Result<ExitCode> {
+    let plan = relocate::plan_relocation(ctx, base, standard)?;
+    if plan.is_empty() {
+        info!("Directories are already in '{}'", ctx.to.display());
+        return Ok(ExitCode::Success);
+    }
+
+    // INVARIANT: never page preview, so the question comes after it.
+    let mut preview = Output::new(PagerMode::Never);
+    write!(preview, "{plan}")?;
+    preview.finish()?;
+    if plan::is_dry_run() {
+        return Ok(ExitCode::Success);
+    }
+    if !ctx.yes && !prompter.confirm("Move directories?")? {
+        return Ok(ExitCode::Success);
+    }
+
+    let mut report = plan.apply()?;
+    let location = Location::load(standard.config_dir())?;
+    plan.verify(&mut report, &DefaultLocator::locate(location.apply(base.clone())));
+    let mut out = Output::new(PagerMode::Never);
+    write!(out, "{report}")?;
+    out.finish()?;
+    match report.has_problems() {
+        true => Ok(ExitCode::Failure),
+        false => Ok(ExitCode::Success),
+    }
+}
+
+/// Determine base directory layout, and directory layout of selected profile,
+/// through location options `opts`.
+///
+/// Directories moved through `move-config-dir` replace those of the standard
+/// layout, while replaced directories of `opts` take precedence over them.
+/// Replaced directories are relative to the current working directory.
+///
+/// # Invariants
+///
+/// Through `export`, child processes, like hooks calling Ricer again, are
+/// handed the directories of the selected profile through their
+/// environment, so they see the same directories.
+fn dir_layout(
+    opts: &LocateOptions,
+    standard: &OverrideLayout,
+    export: bool,
+) -> Result<(OverrideLayout, OverrideLayout)> {
+    let cwd = std::env::current_dir()?;
+    let absolute = |dir: &Option<PathBuf>| dir.as_ref().map(|dir| cwd.join(dir));
+    let location = Location::load(standard.config_dir())?;
+    let base = location
+        .apply(standard.clone())
+        .with_config_dir(absolute(&opts.config_dir))
+        .with_repo_dir(absolute(&opts.data_dir))
+        .with_state_dir(absolute(&opts.state_dir));
+    let profile = match &opts.profile {
+        Some(name) => OverrideLayout::new(ProfileLayout::new(&base, name)?),
+        None => base.clone(),
+    };
+
+    if export {
+        std::env::set_var(CONFIG_DIR_ENV, profile.config_dir());
+        std::env::set_var(DATA_DIR_ENV, profile.repo_dir());
+        std::env::set_var(STATE_DIR_ENV, profile.state_dir());
+        std::env::remove_var(PROFILE_ENV);
+    }
+    Ok((base, profile))
+}
+
+/// Exit code of command run through [`RicerBuilder::run`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitCode {
+    Success,
+    Failure,
+    Code(i32),
+}
+
+impl From<ExitCode> for i32 {
+    fn from(code: ExitCode) -> Self {
+        match code {
+            ExitCode::Success => 0,
+            ExitCode::Failure => 1,
+            ExitCode::Code(code) => code,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        locate::MockDirLayout,
+        testenv::{FileKind, FixtureHarness, ScriptedPrompter},
+    };
+
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+    use rstest::{fixture, rstest};
+    use std::fs;
+
+    #[fixture]
+    fn rice_dir() -> Result<FixtureHarness> {
+        let harness = FixtureHarness::open()?
+            .with_file("config/repos.toml", |fixture| {
+                fixture
+                    .with_data(indoc! {r#"
+                        [repos.vim]
+                        branch = "main"
+                        remote = "origin"
+
+                        [groups.desktop]
+                        members = ["vim", "dwm"]
+                    "#})
+                    .with_kind(FileKind::Normal)
+            })
+            .setup()?;
+        Ok(harness)
+    }
+
+    fn ricer(
+        rice_dir: &FixtureHarness,
+        answers: &[&str],
+    ) -> RicerBuilder<DefaultLocator, ScriptedPrompter> {
+        let root = rice_dir.as_path();
+        let mut layout = MockDirLayout::new();
+        layout.expect_config_dir().return_const(root.join("config"));
+        layout.expect_repo_dir().return_const(root.join("data"));
+        layout.expect_state_dir().return_const(root.join("state"));
+        RicerBuilder::new()
+            .layout(layout)
+            .home(root.join("home"))
+            .prompter(ScriptedPrompter::new(answers.iter().copied()))
+            .install_globals(false)
+    }
+
+    #[rstest]
+    #[case::prune(&["y"], ExitCode::Success, "[\"vim\"]")]
+    #[case::keep(&["n"], ExitCode::Failure, "[\"vim\", \"dwm\"]")]
+    fn ricer_builder_run_ask_through_prompter(
+        rice_dir: Result<FixtureHarness>,
+        #[case] answers: &[&str],
+        #[case] expect: ExitCode,
+        #[case] members: &str,
+    ) -> Result<()> {
+        let rice_dir = rice_dir?;
+        let code = ricer(&rice_dir, answers).run(["ricer", "doctor", "--fix"])?;
+        assert_eq!(code, expect);
+        let data = fs::read_to_string(rice_dir.as_path().join("config/repos.toml"))?;
+        assert!(data.contains(&format!("members = {members}")), "{data}");
+        Ok(())
+    }
+
+    #[rstest]
+    fn ricer_builder_run_finish_failed_commands(rice_dir: Result<FixtureHarness>) -> Result<()> {
+        let rice_dir = rice_dir?;
+        let root = rice_dir.as_path();
+        fs::write(root.join("config/config.toml"), "[features.history]\nenabled = true\n")?;
+
+        assert!(ricer(&rice_dir, &[]).run(["ricer", "deploy", "vim"]).is_err());
+        let history = fs::read_to_string(root.join("state/history.jsonl"))?;
+        assert!(history.contains("\"outcome\":\"failure\""), "{history}");
+        assert!(root.join("state/state.json").exists());
+        Ok(())
+    }
+
+    #[rstest]
+    fn ricer_builder_run_return_err_bad_args(rice_dir: Result<FixtureHarness>) -> Result<()> {
+        let rice_dir = rice_dir?;
+        assert!(ricer(&rice_dir, &[]).run(["ricer", "--no-such-flag"]).is_err());
+        Ok(())
+    }
+}
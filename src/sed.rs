@@ -0,0 +1,363 @@
+// SPDX-FileCopyrightText: 2024 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+//! Search and replace across repositories.
+//!
+//! Configurations of one rice tend to repeat the same values, like the hex
+//! code of a color, across many repositories. Through `ricer sed <PATTERN>
+//! <REPLACEMENT>`, the user can replace every match of a regular expression in
+//! the tracked files of all, or only selected, repositories at once.
+//!
+//! Replacing is done in two steps. First, [`plan_sed`] determines every edit
+//! without touching any file, so the user can review them as a unified diff.
+//! Then, [`SedPlan::apply`] writes the edits, and optionally commits them to
+//! each repository that changed.
+
+use crate::{
+    config::{ConfigFile, ConfigFileError, RepoConfig, RepoSettings},
+    context::SedContext,
+    locate::Locator,
+    util::{RepoSelector, SelectError},
+    vcs::{self, GitRepo, GitRepoError},
+};
+
+use log::{debug, info, warn};
+use regex::{Error as RegexError, Regex};
+use std::{
+    fmt, fs,
+    io::{Error as IoError, ErrorKind},
+    path::PathBuf,
+};
+
+/// Error types for [`plan_sed`] and [`SedPlan`].
+#[derive(Debug, thiserror::Error)]
+pub enum SedError {
+    #[error("Invalid regular expression '{pattern}'")]
+    BadPattern { source: Box<RegexError>, pattern: String },
+
+    #[error("Failed to select repositories")]
+    Select { source: SelectError },
+
+    #[error("Failed to operate on repository '{name}'")]
+    Repo { source: GitRepoError, name: String },
+
+    #[error("Failed to read '{path}'")]
+    FileRead { source: IoError, path: PathBuf },
+
+    #[error("Failed to write '{path}'")]
+    FileWrite { source: IoError, path: PathBuf },
+
+    #[error("Failed to read repository configuration")]
+    Config { source: ConfigFileError },
+}
+
+impl From<ConfigFileError> for SedError {
+    fn from(err: ConfigFileError) -> Self {
+        SedError::Config { source: err }
+    }
+}
+
+impl From<SelectError> for SedError {
+    fn from(err: SelectError) -> Self {
+        SedError::Select { source: err }
+    }
+}
+
+/// Replacement in one tracked file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileEdit {
+    /// Name of repository tracking file.
+    pub repo: String,
+
+    /// Path to file in working directory of repository.
+    pub path: PathBuf,
+
+    /// Path to file relative to working directory of repository.
+    pub tracked: PathBuf,
+
+    /// Number of replaced matches.
+    pub matches: usize,
+
+    /// Contents of file after replacement.
+    pub contents: String,
+
+    /// Unified diff of replacement.
+    pub diff: String,
+}
+
+/// Every edit of a search and replace, in order of repository and file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SedPlan {
+    pub edits: Vec<FileEdit>,
+    gitdirs: Vec<(String, PathBuf)>,
+}
+
+impl SedPlan {
+    pub fn is_empty(&self) -> bool {
+        self.edits.is_empty()
+    }
+
+    /// Names of repositories with at least one edit.
+    pub fn repos(&self) -> Vec<&str> {
+        let mut repos = self.edits.iter().map(|edit| edit.repo.as_str()).collect::<Vec<_>>();
+        repos.dedup();
+        repos
+    }
+
+    /// Write every edit, committing each changed repository with `message`
+    /// if given.
+    ///
+    /// # Invariants
+    ///
+    /// Committing records everything staged in the index of a repository,
+    /// not only the edited files.
+    ///
+    /// # Errors
+    ///
+    /// - Return [`SedError::FileWrite`] if edited file cannot be written.
+    /// - Return [`SedError::Repo`] if edited files cannot be committed.
+    pub fn apply(&self, message: Option<&str>) -> Result<(), SedError> {
+        for edit in &self.edits {
+            info!("Replace {} matches in '{}'", edit.matches, edit.path.display());
+            fs::write(&edit.path, &edit.contents)
+                .map_err(|err| SedError::FileWrite { source: err, path: edit.path.clone() })?;
+        }
+
+        let message = match message {
+            Some(message) => message,
+            None => return Ok(()),
+        };
+
+        for (name, gitdir) in &self.gitdirs {
+            let tracked = self.edits.iter().filter(|edit| &edit.repo == name).map(|e| &e.tracked);
+            let repo_err = |err| SedError::Repo { source: err, name: name.clone() };
+            let repo = GitRepo::open(gitdir).map_err(repo_err)?;
+            repo.stage(tracked).map_err(repo_err)?;
+            repo.commit(message).map_err(repo_err)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for SedPlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for edit in &self.edits {
+            write!(f, "{}", edit.diff)?;
+        }
+        Ok(())
+    }
+}
+
+/// Plan replacing every match of pattern in tracked files from [`SedContext`].
+///
+/// Only repositories matching patterns of [`SedContext`] are searched, or
+/// every registered repository if none were given. Read-only repositories are
+/// never searched. Files that are symbolic links,
+/// are not valid UTF-8, or are missing from the working directory are
+/// skipped. Nothing is written until [`SedPlan::apply`] is called.
+///
+/// # Errors
+///
+/// - Return [`SedError::BadPattern`] if pattern is not a valid regular
+///   expression.
+/// - Return [`SedError::Select`] if patterns select no registered
+///   repository.
+/// - Return [`SedError::Repo`] if tracked files of repository cannot be read.
+/// - Return [`SedError::FileRead`] if tracked file cannot be read.
+/// - Return [`SedError::Config`] if repository configuration cannot be read.
+pub fn plan_sed(ctx: &SedContext, locator: &impl Locator) -> Result<SedPlan, SedError> {
+    let regex = Regex::new(&ctx.pattern).map_err(|err| SedError::BadPattern {
+        source: Box::new(err),
+        pattern: ctx.pattern.clone(),
+    })?;
+
+    let mut plan = SedPlan::default();
+    for settings in selected_repos(ctx, locator)? {
+        let gitdir = settings.gitdir(locator.repos_dir());
+        if !gitdir.exists() {
+            warn!("Skip '{}', because it is not cloned", settings.name);
+            continue;
+        }
+
+        let repo_err = |err| SedError::Repo { source: err, name: settings.name.clone() };
+        let repo = GitRepo::open(&gitdir).map_err(repo_err)?;
+        let workdir = match repo.workdir() {
+            Some(workdir) => workdir.to_path_buf(),
+            None => {
+                warn!("Skip '{}', because it has no working directory", settings.name);
+                continue;
+            }
+        };
+
+        let mut changed = false;
+        for tracked in repo.tracked_files().map_err(repo_err)? {
+            let path = workdir.join(&tracked);
+            let old = match read_text(&path)? {
+                Some(old) => old,
+                None => continue,
+            };
+
+            let matches = regex.find_iter(&old).count();
+            if matches == 0 {
+                continue;
+            }
+
+            let contents = regex.replace_all(&old, ctx.replacement.as_str()).into_owned();
+            if contents == old {
+                continue;
+            }
+
+            let diff = vcs::unified_diff(&tracked, &old, &contents).map_err(repo_err)?;
+            plan.edits.push(FileEdit {
+                repo: settings.name.clone(),
+                path,
+                tracked,
+                matches,
+                contents,
+                diff,
+            });
+            changed = true;
+        }
+
+        if changed {
+            plan.gitdirs.push((settings.name, gitdir));
+        }
+    }
+
+    Ok(plan)
+}
+
+fn selected_repos(ctx: &SedContext, locator: &impl Locator) -> Result<Vec<RepoSettings>, SedError> {
+    let config = ConfigFile::load(RepoConfig, locator)?;
+    let selector = RepoSelector::new(&ctx.repos).strict(ctx.strict).groups(config.groups()?);
+    let selected = selector.select(config.entries()?)?;
+    let (readonly, writable) = selected.into_iter().partition::<Vec<_>, _>(|repo| repo.readonly);
+    for settings in readonly {
+        debug!("Skip '{}', because it is read-only", settings.name);
+    }
+    Ok(writable)
+}
+
+/// Read text file at `path`, or `None` if it should not be edited.
+fn read_text(path: &PathBuf) -> Result<Option<String>, SedError> {
+    match fs::symlink_metadata(path) {
+        Ok(meta) if meta.is_file() => (),
+        Ok(_) => return Ok(None),
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(SedError::FileRead { source: err, path: path.clone() }),
+    }
+
+    match fs::read_to_string(path) {
+        Ok(text) => Ok(Some(text)),
+        Err(err) if err.kind() == ErrorKind::InvalidData => {
+            debug!("Skip '{}', because it is not text", path.display());
+            Ok(None)
+        }
+        Err(err) => Err(SedError::FileRead { source: err, path: path.clone() }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testenv::{mock_locator, parse_ctx, FileKind, FixtureHarness};
+
+    use anyhow::Result;
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+    use rstest::{fixture, rstest};
+
+    #[fixture]
+    fn rice_dir() -> Result<FixtureHarness> {
+        let harness = FixtureHarness::open()?
+            .with_file("config/repos.toml", |fixture| {
+                fixture
+                    .with_data(indoc! {r#"
+                        [repos.dwm]
+                        branch = "main"
+                        remote = "origin"
+                        workdir_home = false
+
+                        [repos.st]
+                        branch = "main"
+                        remote = "origin"
+                        workdir_home = false
+
+                        [repos.vim]
+                        branch = "main"
+                        remote = "origin"
+                        workdir_home = false
+                    "#})
+                    .with_kind(FileKind::Normal)
+            })
+            .with_repo("repos/dwm", |repo| {
+                let repo = repo
+                    .stage("config.h", "static const char col_gray[] = \"#222222\";\n")?
+                    .stage("README", "nothing to see here\n")?;
+                repo.commit("initial commit")?;
+                Ok(repo)
+            })?
+            .with_repo("repos/st", |repo| {
+                let repo = repo.stage("config.h", "static const char *bg = \"#222222\";\n")?;
+                repo.commit("initial commit")?;
+                Ok(repo)
+            })?
+            .setup()?;
+        Ok(harness)
+    }
+
+    #[rstest]
+    fn plan_sed_preview_without_writing(rice_dir: Result<FixtureHarness>) -> Result<()> {
+        let rice_dir = rice_dir?;
+        let locator = mock_locator(&rice_dir);
+        let plan = plan_sed(&parse_ctx::<SedContext>("sed", &["#2{6}", "#1d2021"])?, &locator)?;
+
+        assert_eq!(plan.repos(), vec!["dwm", "st"]);
+        assert_eq!(plan.edits[0].tracked, PathBuf::from("config.h"));
+        assert_eq!(plan.edits[0].matches, 1);
+        assert!(plan.to_string().contains(indoc! {r##"
+            -static const char col_gray[] = "#222222";
+            +static const char col_gray[] = "#1d2021";
+        "##}));
+        let on_disk = fs::read_to_string(rice_dir.as_path().join("repos/dwm.git/config.h"))?;
+        assert!(on_disk.contains("#222222"));
+        Ok(())
+    }
+
+    #[rstest]
+    fn sed_plan_apply_write_and_commit(rice_dir: Result<FixtureHarness>) -> Result<()> {
+        let rice_dir = rice_dir?;
+        let locator = mock_locator(&rice_dir);
+        let plan = plan_sed(
+            &parse_ctx::<SedContext>("sed", &["#(2{6})", "#${1}ff", "--repo", "st"])?,
+            &locator,
+        )?;
+        plan.apply(Some("Change background color"))?;
+
+        let gitdir = rice_dir.as_path().join("repos/st.git");
+        assert_eq!(
+            fs::read_to_string(gitdir.join("config.h"))?,
+            "static const char *bg = \"#222222ff\";\n"
+        );
+        let repo = GitRepo::open(&gitdir)?;
+        assert!(!repo.status()?.dirty);
+        let dwm = fs::read_to_string(rice_dir.as_path().join("repos/dwm.git/config.h"))?;
+        assert!(dwm.contains("\"#222222\""));
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::bad_pattern(&["(unclosed", "x"])]
+    #[case::missing_repo(&["a", "b", "--repo", "nope"])]
+    #[case::no_match(&["a", "b", "x*"])]
+    fn plan_sed_return_err(rice_dir: Result<FixtureHarness>, #[case] args: &[&str]) -> Result<()> {
+        let rice_dir = rice_dir?;
+        let result = plan_sed(&parse_ctx::<SedContext>("sed", args)?, &mock_locator(&rice_dir));
+        match args[0] {
+            "(unclosed" => assert!(matches!(result.unwrap_err(), SedError::BadPattern { .. })),
+            _ => assert!(matches!(result.unwrap_err(), SedError::Select { .. })),
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,626 @@
+// SPDX-FileCopyrightText: 2024 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+//! Rice snapshots.
+//!
+//! A snapshot is a "known good" checkpoint of the user's entire rice. Taking
+//! a snapshot through `ricer snapshot` does three things in one step:
+//!
+//! 1. Tag the current commit of every registered repository with the name of
+//!    the snapshot.
+//! 1. Write the exact commit of every repository into Ricer's lockfile.
+//! 1. Export the lockfile, Ricer's configuration files, and a manifest that
+//!    describes them into a single archive.
+//!
+//! The archive is all that is needed to roll back to the snapshot, or to
+//! replicate it on another machine.
+//!
+//! Through `ricer snapshot --bundle`, the archive also holds a verified Git
+//! bundle of every repository with all of its refs, so the snapshot can be
+//! restored even if a remote disappears. Plain `git clone` restores a
+//! repository from its bundle.
+
+use crate::{
+    clock,
+    config::{is_local_overlay, ConfigFile, ConfigFileError, RepoConfig, RepoSettings},
+    context::SnapshotContext,
+    locate::Locator,
+    vcs::{GitRepo, GitRepoError},
+};
+
+use git2::{Oid, Reference};
+use log::{debug, info, warn};
+use mkdirp::mkdirp;
+use std::{
+    fmt, fs,
+    io::Error as IoError,
+    path::{Path, PathBuf},
+    process::Command,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use toml_edit::{value, Array, DocumentMut, Item, Table};
+
+/// Version of lockfile and manifest format, bumped on breaking changes.
+pub const SNAPSHOT_VERSION: i64 = 1;
+
+/// Error types for [`take_snapshot`].
+#[derive(Debug, thiserror::Error)]
+pub enum SnapshotError {
+    #[error("Failed to load repository configuration")]
+    Config { source: ConfigFileError },
+
+    #[error("Snapshot name '{name}' is not a valid tag name")]
+    BadName { name: String },
+
+    #[error("Repository '{name}' was not found at '{path}'")]
+    MissingRepo { name: String, path: PathBuf },
+
+    #[error("Repository '{name}' has no commits to snapshot")]
+    Unborn { name: String },
+
+    #[error("Repository '{name}' already has tag '{tag}'")]
+    TagExists { name: String, tag: String },
+
+    #[error("Failed to snapshot repository '{name}'")]
+    Repo { source: GitRepoError, name: String },
+
+    #[error("Failed to write '{path}'")]
+    FileWrite { source: IoError, path: PathBuf },
+
+    #[error("Failed to archive snapshot into '{path}': {msg}")]
+    Archive { path: PathBuf, msg: String },
+
+    #[error("Failed to bundle repository '{name}': {msg}")]
+    Bundle { name: String, msg: String },
+}
+
+impl From<ConfigFileError> for SnapshotError {
+    fn from(err: ConfigFileError) -> Self {
+        SnapshotError::Config { source: err }
+    }
+}
+
+/// Locked state of one repository.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LockEntry {
+    pub name: String,
+    pub commit: String,
+    pub branch: String,
+    pub remote: String,
+    pub url: Option<String>,
+}
+
+/// Exact commits of every repository at the time of a snapshot.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Lockfile {
+    pub snapshot: String,
+    pub created: u64,
+    pub repos: Vec<LockEntry>,
+}
+
+impl Lockfile {
+    pub fn to_toml(&self) -> DocumentMut {
+        let mut doc = DocumentMut::new();
+        doc["version"] = value(SNAPSHOT_VERSION);
+        doc["snapshot"] = value(&self.snapshot);
+        doc["created"] = value(self.created as i64);
+
+        let mut repos = Table::new();
+        repos.set_implicit(true);
+        for entry in &self.repos {
+            let mut repo = Table::new();
+            repo["commit"] = value(&entry.commit);
+            repo["tag"] = value(&self.snapshot);
+            repo["branch"] = value(&entry.branch);
+            repo["remote"] = value(&entry.remote);
+            if let Some(url) = &entry.url {
+                repo["url"] = value(url);
+            }
+            repos.insert(&entry.name, Item::Table(repo));
+        }
+        doc["repos"] = Item::Table(repos);
+
+        doc
+    }
+}
+
+impl fmt::Display for Lockfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "# Generated by `ricer snapshot`, do not edit.")?;
+        write!(f, "{}", self.to_toml())
+    }
+}
+
+/// Result of taking a snapshot.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot {
+    pub lock: Lockfile,
+    pub archive: PathBuf,
+}
+
+/// Take snapshot of every registered repository.
+///
+/// Snapshot is named after [`SnapshotContext::name`], or after the current
+/// time if no name was given. The archive is written into
+/// [`SnapshotContext::output`], or into `snapshots/` of the state directory
+/// by default. With [`SnapshotContext::bundle`], a Git bundle of every
+/// repository is stored in the archive under `bundles/`.
+///
+/// # Invariants
+///
+/// 1. Every repository is validated before any of them is tagged, so a
+///    snapshot is either taken of all repositories or of none.
+/// 1. Tags made so far are deleted again if any later step fails, so the
+///    snapshot can be retried under the same name.
+/// 1. Lockfile is only written once the archive was exported.
+/// 1. Repositories whose Git directory is shared through a `path` override
+///    are tagged at that location.
+///
+/// # Errors
+///
+/// - Return [`SnapshotError::BadName`] if snapshot name is not a valid tag.
+/// - Return [`SnapshotError::MissingRepo`] if a registered repository does not
+///   exist.
+/// - Return [`SnapshotError::Unborn`] if a repository has no commits.
+/// - Return [`SnapshotError::TagExists`] if a repository already has a tag
+///   with the snapshot's name.
+/// - Return [`SnapshotError::Repo`] if a repository cannot be inspected or
+///   tagged.
+/// - Return [`SnapshotError::FileWrite`] if lockfile or manifest cannot be
+///   written.
+/// - Return [`SnapshotError::Archive`] if the archive cannot be made.
+/// - Return [`SnapshotError::Bundle`] if a repository cannot be bundled, or
+///   its bundle does not verify.
+/// - Return [`SnapshotError::Config`] if repository configuration cannot be
+///   loaded.
+pub fn take_snapshot(
+    ctx: &SnapshotContext,
+    locator: &impl Locator,
+) -> Result<Snapshot, SnapshotError> {
+    let created = unix_secs(clock::now());
+    let name = ctx.name.clone().unwrap_or_else(|| format!("snapshot-{created}"));
+    if !Reference::is_valid_name(&format!("refs/tags/{name}")) {
+        return Err(SnapshotError::BadName { name });
+    }
+
+    let config = ConfigFile::load(RepoConfig, locator)?;
+    let mut targets = Vec::new();
+    for repo in config.entries()? {
+        targets.push(validate(&repo, &name, locator)?);
+    }
+
+    let mut tagged = Vec::new();
+    let result = tag_and_export(ctx, locator, &name, created, targets, &mut tagged);
+    if result.is_err() {
+        // INVARIANT: failed snapshots leave no tags behind.
+        for (repo, git) in &tagged {
+            if let Err(err) = git.delete_tag(&name) {
+                warn!("Failed to delete tag '{name}' of '{repo}': {err}");
+            }
+        }
+    }
+    result
+}
+
+/// Tag every target as `name`, export archive, and write lockfile.
+///
+/// Every repository that was tagged is pushed into `tagged`, even if a later
+/// step fails.
+fn tag_and_export(
+    ctx: &SnapshotContext,
+    locator: &impl Locator,
+    name: &str,
+    created: u64,
+    targets: Vec<(RepoSettings, GitRepo, Oid)>,
+    tagged: &mut Vec<(String, GitRepo)>,
+) -> Result<Snapshot, SnapshotError> {
+    let mut repos = Vec::new();
+    let mut bundles = Vec::new();
+    for (repo, git, oid) in targets {
+        info!("Tag '{}' at {oid} as '{name}'", repo.name);
+        git.tag(name, oid)
+            .map_err(|err| SnapshotError::Repo { source: err, name: repo.name.clone() })?;
+        if ctx.bundle {
+            bundles.push((repo.name.clone(), git.gitdir().to_path_buf()));
+        }
+        repos.push(LockEntry {
+            url: git.remote_url(&repo.remote),
+            name: repo.name.clone(),
+            commit: oid.to_string(),
+            branch: repo.branch,
+            remote: repo.remote,
+        });
+        tagged.push((repo.name, git));
+    }
+
+    let lock = Lockfile { snapshot: name.into(), created, repos };
+    let output = match &ctx.output {
+        Some(output) => output.clone(),
+        None => locator.state_dir().join("snapshots"),
+    };
+    let archive = export(&lock, &bundles, &output, locator)?;
+    if let Err(err) = write_file(locator.lock_file(), &lock.to_string()) {
+        // INVARIANT: archives are only kept along with the lockfile they export.
+        let _ = fs::remove_file(&archive);
+        return Err(err);
+    }
+
+    Ok(Snapshot { lock, archive })
+}
+
+fn validate(
+    repo: &RepoSettings,
+    tag: &str,
+    locator: &impl Locator,
+) -> Result<(RepoSettings, GitRepo, Oid), SnapshotError> {
+    let repo_err = |err| SnapshotError::Repo { source: err, name: repo.name.clone() };
+    let gitdir = repo.gitdir(locator.repos_dir());
+    if !gitdir.exists() {
+        return Err(SnapshotError::MissingRepo { name: repo.name.clone(), path: gitdir });
+    }
+
+    let git = GitRepo::open(&gitdir).map_err(repo_err)?;
+    let oid = git
+        .head_commit()
+        .map_err(repo_err)?
+        .ok_or_else(|| SnapshotError::Unborn { name: repo.name.clone() })?;
+    if git.has_tag(tag).map_err(repo_err)? {
+        return Err(SnapshotError::TagExists { name: repo.name.clone(), tag: tag.into() });
+    }
+
+    Ok((repo.clone(), git, oid))
+}
+
+/// Export lockfile, configuration, manifest, and bundles of `(name, gitdir)`
+/// pairs into archive at `output`.
+fn export(
+    lock: &Lockfile,
+    bundles: &[(String, PathBuf)],
+    output: &Path,
+    locator: &impl Locator,
+) -> Result<PathBuf, SnapshotError> {
+    let staging = output.join(format!(".{}.staging", lock.snapshot));
+    let archive = output.join(format!("{}.tar.gz", lock.snapshot));
+    let result = stage_export(lock, bundles, &staging, locator)
+        .and_then(|_| archive_dir(&staging, &archive));
+
+    // INVARIANT: never leave staged export data behind.
+    if staging.exists() {
+        let _ = fs::remove_dir_all(&staging);
+    }
+    result?;
+
+    info!("Exported snapshot '{}' to '{}'", lock.snapshot, archive.display());
+    Ok(archive)
+}
+
+fn stage_export(
+    lock: &Lockfile,
+    bundles: &[(String, PathBuf)],
+    staging: &Path,
+    locator: &impl Locator,
+) -> Result<(), SnapshotError> {
+    let mut files = vec![String::from("ricer.lock")];
+    write_file(&staging.join("ricer.lock"), &lock.to_string())?;
+
+    let config_dir = locator.config_dir();
+    for path in exported_config(config_dir, config_dir, locator)? {
+        let relative = path.strip_prefix(config_dir).unwrap();
+        let dest = staging.join("config").join(relative);
+        debug!("Export '{}'", path.display());
+        let data = fs::read(&path)
+            .map_err(|err| SnapshotError::FileWrite { source: err, path: path.clone() })?;
+        mkdirp(dest.parent().unwrap())
+            .map_err(|err| SnapshotError::FileWrite { source: err, path: dest.clone() })?;
+        fs::write(&dest, data)
+            .map_err(|err| SnapshotError::FileWrite { source: err, path: dest })?;
+        files.push(format!("config/{}", relative.display()));
+    }
+
+    for (name, gitdir) in bundles {
+        let relative = format!("bundles/{name}.bundle");
+        let dest = staging.join(&relative);
+        mkdirp(dest.parent().unwrap())
+            .map_err(|err| SnapshotError::FileWrite { source: err, path: dest.clone() })?;
+        bundle_repo(name, gitdir, &dest)?;
+        files.push(relative);
+    }
+
+    let mut manifest = DocumentMut::new();
+    manifest["version"] = value(SNAPSHOT_VERSION);
+    manifest["snapshot"] = value(&lock.snapshot);
+    manifest["created"] = value(lock.created as i64);
+    manifest["lockfile"] = value("ricer.lock");
+    manifest["files"] = value(Array::from_iter(files));
+    write_file(&staging.join("manifest.toml"), &manifest.to_string())
+}
+
+/// Collect configuration files to export, sorted for reproducible archives.
+fn exported_config(
+    dir: &Path,
+    config_dir: &Path,
+    locator: &impl Locator,
+) -> Result<Vec<PathBuf>, SnapshotError> {
+    let read_err = |err| SnapshotError::FileWrite { source: err, path: dir.into() };
+    let mut paths = match fs::read_dir(dir) {
+        Ok(entries) => entries
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(read_err)?,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(read_err(err)),
+    };
+    paths.sort();
+
+    let mut files = Vec::new();
+    for path in paths {
+        // INVARIANT: lockfile is exported at the top, and profiles are separate rices.
+        if path == locator.lock_file() || (dir == config_dir && path.ends_with("profiles")) {
+            continue;
+        }
+
+        // INVARIANT: local overlays only apply to the machine they were written on.
+        if is_local_overlay(&path) {
+            debug!("Skip local overlay '{}'", path.display());
+            continue;
+        }
+
+        match path.is_dir() {
+            true => files.extend(exported_config(&path, config_dir, locator)?),
+            false => files.push(path),
+        }
+    }
+
+    Ok(files)
+}
+
+fn archive_dir(staging: &Path, archive: &Path) -> Result<(), SnapshotError> {
+    let archive_err = |msg: String| SnapshotError::Archive { path: archive.into(), msg };
+    let output = Command::new("tar")
+        .arg("-czf")
+        .arg(archive)
+        .arg("-C")
+        .arg(staging)
+        .arg(".")
+        .output()
+        .map_err(|err| archive_err(format!("cannot run 'tar': {err}")))?;
+
+    if !output.status.success() {
+        return Err(archive_err(String::from_utf8_lossy(&output.stderr).trim().to_string()));
+    }
+
+    Ok(())
+}
+
+/// Bundle every ref of repository at `gitdir` into `dest`, and verify it.
+fn bundle_repo(name: &str, gitdir: &Path, dest: &Path) -> Result<(), SnapshotError> {
+    let bundle_err = |msg: String| SnapshotError::Bundle { name: name.into(), msg };
+    debug!("Bundle '{}' into '{}'", gitdir.display(), dest.display());
+    let mut create = Command::new("git");
+    create.arg("--git-dir").arg(gitdir).args(["bundle", "create"]).arg(dest).arg("--all");
+    let mut verify = Command::new("git");
+    verify.arg("--git-dir").arg(gitdir).args(["bundle", "verify", "--quiet"]).arg(dest);
+
+    for mut cmd in [create, verify] {
+        let output = cmd.output().map_err(|err| bundle_err(format!("cannot run 'git': {err}")))?;
+        if !output.status.success() {
+            return Err(bundle_err(String::from_utf8_lossy(&output.stderr).trim().to_string()));
+        }
+    }
+
+    Ok(())
+}
+
+fn write_file(path: &Path, data: &str) -> Result<(), SnapshotError> {
+    let write_err = |err| SnapshotError::FileWrite { source: err, path: path.into() };
+    if let Some(parent) = path.parent() {
+        mkdirp(parent).map_err(write_err)?;
+    }
+    fs::write(path, data).map_err(write_err)
+}
+
+fn unix_secs(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).map(|time| time.as_secs()).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testenv::{mock_locator, parse_ctx, FileKind, FixtureHarness};
+
+    use anyhow::Result;
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+    use rstest::{fixture, rstest};
+
+    #[fixture]
+    fn rice_dir() -> Result<FixtureHarness> {
+        let harness = FixtureHarness::open()?
+            .with_file("config/repos.toml", |fixture| {
+                fixture
+                    .with_data(indoc! {r#"
+                        [repos.vim]
+                        branch = "main"
+                        remote = "origin"
+                        workdir_home = false
+
+                        [repos.dwm]
+                        branch = "main"
+                        remote = "origin"
+                        workdir_home = false
+                    "#})
+                    .with_kind(FileKind::Normal)
+            })
+            .with_file("config/hooks/hook.sh", |fixture| {
+                fixture.with_data("#!/bin/sh\nexit 0\n").with_kind(FileKind::Script)
+            })
+            .with_file("config/repos.local.toml", |fixture| {
+                fixture.with_data("[repos.vim]\nbranch = \"main\"\n").with_kind(FileKind::Normal)
+            })
+            .with_file("config/profiles/work/repos.toml", |fixture| {
+                fixture.with_data("[repos]\n").with_kind(FileKind::Normal)
+            })
+            .with_repo("repos/vim", |repo| {
+                let repo = repo.stage("vimrc", "config for vim!")?;
+                repo.commit("initial commit")?;
+                Ok(repo)
+            })?
+            .with_repo("repos/dwm", |repo| {
+                let repo = repo.stage("config.h", "configure DWM here")?;
+                repo.commit("initial commit")?;
+                Ok(repo)
+            })?
+            .setup()?;
+        Ok(harness)
+    }
+
+    fn list_archive(archive: &Path) -> Result<Vec<String>> {
+        let output = Command::new("tar").arg("-tzf").arg(archive).output()?;
+        let mut files = String::from_utf8(output.stdout)?
+            .lines()
+            .map(|line| line.trim_start_matches("./").to_string())
+            .filter(|line| !line.is_empty() && !line.ends_with('/'))
+            .collect::<Vec<_>>();
+        files.sort();
+        Ok(files)
+    }
+
+    #[rstest]
+    fn take_snapshot_tag_lock_and_export(rice_dir: Result<FixtureHarness>) -> Result<()> {
+        let rice_dir = rice_dir?;
+        let locator = mock_locator(&rice_dir);
+        let snapshot =
+            take_snapshot(&parse_ctx::<SnapshotContext>("snapshot", &["known-good"])?, &locator)?;
+
+        let names = snapshot.lock.repos.iter().map(|repo| repo.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, ["vim", "dwm"]);
+        for entry in &snapshot.lock.repos {
+            let git = GitRepo::open(rice_dir.as_path().join(format!("repos/{}.git", entry.name)))?;
+            assert!(git.has_tag("known-good")?);
+            assert_eq!(git.head_commit()?.map(|oid| oid.to_string()), Some(entry.commit.clone()));
+        }
+
+        let lock = fs::read_to_string(locator.lock_file())?;
+        assert!(lock.contains("snapshot = \"known-good\""));
+        assert!(lock.contains(&format!("commit = \"{}\"", snapshot.lock.repos[0].commit)));
+
+        assert_eq!(snapshot.archive, rice_dir.as_path().join("state/snapshots/known-good.tar.gz"));
+        assert_eq!(
+            list_archive(&snapshot.archive)?,
+            ["config/hooks/hook.sh", "config/repos.toml", "manifest.toml", "ricer.lock"]
+        );
+        assert!(!rice_dir.as_path().join("state/snapshots/.known-good.staging").exists());
+        Ok(())
+    }
+
+    #[rstest]
+    fn take_snapshot_store_bundles(rice_dir: Result<FixtureHarness>) -> Result<()> {
+        let rice_dir = rice_dir?;
+        let snapshot = take_snapshot(
+            &parse_ctx::<SnapshotContext>("snapshot", &["known-good", "--bundle"])?,
+            &mock_locator(&rice_dir),
+        )?;
+        assert_eq!(
+            list_archive(&snapshot.archive)?,
+            [
+                "bundles/dwm.bundle",
+                "bundles/vim.bundle",
+                "config/hooks/hook.sh",
+                "config/repos.toml",
+                "manifest.toml",
+                "ricer.lock"
+            ]
+        );
+
+        // INVARIANT: bundles restore repositories without their remote.
+        let restore = rice_dir.as_path().join("restore");
+        fs::create_dir(&restore)?;
+        Command::new("tar").arg("-xzf").arg(&snapshot.archive).arg("-C").arg(&restore).output()?;
+        let status = Command::new("git")
+            .arg("clone")
+            .arg("--quiet")
+            .arg(restore.join("bundles/vim.bundle"))
+            .arg(restore.join("vim"))
+            .status()?;
+        assert!(status.success());
+        assert!(GitRepo::open(restore.join("vim"))?.has_tag("known-good")?);
+        assert_eq!(fs::read_to_string(restore.join("vim/vimrc"))?, "config for vim!");
+        Ok(())
+    }
+
+    #[rstest]
+    fn take_snapshot_write_into_output(rice_dir: Result<FixtureHarness>) -> Result<()> {
+        let rice_dir = rice_dir?;
+        let output = rice_dir.as_path().join("backup");
+        let ctx =
+            parse_ctx::<SnapshotContext>("snapshot", &["--output", output.to_str().unwrap()])?;
+        let snapshot = take_snapshot(&ctx, &mock_locator(&rice_dir))?;
+        assert!(snapshot.lock.snapshot.starts_with("snapshot-"));
+        assert!(snapshot.archive.starts_with(&output));
+        assert!(snapshot.archive.exists());
+        Ok(())
+    }
+
+    #[rstest]
+    fn take_snapshot_tag_nothing_on_err(rice_dir: Result<FixtureHarness>) -> Result<()> {
+        let rice_dir = rice_dir?;
+        let locator = mock_locator(&rice_dir);
+        let dwm = GitRepo::open(rice_dir.as_path().join("repos/dwm.git"))?;
+        dwm.tag("known-good", dwm.head_commit()?.unwrap())?;
+
+        let result =
+            take_snapshot(&parse_ctx::<SnapshotContext>("snapshot", &["known-good"])?, &locator);
+        assert!(matches!(result.unwrap_err(), SnapshotError::TagExists { .. }));
+        let vim = GitRepo::open(rice_dir.as_path().join("repos/vim.git"))?;
+        assert!(!vim.has_tag("known-good")?);
+        assert!(!locator.lock_file().exists());
+        Ok(())
+    }
+
+    #[rstest]
+    fn take_snapshot_delete_tags_on_failed_export(rice_dir: Result<FixtureHarness>) -> Result<()> {
+        let rice_dir = rice_dir?;
+        let locator = mock_locator(&rice_dir);
+        let output = rice_dir.as_path().join("backup");
+        fs::write(&output, "not a directory")?;
+
+        let ctx = parse_ctx::<SnapshotContext>(
+            "snapshot",
+            &["known-good", "--output", output.to_str().unwrap()],
+        )?;
+        let result = take_snapshot(&ctx, &locator);
+        assert!(matches!(result.unwrap_err(), SnapshotError::FileWrite { .. }));
+        for name in ["vim", "dwm"] {
+            let git = GitRepo::open(rice_dir.as_path().join(format!("repos/{name}.git")))?;
+            assert!(!git.has_tag("known-good")?);
+        }
+        assert!(!locator.lock_file().exists());
+
+        fs::remove_file(&output)?;
+        assert!(take_snapshot(&ctx, &locator).is_ok());
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::bad_name("bad..name")]
+    #[case::missing_repo("missing")]
+    fn take_snapshot_return_err(
+        rice_dir: Result<FixtureHarness>,
+        #[case] name: &str,
+    ) -> Result<()> {
+        let rice_dir = rice_dir?;
+        if name == "missing" {
+            fs::remove_dir_all(rice_dir.as_path().join("repos/dwm.git"))?;
+        }
+
+        let result = take_snapshot(
+            &parse_ctx::<SnapshotContext>("snapshot", &[name])?,
+            &mock_locator(&rice_dir),
+        );
+        match name {
+            "bad..name" => assert!(matches!(result.unwrap_err(), SnapshotError::BadName { .. })),
+            _ => assert!(matches!(result.unwrap_err(), SnapshotError::MissingRepo { .. })),
+        }
+        Ok(())
+    }
+}
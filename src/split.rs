@@ -0,0 +1,297 @@
+// SPDX-FileCopyrightText: 2024 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+//! Repository splitting.
+//!
+//! Rices tend to start out as one big dotfiles repository, and only later
+//! grow into one repository per program. Through `ricer split <REPO> <DIR>
+//! <NEW>`, directory `DIR` of repository `REPO` becomes repository `NEW` of
+//! its own, keeping the history of every commit that changed `DIR`. Through
+//! `--no-history`, the new repository instead starts from a single commit of
+//! the current contents of `DIR`, which is faster for repositories with a long
+//! history.
+//!
+//! Once split, `DIR` is removed from `REPO` through a commit of its own.
+//! Repositories that use the home directory as their working directory only
+//! stop tracking `DIR`, so the live configuration files stay in place.
+
+use crate::{
+    config::{
+        validate_repo_name, ConfigFile, ConfigFileError, RepoConfig, RepoNameError, RepoSettings,
+        TomlError,
+    },
+    context::SplitContext,
+    init::DEFAULT_REMOTE,
+    locate::Locator,
+    plan::{self, DryRunEffect},
+    vcs::{GitRepo, GitRepoError, RepoInit},
+};
+
+use log::{info, warn};
+use std::{
+    fs,
+    io::Error as IoError,
+    path::{Component, PathBuf},
+};
+
+/// Error types for [`split_repo`].
+#[derive(Debug, thiserror::Error)]
+pub enum SplitError {
+    #[error("Repository '{name}' is not registered")]
+    MissingRepo { name: String },
+
+    #[error("Repository '{name}' is not cloned at '{path}'")]
+    NotCloned { name: String, path: PathBuf },
+
+    #[error("Repository '{name}' already exists")]
+    AlreadyExists { name: String },
+
+    #[error("Invalid repository name '{name}'")]
+    BadName { source: RepoNameError, name: String },
+
+    #[error("Repository '{name}' has no working directory")]
+    NoWorkdir { name: String },
+
+    #[error("Repository '{name}' does not track directory '{dir}'")]
+    NotTracked { name: String, dir: PathBuf },
+
+    #[error("Failed to operate on repository '{name}'")]
+    Repo { source: GitRepoError, name: String },
+
+    #[error("Failed to remove '{path}'")]
+    FileRemove { source: IoError, path: PathBuf },
+
+    #[error("Failed to access repository configuration")]
+    Config { source: ConfigFileError },
+}
+
+impl From<ConfigFileError> for SplitError {
+    fn from(err: ConfigFileError) -> Self {
+        SplitError::Config { source: err }
+    }
+}
+
+/// Repository split out of another.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SplitRepo {
+    /// Settings the new repository was registered with.
+    pub settings: RepoSettings,
+
+    /// Path to Git directory of new repository.
+    pub gitdir: PathBuf,
+
+    /// Number of commits carried over into new repository.
+    pub commits: usize,
+}
+
+/// Split directory of repository into new repository from [`SplitContext`].
+///
+/// New repository uses its own working directory, and is registered with the
+/// branch of the original repository.
+///
+/// # Invariants
+///
+/// 1. Original repository is only changed once the new repository has been
+///    fully made and registered.
+/// 1. Git directory of new repository is removed again if splitting fails.
+/// 1. Files of repositories that use the home directory as their working
+///    directory are never removed.
+///
+/// # Errors
+///
+/// - Return [`SplitError::MissingRepo`] if original repository is not
+///   registered.
+/// - Return [`SplitError::NotCloned`] if original repository is not cloned.
+/// - Return [`SplitError::BadName`] if name of new repository is invalid, see
+///   [`validate_repo_name`].
+/// - Return [`SplitError::AlreadyExists`] if new repository is already
+///   registered or cloned.
+/// - Return [`SplitError::NoWorkdir`] if original repository is bare.
+/// - Return [`SplitError::NotTracked`] if original repository does not track
+///   any file inside directory.
+/// - Return [`SplitError::Repo`] if history cannot be split, or directory
+///   cannot be removed from original repository.
+/// - Return [`SplitError::FileRemove`] if directory cannot be removed from
+///   working directory of original repository.
+/// - Return [`SplitError::Config`] if new repository cannot be registered.
+pub fn split_repo(ctx: &SplitContext, locator: &impl Locator) -> Result<SplitRepo, SplitError> {
+    validate_repo_name(&ctx.name)
+        .map_err(|err| SplitError::BadName { source: err, name: ctx.name.clone() })?;
+    let mut config = ConfigFile::load(RepoConfig, locator)?;
+    let source = match config.get(&ctx.repo) {
+        Ok(settings) => settings,
+        Err(ConfigFileError::Toml {
+            source: TomlError::EntryNotFound { .. } | TomlError::TableNotFound { .. },
+            ..
+        }) => return Err(SplitError::MissingRepo { name: ctx.repo.clone() }),
+        Err(err) => return Err(err.into()),
+    };
+
+    let gitdir = locator.repos_dir().join(format!("{}.git", ctx.name));
+    let registered = config.entries()?.iter().any(|repo| repo.name == ctx.name);
+    if gitdir.exists() || registered {
+        return Err(SplitError::AlreadyExists { name: ctx.name.clone() });
+    }
+
+    let source_err = |err| SplitError::Repo { source: err, name: ctx.repo.clone() };
+    let source_gitdir = source.gitdir(locator.repos_dir());
+    if !source_gitdir.exists() {
+        return Err(SplitError::NotCloned { name: ctx.repo.clone(), path: source_gitdir });
+    }
+    let repo = GitRepo::open(&source_gitdir).map_err(source_err)?;
+    let workdir = match repo.workdir() {
+        Some(workdir) => workdir.to_path_buf(),
+        None => return Err(SplitError::NoWorkdir { name: ctx.repo.clone() }),
+    };
+    let not_tracked = || SplitError::NotTracked { name: ctx.repo.clone(), dir: ctx.dir.clone() };
+    // INVARIANT: directory stays inside working directory, and is not all of it.
+    let inside = ctx.dir.components().all(|part| matches!(part, Component::Normal(_)));
+    if !inside || ctx.dir.as_os_str().is_empty() {
+        return Err(not_tracked());
+    }
+    if !repo.tracked_files().map_err(source_err)?.iter().any(|path| path.starts_with(&ctx.dir)) {
+        return Err(not_tracked());
+    }
+
+    let settings = RepoSettings::new(&ctx.name).branch(&source.branch).remote(DEFAULT_REMOTE);
+    info!("Split '{}' of '{}' into '{}'", ctx.dir.display(), ctx.repo, ctx.name);
+    let squash = format!("Split '{}' from '{}'", ctx.dir.display(), ctx.repo);
+    let effect = || DryRunEffect::Git { gitdir: gitdir.clone(), action: squash.clone() };
+    if !plan::perform(effect) {
+        return Ok(SplitRepo { settings, gitdir, commits: 0 });
+    }
+    let result = GitRepo::init_with(
+        locator.repos_dir().join(&ctx.name),
+        &RepoInit { branch: Some(settings.branch.clone()), ..Default::default() },
+    )
+    .and_then(|new| {
+        repo.split_into(&ctx.dir, &new, &settings.branch, ctx.no_history.then_some(&squash))
+    });
+    let commits = match result {
+        Ok(commits) if commits > 0 => commits,
+        result => {
+            // INVARIANT: never leave a half split repository behind.
+            let _ = fs::remove_dir_all(&gitdir);
+            return match result {
+                Ok(_) => Err(not_tracked()),
+                Err(err) => Err(SplitError::Repo { source: err, name: ctx.name.clone() }),
+            };
+        }
+    };
+
+    config.add_new(settings.clone())?;
+    config.save()?;
+
+    repo.untrack_dir(&ctx.dir).map_err(source_err)?;
+    match repo.is_fake_bare() {
+        true => warn!("Keep '{}', because '{}' uses home directory", ctx.dir.display(), ctx.repo),
+        false => {
+            let path = workdir.join(&ctx.dir);
+            fs::remove_dir_all(&path)
+                .map_err(|err| SplitError::FileRemove { source: err, path })?;
+        }
+    }
+    let message = format!("Split '{}' into '{}'", ctx.dir.display(), ctx.name);
+    repo.commit(message).map_err(source_err)?;
+
+    Ok(SplitRepo { settings, gitdir, commits })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testenv::{mock_locator, parse_ctx, FileKind, FixtureHarness};
+
+    use anyhow::Result;
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+    use rstest::{fixture, rstest};
+
+    #[fixture]
+    fn rice_dir() -> Result<FixtureHarness> {
+        let harness = FixtureHarness::open()?
+            .with_file("config/repos.toml", |fixture| {
+                fixture
+                    .with_data(indoc! {r#"
+                        [repos.dots]
+                        branch = "main"
+                        remote = "origin"
+                        workdir_home = false
+
+                        [repos.vim]
+                        branch = "main"
+                        remote = "origin"
+                        workdir_home = false
+                    "#})
+                    .with_kind(FileKind::Normal)
+            })
+            .with_repo("repos/dots", |repo| {
+                let repo = repo.stage("vim/vimrc", "set number\n")?;
+                repo.commit("Add vimrc")?;
+                let repo = repo.stage("dwm/config.h", "#define MODKEY Mod4Mask\n")?;
+                repo.commit("Add dwm config")?;
+                let repo = repo.stage("vim/colors/gruvbox.vim", "set background=dark\n")?;
+                repo.commit("Add gruvbox colors")?;
+                Ok(repo)
+            })?
+            .setup()?;
+        Ok(harness)
+    }
+
+    #[rstest]
+    #[case::history(&["dots", "vim", "editor"], 2)]
+    #[case::no_history(&["dots", "vim", "editor", "--no-history"], 1)]
+    fn split_repo_move_dir_into_new_repo(
+        rice_dir: Result<FixtureHarness>,
+        #[case] args: &[&str],
+        #[case] commits: usize,
+    ) -> Result<()> {
+        let rice_dir = rice_dir?;
+        let locator = mock_locator(&rice_dir);
+        let split = split_repo(&parse_ctx::<SplitContext>("split", args)?, &locator)?;
+
+        assert_eq!(split.commits, commits);
+        assert_eq!(split.settings, RepoSettings::new("editor").branch("main").remote("origin"));
+        let new = GitRepo::open(&split.gitdir)?;
+        assert_eq!(
+            new.tracked_files()?,
+            vec![PathBuf::from("colors/gruvbox.vim"), PathBuf::from("vimrc")]
+        );
+        assert_eq!(fs::read_to_string(split.gitdir.join("vimrc"))?, "set number\n");
+        assert!(!new.status()?.dirty);
+
+        let dots = rice_dir.as_path().join("repos/dots.git");
+        let old = GitRepo::open(&dots)?;
+        assert_eq!(old.tracked_files()?, vec![PathBuf::from("dwm/config.h")]);
+        assert!(!dots.join("vim").exists());
+        assert!(!old.status()?.dirty);
+
+        let config = ConfigFile::load(RepoConfig, &locator)?;
+        assert_eq!(config.get("editor")?, split.settings);
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::missing_repo(&["nope", "vim", "editor"])]
+    #[case::already_exists(&["dots", "vim", "vim"])]
+    #[case::not_tracked(&["dots", "st", "term"])]
+    #[case::outside_workdir(&["dots", "../dots.git", "term"])]
+    #[case::whole_workdir(&["dots", ".", "term"])]
+    fn split_repo_return_err(
+        rice_dir: Result<FixtureHarness>,
+        #[case] args: &[&str],
+    ) -> Result<()> {
+        let rice_dir = rice_dir?;
+        let result =
+            split_repo(&parse_ctx::<SplitContext>("split", args)?, &mock_locator(&rice_dir));
+        match args[0] {
+            "nope" => assert!(matches!(result.unwrap_err(), SplitError::MissingRepo { .. })),
+            _ if args[2] == "vim" => {
+                assert!(matches!(result.unwrap_err(), SplitError::AlreadyExists { .. }))
+            }
+            _ => assert!(matches!(result.unwrap_err(), SplitError::NotTracked { .. })),
+        }
+        assert!(!rice_dir.as_path().join(format!("repos/{}.git", args[2])).exists());
+        Ok(())
+    }
+}
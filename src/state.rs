@@ -0,0 +1,268 @@
+// SPDX-FileCopyrightText: 2024 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+//! Repository state snapshots.
+//!
+//! Publish a machine-readable snapshot of every tracked repository's state
+//! into `state.json` of the state directory after each command. This allows
+//! dashboards, status bar widgets, and shell prompts to read the current
+//! branch, dirtiness, and divergence of each repository without invoking
+//! Ricer themselves.
+//!
+//! Snapshots are published through [`StateSubscriber`] on the event bus.
+//! Users can opt-out of snapshot publishing through their Ricer configuration
+//! file:
+//!
+//! ```toml
+//! [features.state]
+//! enabled = false
+//! ```
+
+use crate::{
+    clock,
+    json::Json,
+    locate::Locator,
+    ops::{Event, Subscriber, SubscriberError},
+    vcs::{RepoCache, RepoStatus},
+};
+
+use log::{debug, warn};
+use mkdirp::mkdirp;
+use std::{
+    ffi::OsStr,
+    fs,
+    io::Error as IoError,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Name of feature toggle for snapshot publishing.
+pub const STATE_FEATURE: &str = "state";
+
+/// Version of snapshot format, bumped on breaking changes.
+pub const STATE_VERSION: u64 = 1;
+
+/// Error types for [`StateSnapshot`].
+#[derive(Debug, thiserror::Error)]
+pub enum StateError {
+    #[error("Failed to read repository directory '{path}'")]
+    ReadDir { source: IoError, path: PathBuf },
+
+    #[error("Failed to make state directory '{path}'")]
+    MakeDirP { source: IoError, path: PathBuf },
+
+    #[error("Failed to write '{path}'")]
+    FileWrite { source: IoError, path: PathBuf },
+}
+
+/// State of one tracked repository.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RepoState {
+    pub name: String,
+    pub status: RepoStatus,
+}
+
+impl RepoState {
+    pub fn to_json(&self) -> Json {
+        Json::object()
+            .with("name", &self.name)
+            .with("branch", self.status.branch.clone())
+            .with("dirty", self.status.dirty)
+            .with("ahead", self.status.ahead)
+            .with("behind", self.status.behind)
+            .with("last_sync", self.status.last_sync.map(unix_secs))
+    }
+}
+
+/// Snapshot of all tracked repositories.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StateSnapshot {
+    pub generated: SystemTime,
+    pub repos: Vec<RepoState>,
+}
+
+impl StateSnapshot {
+    /// Capture state of every repository in repository directory.
+    ///
+    /// Repositories that cannot be inspected are skipped with a warning, so
+    /// one broken repository does not hide the state of the others.
+    ///
+    /// # Errors
+    ///
+    /// - Return [`StateError::ReadDir`] if repository directory exists, but
+    ///   cannot be read.
+    pub fn capture(locator: &impl Locator) -> Result<Self, StateError> {
+        Self::capture_with(locator, &RepoCache::new())
+    }
+
+    /// Capture state like [`StateSnapshot::capture`], opening repositories
+    /// through `cache`.
+    ///
+    /// # Errors
+    ///
+    /// - Return [`StateError::ReadDir`] if repository directory exists, but
+    ///   cannot be read.
+    pub fn capture_with(locator: &impl Locator, cache: &RepoCache) -> Result<Self, StateError> {
+        let repos_dir = locator.repos_dir();
+        let mut paths = match fs::read_dir(repos_dir) {
+            Ok(entries) => entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| path.is_dir() && path.extension() == Some(OsStr::new("git")))
+                .collect::<Vec<_>>(),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(err) => return Err(StateError::ReadDir { source: err, path: repos_dir.into() }),
+        };
+        paths.sort();
+
+        let mut repos = Vec::new();
+        for path in paths {
+            let name = path.file_stem().unwrap().to_string_lossy().into_owned();
+            match cache.open(&path).and_then(|repo| repo.status()) {
+                Ok(status) => repos.push(RepoState { name, status }),
+                Err(err) => warn!("Skip state of '{name}': {err}"),
+            }
+        }
+
+        Ok(Self { generated: clock::now(), repos })
+    }
+
+    pub fn to_json(&self) -> Json {
+        Json::object()
+            .with("version", STATE_VERSION)
+            .with("generated", unix_secs(self.generated))
+            .with("repos", self.repos.iter().map(RepoState::to_json).collect::<Vec<_>>())
+    }
+
+    /// Write snapshot into `state.json` of state directory.
+    ///
+    /// # Invariants
+    ///
+    /// Snapshot is written into a temporary file that replaces `state.json`,
+    /// so readers never observe a partially written snapshot.
+    ///
+    /// # Errors
+    ///
+    /// - Return [`StateError::MakeDirP`] if state directory cannot be created.
+    /// - Return [`StateError::FileWrite`] if snapshot cannot be written.
+    pub fn write(&self, locator: &impl Locator) -> Result<(), StateError> {
+        let state_dir = locator.state_dir();
+        mkdirp(state_dir)
+            .map_err(|err| StateError::MakeDirP { source: err, path: state_dir.into() })?;
+
+        let path = state_file(locator);
+        let tmp = state_dir.join("state.json.tmp");
+        debug!("Write state snapshot to '{}'", path.display());
+        fs::write(&tmp, format!("{}\n", self.to_json()))
+            .and_then(|_| fs::rename(&tmp, &path))
+            .map_err(|err| StateError::FileWrite { source: err, path })?;
+
+        Ok(())
+    }
+}
+
+/// Expected path to state snapshot file.
+pub fn state_file(locator: &impl Locator) -> PathBuf {
+    locator.state_dir().join("state.json")
+}
+
+fn unix_secs(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).map(|time| time.as_secs()).unwrap_or_default()
+}
+
+/// Publish state snapshot after each command.
+pub struct StateSubscriber<'loc, L>
+where
+    L: Locator,
+{
+    locator: &'loc L,
+    cache: RepoCache,
+}
+
+impl<'loc, L> StateSubscriber<'loc, L>
+where
+    L: Locator,
+{
+    pub fn new(locator: &'loc L) -> Self {
+        Self { locator, cache: RepoCache::new() }
+    }
+
+    /// Open repositories through `cache` shared with the command.
+    pub fn repo_cache(mut self, cache: RepoCache) -> Self {
+        self.cache = cache;
+        self
+    }
+}
+
+impl<L> Subscriber for StateSubscriber<'_, L>
+where
+    L: Locator,
+{
+    fn name(&self) -> &str {
+        "state"
+    }
+
+    fn notify(&mut self, event: &Event<'_>) -> Result<(), SubscriberError> {
+        if let Event::CommandFinished { .. } = event {
+            StateSnapshot::capture_with(self.locator, &self.cache)?.write(self.locator)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        cli::Cli, context::Context, locate::MockLocator, ops::EventBus, testenv::FixtureHarness,
+    };
+
+    use anyhow::Result;
+    use pretty_assertions::assert_eq;
+    use rstest::{fixture, rstest};
+
+    #[fixture]
+    fn state_dir() -> Result<FixtureHarness> {
+        let harness = FixtureHarness::open()?
+            .with_repo("repos/dwm", |repo| repo.stage("config.h", "configure DWM here"))?
+            .with_repo("repos/st", |repo| repo.stage("config.h", "configure st here"))?
+            .setup()?;
+        Ok(harness)
+    }
+
+    #[rstest]
+    fn state_snapshot_capture_all_repos(state_dir: Result<FixtureHarness>) -> Result<()> {
+        let state_dir = state_dir?;
+        let mut locator = MockLocator::new();
+        locator.expect_repos_dir().return_const(state_dir.as_path().join("repos"));
+
+        let snapshot = StateSnapshot::capture(&locator)?;
+        let names = snapshot.repos.iter().map(|repo| repo.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, ["dwm", "st"]);
+        assert!(snapshot.repos.iter().all(|repo| !repo.status.dirty));
+        Ok(())
+    }
+
+    #[rstest]
+    fn state_subscriber_write_snapshot_on_finish(state_dir: Result<FixtureHarness>) -> Result<()> {
+        let state_dir = state_dir?;
+        let mut locator = MockLocator::new();
+        locator.expect_repos_dir().return_const(state_dir.as_path().join("repos"));
+        locator.expect_state_dir().return_const(state_dir.as_path().join("state"));
+
+        let ctx = Context::from(Cli::parse_args(["ricer", "status"])?);
+        let mut bus = EventBus::new().subscribe(StateSubscriber::new(&locator));
+        bus.emit(Event::CommandStarted { context: &ctx })?;
+        assert!(!state_file(&locator).exists());
+
+        bus.emit(Event::CommandFinished { context: &ctx, success: true })?;
+        let data: Json = fs::read_to_string(state_file(&locator))?.parse()?;
+        assert_eq!(data.get("version").and_then(Json::as_f64), Some(1.0));
+        let repos = data.get("repos").and_then(Json::as_array).unwrap();
+        assert_eq!(repos[0].get("name").and_then(Json::as_str), Some("dwm"));
+        assert_eq!(repos[0].get("branch").and_then(Json::as_str), Some("main"));
+        assert_eq!(repos[0].get("dirty").and_then(Json::as_bool), Some(false));
+        assert_eq!(repos[0].get("last_sync"), Some(&Json::Null));
+        Ok(())
+    }
+}
@@ -0,0 +1,271 @@
+// SPDX-FileCopyrightText: 2024 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+//! Aggregate statistics of recorded commands and hooks.
+//!
+//! Through `ricer stats`, the user can see how often each command ran, how
+//! often it failed, and how long it took, from the command history of
+//! [`crate::history`]. Through `ricer stats --hooks`, the same is shown for
+//! every hook script from the hook run log of [`crate::hook`], which helps
+//! finding slow or flaky hooks.
+//!
+//! Both records are only written while the `history` feature is opted into.
+
+use crate::{
+    context::StatsContext,
+    history::{self, HistoryEntry, HistoryError, Outcome},
+    hook::{self, HookLogError, HookRun},
+    locate::Locator,
+};
+
+use std::{collections::HashMap, fmt, time::Duration};
+
+/// Error types for [`stats`].
+#[derive(Debug, thiserror::Error)]
+pub enum StatsError {
+    #[error("Failed to load command history")]
+    History { source: HistoryError },
+
+    #[error("Failed to load hook run log")]
+    HookLog { source: HookLogError },
+}
+
+impl From<HistoryError> for StatsError {
+    fn from(err: HistoryError) -> Self {
+        StatsError::History { source: err }
+    }
+}
+
+impl From<HookLogError> for StatsError {
+    fn from(err: HookLogError) -> Self {
+        StatsError::HookLog { source: err }
+    }
+}
+
+/// Aggregate statistics of one command or hook.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StatRow {
+    pub name: String,
+    pub runs: usize,
+    pub failures: usize,
+    pub total: Duration,
+    pub max: Duration,
+}
+
+impl StatRow {
+    fn new(name: impl Into<String>) -> Self {
+        Self { name: name.into(), runs: 0, failures: 0, total: Duration::ZERO, max: Duration::ZERO }
+    }
+
+    fn add(&mut self, failed: bool, duration: Duration) {
+        self.runs += 1;
+        self.failures += usize::from(failed);
+        self.total += duration;
+        self.max = self.max.max(duration);
+    }
+
+    /// Average duration of one run.
+    pub fn mean(&self) -> Duration {
+        match self.runs {
+            0 => Duration::ZERO,
+            runs => self.total / runs as u32,
+        }
+    }
+}
+
+/// Statistics table of [`stats`].
+///
+/// # Invariants
+///
+/// Rows are sorted by total duration, slowest first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StatsReport {
+    subject: &'static str,
+    rows: Vec<StatRow>,
+}
+
+impl StatsReport {
+    fn aggregate(
+        subject: &'static str,
+        samples: impl IntoIterator<Item = (String, bool, Duration)>,
+    ) -> Self {
+        let mut rows: HashMap<String, StatRow> = HashMap::new();
+        for (name, failed, duration) in samples {
+            rows.entry(name.clone()).or_insert_with(|| StatRow::new(name)).add(failed, duration);
+        }
+
+        let mut rows = rows.into_values().collect::<Vec<_>>();
+        rows.sort_by(|a, b| b.total.cmp(&a.total).then_with(|| a.name.cmp(&b.name)));
+        Self { subject, rows }
+    }
+
+    /// Aggregate statistics of recorded commands.
+    pub fn commands(entries: &[HistoryEntry]) -> Self {
+        let samples = entries.iter().map(|entry| {
+            (entry.command.clone(), entry.outcome != Outcome::Success, entry.duration)
+        });
+        Self::aggregate("Command", samples)
+    }
+
+    /// Aggregate statistics of recorded hook runs.
+    pub fn hooks(runs: &[HookRun]) -> Self {
+        let samples = runs.iter().map(|run| (run.label(), !run.succeeded(), run.duration()));
+        Self::aggregate("Hook", samples)
+    }
+
+    pub fn rows(&self) -> &[StatRow] {
+        &self.rows
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+}
+
+impl fmt::Display for StatsReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let width =
+            self.rows.iter().map(|row| row.name.len()).max().unwrap_or(0).max(self.subject.len());
+        writeln!(
+            f,
+            "{:<width$}  {:>6}  {:>8}  {:>10}  {:>10}  {:>10}",
+            self.subject, "Runs", "Failures", "Mean", "Max", "Total"
+        )?;
+        for row in &self.rows {
+            writeln!(
+                f,
+                "{:<width$}  {:>6}  {:>8}  {:>10}  {:>10}  {:>10}",
+                row.name,
+                row.runs,
+                row.failures,
+                format!("{:.1?}", row.mean()),
+                format!("{:.1?}", row.max),
+                format!("{:.1?}", row.total),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Aggregate statistics of commands, or hooks, from [`StatsContext`].
+///
+/// # Errors
+///
+/// - Return [`StatsError::History`] if command history cannot be read.
+/// - Return [`StatsError::HookLog`] if hook run log cannot be read.
+pub fn stats(ctx: &StatsContext, locator: &impl Locator) -> Result<StatsReport, StatsError> {
+    match ctx.hooks {
+        true => Ok(StatsReport::hooks(&hook::load_runs(locator)?)),
+        false => Ok(StatsReport::commands(&history::load(locator)?)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        hook::{append_run, HookKind},
+        locate::MockLocator,
+        testenv::{parse_ctx, FixtureHarness},
+    };
+
+    use anyhow::Result;
+    use pretty_assertions::assert_eq;
+    use rstest::{fixture, rstest};
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    #[fixture]
+    fn state_dir() -> Result<FixtureHarness> {
+        FixtureHarness::open()
+    }
+
+    fn locator(state_dir: &FixtureHarness) -> MockLocator {
+        let mut locator = MockLocator::new();
+        locator.expect_state_dir().return_const(state_dir.as_path().join("state"));
+        locator
+    }
+
+    fn run(name: &str, millis: u64, code: Option<i32>) -> HookRun {
+        let started = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        HookRun {
+            command: "commit".into(),
+            kind: HookKind::Pre,
+            index: 0,
+            name: Some(name.into()),
+            script: "fmt.sh".into(),
+            started,
+            finished: started + Duration::from_millis(millis),
+            code,
+        }
+    }
+
+    #[rstest]
+    fn stats_aggregate_hook_runs(state_dir: Result<FixtureHarness>) -> Result<()> {
+        let state_dir = state_dir?;
+        let locator = locator(&state_dir);
+        for run in [run("fmt", 10, Some(0)), run("lint", 5, Some(1)), run("fmt", 30, None)] {
+            append_run(&locator, &run)?;
+        }
+
+        let report = stats(&parse_ctx::<StatsContext>("stats", &["--hooks"])?, &locator)?;
+        let expect = vec![
+            StatRow {
+                name: "commit pre 'fmt'".into(),
+                runs: 2,
+                failures: 1,
+                total: Duration::from_millis(40),
+                max: Duration::from_millis(30),
+            },
+            StatRow {
+                name: "commit pre 'lint'".into(),
+                runs: 1,
+                failures: 1,
+                total: Duration::from_millis(5),
+                max: Duration::from_millis(5),
+            },
+        ];
+        assert_eq!(report.rows(), expect);
+        assert_eq!(report.rows()[0].mean(), Duration::from_millis(20));
+        Ok(())
+    }
+
+    #[rstest]
+    fn stats_aggregate_commands(state_dir: Result<FixtureHarness>) -> Result<()> {
+        let state_dir = state_dir?;
+        let locator = locator(&state_dir);
+        for (command, outcome, secs) in [
+            ("pull", Outcome::Success, 2),
+            ("pull", Outcome::Cancelled, 1),
+            ("commit", Outcome::Success, 4),
+        ] {
+            let entry = HistoryEntry {
+                time: SystemTime::now(),
+                command: command.into(),
+                args: vec![command.into()],
+                repos: Vec::new(),
+                outcome,
+                duration: Duration::from_secs(secs),
+            };
+            history::append(&locator, &entry)?;
+        }
+
+        let report = stats(&parse_ctx::<StatsContext>("stats", &[])?, &locator)?;
+        let rows = report
+            .rows()
+            .iter()
+            .map(|row| (row.name.as_str(), row.runs, row.failures))
+            .collect::<Vec<_>>();
+        assert_eq!(rows, vec![("commit", 1, 0), ("pull", 2, 1)]);
+        Ok(())
+    }
+
+    #[rstest]
+    fn stats_report_display_table() {
+        let report = StatsReport::hooks(&[run("fmt", 10, Some(0))]);
+        let table = report.to_string();
+        let lines = table.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("Hook"));
+        assert!(lines[1].starts_with("commit pre 'fmt'       1         0      10.0ms"));
+    }
+}
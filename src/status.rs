@@ -0,0 +1,404 @@
+// SPDX-FileCopyrightText: 2024 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+//! Repository status reports.
+//!
+//! Report branch, uncommitted changes, and divergence from upstream of every
+//! registered repository through `ricer status`. Looking for untracked files
+//! in a working directory as large as the user's home directory is slow, so
+//! each repository can set how untracked files are looked for:
+//!
+//! ```toml
+//! [repos.vim]
+//! untracked = "no"
+//! ```
+//!
+//! Passing `--assume-clean` skips scanning working directories altogether,
+//! only reporting changes staged in the index. Repositories pinned to a commit
+//! or tag through `pin` are marked as such.
+//!
+//! Reports of every repository are gathered into a [`StatusTable`]. By
+//! default, each repository gets a full report that also lists its untracked
+//! files, while `--terse` gives one aligned line per repository.
+
+use crate::{
+    config::ConfigFileError,
+    context::StatusContext,
+    locate::Locator,
+    util::{RepoIter, RepoResult, RepoSelector, SelectError},
+    vcs::{GitRepoError, RepoCache, RepoStatus, StatusScan},
+};
+
+use log::warn;
+use std::{fmt, path::PathBuf};
+
+/// Error types for [`repo_statuses`].
+#[derive(Debug, thiserror::Error)]
+pub enum StatusError {
+    #[error("Failed to select repositories")]
+    Select { source: SelectError },
+
+    #[error("Failed to load repository configuration")]
+    Config { source: ConfigFileError },
+}
+
+impl From<ConfigFileError> for StatusError {
+    fn from(err: ConfigFileError) -> Self {
+        StatusError::Config { source: err }
+    }
+}
+
+impl From<SelectError> for StatusError {
+    fn from(err: SelectError) -> Self {
+        StatusError::Select { source: err }
+    }
+}
+
+/// Result of getting status of one repository.
+pub type StatusResult = RepoResult<StatusReport, GitRepoError>;
+
+/// Status of one registered repository.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StatusReport {
+    pub name: String,
+    pub status: RepoStatus,
+
+    /// Commit or tag repository is pinned to.
+    pub pin: Option<String>,
+
+    /// Untracked files of working directory, only looked for in full
+    /// reports.
+    pub untracked: Vec<PathBuf>,
+
+    /// Give a short status report when displayed.
+    pub terse: bool,
+}
+
+/// Display report, padding name of short reports to the given width.
+impl fmt::Display for StatusReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let RepoStatus { branch, dirty, ahead, behind, .. } = &self.status;
+        if self.terse {
+            let width = f.width().unwrap_or(0);
+            write!(f, "{:width$} {}", self.name, branch.as_deref().unwrap_or("HEAD"))?;
+            if *dirty {
+                write!(f, "*")?;
+            }
+            if *ahead > 0 {
+                write!(f, " +{ahead}")?;
+            }
+            if *behind > 0 {
+                write!(f, " -{behind}")?;
+            }
+            if let Some(pin) = &self.pin {
+                write!(f, " @{pin}")?;
+            }
+            return Ok(());
+        }
+
+        match branch {
+            Some(branch) => write!(f, "{}: on branch '{branch}'", self.name)?,
+            None => write!(f, "{}: detached HEAD", self.name)?,
+        }
+        if let Some(pin) = &self.pin {
+            write!(f, ", pinned to '{pin}'")?;
+        }
+        match dirty {
+            true => write!(f, ", uncommitted changes")?,
+            false => write!(f, ", clean")?,
+        }
+        if *ahead > 0 {
+            write!(f, ", {ahead} ahead")?;
+        }
+        if *behind > 0 {
+            write!(f, ", {behind} behind")?;
+        }
+        for path in &self.untracked {
+            write!(f, "\n  untracked: {}", path.display())?;
+        }
+        Ok(())
+    }
+}
+
+/// Status of every repository gathered by [`repo_statuses`].
+#[derive(Debug, Default)]
+pub struct StatusTable {
+    results: Vec<(String, StatusResult)>,
+}
+
+impl StatusTable {
+    pub fn new(results: Vec<(String, StatusResult)>) -> Self {
+        Self { results }
+    }
+
+    /// Result of each repository in configuration order.
+    pub fn results(&self) -> &[(String, StatusResult)] {
+        &self.results
+    }
+
+    /// Status of at least one repository could not be determined.
+    pub fn has_failed(&self) -> bool {
+        self.results.iter().any(|(_, result)| result.is_failed())
+    }
+}
+
+/// Display one report per line, aligning the columns of short reports.
+impl fmt::Display for StatusTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let width = self.results.iter().map(|(name, _)| name.len()).max().unwrap_or(0);
+        for (name, result) in &self.results {
+            match result {
+                RepoResult::Done(report) => writeln!(f, "{report:width$}")?,
+                _ => writeln!(f, "{name}: {result}")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Get status of every registered repository, or those matching patterns, from
+/// [`StatusContext`].
+///
+/// Repositories are scanned according to their `untracked` setting, unless
+/// `--assume-clean` was given. Registered repositories that were not cloned
+/// yet are skipped with a warning. Repositories whose status cannot be
+/// determined fail on their own without stopping the others.
+///
+/// # Errors
+///
+/// - Return [`StatusError::Config`] if repository configuration cannot be
+///   loaded.
+/// - Return [`StatusError::Select`] if repository patterns select no
+///   registered repository.
+pub fn repo_statuses(
+    ctx: &StatusContext,
+    locator: &impl Locator,
+) -> Result<StatusTable, StatusError> {
+    repo_statuses_with(ctx, locator, &RepoCache::new())
+}
+
+/// Get status of repositories like [`repo_statuses`], opening them through
+/// `cache`.
+///
+/// Lets later steps of the same command, e.g., the state snapshot, reuse
+/// the open repositories.
+///
+/// # Errors
+///
+/// - Return [`StatusError`] like [`repo_statuses`].
+pub fn repo_statuses_with(
+    ctx: &StatusContext,
+    locator: &impl Locator,
+    cache: &RepoCache,
+) -> Result<StatusTable, StatusError> {
+    let selector = RepoSelector::new(&ctx.repos).strict(ctx.strict);
+    let repos = RepoIter::load::<StatusError>(locator, selector)?.cache(cache.clone());
+    let results = repos.try_each(|settings, repo| {
+        let scan = StatusScan { untracked: settings.untracked, assume_clean: ctx.assume_clean };
+        Ok(StatusReport {
+            name: settings.name.clone(),
+            status: repo.status_with(scan)?,
+            pin: settings.pin.clone(),
+            untracked: match ctx.terse {
+                true => Vec::new(),
+                false => repo.untracked_files(scan)?,
+            },
+            terse: ctx.terse,
+        })
+    });
+
+    let mut reports = Vec::new();
+    for (settings, result) in results {
+        if let RepoResult::Missing = result {
+            warn!("Skip status of '{}', because it is not cloned", settings.name);
+            continue;
+        }
+        reports.push((settings.name, result));
+    }
+
+    Ok(StatusTable::new(reports))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testenv::{mock_locator, parse_ctx, FileKind, FixtureHarness};
+
+    use anyhow::Result;
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+    use rstest::{fixture, rstest};
+    use std::fs;
+
+    #[fixture]
+    fn rice_dir() -> Result<FixtureHarness> {
+        let harness = FixtureHarness::open()?
+            .with_file("config/repos.toml", |fixture| {
+                fixture
+                    .with_data(indoc! {r#"
+                        [repos.dwm]
+                        branch = "main"
+                        remote = "origin"
+                        workdir_home = false
+
+                        [repos.st]
+                        branch = "main"
+                        remote = "origin"
+                        workdir_home = false
+                        untracked = "no"
+
+                        [repos.vim]
+                        branch = "main"
+                        remote = "origin"
+                        workdir_home = false
+                    "#})
+                    .with_kind(FileKind::Normal)
+            })
+            .with_repo("repos/dwm", |repo| {
+                let repo = repo.stage("config.h", "configure DWM here")?;
+                repo.commit("initial commit")?;
+                Ok(repo)
+            })?
+            .with_repo("repos/st", |repo| {
+                let repo = repo.stage("config.h", "configure st here")?;
+                repo.commit("initial commit")?;
+                Ok(repo)
+            })?
+            .setup()?;
+        Ok(harness)
+    }
+
+    fn dirty(table: &StatusTable) -> Vec<(&str, bool)> {
+        table
+            .results()
+            .iter()
+            .filter_map(|(name, result)| match result {
+                RepoResult::Done(report) => Some((name.as_str(), report.status.dirty)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[rstest]
+    fn repo_statuses_honor_untracked_setting(rice_dir: Result<FixtureHarness>) -> Result<()> {
+        let rice_dir = rice_dir?;
+        let locator = mock_locator(&rice_dir);
+        for repo in ["dwm", "st"] {
+            fs::write(rice_dir.as_path().join(format!("repos/{repo}.git/new.txt")), "untracked")?;
+        }
+
+        let reports = repo_statuses(&parse_ctx::<StatusContext>("status", &[])?, &locator)?;
+        assert_eq!(dirty(&reports), vec![("dwm", true), ("st", false)]);
+        Ok(())
+    }
+
+    #[rstest]
+    fn repo_statuses_assume_clean_only_report_index(
+        rice_dir: Result<FixtureHarness>,
+    ) -> Result<()> {
+        let rice_dir = rice_dir?;
+        let locator = mock_locator(&rice_dir);
+        fs::write(rice_dir.as_path().join("repos/dwm.git/config.h"), "modified")?;
+        fs::write(rice_dir.as_path().join("repos/st.git/new.c"), "staged")?;
+        rice_dir.get_repo("repos/st")?.add("new.c")?;
+
+        let reports =
+            repo_statuses(&parse_ctx::<StatusContext>("status", &["--assume-clean"])?, &locator)?;
+        assert_eq!(dirty(&reports), vec![("dwm", false), ("st", true)]);
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::plain_name(&["st"], vec!["st"])]
+    #[case::glob(&["d*"], vec!["dwm"])]
+    #[case::skip_uncloned(&["*"], vec!["dwm", "st"])]
+    fn repo_statuses_only_report_selected_repos(
+        rice_dir: Result<FixtureHarness>,
+        #[case] patterns: &[&str],
+        #[case] expect: Vec<&str>,
+    ) -> Result<()> {
+        let rice_dir = rice_dir?;
+        let reports = repo_statuses(
+            &parse_ctx::<StatusContext>("status", patterns)?,
+            &mock_locator(&rice_dir),
+        )?;
+        let names = reports.results().iter().map(|(name, _)| name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, expect);
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::full(&[], vec!["dwm: on branch 'main', uncommitted changes", "  untracked: new.txt", "st: on branch 'main', clean"])]
+    #[case::terse(&["--terse"], vec!["dwm main*", "st  main"])]
+    fn repo_statuses_render_table(
+        rice_dir: Result<FixtureHarness>,
+        #[case] args: &[&str],
+        #[case] expect: Vec<&str>,
+    ) -> Result<()> {
+        let rice_dir = rice_dir?;
+        for repo in ["dwm", "st"] {
+            fs::write(rice_dir.as_path().join(format!("repos/{repo}.git/new.txt")), "untracked")?;
+        }
+
+        let table =
+            repo_statuses(&parse_ctx::<StatusContext>("status", args)?, &mock_locator(&rice_dir))?;
+        assert_eq!(table.to_string().lines().collect::<Vec<_>>(), expect);
+        assert!(!table.has_failed());
+        Ok(())
+    }
+
+    #[rstest]
+    fn repo_statuses_with_reuse_open_repos(rice_dir: Result<FixtureHarness>) -> Result<()> {
+        let rice_dir = rice_dir?;
+        let locator = mock_locator(&rice_dir);
+        let cache = RepoCache::new();
+        let ctx = parse_ctx::<StatusContext>("status", &[])?;
+
+        let first = repo_statuses_with(&ctx, &locator, &cache)?;
+        assert_eq!(cache.len(), 2);
+        fs::write(rice_dir.as_path().join("repos/dwm.git/new.txt"), "untracked")?;
+        let second = repo_statuses_with(&ctx, &locator, &cache)?;
+        assert_eq!(cache.len(), 2);
+        assert_eq!(dirty(&first), vec![("dwm", false), ("st", false)]);
+        assert_eq!(dirty(&second), vec![("dwm", true), ("st", false)]);
+        Ok(())
+    }
+
+    #[rstest]
+    fn repo_statuses_return_err_no_match(rice_dir: Result<FixtureHarness>) -> Result<()> {
+        let rice_dir = rice_dir?;
+        let result = repo_statuses(
+            &parse_ctx::<StatusContext>("status", &["x*"])?,
+            &mock_locator(&rice_dir),
+        );
+        assert!(matches!(result.unwrap_err(), StatusError::Select { .. }));
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::terse(true, None, "vim main* +2")]
+    #[case::verbose(false, None, "vim: on branch 'main', uncommitted changes, 2 ahead")]
+    #[case::terse_pinned(true, Some("v1.2"), "vim main* +2 @v1.2")]
+    #[case::verbose_pinned(
+        false,
+        Some("v1.2"),
+        "vim: on branch 'main', pinned to 'v1.2', uncommitted changes, 2 ahead"
+    )]
+    fn status_report_display(#[case] terse: bool, #[case] pin: Option<&str>, #[case] expect: &str) {
+        let status = RepoStatus {
+            branch: Some("main".into()),
+            dirty: true,
+            ahead: 2,
+            behind: 0,
+            last_sync: None,
+        };
+        let report = StatusReport {
+            name: "vim".into(),
+            status,
+            pin: pin.map(Into::into),
+            untracked: Vec::new(),
+            terse,
+        };
+        assert_eq!(report.to_string(), expect);
+    }
+}
@@ -0,0 +1,297 @@
+// SPDX-FileCopyrightText: 2024 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+//! Branch switching.
+//!
+//! Rices that keep each theme on a branch of their own swap themes through
+//! `ricer switch <REPO> <BRANCH>`. The branch is checked out, and created
+//! from its remote-tracking branch first if it only exists on the remote of
+//! the repository. Post hooks of `switch` run afterwards, e.g., to reload
+//! the window manager.
+//!
+//! Through `--save`, the branch becomes the one the current host uses,
+//! recorded in `host_branches` of the repository, which bootstrapping
+//! honors when it clones the repository on that host again.
+
+use crate::{
+    bootstrap::Machine,
+    config::{ConfigFile, ConfigFileError, RepoConfig, TomlError},
+    context::SwitchContext,
+    locate::Locator,
+    plan::{self, DryRunEffect},
+    vcs::{GitRepo, GitRepoError},
+};
+
+use git2::ErrorCode;
+use log::info;
+use std::{fmt, path::PathBuf};
+
+/// Error types for [`switch_repo`].
+#[derive(Debug, thiserror::Error)]
+pub enum SwitchError {
+    #[error("Repository '{name}' is not registered")]
+    MissingRepo { name: String },
+
+    #[error("Repository '{name}' is not cloned at '{path}'")]
+    NotCloned { name: String, path: PathBuf },
+
+    #[error("Repository '{name}' is pinned to '{pin}', unpin it before switching branches")]
+    Pinned { name: String, pin: String },
+
+    #[error("Branch '{branch}' of '{name}' exists neither locally, nor on remote '{remote}'")]
+    NoBranch { name: String, branch: String, remote: String },
+
+    #[error("Cannot save branch for current host, because its host name is unknown")]
+    NoHost,
+
+    #[error("Failed to operate on repository '{name}'")]
+    Repo { source: GitRepoError, name: String },
+
+    #[error("Failed to access repository configuration")]
+    Config { source: ConfigFileError },
+}
+
+impl From<ConfigFileError> for SwitchError {
+    fn from(err: ConfigFileError) -> Self {
+        SwitchError::Config { source: err }
+    }
+}
+
+/// Repository switched through [`switch_repo`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SwitchedRepo {
+    pub name: String,
+    pub branch: String,
+
+    /// Branch was created from its remote-tracking branch.
+    pub created: bool,
+
+    /// Host that now uses the branch through `--save`.
+    pub saved: Option<String>,
+}
+
+impl fmt::Display for SwitchedRepo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Switched '{}' to branch '{}'", self.name, self.branch)?;
+        if self.created {
+            write!(f, ", new from its remote")?;
+        }
+        if let Some(host) = &self.saved {
+            write!(f, ", saved for host '{host}'")?;
+        }
+        Ok(())
+    }
+}
+
+/// Check out branch of repository from [`SwitchContext`].
+///
+/// # Invariants
+///
+/// 1. Local branches are never reset, a local branch of the same name wins
+///    over the remote-tracking branch.
+/// 1. Uncommitted changes are never overwritten.
+/// 1. Branch of current host is only saved once the switch was done.
+///
+/// # Errors
+///
+/// - Return [`SwitchError::MissingRepo`] if repository is not registered.
+/// - Return [`SwitchError::NotCloned`] if repository is not cloned.
+/// - Return [`SwitchError::Pinned`] if repository is pinned to a revision.
+/// - Return [`SwitchError::NoBranch`] if branch exists neither locally, nor
+///   on the remote of the repository.
+/// - Return [`SwitchError::NoHost`] if branch should be saved, but host name
+///   of `machine` is unknown.
+/// - Return [`SwitchError::Repo`] if branch cannot be checked out, e.g.,
+///   because it would overwrite uncommitted changes.
+/// - Return [`SwitchError::Config`] if repository configuration cannot be
+///   read or written.
+pub fn switch_repo(
+    ctx: &SwitchContext,
+    locator: &impl Locator,
+    machine: &Machine,
+) -> Result<SwitchedRepo, SwitchError> {
+    let name = ctx.repo.clone();
+    let branch = ctx.branch.clone();
+    let mut config = ConfigFile::load(RepoConfig, locator)?;
+    let settings = match config.get(&name) {
+        Ok(settings) => settings,
+        Err(ConfigFileError::Toml {
+            source: TomlError::EntryNotFound { .. } | TomlError::TableNotFound { .. },
+            ..
+        }) => return Err(SwitchError::MissingRepo { name }),
+        Err(err) => return Err(err.into()),
+    };
+    if let Some(pin) = settings.pin.clone() {
+        return Err(SwitchError::Pinned { name, pin });
+    }
+    // INVARIANT: fail before touching the repository if branch cannot be saved.
+    let host = match (ctx.save, machine.host.clone()) {
+        (true, None) => return Err(SwitchError::NoHost),
+        (save, host) => host.filter(|_| save),
+    };
+
+    let gitdir = settings.gitdir(locator.repos_dir());
+    if !gitdir.exists() {
+        return Err(SwitchError::NotCloned { name, path: gitdir });
+    }
+    let repo_err = |err| SwitchError::Repo { source: err, name: name.clone() };
+    let repo = GitRepo::open(&gitdir).map_err(repo_err)?;
+    let created = match repo.track_branch(&settings.remote, &branch) {
+        Ok(created) => created,
+        Err(GitRepoError::LibGit2 { source }) if source.code() == ErrorCode::NotFound => {
+            return Err(SwitchError::NoBranch { name, branch, remote: settings.remote });
+        }
+        Err(err) => return Err(repo_err(err)),
+    };
+
+    // INVARIANT: dry runs never create the branch, so there is nothing to check out yet.
+    if created && plan::is_dry_run() {
+        let action = format!("Check out branch '{branch}'");
+        plan::perform(|| DryRunEffect::Git { gitdir: gitdir.clone(), action });
+    } else {
+        repo.checkout_branch(&branch).map_err(repo_err)?;
+    }
+    info!("Checked out '{branch}' of '{name}'");
+
+    if let Some(host) = &host {
+        let settings = settings.host_branch(host, &branch);
+        config.transaction(|config| config.upsert(settings))?;
+    }
+
+    Ok(SwitchedRepo { name, branch, created, saved: host })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::OsType,
+        testenv::{mock_locator, parse_ctx, FileKind, FixtureHarness},
+    };
+
+    use anyhow::Result;
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+    use rstest::{fixture, rstest};
+    use std::fs;
+
+    #[fixture]
+    fn rice_dir() -> Result<FixtureHarness> {
+        let harness = FixtureHarness::open()?
+            .with_file("config/repos.toml", |fixture| {
+                fixture
+                    .with_data(indoc! {r#"
+                        [repos.themes]
+                        branch = "main"
+                        remote = "origin"
+                        workdir_home = false
+
+                        [repos.kiosk]
+                        branch = "main"
+                        remote = "origin"
+                        pin = "v1.2"
+                    "#})
+                    .with_kind(FileKind::Normal)
+            })
+            .with_repo("repos/themes", |repo| {
+                let repo = repo.stage("colors", "gruvbox\n")?;
+                repo.commit("Add colors")?;
+                Ok(repo)
+            })?
+            .setup()?;
+
+        let themes = git2::Repository::open(harness.as_path().join("repos/themes.git"))?;
+        let commit = themes.head()?.peel_to_commit()?;
+        themes.branch("nord", &commit, false)?;
+        themes.remote("origin", "https://example.com/themes.git")?;
+        themes.reference("refs/remotes/origin/dracula", commit.id(), false, "fetch")?;
+        Ok(harness)
+    }
+
+    fn machine() -> Machine {
+        Machine::new(OsType::Unix).host("lovelace")
+    }
+
+    #[rstest]
+    #[case::local(&["themes", "nord"], "nord", false)]
+    #[case::remote(&["themes", "dracula"], "dracula", true)]
+    fn switch_repo_check_out_branch(
+        rice_dir: Result<FixtureHarness>,
+        #[case] args: &[&str],
+        #[case] branch: &str,
+        #[case] created: bool,
+    ) -> Result<()> {
+        let rice_dir = rice_dir?;
+        let switched = switch_repo(
+            &parse_ctx::<SwitchContext>("switch", args)?,
+            &mock_locator(&rice_dir),
+            &machine(),
+        )?;
+        assert_eq!(switched.created, created);
+        assert_eq!(switched.saved, None);
+
+        let themes = GitRepo::open(rice_dir.as_path().join("repos/themes.git"))?;
+        assert_eq!(themes.head_branch().as_deref(), Some(branch));
+        let data = fs::read_to_string(rice_dir.as_path().join("config/repos.toml"))?;
+        assert!(!data.contains("host_branches"));
+        Ok(())
+    }
+
+    #[rstest]
+    fn switch_repo_save_branch_of_host(rice_dir: Result<FixtureHarness>) -> Result<()> {
+        let rice_dir = rice_dir?;
+        let locator = mock_locator(&rice_dir);
+        let switched = switch_repo(
+            &parse_ctx::<SwitchContext>("switch", &["themes", "nord", "--save"])?,
+            &locator,
+            &machine(),
+        )?;
+        assert_eq!(switched.saved.as_deref(), Some("lovelace"));
+        assert_eq!(
+            switched.to_string(),
+            "Switched 'themes' to branch 'nord', saved for host 'lovelace'"
+        );
+
+        let config = ConfigFile::load(RepoConfig, &locator)?;
+        let settings = config.get("themes")?;
+        assert_eq!(settings.branch, "main");
+        assert_eq!(settings.branch_for(Some("lovelace")), "nord");
+        Ok(())
+    }
+
+    #[rstest]
+    fn switch_repo_return_err_no_branch(rice_dir: Result<FixtureHarness>) -> Result<()> {
+        let rice_dir = rice_dir?;
+        let result = switch_repo(
+            &parse_ctx::<SwitchContext>("switch", &["themes", "nope"])?,
+            &mock_locator(&rice_dir),
+            &machine(),
+        );
+        assert!(matches!(result, Err(SwitchError::NoBranch { .. })));
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::pinned(&["kiosk", "main"], machine())]
+    #[case::missing(&["dwm", "main"], machine())]
+    #[case::no_host(&["themes", "nord", "--save"], Machine::new(OsType::Unix))]
+    fn switch_repo_return_err_before_switching(
+        rice_dir: Result<FixtureHarness>,
+        #[case] args: &[&str],
+        #[case] machine: Machine,
+    ) -> Result<()> {
+        let rice_dir = rice_dir?;
+        let result = switch_repo(
+            &parse_ctx::<SwitchContext>("switch", args)?,
+            &mock_locator(&rice_dir),
+            &machine,
+        );
+        assert!(matches!(
+            result,
+            Err(SwitchError::Pinned { .. } | SwitchError::MissingRepo { .. } | SwitchError::NoHost)
+        ));
+        let themes = GitRepo::open(rice_dir.as_path().join("repos/themes.git"))?;
+        assert_eq!(themes.head_branch().as_deref(), Some("main"));
+        Ok(())
+    }
+}
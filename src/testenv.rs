@@ -1,14 +1,22 @@
 // SPDX-FileCopyrightText: 2024 Jason Pena <jasonpena@awkless.com>
 // SPDX-License-Identifier: MIT
 
+use crate::{
+    cli::Cli,
+    locate::MockLocator,
+    ui::{Prompter, UiError},
+};
+
 use anyhow::{anyhow, Result};
 use git2::{Commit, Oid, Repository, RepositoryInitOptions};
 use is_executable::IsExecutable;
 use mkdirp::mkdirp;
 use std::{
-    collections::HashMap,
+    cell::RefCell,
+    collections::{HashMap, VecDeque},
     ffi::OsStr,
     fs::{metadata, read_to_string, set_permissions, write},
+    io::Read,
     path::{Path, PathBuf},
 };
 use tempfile::{Builder as TempFileBuilder, TempDir};
@@ -129,7 +137,7 @@ pub fn sync_untracked(&mut self) -> Result<()> {
             };
 
             // Insert untracked repository fixture.
-            if entry.path().extension() == Some(&OsStr::new("git")) {
+            if entry.path().extension() == Some(OsStr::new("git")) {
                 if entry.file_type().is_dir() && !self.repos.contains_key(entry.path()) {
                     let repo = RepoFixture::open(entry.path())?;
                     self.repos.insert(entry.path().to_path_buf(), repo);
@@ -333,3 +341,91 @@ pub fn as_path(&self) -> &Path {
         self.root.as_path()
     }
 }
+
+/// Prompter answering every question from a script of answers.
+///
+/// Questions asked, and diffs and lists shown are recorded, so tests can check
+/// what the user would have seen. Hook scripts are accepted through answer
+/// "a".
+#[derive(Debug, Default)]
+pub struct ScriptedPrompter {
+    answers: RefCell<VecDeque<String>>,
+    questions: RefCell<Vec<String>>,
+    diffs: RefCell<Vec<String>>,
+    lists: RefCell<Vec<Vec<String>>>,
+}
+
+impl ScriptedPrompter {
+    pub fn new<S: Into<String>>(answers: impl IntoIterator<Item = S>) -> Self {
+        let answers = answers.into_iter().map(Into::into).collect();
+        Self { answers: RefCell::new(answers), ..Default::default() }
+    }
+
+    pub fn questions(&self) -> Vec<String> {
+        self.questions.borrow().clone()
+    }
+
+    pub fn diffs(&self) -> Vec<String> {
+        self.diffs.borrow().clone()
+    }
+
+    /// Items of every list shown, in order.
+    pub fn lists(&self) -> Vec<Vec<String>> {
+        self.lists.borrow().clone()
+    }
+}
+
+impl Prompter for ScriptedPrompter {
+    fn ask(&self, question: &str) -> Result<Option<String>, UiError> {
+        self.questions.borrow_mut().push(question.into());
+        Ok(self.answers.borrow_mut().pop_front())
+    }
+
+    fn show_diff(&self, diff: &str) -> Result<(), UiError> {
+        self.diffs.borrow_mut().push(diff.into());
+        Ok(())
+    }
+
+    fn show_list(&self, _: &str, items: &[String]) -> Result<(), UiError> {
+        self.lists.borrow_mut().push(items.to_vec());
+        Ok(())
+    }
+
+    fn review_hook(
+        &self,
+        script: &Path,
+        _: Option<&Path>,
+        _: &mut dyn Read,
+    ) -> Result<bool, UiError> {
+        let answer = self.ask(&format!("Run '{}'?", script.display()))?;
+        Ok(answer.as_deref() == Some("a"))
+    }
+}
+
+/// Mock locator with the default directory layout rooted at `harness`.
+///
+/// Configuration files live below "config", repositories below "repos", and
+/// state below "state".
+pub fn mock_locator(harness: &FixtureHarness) -> MockLocator {
+    let root = harness.as_path();
+    let mut locator = MockLocator::new();
+    locator.expect_config_dir().return_const(root.join("config"));
+    locator.expect_hooks_dir().return_const(root.join("config/hooks"));
+    locator.expect_hooks_config().return_const(root.join("config/hooks.toml"));
+    locator.expect_repos_dir().return_const(root.join("repos"));
+    locator.expect_repos_config().return_const(root.join("config/repos.toml"));
+    locator.expect_ricer_config().return_const(root.join("config/config.toml"));
+    locator.expect_state_dir().return_const(root.join("state"));
+    locator.expect_logs_dir().return_const(root.join("state/logs"));
+    locator.expect_lock_file().return_const(root.join("config/ricer.lock"));
+    locator.expect_templates_dir().return_const(root.join("config/templates"));
+    locator.expect_ignores_dir().return_const(root.join("config/ignores"));
+    locator
+}
+
+/// Parse `args` of `command` into its context, as `ricer <command> <args>...`
+/// would.
+pub fn parse_ctx<C: From<Cli>>(command: &str, args: &[&str]) -> Result<C> {
+    let args = ["ricer", command].into_iter().chain(args.iter().copied());
+    Ok(C::from(Cli::parse_args(args)?))
+}
@@ -0,0 +1,228 @@
+// SPDX-FileCopyrightText: 2024 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+//! Command phase timings.
+//!
+//! Record how long each phase of a command takes, e.g., loading configuration
+//! files, fetching a given repository, or running hooks. Timings are gathered
+//! through the event bus in two ways:
+//!
+//! 1. [`TimingSubscriber`] measures phases that commands announce through
+//!    [`Event::PhaseStarted`] and [`Event::PhaseFinished`].
+//! 1. [`Timed`] measures how long another subscriber takes to handle each
+//!    event, e.g., how long hooks take to run.
+//!
+//! All timings are collected into a shared [`Timings`] record, which the user
+//! can view through the `--timings` flag.
+
+use crate::ops::{Event, Subscriber, SubscriberError};
+
+use std::{
+    cell::RefCell,
+    fmt,
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+/// Shared record of command phase durations.
+///
+/// Cloning yields another handle to the same record.
+#[derive(Debug, Clone)]
+pub struct Timings {
+    started: Instant,
+    phases: Rc<RefCell<Vec<(String, Duration)>>>,
+}
+
+impl Timings {
+    pub fn new() -> Self {
+        Self { started: Instant::now(), phases: Default::default() }
+    }
+
+    /// Record `duration` of `phase`.
+    pub fn record(&self, phase: impl Into<String>, duration: Duration) {
+        self.phases.borrow_mut().push((phase.into(), duration));
+    }
+
+    /// Record how long `job` takes as `phase`.
+    pub fn time<T>(&self, phase: impl Into<String>, job: impl FnOnce() -> T) -> T {
+        let start = Instant::now();
+        let result = job();
+        self.record(phase, start.elapsed());
+        result
+    }
+
+    /// Phases in the order they finished.
+    pub fn phases(&self) -> Vec<(String, Duration)> {
+        self.phases.borrow().clone()
+    }
+
+    /// Time since record was made.
+    pub fn total(&self) -> Duration {
+        self.started.elapsed()
+    }
+}
+
+impl Default for Timings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Display for Timings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let phases = self.phases();
+        let width = phases.iter().map(|(phase, _)| phase.len()).max().unwrap_or(0).max(5);
+        writeln!(f, "{:<width$}  {:>10}", "Phase", "Time")?;
+        for (phase, duration) in &phases {
+            writeln!(f, "{phase:<width$}  {:>10}", format!("{duration:.1?}"))?;
+        }
+        writeln!(f, "{:<width$}  {:>10}", "total", format!("{:.1?}", self.total()))
+    }
+}
+
+/// Measure phases announced through the event bus.
+///
+/// # Invariants
+///
+/// Phases that never finish are not recorded.
+#[derive(Debug)]
+pub struct TimingSubscriber {
+    timings: Timings,
+    open: Vec<(String, Instant)>,
+}
+
+impl TimingSubscriber {
+    pub fn new(timings: Timings) -> Self {
+        Self { timings, open: Vec::new() }
+    }
+}
+
+impl Subscriber for TimingSubscriber {
+    fn name(&self) -> &str {
+        "timing"
+    }
+
+    fn notify(&mut self, event: &Event<'_>) -> Result<(), SubscriberError> {
+        match event {
+            Event::PhaseStarted { phase, .. } => {
+                self.open.push((phase.to_string(), Instant::now()))
+            }
+            Event::PhaseFinished { phase, .. } => {
+                // INVARIANT: phases may nest, so finish most recent phase of same name.
+                if let Some(index) = self.open.iter().rposition(|(name, _)| name == phase) {
+                    let (name, start) = self.open.remove(index);
+                    self.timings.record(name, start.elapsed());
+                }
+            }
+            _ => (),
+        }
+
+        Ok(())
+    }
+}
+
+/// Measure how long a subscriber takes to handle each event.
+///
+/// Each measurement is recorded as `<subscriber> (<event>)`, e.g.,
+/// `hook (command started)`.
+#[derive(Debug)]
+pub struct Timed<S>
+where
+    S: Subscriber,
+{
+    inner: S,
+    timings: Timings,
+}
+
+impl<S> Timed<S>
+where
+    S: Subscriber,
+{
+    pub fn new(inner: S, timings: Timings) -> Self {
+        Self { inner, timings }
+    }
+}
+
+impl<S> Subscriber for Timed<S>
+where
+    S: Subscriber,
+{
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn notify(&mut self, event: &Event<'_>) -> Result<(), SubscriberError> {
+        let start = Instant::now();
+        let result = self.inner.notify(event);
+        self.timings.record(format!("{} ({event})", self.inner.name()), start.elapsed());
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{cli::Cli, context::Context, ops::EventBus};
+
+    use anyhow::Result;
+    use pretty_assertions::assert_eq;
+    use std::thread::sleep;
+
+    #[derive(Debug)]
+    struct SlowSubscriber;
+
+    impl Subscriber for SlowSubscriber {
+        fn name(&self) -> &str {
+            "slow"
+        }
+
+        fn notify(&mut self, _: &Event<'_>) -> Result<(), SubscriberError> {
+            sleep(Duration::from_millis(5));
+            Ok(())
+        }
+    }
+
+    fn names(timings: &Timings) -> Vec<String> {
+        timings.phases().into_iter().map(|(name, _)| name).collect()
+    }
+
+    #[test]
+    fn timing_subscriber_record_phases() -> Result<()> {
+        let timings = Timings::new();
+        let ctx = Context::from(Cli::parse_args(["ricer", "pull"])?);
+        let mut bus = EventBus::new().subscribe(TimingSubscriber::new(timings.clone()));
+        bus.emit(Event::PhaseStarted { context: &ctx, phase: "fetch 'vim'" })?;
+        bus.emit(Event::PhaseStarted { context: &ctx, phase: "merge 'vim'" })?;
+        bus.emit(Event::PhaseFinished { context: &ctx, phase: "merge 'vim'" })?;
+        bus.emit(Event::PhaseFinished { context: &ctx, phase: "fetch 'vim'" })?;
+        bus.emit(Event::PhaseStarted { context: &ctx, phase: "unfinished" })?;
+        assert_eq!(names(&timings), ["merge 'vim'", "fetch 'vim'"]);
+        Ok(())
+    }
+
+    #[test]
+    fn timed_record_each_event() -> Result<()> {
+        let timings = Timings::new();
+        let ctx = Context::from(Cli::parse_args(["ricer", "pull"])?);
+        let mut bus = EventBus::new().subscribe(Timed::new(SlowSubscriber, timings.clone()));
+        bus.emit(Event::CommandStarted { context: &ctx })?;
+        bus.emit(Event::CommandFinished { context: &ctx, success: true })?;
+        assert_eq!(names(&timings), ["slow (command started)", "slow (command finished)"]);
+        assert!(timings.phases().iter().all(|(_, time)| *time >= Duration::from_millis(5)));
+        Ok(())
+    }
+
+    #[test]
+    fn timings_display_table() {
+        let timings = Timings::new();
+        timings.record("config load", Duration::from_micros(1500));
+        timings.time("hooks", || ());
+        let table = timings.to_string();
+        let lines = table.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 4);
+        assert!(lines[0].starts_with("Phase"));
+        assert_eq!(lines[1], "config load       1.5ms");
+        assert!(lines[2].starts_with("hooks"));
+        assert!(lines[3].starts_with("total"));
+    }
+}
@@ -0,0 +1,713 @@
+// SPDX-FileCopyrightText: 2024 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+//! Terminal output.
+//!
+//! Commands write their reports through [`Output`] instead of printing to
+//! standard output directly. Once a command is done, output that does not fit
+//! into the terminal is paged through [Minus][minus] automatically, just like
+//! Git does. Paging can be turned off for one invocation through `--no-pager`,
+//! or permanently through Ricer's general configuration file:
+//!
+//! ```toml
+//! [ui]
+//! pager = "never"
+//! ```
+//!
+//! Output can also be colored through [`Output::paint`]. Colors are only used
+//! when writing to a terminal, and can be turned off through the `NO_COLOR`
+//! environment variable.
+//!
+//! Errors that stop a command are rendered through [`ErrorReport`], which
+//! lists every cause of the error, along with what failing hooks printed to
+//! standard error.
+//!
+//! Interactive flows never read standard input directly. They ask the user
+//! through a [`Prompter`] instead, which is [`TerminalPrompter`] for real
+//! invocations, so tests can answer on behalf of the user.
+//!
+//! [minus]: https://docs.rs/minus/latest/minus/
+
+use crate::{
+    cancel,
+    config::{PagerMode, UiSettings},
+    context::Context,
+    hook::{CmdHookError, HookPager, HookPagerError},
+};
+
+use crossterm::terminal;
+use log::warn;
+use minus::{error::MinusError, page_all, ExitStrategy, Pager};
+use std::{
+    env,
+    error::Error,
+    fmt,
+    io::{self, BufRead, Error as IoError, IsTerminal, Read, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+/// Error types for [`Output`].
+#[derive(Debug, thiserror::Error)]
+pub enum UiError {
+    #[error("Minus pager failed because '{source}'")]
+    Pager { source: MinusError },
+
+    #[error("Failed to write command output")]
+    Write { source: IoError },
+
+    #[error("Failed to read answer of user")]
+    Read { source: IoError },
+}
+
+impl From<MinusError> for UiError {
+    fn from(err: MinusError) -> Self {
+        UiError::Pager { source: err }
+    }
+}
+
+impl From<HookPagerError> for UiError {
+    fn from(err: HookPagerError) -> Self {
+        match err {
+            HookPagerError::Minus { source } => UiError::Pager { source },
+            HookPagerError::Read { source } => UiError::Read { source },
+        }
+    }
+}
+
+/// Determine pager mode of command.
+///
+/// The `--no-pager` flag takes precedence over configured pager mode.
+pub fn pager_mode(context: &Context, settings: &UiSettings) -> PagerMode {
+    match context.shared() {
+        Some(shared) if shared.no_pager => PagerMode::Never,
+        _ => settings.pager,
+    }
+}
+
+/// Text styles for colored output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Style {
+    Bold,
+    Dim,
+    Red,
+    Green,
+    Yellow,
+    Cyan,
+}
+
+impl Style {
+    fn ansi_code(&self) -> &'static str {
+        match self {
+            Style::Bold => "1",
+            Style::Dim => "2",
+            Style::Red => "31",
+            Style::Green => "32",
+            Style::Yellow => "33",
+            Style::Cyan => "36",
+        }
+    }
+}
+
+/// Command output writer.
+///
+/// Buffers all command output, and decides whether to page it or to print it
+/// as-is once [`Output::finish`] is called.
+///
+/// # Invariants
+///
+/// 1. Output is never paged nor colored if standard output is not a terminal.
+/// 1. Output is only paged automatically if it does not fit into the
+///    terminal.
+#[derive(Debug)]
+pub struct Output {
+    buffer: String,
+    pager: PagerMode,
+    terminal: Option<(u16, u16)>,
+    color: bool,
+    stderr: bool,
+}
+
+impl Output {
+    /// Construct new writer for standard output.
+    ///
+    /// Terminal size and color support are detected from standard output.
+    pub fn new(pager: PagerMode) -> Self {
+        let terminal = match io::stdout().is_terminal() {
+            true => terminal::size().ok(),
+            false => None,
+        };
+        let color = terminal.is_some()
+            && env::var_os("NO_COLOR").map_or(true, |value| value.is_empty())
+            && env::var_os("TERM").map_or(true, |term| term != "dumb");
+
+        Self { buffer: String::new(), pager, terminal, color, stderr: false }
+    }
+
+    /// Construct new writer for standard error, which is never paged.
+    ///
+    /// Color support is detected from standard error, so reports about the
+    /// run itself, like timings, stay out of piped command output.
+    pub fn stderr() -> Self {
+        let color = io::stderr().is_terminal()
+            && env::var_os("NO_COLOR").map_or(true, |value| value.is_empty())
+            && env::var_os("TERM").map_or(true, |term| term != "dumb");
+
+        Self { buffer: String::new(), pager: PagerMode::Never, terminal: None, color, stderr: true }
+    }
+
+    /// Override detected terminal size as columns and rows.
+    ///
+    /// A size of `None` means that standard output is not a terminal.
+    pub fn terminal(mut self, size: Option<(u16, u16)>) -> Self {
+        self.terminal = size;
+        self
+    }
+
+    /// Override detected color support.
+    pub fn color(mut self, choice: bool) -> Self {
+        self.color = choice;
+        self
+    }
+
+    pub fn as_str(&self) -> &str {
+        self.buffer.as_str()
+    }
+
+    /// Apply `style` to `text` if colors are supported.
+    pub fn paint(&self, style: Style, text: impl fmt::Display) -> String {
+        match self.color {
+            true => format!("\x1b[{}m{text}\x1b[0m", style.ansi_code()),
+            false => text.to_string(),
+        }
+    }
+
+    /// Write unified `diff`, coloring added, removed, and header lines.
+    pub fn write_diff(&mut self, diff: &str) {
+        for line in diff.lines() {
+            let style = if line.starts_with("+++") || line.starts_with("---") {
+                Style::Bold
+            } else if line.starts_with('+') {
+                Style::Green
+            } else if line.starts_with('-') {
+                Style::Red
+            } else if line.starts_with("@@") {
+                Style::Cyan
+            } else {
+                Style::Dim
+            };
+            let line = self.paint(style, line);
+            self.buffer.push_str(&line);
+            self.buffer.push('\n');
+        }
+    }
+
+    /// Determine if buffered output should be paged.
+    pub fn should_page(&self) -> bool {
+        let (columns, rows) = match self.terminal {
+            Some(size) => size,
+            None => return false,
+        };
+
+        match self.pager {
+            PagerMode::Never => false,
+            PagerMode::Always => !self.buffer.is_empty(),
+            // INVARIANT: keep last terminal row free for the shell prompt.
+            PagerMode::Auto => display_rows(&self.buffer, columns) >= usize::from(rows),
+        }
+    }
+
+    /// Flush buffered output to pager or standard output, or to standard
+    /// error for writers of [`Output::stderr`].
+    ///
+    /// # Errors
+    ///
+    /// - Return [`UiError::Pager`] if output could not be paged.
+    /// - Return [`UiError::Write`] if output could not be written.
+    pub fn finish(self) -> Result<(), UiError> {
+        if self.stderr {
+            let mut stderr = io::stderr().lock();
+            return stderr
+                .write_all(self.buffer.as_bytes())
+                .and_then(|_| stderr.flush())
+                .map_err(|err| UiError::Write { source: err });
+        }
+
+        if self.should_page() {
+            let pager = Pager::new();
+            pager.set_run_no_overflow(self.pager == PagerMode::Always)?;
+            pager.push_str(&self.buffer)?;
+            pager.set_exit_strategy(ExitStrategy::PagerQuit)?;
+            page_all(pager)?;
+            return Ok(());
+        }
+
+        let mut stdout = io::stdout().lock();
+        stdout
+            .write_all(self.buffer.as_bytes())
+            .and_then(|_| stdout.flush())
+            .map_err(|err| UiError::Write { source: err })
+    }
+}
+
+impl fmt::Write for Output {
+    fn write_str(&mut self, text: &str) -> fmt::Result {
+        self.buffer.push_str(text);
+        Ok(())
+    }
+}
+
+/// Ask user a yes or no `question` on standard error, reading answer from
+/// `input`.
+///
+/// Only "y" or "yes" count as a yes, ignoring case. Anything else, including
+/// no answer at all or cancelling, counts as a no.
+///
+/// # Errors
+///
+/// - Return [`UiError::Write`] if question cannot be written.
+/// - Return [`UiError::Read`] if answer cannot be read.
+pub fn confirm(question: &str, input: &mut impl BufRead) -> Result<bool, UiError> {
+    let answer = ask(&format!("{question} [y/N]"), input)?;
+    Ok(answer.is_some_and(|answer| is_yes(&answer)))
+}
+
+fn is_yes(answer: &str) -> bool {
+    answer.eq_ignore_ascii_case("y") || answer.eq_ignore_ascii_case("yes")
+}
+
+/// Ask user `question` on standard error, reading answer from `input`.
+///
+/// Will return answer without surrounding whitespace, or `None` if input
+/// ended before an answer was given, or the command was cancelled.
+///
+/// # Errors
+///
+/// - Return [`UiError::Write`] if question cannot be written.
+/// - Return [`UiError::Read`] if answer cannot be read.
+pub fn ask(question: &str, input: &mut impl BufRead) -> Result<Option<String>, UiError> {
+    let mut stderr = io::stderr().lock();
+    write!(stderr, "{question} ")
+        .and_then(|_| stderr.flush())
+        .map_err(|err| UiError::Write { source: err })?;
+
+    let mut answer = String::new();
+    let read = input.read_line(&mut answer).map_err(|err| UiError::Read { source: err })?;
+    match read == 0 || cancel::is_cancelled() {
+        true => Ok(None),
+        false => Ok(Some(answer.trim().into())),
+    }
+}
+
+/// Ask the user questions on behalf of a command.
+///
+/// Implementations decide where questions go and where answers come from,
+/// e.g., [`TerminalPrompter`] asks through the terminal.
+pub trait Prompter {
+    /// Ask user `question`.
+    ///
+    /// Will return answer without surrounding whitespace, or `None` if no
+    /// answer was given, e.g., input ended or the command was cancelled.
+    ///
+    /// # Errors
+    ///
+    /// - Return [`UiError::Write`] if question cannot be written.
+    /// - Return [`UiError::Read`] if answer cannot be read.
+    fn ask(&self, question: &str) -> Result<Option<String>, UiError>;
+
+    /// Ask user `question` whose answer is secret, e.g., a passphrase.
+    ///
+    /// Answers are not echoed where possible, and are returned as-is.
+    ///
+    /// # Errors
+    ///
+    /// - Return [`UiError::Write`] if question cannot be written.
+    /// - Return [`UiError::Read`] if answer cannot be read.
+    fn ask_secret(&self, question: &str) -> Result<Option<String>, UiError> {
+        self.ask(question)
+    }
+
+    /// Show unified `diff` to user before asking about it.
+    ///
+    /// # Errors
+    ///
+    /// - Return [`UiError::Write`] if diff cannot be written.
+    fn show_diff(&self, diff: &str) -> Result<(), UiError>;
+
+    /// Show `items` listed under `heading` to user before asking about them.
+    ///
+    /// # Errors
+    ///
+    /// - Return [`UiError::Pager`] if list cannot be paged.
+    /// - Return [`UiError::Write`] if list cannot be written.
+    fn show_list(&self, heading: &str, items: &[String]) -> Result<(), UiError>;
+
+    /// Show hook `script` read from `data` to user, and ask whether to run it
+    /// at `workdir`.
+    ///
+    /// # Errors
+    ///
+    /// - Return [`UiError::Pager`] if hook script cannot be shown.
+    /// - Return [`UiError::Read`] if `data` cannot be read.
+    fn review_hook(
+        &self,
+        script: &Path,
+        workdir: Option<&Path>,
+        data: &mut dyn Read,
+    ) -> Result<bool, UiError>;
+
+    /// Ask user a yes or no `question`.
+    ///
+    /// Only "y" or "yes" count as a yes, ignoring case, like [`confirm`].
+    ///
+    /// # Errors
+    ///
+    /// - Return [`UiError::Write`] if question cannot be written.
+    /// - Return [`UiError::Read`] if answer cannot be read.
+    fn confirm(&self, question: &str) -> Result<bool, UiError> {
+        let answer = self.ask(&format!("{question} [y/N]"))?;
+        Ok(answer.is_some_and(|answer| is_yes(&answer)))
+    }
+
+    /// Ask user `question` again until answer parses into a choice.
+    ///
+    /// Will return `None` if no answer was given.
+    ///
+    /// # Errors
+    ///
+    /// - Return [`UiError::Write`] if question cannot be written.
+    /// - Return [`UiError::Read`] if answer cannot be read.
+    fn choose<T>(&self, question: &str) -> Result<Option<T>, UiError>
+    where
+        T: FromStr<Err = String>,
+        Self: Sized,
+    {
+        loop {
+            let answer = match self.ask(question)? {
+                Some(answer) => answer,
+                None => return Ok(None),
+            };
+            match answer.parse() {
+                Ok(choice) => return Ok(Some(choice)),
+                Err(msg) => warn!("{msg}"),
+            }
+        }
+    }
+}
+
+/// Ask the user through the terminal.
+///
+/// Questions are written to standard error, and answers are read from
+/// standard input. Hook scripts are paged through [`HookPager`].
+#[derive(Debug, Default, Clone, Copy)]
+pub struct TerminalPrompter;
+
+impl Prompter for TerminalPrompter {
+    fn ask(&self, question: &str) -> Result<Option<String>, UiError> {
+        ask(question, &mut io::stdin().lock())
+    }
+
+    fn ask_secret(&self, question: &str) -> Result<Option<String>, UiError> {
+        if !io::stdin().is_terminal() {
+            return self.ask(question);
+        }
+
+        let mut stderr = io::stderr().lock();
+        write!(stderr, "{question} ")
+            .and_then(|_| stderr.flush())
+            .map_err(|err| UiError::Write { source: err })?;
+
+        // INVARIANT: raw mode turns echo off, and is always turned off again.
+        terminal::enable_raw_mode().map_err(|err| UiError::Read { source: err })?;
+        let answer = read_secret(&mut io::stdin().lock());
+        let restored = terminal::disable_raw_mode();
+        writeln!(stderr).map_err(|err| UiError::Write { source: err })?;
+        restored.map_err(|err| UiError::Read { source: err })?;
+
+        match answer.map_err(|err| UiError::Read { source: err })? {
+            Some(_) if cancel::is_cancelled() => Ok(None),
+            answer => Ok(answer),
+        }
+    }
+
+    fn show_diff(&self, diff: &str) -> Result<(), UiError> {
+        // INVARIANT: never page diff, so the question comes after it.
+        let mut preview = Output::new(PagerMode::Never);
+        preview.write_diff(diff);
+        preview.finish()
+    }
+
+    fn show_list(&self, heading: &str, items: &[String]) -> Result<(), UiError> {
+        // INVARIANT: long lists are paged, and the question comes once the pager quits.
+        let mut list = Output::new(PagerMode::Auto);
+        list.buffer.push_str(&format!("{heading}\n"));
+        for item in items {
+            list.buffer.push_str(&format!("  {item}\n"));
+        }
+        list.finish()
+    }
+
+    fn review_hook(
+        &self,
+        script: &Path,
+        workdir: Option<&Path>,
+        data: &mut dyn Read,
+    ) -> Result<bool, UiError> {
+        let pager = HookPager::new();
+        pager.page_and_prompt(script, &workdir.map(PathBuf::from), data)?;
+        Ok(pager.choice())
+    }
+}
+
+/// Read secret answer from `input` in raw mode, up to the end of the line.
+///
+/// Will return `None` if input ended, or the user pressed Ctrl-C.
+fn read_secret(input: &mut impl BufRead) -> Result<Option<String>, IoError> {
+    let mut answer = Vec::new();
+    for byte in input.bytes() {
+        match byte? {
+            b'\r' | b'\n' => return Ok(Some(String::from_utf8_lossy(&answer).into_owned())),
+            0x03 | 0x04 => return Ok(None),
+            0x7f | 0x08 => {
+                answer.pop();
+            }
+            byte => answer.push(byte),
+        }
+    }
+
+    Ok(None)
+}
+
+/// Human readable report of error that stopped a command.
+///
+/// Lists error followed by each of its causes. Causes that are failed hooks
+/// also show the tail of what the hook wrote to standard error, so the user
+/// can tell why it failed without rerunning it.
+#[derive(Debug)]
+pub struct ErrorReport<'err> {
+    error: &'err (dyn Error + 'static),
+}
+
+impl<'err> ErrorReport<'err> {
+    pub fn new(error: &'err (dyn Error + 'static)) -> Self {
+        Self { error }
+    }
+}
+
+impl fmt::Display for ErrorReport<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut error = self.error;
+        write!(f, "{error}")?;
+        loop {
+            if let Some(stderr) =
+                error.downcast_ref::<CmdHookError>().and_then(CmdHookError::stderr)
+            {
+                write!(f, "\n  hook stderr:")?;
+                for line in stderr.lines() {
+                    write!(f, "\n    | {line}")?;
+                }
+            }
+
+            match error.source() {
+                Some(cause) => {
+                    write!(f, "\n  caused by: {cause}")?;
+                    error = cause;
+                }
+                None => return Ok(()),
+            }
+        }
+    }
+}
+
+/// Count terminal rows needed to display `text` with line wrapping.
+fn display_rows(text: &str, columns: u16) -> usize {
+    let columns = usize::from(columns.max(1));
+    text.lines().map(|line| visible_width(line).div_ceil(columns).max(1)).sum()
+}
+
+/// Count characters of `line` that are not part of ANSI escape sequences.
+fn visible_width(line: &str) -> usize {
+    let mut width = 0;
+    let mut escape = false;
+    for ch in line.chars() {
+        match (escape, ch) {
+            (false, '\x1b') => escape = true,
+            (true, 'm') => escape = false,
+            (true, _) => (),
+            (false, _) => width += 1,
+        }
+    }
+    width
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        add::HunkChoice,
+        cli::Cli,
+        hook::{HookId, HookKind},
+        testenv::ScriptedPrompter,
+    };
+
+    use anyhow::Result;
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+    use std::fmt::Write as _;
+
+    fn output(pager: PagerMode, terminal: Option<(u16, u16)>, lines: usize) -> Result<Output> {
+        let mut out = Output::new(pager).terminal(terminal).color(false);
+        for line in 0..lines {
+            writeln!(out, "line {line}")?;
+        }
+        Ok(out)
+    }
+
+    #[rstest]
+    #[case::auto_fits(PagerMode::Auto, Some((80, 24)), 10, false)]
+    #[case::auto_overflows(PagerMode::Auto, Some((80, 24)), 30, true)]
+    #[case::always(PagerMode::Always, Some((80, 24)), 1, true)]
+    #[case::always_empty(PagerMode::Always, Some((80, 24)), 0, false)]
+    #[case::never(PagerMode::Never, Some((80, 24)), 30, false)]
+    #[case::not_terminal(PagerMode::Always, None, 30, false)]
+    fn output_should_page(
+        #[case] pager: PagerMode,
+        #[case] terminal: Option<(u16, u16)>,
+        #[case] lines: usize,
+        #[case] expect: bool,
+    ) -> Result<()> {
+        assert_eq!(output(pager, terminal, lines)?.should_page(), expect);
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::plain("hello\nworld\n", 80, 2)]
+    #[case::wrapped("0123456789\n", 4, 3)]
+    #[case::empty_line("a\n\nb\n", 80, 3)]
+    #[case::ansi("\x1b[1m0123\x1b[0m\n", 4, 1)]
+    fn display_rows_count_wrapped_lines(
+        #[case] text: &str,
+        #[case] columns: u16,
+        #[case] expect: usize,
+    ) {
+        assert_eq!(display_rows(text, columns), expect);
+    }
+
+    #[rstest]
+    #[case::color(true, "\x1b[32mok\x1b[0m")]
+    #[case::no_color(false, "ok")]
+    fn output_paint_return_styled(#[case] color: bool, #[case] expect: &str) {
+        let out = Output::new(PagerMode::Auto).color(color);
+        assert_eq!(out.paint(Style::Green, "ok"), expect);
+    }
+
+    #[rstest]
+    fn output_write_diff_color_lines() {
+        let mut out = Output::new(PagerMode::Never).color(true);
+        out.write_diff("@@ -1 +1 @@\n-old\n+new\n");
+        assert_eq!(
+            out.as_str(),
+            "\x1b[36m@@ -1 +1 @@\x1b[0m\n\x1b[31m-old\x1b[0m\n\x1b[32m+new\x1b[0m\n"
+        );
+    }
+
+    #[rstest]
+    #[case::configured(vec!["ricer", "env"], PagerMode::Always, PagerMode::Always)]
+    #[case::no_pager(vec!["ricer", "--no-pager", "env"], PagerMode::Always, PagerMode::Never)]
+    fn pager_mode_return_mode(
+        #[case] args: Vec<&str>,
+        #[case] configured: PagerMode,
+        #[case] expect: PagerMode,
+    ) -> Result<()> {
+        let ctx = Context::from(Cli::parse_args(args)?);
+        let result = pager_mode(&ctx, &UiSettings::new().pager(configured));
+        assert_eq!(result, expect);
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::yes("y\n", true)]
+    #[case::yes_long("Yes\n", true)]
+    #[case::no("n\n", false)]
+    #[case::empty("\n", false)]
+    #[case::eof("", false)]
+    fn confirm_return_answer(#[case] input: &str, #[case] expect: bool) -> Result<()> {
+        assert_eq!(confirm("Write?", &mut input.as_bytes())?, expect);
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::answer(" q \n", Some("q"))]
+    #[case::empty("\n", Some(""))]
+    #[case::eof("", None)]
+    fn ask_return_trimmed_answer(#[case] input: &str, #[case] expect: Option<&str>) -> Result<()> {
+        assert_eq!(ask("Stage?", &mut input.as_bytes())?.as_deref(), expect);
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::carriage_return(" pass \r", Some(" pass "))]
+    #[case::backspace("passs\x7f\n", Some("pass"))]
+    #[case::ctrl_c("pa\x03ss\r", None)]
+    #[case::eof("pass", None)]
+    fn read_secret_return_raw_answer(
+        #[case] input: &str,
+        #[case] expect: Option<&str>,
+    ) -> Result<()> {
+        assert_eq!(read_secret(&mut input.as_bytes())?.as_deref(), expect);
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::yes(vec!["yes"], true)]
+    #[case::no(vec!["n"], false)]
+    #[case::no_answer(vec![], false)]
+    fn prompter_confirm_return_answer(
+        #[case] answers: Vec<&str>,
+        #[case] expect: bool,
+    ) -> Result<()> {
+        let prompter = ScriptedPrompter::new(answers);
+        assert_eq!(prompter.confirm("Write?")?, expect);
+        assert_eq!(prompter.questions(), ["Write? [y/N]"]);
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::valid(vec!["y"], Some(HunkChoice::Stage), 1)]
+    #[case::retry_invalid(vec!["maybe", "", "q"], Some(HunkChoice::Quit), 3)]
+    #[case::no_answer(vec!["maybe"], None, 2)]
+    fn prompter_choose_ask_until_valid(
+        #[case] answers: Vec<&str>,
+        #[case] expect: Option<HunkChoice>,
+        #[case] asked: usize,
+    ) -> Result<()> {
+        let prompter = ScriptedPrompter::new(answers);
+        assert_eq!(prompter.choose("Stage?")?, expect);
+        assert_eq!(prompter.questions().len(), asked);
+        Ok(())
+    }
+
+    #[rstest]
+    fn error_report_show_causes_and_hook_stderr() {
+        let hook = HookId {
+            cmd: "commit".into(),
+            kind: HookKind::Pre,
+            index: 1,
+            name: Some("lint".into()),
+            script: "/hooks/lint.sh".into(),
+        };
+        let err = CmdHookError::HookFailed {
+            hook: Box::new(hook),
+            code: 1,
+            stderr: "lint.sh: 2 warnings".into(),
+        };
+        let err = anyhow::Error::new(err).context("Subscriber 'hook' failed");
+        let expect = indoc! {"
+            Subscriber 'hook' failed
+              caused by: pre hook #2 'lint' of 'commit' ('/hooks/lint.sh') failed with exit code 1
+              hook stderr:
+                | lint.sh: 2 warnings"};
+        assert_eq!(ErrorReport::new(err.as_ref()).to_string(), expect);
+    }
+}
@@ -0,0 +1,21 @@
+// SPDX-FileCopyrightText: 2024 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+//! Utilities shared by subsystems of Ricer.
+//!
+//! Anything here is not tied to one command, but used by several of them,
+//! e.g., [`Workspace`] provides scratch space to any command that needs it,
+//! [`RepoSelector`] resolves repository patterns of batch commands, and
+//! [`RepoIter`] runs batch commands on the selected repositories one by one.
+
+mod iter;
+mod remove;
+mod select;
+mod size;
+mod workspace;
+
+pub use iter::*;
+pub use remove::*;
+pub use select::*;
+pub use size::*;
+pub use workspace::*;
@@ -0,0 +1,270 @@
+// SPDX-FileCopyrightText: 2024 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+use super::{RepoSelector, SelectError};
+use crate::{
+    config::{ConfigFile, ConfigFileError, RepoConfig, RepoSettings},
+    hook::PlanChanges,
+    locate::Locator,
+    vcs::{GitRepo, GitRepoError, RepoCache},
+};
+
+use log::info;
+use std::{error::Error, fmt, path::PathBuf, vec};
+
+/// Outcome of one repository visited by [`RepoIter::try_each`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RepoResult<T, E> {
+    /// Operation succeeded with the given result.
+    Done(T),
+
+    /// Repository does not exist in repository directory.
+    Missing,
+
+    /// Repository could not be opened, or operation failed on it.
+    Failed(E),
+}
+
+impl<T, E> RepoResult<T, E> {
+    pub fn is_failed(&self) -> bool {
+        matches!(self, RepoResult::Failed(_))
+    }
+}
+
+/// Display result, showing failures together with their causes.
+impl<T: fmt::Display, E: Error> fmt::Display for RepoResult<T, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RepoResult::Done(done) => write!(f, "{done}"),
+            RepoResult::Missing => write!(f, "not cloned"),
+            RepoResult::Failed(err) => {
+                write!(f, "failed: {err}")?;
+                let mut source = err.source();
+                while let Some(cause) = source {
+                    write!(f, ": {cause}")?;
+                    source = cause.source();
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Iterate over registered repositories of batch commands.
+///
+/// Loads repository configuration once, and yields the settings of every
+/// repository selected through a [`RepoSelector`] along with its Git
+/// directory. Through [`RepoIter::try_each`], an operation runs on every
+/// cloned repository, and its result is kept per repository, so one failing
+/// repository never stops the others.
+///
+/// # Invariants
+///
+/// 1. Repositories are visited in the order of the repository configuration
+///    file.
+/// 1. Repositories skipped through [`RepoIter::changes`] are never visited.
+#[derive(Debug)]
+pub struct RepoIter {
+    entries: vec::IntoIter<RepoSettings>,
+    repos_dir: PathBuf,
+    changes: PlanChanges,
+    cache: RepoCache,
+}
+
+impl RepoIter {
+    /// Load repositories of `locator` selected through `selector`, expanding
+    /// repository groups of configuration.
+    ///
+    /// # Errors
+    ///
+    /// - Return `E` from [`ConfigFileError`] if repository configuration
+    ///   cannot be read.
+    /// - Return `E` from [`SelectError`] if `selector` fails to select.
+    pub fn load<E>(locator: &impl Locator, selector: RepoSelector) -> Result<Self, E>
+    where
+        E: From<ConfigFileError> + From<SelectError>,
+    {
+        let config = ConfigFile::load(RepoConfig, locator)?;
+        let entries = selector.groups(config.groups()?).select(config.entries()?)?;
+        Ok(Self {
+            entries: entries.into_iter(),
+            repos_dir: locator.repos_dir().into(),
+            changes: PlanChanges::default(),
+            cache: RepoCache::new(),
+        })
+    }
+
+    /// Leave repositories skipped by plan `changes` of pre hooks alone.
+    pub fn changes(mut self, changes: &PlanChanges) -> Self {
+        self.changes = changes.clone();
+        self
+    }
+
+    /// Open repositories through `cache` instead.
+    pub fn cache(mut self, cache: RepoCache) -> Self {
+        self.cache = cache;
+        self
+    }
+
+    /// Run `op` on every cloned repository, keeping its result per
+    /// repository.
+    pub fn try_each<T, E>(
+        self,
+        mut op: impl FnMut(&RepoSettings, &GitRepo) -> Result<T, E>,
+    ) -> Vec<(RepoSettings, RepoResult<T, E>)>
+    where
+        E: From<GitRepoError>,
+    {
+        let cache = self.cache.clone();
+        self.map(|(settings, gitdir)| {
+            let result = match gitdir.exists() {
+                false => RepoResult::Missing,
+                true => {
+                    match cache.open(&gitdir).map_err(E::from).and_then(|repo| op(&settings, &repo))
+                    {
+                        Ok(done) => RepoResult::Done(done),
+                        Err(err) => RepoResult::Failed(err),
+                    }
+                }
+            };
+            (settings, result)
+        })
+        .collect()
+    }
+}
+
+impl Iterator for RepoIter {
+    type Item = (RepoSettings, PathBuf);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for settings in self.entries.by_ref() {
+            // INVARIANT: hooks can only narrow which repositories a command touches.
+            if self.changes.is_skipped(&settings.name) {
+                info!("Skip '{}', because a hook asked to", settings.name);
+                continue;
+            }
+
+            let gitdir = settings.gitdir(&self.repos_dir);
+            return Some((settings, gitdir));
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testenv::{mock_locator, FileKind, FixtureHarness};
+
+    use anyhow::Result;
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+    use rstest::{fixture, rstest};
+
+    #[derive(Debug, PartialEq, Eq, thiserror::Error)]
+    enum TestError {
+        #[error("config")]
+        Config,
+
+        #[error("select")]
+        Select,
+
+        #[error("repo")]
+        Repo,
+
+        #[error("{0}")]
+        Op(String),
+    }
+
+    impl From<ConfigFileError> for TestError {
+        fn from(_: ConfigFileError) -> Self {
+            TestError::Config
+        }
+    }
+
+    impl From<SelectError> for TestError {
+        fn from(_: SelectError) -> Self {
+            TestError::Select
+        }
+    }
+
+    impl From<GitRepoError> for TestError {
+        fn from(_: GitRepoError) -> Self {
+            TestError::Repo
+        }
+    }
+
+    #[fixture]
+    fn rice_dir() -> Result<FixtureHarness> {
+        let harness = FixtureHarness::open()?
+            .with_file("config/repos.toml", |fixture| {
+                fixture
+                    .with_data(indoc! {r#"
+                        [repos.vim]
+                        branch = "main"
+                        remote = "origin"
+                        workdir_home = false
+
+                        [repos.dwm]
+                        branch = "main"
+                        remote = "origin"
+                        workdir_home = false
+
+                        [repos.st]
+                        branch = "main"
+                        remote = "origin"
+                        workdir_home = false
+                    "#})
+                    .with_kind(FileKind::Normal)
+            })
+            .with_repo("repos/vim", Ok)?
+            .with_repo("repos/dwm", Ok)?
+            .setup()?;
+        Ok(harness)
+    }
+
+    #[rstest]
+    fn repo_iter_try_each_keep_going_after_failure(rice_dir: Result<FixtureHarness>) -> Result<()> {
+        let rice_dir = rice_dir?;
+        let iter: RepoIter =
+            RepoIter::load::<TestError>(&mock_locator(&rice_dir), RepoSelector::default())?;
+        let results = iter.try_each(|settings, _| match settings.name.as_str() {
+            "vim" => Err(TestError::Op("boom".into())),
+            name => Ok(name.to_uppercase()),
+        });
+
+        let results = results
+            .into_iter()
+            .map(|(settings, result)| (settings.name, result))
+            .collect::<Vec<_>>();
+        let expect = vec![
+            ("vim".to_string(), RepoResult::Failed(TestError::Op("boom".into()))),
+            ("dwm".to_string(), RepoResult::Done("DWM".to_string())),
+            ("st".to_string(), RepoResult::Missing),
+        ];
+        assert_eq!(results, expect);
+        Ok(())
+    }
+
+    #[rstest]
+    fn repo_iter_next_leave_skipped_repos_alone(rice_dir: Result<FixtureHarness>) -> Result<()> {
+        let rice_dir = rice_dir?;
+        let changes = PlanChanges { skip_repos: vec!["dwm".into()], ..Default::default() };
+        let selector = RepoSelector::new(&["vim".into(), "dwm".into()]);
+        let iter =
+            RepoIter::load::<TestError>(&mock_locator(&rice_dir), selector)?.changes(&changes);
+        let names = iter.map(|(settings, _)| settings.name).collect::<Vec<_>>();
+        assert_eq!(names, ["vim"]);
+        Ok(())
+    }
+
+    #[rstest]
+    fn repo_iter_load_return_err_select(rice_dir: Result<FixtureHarness>) -> Result<()> {
+        let rice_dir = rice_dir?;
+        let selector = RepoSelector::new(&["nope".into()]);
+        let result = RepoIter::load::<TestError>(&mock_locator(&rice_dir), selector);
+        assert_eq!(result.unwrap_err(), TestError::Select);
+        Ok(())
+    }
+}
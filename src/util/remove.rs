@@ -0,0 +1,54 @@
+// SPDX-FileCopyrightText: 2024 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// Remove file at `path` relative to `root`, along with every directory it
+/// leaves empty on the way up to `root`.
+///
+/// # Invariants
+///
+/// 1. `root` itself is never removed, nor are directories that still hold
+///    anything.
+///
+/// # Errors
+///
+/// - Return [`io::Error`] if file cannot be removed.
+pub fn remove_file_pruning(root: &Path, path: &Path) -> io::Result<PathBuf> {
+    let file = root.join(path);
+    fs::remove_file(&file)?;
+    for dir in path.ancestors().skip(1).take_while(|dir| !dir.as_os_str().is_empty()) {
+        if fs::remove_dir(root.join(dir)).is_err() {
+            break;
+        }
+    }
+
+    Ok(file)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testenv::FixtureHarness;
+
+    use anyhow::Result;
+    use rstest::rstest;
+
+    #[rstest]
+    fn remove_file_pruning_keep_root_and_used_dirs() -> Result<()> {
+        let root = FixtureHarness::open()?;
+        let root = root.as_path();
+        fs::create_dir_all(root.join("a/b/c"))?;
+        fs::write(root.join("a/b/c/file"), "remove me")?;
+        fs::write(root.join("a/keep"), "keep me")?;
+
+        remove_file_pruning(root, Path::new("a/b/c/file"))?;
+        assert!(!root.join("a/b").exists());
+        assert!(root.join("a/keep").exists());
+        assert!(root.exists());
+        Ok(())
+    }
+}
@@ -0,0 +1,288 @@
+// SPDX-FileCopyrightText: 2024 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+use crate::config::{RepoGroup, RepoSettings};
+
+use glob::{Pattern, PatternError};
+use log::{debug, warn};
+
+/// Error types for [`RepoSelector`].
+#[derive(Debug, thiserror::Error)]
+pub enum SelectError {
+    #[error("Repository pattern '{pattern}' is invalid")]
+    BadPattern { source: PatternError, pattern: String },
+
+    #[error("Repository '{name}' is not registered")]
+    MissingRepo { name: String },
+
+    #[error("Repository pattern '{pattern}' matches no registered repository")]
+    NoMatch { pattern: String },
+
+    #[error("Repository group '{group}' contains itself through '{cycle}'")]
+    GroupCycle { group: String, cycle: String },
+
+    #[error("Member '{member}' of repository group '{group}' is not registered")]
+    MissingMember { group: String, member: String },
+}
+
+/// Select repositories by name or glob pattern.
+///
+/// Batch commands take any number of patterns, e.g., `ricer pull 'dwm*' st`,
+/// that are resolved against the names of registered repositories. A pattern
+/// without any of `*`, `?`, or `[` is a plain name, and must name a registered
+/// repository. A glob pattern may match any number of repositories.
+///
+/// A plain name may also name a [`RepoGroup`], e.g., `ricer pull desktop`,
+/// which selects every member of the group. Members are patterns themselves,
+/// and may name other groups.
+///
+/// # Invariants
+///
+/// 1. Selected repositories keep the order of the repository configuration
+///    file, and are selected once no matter how many patterns match them.
+/// 1. No patterns at all selects every repository.
+/// 1. Repositories shadow groups of the same name.
+#[derive(Debug, Default, Clone)]
+pub struct RepoSelector {
+    patterns: Vec<String>,
+    strict: bool,
+    groups: Vec<RepoGroup>,
+}
+
+impl RepoSelector {
+    /// Construct new selector of repositories matching any of `patterns`.
+    pub fn new(patterns: &[String]) -> Self {
+        Self { patterns: patterns.to_vec(), strict: false, groups: Vec::new() }
+    }
+
+    /// Expand names of `groups` into their members.
+    pub fn groups(mut self, groups: Vec<RepoGroup>) -> Self {
+        self.groups = groups;
+        self
+    }
+
+    /// Fail instead of warning when a glob pattern matches nothing, while
+    /// other patterns do.
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Check if no patterns were given, i.e., every repository is selected.
+    pub fn is_empty(&self) -> bool {
+        self.patterns.is_empty()
+    }
+
+    /// Select repositories of `repos` matching any pattern.
+    ///
+    /// # Errors
+    ///
+    /// - Return [`SelectError::BadPattern`] if a pattern is not a valid glob.
+    /// - Return [`SelectError::MissingRepo`] if a plain name is not
+    ///   registered.
+    /// - Return [`SelectError::NoMatch`] if no repository was selected at all,
+    ///   or if strict and a glob pattern matches nothing.
+    /// - Return [`SelectError::GroupCycle`] if a group contains itself.
+    /// - Return [`SelectError::MissingMember`] if a plain name of a group
+    ///   member is neither registered nor a group.
+    pub fn select(&self, repos: Vec<RepoSettings>) -> Result<Vec<RepoSettings>, SelectError> {
+        if self.is_empty() {
+            return Ok(repos);
+        }
+
+        let mut expanded = Vec::new();
+        for pattern in &self.patterns {
+            self.expand(pattern, &repos, &mut Vec::new(), &mut expanded)?;
+        }
+        if expanded.is_empty() {
+            return Err(SelectError::NoMatch { pattern: self.patterns.join(", ") });
+        }
+
+        let mut patterns = Vec::new();
+        for pattern in &expanded {
+            let glob = Pattern::new(pattern)
+                .map_err(|err| SelectError::BadPattern { source: err, pattern: pattern.clone() })?;
+            patterns.push((pattern, glob, false));
+        }
+
+        let mut selected = Vec::new();
+        for settings in repos {
+            let mut matched = false;
+            for (_, glob, hit) in patterns.iter_mut() {
+                if glob.matches(&settings.name) {
+                    *hit = true;
+                    matched = true;
+                }
+            }
+            if matched {
+                selected.push(settings);
+            }
+        }
+
+        let unmatched = patterns.iter().filter(|(_, _, hit)| !hit).map(|(pattern, _, _)| *pattern);
+        for pattern in unmatched {
+            if !is_glob(pattern) {
+                return Err(SelectError::MissingRepo { name: pattern.clone() });
+            }
+            if self.strict || selected.is_empty() {
+                return Err(SelectError::NoMatch { pattern: pattern.clone() });
+            }
+            warn!("Repository pattern '{pattern}' matches no registered repository");
+        }
+
+        debug!("Selected {} repositories through {:?}", selected.len(), self.patterns);
+        Ok(selected)
+    }
+
+    /// Expand `pattern` into `expanded`, following groups through `trail`.
+    fn expand(
+        &self,
+        pattern: &str,
+        repos: &[RepoSettings],
+        trail: &mut Vec<String>,
+        expanded: &mut Vec<String>,
+    ) -> Result<(), SelectError> {
+        let is_repo = |name: &str| repos.iter().any(|settings| settings.name == name);
+        let group = match self.group(pattern) {
+            Some(group) if !is_repo(pattern) => group,
+            _ => {
+                expanded.push(pattern.into());
+                return Ok(());
+            }
+        };
+
+        if let Some(start) = trail.iter().position(|name| *name == group.name) {
+            let mut cycle = trail[start..].to_vec();
+            cycle.push(group.name.clone());
+            return Err(SelectError::GroupCycle {
+                group: group.name.clone(),
+                cycle: cycle.join(" -> "),
+            });
+        }
+
+        debug!("Expand repository group '{}' into {:?}", group.name, group.members);
+        trail.push(group.name.clone());
+        for member in &group.members {
+            if !is_glob(member) && !is_repo(member) && self.group(member).is_none() {
+                return Err(SelectError::MissingMember {
+                    group: group.name.clone(),
+                    member: member.clone(),
+                });
+            }
+            self.expand(member, repos, trail, expanded)?;
+        }
+        trail.pop();
+        Ok(())
+    }
+
+    fn group(&self, name: &str) -> Option<&RepoGroup> {
+        self.groups.iter().find(|group| group.name == name)
+    }
+}
+
+/// Check if `pattern` is a glob pattern rather than a plain name.
+pub(crate) fn is_glob(pattern: &str) -> bool {
+    pattern.contains(['*', '?', '['])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    fn repos() -> Vec<RepoSettings> {
+        ["dwm", "dmenu", "st", "vim"].into_iter().map(RepoSettings::new).collect()
+    }
+
+    #[rstest]
+    #[case::everything(&[], false, vec!["dwm", "dmenu", "st", "vim"])]
+    #[case::plain_name(&["st"], false, vec!["st"])]
+    #[case::glob(&["d*"], false, vec!["dwm", "dmenu"])]
+    #[case::config_order(&["vim", "d?m"], false, vec!["dwm", "vim"])]
+    #[case::select_once(&["d*", "dwm"], true, vec!["dwm", "dmenu"])]
+    #[case::partial_match(&["d*", "x*"], false, vec!["dwm", "dmenu"])]
+    #[case::char_class(&["[sv]*"], true, vec!["st", "vim"])]
+    fn repo_selector_select_return_matching(
+        #[case] patterns: &[&str],
+        #[case] strict: bool,
+        #[case] expect: Vec<&str>,
+    ) -> Result<(), SelectError> {
+        let patterns = patterns.iter().map(ToString::to_string).collect::<Vec<_>>();
+        let selected = RepoSelector::new(&patterns).strict(strict).select(repos())?;
+        let names = selected.iter().map(|settings| settings.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, expect);
+        Ok(())
+    }
+
+    fn groups() -> Vec<RepoGroup> {
+        vec![
+            RepoGroup::new("desktop").members(["dwm", "x11"]),
+            RepoGroup::new("x11").members(["st", "d*"]),
+            RepoGroup::new("vim").members(["dwm"]),
+            RepoGroup::new("loop").members(["vim", "spin"]),
+            RepoGroup::new("spin").members(["loop"]),
+            RepoGroup::new("broken").members(["st", "nope"]),
+            RepoGroup::new("empty"),
+        ]
+    }
+
+    #[rstest]
+    #[case::group(&["desktop"], vec!["dwm", "dmenu", "st"])]
+    #[case::group_and_name(&["vim", "x11"], vec!["dwm", "dmenu", "st", "vim"])]
+    #[case::repo_shadows_group(&["vim"], vec!["vim"])]
+    fn repo_selector_select_expand_groups(
+        #[case] patterns: &[&str],
+        #[case] expect: Vec<&str>,
+    ) -> Result<(), SelectError> {
+        let patterns = patterns.iter().map(ToString::to_string).collect::<Vec<_>>();
+        let selected = RepoSelector::new(&patterns).groups(groups()).select(repos())?;
+        let names = selected.iter().map(|settings| settings.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, expect);
+        Ok(())
+    }
+
+    #[rstest]
+    fn repo_selector_select_return_err_group_cycle() {
+        let patterns = vec!["spin".to_string()];
+        let result = RepoSelector::new(&patterns).groups(groups()).select(repos());
+        match result.unwrap_err() {
+            SelectError::GroupCycle { group, cycle } => {
+                assert_eq!(group, "spin");
+                assert_eq!(cycle, "spin -> loop -> spin");
+            }
+            err => panic!("Expected group cycle, found '{err}'"),
+        }
+    }
+
+    #[rstest]
+    #[case::missing_member(&["broken"])]
+    #[case::empty_group(&["empty"])]
+    fn repo_selector_select_return_err_bad_group(#[case] patterns: &[&str]) {
+        let patterns = patterns.iter().map(ToString::to_string).collect::<Vec<_>>();
+        let result = RepoSelector::new(&patterns).groups(groups()).select(repos());
+        match patterns[0].as_str() {
+            "broken" => assert!(matches!(
+                result.unwrap_err(),
+                SelectError::MissingMember { group, member } if group == "broken" && member == "nope"
+            )),
+            _ => assert!(matches!(result.unwrap_err(), SelectError::NoMatch { .. })),
+        }
+    }
+
+    #[rstest]
+    #[case::bad_pattern(&["[d"], false)]
+    #[case::missing_repo(&["d*", "nope"], false)]
+    #[case::no_match(&["x*"], false)]
+    #[case::strict_partial_match(&["d*", "x*"], true)]
+    fn repo_selector_select_return_err(#[case] patterns: &[&str], #[case] strict: bool) {
+        let patterns = patterns.iter().map(ToString::to_string).collect::<Vec<_>>();
+        let result = RepoSelector::new(&patterns).strict(strict).select(repos());
+        match patterns[patterns.len() - 1].as_str() {
+            "[d" => assert!(matches!(result.unwrap_err(), SelectError::BadPattern { .. })),
+            "nope" => assert!(matches!(result.unwrap_err(), SelectError::MissingRepo { .. })),
+            _ => assert!(matches!(result.unwrap_err(), SelectError::NoMatch { .. })),
+        }
+    }
+}
@@ -0,0 +1,33 @@
+// SPDX-FileCopyrightText: 2024 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+/// Format `bytes` with a binary unit, e.g., `1.5 MiB`.
+pub fn human_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{size:.1} {}", UNITS[unit])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::bytes(512, "512 B")]
+    #[case::kibibytes(1536, "1.5 KiB")]
+    #[case::gibibytes(3 * 1024 * 1024 * 1024, "3.0 GiB")]
+    fn human_size_use_binary_units(#[case] bytes: u64, #[case] expect: &str) {
+        assert_eq!(human_size(bytes), expect);
+    }
+}
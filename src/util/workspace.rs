@@ -0,0 +1,221 @@
+// SPDX-FileCopyrightText: 2024 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+use crate::locate::Locator;
+
+use log::{debug, warn};
+use mkdirp::mkdirp;
+use std::{
+    fs,
+    io::Error as IoError,
+    path::{Path, PathBuf},
+    process,
+    time::{Duration, SystemTime},
+};
+use tempfile::TempDir;
+
+/// Directory of state directory that workspaces are made in.
+pub const WORKSPACES_DIR: &str = "workspaces";
+
+/// Age after which workspaces of other processes count as left behind, on
+/// systems where Ricer cannot tell whether their process is still running.
+const STALE_AFTER: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Error types for [`Workspace`].
+#[derive(Debug, thiserror::Error)]
+pub enum WorkspaceError {
+    #[error("Workspace namespace '{namespace}' must only use letters, digits, '-', or '_'")]
+    BadNamespace { namespace: String },
+
+    #[error("Failed to make workspace directory '{path}'")]
+    MakeDir { source: IoError, path: PathBuf },
+}
+
+/// Scratch directory of a command.
+///
+/// Workspaces are made in [`WORKSPACES_DIR`] of the state directory, and are
+/// namespaced by the subsystem that uses them, e.g., `export`. Every workspace
+/// gets a directory of its own named `<namespace>-<pid>-<random>`, so any
+/// number of commands, or threads of one command, can use the same namespace
+/// at once without stepping on each other.
+///
+/// # Invariants
+///
+/// 1. Workspace directory and everything in it is removed once the workspace
+///    is dropped, which includes commands unwinding after being cancelled
+///    through [`crate::cancel`].
+/// 1. Workspaces left behind by processes that were killed outright are
+///    removed the next time a workspace of the same namespace is made.
+#[derive(Debug)]
+pub struct Workspace {
+    namespace: String,
+    dir: TempDir,
+}
+
+impl Workspace {
+    /// Make new workspace in `namespace`.
+    ///
+    /// # Errors
+    ///
+    /// - Return [`WorkspaceError::BadNamespace`] if `namespace` is empty, or
+    ///   contains anything but letters, digits, `-`, or `_`.
+    /// - Return [`WorkspaceError::MakeDir`] if workspace directory cannot be
+    ///   made.
+    pub fn new(
+        locator: &impl Locator,
+        namespace: impl Into<String>,
+    ) -> Result<Self, WorkspaceError> {
+        let namespace = namespace.into();
+        let valid = |c: char| c.is_ascii_alphanumeric() || c == '-' || c == '_';
+        if namespace.is_empty() || !namespace.chars().all(valid) {
+            return Err(WorkspaceError::BadNamespace { namespace });
+        }
+
+        let root = locator.state_dir().join(WORKSPACES_DIR);
+        mkdirp(&root).map_err(|err| WorkspaceError::MakeDir { source: err, path: root.clone() })?;
+        sweep(&root, &namespace);
+
+        let prefix = format!("{namespace}-{}-", process::id());
+        let dir = tempfile::Builder::new()
+            .prefix(&prefix)
+            .tempdir_in(&root)
+            .map_err(|err| WorkspaceError::MakeDir { source: err, path: root.join(&prefix) })?;
+        debug!("Make workspace '{}'", dir.path().display());
+
+        Ok(Self { namespace, dir })
+    }
+
+    pub fn namespace(&self) -> &str {
+        &self.namespace
+    }
+
+    pub fn path(&self) -> &Path {
+        self.dir.path()
+    }
+
+    /// Get path of `path` inside workspace.
+    pub fn join(&self, path: impl AsRef<Path>) -> PathBuf {
+        self.dir.path().join(path)
+    }
+}
+
+/// Remove workspaces of `namespace` in `root` that were left behind.
+///
+/// Failing to remove a workspace only warns, because it never keeps a new
+/// workspace from being made.
+fn sweep(root: &Path, namespace: &str) {
+    let entries = match fs::read_dir(root) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+
+    for entry in entries.flatten() {
+        let name = entry.file_name();
+        let pid = name
+            .to_str()
+            .and_then(|name| name.strip_prefix(namespace)?.strip_prefix('-'))
+            .and_then(|rest| rest.split_once('-'))
+            .and_then(|(pid, _)| pid.parse::<u32>().ok());
+        let pid = match pid {
+            Some(pid) if pid != process::id() => pid,
+            _ => continue,
+        };
+
+        if is_stale(&entry.path(), pid) {
+            match fs::remove_dir_all(entry.path()) {
+                Ok(_) => debug!("Remove left behind workspace '{}'", entry.path().display()),
+                Err(err) => warn!(
+                    "Failed to remove left behind workspace '{}': {err}",
+                    entry.path().display()
+                ),
+            }
+        }
+    }
+}
+
+/// Check if file or directory at `path` made by process `pid` was left
+/// behind, e.g., because the process was killed.
+///
+/// # Invariants
+///
+/// Files of running processes are never considered left behind where `/proc`
+/// can tell that the process is running.
+pub(crate) fn is_stale(path: &Path, pid: u32) -> bool {
+    let proc = Path::new("/proc");
+    if proc.is_dir() {
+        return !proc.join(pid.to_string()).exists();
+    }
+
+    fs::metadata(path)
+        .and_then(|meta| meta.modified())
+        .ok()
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+        .is_some_and(|age| age > STALE_AFTER)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{locate::MockLocator, testenv::FixtureHarness};
+
+    use anyhow::Result;
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    fn locator(harness: &FixtureHarness) -> MockLocator {
+        let mut locator = MockLocator::new();
+        locator.expect_state_dir().return_const(harness.as_path().join("state"));
+        locator
+    }
+
+    #[rstest]
+    fn workspace_new_remove_dir_on_drop() -> Result<()> {
+        let harness = FixtureHarness::open()?;
+        let locator = locator(&harness);
+        let first = Workspace::new(&locator, "export")?;
+        let second = Workspace::new(&locator, "export")?;
+        fs::write(first.join("scratch.txt"), "scratch")?;
+
+        assert_ne!(first.path(), second.path());
+        assert!(first.path().starts_with(harness.as_path().join("state/workspaces")));
+        let name = first.path().file_name().unwrap().to_string_lossy().into_owned();
+        assert!(name.starts_with(&format!("export-{}-", process::id())));
+
+        let path = first.path().to_path_buf();
+        drop(first);
+        assert!(!path.exists());
+        assert!(second.path().exists());
+        Ok(())
+    }
+
+    #[rstest]
+    fn workspace_new_sweep_left_behind() -> Result<()> {
+        let harness = FixtureHarness::open()?;
+        let root = harness.as_path().join("state/workspaces");
+        // PIDs never get this large, so no running process owns these.
+        let left_behind = root.join(format!("export-{}-abc123", u32::MAX));
+        let other = root.join(format!("render-{}-abc123", u32::MAX));
+        fs::create_dir_all(&left_behind)?;
+        fs::create_dir_all(&other)?;
+
+        let workspace = Workspace::new(&locator(&harness), "export")?;
+        assert_eq!(workspace.namespace(), "export");
+        if Path::new("/proc").is_dir() {
+            assert!(!left_behind.exists());
+        }
+        assert!(other.exists());
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::empty("")]
+    #[case::separator("../escape")]
+    #[case::space("my space")]
+    fn workspace_new_return_err_bad_namespace(#[case] namespace: &str) -> Result<()> {
+        let harness = FixtureHarness::open()?;
+        let result = Workspace::new(&locator(&harness), namespace);
+        assert!(matches!(result.unwrap_err(), WorkspaceError::BadNamespace { .. }));
+        assert!(!harness.as_path().join("state").exists());
+        Ok(())
+    }
+}
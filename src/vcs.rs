@@ -1,12 +1,44 @@
 // SPDX-FileCopyrightText: 2024 Jason Pena <jasonpena@awkless.com>
 // SPDX-License-Identifier: MIT
 
+use crate::{
+    cancel::{self, CleanupGuard},
+    hostkey,
+    plan::{self, DryRunEffect},
+};
+
+mod credential;
+mod exclude;
+mod progress;
+
+pub use credential::*;
+pub use exclude::*;
+pub use progress::*;
+
+use directories::BaseDirs;
 use git2::{
-    build::CheckoutBuilder, AnnotatedCommit, AutotagOption, BranchType, Commit, Error as Git2Error,
-    FetchOptions, Oid, Reference, Remote, RemoteCallbacks, Repository, RepositoryInitOptions,
+    build::{CheckoutBuilder, RepoBuilder},
+    AnnotatedCommit, AutotagOption, BranchType, Commit, Delta, DiffOptions, Direction,
+    Error as Git2Error, ErrorCode, FetchOptions, Index, ObjectType, Oid, Patch, PushOptions,
+    Reference, Remote, RemoteCallbacks, Repository, RepositoryInitOptions, Sort, Status,
+    StatusOptions, StatusShow, TreeWalkMode, TreeWalkResult,
 };
-use log::info;
-use std::{ffi::OsStr, io::Error as IoError, path::Path, process::Command};
+use log::{debug, info};
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    ffi::OsStr,
+    fmt, fs,
+    io::Error as IoError,
+    path::{Path, PathBuf},
+    process::Command,
+    rc::Rc,
+    str::FromStr,
+    time::SystemTime,
+};
+
+/// Temporary reference to split history of [`GitRepo::split_into`].
+const SPLIT_REF: &str = "refs/ricer/split";
 
 pub struct GitRepo {
     repo: Repository,
@@ -21,8 +53,7 @@ impl GitRepo {
     ///
     /// - Return [`GitRepoError::LibGit2`] if repository cannot be created.
     pub fn init(path: impl AsRef<Path>) -> Result<Self, GitRepoError> {
-        let repo = Repository::init(format!("{}.git", path.as_ref().display()))?;
-        Ok(Self { repo })
+        Self::init_with(path, &RepoInit::default())
     }
 
     /// Create new Git repository that uses fake bare technique at `path`.
@@ -36,12 +67,29 @@ pub fn init_fake_bare(
         gitdir: impl AsRef<Path>,
         workdir: impl AsRef<Path>,
     ) -> Result<Self, GitRepoError> {
+        let init = RepoInit { workdir: Some(workdir.as_ref().into()), ..Default::default() };
+        Self::init_with(gitdir, &init)
+    }
+
+    /// Create new Git repository at `path` as described by [`RepoInit`].
+    ///
+    /// Will create any necessary directories to repository.
+    ///
+    /// # Errors
+    ///
+    /// - Return [`GitRepoError::LibGit2`] if repository cannot be created.
+    pub fn init_with(path: impl AsRef<Path>, init: &RepoInit) -> Result<Self, GitRepoError> {
         let mut opts = RepositoryInitOptions::new();
-        opts.bare(false);
-        opts.no_dotgit_dir(true);
-        opts.workdir_path(workdir.as_ref());
+        if let Some(branch) = &init.branch {
+            opts.initial_head(branch);
+        }
+        if let Some(workdir) = &init.workdir {
+            opts.bare(false);
+            opts.no_dotgit_dir(true);
+            opts.workdir_path(workdir);
+        }
 
-        let repo = Repository::init_opts(format!("{}.git", gitdir.as_ref().display()), &opts)?;
+        let repo = Repository::init_opts(format!("{}.git", path.as_ref().display()), &opts)?;
         Ok(Self { repo })
     }
 
@@ -59,11 +107,64 @@ pub fn open(path: impl AsRef<Path>) -> Result<Self, GitRepoError> {
 
     /// Clone existing Git repository from `url` into `path`.
     ///
+    /// # Invariants
+    ///
+    /// Partially cloned repositories are removed if cloning fails or is
+    /// cancelled.
+    ///
     /// # Errors
     ///
     /// - Return [`GitRepoError::LibGit2`] if repository cannot be cloned.
+    /// - Return [`GitRepoError::Cancelled`] if cloning was cancelled.
     pub fn clone(url: impl AsRef<str>, into: impl AsRef<Path>) -> Result<Self, GitRepoError> {
-        let repo = Repository::clone(url.as_ref(), format!("{}.git", into.as_ref().display()))?;
+        let path = PathBuf::from(format!("{}.git", into.as_ref().display()));
+        let guard = CleanupGuard::new(&path);
+        let mut opts = FetchOptions::new();
+        opts.remote_callbacks(remote_callbacks());
+        let repo = RepoBuilder::new()
+            .fetch_options(opts)
+            .clone(url.as_ref(), &path)
+            .map_err(cancellable)?;
+        guard.keep();
+        Ok(Self { repo })
+    }
+
+    /// Clone existing Git repository from `url` into `into` through the fake
+    /// bare technique, using `workdir` as its working directory.
+    ///
+    /// Nothing is checked out into `workdir`, so files that would be
+    /// overwritten can be found through [`GitRepo::conflicting_files`] first.
+    /// Check out files through [`GitRepo::force_checkout_head`] afterwards.
+    ///
+    /// # Invariants
+    ///
+    /// Partially cloned repositories are removed if cloning fails or is
+    /// cancelled.
+    ///
+    /// # Errors
+    ///
+    /// - Return [`GitRepoError::LibGit2`] if repository cannot be cloned.
+    /// - Return [`GitRepoError::Cancelled`] if cloning was cancelled.
+    pub fn clone_fake_bare(
+        url: impl AsRef<str>,
+        into: impl AsRef<Path>,
+        workdir: impl AsRef<Path>,
+    ) -> Result<Self, GitRepoError> {
+        let path = PathBuf::from(format!("{}.git", into.as_ref().display()));
+        let guard = CleanupGuard::new(&path);
+        let mut opts = FetchOptions::new();
+        opts.remote_callbacks(remote_callbacks());
+        let repo = RepoBuilder::new()
+            .bare(true)
+            .fetch_options(opts)
+            .clone(url.as_ref(), &path)
+            .map_err(cancellable)?;
+
+        let mut config = repo.config()?;
+        config.set_bool("core.bare", false)?;
+        config.set_str("core.worktree", &workdir.as_ref().to_string_lossy())?;
+        let repo = Repository::open(&path)?;
+        guard.keep();
         Ok(Self { repo })
     }
 
@@ -75,8 +176,22 @@ pub fn clone(url: impl AsRef<str>, into: impl AsRef<Path>) -> Result<Self, GitRe
     ///
     /// - Return [`GitRepoError::LibGit2`] if commit cannot be created.
     pub fn commit(&self, msg: impl AsRef<str>) -> Result<Oid, GitRepoError> {
-        let mut index = self.repo.index()?;
-        let tree_id = index.write_tree()?;
+        let tree_id = self.repo.index()?.write_tree()?;
+        self.commit_tree(msg, tree_id)
+    }
+
+    /// Commit `tree` on top of HEAD, leaving index as-is.
+    ///
+    /// Will return Git OID of commit, or of HEAD through `--dry-run`.
+    ///
+    /// # Errors
+    ///
+    /// - Return [`GitRepoError::LibGit2`] if commit cannot be created.
+    pub fn commit_tree(&self, msg: impl AsRef<str>, tree: Oid) -> Result<Oid, GitRepoError> {
+        if !self.perform(|| format!("Commit '{}'", summary(msg.as_ref()))) {
+            return Ok(self.head_commit()?.unwrap_or_else(Oid::zero));
+        }
+
         let sig = self.repo.signature()?;
         let mut parents = Vec::new();
 
@@ -90,13 +205,159 @@ pub fn commit(&self, msg: impl AsRef<str>) -> Result<Oid, GitRepoError> {
             &sig,
             &sig,
             msg.as_ref(),
-            &self.repo.find_tree(tree_id).expect("Failed to find tree"),
+            &self.repo.find_tree(tree)?,
             &parents,
         )?;
 
         Ok(oid)
     }
 
+    /// Amend latest commit with staged changes, replacing its message with
+    /// `msg` if given.
+    ///
+    /// # Errors
+    ///
+    /// - Return [`GitRepoError::LibGit2`] if latest commit cannot be amended,
+    ///   e.g., nothing was committed yet.
+    pub fn amend(&self, msg: Option<&str>) -> Result<Oid, GitRepoError> {
+        let tree_id = self.repo.index()?.write_tree()?;
+        self.amend_tree(msg, tree_id)
+    }
+
+    /// Replace tree of latest commit with `tree`, and its message with `msg`
+    /// if given, leaving index as-is.
+    ///
+    /// # Errors
+    ///
+    /// - Return [`GitRepoError::LibGit2`] if latest commit cannot be amended,
+    ///   e.g., nothing was committed yet.
+    pub fn amend_tree(&self, msg: Option<&str>, tree: Oid) -> Result<Oid, GitRepoError> {
+        let tree = self.repo.find_tree(tree)?;
+        let head = self.repo.head()?.peel_to_commit()?;
+        if !self.perform(|| "Amend latest commit".into()) {
+            return Ok(head.id());
+        }
+        Ok(head.amend(Some("HEAD"), None, None, None, msg, Some(&tree))?)
+    }
+
+    /// Write tree of HEAD with staged entries of `paths` swapped in, leaving
+    /// every other staged change out.
+    ///
+    /// Returns `None` if that tree does not differ from tree of HEAD.
+    ///
+    /// # Errors
+    ///
+    /// - Return [`GitRepoError::LibGit2`] if tree cannot be written.
+    pub fn staged_tree<P>(&self, paths: &[P]) -> Result<Option<Oid>, GitRepoError>
+    where
+        P: AsRef<Path>,
+    {
+        let base = match self.head_commit()? {
+            Some(oid) => Some(self.repo.find_commit(oid)?.tree()?),
+            None => None,
+        };
+
+        let staged = self.repo.index()?;
+        let mut index = Index::new()?;
+        if let Some(base) = &base {
+            index.read_tree(base)?;
+        }
+        for path in paths {
+            match staged.get_path(path.as_ref(), 0) {
+                Some(entry) => index.add(&entry)?,
+                None => index.remove_path(path.as_ref())?,
+            }
+        }
+
+        let tree = index.write_tree_to(&self.repo)?;
+        match base {
+            Some(base) if base.id() == tree => Ok(None),
+            None if index.is_empty() => Ok(None),
+            _ => Ok(Some(tree)),
+        }
+    }
+
+    /// Replace message of latest commit with `msg`, leaving staged changes
+    /// out of it.
+    ///
+    /// # Errors
+    ///
+    /// - Return [`GitRepoError::LibGit2`] if latest commit cannot be amended,
+    ///   e.g., nothing was committed yet.
+    pub fn reword(&self, msg: &str) -> Result<Oid, GitRepoError> {
+        let head = self.repo.head()?.peel_to_commit()?;
+        if !self.perform(|| format!("Reword latest commit to '{}'", summary(msg))) {
+            return Ok(head.id());
+        }
+        Ok(head.amend(Some("HEAD"), None, None, None, Some(msg), None)?)
+    }
+
+    /// Check if anything is staged for the next commit.
+    ///
+    /// # Errors
+    ///
+    /// - Return [`GitRepoError::LibGit2`] if index cannot be compared to HEAD.
+    pub fn has_staged(&self) -> Result<bool, GitRepoError> {
+        let index = self.repo.index()?;
+        let head = match self.head_commit()? {
+            Some(oid) => self.repo.find_commit(oid)?.tree()?,
+            None => return Ok(!index.is_empty()),
+        };
+        let diff = self.repo.diff_tree_to_index(Some(&head), Some(&index), None)?;
+        Ok(diff.deltas().len() > 0)
+    }
+
+    /// List files whose staged changes add or modify content, i.e., staged
+    /// deletions are left out.
+    ///
+    /// Paths are relative to working directory.
+    ///
+    /// # Errors
+    ///
+    /// - Return [`GitRepoError::LibGit2`] if index cannot be compared to HEAD.
+    pub fn staged_files(&self) -> Result<Vec<PathBuf>, GitRepoError> {
+        let index = self.repo.index()?;
+        let head = match self.head_commit()? {
+            Some(oid) => Some(self.repo.find_commit(oid)?.tree()?),
+            None => None,
+        };
+        let diff = self.repo.diff_tree_to_index(head.as_ref(), Some(&index), None)?;
+        let paths = diff
+            .deltas()
+            .filter(|delta| delta.status() != Delta::Deleted)
+            .filter_map(|delta| delta.new_file().path().map(PathBuf::from))
+            .collect();
+        Ok(paths)
+    }
+
+    /// Describe staged content of `paths`.
+    ///
+    /// Paths without a staged entry, e.g., staged deletions, are left out.
+    ///
+    /// # Errors
+    ///
+    /// - Return [`GitRepoError::LibGit2`] if index or staged content cannot
+    ///   be read.
+    pub fn staged_blobs<P>(&self, paths: &[P]) -> Result<Vec<StagedBlob>, GitRepoError>
+    where
+        P: AsRef<Path>,
+    {
+        let index = self.repo.index()?;
+        let mut blobs = Vec::new();
+        for path in paths {
+            let Some(entry) = index.get_path(path.as_ref(), 0) else {
+                continue;
+            };
+            let blob = self.repo.find_blob(entry.id)?;
+            blobs.push(StagedBlob {
+                path: path.as_ref().into(),
+                size: blob.size() as u64,
+                binary: blob.is_binary(),
+            });
+        }
+        Ok(blobs)
+    }
+
     /// Find a commit from object ID.
     ///
     /// # Errors
@@ -116,25 +377,74 @@ pub fn find_commit(&self, oid: Oid) -> Result<Commit<'_>, GitRepoError> {
     /// # Errors
     ///
     /// - Return [`GitRepoError::LibGit2`] if pull cannot be performed.
+    /// - Return [`GitRepoError::BranchMigrated`] if `branch` no longer exists
+    ///   on `remote`, but `remote` advertises a new default branch.
+    /// - Return [`GitRepoError::NoSuchBranch`] if `branch` no longer exists on
+    ///   `remote`, and `remote` advertises no other default branch.
     pub fn pull(
         &self,
         remote: impl AsRef<str>,
         branch: impl AsRef<str>,
     ) -> Result<(), GitRepoError> {
-        let mut remote = self.repo.find_remote(remote.as_ref())?;
-        let fetch = self.fetch(&[branch.as_ref()], &mut remote)?;
+        if !self.perform(|| format!("Pull '{}' from '{}'", branch.as_ref(), remote.as_ref())) {
+            return Ok(());
+        }
+        let mut handle = self.repo.find_remote(remote.as_ref())?;
+        let fetch = match self.fetch(&[branch.as_ref()], &mut handle) {
+            Ok(fetch) => fetch,
+            // INVARIANT: only ask remote for its default branch once `branch` could not be fetched.
+            Err(err @ GitRepoError::NoSuchBranch { .. }) => {
+                return match self.detect_branch_migration(remote.as_ref(), branch.as_ref())? {
+                    Some(BranchMigration { remote, from, to }) => {
+                        Err(GitRepoError::BranchMigrated { remote, from, to })
+                    }
+                    None => Err(err),
+                };
+            }
+            Err(err) => return Err(err),
+        };
         self.full_merge(branch.as_ref(), fetch)?;
         Ok(())
     }
 
+    /// Fetch `branch` from `remote` without merging it.
+    ///
+    /// Updates remote-tracking branch and `FETCH_HEAD`, so divergence and time
+    /// of last sync can be inspected through [`GitRepo::status`].
+    ///
+    /// # Errors
+    ///
+    /// - Return [`GitRepoError::LibGit2`] if fetch cannot be performed.
+    /// - Return [`GitRepoError::NoSuchBranch`] if `branch` does not exist on
+    ///   `remote`.
+    pub fn fetch_remote(
+        &self,
+        remote: impl AsRef<str>,
+        branch: impl AsRef<str>,
+    ) -> Result<(), GitRepoError> {
+        if !self.perform(|| format!("Fetch '{}' from '{}'", branch.as_ref(), remote.as_ref())) {
+            return Ok(());
+        }
+        let mut remote = self.repo.find_remote(remote.as_ref())?;
+        self.fetch(&[branch.as_ref()], &mut remote)?;
+        Ok(())
+    }
+
     pub fn push(
         &self,
         remote: impl AsRef<str>,
         branch: impl AsRef<str>,
     ) -> Result<(), GitRepoError> {
+        if !self.perform(|| format!("Push '{}' to '{}'", branch.as_ref(), remote.as_ref())) {
+            return Ok(());
+        }
         let mut remote = self.repo.find_remote(remote.as_ref())?;
         let branch = self.repo.find_branch(branch.as_ref(), BranchType::Local)?;
-        remote.push(&[branch.into_reference().name().unwrap_or("master")], None)?;
+        let mut opts = PushOptions::new();
+        opts.remote_callbacks(remote_callbacks());
+        remote
+            .push(&[branch.into_reference().name().unwrap_or("master")], Some(&mut opts))
+            .map_err(cancellable)?;
         Ok(())
     }
 
@@ -151,6 +461,12 @@ pub fn syscall(
         &self,
         args: impl IntoIterator<Item = impl AsRef<OsStr>>,
     ) -> Result<(), GitRepoError> {
+        let args = args.into_iter().map(|arg| arg.as_ref().to_os_string()).collect::<Vec<_>>();
+        let line = || args.iter().map(|arg| arg.to_string_lossy()).collect::<Vec<_>>().join(" ");
+        if !self.perform(|| format!("Run 'git {}'", line())) {
+            return Ok(());
+        }
+
         let output = Command::new("git")
             .args([
                 "--git-dir",
@@ -169,41 +485,977 @@ pub fn syscall(
         let msg = String::from_utf8_lossy(output.stdout.as_slice()).into_owned();
         info!("Git binary success: {msg}");
 
-        Ok(())
+        Ok(())
+    }
+
+    /// Detect if `branch` was replaced by a new default branch on `remote`.
+    ///
+    /// Forges like GitHub allow the default branch of a repository to be
+    /// renamed, e.g., "master" to "main". Once that happens, `branch` no longer
+    /// exists on `remote`. Will return `None` if `branch` still exists on
+    /// `remote`, or if `remote` does not advertise a new default branch.
+    ///
+    /// # Errors
+    ///
+    /// - Return [`GitRepoError::LibGit2`] if `remote` cannot be queried.
+    pub fn detect_branch_migration(
+        &self,
+        remote: impl AsRef<str>,
+        branch: impl AsRef<str>,
+    ) -> Result<Option<BranchMigration>, GitRepoError> {
+        let mut handle = self.repo.find_remote(remote.as_ref())?;
+        handle.connect_auth(Direction::Fetch, Some(remote_callbacks()), None)?;
+
+        let refname = format!("refs/heads/{}", branch.as_ref());
+        if handle.list()?.iter().any(|head| head.name() == refname) {
+            handle.disconnect()?;
+            return Ok(None);
+        }
+
+        let default = match handle.default_branch() {
+            Ok(buf) => buf.as_str().map(|name| name.to_string()),
+            Err(err) if err.code() == ErrorCode::NotFound => None,
+            Err(err) => return Err(err.into()),
+        };
+        handle.disconnect()?;
+
+        let migration = default
+            .as_deref()
+            .and_then(|name| name.strip_prefix("refs/heads/"))
+            .filter(|name| *name != branch.as_ref())
+            .map(|name| BranchMigration {
+                remote: remote.as_ref().into(),
+                from: branch.as_ref().into(),
+                to: name.into(),
+            });
+        Ok(migration)
+    }
+
+    /// Migrate local tracking refs to new default branch of remote.
+    ///
+    /// Fetches new default branch from remote, renames local branch to match
+    /// it, and sets the upstream of the renamed branch to the new default
+    /// branch. If a local branch already exists with the new name, then it will
+    /// be kept, and only have its upstream set.
+    ///
+    /// # Errors
+    ///
+    /// - Return [`GitRepoError::LibGit2`] if migration cannot be performed.
+    pub fn migrate_branch(&self, migration: &BranchMigration) -> Result<(), GitRepoError> {
+        let BranchMigration { remote, from, to } = migration;
+        if !self.perform(|| format!("Migrate branch '{from}' to '{to}' of remote '{remote}'")) {
+            return Ok(());
+        }
+        info!("Migrate branch '{from}' to '{to}' of remote '{remote}'");
+        let mut handle = self.repo.find_remote(remote)?;
+        let refspec = format!("+refs/heads/{to}:refs/remotes/{remote}/{to}");
+        let mut opts = FetchOptions::new();
+        opts.remote_callbacks(remote_callbacks());
+        handle.fetch(&[refspec.as_str()], Some(&mut opts), None).map_err(cancellable)?;
+
+        let mut branch = match self.repo.find_branch(to, BranchType::Local) {
+            Ok(branch) => branch,
+            Err(err) if err.code() == ErrorCode::NotFound => {
+                match self.repo.find_branch(from, BranchType::Local) {
+                    Ok(mut branch) => branch.rename(to, false)?,
+                    Err(err) if err.code() == ErrorCode::NotFound => {
+                        let commit = self
+                            .repo
+                            .find_reference(&format!("refs/remotes/{remote}/{to}"))?
+                            .peel_to_commit()?;
+                        self.repo.branch(to, &commit, false)?
+                    }
+                    Err(err) => return Err(err.into()),
+                }
+            }
+            Err(err) => return Err(err.into()),
+        };
+        branch.set_upstream(Some(&format!("{remote}/{to}")))?;
+
+        Ok(())
+    }
+
+    /// Summarize current state of repository.
+    ///
+    /// Untracked files do not make fake bare repositories dirty, because their
+    /// working directory is usually the user's entire home directory.
+    ///
+    /// # Errors
+    ///
+    /// - Return [`GitRepoError::LibGit2`] if status cannot be determined.
+    pub fn status(&self) -> Result<RepoStatus, GitRepoError> {
+        self.status_with(StatusScan::default())
+    }
+
+    /// Summarize current state of repository, looking for changes through
+    /// `scan`.
+    ///
+    /// # Errors
+    ///
+    /// - Return [`GitRepoError::LibGit2`] if status cannot be determined.
+    pub fn status_with(&self, scan: StatusScan) -> Result<RepoStatus, GitRepoError> {
+        // INVARIANT: read HEAD directly so unborn branches are still reported.
+        let branch = self
+            .repo
+            .find_reference("HEAD")?
+            .symbolic_target()
+            .and_then(|target| target.strip_prefix("refs/heads/"))
+            .map(String::from);
+
+        let dirty = match self.repo.is_bare() {
+            true => false,
+            false => !self.status_entries(scan)?.is_empty(),
+        };
+
+        let (ahead, behind) = match &branch {
+            Some(branch) => self.ahead_behind(branch)?,
+            None => (0, 0),
+        };
+
+        let last_sync = fs::metadata(self.repo.path().join("FETCH_HEAD"))
+            .and_then(|metadata| metadata.modified())
+            .ok();
+
+        Ok(RepoStatus { branch, dirty, ahead, behind, last_sync })
+    }
+
+    /// List files with uncommitted changes, looking for them through `scan`.
+    ///
+    /// Paths are relative to working directory. Bare repositories never have
+    /// any.
+    ///
+    /// # Errors
+    ///
+    /// - Return [`GitRepoError::LibGit2`] if status cannot be determined.
+    pub fn changed_files(&self, scan: StatusScan) -> Result<Vec<PathBuf>, GitRepoError> {
+        if self.repo.is_bare() {
+            return Ok(Vec::new());
+        }
+
+        Ok(self.status_entries(scan)?.into_iter().map(|(path, _)| path).collect())
+    }
+
+    /// List untracked files of working directory, looking for them through
+    /// `scan`.
+    ///
+    /// Paths are relative to working directory. Bare repositories never have
+    /// any.
+    ///
+    /// # Errors
+    ///
+    /// - Return [`GitRepoError::LibGit2`] if status cannot be determined.
+    pub fn untracked_files(&self, scan: StatusScan) -> Result<Vec<PathBuf>, GitRepoError> {
+        if self.repo.is_bare() {
+            return Ok(Vec::new());
+        }
+
+        Ok(self
+            .status_entries(scan)?
+            .into_iter()
+            .filter(|(_, status)| status.is_wt_new())
+            .map(|(path, _)| path)
+            .collect())
+    }
+
+    /// List untracked files of working directory inside `paths`, or anywhere
+    /// if `paths` is empty, including those inside untracked directories.
+    ///
+    /// Files Git ignores are only listed through `ignored`, see
+    /// [`ExcludeRules`]. Paths are relative to working directory. Bare
+    /// repositories never have any.
+    ///
+    /// # Errors
+    ///
+    /// - Return [`GitRepoError::LibGit2`] if status cannot be determined.
+    pub fn untracked_files_in<P>(
+        &self,
+        paths: &[P],
+        ignored: bool,
+    ) -> Result<Vec<PathBuf>, GitRepoError>
+    where
+        P: AsRef<Path>,
+    {
+        if self.repo.is_bare() {
+            return Ok(Vec::new());
+        }
+
+        let mut opts = StatusOptions::new();
+        opts.include_untracked(true)
+            .recurse_untracked_dirs(true)
+            .include_ignored(true)
+            .recurse_ignored_dirs(true);
+        for path in paths {
+            opts.pathspec(path.as_ref());
+        }
+        let statuses = self.repo.statuses(Some(&mut opts))?;
+        let rules = self.exclude_rules()?;
+        let mut files = Vec::new();
+        for entry in statuses.iter() {
+            let status = entry.status();
+            // INVARIANT: nested repositories show up as directories, never list into them.
+            let Some(path) = entry.path().filter(|path| !path.ends_with('/')).map(PathBuf::from)
+            else {
+                continue;
+            };
+            if !status.is_wt_new() && !status.is_ignored() {
+                continue;
+            }
+
+            // INVARIANT: settle ignored files again like Git, see ExcludeRules.
+            let is_ignored = match &rules {
+                Some(rules) => rules.is_ignored(&path),
+                None => status.is_ignored(),
+            };
+            if ignored || !is_ignored {
+                files.push(path);
+            }
+        }
+
+        Ok(files)
+    }
+
+    /// Paths with changes of working directory, looking for them through
+    /// `scan`, with untracked files ignored like Git does, see
+    /// [`ExcludeRules`].
+    fn status_entries(&self, scan: StatusScan) -> Result<Vec<(PathBuf, Status)>, GitRepoError> {
+        let statuses = self.repo.statuses(Some(&mut self.status_options(scan)))?;
+        let rules = self.exclude_rules()?;
+        let mut entries = Vec::new();
+        for entry in statuses.iter() {
+            let Some(path) = entry.path().map(PathBuf::from) else {
+                continue;
+            };
+
+            // INVARIANT: libgit2 never lets sources of higher precedence
+            // re-include what others ignore, so settle untracked files again.
+            let status = entry.status();
+            let untracked = status.is_wt_new() || status.is_ignored();
+            let status = match &rules {
+                Some(rules) if untracked && rules.is_ignored(&path) => continue,
+                Some(_) if untracked => Status::WT_NEW,
+                _ if status.is_ignored() => continue,
+                _ => status,
+            };
+            entries.push((path, status));
+        }
+
+        Ok(entries)
+    }
+
+    /// Ignore rules of working directory, if any.
+    fn exclude_rules(&self) -> Result<Option<ExcludeRules>, GitRepoError> {
+        let Some(workdir) = self.repo.workdir() else {
+            return Ok(None);
+        };
+
+        // INVARIANT: Git falls back to `$XDG_CONFIG_HOME/git/ignore`.
+        let excludes_file = match self.repo.config()?.get_path("core.excludesFile") {
+            Ok(path) => Some(path),
+            Err(err) if err.code() == ErrorCode::NotFound => {
+                BaseDirs::new().map(|dirs| dirs.config_dir().join("git/ignore"))
+            }
+            Err(err) => return Err(err.into()),
+        };
+        Ok(Some(ExcludeRules::new(workdir, self.repo.path(), excludes_file.as_deref())))
+    }
+
+    fn status_options(&self, scan: StatusScan) -> StatusOptions {
+        let mut opts = StatusOptions::new();
+        opts.include_ignored(false);
+        match scan.assume_clean {
+            // INVARIANT: never touch the working directory when assumed clean.
+            true => opts.show(StatusShow::Index).include_untracked(false),
+            false => {
+                let default = match self.is_fake_bare() {
+                    true => UntrackedFiles::No,
+                    false => UntrackedFiles::Normal,
+                };
+                let untracked = scan.untracked.unwrap_or(default);
+                // INVARIANT: ignored files are settled through ExcludeRules.
+                opts.include_untracked(untracked != UntrackedFiles::No)
+                    .include_ignored(untracked != UntrackedFiles::No)
+                    .recurse_untracked_dirs(untracked == UntrackedFiles::All)
+            }
+        };
+        opts
+    }
+
+    /// Get up to `limit` latest commits of HEAD, newest first.
+    ///
+    /// Returns nothing if HEAD is unborn.
+    ///
+    /// # Errors
+    ///
+    /// - Return [`GitRepoError::LibGit2`] if history cannot be walked.
+    pub fn recent_commits(&self, limit: usize) -> Result<Vec<CommitSummary>, GitRepoError> {
+        if self.head_commit()?.is_none() {
+            return Ok(Vec::new());
+        }
+
+        let mut revwalk = self.repo.revwalk()?;
+        revwalk.set_sorting(Sort::TOPOLOGICAL | Sort::TIME)?;
+        revwalk.push_head()?;
+        let mut commits = Vec::new();
+        for oid in revwalk.take(limit) {
+            let commit = self.repo.find_commit(oid?)?;
+            commits.push(CommitSummary {
+                id: commit.id(),
+                summary: commit.summary().unwrap_or_default().into(),
+                author: commit.author().name().unwrap_or_default().into(),
+            });
+        }
+
+        Ok(commits)
+    }
+
+    /// Get commit that HEAD points to.
+    ///
+    /// Returns `None` if HEAD is unborn, i.e., nothing was committed yet.
+    ///
+    /// # Errors
+    ///
+    /// - Return [`GitRepoError::LibGit2`] if HEAD cannot be resolved.
+    pub fn head_commit(&self) -> Result<Option<Oid>, GitRepoError> {
+        match self.repo.head() {
+            Ok(head) => Ok(Some(head.peel_to_commit()?.id())),
+            Err(err) if err.code() == ErrorCode::UnbornBranch => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Check out commit or tag `rev`, detaching HEAD at it.
+    ///
+    /// Returns commit that was checked out.
+    ///
+    /// # Errors
+    ///
+    /// - Return [`GitRepoError::LibGit2`] if `rev` does not name a commit, or
+    ///   if checking it out would overwrite uncommitted changes.
+    pub fn checkout_rev(&self, rev: impl AsRef<str>) -> Result<Oid, GitRepoError> {
+        let commit = self.repo.revparse_single(rev.as_ref())?.peel_to_commit()?;
+        if !self.perform(|| format!("Check out '{}'", rev.as_ref())) {
+            return Ok(commit.id());
+        }
+        self.repo.checkout_tree(commit.as_object(), Some(CheckoutBuilder::default().safe()))?;
+        self.repo.set_head_detached(commit.id())?;
+        Ok(commit.id())
+    }
+
+    /// Find files of HEAD that already exist in working directory with other
+    /// contents.
+    ///
+    /// Paths are relative to working directory, in tree order.
+    ///
+    /// # Errors
+    ///
+    /// - Return [`GitRepoError::LibGit2`] if tree of HEAD cannot be walked.
+    pub fn conflicting_files(&self) -> Result<Vec<PathBuf>, GitRepoError> {
+        let (workdir, oid) = match (self.repo.workdir(), self.head_commit()?) {
+            (Some(workdir), Some(oid)) => (workdir, oid),
+            _ => return Ok(Vec::new()),
+        };
+
+        let mut conflicts = Vec::new();
+        self.repo.find_commit(oid)?.tree()?.walk(TreeWalkMode::PreOrder, |root, entry| {
+            if entry.kind() != Some(ObjectType::Blob) {
+                return TreeWalkResult::Ok;
+            }
+            let path = Path::new(root).join(String::from_utf8_lossy(entry.name_bytes()).as_ref());
+            let local = workdir.join(&path);
+            let same = match fs::symlink_metadata(&local) {
+                Err(_) => return TreeWalkResult::Ok,
+                Ok(meta) if meta.is_dir() => false,
+                Ok(_) => fs::read(&local)
+                    .ok()
+                    .and_then(|data| Oid::hash_object(ObjectType::Blob, &data).ok())
+                    .is_some_and(|local| local == entry.id()),
+            };
+            if !same {
+                conflicts.push(path);
+            }
+            TreeWalkResult::Ok
+        })?;
+
+        Ok(conflicts)
+    }
+
+    /// Get contents of file at `path` in tree of HEAD.
+    ///
+    /// Returns `None` if HEAD is unborn, or has no file at `path`.
+    ///
+    /// # Errors
+    ///
+    /// - Return [`GitRepoError::LibGit2`] if file cannot be read.
+    pub fn head_file(&self, path: impl AsRef<Path>) -> Result<Option<Vec<u8>>, GitRepoError> {
+        let oid = match self.head_commit()? {
+            Some(oid) => oid,
+            None => return Ok(None),
+        };
+        let entry = match self.repo.find_commit(oid)?.tree()?.get_path(path.as_ref()) {
+            Ok(entry) => entry,
+            Err(err) if err.code() == ErrorCode::NotFound => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+        let data = self.repo.find_blob(entry.id())?.content().to_vec();
+        Ok(Some(data))
+    }
+
+    /// Check out every file of HEAD, overwriting whatever is in their way.
+    ///
+    /// # Errors
+    ///
+    /// - Return [`GitRepoError::LibGit2`] if files cannot be checked out.
+    pub fn force_checkout_head(&self) -> Result<(), GitRepoError> {
+        if !self.perform(|| "Check out files of HEAD".into()) {
+            return Ok(());
+        }
+        self.repo.checkout_head(Some(CheckoutBuilder::default().force()))?;
+        Ok(())
+    }
+
+    /// Check out local `branch`, attaching HEAD to it again.
+    ///
+    /// # Errors
+    ///
+    /// - Return [`GitRepoError::LibGit2`] if `branch` does not exist, or if
+    ///   checking it out would overwrite uncommitted changes.
+    pub fn checkout_branch(&self, branch: impl AsRef<str>) -> Result<(), GitRepoError> {
+        let refname = format!("refs/heads/{}", branch.as_ref());
+        let commit = self.repo.find_reference(&refname)?.peel_to_commit()?;
+        if !self.perform(|| format!("Check out branch '{}'", branch.as_ref())) {
+            return Ok(());
+        }
+        self.repo.checkout_tree(commit.as_object(), Some(CheckoutBuilder::default().safe()))?;
+        self.repo.set_head(&refname)?;
+        Ok(())
+    }
+
+    /// Point HEAD at local `branch` without checking anything out, creating
+    /// `branch` from its remote-tracking branch of `remote` if need be.
+    ///
+    /// # Errors
+    ///
+    /// - Return [`GitRepoError::LibGit2`] if `branch` exists neither locally,
+    ///   nor on `remote`.
+    pub fn switch_head(
+        &self,
+        remote: impl AsRef<str>,
+        branch: impl AsRef<str>,
+    ) -> Result<(), GitRepoError> {
+        let branch = branch.as_ref();
+        if !self.perform(|| format!("Switch HEAD to branch '{branch}'")) {
+            return Ok(());
+        }
+        self.track_branch(remote, branch)?;
+        self.repo.set_head(&format!("refs/heads/{branch}"))?;
+        Ok(())
+    }
+
+    /// Create local `branch` from its remote-tracking branch of `remote`,
+    /// unless local `branch` exists already.
+    ///
+    /// Returns `true` if `branch` is new.
+    ///
+    /// # Errors
+    ///
+    /// - Return [`GitRepoError::LibGit2`] if `branch` exists neither locally,
+    ///   nor on `remote`.
+    pub fn track_branch(
+        &self,
+        remote: impl AsRef<str>,
+        branch: impl AsRef<str>,
+    ) -> Result<bool, GitRepoError> {
+        let branch = branch.as_ref();
+        if self.repo.find_branch(branch, BranchType::Local).is_ok() {
+            return Ok(false);
+        }
+
+        let upstream = format!("{}/{branch}", remote.as_ref());
+        let commit =
+            self.repo.find_branch(&upstream, BranchType::Remote)?.get().peel_to_commit()?;
+        if self.perform(|| format!("Create branch '{branch}' tracking '{upstream}'")) {
+            self.repo.branch(branch, &commit, false)?.set_upstream(Some(&upstream))?;
+        }
+        Ok(true)
+    }
+
+    /// Count commits of `branch` on `remote` that are not reachable from
+    /// commit `oid`.
+    ///
+    /// Uses remote-tracking branch as of last fetch. Returns zero if `branch`
+    /// was never fetched from `remote`.
+    ///
+    /// # Errors
+    ///
+    /// - Return [`GitRepoError::LibGit2`] if commits cannot be counted.
+    pub fn upstream_ahead_of(
+        &self,
+        remote: impl AsRef<str>,
+        branch: impl AsRef<str>,
+        oid: Oid,
+    ) -> Result<usize, GitRepoError> {
+        let refname = format!("refs/remotes/{}/{}", remote.as_ref(), branch.as_ref());
+        let upstream = match self.repo.find_reference(&refname) {
+            Ok(upstream) => upstream.peel_to_commit()?.id(),
+            Err(err) if err.code() == ErrorCode::NotFound => return Ok(0),
+            Err(err) => return Err(err.into()),
+        };
+        let (ahead, _) = self.repo.graph_ahead_behind(upstream, oid)?;
+        Ok(ahead)
+    }
+
+    /// Check if tag `name` exists.
+    ///
+    /// # Errors
+    ///
+    /// - Return [`GitRepoError::LibGit2`] if tag lookup fails.
+    pub fn has_tag(&self, name: impl AsRef<str>) -> Result<bool, GitRepoError> {
+        match self.repo.find_reference(&format!("refs/tags/{}", name.as_ref())) {
+            Ok(_) => Ok(true),
+            Err(err) if err.code() == ErrorCode::NotFound => Ok(false),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Create lightweight tag `name` pointing to commit `oid`.
+    ///
+    /// # Errors
+    ///
+    /// - Return [`GitRepoError::LibGit2`] if tag cannot be created, e.g., it
+    ///   already exists.
+    pub fn tag(&self, name: impl AsRef<str>, oid: Oid) -> Result<(), GitRepoError> {
+        let target = self.repo.find_object(oid, None)?;
+        if !self.perform(|| format!("Tag '{oid}' as '{}'", name.as_ref())) {
+            return Ok(());
+        }
+        self.repo.tag_lightweight(name.as_ref(), &target, false)?;
+        Ok(())
+    }
+
+    /// Delete tag `name`.
+    ///
+    /// # Errors
+    ///
+    /// - Return [`GitRepoError::LibGit2`] if tag cannot be deleted, e.g., it
+    ///   does not exist.
+    pub fn delete_tag(&self, name: impl AsRef<str>) -> Result<(), GitRepoError> {
+        if !self.perform(|| format!("Delete tag '{}'", name.as_ref())) {
+            return Ok(());
+        }
+        self.repo.tag_delete(name.as_ref())?;
+        Ok(())
+    }
+
+    /// Get URL of `remote` if it exists.
+    pub fn remote_url(&self, remote: impl AsRef<str>) -> Option<String> {
+        self.repo.find_remote(remote.as_ref()).ok()?.url().map(String::from)
+    }
+
+    /// Get names of every remote.
+    ///
+    /// # Errors
+    ///
+    /// - Return [`GitRepoError::LibGit2`] if remotes cannot be listed.
+    pub fn remotes(&self) -> Result<Vec<String>, GitRepoError> {
+        let remotes = self.repo.remotes()?;
+        Ok(remotes.iter().flatten().map(String::from).collect())
+    }
+
+    /// Point `remote` at `url`.
+    ///
+    /// # Errors
+    ///
+    /// - Return [`GitRepoError::LibGit2`] if URL of remote cannot be set.
+    pub fn set_remote_url(
+        &self,
+        remote: impl AsRef<str>,
+        url: impl AsRef<str>,
+    ) -> Result<(), GitRepoError> {
+        if !self.perform(|| format!("Point remote '{}' at '{}'", remote.as_ref(), url.as_ref())) {
+            return Ok(());
+        }
+        self.repo.remote_set_url(remote.as_ref(), url.as_ref())?;
+        Ok(())
+    }
+
+    /// Get name of branch HEAD points to, unless HEAD is detached or unborn.
+    pub fn head_branch(&self) -> Option<String> {
+        let head = self.repo.head().ok()?;
+        match head.is_branch() {
+            true => head.shorthand().map(String::from),
+            false => None,
+        }
+    }
+
+    /// Stage `paths` relative to working directory.
+    ///
+    /// Returns staged paths. Like `git add`, untracked paths that are ignored
+    /// are left out, see [`GitRepo::is_ignored`].
+    ///
+    /// # Errors
+    ///
+    /// - Return [`GitRepoError::LibGit2`] if paths cannot be staged.
+    pub fn stage<P>(&self, paths: impl IntoIterator<Item = P>) -> Result<Vec<PathBuf>, GitRepoError>
+    where
+        P: AsRef<Path>,
+    {
+        let mut index = self.repo.index()?;
+        let mut staged = Vec::new();
+        for path in paths {
+            let path = path.as_ref();
+            if self.is_ignored(path)? {
+                debug!("Leave '{}' unstaged, because it is ignored", path.display());
+                continue;
+            }
+            index.add_path(path)?;
+            staged.push(path.to_path_buf());
+        }
+        // INVARIANT: dry runs only stage into the index in memory.
+        if staged.is_empty() || self.perform(|| format!("Stage {} files", staged.len())) {
+            index.write()?;
+        }
+        Ok(staged)
+    }
+
+    /// Check if untracked `path` relative to working directory is ignored.
+    ///
+    /// Patterns of `core.excludesFile`, of `info/exclude`, which holds the
+    /// patterns of Ricer's ignore files, and of `.gitignore` files, are
+    /// honored in the order of precedence Git gives them, see
+    /// [`ExcludeRules`]. Bare repositories never ignore anything.
+    ///
+    /// # Invariants
+    ///
+    /// Tracked files are never ignored, even if patterns match them.
+    ///
+    /// # Errors
+    ///
+    /// - Return [`GitRepoError::LibGit2`] if index or ignore rules cannot be
+    ///   read.
+    pub fn is_ignored(&self, path: impl AsRef<Path>) -> Result<bool, GitRepoError> {
+        let path = path.as_ref();
+        if self.repo.index()?.get_path(path, 0).is_some() {
+            return Ok(false);
+        }
+
+        Ok(self.exclude_rules()?.is_some_and(|rules| rules.is_ignored(path)))
+    }
+
+    /// Stage current state of every file of `paths` in working directory,
+    /// i.e., add files that exist, and remove those that were deleted.
+    ///
+    /// Untracked paths that are ignored are left out, see
+    /// [`GitRepo::is_ignored`].
+    ///
+    /// # Errors
+    ///
+    /// - Return [`GitRepoError::LibGit2`] if index cannot be updated.
+    pub fn stage_changes<P>(&self, paths: &[P]) -> Result<(), GitRepoError>
+    where
+        P: AsRef<Path>,
+    {
+        let Some(workdir) = self.repo.workdir() else {
+            return Ok(());
+        };
+
+        let mut index = self.repo.index()?;
+        for path in paths {
+            match workdir.join(path.as_ref()).symlink_metadata().is_ok() {
+                true if self.is_ignored(path)? => {
+                    debug!("Leave '{}' unstaged, because it is ignored", path.as_ref().display())
+                }
+                true => index.add_path(path.as_ref())?,
+                false => index.remove_path(path.as_ref())?,
+            }
+        }
+        index.write()?;
+        Ok(())
+    }
+
+    /// Hunks of changes to tracked files in working directory that are not
+    /// staged yet, limited to `paths` if any are given.
+    ///
+    /// # Invariants
+    ///
+    /// Only modified text files are split into hunks. Deleted, binary, or
+    /// untracked files are left out.
+    ///
+    /// # Errors
+    ///
+    /// - Return [`GitRepoError::LibGit2`] if working directory cannot be
+    ///   compared against index.
+    pub fn unstaged_hunks<P>(&self, paths: &[P]) -> Result<Vec<Hunk>, GitRepoError>
+    where
+        P: AsRef<Path>,
+    {
+        let mut opts = DiffOptions::new();
+        for path in paths {
+            opts.pathspec(path.as_ref());
+        }
+        let diff = self.repo.diff_index_to_workdir(None, Some(&mut opts))?;
+
+        let mut hunks = Vec::new();
+        for (index, delta) in diff.deltas().enumerate() {
+            if delta.status() != Delta::Modified || delta.flags().is_binary() {
+                continue;
+            }
+            let path = match delta.new_file().path() {
+                Some(path) => path.to_path_buf(),
+                None => continue,
+            };
+            let patch = match Patch::from_diff(&diff, index)? {
+                Some(patch) => patch,
+                None => continue,
+            };
+
+            for hunk_index in 0..patch.num_hunks() {
+                let (hunk, line_count) = patch.hunk(hunk_index)?;
+                let mut lines = Vec::new();
+                for line_index in 0..line_count {
+                    let line = patch.line_in_hunk(hunk_index, line_index)?;
+                    if matches!(line.origin(), ' ' | '+' | '-') {
+                        lines.push((line.origin(), line.content().to_vec()));
+                    }
+                }
+                hunks.push(Hunk {
+                    path: path.clone(),
+                    header: String::from_utf8_lossy(hunk.header()).trim_end().into(),
+                    old_start: hunk.old_start() as usize,
+                    old_lines: hunk.old_lines() as usize,
+                    lines,
+                });
+            }
+        }
+
+        Ok(hunks)
+    }
+
+    /// Stage `hunks` of [`GitRepo::unstaged_hunks`], leaving the rest of the
+    /// changes to their files unstaged.
+    ///
+    /// # Invariants
+    ///
+    /// Hunks are applied to the staged contents of their file in order of
+    /// appearance, so they must not overlap.
+    ///
+    /// # Errors
+    ///
+    /// - Return [`GitRepoError::LibGit2`] if staged contents of a file cannot
+    ///   be read or updated.
+    pub fn stage_hunks(&self, hunks: &[&Hunk]) -> Result<(), GitRepoError> {
+        let mut index = self.repo.index()?;
+        let mut paths = hunks.iter().map(|hunk| hunk.path.as_path()).collect::<Vec<_>>();
+        paths.dedup();
+
+        for path in paths {
+            let mut entry = index.get_path(path, 0).ok_or_else(|| {
+                Git2Error::from_str(&format!("'{}' is not in index", path.display()))
+            })?;
+            let mut selected =
+                hunks.iter().copied().filter(|hunk| hunk.path == path).collect::<Vec<_>>();
+            selected.sort_by_key(|hunk| hunk.old_start);
+
+            let staged = self.repo.find_blob(entry.id)?;
+            let contents = apply_hunks(staged.content(), &selected);
+            entry.file_size = contents.len() as u32;
+            index.add_frombuffer(&entry, &contents)?;
+        }
+        index.write()?;
+
+        Ok(())
+    }
+
+    /// Split directory `prefix` out into repository `into`, checking it out
+    /// on `branch`.
+    ///
+    /// Every commit of HEAD that changed `prefix` is rewritten with `prefix`
+    /// as its root, keeping its author, committer, and message, like `git
+    /// subtree split` does. Through `squash`, history is dropped instead, and
+    /// a single commit of the current contents of `prefix` is made with
+    /// `squash` as its message. Will return number of commits split, which is
+    /// zero if `prefix` never was a directory in history.
+    ///
+    /// # Invariants
+    ///
+    /// Rewritten commits are written to this repository, and fetched from
+    /// here into `into`. Branches of this repository are left untouched.
+    ///
+    /// # Errors
+    ///
+    /// - Return [`GitRepoError::LibGit2`] if history cannot be rewritten,
+    ///   fetched, or checked out.
+    pub fn split_into(
+        &self,
+        prefix: &Path,
+        into: &GitRepo,
+        branch: &str,
+        squash: Option<&str>,
+    ) -> Result<usize, GitRepoError> {
+        let subtree = |commit: &Commit| -> Result<Option<Oid>, GitRepoError> {
+            match commit.tree()?.get_path(prefix) {
+                Ok(entry) if entry.kind() == Some(ObjectType::Tree) => Ok(Some(entry.id())),
+                Ok(_) => Ok(None),
+                Err(err) if err.code() == ErrorCode::NotFound => Ok(None),
+                Err(err) => Err(err.into()),
+            }
+        };
+
+        let head = self.repo.head()?.peel_to_commit()?;
+        let (tip, count) = match squash {
+            Some(message) => match subtree(&head)? {
+                Some(tree) => {
+                    let sig = self.repo.signature()?;
+                    let tree = self.repo.find_tree(tree)?;
+                    (Some(self.repo.commit(None, &sig, &sig, message, &tree, &[])?), 1)
+                }
+                None => (None, 0),
+            },
+            None => {
+                let mut walk = self.repo.revwalk()?;
+                walk.set_sorting(Sort::TOPOLOGICAL | Sort::REVERSE)?;
+                walk.push(head.id())?;
+
+                let mut split = HashMap::<Oid, Option<Oid>>::new();
+                let mut count = 0;
+                for oid in walk {
+                    let commit = self.repo.find_commit(oid?)?;
+                    let mut parents = Vec::new();
+                    for parent in commit.parent_ids() {
+                        if let Some(Some(parent)) = split.get(&parent) {
+                            if !parents.contains(parent) {
+                                parents.push(*parent);
+                            }
+                        }
+                    }
+
+                    let tree = match subtree(&commit)? {
+                        Some(tree) => tree,
+                        None => {
+                            split.insert(commit.id(), parents.first().copied());
+                            continue;
+                        }
+                    };
+
+                    // INVARIANT: commits that leave prefix unchanged are skipped.
+                    if let [parent] = parents.as_slice() {
+                        if self.repo.find_commit(*parent)?.tree_id() == tree {
+                            split.insert(commit.id(), Some(*parent));
+                            continue;
+                        }
+                    }
+
+                    let parents = parents
+                        .iter()
+                        .map(|parent| self.repo.find_commit(*parent))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    let oid = self.repo.commit(
+                        None,
+                        &commit.author(),
+                        &commit.committer(),
+                        &String::from_utf8_lossy(commit.message_bytes()),
+                        &self.repo.find_tree(tree)?,
+                        &parents.iter().collect::<Vec<_>>(),
+                    )?;
+                    split.insert(commit.id(), Some(oid));
+                    count += 1;
+                }
+                (split.get(&head.id()).copied().flatten(), count)
+            }
+        };
+
+        let tip = match tip {
+            Some(tip) => tip,
+            None => return Ok(0),
+        };
+
+        let mut split_ref = self.repo.reference(SPLIT_REF, tip, true, "ricer split")?;
+        let url = self.repo.path().to_string_lossy().into_owned();
+        let fetched = into.repo.remote_anonymous(&url).and_then(|mut remote| {
+            remote.fetch(&[format!("+{SPLIT_REF}:refs/heads/{branch}")], None, None)
+        });
+        split_ref.delete()?;
+        fetched?;
+
+        into.repo.set_head(&format!("refs/heads/{branch}"))?;
+        into.repo.checkout_head(Some(CheckoutBuilder::new().force()))?;
+        Ok(count)
+    }
+
+    /// Stop tracking every file inside directory `prefix`, keeping them in
+    /// working directory.
+    ///
+    /// # Errors
+    ///
+    /// - Return [`GitRepoError::LibGit2`] if index cannot be updated.
+    pub fn untrack_dir(&self, prefix: &Path) -> Result<(), GitRepoError> {
+        let mut index = self.repo.index()?;
+        index.remove_dir(prefix, 0)?;
+        index.write()?;
+        Ok(())
+    }
+
+    /// Paths of files tracked in index, relative to working directory.
+    ///
+    /// # Errors
+    ///
+    /// - Return [`GitRepoError::LibGit2`] if index cannot be read.
+    pub fn tracked_files(&self) -> Result<Vec<PathBuf>, GitRepoError> {
+        let index = self.repo.index()?;
+        let files = index
+            .iter()
+            .map(|entry| PathBuf::from(String::from_utf8_lossy(&entry.path).into_owned()))
+            .collect();
+        Ok(files)
+    }
+
+    /// Get Git directory of repository.
+    pub fn gitdir(&self) -> &Path {
+        self.repo.path()
+    }
+
+    /// Get working directory of repository, if it is not bare.
+    pub fn workdir(&self) -> Option<&Path> {
+        self.repo.workdir()
+    }
+
+    pub fn is_fake_bare(&self) -> bool {
+        !self.repo.is_bare() && !self.repo.path().ends_with(".git")
+    }
+
+    /// Check if Git operation described by `action` should be performed, see
+    /// [`plan::perform`].
+    fn perform(&self, action: impl FnOnce() -> String) -> bool {
+        plan::perform(|| DryRunEffect::Git { gitdir: self.repo.path().into(), action: action() })
     }
 
-    pub fn is_fake_bare(&self) -> bool {
-        !self.repo.is_bare() && !self.repo.path().ends_with(".git")
+    pub(crate) fn ahead_behind(&self, branch: &str) -> Result<(usize, usize), GitRepoError> {
+        let local = match self.repo.find_branch(branch, BranchType::Local) {
+            Ok(local) => local,
+            Err(err) if err.code() == ErrorCode::NotFound => return Ok((0, 0)),
+            Err(err) => return Err(err.into()),
+        };
+        let upstream = match local.upstream() {
+            Ok(upstream) => upstream,
+            Err(err) if err.code() == ErrorCode::NotFound => return Ok((0, 0)),
+            Err(err) => return Err(err.into()),
+        };
+
+        match (local.get().target(), upstream.get().target()) {
+            (Some(local), Some(upstream)) => Ok(self.repo.graph_ahead_behind(local, upstream)?),
+            _ => Ok((0, 0)),
+        }
     }
 
     pub(crate) fn fetch(
         &self,
         refs: &[&str],
         remote: &mut Remote,
-    ) -> Result<AnnotatedCommit, GitRepoError> {
-        let mut cb = RemoteCallbacks::new();
-
-        // Print transfer progress...
-        cb.transfer_progress(|stats| {
-            if stats.received_objects() == stats.total_objects() {
-                info!("Resolving deltas {}/{}", stats.indexed_deltas(), stats.total_deltas(),);
-            } else if stats.total_objects() > 0 {
-                info!(
-                    "Received {}/{} objects ({}) in {} bytes",
-                    stats.received_objects(),
-                    stats.total_objects(),
-                    stats.indexed_objects(),
-                    stats.received_bytes(),
-                );
-            }
-            true
-        });
-
+    ) -> Result<AnnotatedCommit<'_>, GitRepoError> {
         let mut opts = FetchOptions::new();
-        opts.remote_callbacks(cb);
+        opts.remote_callbacks(remote_callbacks());
         opts.download_tags(AutotagOption::All);
         info!("Fetching {} for repo", remote.name().unwrap_or("origin"));
-        remote.fetch(refs, Some(&mut opts), None)?;
+        remote.fetch(refs, Some(&mut opts), None).map_err(cancellable)?;
 
         let stats = remote.stats();
         if stats.local_objects() > 0 {
@@ -223,8 +1475,28 @@ pub(crate) fn fetch(
             );
         }
 
-        let head = self.repo.find_reference("FETCH_HEAD")?;
-        let commit = self.repo.reference_to_annotated_commit(&head)?;
+        // INVARIANT: merge what was fetched for merging, not tags fetched alongside it.
+        let mut merge = None;
+        self.repo.fetchhead_foreach(|_, _, oid, is_merge| {
+            if is_merge && merge.is_none() {
+                merge = Some(*oid);
+            }
+            true
+        })?;
+        let commit = match merge {
+            Some(oid) => self.repo.find_annotated_commit(oid)?,
+            // INVARIANT: never merge stale or unrelated heads when a named branch was not fetched.
+            None if !refs.is_empty() => {
+                return Err(GitRepoError::NoSuchBranch {
+                    remote: remote.name().unwrap_or_default().into(),
+                    branch: refs.join(", "),
+                });
+            }
+            None => {
+                let head = self.repo.find_reference("FETCH_HEAD")?;
+                self.repo.reference_to_annotated_commit(&head)?
+            }
+        };
         Ok(commit)
     }
 
@@ -321,6 +1593,301 @@ pub(crate) fn full_merge(
     }
 }
 
+/// Cache of open repositories for one command invocation.
+///
+/// Opening a repository means discovering it, and parsing its configuration
+/// files, which dominates commands that only inspect many repositories, e.g.,
+/// `ricer status` followed by the state snapshot of [`crate::state`]. Handles
+/// are keyed by the path they were opened through, and reused by every later
+/// open of the same path.
+///
+/// Cloning yields another handle to the same cache.
+///
+/// # Invariants
+///
+/// Failed opens are never cached, so they are retried by later opens.
+#[derive(Clone, Default)]
+pub struct RepoCache {
+    repos: Rc<RefCell<HashMap<PathBuf, Rc<GitRepo>>>>,
+}
+
+impl RepoCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Open repository at `path`, reusing the handle of an earlier open.
+    ///
+    /// # Errors
+    ///
+    /// - Return [`GitRepoError::LibGit2`] if repository cannot be opened.
+    pub fn open(&self, path: impl AsRef<Path>) -> Result<Rc<GitRepo>, GitRepoError> {
+        let path = path.as_ref();
+        if let Some(repo) = self.repos.borrow().get(path) {
+            debug!("Reuse open repository '{}'", path.display());
+            return Ok(Rc::clone(repo));
+        }
+
+        let repo = Rc::new(GitRepo::open(path)?);
+        self.repos.borrow_mut().insert(path.into(), Rc::clone(&repo));
+        Ok(repo)
+    }
+
+    /// Number of open repositories.
+    pub fn len(&self) -> usize {
+        self.repos.borrow().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.repos.borrow().is_empty()
+    }
+}
+
+impl fmt::Debug for RepoCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RepoCache").field("repos", &self.repos.borrow().keys()).finish()
+    }
+}
+
+/// Callbacks that report transfer progress through [`progress`], abort once
+/// cancelled, check SSH host keys through [`hostkey::certificate_check`], and
+/// authenticate through [`credentials`].
+///
+/// # Invariants
+///
+/// Progress is finished once the callbacks are dropped, i.e., once the remote
+/// operation using them is over.
+fn remote_callbacks<'cb>() -> RemoteCallbacks<'cb> {
+    let mut cb = RemoteCallbacks::new();
+    let mut attempts = CredentialAttempts::default();
+    cb.credentials(move |url, username, allowed| {
+        credentials(&mut attempts, url, username, allowed)
+    });
+    cb.certificate_check(hostkey::certificate_check);
+    let guard = ProgressGuard;
+    cb.transfer_progress(move |stats| {
+        // INVARIANT: guard lives as long as the callbacks do.
+        let _guard = &guard;
+        // INVARIANT: returning false makes libgit2 abort the transfer.
+        if cancel::is_cancelled() {
+            return false;
+        }
+
+        progress().update(&TransferProgress::fetch(&stats));
+        true
+    });
+    cb.push_transfer_progress(|current, total, bytes| {
+        progress().update(&TransferProgress::push(current, total, bytes));
+    });
+    cb
+}
+
+/// First line of commit message `msg`.
+fn summary(msg: &str) -> &str {
+    msg.lines().next().unwrap_or_default()
+}
+
+/// Tell transfers aborted through [`remote_callbacks`] apart from failures.
+fn cancellable(err: Git2Error) -> GitRepoError {
+    match cancel::is_cancelled() && err.code() == ErrorCode::User {
+        true => GitRepoError::Cancelled,
+        false => err.into(),
+    }
+}
+
+/// Render unified diff of file at `path` changing from `old` to `new`.
+///
+/// # Errors
+///
+/// - Return [`GitRepoError::LibGit2`] if diff cannot be generated.
+pub fn unified_diff(path: &Path, old: &str, new: &str) -> Result<String, GitRepoError> {
+    let mut patch =
+        Patch::from_buffers(old.as_bytes(), Some(path), new.as_bytes(), Some(path), None)?;
+    let buf = patch.to_buf()?;
+    Ok(String::from_utf8_lossy(&buf).into_owned())
+}
+
+/// Apply `hunks`, sorted by their start, to `old` contents of file.
+fn apply_hunks(old: &[u8], hunks: &[&Hunk]) -> Vec<u8> {
+    let old = old.split_inclusive(|byte| *byte == b'\n').collect::<Vec<_>>();
+    let mut new = Vec::new();
+    let mut next = 0;
+
+    for hunk in hunks {
+        // INVARIANT: pure additions start right after their old start line.
+        let start = match hunk.old_lines {
+            0 => hunk.old_start,
+            _ => hunk.old_start.saturating_sub(1),
+        };
+        old[next..start].iter().for_each(|line| new.extend_from_slice(line));
+        next = start;
+
+        for (origin, content) in &hunk.lines {
+            match origin {
+                ' ' => {
+                    new.extend_from_slice(old[next]);
+                    next += 1;
+                }
+                '-' => next += 1,
+                '+' => new.extend_from_slice(content),
+                _ => (),
+            }
+        }
+    }
+    old[next..].iter().for_each(|line| new.extend_from_slice(line));
+
+    new
+}
+
+/// One hunk of changes to a file, as in a unified diff.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hunk {
+    /// Path to changed file, relative to working directory.
+    pub path: PathBuf,
+
+    /// Header of hunk, e.g., `@@ -1,3 +1,4 @@`.
+    pub header: String,
+
+    old_start: usize,
+    old_lines: usize,
+    lines: Vec<(char, Vec<u8>)>,
+}
+
+impl fmt::Display for Hunk {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.header)?;
+        for (origin, content) in &self.lines {
+            let content = String::from_utf8_lossy(content);
+            match content.strip_suffix('\n') {
+                Some(line) => writeln!(f, "{origin}{line}")?,
+                None => writeln!(f, "{origin}{content}\n\\ No newline at end of file")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Staged content of a file, see [`GitRepo::staged_blobs`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StagedBlob {
+    /// Path to file, relative to working directory.
+    pub path: PathBuf,
+
+    /// Size of staged content in bytes.
+    pub size: u64,
+
+    /// Staged content looks binary, like Git decides for diffs.
+    pub binary: bool,
+}
+
+/// Default branch migration of a remote.
+///
+/// Describes that `from` branch no longer exists on `remote`, because `remote`
+/// now uses `to` as its default branch instead.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BranchMigration {
+    pub remote: String,
+    pub from: String,
+    pub to: String,
+}
+
+/// How untracked files of working directory are looked for, like Git's
+/// `status.showUntrackedFiles` option.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum UntrackedFiles {
+    /// Do not look for untracked files.
+    No,
+
+    /// Look for untracked files, but not inside untracked directories.
+    Normal,
+
+    /// Look for untracked files inside untracked directories as well.
+    All,
+}
+
+impl FromStr for UntrackedFiles {
+    type Err = String;
+
+    fn from_str(mode: &str) -> Result<Self, Self::Err> {
+        match mode {
+            "no" => Ok(UntrackedFiles::No),
+            "normal" => Ok(UntrackedFiles::Normal),
+            "all" => Ok(UntrackedFiles::All),
+            _ => Err(format!("invalid untracked files mode '{mode}'")),
+        }
+    }
+}
+
+impl fmt::Display for UntrackedFiles {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UntrackedFiles::No => write!(f, "no"),
+            UntrackedFiles::Normal => write!(f, "normal"),
+            UntrackedFiles::All => write!(f, "all"),
+        }
+    }
+}
+
+/// How [`GitRepo::init_with`] creates new repositories.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct RepoInit {
+    /// Branch unborn HEAD points at, or libgit2's default if `None`.
+    pub branch: Option<String>,
+
+    /// Working directory of repository that uses fake bare technique, or
+    /// `None` for a normal repository.
+    pub workdir: Option<PathBuf>,
+}
+
+/// How [`GitRepo::status_with`] looks for changes.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct StatusScan {
+    /// How untracked files are looked for. Fake bare repositories default to
+    /// [`UntrackedFiles::No`], and all others to [`UntrackedFiles::Normal`].
+    pub untracked: Option<UntrackedFiles>,
+
+    /// Skip scanning working directory, only comparing index against HEAD.
+    pub assume_clean: bool,
+}
+
+/// Summary of one commit for [`GitRepo::recent_commits`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommitSummary {
+    pub id: Oid,
+
+    /// First line of commit message.
+    pub summary: String,
+
+    /// Name of commit author.
+    pub author: String,
+}
+
+impl fmt::Display for CommitSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let id = self.id.to_string();
+        write!(f, "{} {} ({})", &id[..7], self.summary, self.author)
+    }
+}
+
+/// Summary of repository state.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RepoStatus {
+    /// Current branch, or `None` if HEAD is detached.
+    pub branch: Option<String>,
+
+    /// Repository has uncommitted changes.
+    pub dirty: bool,
+
+    /// Number of commits current branch is ahead of its upstream.
+    pub ahead: usize,
+
+    /// Number of commits current branch is behind its upstream.
+    pub behind: usize,
+
+    /// Time of last fetch from any remote.
+    pub last_sync: Option<SystemTime>,
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum GitRepoError {
     #[error("Failed to perform libgit2 operation")]
@@ -331,6 +1898,18 @@ pub enum GitRepoError {
 
     #[error("Git binary failure: {msg}")]
     GitBin { msg: String },
+
+    #[error(
+        "Branch '{from}' no longer exists on remote '{remote}', which now uses '{to}' as its \
+        default branch"
+    )]
+    BranchMigrated { remote: String, from: String, to: String },
+
+    #[error("Branch '{branch}' not found on remote '{remote}'")]
+    NoSuchBranch { remote: String, branch: String },
+
+    #[error("Operation was cancelled")]
+    Cancelled,
 }
 
 impl From<Git2Error> for GitRepoError {
@@ -387,6 +1966,46 @@ fn git_repo_init_fake_bare_return_self(repo_dir: Result<FixtureHarness>) -> Resu
         Ok(())
     }
 
+    #[rstest]
+    #[case::normal(false)]
+    #[case::fake_bare(true)]
+    fn git_repo_init_with_commit_on_branch(
+        repo_dir: Result<FixtureHarness>,
+        #[case] fake_bare: bool,
+    ) -> Result<()> {
+        let repo_dir = repo_dir?;
+        let init = RepoInit {
+            branch: Some("dev".into()),
+            workdir: fake_bare.then(|| repo_dir.as_path().into()),
+        };
+        let repo = GitRepo::init_with(repo_dir.as_path().join("foo"), &init)?;
+        let mut config = repo.repo.config()?;
+        config.set_str("user.name", "John Doe")?;
+        config.set_str("user.email", "john@doe.com")?;
+        repo.commit("Initial commit")?;
+
+        assert_eq!(repo.is_fake_bare(), fake_bare);
+        assert_eq!(repo.head_branch().as_deref(), Some("dev"));
+        Ok(())
+    }
+
+    #[rstest]
+    fn repo_cache_open_reuse_handles(repo_dir: Result<FixtureHarness>) -> Result<()> {
+        let repo_dir = repo_dir?;
+        let cache = RepoCache::new();
+        let dwm = repo_dir.get_repo("dwm")?.as_path();
+
+        let first = cache.open(dwm)?;
+        let second = cache.clone().open(dwm)?;
+        assert!(Rc::ptr_eq(&first, &second));
+        assert_eq!(cache.len(), 1);
+
+        assert!(cache.open(repo_dir.as_path().join("missing")).is_err());
+        assert_eq!(cache.len(), 1);
+
+        Ok(())
+    }
+
     #[rstest]
     fn git_repo_open_return_self(repo_dir: Result<FixtureHarness>) -> Result<()> {
         let repo_dir = repo_dir?;
@@ -458,6 +2077,308 @@ fn git_repo_push_return_ok(
         Ok(())
     }
 
+    #[rstest]
+    fn git_repo_detect_branch_migration_return_none(
+        repo_dir: Result<FixtureHarness>,
+    ) -> Result<()> {
+        let repo_dir = repo_dir?;
+        let remote = repo_dir.get_repo("github")?;
+        let local = repo_dir.get_repo("dwm")?;
+        let repo = GitRepo::open(local.as_path())?;
+        repo.syscall(["remote", "add", "origin", remote.as_path().to_str().unwrap()])?;
+        repo.push("origin", "main")?;
+
+        let result = repo.detect_branch_migration("origin", "main")?;
+        assert_eq!(result, None);
+        Ok(())
+    }
+
+    #[rstest]
+    fn git_repo_detect_branch_migration_return_some(
+        repo_dir: Result<FixtureHarness>,
+    ) -> Result<()> {
+        let repo_dir = repo_dir?;
+        let remote = repo_dir.get_repo("github")?;
+        let local = repo_dir.get_repo("dwm")?;
+        let repo = GitRepo::open(local.as_path())?;
+        repo.syscall(["remote", "add", "origin", remote.as_path().to_str().unwrap()])?;
+        repo.push("origin", "main")?;
+
+        let result = repo.detect_branch_migration("origin", "master")?;
+        let expect =
+            BranchMigration { remote: "origin".into(), from: "master".into(), to: "main".into() };
+        assert_eq!(result, Some(expect));
+        Ok(())
+    }
+
+    #[rstest]
+    fn git_repo_pull_return_err_branch_migrated(repo_dir: Result<FixtureHarness>) -> Result<()> {
+        let repo_dir = repo_dir?;
+        let remote = repo_dir.get_repo("github")?;
+        let local = repo_dir.get_repo("dwm")?;
+        let repo = GitRepo::open(local.as_path())?;
+        repo.syscall(["remote", "add", "origin", remote.as_path().to_str().unwrap()])?;
+        repo.push("origin", "main")?;
+
+        let result = repo.pull("origin", "master");
+        assert!(matches!(result.unwrap_err(), GitRepoError::BranchMigrated { .. }));
+        Ok(())
+    }
+
+    #[rstest]
+    fn git_repo_migrate_branch_rename_local_branch(repo_dir: Result<FixtureHarness>) -> Result<()> {
+        let repo_dir = repo_dir?;
+        let remote = repo_dir.get_repo("github")?;
+        let local = repo_dir.get_repo("dwm")?;
+        let repo = GitRepo::open(local.as_path())?;
+        repo.syscall(["remote", "add", "origin", remote.as_path().to_str().unwrap()])?;
+        repo.push("origin", "main")?;
+        repo.syscall(["branch", "-m", "main", "master"])?;
+
+        let migration = repo.detect_branch_migration("origin", "master")?.unwrap();
+        repo.migrate_branch(&migration)?;
+        let branch = repo.repo.find_branch("main", BranchType::Local)?;
+        assert_eq!(branch.upstream()?.name()?, Some("origin/main"));
+        assert!(repo.repo.find_branch("master", BranchType::Local).is_err());
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::normal_repo("dwm", true)]
+    #[case::fake_bare_repo("vim", false)]
+    fn git_repo_status_report_dirty(
+        repo_dir: Result<FixtureHarness>,
+        #[case] repo: &str,
+        #[case] untracked_dirty: bool,
+    ) -> Result<()> {
+        let repo_dir = repo_dir?;
+        let fixture = repo_dir.get_repo(repo)?;
+        let repo = GitRepo::open(fixture.as_path())?;
+        let status = repo.status()?;
+        assert_eq!(status.branch.as_deref(), Some("main"));
+        assert!(!status.dirty);
+        assert_eq!(status.last_sync, None);
+
+        let workdir = repo.repo.workdir().unwrap();
+        FileFixture::new(workdir.join("untracked.txt"))
+            .with_data("not tracked")
+            .with_kind(FileKind::Normal)
+            .write()?;
+        assert_eq!(repo.status()?.dirty, untracked_dirty);
+        Ok(())
+    }
+
+    #[rstest]
+    fn git_repo_changed_files_list_dirty_paths(repo_dir: Result<FixtureHarness>) -> Result<()> {
+        let repo_dir = repo_dir?;
+        let fixture = repo_dir.get_repo("dwm")?;
+        let repo = GitRepo::open(fixture.as_path())?;
+        assert!(repo.changed_files(StatusScan::default())?.is_empty());
+
+        FileFixture::new(fixture.as_path().join("untracked.txt"))
+            .with_data("not tracked")
+            .with_kind(FileKind::Normal)
+            .write()?;
+        let scan = StatusScan { untracked: Some(UntrackedFiles::No), ..Default::default() };
+        assert!(repo.changed_files(scan)?.is_empty());
+        assert_eq!(
+            repo.changed_files(StatusScan::default())?,
+            vec![PathBuf::from("untracked.txt")]
+        );
+        Ok(())
+    }
+
+    /// Ignore files in fake bare `repo` through every source of ignore rules Git
+    /// honors, each overriding the one before it.
+    fn write_ignore_rules(repo: &GitRepo) -> Result<()> {
+        let excludes = repo.gitdir().join("global-excludes");
+        fs::write(&excludes, "*.log\n*.txt\nvimrc\n")?;
+        repo.repo.config()?.set_str("core.excludesFile", excludes.to_str().unwrap())?;
+
+        let exclude = ["!keep.log", "!*.md", crate::ignore::EXCLUDE_BEGIN, "cache/", "*.md"];
+        fs::create_dir_all(repo.gitdir().join("info"))?;
+        fs::write(repo.gitdir().join("info/exclude"), exclude.join("\n"))?;
+
+        let workdir = repo.workdir().unwrap();
+        fs::write(workdir.join(".gitignore"), ".gitignore\n!notes.txt\n")?;
+        for path in ["debug.log", "keep.log", "cache/page.html", "notes.txt", "todo.md", "vimrc"] {
+            FileFixture::new(workdir.join(path))
+                .with_data("not tracked")
+                .with_kind(FileKind::Normal)
+                .write()?;
+        }
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::global_excludes("debug.log", true)]
+    #[case::repo_excludes_override_global_excludes("keep.log", false)]
+    #[case::ricer_ignores("cache/page.html", true)]
+    #[case::ricer_ignores_override_repo_excludes("todo.md", true)]
+    #[case::gitignore_override_global_excludes("notes.txt", false)]
+    #[case::tracked_file("vimrc", false)]
+    fn git_repo_is_ignored_honor_precedence(
+        repo_dir: Result<FixtureHarness>,
+        #[case] path: &str,
+        #[case] expect: bool,
+    ) -> Result<()> {
+        let repo_dir = repo_dir?;
+        let repo = GitRepo::open(repo_dir.get_repo("vim")?.as_path())?;
+        write_ignore_rules(&repo)?;
+        assert_eq!(repo.is_ignored(path)?, expect);
+        Ok(())
+    }
+
+    #[rstest]
+    fn git_repo_untracked_files_leave_out_ignored(repo_dir: Result<FixtureHarness>) -> Result<()> {
+        let repo_dir = repo_dir?;
+        let repo = GitRepo::open(repo_dir.get_repo("vim")?.as_path())?;
+        write_ignore_rules(&repo)?;
+        fs::write(repo.workdir().unwrap().join("vimrc"), "set number")?;
+
+        // INVARIANT: working directory of fixture holds other fixture repos too.
+        let written = |paths: Vec<PathBuf>| {
+            let names =
+                ["debug.log", "keep.log", "cache/page.html", "notes.txt", "todo.md", "vimrc"];
+            paths.into_iter().filter(move |path| names.iter().any(|name| path == Path::new(name)))
+        };
+        let scan = StatusScan { untracked: Some(UntrackedFiles::All), ..Default::default() };
+        assert_eq!(
+            written(repo.untracked_files(scan)?).collect::<Vec<_>>(),
+            [PathBuf::from("keep.log"), "notes.txt".into()]
+        );
+        assert_eq!(
+            written(repo.changed_files(scan)?).collect::<Vec<_>>(),
+            [PathBuf::from("keep.log"), "notes.txt".into(), "vimrc".into()]
+        );
+        Ok(())
+    }
+
+    #[rstest]
+    fn git_repo_stage_leave_out_ignored(repo_dir: Result<FixtureHarness>) -> Result<()> {
+        let repo_dir = repo_dir?;
+        let repo = GitRepo::open(repo_dir.get_repo("vim")?.as_path())?;
+        write_ignore_rules(&repo)?;
+
+        assert_eq!(repo.stage(["debug.log", "notes.txt"])?, [PathBuf::from("notes.txt")]);
+        repo.stage_changes(&["cache/page.html", "keep.log"])?;
+        assert_eq!(repo.staged_files()?, [PathBuf::from("keep.log"), "notes.txt".into()]);
+        Ok(())
+    }
+
+    #[rstest]
+    fn git_repo_commit_tree_leave_other_changes_staged(
+        repo_dir: Result<FixtureHarness>,
+    ) -> Result<()> {
+        let repo_dir = repo_dir?;
+        let fixture = repo_dir.get_repo("dwm")?;
+        let repo = GitRepo::open(fixture.as_path())?;
+        assert_eq!(repo.staged_tree(&["config.h"])?, None);
+
+        fs::write(fixture.as_path().join("config.h"), "configure DWM elsewhere")?;
+        fs::remove_file(fixture.as_path().join("Makefile"))?;
+        fs::write(fixture.as_path().join("dwm.c"), "new source code for DWM")?;
+        repo.stage_changes(&["config.h", "Makefile", "dwm.c"])?;
+        let tree = repo.staged_tree(&["config.h", "Makefile"])?.unwrap();
+        let oid = repo.commit_tree("partial commit", tree)?;
+
+        let committed = repo.find_commit(oid)?.tree()?;
+        assert!(committed.get_path(Path::new("Makefile")).is_err());
+        assert!(committed.get_path(Path::new("dwm.c")).is_ok());
+        assert_eq!(repo.changed_files(StatusScan::default())?, vec![PathBuf::from("dwm.c")]);
+        assert!(repo.has_staged()?);
+        Ok(())
+    }
+
+    #[rstest]
+    fn git_repo_staged_files_leave_out_deletions(repo_dir: Result<FixtureHarness>) -> Result<()> {
+        let repo_dir = repo_dir?;
+        let fixture = repo_dir.get_repo("dwm")?;
+        let repo = GitRepo::open(fixture.as_path())?;
+        assert!(repo.staged_files()?.is_empty());
+
+        fs::write(fixture.as_path().join("config.h"), "configure DWM elsewhere")?;
+        fs::remove_file(fixture.as_path().join("Makefile"))?;
+        fs::write(fixture.as_path().join("new.c"), "new source code for DWM")?;
+        repo.stage_changes(&["config.h", "Makefile", "new.c"])?;
+        assert_eq!(repo.staged_files()?, vec![PathBuf::from("config.h"), PathBuf::from("new.c")]);
+        Ok(())
+    }
+
+    #[rstest]
+    fn git_repo_recent_commits_newest_first(repo_dir: Result<FixtureHarness>) -> Result<()> {
+        let mut repo_dir = repo_dir?;
+        let fixture = repo_dir.get_repo_mut("dwm")?;
+        FileFixture::new(fixture.as_path().join("new.c"))
+            .with_data("some new data")
+            .with_kind(FileKind::Normal)
+            .write()?;
+        fixture.add("new.c")?;
+        let repo = GitRepo::open(fixture.as_path())?;
+        repo.commit("Add new.c")?;
+
+        let commits = repo.recent_commits(5)?;
+        assert_eq!(commits[0].summary, "Add new.c");
+        assert_eq!(commits.len(), repo.recent_commits(usize::MAX)?.len().min(5));
+        assert_eq!(repo.recent_commits(1)?, commits[..1]);
+        Ok(())
+    }
+
+    #[rstest]
+    fn git_repo_status_count_ahead(repo_dir: Result<FixtureHarness>) -> Result<()> {
+        let mut repo_dir = repo_dir?;
+        let remote = repo_dir.get_repo("github")?.as_path().to_path_buf();
+        let fixture = repo_dir.get_repo_mut("dwm")?;
+        let repo = GitRepo::open(fixture.as_path())?;
+        repo.syscall(["remote", "add", "origin", remote.to_str().unwrap()])?;
+        repo.push("origin", "main")?;
+        repo.syscall(["fetch", "origin"])?;
+        repo.syscall(["branch", "--set-upstream-to=origin/main"])?;
+
+        FileFixture::new(fixture.as_path().join("new.c"))
+            .with_data("some new data")
+            .with_kind(FileKind::Normal)
+            .write()?;
+        fixture.add("new.c")?;
+        repo.commit("Add new.c")?;
+
+        let status = repo.status()?;
+        assert_eq!((status.ahead, status.behind), (1, 0));
+        assert!(status.last_sync.is_some());
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::first_hunk(0, "ONE\n2\n3\n4\n5\n6\n7\n8\n9\n10\n11\n12\n")]
+    #[case::last_hunk(1, "1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n11\nTWELVE\n13\n")]
+    fn git_repo_stage_hunks_stage_only_selected(
+        repo_dir: Result<FixtureHarness>,
+        #[case] selected: usize,
+        #[case] expect: &str,
+    ) -> Result<()> {
+        let mut repo_dir = repo_dir?;
+        let fixture = repo_dir.get_repo_mut("dwm")?;
+        let lines = (1..=12).map(|line| format!("{line}\n")).collect::<String>();
+        let path = fixture.as_path().join("notes.txt");
+        FileFixture::new(&path).with_data(lines.as_str()).with_kind(FileKind::Normal).write()?;
+        fixture.add("notes.txt")?;
+        let changed = lines.replacen("1\n", "ONE\n", 1).replace("12\n", "TWELVE\n13\n");
+        FileFixture::new(&path).with_data(changed.as_str()).with_kind(FileKind::Normal).write()?;
+
+        let repo = GitRepo::open(fixture.as_path())?;
+        let hunks = repo.unstaged_hunks(&["notes.txt"])?;
+        assert_eq!(hunks.len(), 2);
+        assert!(hunks[1].to_string().contains("-12\n+TWELVE\n+13\n"));
+        repo.stage_hunks(&[&hunks[selected]])?;
+
+        let index = repo.repo.index()?;
+        let entry = index.get_path(Path::new("notes.txt"), 0).unwrap();
+        let staged = repo.repo.find_blob(entry.id)?;
+        assert_eq!(String::from_utf8_lossy(staged.content()), expect);
+        assert_eq!(repo.unstaged_hunks(&["notes.txt"])?.len(), 1);
+        Ok(())
+    }
+
     #[rstest]
     fn git_repo_syscall_return_ok(
         repo_dir: Result<FixtureHarness>,
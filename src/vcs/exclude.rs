@@ -0,0 +1,179 @@
+// SPDX-FileCopyrightText: 2024 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+use glob::{MatchOptions, Pattern};
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+const GLOB_OPTIONS: MatchOptions = MatchOptions {
+    case_sensitive: true,
+    require_literal_separator: true,
+    require_literal_leading_dot: false,
+};
+
+/// Ignore rules of a working directory, decided in the order of precedence
+/// Git gives them.
+///
+/// Sources are, from lowest to highest precedence, `core.excludesFile`, then
+/// `info/exclude` of the Git directory, then `.gitignore` files of the working
+/// directory, where deeper files win over those above them. Within a source,
+/// later patterns win over earlier ones. Unlike libgit2, a negated pattern
+/// re-includes paths that a source of lower precedence ignores, like Git.
+///
+/// # Invariants
+///
+/// 1. Paths inside an ignored directory stay ignored, whatever negated
+///    patterns say about them, like Git.
+/// 1. Missing or unreadable sources have no patterns.
+#[derive(Debug)]
+pub struct ExcludeRules {
+    workdir: PathBuf,
+    base: Vec<IgnoreRule>,
+    gitignores: RefCell<HashMap<PathBuf, Vec<IgnoreRule>>>,
+}
+
+impl ExcludeRules {
+    /// Construct new ignore rules of `workdir`, reading `info/exclude` of
+    /// `gitdir`, and `excludes_file` if any.
+    pub fn new(workdir: impl Into<PathBuf>, gitdir: &Path, excludes_file: Option<&Path>) -> Self {
+        let mut base = excludes_file.map(read_rules).unwrap_or_default();
+        base.extend(read_rules(&gitdir.join("info/exclude")));
+        Self { workdir: workdir.into(), base, gitignores: RefCell::new(HashMap::new()) }
+    }
+
+    /// Check if `path` relative to working directory is ignored.
+    pub fn is_ignored(&self, path: impl AsRef<Path>) -> bool {
+        let path = path.as_ref();
+        let mut ancestors =
+            path.ancestors().filter(|dir| !dir.as_os_str().is_empty()).collect::<Vec<_>>();
+        ancestors.reverse();
+        for current in ancestors {
+            let is_dir = current != path || self.workdir.join(path).is_dir();
+            let ignored = self.decide(current, is_dir);
+            if current == path || ignored {
+                return ignored;
+            }
+        }
+
+        false
+    }
+
+    /// Decide whether `path` itself is ignored through last matching pattern.
+    fn decide(&self, path: &Path, is_dir: bool) -> bool {
+        let mut ignored = last_match(&self.base, path, is_dir);
+        let mut dirs = path.ancestors().skip(1).collect::<Vec<_>>();
+        dirs.reverse();
+        for dir in dirs {
+            let mut gitignores = self.gitignores.borrow_mut();
+            let rules = gitignores
+                .entry(dir.into())
+                .or_insert_with(|| read_rules(&self.workdir.join(dir).join(".gitignore")));
+            // INVARIANT: patterns of .gitignore files are relative to their directory.
+            let relative = path.strip_prefix(dir).unwrap_or(path);
+            ignored = last_match(rules, relative, is_dir).or(ignored);
+        }
+
+        ignored.unwrap_or(false)
+    }
+}
+
+/// One pattern of an ignore file.
+#[derive(Debug, Clone)]
+struct IgnoreRule {
+    pattern: Pattern,
+    negate: bool,
+    dir_only: bool,
+    anchored: bool,
+}
+
+impl IgnoreRule {
+    /// Parse one `line` of an ignore file.
+    ///
+    /// Returns `None` for blank lines, comments, and invalid patterns, which
+    /// Git silently skips too.
+    fn parse(line: &str) -> Option<Self> {
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+
+        let (negate, line) = match line.strip_prefix('!') {
+            Some(line) => (true, line),
+            None => (false, line.strip_prefix('\\').unwrap_or(line)),
+        };
+        let dir_only = line.ends_with('/');
+        let line = line.trim_end_matches('/');
+        let anchored = line.contains('/');
+        let pattern = Pattern::new(line.trim_start_matches('/')).ok()?;
+        Some(Self { pattern, negate, dir_only, anchored })
+    }
+
+    /// Check if rule matches `path` itself, not any of its ancestors.
+    fn matches(&self, path: &Path, is_dir: bool) -> bool {
+        if self.dir_only && !is_dir {
+            return false;
+        }
+
+        match self.anchored {
+            true => self.pattern.matches_path_with(path, GLOB_OPTIONS),
+            false => path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| self.pattern.matches_with(name, GLOB_OPTIONS)),
+        }
+    }
+}
+
+/// Decision of last rule of `rules` that matches `path`, if any.
+fn last_match(rules: &[IgnoreRule], path: &Path, is_dir: bool) -> Option<bool> {
+    rules.iter().rev().find(|rule| rule.matches(path, is_dir)).map(|rule| !rule.negate)
+}
+
+fn read_rules(path: &Path) -> Vec<IgnoreRule> {
+    let data = fs::read_to_string(path).unwrap_or_default();
+    data.lines().filter_map(IgnoreRule::parse).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testenv::FixtureHarness;
+
+    use anyhow::Result;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::unanchored("*.log", "logs/debug.log", false, true)]
+    #[case::anchored("/debug.log", "logs/debug.log", false, false)]
+    #[case::middle_slash("logs/*.log", "logs/debug.log", false, true)]
+    #[case::dir_only_dir("cache/", "cache", true, true)]
+    #[case::dir_only_file("cache/", "cache", false, false)]
+    #[case::escaped_comment("\\#notes", "#notes", false, true)]
+    fn ignore_rule_matches_follow_gitignore_syntax(
+        #[case] line: &str,
+        #[case] path: &str,
+        #[case] is_dir: bool,
+        #[case] expect: bool,
+    ) {
+        let rule = IgnoreRule::parse(line).unwrap();
+        assert_eq!(rule.matches(Path::new(path), is_dir), expect);
+    }
+
+    #[rstest]
+    fn exclude_rules_is_ignored_keep_ignored_dir_contents_ignored() -> Result<()> {
+        let workdir = FixtureHarness::open()?;
+        let gitdir = workdir.as_path().join(".git");
+        fs::create_dir_all(gitdir.join("info"))?;
+        fs::write(gitdir.join("info/exclude"), "cache/\n!cache/keep.txt\n")?;
+        fs::create_dir_all(workdir.as_path().join("cache"))?;
+
+        let rules = ExcludeRules::new(workdir.as_path(), &gitdir, None);
+        assert!(rules.is_ignored("cache"));
+        assert!(rules.is_ignored("cache/keep.txt"));
+        Ok(())
+    }
+}
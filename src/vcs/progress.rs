@@ -0,0 +1,269 @@
+// SPDX-FileCopyrightText: 2024 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+use crate::util::human_size;
+
+use log::debug;
+use std::{
+    fmt,
+    io::Write,
+    sync::{Mutex, OnceLock},
+};
+
+/// Stage of a transfer with a remote.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TransferStage {
+    /// Objects are downloaded from remote.
+    Receiving,
+
+    /// Downloaded deltas are resolved into objects.
+    Resolving,
+
+    /// Objects are uploaded to remote.
+    Writing,
+}
+
+impl fmt::Display for TransferStage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TransferStage::Receiving => write!(f, "Receiving objects"),
+            TransferStage::Resolving => write!(f, "Resolving deltas"),
+            TransferStage::Writing => write!(f, "Writing objects"),
+        }
+    }
+}
+
+/// Snapshot of a transfer with a remote, as reported to [`Progress`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct TransferProgress {
+    pub stage: TransferStage,
+    pub current: usize,
+    pub total: usize,
+
+    /// Bytes transferred so far, which deltas do not count.
+    pub bytes: usize,
+}
+
+impl TransferProgress {
+    /// Snapshot of fetch or clone that libgit2 reported through `stats`.
+    pub fn fetch(stats: &git2::Progress<'_>) -> Self {
+        match stats.received_objects() == stats.total_objects() && stats.total_deltas() > 0 {
+            true => Self {
+                stage: TransferStage::Resolving,
+                current: stats.indexed_deltas(),
+                total: stats.total_deltas(),
+                bytes: stats.received_bytes(),
+            },
+            false => Self {
+                stage: TransferStage::Receiving,
+                current: stats.received_objects(),
+                total: stats.total_objects(),
+                bytes: stats.received_bytes(),
+            },
+        }
+    }
+
+    /// Snapshot of push that libgit2 reported.
+    pub fn push(current: usize, total: usize, bytes: usize) -> Self {
+        Self { stage: TransferStage::Writing, current, total, bytes }
+    }
+
+    /// Percentage of transfer stage that is done.
+    pub fn percent(&self) -> usize {
+        match self.total {
+            0 => 100,
+            total => self.current.min(total) * 100 / total,
+        }
+    }
+}
+
+/// Receiver of progress of long-running Git operations, i.e., clone, fetch,
+/// pull, and push.
+///
+/// # Invariants
+///
+/// 1. [`Progress::finish`] is called once the remote operation that reported
+///    progress is over, whether it succeeded or not.
+pub trait Progress: Send + Sync {
+    /// Report current state of a transfer.
+    fn update(&self, progress: &TransferProgress);
+
+    /// Report that transfer is over.
+    fn finish(&self);
+}
+
+impl<P: Progress + ?Sized> Progress for Box<P> {
+    fn update(&self, progress: &TransferProgress) {
+        self.as_ref().update(progress)
+    }
+
+    fn finish(&self) {
+        self.as_ref().finish()
+    }
+}
+
+/// Progress that nobody gets to see.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct SilentProgress;
+
+impl Progress for SilentProgress {
+    fn update(&self, _: &TransferProgress) {}
+
+    fn finish(&self) {}
+}
+
+/// Progress bar drawn on one line of a terminal, e.g.,
+/// `Receiving objects:  45% (450/1000), 1.2 MiB`.
+///
+/// # Invariants
+///
+/// 1. Line is only redrawn once percentage or stage changes.
+/// 1. Every stage ends up on a line of its own.
+#[derive(Debug)]
+pub struct TerminalProgress<W: Write + Send> {
+    state: Mutex<TerminalState<W>>,
+}
+
+#[derive(Debug)]
+struct TerminalState<W> {
+    out: W,
+    drawn: Option<(TransferStage, usize)>,
+}
+
+impl<W: Write + Send> TerminalProgress<W> {
+    /// Draw progress bars into `out`, typically standard error.
+    pub fn new(out: W) -> Self {
+        Self { state: Mutex::new(TerminalState { out, drawn: None }) }
+    }
+
+    /// Destructure into terminal output.
+    pub fn into_inner(self) -> W {
+        self.state.into_inner().unwrap_or_else(|err| err.into_inner()).out
+    }
+}
+
+impl<W: Write + Send> Progress for TerminalProgress<W> {
+    fn update(&self, progress: &TransferProgress) {
+        if progress.total == 0 {
+            return; // Nothing worth drawing yet.
+        }
+
+        let mut state = self.state.lock().unwrap_or_else(|err| err.into_inner());
+        let percent = progress.percent();
+        let drawn = Some((progress.stage, percent));
+        if state.drawn == drawn {
+            return;
+        }
+
+        let mut line = String::new();
+        if state.drawn.is_some_and(|(stage, _)| stage != progress.stage) {
+            line.push('\n');
+        }
+        line.push_str(&format!(
+            "\r{}: {percent:>3}% ({}/{})",
+            progress.stage, progress.current, progress.total
+        ));
+        if progress.stage != TransferStage::Resolving {
+            line.push_str(&format!(", {}", human_size(progress.bytes as u64)));
+        }
+        // INVARIANT: clear rest of line in case it was drawn longer before.
+        line.push_str("\x1b[K");
+
+        // INVARIANT: failing to draw progress never fails the transfer itself.
+        let _ = state.out.write_all(line.as_bytes()).and_then(|_| state.out.flush());
+        state.drawn = drawn;
+    }
+
+    fn finish(&self) {
+        let mut state = self.state.lock().unwrap_or_else(|err| err.into_inner());
+        if state.drawn.take().is_some() {
+            let _ = state.out.write_all(b"\n").and_then(|_| state.out.flush());
+        }
+    }
+}
+
+fn installed() -> &'static OnceLock<Box<dyn Progress>> {
+    static PROGRESS: OnceLock<Box<dyn Progress>> = OnceLock::new();
+    &PROGRESS
+}
+
+/// Report progress of every remote operation into `progress` from now on.
+///
+/// Only the first call takes effect. Without it, progress is reported into
+/// [`SilentProgress`].
+pub fn install_progress(progress: impl Progress + 'static) {
+    if installed().set(Box::new(progress)).is_err() {
+        debug!("Progress reporter was already installed");
+    }
+}
+
+/// Progress given to [`install_progress`], if any.
+pub(crate) fn progress() -> &'static dyn Progress {
+    match installed().get() {
+        Some(progress) => progress.as_ref(),
+        None => &SilentProgress,
+    }
+}
+
+/// Finish progress of a remote operation once dropped.
+#[derive(Debug, Default)]
+pub(crate) struct ProgressGuard;
+
+impl Drop for ProgressGuard {
+    fn drop(&mut self) {
+        progress().finish();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    #[rstest]
+    fn terminal_progress_update_redraw_on_change() {
+        let progress = TerminalProgress::new(Vec::new());
+        progress.update(&TransferProgress::push(1, 4, 512));
+        progress.update(&TransferProgress::push(1, 4, 600));
+        progress.update(&TransferProgress::push(4, 4, 2048));
+        progress.finish();
+        progress.finish();
+
+        let out = String::from_utf8(progress.into_inner()).unwrap();
+        assert_eq!(
+            out,
+            "\rWriting objects:  25% (1/4), 512 B\x1b[K\
+             \rWriting objects: 100% (4/4), 2.0 KiB\x1b[K\n"
+        );
+    }
+
+    #[rstest]
+    fn terminal_progress_update_put_stages_on_own_line() {
+        let progress = TerminalProgress::new(Vec::new());
+        let receiving =
+            TransferProgress { stage: TransferStage::Receiving, current: 2, total: 2, bytes: 10 };
+        let resolving =
+            TransferProgress { stage: TransferStage::Resolving, current: 0, total: 1, bytes: 10 };
+        progress.update(&receiving);
+        progress.update(&resolving);
+
+        let out = String::from_utf8(progress.into_inner()).unwrap();
+        assert_eq!(
+            out,
+            "\rReceiving objects: 100% (2/2), 10 B\x1b[K\n\rResolving deltas:   0% (0/1)\x1b[K"
+        );
+    }
+
+    #[rstest]
+    #[case::half(TransferProgress::push(5, 10, 0), 50)]
+    #[case::nothing_to_do(TransferProgress::push(0, 0, 0), 100)]
+    #[case::overshoot(TransferProgress::push(11, 10, 0), 100)]
+    fn transfer_progress_percent_stay_in_bounds(
+        #[case] progress: TransferProgress,
+        #[case] expect: usize,
+    ) {
+        assert_eq!(progress.percent(), expect);
+    }
+}
@@ -0,0 +1,500 @@
+// SPDX-FileCopyrightText: 2024 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+//! Detection of interrupted runs.
+//!
+//! Every running command journals what it is about to do into [`RUNS_DIR`] of
+//! the state directory, through a [`RunGuard`] that removes its entry again
+//! once the command ends, however it ends. An entry whose process is gone
+//! thus belongs to a Ricer that crashed or was killed outright.
+//!
+//! On startup, [`recover`] clears out entries of such interrupted runs, along
+//! with Git lock files they left behind, so Git does not refuse to touch the
+//! repositories anymore. Interrupted runs of commands that change
+//! repositories are returned, so they can be warned about before the next
+//! command proceeds.
+
+use crate::{
+    clock,
+    config::{ConfigFile, RepoConfig},
+    context::Context,
+    history::{shell_quote, unix_secs, utc_timestamp},
+    json::Json,
+    locate::Locator,
+    util::is_stale,
+    vcs::GitRepo,
+};
+
+use log::{debug, warn};
+use mkdirp::mkdirp;
+use std::{
+    ffi::OsString,
+    fmt, fs,
+    io::Error as IoError,
+    path::{Path, PathBuf},
+    process,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// Directory of state directory that run entries are journaled in.
+pub const RUNS_DIR: &str = "runs";
+
+/// Lock files Git leaves behind in Git directories if it gets killed.
+const GIT_LOCKS: &[&str] = &["index.lock", "HEAD.lock", "config.lock"];
+
+/// Error types for [`RunGuard`].
+#[derive(Debug, thiserror::Error)]
+pub enum WatchdogError {
+    #[error("Failed to make run directory '{path}'")]
+    MakeDir { source: IoError, path: PathBuf },
+
+    #[error("Failed to write run entry '{path}'")]
+    FileWrite { source: IoError, path: PathBuf },
+}
+
+/// Journal entry of a running command.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RunEntry {
+    /// Process running command.
+    pub pid: u32,
+
+    /// Name of command, or "git" for Git shortcuts.
+    pub command: String,
+
+    /// Command-line arguments after the Ricer binary.
+    pub args: Vec<String>,
+
+    /// When command started.
+    pub started: SystemTime,
+
+    /// Whether command changes repositories it operates on.
+    pub destructive: bool,
+
+    /// Repositories command targets, or none if it targets all of them.
+    pub repos: Vec<String>,
+}
+
+impl RunEntry {
+    /// Journal entry of command of `ctx` run by this process.
+    pub fn new<I, T>(ctx: &Context, args: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<OsString>,
+    {
+        let (command, repos) = match ctx {
+            Context::Git(git_ctx) => {
+                ("git".into(), vec![git_ctx.repo.to_string_lossy().into_owned()])
+            }
+            _ => (ctx.to_string(), ctx.target_repos().into_iter().map(String::from).collect()),
+        };
+
+        Self {
+            pid: process::id(),
+            command,
+            args: args.into_iter().map(|arg| arg.into().to_string_lossy().into_owned()).collect(),
+            started: clock::now(),
+            destructive: ctx.is_mutating(),
+            repos,
+        }
+    }
+
+    pub fn to_json(&self) -> Json {
+        Json::object()
+            .with("pid", u64::from(self.pid))
+            .with("command", &self.command)
+            .with("args", self.args.clone())
+            .with("started", unix_secs(self.started))
+            .with("destructive", self.destructive)
+            .with("repos", self.repos.clone())
+    }
+
+    /// Read entry from JSON object, or `None` if it is malformed.
+    pub fn from_json(json: &Json) -> Option<Self> {
+        let strings = |key| -> Option<Vec<String>> {
+            json.get(key)?.as_array()?.iter().map(|s| s.as_str().map(String::from)).collect()
+        };
+
+        Some(Self {
+            pid: json.get("pid")?.as_f64()? as u32,
+            command: json.get("command")?.as_str()?.into(),
+            args: strings("args")?,
+            started: UNIX_EPOCH + Duration::from_secs(json.get("started")?.as_f64()? as u64),
+            destructive: json.get("destructive")?.as_bool()?,
+            repos: strings("repos")?,
+        })
+    }
+}
+
+/// Run that never finished, because its process died.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InterruptedRun {
+    pub entry: RunEntry,
+
+    /// Git lock files left behind by the run that were removed.
+    pub removed_locks: Vec<PathBuf>,
+}
+
+impl fmt::Display for InterruptedRun {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let args = self.entry.args.iter().map(|arg| shell_quote(arg)).collect::<Vec<_>>();
+        writeln!(
+            f,
+            "Previous 'ricer {}' (pid {}) started {} UTC, but never finished",
+            args.join(" "),
+            self.entry.pid,
+            utc_timestamp(unix_secs(self.entry.started))
+        )?;
+        if self.entry.destructive {
+            let repos = match self.entry.repos.is_empty() {
+                true => "every repository".into(),
+                false => self.entry.repos.join(", "),
+            };
+            writeln!(f, "It may have left changes to {repos} half-done")?;
+        }
+        for lock in &self.removed_locks {
+            writeln!(f, "Removed stale lock '{}'", lock.display())?;
+        }
+        write!(
+            f,
+            "Check repositories through 'ricer status' and 'ricer doctor' before running 'ricer {}' \
+             again",
+            self.entry.command
+        )
+    }
+}
+
+/// Journal entry of the running command.
+///
+/// # Invariants
+///
+/// 1. Entry is removed once the guard is dropped, which includes commands
+///    that fail, or unwind after being cancelled through [`crate::cancel`].
+/// 1. Entries of processes that were killed outright are left behind for
+///    [`recover`] to find.
+#[derive(Debug)]
+pub struct RunGuard {
+    path: PathBuf,
+}
+
+impl RunGuard {
+    /// Journal `entry` until guard is dropped.
+    ///
+    /// # Errors
+    ///
+    /// - Return [`WatchdogError::MakeDir`] if run directory cannot be made.
+    /// - Return [`WatchdogError::FileWrite`] if entry cannot be written.
+    pub fn start(locator: &impl Locator, entry: &RunEntry) -> Result<Self, WatchdogError> {
+        let root = locator.state_dir().join(RUNS_DIR);
+        mkdirp(&root).map_err(|err| WatchdogError::MakeDir { source: err, path: root.clone() })?;
+
+        let path = root.join(format!("{}.json", entry.pid));
+        fs::write(&path, entry.to_json().to_string())
+            .map_err(|err| WatchdogError::FileWrite { source: err, path: path.clone() })?;
+        debug!("Journal '{}' command into '{}'", entry.command, path.display());
+
+        Ok(Self { path })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for RunGuard {
+    fn drop(&mut self) {
+        if let Err(err) = fs::remove_file(&self.path) {
+            warn!("Failed to remove run entry '{}': {err}", self.path.display());
+        }
+    }
+}
+
+/// Clean up after runs that were interrupted.
+///
+/// Entries of dead processes are removed from the journal. Git lock files of
+/// repositories they targeted are removed too, if they were made after the
+/// run started. Returns every interrupted run that either changed
+/// repositories, or left locks behind.
+///
+/// Failing to clean up only warns, because it never keeps the next command
+/// from running.
+///
+/// # Invariants
+///
+/// 1. Entries of running processes are never removed where `/proc` can tell
+///    that the process is running. See [`crate::util::Workspace`].
+/// 1. Git lock files are left alone while any other Ricer is running, because
+///    they may belong to it.
+pub fn recover(locator: &impl Locator) -> Vec<InterruptedRun> {
+    let root = locator.state_dir().join(RUNS_DIR);
+    let entries = match fs::read_dir(&root) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+
+    let mut running = false;
+    let mut interrupted = Vec::new();
+    for file in entries.flatten() {
+        let path = file.path();
+        let pid = path
+            .file_name()
+            .and_then(|name| name.to_str()?.strip_suffix(".json")?.parse::<u32>().ok());
+        let pid = match pid {
+            Some(pid) if pid != process::id() => pid,
+            _ => continue,
+        };
+
+        if !is_stale(&path, pid) {
+            running = true;
+            continue;
+        }
+
+        let entry = fs::read_to_string(&path)
+            .ok()
+            .and_then(|data| data.parse::<Json>().ok())
+            .as_ref()
+            .and_then(RunEntry::from_json);
+        match fs::remove_file(&path) {
+            Ok(_) => debug!("Remove run entry '{}' of dead process", path.display()),
+            Err(err) => warn!("Failed to remove run entry '{}': {err}", path.display()),
+        }
+        match entry {
+            Some(entry) => interrupted.push(entry),
+            None => debug!("Skip malformed run entry '{}'", path.display()),
+        }
+    }
+
+    interrupted
+        .into_iter()
+        .map(|entry| {
+            let removed_locks = match running {
+                true => {
+                    debug!("Keep Git locks of pid {}, because Ricer is running", entry.pid);
+                    Vec::new()
+                }
+                false => remove_stale_locks(locator, &entry),
+            };
+            InterruptedRun { entry, removed_locks }
+        })
+        .filter(|run| run.entry.destructive || !run.removed_locks.is_empty())
+        .collect()
+}
+
+fn remove_stale_locks(locator: &impl Locator, entry: &RunEntry) -> Vec<PathBuf> {
+    let repos = match ConfigFile::load(RepoConfig, locator).and_then(|config| config.entries()) {
+        Ok(repos) => repos,
+        Err(err) => {
+            debug!("Skip Git locks of pid {}: {err}", entry.pid);
+            return Vec::new();
+        }
+    };
+
+    let mut removed = Vec::new();
+    let targets =
+        repos.iter().filter(|repo| entry.repos.is_empty() || entry.repos.contains(&repo.name));
+    for settings in targets {
+        let Ok(repo) = GitRepo::open(settings.gitdir(locator.repos_dir())) else {
+            continue;
+        };
+
+        for lock in GIT_LOCKS.iter().map(|lock| repo.gitdir().join(lock)) {
+            // INVARIANT: locks older than the run were never made by it.
+            let made_by_run = fs::metadata(&lock)
+                .and_then(|meta| meta.modified())
+                .is_ok_and(|modified| unix_secs(modified) >= unix_secs(entry.started));
+            if !made_by_run {
+                continue;
+            }
+
+            match fs::remove_file(&lock) {
+                Ok(_) => removed.push(lock),
+                Err(err) => warn!("Failed to remove stale lock '{}': {err}", lock.display()),
+            }
+        }
+    }
+
+    removed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        cli::Cli,
+        locate::MockLocator,
+        testenv::{FileKind, FixtureHarness},
+    };
+
+    use anyhow::Result;
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+    use rstest::{fixture, rstest};
+
+    #[fixture]
+    fn harness() -> Result<FixtureHarness> {
+        let harness = FixtureHarness::open()?
+            .with_file("repos.toml", |fixture| {
+                fixture
+                    .with_data(indoc! {r#"
+                        [repos.vim]
+                        branch = "main"
+                        remote = "origin"
+
+                        [repos.dwm]
+                        branch = "main"
+                        remote = "origin"
+                    "#})
+                    .with_kind(FileKind::Normal)
+            })
+            .with_bare_repo("repos/vim")?
+            .with_bare_repo("repos/dwm")?
+            .setup()?;
+        Ok(harness)
+    }
+
+    fn locator(harness: &FixtureHarness) -> MockLocator {
+        let mut locator = MockLocator::new();
+        locator.expect_state_dir().return_const(harness.as_path().join("state"));
+        locator.expect_repos_config().return_const(harness.as_path().join("repos.toml"));
+        locator.expect_repos_dir().return_const(harness.as_path().join("repos"));
+        locator
+    }
+
+    fn dead_entry(args: &[&str]) -> Result<RunEntry> {
+        let ctx = Context::from(Cli::parse_args(args)?);
+        let mut entry = RunEntry::new(&ctx, &args[1..]);
+        // PIDs never get this large, so no running process owns this entry.
+        entry.pid = u32::MAX;
+        entry.started = UNIX_EPOCH + Duration::from_secs(unix_secs(SystemTime::now()));
+        Ok(entry)
+    }
+
+    fn journal(harness: &FixtureHarness, entry: &RunEntry) -> Result<PathBuf> {
+        let root = harness.as_path().join("state").join(RUNS_DIR);
+        fs::create_dir_all(&root)?;
+        let path = root.join(format!("{}.json", entry.pid));
+        fs::write(&path, entry.to_json().to_string())?;
+        Ok(path)
+    }
+
+    #[rstest]
+    #[case::delete(&["ricer", "delete", "vim"], "delete", true, &["vim"])]
+    #[case::batch(&["ricer", "commit", "-m", "all: update"], "commit", true, &[])]
+    #[case::status(&["ricer", "status"], "status", false, &[])]
+    #[case::git(&["ricer", "vim", "commit", "-m", "test"], "git", true, &["vim"])]
+    fn run_entry_new_journal_command(
+        #[case] args: &[&str],
+        #[case] command: &str,
+        #[case] destructive: bool,
+        #[case] repos: &[&str],
+    ) -> Result<()> {
+        let ctx = Context::from(Cli::parse_args(args)?);
+        let entry = RunEntry::new(&ctx, &args[1..]);
+        assert_eq!(entry.pid, process::id());
+        assert_eq!(entry.command, command);
+        assert_eq!(entry.args, args[1..]);
+        assert_eq!(entry.destructive, destructive);
+        assert_eq!(entry.repos, repos);
+        Ok(())
+    }
+
+    #[rstest]
+    fn run_entry_from_json_roundtrip() -> Result<()> {
+        let entry = dead_entry(&["ricer", "delete", "vim"])?;
+        let json = entry.to_json().to_string().parse::<Json>()?;
+        assert_eq!(RunEntry::from_json(&json), Some(entry));
+        Ok(())
+    }
+
+    #[rstest]
+    fn run_guard_start_remove_entry_on_drop(harness: Result<FixtureHarness>) -> Result<()> {
+        let harness = harness?;
+        let ctx = Context::from(Cli::parse_args(["ricer", "status"])?);
+        let guard = RunGuard::start(&locator(&harness), &RunEntry::new(&ctx, ["status"]))?;
+        let expect = harness.as_path().join(format!("state/runs/{}.json", process::id()));
+        assert_eq!(guard.path(), expect);
+
+        let json = fs::read_to_string(guard.path())?.parse::<Json>()?;
+        assert_eq!(RunEntry::from_json(&json).map(|entry| entry.command), Some("status".into()));
+        drop(guard);
+        assert!(!expect.exists());
+        Ok(())
+    }
+
+    #[rstest]
+    fn recover_remove_stale_locks(harness: Result<FixtureHarness>) -> Result<()> {
+        let harness = harness?;
+        if !Path::new("/proc").is_dir() {
+            return Ok(());
+        }
+
+        let entry = dead_entry(&["ricer", "delete", "vim"])?;
+        let path = journal(&harness, &entry)?;
+        let vim_lock = harness.as_path().join("repos/vim.git/index.lock");
+        let dwm_lock = harness.as_path().join("repos/dwm.git/index.lock");
+        fs::write(&vim_lock, "")?;
+        fs::write(&dwm_lock, "")?;
+
+        let result = recover(&locator(&harness));
+        let expect = vec![InterruptedRun { entry, removed_locks: vec![vim_lock.clone()] }];
+        assert_eq!(result, expect);
+        assert!(!path.exists());
+        assert!(!vim_lock.exists());
+        assert!(dwm_lock.exists());
+        Ok(())
+    }
+
+    #[rstest]
+    fn recover_skip_finished_and_running(harness: Result<FixtureHarness>) -> Result<()> {
+        let harness = harness?;
+        if !Path::new("/proc").is_dir() {
+            return Ok(());
+        }
+
+        let quiet = dead_entry(&["ricer", "status"])?;
+        let quiet_path = journal(&harness, &quiet)?;
+        let mut running = dead_entry(&["ricer", "delete", "vim"])?;
+        running.pid = 1;
+        let running_path = journal(&harness, &running)?;
+
+        assert_eq!(recover(&locator(&harness)), Vec::new());
+        assert!(!quiet_path.exists());
+        assert!(running_path.exists());
+        Ok(())
+    }
+
+    #[rstest]
+    fn recover_keep_locks_while_ricer_runs(harness: Result<FixtureHarness>) -> Result<()> {
+        let harness = harness?;
+        if !Path::new("/proc").is_dir() {
+            return Ok(());
+        }
+
+        let entry = dead_entry(&["ricer", "delete", "vim"])?;
+        journal(&harness, &entry)?;
+        let mut running = dead_entry(&["ricer", "status"])?;
+        running.pid = 1;
+        journal(&harness, &running)?;
+        let lock = harness.as_path().join("repos/vim.git/index.lock");
+        fs::write(&lock, "")?;
+
+        let result = recover(&locator(&harness));
+        assert_eq!(result, vec![InterruptedRun { entry, removed_locks: Vec::new() }]);
+        assert!(lock.exists());
+        Ok(())
+    }
+
+    #[rstest]
+    fn interrupted_run_display_recovery_suggestions() -> Result<()> {
+        let mut entry = dead_entry(&["ricer", "delete", "vim"])?;
+        entry.pid = 4242;
+        entry.started = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let run = InterruptedRun { entry, removed_locks: vec!["repos/vim.git/index.lock".into()] };
+        let expect = indoc! {"
+            Previous 'ricer delete vim' (pid 4242) started 2023-11-14 22:13:20 UTC, but never finished
+            It may have left changes to vim half-done
+            Removed stale lock 'repos/vim.git/index.lock'
+            Check repositories through 'ricer status' and 'ricer doctor' before running 'ricer delete' again"};
+        assert_eq!(run.to_string(), expect);
+        Ok(())
+    }
+}